@@ -535,10 +535,11 @@ func _on_remote_weapon_switched(remote_player_id: int, weapon_id: int) -> void:
 ## @param damaged_player_id: ID of the player who was damaged
 ## @param damage_amount: Amount of damage taken
 ## @param attacker_id: ID of the attacking player
-func _on_network_damage_received(damaged_player_id: int, damage_amount: int, attacker_id: int) -> void:
+## @param health: Server health after the hit (the only source of truth)
+func _on_network_damage_received(damaged_player_id: int, damage_amount: int, attacker_id: int, health: int) -> void:
 	# Only apply damage if this player instance matches the damaged player ID
-	if player_id == damaged_player_id:
-		take_damage(damage_amount)
+	if player_id == damaged_player_id and damageable:
+		damageable.current_health = health
 		print("Player %d took %d damage from player %d" % [player_id, damage_amount, attacker_id])
 
 ## update_target_position
@@ -14,6 +14,11 @@ var connected_to_udp: bool = false
 var current_request_id: int = 0
 var pending_requests: Dictionary = {}
 
+# Fragmented server packets being reassembled (message_id -> {total, parts, started})
+const FRAGMENT_TIMEOUT_MSEC = 2000 # Incomplete messages are dropped after this long
+const MAX_FRAGMENTS = 32 # Matches the server's limit
+var pending_fragments: Dictionary = {}
+
 signal http_response_received(request_id: int, result: int, response_code: int, headers: Array, body: PackedByteArray) # Signal emitted when HTTP response is received
 
 signal udp_packet_received(data: Dictionary) # Signal emitted when UDP packet is received
@@ -113,18 +118,63 @@ func process_udp_packets() -> void:
 		var json = JSON.new()
 		var parse_result = json.parse(packet_string)
 
-		if parse_result == OK:
-			udp_packet_received.emit(json.data)
-		else:
+		if parse_result != OK:
 			push_error("UDP: Failed to parse packet: '" + packet_string + "' (error: " + str(parse_result) + ")")
+		elif json.data is Dictionary and json.data.get("type") == "fragment":
+			_receive_fragment(json.data)
+		else:
+			udp_packet_received.emit(json.data)
 
 		packets_processed += 1
 
+	_expire_fragments()
+
+## Buffers one slice of an oversized server packet; emits the packet once every slice has arrived
+## Slices are chunks of the original JSON text, joined in index order
+func _receive_fragment(fragment: Dictionary) -> void:
+	var message_id = int(fragment.get("message_id", -1))
+	var index = int(fragment.get("index", -1))
+	var total = int(fragment.get("total", 0))
+	var data = fragment.get("data")
+	if total < 1 or total > MAX_FRAGMENTS or index < 0 or index >= total or not data is String:
+		push_error("UDP: Dropping malformed fragment of message " + str(message_id))
+		return
+
+	if not pending_fragments.has(message_id):
+		pending_fragments[message_id] = {"total": total, "parts": {}, "started": Time.get_ticks_msec()}
+	var pending = pending_fragments[message_id]
+	if pending["total"] != total:
+		push_error("UDP: Dropping message " + str(message_id) + ": fragments disagree on total")
+		pending_fragments.erase(message_id)
+		return
+	pending["parts"][index] = data
+	if pending["parts"].size() < total:
+		return
+
+	pending_fragments.erase(message_id)
+	var text = ""
+	for i in range(total):
+		text += pending["parts"][i]
+	var json = JSON.new()
+	if json.parse(text) == OK:
+		udp_packet_received.emit(json.data)
+	else:
+		push_error("UDP: Failed to parse reassembled message " + str(message_id))
+
+## Drops messages whose remaining fragments were lost
+func _expire_fragments() -> void:
+	var now = Time.get_ticks_msec()
+	for message_id in pending_fragments.keys():
+		if now - pending_fragments[message_id]["started"] > FRAGMENT_TIMEOUT_MSEC:
+			push_warning("UDP: Dropping message " + str(message_id) + ": fragments timed out")
+			pending_fragments.erase(message_id)
+
 ## Closes UDP connection
 func close_udp() -> void:
 	if udp_peer:
 		udp_peer.close()
 	connected_to_udp = false
+	pending_fragments.clear()
 
 ## Checks if UDP is connected
 func is_udp_connected() -> bool:
@@ -25,7 +25,7 @@ signal player_left(player_id: int)
 signal position_update_received(player_id: int, position: Vector3, rotation: Vector3)
 signal server_dummy_updated(position: Vector3)
 signal weapon_switched(player_id: int, weapon_id: int)
-signal player_damaged(player_id: int, damage: int, attacker_id: int)
+signal player_damaged(player_id: int, damage: int, attacker_id: int, health: int)
 
 # Connection events
 signal connection_confirmed()
@@ -74,8 +74,8 @@ func on_weapon_switched(player_id: int, weapon_id: int) -> void:
 	weapon_switched.emit(player_id, weapon_id)
 
 ## Callback: A player was damaged
-func on_player_damaged(player_id: int, damage: int, attacker_id: int) -> void:
-	player_damaged.emit(player_id, damage, attacker_id)
+func on_player_damaged(player_id: int, damage: int, attacker_id: int, health: int) -> void:
+	player_damaged.emit(player_id, damage, attacker_id, health)
 
 ## Callback: UDP connection confirmed
 func on_connection_confirmed() -> void:
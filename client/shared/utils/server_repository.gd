@@ -20,6 +20,7 @@ var current_lobby: Dictionary = {}
 var player_id: int = -1
 var connected_players: Dictionary = {}
 var _last_joined_lobby_code: String = ""
+var join_cookie: String = ""  # Handshake cookie echoed in the UDP join
 
 # Connection state
 var connection_state: int = 0  # ConnectionState enum from callbacks
@@ -250,6 +251,8 @@ func send_join_packet() -> void:
 		"lobby_code": current_lobby.get("code", ""),
 		"player_id": player_id
 	}
+	if not join_cookie.is_empty():
+		packet["cookie"] = join_cookie
 
 	print("Sending join packet to server - lobby: ", current_lobby.get("code", ""), " player_id: ", player_id)
 	adaptor.send_udp_packet(packet)
@@ -296,6 +299,22 @@ func _on_udp_packet_received(data: Dictionary) -> void:
 	var packet_type = data.get("type", "")
 
 	match packet_type:
+		"challenge":
+			# Server wants proof we own our address; resend the join with its cookie
+			var cookie = data.get("cookie", "")
+			if cookie != join_cookie:  # The same cookie refused twice would only loop
+				join_cookie = cookie
+				send_join_packet()
+
+		"rebind":
+			# Our address changed (NAT rebinding); confirm it so the server resumes sending here
+			if adaptor and adaptor.is_udp_connected():
+				adaptor.send_udp_packet({
+					"type": "rebind_ack",
+					"player_id": player_id,
+					"generation": data.get("generation", 0)
+				})
+
 		"welcome":
 			print("=== UDP CONNECTION CONFIRMED ===")
 			print("Received welcome from server - connection confirmed!")
@@ -362,7 +381,8 @@ func _on_udp_packet_received(data: Dictionary) -> void:
 			var damaged_player_id = data.get("player_id", -1)
 			var damage_amount = data.get("damage", 0)
 			var attacker_id = data.get("attacker_id", -1)
-			callbacks.on_player_damaged(damaged_player_id, damage_amount, attacker_id)
+			var health = data.get("health", 0)
+			callbacks.on_player_damaged(damaged_player_id, damage_amount, attacker_id, health)
 
 # Utility methods
 
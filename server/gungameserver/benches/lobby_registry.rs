@@ -0,0 +1,71 @@
+use arc_swap::ArcSwap;
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use gungameserver::domain::lobbies;
+use gungameserver::state::commands::LobbyCommand;
+use gungameserver::state::lobby::Lobby;
+use gungameserver::state::server_state::{LobbyHandle, ServerState};
+use std::sync::Arc;
+use tokio::runtime::Runtime;
+use tokio::sync::{mpsc, RwLock};
+
+const LOBBY_COUNT: usize = 5000;
+
+fn lobby_handle(code: &str) -> LobbyHandle {
+    let lobby = Lobby::new(code.to_string(), 8, "world".to_string());
+    let summary = ArcSwap::from_pointee(lobbies::summarize(&lobby));
+    let presence = lobby.presence.clone();
+    let (tx, _rx) = mpsc::channel::<LobbyCommand>(1);
+    LobbyHandle {
+        lobby: Arc::new(RwLock::new(lobby)),
+        command_tx: tx,
+        task_handle: tokio::spawn(async {}),
+        summary,
+        presence,
+    }
+}
+
+fn populated_state(count: usize) -> ServerState {
+    let state = ServerState::new();
+    for i in 0..count {
+        let code = format!("LOBBY{}", i);
+        state.insert_lobby(code.clone(), lobby_handle(&code));
+    }
+    state
+}
+
+fn bench_registry(c: &mut Criterion) {
+    let runtime = Runtime::new().unwrap();
+    let _guard = runtime.enter();
+
+    c.bench_function("lobby_create", |b| {
+        b.iter_batched(
+            ServerState::new,
+            |state| {
+                for i in 0..1000 {
+                    let code = format!("LOBBY{}", i);
+                    state.insert_lobby(code.clone(), lobby_handle(&code));
+                }
+                state
+            },
+            BatchSize::LargeInput,
+        )
+    });
+
+    let state = populated_state(LOBBY_COUNT);
+    let codes: Vec<String> = (0..LOBBY_COUNT).map(|i| format!("LOBBY{}", i)).collect();
+
+    c.bench_function("lobby_lookup", |b| {
+        let mut i = 0;
+        b.iter(|| {
+            i = (i + 1) % codes.len();
+            black_box(state.get_lobby_tx(&codes[i]))
+        })
+    });
+
+    c.bench_function("lobby_summaries", |b| {
+        b.iter(|| black_box(state.lobby_summaries()))
+    });
+}
+
+criterion_group!(benches, bench_registry);
+criterion_main!(benches);
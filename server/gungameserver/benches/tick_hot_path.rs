@@ -0,0 +1,158 @@
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+use gungameserver::domain::lobbies;
+use gungameserver::state::bandwidth::BandwidthTracker;
+use gungameserver::state::commands::LobbyCommand;
+use gungameserver::state::lobby::Lobby;
+use gungameserver::tick::delta_sync;
+use gungameserver::tick::lobby_tick::{self, TickIo};
+use gungameserver::tick::outbound::Outbox;
+use gungameserver::utils::buffers::{PacketBuffer, SyncEvent};
+use gungameserver::utils::config::Config;
+use gungameserver::utils::weapondb::WeaponDb;
+use std::sync::Arc;
+use std::time::SystemTime;
+
+const PLAYER_COUNTS: [u32; 3] = [8, 32, 128];
+
+/// A lobby of `players` connected players, all off their fire-rate cooldown
+fn populated_lobby(players: u32, weapons: &WeaponDb) -> Lobby {
+    let mut lobby = Lobby::new("BENCH".to_string(), players, "world".to_string());
+    for id in 1..=players {
+        lobbies::add_player(&mut lobby, id, format!("Player{}", id), WeaponDb::default_weapon_id(), weapons).unwrap();
+        let addr = format!("127.0.0.1:{}", 10_000 + id).parse().unwrap();
+        lobbies::set_player_address(&mut lobby, id, addr).unwrap();
+        let player = lobby.players.get_mut(&id).unwrap();
+        player.last_shot_time = SystemTime::UNIX_EPOCH;
+        player.equip_end_time = None;
+    }
+    lobby.pending_events.clear();
+    lobby.clear_dirty();
+    lobby
+}
+
+/// One tick of typical input: everyone moves, a quarter of the lobby shoots its neighbour
+fn tick_commands(players: u32, tick: u32) -> Vec<LobbyCommand> {
+    let mut commands: Vec<LobbyCommand> = (1..=players)
+        .map(|id| LobbyCommand::PositionUpdate {
+            player_id: id,
+            position: (id as f32 + tick as f32 * 0.1, 1.0, (id % 7) as f32),
+            rotation: (0.0, tick as f32 * 0.05, 0.0),
+        })
+        .collect();
+    commands.extend((1..=players).step_by(4).map(|id| LobbyCommand::Shoot {
+        player_id: id,
+        target_id: Some(id % players + 1),
+        pellet_hits: Vec::new(),
+    }));
+    commands
+}
+
+/// The lobby loop's tick body; returns the bytes it queued for clients
+fn full_tick(lobby: &mut Lobby, weapons: &WeaponDb, config: &Config, commands: Vec<LobbyCommand>) -> usize {
+    let (outbox, mut rx) = Outbox::new(config.max_send_failures);
+    let mut io = TickIo { outbox, bandwidth: Arc::new(BandwidthTracker::new()), send_buffer: PacketBuffer::default() };
+    lobby_tick::run_tick(lobby, weapons, config, None, &mut io, commands);
+    let mut bytes = 0;
+    while let Ok(packet) = rx.try_recv() {
+        bytes += packet.data.len();
+    }
+    bytes
+}
+
+fn bench_process_command(c: &mut Criterion) {
+    let weapons = WeaponDb::load();
+    let mut group = c.benchmark_group("process_command");
+    for players in PLAYER_COUNTS {
+        group.throughput(Throughput::Elements(tick_commands(players, 0).len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(players), &players, |b, &players| {
+            b.iter_batched(
+                || (populated_lobby(players, &weapons), tick_commands(players, 1)),
+                |(mut lobby, commands)| {
+                    lobby_tick::apply_commands(&mut lobby, &weapons, commands);
+                    lobby
+                },
+                BatchSize::LargeInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+fn bench_collect_dirty_events(c: &mut Criterion) {
+    let weapons = WeaponDb::load();
+    let mut group = c.benchmark_group("collect_dirty_events");
+    for players in PLAYER_COUNTS {
+        group.bench_with_input(BenchmarkId::from_parameter(players), &players, |b, &players| {
+            b.iter_batched(
+                || {
+                    let mut lobby = populated_lobby(players, &weapons);
+                    lobby_tick::apply_commands(&mut lobby, &weapons, tick_commands(players, 1));
+                    lobby
+                },
+                |mut lobby| black_box(delta_sync::collect_dirty_events(&mut lobby)),
+                BatchSize::LargeInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+fn bench_serialize_packets(c: &mut Criterion) {
+    let weapons = WeaponDb::load();
+    let lobby = populated_lobby(8, &weapons);
+    let events = [
+        ("health", SyncEvent::HealthChanged { player_id: 2, health: 80, max_health: 100 }),
+        ("hit_confirm", SyncEvent::HitConfirmed {
+            attacker_id: 1,
+            target_id: 2,
+            damage: 20,
+            multiplier: 1.0,
+            pellets: Vec::new(),
+            armor_absorbed: 0,
+            target_armor: 0,
+        }),
+        ("player_killed", SyncEvent::PlayerKilled {
+            killer_id: 1,
+            killer_name: "Player1".to_string(),
+            victim_id: 2,
+            victim_name: "Player2".to_string(),
+            weapon_id: 1,
+            weapon_name: "Golden Friend".to_string(),
+            killer_killstreak: 3,
+        }),
+    ];
+
+    let mut group = c.benchmark_group("serialize_packet");
+    let player = &lobby.players[&1];
+    group.bench_function("position_update", |b| {
+        b.iter(|| black_box(serde_json::to_vec(&lobby_tick::position_packet(black_box(player))).unwrap()))
+    });
+    for (name, event) in &events {
+        group.bench_function(*name, |b| {
+            b.iter(|| {
+                let packet = lobby_tick::event_packet(&lobby, black_box(event)).unwrap();
+                black_box(serde_json::to_vec(&packet).unwrap())
+            })
+        });
+    }
+    group.finish();
+}
+
+fn bench_full_tick(c: &mut Criterion) {
+    let weapons = WeaponDb::load();
+    let config = Config::default();
+    let mut group = c.benchmark_group("full_tick");
+    for players in PLAYER_COUNTS {
+        group.bench_with_input(BenchmarkId::from_parameter(players), &players, |b, &players| {
+            b.iter_batched(
+                || (populated_lobby(players, &weapons), tick_commands(players, 1)),
+                |(mut lobby, commands)| black_box(full_tick(&mut lobby, &weapons, &config, commands)),
+                BatchSize::LargeInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_process_command, bench_collect_dirty_events, bench_serialize_packets, bench_full_tick);
+criterion_main!(benches);
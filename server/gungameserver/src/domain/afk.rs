@@ -0,0 +1,203 @@
+use crate::domain::{bots, lobbies};
+use crate::state::commands::LobbyCommand;
+use crate::state::lobby::Lobby;
+use crate::state::server_state::ServerState;
+use crate::utils::buffers::SyncEvent;
+use std::time::{Duration, SystemTime};
+
+/// Reason sent with kicks of AFK players
+pub const AFK_KICK_REASON: &str = "AFK";
+
+/// How long without gameplay input before each AFK step, measured from the last input
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AfkLimits {
+    /// Moved to spectator, freeing their spot in the match
+    pub spectate_after: Duration,
+    /// Kicked, in ranked lobbies only
+    pub kick_after: Duration,
+    /// Lead time of the warning sent before each step
+    pub warning: Duration,
+}
+
+/// The player a command counts as gameplay input for
+/// Heartbeats and position updates that don't move or turn the player don't count
+pub fn input_player(lobby: &Lobby, cmd: &LobbyCommand) -> Option<u32> {
+    match cmd {
+        LobbyCommand::PositionUpdate { player_id, position, rotation, .. } => {
+            let player = lobby.players.get(player_id)?;
+            (player.position != *position || player.rotation != *rotation).then_some(*player_id)
+        }
+        LobbyCommand::Shoot { player_id, .. }
+        | LobbyCommand::FireHeld { player_id, .. }
+        | LobbyCommand::Reload { player_id }
+        | LobbyCommand::Pickup { player_id, .. }
+        | LobbyCommand::LootPickup { player_id, .. }
+        | LobbyCommand::WeaponSwitch { player_id, .. }
+        | LobbyCommand::WeaponCycle { player_id, .. } => Some(*player_id),
+        _ => None,
+    }
+}
+
+/// Record gameplay input; spectators moved there for being AFK rejoin at the next respawn check
+pub fn mark_active(lobby: &mut Lobby, player_id: u32, now: SystemTime) {
+    let Some(player) = lobby.players.get_mut(&player_id) else {
+        return;
+    };
+    player.last_activity = now;
+    player.afk_warned = false;
+    if player.spectating {
+        player.spectating = false;
+        player.respawn_time = Some(now);
+        lobby.timers.respawns.schedule(player_id, now);
+        lobby.push_event(SyncEvent::SpectatorChanged { player_id, spectating: false });
+    }
+}
+
+fn spectate(lobby: &mut Lobby, player_id: u32) {
+    let Some(player) = lobby.players.get_mut(&player_id) else {
+        return;
+    };
+    // Dead without a respawn timer: no shooting, no being shot, no spawning back in
+    player.spectating = true;
+    player.is_dead = true;
+    player.respawn_time = None;
+    player.trigger_held = false;
+    player.fire_target = None;
+    player.afk_warned = false;
+    lobby.push_event(SyncEvent::SpectatorChanged { player_id, spectating: true });
+}
+
+/// Warn, move to spectator, and (in ranked lobbies) kick players idle past the limits
+/// Only live, unpaused matches count toward being AFK; returns players kicked
+pub fn update(lobby: &mut Lobby, limits: &AfkLimits, now: SystemTime, server_state: Option<&ServerState>) -> Vec<u32> {
+    if !lobby.is_match_live() || lobby.is_paused() {
+        for player in lobby.players.values_mut() {
+            player.last_activity = now;
+        }
+        return Vec::new();
+    }
+
+    let ranked = lobby.settings.ranked;
+    let mut warnings = Vec::new();
+    let mut to_spectate = Vec::new();
+    let mut to_kick = Vec::new();
+    for player in lobby.players.values() {
+        if player.id == 999 || bots::is_bot(player.id) {
+            continue;
+        }
+        // Spectator first; ranked lobbies then kick players who stay idle
+        let deadline = match (player.spectating, ranked) {
+            (false, _) => limits.spectate_after,
+            (true, true) => limits.kick_after,
+            (true, false) => continue,
+        };
+        let idle = now.duration_since(player.last_activity).unwrap_or_default();
+        if idle >= deadline {
+            if player.spectating { to_kick.push(player.id) } else { to_spectate.push(player.id) }
+        } else if !player.afk_warned && idle + limits.warning >= deadline {
+            warnings.push((player.id, (deadline - idle).as_secs_f32().ceil() as u64, player.spectating));
+        }
+    }
+
+    for (player_id, seconds_remaining, kick) in warnings {
+        if let Some(player) = lobby.players.get_mut(&player_id) {
+            player.afk_warned = true;
+        }
+        lobby.push_event(SyncEvent::AfkWarning { player_id, seconds_remaining, kick });
+    }
+    for player_id in to_spectate {
+        log::info!("Player {} moved to spectator for being AFK in lobby {}", player_id, lobby.code);
+        spectate(lobby, player_id);
+    }
+    to_kick.retain(|player_id| lobbies::leave_lobby(lobby, *player_id, server_state).is_some());
+    for player_id in &to_kick {
+        log::info!("Player {} kicked for being AFK in lobby {}", player_id, lobby.code);
+        lobby.push_event(SyncEvent::PlayerKicked { player_id: *player_id, reason: AFK_KICK_REASON.to_string() });
+    }
+    to_kick
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::lobby::{LobbySettings, MatchPhase, Player};
+
+    const LIMITS: AfkLimits = AfkLimits {
+        spectate_after: Duration::from_secs(60),
+        kick_after: Duration::from_secs(120),
+        warning: Duration::from_secs(10),
+    };
+
+    fn live_lobby(ranked: bool) -> (Lobby, SystemTime) {
+        let settings = LobbySettings { ranked, ..Default::default() };
+        let mut lobby = Lobby::with_settings("AFK1".to_string(), 4, "world".to_string(), settings);
+        lobby.phase = MatchPhase::InProgress;
+        let start = SystemTime::now();
+        for id in [1, 2] {
+            let mut player = Player::new_player(id, format!("P{}", id), 1, 20);
+            player.last_activity = start;
+            lobby.players.insert(id, player);
+        }
+        (lobby, start)
+    }
+
+    fn afk_events(lobby: &mut Lobby) -> Vec<SyncEvent> {
+        lobby.pending_events.drain(..).collect()
+    }
+
+    #[test]
+    fn test_warn_then_spectate_then_return() {
+        let (mut lobby, start) = live_lobby(false);
+        update(&mut lobby, &LIMITS, start + Duration::from_secs(52), None);
+        let events = afk_events(&mut lobby);
+        assert_eq!(events.len(), 2);
+        assert!(events.iter().all(|e| matches!(e, SyncEvent::AfkWarning { seconds_remaining: 8, kick: false, .. })));
+
+        // Player 2 moves; only player 1 goes to spectator
+        mark_active(&mut lobby, 2, start + Duration::from_secs(55));
+        update(&mut lobby, &LIMITS, start + Duration::from_secs(60), None);
+        assert!(lobby.players[&1].spectating && lobby.players[&1].is_dead);
+        assert!(!lobby.players[&2].spectating);
+        assert!(matches!(afk_events(&mut lobby)[..], [SyncEvent::SpectatorChanged { player_id: 1, spectating: true }]));
+
+        // Casual lobbies never kick
+        assert!(update(&mut lobby, &LIMITS, start + Duration::from_secs(600), None).is_empty());
+        assert!(lobby.players.contains_key(&1));
+
+        mark_active(&mut lobby, 1, start + Duration::from_secs(601));
+        assert!(!lobby.players[&1].spectating);
+        assert!(lobby.players[&1].respawn_time.is_some());
+    }
+
+    #[test]
+    fn test_ranked_kicks_idle_spectators() {
+        let (mut lobby, start) = live_lobby(true);
+        mark_active(&mut lobby, 2, start + Duration::from_secs(100));
+        update(&mut lobby, &LIMITS, start + Duration::from_secs(60), None);
+        afk_events(&mut lobby);
+
+        update(&mut lobby, &LIMITS, start + Duration::from_secs(115), None);
+        assert!(matches!(afk_events(&mut lobby)[..], [SyncEvent::AfkWarning { player_id: 1, kick: true, .. }]));
+        assert_eq!(update(&mut lobby, &LIMITS, start + Duration::from_secs(120), None), vec![1]);
+        assert!(!lobby.players.contains_key(&1));
+        assert!(lobby.players.contains_key(&2));
+    }
+
+    #[test]
+    fn test_idle_outside_live_match_not_counted() {
+        let (mut lobby, start) = live_lobby(true);
+        lobby.phase = MatchPhase::Waiting;
+        update(&mut lobby, &LIMITS, start + Duration::from_secs(600), None);
+        lobby.phase = MatchPhase::InProgress;
+        update(&mut lobby, &LIMITS, start + Duration::from_secs(640), None);
+        assert!(lobby.pending_events.is_empty());
+
+        // Standing still while heartbeating isn't input
+        let player = &lobby.players[&1];
+        let still = LobbyCommand::PositionUpdate { player_id: 1, position: player.position, rotation: player.rotation };
+        assert_eq!(input_player(&lobby, &still), None);
+        let moved = LobbyCommand::PositionUpdate { player_id: 1, position: (3.0, 1.0, 0.0), rotation: player.rotation };
+        assert_eq!(input_player(&lobby, &moved), Some(1));
+        assert_eq!(input_player(&lobby, &LobbyCommand::Reload { player_id: 2 }), Some(2));
+    }
+}
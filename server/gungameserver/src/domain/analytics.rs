@@ -0,0 +1,139 @@
+use crate::state::lobby::Lobby;
+use std::collections::{HashMap, VecDeque};
+
+/// Side length of a heatmap cell in world units
+pub const CELL_SIZE: f32 = 5.0;
+
+/// Samples kept per lobby; the oldest are dropped first
+pub const MAX_SAMPLES: usize = 20_000;
+
+/// Ticks between position samples (~1 per second at 50Hz)
+pub const SAMPLE_EVERY_TICKS: u64 = 50;
+
+/// Coarse position of one player at one tick
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PositionSample {
+    pub player_id: u32,
+    pub cell: (i32, i32),
+    pub tick: u64,
+}
+
+/// Aggregated sample count for one grid cell
+#[derive(Debug, Clone, PartialEq, serde::Serialize, utoipa::ToSchema)]
+pub struct HeatmapCell {
+    pub x: i32,
+    pub z: i32,
+    pub count: u32,
+}
+
+/// Bounded ring of position samples for a lobby
+#[derive(Debug, Clone)]
+pub struct AnalyticsBuffer {
+    samples: VecDeque<PositionSample>,
+    capacity: usize,
+}
+
+impl AnalyticsBuffer {
+    pub fn new(capacity: usize) -> Self {
+        Self { samples: VecDeque::new(), capacity }
+    }
+
+    pub fn push(&mut self, sample: PositionSample) {
+        if self.samples.len() >= self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    /// Sample counts per cell, busiest first
+    pub fn heatmap(&self) -> Vec<HeatmapCell> {
+        let mut counts: HashMap<(i32, i32), u32> = HashMap::new();
+        for sample in &self.samples {
+            *counts.entry(sample.cell).or_insert(0) += 1;
+        }
+        let mut cells: Vec<HeatmapCell> = counts.into_iter()
+            .map(|((x, z), count)| HeatmapCell { x, z, count })
+            .collect();
+        cells.sort_by_key(|c| (std::cmp::Reverse(c.count), c.x, c.z));
+        cells
+    }
+}
+
+impl Default for AnalyticsBuffer {
+    fn default() -> Self {
+        Self::new(MAX_SAMPLES)
+    }
+}
+
+/// Grid cell on the ground plane (x/z; y is vertical)
+pub fn cell_for(position: (f32, f32, f32)) -> (i32, i32) {
+    ((position.0 / CELL_SIZE).floor() as i32, (position.2 / CELL_SIZE).floor() as i32)
+}
+
+/// Sample every living player's position while a match is in progress
+pub fn record_positions(lobby: &mut Lobby, tick: u64) {
+    if !lobby.is_match_live() {
+        return;
+    }
+    let samples: Vec<PositionSample> = lobby.players.values()
+        .filter(|p| p.id != 999 && !p.is_dead) // Exclude dummy bot
+        .map(|p| PositionSample { player_id: p.id, cell: cell_for(p.position), tick })
+        .collect();
+    for sample in samples {
+        lobby.analytics.push(sample);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::lobbies::add_player;
+    use crate::state::lobby::MatchPhase;
+    use crate::utils::weapondb::WeaponDb;
+
+    #[test]
+    fn test_cell_for() {
+        assert_eq!(cell_for((0.0, 10.0, 0.0)), (0, 0));
+        assert_eq!(cell_for((7.5, 0.0, -0.5)), (1, -1));
+    }
+
+    #[test]
+    fn test_buffer_is_bounded() {
+        let mut buffer = AnalyticsBuffer::new(2);
+        for tick in 0..3 {
+            buffer.push(PositionSample { player_id: 1, cell: (tick as i32, 0), tick });
+        }
+        assert_eq!(buffer.len(), 2);
+        assert!(buffer.heatmap().iter().all(|c| c.x != 0));
+    }
+
+    #[test]
+    fn test_record_positions_only_during_match() {
+        let mut lobby = Lobby::new("TEST".to_string(), 4, "world".to_string());
+        let weapons = WeaponDb::load();
+        add_player(&mut lobby, 1, "P1".to_string(), 1, &weapons).unwrap();
+        add_player(&mut lobby, 2, "P2".to_string(), 1, &weapons).unwrap();
+        lobby.players.get_mut(&2).unwrap().position = (12.0, 0.0, 3.0);
+
+        record_positions(&mut lobby, 0);
+        assert!(lobby.analytics.is_empty());
+
+        lobby.phase = MatchPhase::InProgress;
+        record_positions(&mut lobby, 50);
+        record_positions(&mut lobby, 100);
+        lobby.players.get_mut(&2).unwrap().is_dead = true;
+        record_positions(&mut lobby, 150);
+
+        let heatmap = lobby.analytics.heatmap();
+        assert_eq!(heatmap[0], HeatmapCell { x: 0, z: 0, count: 3 });
+        assert_eq!(heatmap[1], HeatmapCell { x: 2, z: 0, count: 2 });
+    }
+}
@@ -0,0 +1,76 @@
+use crate::state::lobby::Lobby;
+use crate::state::loadouts::Loadout;
+use crate::utils::buffers::SyncEvent;
+use crate::utils::weapondb::DamageType;
+
+/// Most armor a player can carry
+pub const MAX_ARMOR: u32 = 100;
+
+/// Loadout attachment that spawns the player wearing armor
+pub const ARMOR_ATTACHMENT: &str = "armor_vest";
+
+/// Armor an armor_vest loadout spawns with
+pub const LOADOUT_ARMOR: u32 = 50;
+
+/// Share of a hit's damage armor soaks up, by damage type
+pub fn mitigation(damage_type: DamageType) -> f32 {
+    match damage_type {
+        DamageType::Ballistic => 0.5,
+        DamageType::Explosive => 0.3,
+        DamageType::Melee => 0.2,
+        DamageType::Fall => 0.25,
+    }
+}
+
+/// Damage absorbed from a hit; never more than the armor left, which it uses up point for point
+pub fn absorbed(armor: u32, damage: u32, damage_type: DamageType) -> u32 {
+    ((damage as f32 * mitigation(damage_type)) as u32).min(armor)
+}
+
+/// Armor a player spawns with using this loadout
+pub fn spawn_armor(loadout: Option<&Loadout>) -> u32 {
+    match loadout {
+        Some(loadout) if loadout.attachments.iter().any(|a| a == ARMOR_ATTACHMENT) => LOADOUT_ARMOR,
+        _ => 0,
+    }
+}
+
+/// Set a player's armor (capped), telling clients when it changed
+pub fn set(lobby: &mut Lobby, player_id: u32, armor: u32) -> Result<(), &'static str> {
+    let player = lobby.players.get_mut(&player_id).ok_or("Player not found")?;
+    let armor = armor.min(MAX_ARMOR);
+    if player.armor != armor {
+        player.armor = armor;
+        lobby.push_event(SyncEvent::ArmorChanged { player_id, armor });
+    }
+    Ok(())
+}
+
+/// Add armor from a pickup
+pub fn grant(lobby: &mut Lobby, player_id: u32, amount: u32) -> Result<(), &'static str> {
+    let player = lobby.players.get(&player_id).ok_or("Player not found")?;
+    if player.is_dead {
+        return Err("Player is dead");
+    }
+    let armor = player.armor.saturating_add(amount);
+    set(lobby, player_id, armor)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_absorption_by_type_and_armor_left() {
+        assert_eq!(absorbed(100, 40, DamageType::Ballistic), 20);
+        assert_eq!(absorbed(100, 40, DamageType::Explosive), 12);
+        assert_eq!(absorbed(100, 40, DamageType::Melee), 8);
+        assert_eq!(absorbed(100, 40, DamageType::Fall), 10);
+        assert_eq!(absorbed(5, 40, DamageType::Ballistic), 5);
+        assert_eq!(absorbed(0, 40, DamageType::Ballistic), 0);
+
+        let vest = Loadout { name: "tank".to_string(), primary: 1, secondary: None, attachments: vec![ARMOR_ATTACHMENT.to_string()] };
+        assert_eq!(spawn_armor(Some(&vest)), LOADOUT_ARMOR);
+        assert_eq!(spawn_armor(None), 0);
+    }
+}
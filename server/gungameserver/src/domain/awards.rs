@@ -0,0 +1,86 @@
+use crate::domain::bots;
+use crate::state::lobby::MatchStanding;
+
+/// Shots a player must have fired to be considered for Best Accuracy
+pub const MIN_ACCURACY_SHOTS: u32 = 10;
+
+/// What an award is measured by; None leaves the player out of the running
+type Criterion = fn(&MatchStanding) -> Option<f32>;
+
+/// An end-of-match award and who won it
+#[derive(Debug, Clone, PartialEq, serde::Serialize, utoipa::ToSchema)]
+pub struct MatchAward {
+    /// mvp, most_kills, best_streak, most_damage or best_accuracy
+    pub award: &'static str,
+    pub player_id: u32,
+    pub name: String,
+    /// Score, kills, streak, damage or accuracy (0-1) the award was won with
+    pub value: f32,
+}
+
+/// Awards for a finished match, from its standings (best placed player first)
+/// Ties go to the better placed player; an award nobody scored in is left out, and bots never win one
+pub fn compute(standings: &[MatchStanding]) -> Vec<MatchAward> {
+    let humans: Vec<&MatchStanding> = standings.iter().filter(|s| !bots::is_bot(s.player_id)).collect();
+    let criteria: [(&'static str, Criterion); 5] = [
+        ("mvp", |s| Some(s.score as f32)),
+        ("most_kills", |s| Some(s.kills as f32)),
+        ("best_streak", |s| Some(s.stats.best_killstreak as f32)),
+        ("most_damage", |s| Some(s.stats.damage_dealt as f32)),
+        ("best_accuracy", |s| (s.stats.shots_fired >= MIN_ACCURACY_SHOTS).then(|| s.stats.accuracy())),
+    ];
+
+    criteria.iter()
+        .filter_map(|(award, value_of)| {
+            let mut best: Option<(&MatchStanding, f32)> = None;
+            for standing in &humans {
+                if let Some(value) = value_of(standing) {
+                    if best.is_none_or(|(_, top)| value > top) {
+                        best = Some((standing, value));
+                    }
+                }
+            }
+            let (winner, value) = best.filter(|(_, value)| *value > 0.0)?;
+            Some(MatchAward { award, player_id: winner.player_id, name: winner.name.clone(), value })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::lobby::MatchStats;
+
+    fn standing(player_id: u32, score: u32, kills: u32, stats: MatchStats) -> MatchStanding {
+        MatchStanding {
+            player_id,
+            name: format!("P{}", player_id),
+            score,
+            kills,
+            deaths: 0,
+            stats,
+            accuracy: stats.accuracy(),
+            rating_change: None,
+            round_wins: 0,
+        }
+    }
+
+    #[test]
+    fn test_awards_pick_leaders_and_break_ties_by_placing() {
+        let standings = vec![
+            standing(1, 300, 3, MatchStats { shots_fired: 40, shots_hit: 12, damage_dealt: 300, best_killstreak: 2, ..Default::default() }),
+            standing(2, 300, 5, MatchStats { shots_fired: 9, shots_hit: 9, damage_dealt: 500, best_killstreak: 2, ..Default::default() }),
+            standing(3, 100, 1, MatchStats { shots_fired: 10, shots_hit: 5, damage_dealt: 100, best_killstreak: 1, ..Default::default() }),
+        ];
+        let awards: Vec<(&str, u32)> = compute(&standings).iter().map(|a| (a.award, a.player_id)).collect();
+        // Player 2's perfect accuracy is on too few shots to count
+        assert_eq!(awards, vec![("mvp", 1), ("most_kills", 2), ("best_streak", 1), ("most_damage", 2), ("best_accuracy", 3)]);
+    }
+
+    #[test]
+    fn test_no_awards_without_play() {
+        let standings = vec![standing(1, 0, 0, MatchStats::default()), standing(2, 0, 0, MatchStats::default())];
+        assert!(compute(&standings).is_empty());
+        assert!(compute(&[]).is_empty());
+    }
+}
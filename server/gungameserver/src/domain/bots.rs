@@ -0,0 +1,151 @@
+use crate::domain::lobbies;
+use crate::state::lobby::Lobby;
+use crate::utils::weapondb::{WeaponDb, WeaponLookup};
+
+/// Ids from here up belong to fill bots (human ids are handed out counting up from 1)
+pub const BOT_ID_BASE: u32 = 2_000_000_000;
+
+/// Names handed to fill bots in join order
+const BOT_NAMES: [&str; 8] = ["Alpha", "Bravo", "Charlie", "Delta", "Echo", "Foxtrot", "Golf", "Hotel"];
+
+/// Whether a player id belongs to a fill bot
+pub fn is_bot(player_id: u32) -> bool {
+    player_id >= BOT_ID_BASE
+}
+
+/// Players that aren't bots (fill bots or the dummy bot)
+pub fn human_count(lobby: &Lobby) -> usize {
+    lobby.players.keys().filter(|id| **id != 999 && !is_bot(**id)).count()
+}
+
+/// Fill bots in the lobby, newest first
+fn bot_ids(lobby: &Lobby) -> Vec<u32> {
+    let mut ids: Vec<u32> = lobby.players.keys().copied().filter(|id| is_bot(*id)).collect();
+    ids.sort_unstable_by(|a, b| b.cmp(a));
+    ids
+}
+
+/// Drop the newest bot so a human can take its slot in a full lobby
+/// The bot is announced as leaving on the next tick
+pub fn make_room(lobby: &mut Lobby) -> Option<u32> {
+    if !lobby.settings.fill_with_bots {
+        return None;
+    }
+    let bot_id = *bot_ids(lobby).first()?;
+    lobbies::remove_player(lobby, bot_id);
+    lobby.evicted_bots.push(bot_id);
+    Some(bot_id)
+}
+
+/// Add or remove bots so humans plus bots match the lobby's target population
+/// Lobbies without humans run no bots; returns bots that joined and bots that left
+pub fn update(lobby: &mut Lobby, weapons: &impl WeaponLookup) -> (Vec<(u32, String)>, Vec<u32>) {
+    let mut left = std::mem::take(&mut lobby.evicted_bots);
+    let mut joined = Vec::new();
+    let wanted = if lobby.settings.fill_with_bots && human_count(lobby) > 0 {
+        (lobby.settings.target_players as usize).saturating_sub(human_count(lobby))
+    } else {
+        0
+    };
+
+    let bots = bot_ids(lobby);
+    for bot_id in bots.iter().take(bots.len().saturating_sub(wanted)) {
+        lobbies::remove_player(lobby, *bot_id);
+        left.push(*bot_id);
+    }
+
+    let mut next = 0;
+    for _ in bots.len()..wanted {
+        while lobby.players.contains_key(&(BOT_ID_BASE + next)) {
+            next += 1;
+        }
+        let bot_id = BOT_ID_BASE + next;
+        let name = format!("[BOT] {}", BOT_NAMES[next as usize % BOT_NAMES.len()]);
+        // Bots never take reserved slots; stop once the lobby fills
+        if lobbies::add_player(lobby, bot_id, name.clone(), WeaponDb::default_weapon_id(), weapons).is_err() {
+            break;
+        }
+        joined.push((bot_id, name));
+    }
+
+    left.retain(|id| !joined.iter().any(|(bot_id, _)| bot_id == id));
+    (joined, left)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::lobbies::{add_player, leave_lobby};
+    use crate::state::global_stats::GlobalStats;
+    use crate::state::lobby::LobbySettings;
+
+    fn bot_lobby(target_players: u32) -> Lobby {
+        let settings = LobbySettings { fill_with_bots: true, target_players, ..Default::default() };
+        Lobby::with_settings("BOTS".to_string(), 4, "world".to_string(), settings)
+    }
+
+    #[test]
+    fn test_bots_fill_to_target() {
+        let weapons = WeaponDb::load();
+        let mut lobby = bot_lobby(3);
+        assert_eq!(update(&mut lobby, &weapons), (vec![], vec![])); // Nobody to play with
+
+        add_player(&mut lobby, 1, "P1".to_string(), 1, &weapons).unwrap();
+        let (joined, _) = update(&mut lobby, &weapons);
+        assert_eq!(joined.len(), 2);
+        assert!(joined.iter().all(|(id, name)| is_bot(*id) && name.starts_with("[BOT]")));
+        assert_eq!(lobby.players.len(), 3);
+        assert_eq!(lobby.owner_id, Some(1));
+
+        add_player(&mut lobby, 2, "P2".to_string(), 1, &weapons).unwrap();
+        let (joined, left) = update(&mut lobby, &weapons);
+        assert!(joined.is_empty());
+        assert_eq!(left, vec![BOT_ID_BASE + 1]);
+        assert_eq!(lobby.players.len(), 3);
+
+        // The last human leaving takes the bots with them
+        leave_lobby(&mut lobby, 1, None);
+        leave_lobby(&mut lobby, 2, None);
+        let (_, left) = update(&mut lobby, &weapons);
+        assert_eq!(left, vec![BOT_ID_BASE]);
+        assert!(lobby.players.is_empty());
+    }
+
+    #[test]
+    fn test_human_takes_bot_slot_in_full_lobby() {
+        let weapons = WeaponDb::load();
+        let mut lobby = bot_lobby(4);
+        add_player(&mut lobby, 1, "P1".to_string(), 1, &weapons).unwrap();
+        update(&mut lobby, &weapons);
+        assert_eq!(lobby.players.len(), 4);
+
+        add_player(&mut lobby, 2, "P2".to_string(), 1, &weapons).unwrap();
+        assert_eq!(lobby.players.len(), 4);
+        assert_eq!(update(&mut lobby, &weapons), (vec![], vec![BOT_ID_BASE + 2]));
+
+        // Without bot fill a full lobby stays full
+        lobby.settings.fill_with_bots = false;
+        assert_eq!(add_player(&mut lobby, 3, "P3".to_string(), 1, &weapons), Err("Lobby is full"));
+    }
+
+    #[test]
+    fn test_bots_kept_out_of_global_stats() {
+        let weapons = WeaponDb::load();
+        let mut lobby = bot_lobby(2);
+        lobby.settings.ranked = true;
+        add_player(&mut lobby, 1, "P1".to_string(), 1, &weapons).unwrap();
+        update(&mut lobby, &weapons);
+        for player in lobby.players.values_mut() {
+            player.kills = 1;
+            player.score = 100;
+        }
+
+        let stats = GlobalStats::new();
+        let standings = lobbies::end_match(&mut lobby, Some(&stats));
+        assert_eq!(standings.len(), 2);
+        assert!(standings.iter().all(|s| s.rating_change.is_some() != is_bot(s.player_id)));
+        assert!(stats.get_stats(1).is_some());
+        assert!(stats.get_stats(BOT_ID_BASE).is_none());
+        assert_eq!(lobbies::ready_counts(&lobby), (0, 1));
+    }
+}
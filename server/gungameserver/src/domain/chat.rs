@@ -0,0 +1,120 @@
+use crate::state::lobby::Lobby;
+use crate::utils::buffers::SyncEvent;
+use std::time::Duration;
+use crate::utils::clock;
+
+/// Maximum allowed chat message length (in characters)
+pub const MAX_MESSAGE_LENGTH: usize = 256;
+
+/// Minimum time between chat messages or whispers from the same player
+const WHISPER_COOLDOWN: Duration = Duration::from_millis(500);
+
+/// Sender id of whispers from the server itself (player ids start at 1)
+pub const SERVER_SENDER_ID: u32 = 0;
+
+/// Sender name shown on whispers from the server
+pub const SERVER_SENDER_NAME: &str = "Server";
+
+fn trimmed(text: &str) -> Result<&str, &'static str> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Err("Empty message");
+    }
+    if text.chars().count() > MAX_MESSAGE_LENGTH {
+        return Err("Message too long");
+    }
+    Ok(text)
+}
+
+fn consume_rate_limit(lobby: &mut Lobby, from_id: u32) -> Result<(), &'static str> {
+    let sender = lobby.players.get_mut(&from_id).ok_or("Sender not found")?;
+    let now = clock::now();
+    if let Ok(elapsed) = now.duration_since(sender.last_whisper_time) {
+        if elapsed < WHISPER_COOLDOWN {
+            return Err("Rate limited");
+        }
+    }
+    sender.last_whisper_time = now;
+    Ok(())
+}
+
+/// Validate a lobby chat message and consume the sender's rate limit
+/// Returns the trimmed message text to broadcast
+pub fn prepare_message(lobby: &mut Lobby, from_id: u32, text: &str) -> Result<String, &'static str> {
+    let text = trimmed(text)?;
+    consume_rate_limit(lobby, from_id)?;
+    Ok(text.to_string())
+}
+
+/// A private message from the server to one player
+pub fn server_whisper(to_id: u32, text: impl Into<String>) -> SyncEvent {
+    SyncEvent::Whisper {
+        from_id: SERVER_SENDER_ID,
+        from_name: SERVER_SENDER_NAME.to_string(),
+        to_id,
+        text: text.into(),
+    }
+}
+
+/// Validate a whisper and consume the sender's rate limit
+/// Returns the trimmed message text to relay
+pub fn prepare_whisper(
+    lobby: &mut Lobby,
+    from_id: u32,
+    to_id: u32,
+    text: &str,
+) -> Result<String, &'static str> {
+    let text = trimmed(text)?;
+    if from_id == to_id {
+        return Err("Cannot whisper yourself");
+    }
+    if !lobby.players.contains_key(&to_id) {
+        return Err("Recipient not in lobby");
+    }
+
+    consume_rate_limit(lobby, from_id)?;
+    Ok(text.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::lobbies::add_player;
+    use crate::utils::weapondb::WeaponDb;
+
+    fn lobby_with_players() -> Lobby {
+        let mut lobby = Lobby::new("TEST".to_string(), 4, "world".to_string());
+        let weapons = WeaponDb::load();
+        add_player(&mut lobby, 1, "Alice".to_string(), 1, &weapons).unwrap();
+        add_player(&mut lobby, 2, "Bob".to_string(), 1, &weapons).unwrap();
+        lobby
+    }
+
+    #[test]
+    fn test_prepare_whisper() {
+        let mut lobby = lobby_with_players();
+        assert_eq!(prepare_whisper(&mut lobby, 1, 2, "  hello  "), Ok("hello".to_string()));
+    }
+
+    #[test]
+    fn test_whisper_validation() {
+        let mut lobby = lobby_with_players();
+        assert_eq!(prepare_whisper(&mut lobby, 1, 2, "   "), Err("Empty message"));
+        assert_eq!(prepare_whisper(&mut lobby, 1, 1, "hi"), Err("Cannot whisper yourself"));
+        assert_eq!(prepare_whisper(&mut lobby, 1, 3, "hi"), Err("Recipient not in lobby"));
+        let long = "a".repeat(MAX_MESSAGE_LENGTH + 1);
+        assert_eq!(prepare_whisper(&mut lobby, 1, 2, &long), Err("Message too long"));
+    }
+
+    #[test]
+    fn test_whisper_rate_limit() {
+        let mut lobby = lobby_with_players();
+        assert!(prepare_whisper(&mut lobby, 1, 2, "first").is_ok());
+        assert_eq!(prepare_whisper(&mut lobby, 1, 2, "second"), Err("Rate limited"));
+        // Other players have their own limit
+        assert!(prepare_whisper(&mut lobby, 2, 1, "reply").is_ok());
+        // Lobby chat shares the limit
+        assert_eq!(prepare_message(&mut lobby, 1, "gg"), Err("Rate limited"));
+        assert_eq!(prepare_message(&mut lobby, 3, "gg"), Err("Sender not found"));
+    }
+}
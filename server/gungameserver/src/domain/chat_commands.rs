@@ -0,0 +1,196 @@
+use crate::domain::votes::{self, VoteKind};
+use crate::domain::{bots, logic};
+use crate::state::lobby::Lobby;
+use crate::utils::buffers::SyncEvent;
+use std::time::{Duration, SystemTime};
+
+/// Messages starting with this are commands rather than chat
+pub const COMMAND_PREFIX: char = '/';
+
+/// Who may run a command
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Permission {
+    Everyone,
+    /// In the match: not spectating, and not a bot
+    Playing,
+}
+
+/// A chat command the server understands
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChatCommand {
+    Kill,
+    Stats,
+    VoteKick,
+    Players,
+    Help,
+}
+
+/// How a command is invoked and who may use it how often
+#[derive(Debug, Clone, Copy)]
+pub struct CommandSpec {
+    pub command: ChatCommand,
+    pub name: &'static str,
+    pub usage: &'static str,
+    pub permission: Permission,
+    pub cooldown: Duration,
+}
+
+/// Every chat command, in the order /help lists them
+pub const COMMANDS: &[CommandSpec] = &[
+    CommandSpec { command: ChatCommand::Kill, name: "kill", usage: "/kill", permission: Permission::Playing, cooldown: Duration::from_secs(5) },
+    CommandSpec { command: ChatCommand::Stats, name: "stats", usage: "/stats [player]", permission: Permission::Everyone, cooldown: Duration::from_secs(2) },
+    CommandSpec { command: ChatCommand::VoteKick, name: "votekick", usage: "/votekick <player>", permission: Permission::Playing, cooldown: Duration::from_secs(30) },
+    CommandSpec { command: ChatCommand::Players, name: "players", usage: "/players", permission: Permission::Everyone, cooldown: Duration::from_secs(2) },
+    CommandSpec { command: ChatCommand::Help, name: "help", usage: "/help", permission: Permission::Everyone, cooldown: Duration::from_secs(1) },
+];
+
+/// Whether a chat message is a command
+pub fn is_command(text: &str) -> bool {
+    text.trim_start().starts_with(COMMAND_PREFIX)
+}
+
+/// Look up a command by name, ignoring case
+pub fn find(name: &str) -> Option<&'static CommandSpec> {
+    COMMANDS.iter().find(|spec| spec.name.eq_ignore_ascii_case(name))
+}
+
+fn check_permission(lobby: &Lobby, player_id: u32, permission: Permission) -> Result<(), &'static str> {
+    let player = lobby.players.get(&player_id).ok_or("Player not found")?;
+    match permission {
+        Permission::Everyone => Ok(()),
+        Permission::Playing if player.spectating || bots::is_bot(player_id) => Err("Only players in the match can use that"),
+        Permission::Playing => Ok(()),
+    }
+}
+
+/// A player named in a command argument, by name (ignoring case) or id
+fn resolve_player(lobby: &Lobby, arg: &str) -> Result<u32, &'static str> {
+    lobby.players.values()
+        .filter(|p| p.id != 999)
+        .find(|p| p.name.eq_ignore_ascii_case(arg))
+        .or_else(|| arg.parse().ok().and_then(|id| lobby.players.get(&id)))
+        .map(|p| p.id)
+        .ok_or("No such player")
+}
+
+/// Run a chat command for a player; returns the reply to whisper back to them
+/// Cooldowns are only consumed by commands that succeed
+pub fn execute(lobby: &mut Lobby, player_id: u32, text: &str, now: SystemTime) -> Result<String, &'static str> {
+    let mut parts = text.trim().trim_start_matches(COMMAND_PREFIX).split_whitespace();
+    let name = parts.next().ok_or("Unknown command, try /help")?;
+    let args: Vec<&str> = parts.collect();
+    let spec = find(name).ok_or("Unknown command, try /help")?;
+    check_permission(lobby, player_id, spec.permission)?;
+
+    let last_used = lobby.players.get(&player_id).and_then(|p| p.command_last_used.get(spec.name).copied());
+    if let Some(last_used) = last_used {
+        if now.duration_since(last_used).is_ok_and(|elapsed| elapsed < spec.cooldown) {
+            return Err("Command on cooldown");
+        }
+    }
+
+    let reply = run(lobby, player_id, spec, &args)?;
+    if let Some(player) = lobby.players.get_mut(&player_id) {
+        player.command_last_used.insert(spec.name, now);
+    }
+    Ok(reply)
+}
+
+fn run(lobby: &mut Lobby, player_id: u32, spec: &CommandSpec, args: &[&str]) -> Result<String, &'static str> {
+    match spec.command {
+        ChatCommand::Kill => {
+            if !lobby.is_match_live() || lobby.is_paused() {
+                return Err("No match in progress");
+            }
+            if lobby.players.get(&player_id).is_some_and(|p| p.is_dead) {
+                return Err("Already dead");
+            }
+            logic::kill_player(lobby, player_id)?;
+            lobby.push_event(SyncEvent::PlayerDied { player_id, cause: logic::DeathCause::Suicide.as_str() });
+            Ok("You killed yourself".to_string())
+        }
+        ChatCommand::Stats => {
+            let target_id = match args.first() {
+                Some(arg) => resolve_player(lobby, arg)?,
+                None => player_id,
+            };
+            let player = lobby.players.get(&target_id).ok_or("No such player")?;
+            Ok(format!(
+                "{}: {} kills, {} deaths, {} score, best streak {}",
+                player.name, player.kills, player.deaths, player.score, player.match_stats.best_killstreak
+            ))
+        }
+        ChatCommand::VoteKick => {
+            let arg = args.first().ok_or("Usage: /votekick <player>")?;
+            let target_id = resolve_player(lobby, arg)?;
+            votes::start_vote(lobby, player_id, VoteKind::Kick { target_id }, votes::VOTE_DURATION_SECS)?;
+            Ok(format!("Vote to kick {} started", lobby.players[&target_id].name))
+        }
+        ChatCommand::Players => {
+            let mut players: Vec<_> = lobby.players.values().filter(|p| p.id != 999).collect();
+            players.sort_by_key(|p| p.id);
+            let names: Vec<String> = players.iter().map(|p| format!("{} ({})", p.name, p.id)).collect();
+            Ok(format!("{} players: {}", names.len(), names.join(", ")))
+        }
+        ChatCommand::Help => {
+            let usages: Vec<&str> = COMMANDS.iter()
+                .filter(|spec| check_permission(lobby, player_id, spec.permission).is_ok())
+                .map(|spec| spec.usage)
+                .collect();
+            Ok(format!("Commands: {}", usages.join(", ")))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::lobbies::add_player;
+    use crate::state::lobby::MatchPhase;
+    use crate::utils::weapondb::WeaponDb;
+
+    fn live_lobby() -> Lobby {
+        let mut lobby = Lobby::new("CMDS".to_string(), 4, "world".to_string());
+        let weapons = WeaponDb::load();
+        add_player(&mut lobby, 1, "Alice".to_string(), 1, &weapons).unwrap();
+        add_player(&mut lobby, 2, "Bob".to_string(), 1, &weapons).unwrap();
+        add_player(&mut lobby, 3, "Carol".to_string(), 1, &weapons).unwrap();
+        lobby.phase = MatchPhase::InProgress;
+        lobby
+    }
+
+    #[test]
+    fn test_kill_and_cooldown() {
+        let mut lobby = live_lobby();
+        let now = SystemTime::now();
+        assert!(is_command("  /kill") && !is_command("gg /kill"));
+        assert!(execute(&mut lobby, 1, "/KILL", now).is_ok());
+        assert!(lobby.players[&1].is_dead);
+        assert_eq!(lobby.players[&1].deaths, 1);
+        assert!(lobby.pending_events.iter().any(|e| matches!(e, SyncEvent::PlayerDied { player_id: 1, cause: "suicide" })));
+
+        lobby.players.get_mut(&1).unwrap().is_dead = false;
+        assert_eq!(execute(&mut lobby, 1, "/kill", now + Duration::from_secs(1)), Err("Command on cooldown"));
+        assert!(execute(&mut lobby, 1, "/kill", now + Duration::from_secs(6)).is_ok());
+
+        // Failed commands don't start the cooldown
+        lobby.players.get_mut(&2).unwrap().spectating = true;
+        assert_eq!(execute(&mut lobby, 2, "/kill", now), Err("Only players in the match can use that"));
+        assert_eq!(execute(&mut lobby, 2, "/dance", now), Err("Unknown command, try /help"));
+        assert!(lobby.players[&2].command_last_used.is_empty());
+    }
+
+    #[test]
+    fn test_stats_players_and_votekick() {
+        let mut lobby = live_lobby();
+        let now = SystemTime::now();
+        lobby.players.get_mut(&2).unwrap().kills = 4;
+        assert_eq!(execute(&mut lobby, 1, "/stats bob", now), Ok("Bob: 4 kills, 0 deaths, 0 score, best streak 0".to_string()));
+        assert_eq!(execute(&mut lobby, 1, "/players", now), Ok("3 players: Alice (1), Bob (2), Carol (3)".to_string()));
+
+        assert_eq!(execute(&mut lobby, 1, "/votekick", now), Err("Usage: /votekick <player>"));
+        assert_eq!(execute(&mut lobby, 1, "/votekick 3", now), Ok("Vote to kick Carol started".to_string()));
+        assert_eq!(lobby.active_vote.as_ref().map(|v| v.kind.clone()), Some(VoteKind::Kick { target_id: 3 }));
+        assert_eq!(execute(&mut lobby, 2, "/votekick Alice", now), Err("A vote is already running"));
+    }
+}
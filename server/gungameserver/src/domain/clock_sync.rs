@@ -0,0 +1,121 @@
+use crate::state::lobby::Lobby;
+use std::time::UNIX_EPOCH;
+use crate::utils::clock;
+
+/// Weight of a new sample in the smoothed offset, jitter and drift (EWMA)
+const SMOOTHING: f64 = 0.1;
+
+/// Interpolation delay recommended to a client with no jitter, in ticks
+const BASE_INTERP_TICKS: f64 = 2.0;
+
+/// Multiples of a client's jitter added on top of the base delay
+const JITTER_MARGIN: f64 = 2.0;
+
+/// Clock statistics for one client, built from its `time_sync` requests
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct ClockStats {
+    pub samples: u32,
+    /// Smoothed server clock minus client clock
+    pub offset_ms: f64,
+    /// Smoothed distance of samples from the offset
+    pub jitter_ms: f64,
+    /// How fast the offset moves (the clocks tick at different rates)
+    pub drift_ms_per_sec: f64,
+    last_sample_ms: u64,
+    last_sample_offset_ms: f64,
+}
+
+impl ClockStats {
+    /// Interpolation delay the client should buffer remote players by
+    pub fn interp_delay_ms(&self, tick_interval_ms: u64) -> f64 {
+        BASE_INTERP_TICKS * tick_interval_ms as f64 + JITTER_MARGIN * self.jitter_ms
+    }
+}
+
+/// Answer to one `time_sync` request, sent back to the client
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimeSyncReply {
+    pub player_id: u32,
+    pub seq: Option<u64>,
+    pub client_time_ms: f64,
+    pub received_at_ms: u64,
+    pub server_time_ms: u64,
+    pub tick: u64,
+    pub tick_interval_ms: u64,
+    pub stats: ClockStats,
+}
+
+/// Milliseconds since the Unix epoch on the server clock
+pub fn now_ms() -> u64 {
+    clock::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Fold one request into the player's clock stats
+/// The request is assumed to have taken half the player's smoothed RTT to arrive
+pub fn record_sample(
+    lobby: &mut Lobby,
+    player_id: u32,
+    client_time_ms: f64,
+    received_at_ms: u64,
+) -> Result<ClockStats, &'static str> {
+    if !client_time_ms.is_finite() {
+        return Err("Invalid client time");
+    }
+    let player = lobby.players.get(&player_id).ok_or("Player not found")?;
+    let one_way_ms = player.rtt_ms.unwrap_or(0.0) as f64 / 2.0;
+    let sample = received_at_ms as f64 - (client_time_ms + one_way_ms);
+
+    let stats = lobby.clock_stats.entry(player_id).or_default();
+    if stats.samples == 0 {
+        stats.offset_ms = sample;
+    } else {
+        let elapsed_secs = received_at_ms.saturating_sub(stats.last_sample_ms) as f64 / 1000.0;
+        // Error against where drift says the offset should be by now
+        let predicted = stats.offset_ms + stats.drift_ms_per_sec * elapsed_secs;
+        let error = sample - predicted;
+        stats.jitter_ms += SMOOTHING * (error.abs() - stats.jitter_ms);
+        stats.offset_ms = predicted + SMOOTHING * error;
+        if elapsed_secs > 0.0 {
+            let drift = (sample - stats.last_sample_offset_ms) / elapsed_secs;
+            stats.drift_ms_per_sec += SMOOTHING * (drift - stats.drift_ms_per_sec);
+        }
+    }
+    stats.samples += 1;
+    stats.last_sample_ms = received_at_ms;
+    stats.last_sample_offset_ms = sample;
+    Ok(*stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::lobbies::add_player;
+    use crate::utils::weapondb::WeaponDb;
+
+    #[test]
+    fn test_offset_smoothed_and_drift_tracked() {
+        let mut lobby = Lobby::new("TEST".to_string(), 4, "world".to_string());
+        add_player(&mut lobby, 1, "P1".to_string(), 1, &WeaponDb::load()).unwrap();
+        lobby.players.get_mut(&1).unwrap().rtt_ms = Some(40.0);
+
+        // Client clock 1000ms behind, request arrives 20ms after sending
+        let stats = record_sample(&mut lobby, 1, 9_000.0, 10_020).unwrap();
+        assert_eq!(stats.offset_ms, 1000.0);
+        assert_eq!(stats.jitter_ms, 0.0);
+
+        // The client clock runs slow: 10ms more offset per second
+        for second in 1..=20u64 {
+            record_sample(&mut lobby, 1, 9_000.0 + second as f64 * 1000.0, 10_020 + second * 1010).unwrap();
+        }
+        let stats = lobby.clock_stats[&1];
+        assert_eq!(stats.samples, 21);
+        assert!(stats.offset_ms > 1000.0);
+        assert!((5.0..=10.0).contains(&stats.drift_ms_per_sec));
+        assert!(stats.interp_delay_ms(20) > 40.0);
+
+        assert_eq!(record_sample(&mut lobby, 2, 0.0, 0), Err("Player not found"));
+    }
+}
@@ -0,0 +1,148 @@
+use crate::domain::bots;
+use crate::state::lobby::Lobby;
+use crate::utils::buffers::SyncEvent;
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime};
+
+/// Missed heartbeats before a client counts as degraded
+pub const DEGRADED_AFTER_MISSED: u32 = 3;
+
+/// Missed heartbeats before a client counts as lost; it only gets critical updates until it's heard from again
+pub const LOST_AFTER_MISSED: u32 = 6;
+
+/// How a client's link looks from the gap since its last packet
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ConnectionState {
+    #[default]
+    Connected,
+    Degraded,
+    Lost,
+}
+
+impl ConnectionState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ConnectionState::Connected => "connected",
+            ConnectionState::Degraded => "degraded",
+            ConnectionState::Lost => "lost",
+        }
+    }
+}
+
+/// Classify a heartbeat gap against the interval clients were told to keep
+pub fn classify(gap: Duration, heartbeat_interval: Duration) -> ConnectionState {
+    if gap >= heartbeat_interval * LOST_AFTER_MISSED {
+        ConnectionState::Lost
+    } else if gap >= heartbeat_interval * DEGRADED_AFTER_MISSED {
+        ConnectionState::Degraded
+    } else {
+        ConnectionState::Connected
+    }
+}
+
+/// Last classification of each client that isn't plainly connected
+#[derive(Debug, Default)]
+pub struct Connectivity {
+    states: HashMap<u32, ConnectionState>,
+}
+
+impl Connectivity {
+    pub fn state_of(&self, player_id: u32) -> ConnectionState {
+        self.states.get(&player_id).copied().unwrap_or_default()
+    }
+
+    pub fn is_lost(&self, player_id: u32) -> bool {
+        self.state_of(player_id) == ConnectionState::Lost
+    }
+
+    pub fn forget(&mut self, player_id: u32) {
+        self.states.remove(&player_id);
+    }
+}
+
+/// Reclassify every human client from the time since it was last heard from
+/// Each change is announced to the player's teammates and to spectators
+pub fn update(lobby: &mut Lobby, heartbeat_interval: Duration, now: SystemTime) {
+    let mut changed = Vec::new();
+    for (player_id, player) in &lobby.players {
+        if *player_id == 999 || bots::is_bot(*player_id) {
+            continue;
+        }
+        // Transferred players are given a grace period by a last_update in the future
+        let gap = now.duration_since(player.last_update).unwrap_or_default();
+        let state = classify(gap, heartbeat_interval);
+        if state != lobby.connectivity.state_of(*player_id) {
+            changed.push((*player_id, state));
+        }
+    }
+    changed.sort_by_key(|(player_id, _)| *player_id);
+
+    for (player_id, state) in changed {
+        match state {
+            ConnectionState::Connected => lobby.connectivity.states.remove(&player_id),
+            _ => lobby.connectivity.states.insert(player_id, state),
+        };
+        log::info!("Player {} in lobby {} is now {}", player_id, lobby.code, state.as_str());
+        for to_id in watchers(lobby, player_id) {
+            lobby.push_event(SyncEvent::ConnectionDegraded { to_id, player_id, state });
+        }
+    }
+}
+
+/// Players told about `player_id`'s connection: spectators, and teammates in team lobbies
+fn watchers(lobby: &Lobby, player_id: u32) -> Vec<u32> {
+    let team = lobby.teams.team_of(player_id);
+    let mut ids: Vec<u32> = lobby.players.values()
+        .filter(|p| p.id != player_id && !bots::is_bot(p.id))
+        .filter(|p| p.spectating || (team.is_some() && lobby.teams.team_of(p.id) == team))
+        .map(|p| p.id)
+        .collect();
+    ids.sort_unstable();
+    ids
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::lobby::LobbySettings;
+
+    #[test]
+    fn test_classify_by_missed_heartbeats() {
+        let interval = Duration::from_secs(2);
+        assert_eq!(classify(Duration::from_secs(5), interval), ConnectionState::Connected);
+        assert_eq!(classify(Duration::from_secs(6), interval), ConnectionState::Degraded);
+        assert_eq!(classify(Duration::from_secs(12), interval), ConnectionState::Lost);
+    }
+
+    #[test]
+    fn test_changes_notify_teammates_and_spectators() {
+        let settings = LobbySettings { team_count: 2, ..Default::default() };
+        let mut lobby = Lobby::with_settings("CONN".to_string(), 8, "world".to_string(), settings);
+        let now = SystemTime::now();
+        for id in 1..=4 {
+            let mut player = Lobby::new_player(id, format!("P{}", id), 1, 20);
+            player.last_update = now;
+            lobby.players.insert(id, player);
+            crate::domain::teams::assign(&mut lobby, id, None);
+        }
+        // 1 and 3 share a team; 4 is spectating from the other one
+        lobby.players.get_mut(&4).unwrap().spectating = true;
+        lobby.pending_events.clear();
+
+        let interval = Duration::from_secs(2);
+        update(&mut lobby, interval, now + Duration::from_secs(1));
+        assert!(lobby.pending_events.is_empty());
+
+        lobby.players.get_mut(&1).unwrap().last_update = now - Duration::from_secs(6);
+        update(&mut lobby, interval, now);
+        assert_eq!(lobby.connectivity.state_of(1), ConnectionState::Degraded);
+        let told: Vec<Option<u32>> = lobby.pending_events.iter().map(|e| e.recipient()).collect();
+        assert_eq!(told, vec![Some(3), Some(4)]);
+
+        lobby.pending_events.clear();
+        lobby.players.get_mut(&1).unwrap().last_update = now;
+        update(&mut lobby, interval, now);
+        assert_eq!(lobby.connectivity.state_of(1), ConnectionState::Connected);
+        assert!(matches!(lobby.pending_events[0], SyncEvent::ConnectionDegraded { state: ConnectionState::Connected, .. }));
+    }
+}
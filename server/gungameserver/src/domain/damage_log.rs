@@ -0,0 +1,106 @@
+use crate::domain::validation::ViolationKind;
+use crate::utils::weapondb::WeaponData;
+
+/// Damage records kept per match; later hits are dropped and the log marked truncated
+pub const MAX_DAMAGE_RECORDS: usize = 20_000;
+
+/// Hits farther than the weapon's range times this (plus slack) are flagged
+const RANGE_TOLERANCE: f32 = 1.5;
+
+/// Extra distance allowed on top of the scaled range (position updates lag)
+const RANGE_SLACK: f32 = 2.0;
+
+/// Hits allowed per second beyond what the weapon's fire rate explains
+const RATE_SLACK: usize = 1;
+
+/// One landed hit, stamped with the match tick it landed on
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, utoipa::ToSchema)]
+pub struct DamageRecord {
+    pub tick: u64,
+    pub attacker_id: u32,
+    pub victim_id: u32,
+    pub weapon_id: u32,
+    pub amount: u32,
+    pub health_after: u32,
+    /// Attacker to victim when the hit landed
+    pub distance: f32,
+}
+
+/// Every hit of one match, in tick order (dispute resolution, anti-cheat)
+#[derive(Debug, Clone, Default)]
+pub struct DamageLog {
+    pub truncated: bool,
+    records: Vec<DamageRecord>,
+}
+
+impl DamageLog {
+    pub fn record(&mut self, record: DamageRecord) {
+        if self.records.len() >= MAX_DAMAGE_RECORDS {
+            self.truncated = true;
+            return;
+        }
+        self.records.push(record);
+    }
+
+    pub fn records(&self) -> &[DamageRecord] {
+        &self.records
+    }
+
+    /// Hits dealt or taken by one player
+    pub fn involving(&self, player_id: u32) -> Vec<DamageRecord> {
+        self.records.iter()
+            .filter(|r| r.attacker_id == player_id || r.victim_id == player_id)
+            .copied()
+            .collect()
+    }
+
+    /// Check the latest hit against the weapon and the attacker's recent hits
+    /// `ticks_per_sec` sizes the window the fire rate is checked over
+    pub fn check(&self, record: &DamageRecord, weapon: &WeaponData, ticks_per_sec: u64) -> Option<ViolationKind> {
+        if record.distance > weapon.range * RANGE_TOLERANCE + RANGE_SLACK {
+            return Some(ViolationKind::HitOutOfRange);
+        }
+        let window_start = record.tick.saturating_sub(ticks_per_sec.saturating_sub(1));
+        let recent = self.records.iter().rev()
+            .take_while(|r| r.tick >= window_start)
+            .filter(|r| r.attacker_id == record.attacker_id && r.weapon_id == record.weapon_id)
+            .count();
+        if recent > weapon.fire_rate.ceil() as usize + RATE_SLACK {
+            return Some(ViolationKind::DamageRateExceeded);
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::weapondb::WeaponDb;
+
+    fn hit(tick: u64, attacker_id: u32, distance: f32) -> DamageRecord {
+        DamageRecord { tick, attacker_id, victim_id: 9, weapon_id: 1, amount: 20, health_after: 80, distance }
+    }
+
+    #[test]
+    fn test_check_flags_range_and_rate() {
+        let weapons = WeaponDb::load();
+        let rifle = weapons.get(1).unwrap(); // 4 shots/s, range 100
+        let mut log = DamageLog::default();
+
+        assert_eq!(log.check(&hit(0, 1, 500.0), rifle, 50), Some(ViolationKind::HitOutOfRange));
+
+        // Fire rate explains up to 5 hits per second
+        for tick in 0..5 {
+            log.record(hit(tick * 10, 1, 10.0));
+            assert_eq!(log.check(&hit(tick * 10, 1, 10.0), rifle, 50), None);
+        }
+        log.record(hit(45, 1, 10.0));
+        assert_eq!(log.check(&hit(45, 1, 10.0), rifle, 50), Some(ViolationKind::DamageRateExceeded));
+
+        // A second later the window has moved on
+        log.record(hit(100, 1, 10.0));
+        assert_eq!(log.check(&hit(100, 1, 10.0), rifle, 50), None);
+        assert_eq!(log.involving(1).len(), 7);
+        assert!(log.involving(2).is_empty());
+    }
+}
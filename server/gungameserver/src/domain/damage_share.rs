@@ -0,0 +1,86 @@
+use std::collections::HashMap;
+
+/// Score a kill is worth before the killstreak bonus
+pub const KILL_SCORE: u32 = 100;
+
+/// Damage each player has taken from each attacker since they last died
+#[derive(Debug, Default)]
+pub struct DamageAttribution {
+    taken: HashMap<u32, Vec<(u32, u32)>>, // victim -> (attacker, damage), first attacker first
+}
+
+impl DamageAttribution {
+    pub fn record(&mut self, attacker_id: u32, victim_id: u32, amount: u32) {
+        if attacker_id == victim_id || amount == 0 {
+            return;
+        }
+        let by_attacker = self.taken.entry(victim_id).or_default();
+        match by_attacker.iter_mut().find(|(id, _)| *id == attacker_id) {
+            Some((_, total)) => *total += amount,
+            None => by_attacker.push((attacker_id, amount)),
+        }
+    }
+
+    /// Damage a victim took this life, clearing it for the next one
+    pub fn take(&mut self, victim_id: u32) -> Vec<(u32, u32)> {
+        self.taken.remove(&victim_id).unwrap_or_default()
+    }
+
+    /// Drop a leaving player both as victim and as attacker
+    pub fn forget(&mut self, player_id: u32) {
+        self.taken.remove(&player_id);
+        for by_attacker in self.taken.values_mut() {
+            by_attacker.retain(|(id, _)| *id != player_id);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.taken.clear();
+    }
+}
+
+/// Split `points` among the victim's attackers by damage dealt, as (player_id, points)
+/// Shares round down; the remainder, and everything when no damage was recorded, goes to the killer
+pub fn split(killer_id: u32, points: u32, damage: &[(u32, u32)]) -> Vec<(u32, u32)> {
+    let total: u64 = damage.iter().map(|(_, amount)| *amount as u64).sum();
+    let mut shares: Vec<(u32, u32)> = damage.iter()
+        .filter(|(id, _)| *id != killer_id && total > 0)
+        .map(|(id, amount)| (*id, (points as u64 * *amount as u64 / total) as u32))
+        .filter(|(_, share)| *share > 0)
+        .collect();
+    let given: u32 = shares.iter().map(|(_, share)| share).sum();
+    shares.insert(0, (killer_id, points - given));
+    shares
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kill_score_split_by_damage() {
+        let mut attribution = DamageAttribution::default();
+        attribution.record(2, 1, 60);
+        attribution.record(3, 1, 20);
+        attribution.record(2, 1, 10);
+        attribution.record(1, 1, 50); // Self damage earns nothing
+        let damage = attribution.take(1);
+        assert_eq!(damage, vec![(2, 70), (3, 20)]);
+        assert!(attribution.take(1).is_empty());
+
+        // 3 finished off a victim 2 did most of the work on
+        assert_eq!(split(3, KILL_SCORE, &damage), vec![(3, 23), (2, 77)]);
+        assert_eq!(split(4, KILL_SCORE, &[]), vec![(4, KILL_SCORE)]);
+    }
+
+    #[test]
+    fn test_forget_drops_player_on_both_sides() {
+        let mut attribution = DamageAttribution::default();
+        attribution.record(2, 1, 30);
+        attribution.record(3, 1, 30);
+        attribution.record(1, 2, 30);
+        attribution.forget(2);
+        assert_eq!(attribution.take(1), vec![(3, 30)]);
+        assert!(attribution.take(2).is_empty());
+    }
+}
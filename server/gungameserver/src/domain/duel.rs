@@ -0,0 +1,225 @@
+use crate::domain::logic;
+use crate::state::lobby::{GameMode, Lobby};
+use crate::utils::buffers::SyncEvent;
+use crate::utils::weapondb::WeaponLookup;
+use std::collections::HashMap;
+
+/// Pause between one round ending and the next starting
+pub const ROUND_INTERMISSION_SECS: f32 = 3.0;
+
+/// How a duel round was decided
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RoundEnd {
+    Death,
+    /// Round timer ran out; more health left (as a fraction) wins
+    Time,
+    /// The opponent left; forfeits the whole match
+    Forfeit,
+}
+
+impl RoundEnd {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RoundEnd::Death => "death",
+            RoundEnd::Time => "time",
+            RoundEnd::Forfeit => "forfeit",
+        }
+    }
+}
+
+/// Round progress of the current duel match
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DuelState {
+    /// Current or last played round (0 before the first)
+    pub round: u32,
+    /// Between round_started and round_ended; combat is only allowed then
+    pub round_live: bool,
+    pub round_remaining: f32,
+    intermission: f32,
+    pub wins: HashMap<u32, u32>,
+}
+
+impl DuelState {
+    /// Round wins by player, most first
+    pub fn standings(&self) -> Vec<(u32, u32)> {
+        let mut standings: Vec<(u32, u32)> = self.wins.iter().map(|(id, wins)| (*id, *wins)).collect();
+        standings.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        standings
+    }
+}
+
+/// Round wins needed to take a best-of-`rounds` duel
+pub fn rounds_to_win(rounds: u32) -> u32 {
+    rounds / 2 + 1
+}
+
+/// Players taking part (not the dummy bot or spectators)
+fn duelists(lobby: &Lobby) -> Vec<u32> {
+    let mut ids: Vec<u32> = lobby.players.values()
+        .filter(|p| p.id != 999 && !p.spectating) // Exclude dummy bot
+        .map(|p| p.id)
+        .collect();
+    ids.sort_unstable();
+    ids
+}
+
+/// Advance a live duel by one tick: start rounds, time them, and decide them
+/// Returns the match winner once someone has won enough rounds (or the opponent left)
+pub fn update(lobby: &mut Lobby, weapons: &impl WeaponLookup, dt: f32) -> Option<u32> {
+    if lobby.settings.game_mode != GameMode::Duel || !lobby.is_match_live() {
+        return None;
+    }
+    let duelists = duelists(lobby);
+    if duelists.len() < 2 {
+        // Alone from the start there's nobody to duel; once rounds are underway the opponent forfeits
+        let winner = *duelists.first()?;
+        if lobby.duel.round == 0 {
+            return None;
+        }
+        end_round(lobby, Some(winner), RoundEnd::Forfeit);
+        return Some(winner);
+    }
+
+    if !lobby.duel.round_live {
+        lobby.duel.intermission -= dt;
+        if lobby.duel.intermission <= 0.0 {
+            start_round(lobby, weapons, &duelists);
+        }
+        return None;
+    }
+
+    lobby.duel.round_remaining -= dt;
+    let alive: Vec<u32> = duelists.iter().copied()
+        .filter(|id| lobby.players.get(id).is_some_and(|p| !p.is_dead))
+        .collect();
+    let (winner, reason) = match alive.as_slice() {
+        [] => (None, RoundEnd::Death),
+        [survivor] => (Some(*survivor), RoundEnd::Death),
+        _ if lobby.duel.round_remaining <= 0.0 => (healthiest(lobby, &alive), RoundEnd::Time),
+        _ => return None,
+    };
+    end_round(lobby, winner, reason);
+    match_winner(lobby)
+}
+
+/// Highest health fraction, or None on a tie
+fn healthiest(lobby: &Lobby, ids: &[u32]) -> Option<u32> {
+    let fraction = |id: &u32| lobby.players.get(id)
+        .map(|p| p.current_health as f32 / p.max_health.max(1) as f32)
+        .unwrap_or(0.0);
+    let mut ranked: Vec<(u32, f32)> = ids.iter().map(|id| (*id, fraction(id))).collect();
+    ranked.sort_by(|a, b| b.1.total_cmp(&a.1));
+    match ranked.as_slice() {
+        [(best, top), (_, next), ..] if top > next => Some(*best),
+        _ => None,
+    }
+}
+
+/// Reset both duelists to full health and their loadouts, and start the clock
+fn start_round(lobby: &mut Lobby, weapons: &impl WeaponLookup, duelists: &[u32]) {
+    lobby.duel.round += 1;
+    lobby.duel.round_live = true;
+    lobby.duel.round_remaining = lobby.settings.duel_round_secs as f32;
+    for &player_id in duelists {
+        if logic::respawn_player(lobby, player_id).is_err() {
+            continue;
+        }
+        if let Some(player) = lobby.players.get_mut(&player_id) {
+            if player.pending_loadout.is_none() {
+                player.pending_loadout = player.active_loadout.clone();
+            }
+        }
+        if let Err(e) = logic::apply_pending_loadout(lobby, weapons, player_id) {
+            log::debug!("Loadout not applied for duelist {}: {}", player_id, e);
+        }
+    }
+    lobby.push_event(SyncEvent::RoundStarted {
+        round: lobby.duel.round,
+        rounds: lobby.settings.duel_rounds,
+        time_limit_secs: lobby.settings.duel_round_secs,
+    });
+}
+
+fn end_round(lobby: &mut Lobby, winner: Option<u32>, reason: RoundEnd) {
+    let duel = &mut lobby.duel;
+    duel.round_live = false;
+    duel.intermission = ROUND_INTERMISSION_SECS;
+    if let Some(winner) = winner {
+        *duel.wins.entry(winner).or_default() += 1;
+    }
+    let event = SyncEvent::RoundEnded {
+        round: duel.round,
+        winner,
+        reason: reason.as_str(),
+        wins: duel.standings(),
+    };
+    lobby.push_event(event);
+}
+
+/// Whoever reached the round wins needed; after the last scheduled round, the leader
+/// Level after every round (draws) means sudden-death rounds until someone leads
+fn match_winner(lobby: &Lobby) -> Option<u32> {
+    let rounds = lobby.settings.duel_rounds;
+    let standings = lobby.duel.standings();
+    let (leader, wins) = *standings.first()?;
+    let runner_up = standings.get(1).map_or(0, |(_, wins)| *wins);
+    let decided = wins >= rounds_to_win(rounds) || (lobby.duel.round >= rounds && wins > runner_up);
+    decided.then_some(leader)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::lobbies::add_player;
+    use crate::state::lobby::{LobbySettings, MatchPhase};
+    use crate::utils::weapondb::WeaponDb;
+
+    fn duel_lobby(weapons: &WeaponDb, rounds: u32) -> Lobby {
+        let settings = LobbySettings { game_mode: GameMode::Duel, duel_rounds: rounds, duel_round_secs: 30, ..Default::default() };
+        let mut lobby = Lobby::with_settings("DUEL".to_string(), 2, "world".to_string(), settings);
+        add_player(&mut lobby, 1, "Alice".to_string(), 1, weapons).unwrap();
+        add_player(&mut lobby, 2, "Bob".to_string(), 1, weapons).unwrap();
+        lobby.phase = MatchPhase::InProgress;
+        lobby
+    }
+
+    #[test]
+    fn test_best_of_three_decided_by_deaths() {
+        let weapons = WeaponDb::load();
+        let mut lobby = duel_lobby(&weapons, 3);
+
+        assert_eq!(update(&mut lobby, &weapons, 0.02), None);
+        assert!(lobby.duel.round_live);
+        assert!(matches!(lobby.pending_events.last(), Some(SyncEvent::RoundStarted { round: 1, rounds: 3, .. })));
+
+        logic::kill_player(&mut lobby, 2).unwrap();
+        assert_eq!(update(&mut lobby, &weapons, 0.02), None);
+        assert!(!lobby.duel.round_live);
+        assert_eq!(lobby.duel.wins[&1], 1);
+
+        // Next round starts after the intermission with both back at full health
+        update(&mut lobby, &weapons, ROUND_INTERMISSION_SECS);
+        assert_eq!(lobby.duel.round, 2);
+        assert!(!lobby.players[&2].is_dead);
+        assert_eq!(lobby.players[&2].current_health, lobby.players[&2].max_health);
+
+        logic::kill_player(&mut lobby, 2).unwrap();
+        assert_eq!(update(&mut lobby, &weapons, 0.02), Some(1));
+        assert!(matches!(&lobby.pending_events.last(), Some(SyncEvent::RoundEnded { round: 2, winner: Some(1), reason: "death", .. })));
+    }
+
+    #[test]
+    fn test_time_limit_favours_health_and_leaver_forfeits() {
+        let weapons = WeaponDb::load();
+        let mut lobby = duel_lobby(&weapons, 5);
+        update(&mut lobby, &weapons, 0.02);
+
+        lobby.players.get_mut(&1).unwrap().current_health = 40;
+        assert_eq!(update(&mut lobby, &weapons, 30.0), None);
+        assert_eq!(lobby.duel.standings(), vec![(2, 1)]);
+        assert!(matches!(&lobby.pending_events.last(), Some(SyncEvent::RoundEnded { reason: "time", .. })));
+
+        lobby.players.remove(&2);
+        assert_eq!(update(&mut lobby, &weapons, 0.02), Some(1));
+    }
+}
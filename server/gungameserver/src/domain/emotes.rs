@@ -0,0 +1,136 @@
+use crate::domain::interest;
+use crate::state::lobby::Lobby;
+use crate::utils::buffers::SyncEvent;
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime};
+
+/// Minimum time between emotes from the same player
+pub const EMOTE_COOLDOWN: Duration = Duration::from_secs(3);
+
+/// Players further than this from an emote aren't told about it
+pub const EMOTE_VISIBLE_RADIUS: f32 = 40.0;
+
+/// An emote clients know how to play, and how long it lasts
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EmoteSpec {
+    pub id: &'static str,
+    pub duration: Duration,
+}
+
+/// Every emote a player may use
+pub const EMOTES: &[EmoteSpec] = &[
+    EmoteSpec { id: "wave", duration: Duration::from_secs(2) },
+    EmoteSpec { id: "salute", duration: Duration::from_secs(2) },
+    EmoteSpec { id: "thumbs_up", duration: Duration::from_secs(2) },
+    EmoteSpec { id: "laugh", duration: Duration::from_secs(3) },
+    EmoteSpec { id: "taunt", duration: Duration::from_secs(3) },
+    EmoteSpec { id: "dance", duration: Duration::from_secs(6) },
+];
+
+pub fn find(id: &str) -> Option<&'static EmoteSpec> {
+    EMOTES.iter().find(|spec| spec.id == id)
+}
+
+/// An emote still playing
+#[derive(Debug, Clone, Copy)]
+pub struct ActiveEmote {
+    pub spec: &'static EmoteSpec,
+    pub ends_at: SystemTime,
+}
+
+/// Emotes playing and when each player last emoted
+#[derive(Debug, Default)]
+pub struct EmoteState {
+    active: HashMap<u32, ActiveEmote>,
+    last_used: HashMap<u32, SystemTime>,
+}
+
+impl EmoteState {
+    /// Emotes still playing as (player_id, emote, seconds left), in player order
+    pub fn playing(&self, now: SystemTime) -> Vec<(u32, &'static str, f32)> {
+        let mut playing: Vec<_> = self.active.iter()
+            .filter_map(|(id, emote)| emote.ends_at.duration_since(now).ok().map(|left| (*id, emote.spec.id, left.as_secs_f32())))
+            .filter(|(_, _, left)| *left > 0.0)
+            .collect();
+        playing.sort_by_key(|(id, _, _)| *id);
+        playing
+    }
+
+    pub fn forget(&mut self, player_id: u32) {
+        self.active.remove(&player_id);
+        self.last_used.remove(&player_id);
+    }
+
+    fn prune(&mut self, now: SystemTime) {
+        self.active.retain(|_, emote| emote.ends_at > now);
+        self.last_used.retain(|_, at| now.duration_since(*at).is_ok_and(|elapsed| elapsed < EMOTE_COOLDOWN));
+    }
+}
+
+/// Play an emote for a player and tell the players near them
+/// Moving, shooting or dying doesn't cancel it; clients blend it out themselves
+pub fn play(lobby: &mut Lobby, player_id: u32, emote: &str, now: SystemTime) -> Result<(), &'static str> {
+    let spec = find(emote).ok_or("Unknown emote")?;
+    let player = lobby.players.get(&player_id).ok_or("Player not found")?;
+    if player.spectating {
+        return Err("Spectators can't emote");
+    }
+    if player.is_dead {
+        return Err("Dead players can't emote");
+    }
+    let position = player.position;
+
+    lobby.emotes.prune(now);
+    if lobby.emotes.last_used.contains_key(&player_id) {
+        return Err("Emote on cooldown");
+    }
+    lobby.emotes.last_used.insert(player_id, now);
+    lobby.emotes.active.insert(player_id, ActiveEmote { spec, ends_at: now + spec.duration });
+
+    let duration = spec.duration.as_secs_f32();
+    for to_id in interest::players_within(lobby, position, EMOTE_VISIBLE_RADIUS, player_id) {
+        lobby.push_event(SyncEvent::EmotePlayed { to_id, player_id, emote: spec.id, duration });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lobby() -> Lobby {
+        let mut lobby = Lobby::new("TEST".to_string(), 8, "world".to_string());
+        for (id, x) in [(1, 0.0), (2, 10.0), (3, 100.0)] {
+            let mut player = Lobby::new_player(id, format!("P{}", id), 1, 20);
+            player.position = (x, 0.0, 0.0);
+            lobby.players.insert(id, player);
+        }
+        lobby
+    }
+
+    #[test]
+    fn test_emote_reaches_nearby_players() {
+        let mut lobby = lobby();
+        let now = SystemTime::now();
+        play(&mut lobby, 1, "wave", now).unwrap();
+
+        let recipients: Vec<u32> = lobby.pending_events.iter().filter_map(|e| e.recipient()).collect();
+        assert_eq!(recipients, vec![2]);
+        assert!(matches!(lobby.pending_events[0], SyncEvent::EmotePlayed { player_id: 1, emote: "wave", .. }));
+        assert_eq!(lobby.emotes.playing(now + Duration::from_secs(1)), vec![(1, "wave", 1.0)]);
+        assert!(lobby.emotes.playing(now + Duration::from_secs(2)).is_empty());
+    }
+
+    #[test]
+    fn test_emote_validation_and_cooldown() {
+        let mut lobby = lobby();
+        let now = SystemTime::now();
+        assert_eq!(play(&mut lobby, 1, "moonwalk", now), Err("Unknown emote"));
+        play(&mut lobby, 1, "dance", now).unwrap();
+        assert_eq!(play(&mut lobby, 1, "wave", now + Duration::from_secs(1)), Err("Emote on cooldown"));
+        play(&mut lobby, 1, "wave", now + EMOTE_COOLDOWN).unwrap();
+
+        lobby.players.get_mut(&2).unwrap().is_dead = true;
+        assert_eq!(play(&mut lobby, 2, "taunt", now), Err("Dead players can't emote"));
+    }
+}
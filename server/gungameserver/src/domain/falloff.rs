@@ -0,0 +1,85 @@
+use crate::domain::logic::MAX_DAMAGE;
+use crate::domain::pellets;
+use crate::utils::weapondb::WeaponData;
+
+/// Distance between the points of a dumped curve
+pub const CURVE_STEP: f32 = 1.0;
+
+/// Damage one shot of a weapon deals at a distance, as the server resolves it
+#[derive(Debug, Clone, PartialEq, serde::Serialize, utoipa::ToSchema)]
+pub struct FalloffPoint {
+    pub distance: f32,
+    /// Chance each pellet lands when the server rolls the spread (1 for single-shot weapons in range)
+    pub pellet_hit_chance: f32,
+    /// Damage when every pellet lands, after the per-hit cap
+    pub max_damage: u32,
+    /// Average damage per shot
+    pub expected_damage: f32,
+}
+
+/// Damage at one distance: nothing lands past `range`, and multi-pellet weapons lose
+/// pellets to spread as the cone widens. Ramp-up depends on consecutive hits, not distance,
+/// so it is left out (multipliers apply on top)
+pub fn at(weapon: &WeaponData, distance: f32) -> FalloffPoint {
+    let distance = distance.max(0.0);
+    if distance > weapon.range {
+        return FalloffPoint { distance, pellet_hit_chance: 0.0, max_damage: 0, expected_damage: 0.0 };
+    }
+    let chance = if weapon.pellets > 1 { pellets::hit_chance(distance, weapon.spread) } else { 1.0 };
+    let max_damage = (weapon.damage * weapon.pellets).min(MAX_DAMAGE);
+    let expected_damage = (weapon.damage as f32 * weapon.pellets as f32 * chance).min(MAX_DAMAGE as f32);
+    FalloffPoint { distance, pellet_hit_chance: chance, max_damage, expected_damage }
+}
+
+/// Points every `step` from 0 out to the weapon's range (inclusive)
+pub fn curve(weapon: &WeaponData, step: f32) -> Vec<FalloffPoint> {
+    let step = step.max(0.01);
+    let steps = (weapon.range / step).floor() as u32;
+    let mut points: Vec<FalloffPoint> = (0..=steps).map(|i| at(weapon, i as f32 * step)).collect();
+    if points.last().is_some_and(|last| last.distance < weapon.range) {
+        points.push(at(weapon, weapon.range));
+    }
+    points
+}
+
+/// Curve as CSV with a header row, for pasting into a spreadsheet
+pub fn to_csv(points: &[FalloffPoint]) -> String {
+    let mut csv = String::from("distance,pellet_hit_chance,max_damage,expected_damage\n");
+    for point in points {
+        csv.push_str(&format!("{},{:.4},{},{:.2}\n", point.distance, point.pellet_hit_chance, point.max_damage, point.expected_damage));
+    }
+    csv
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::weapondb::WeaponDb;
+
+    #[test]
+    fn test_falloff_follows_range_and_spread() {
+        let weapons = WeaponDb::load();
+        let pistol = weapons.get(1).unwrap();
+        assert_eq!(at(pistol, 1.0).expected_damage, pistol.damage as f32);
+        assert_eq!(at(pistol, pistol.range + 1.0).max_damage, 0);
+
+        let scattergun = weapons.get(4).unwrap();
+        let close = at(scattergun, 2.0);
+        let far = at(scattergun, 25.0);
+        assert_eq!(close.max_damage, MAX_DAMAGE.min(scattergun.damage * scattergun.pellets));
+        assert!(far.expected_damage < close.expected_damage);
+    }
+
+    #[test]
+    fn test_curve_covers_range_and_dumps_csv() {
+        let weapons = WeaponDb::load();
+        let scattergun = weapons.get(4).unwrap();
+        let points = curve(scattergun, 7.0);
+        assert_eq!(points.first().unwrap().distance, 0.0);
+        assert_eq!(points.last().unwrap().distance, scattergun.range);
+
+        let csv = to_csv(&points);
+        assert!(csv.starts_with("distance,"));
+        assert_eq!(csv.lines().count(), points.len() + 1);
+    }
+}
@@ -0,0 +1,86 @@
+use crate::state::lobby::Lobby;
+use std::collections::{HashMap, VecDeque};
+
+/// Ticks of position history kept per player (~0.6s at 50Hz)
+pub const HISTORY_TICKS: usize = 32;
+
+/// Where a player was on one tick
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PositionRecord {
+    pub tick: u64,
+    pub position: (f32, f32, f32),
+    pub rotation: (f32, f32, f32),
+}
+
+/// Recent per-tick positions of every living player (lag compensation, kill cams)
+#[derive(Debug, Clone, Default)]
+pub struct PositionHistory {
+    players: HashMap<u32, VecDeque<PositionRecord>>,
+    latest_tick: u64,
+}
+
+impl PositionHistory {
+    pub fn push(&mut self, player_id: u32, record: PositionRecord) {
+        self.latest_tick = self.latest_tick.max(record.tick);
+        let samples = self.players.entry(player_id).or_default();
+        if samples.len() >= HISTORY_TICKS {
+            samples.pop_front();
+        }
+        samples.push_back(record);
+    }
+
+    /// Drop a player's samples (on leave or death)
+    pub fn forget(&mut self, player_id: u32) {
+        self.players.remove(&player_id);
+    }
+
+    /// A player's samples, oldest first
+    pub fn samples(&self, player_id: u32) -> Vec<PositionRecord> {
+        self.players.get(&player_id)
+            .map(|samples| samples.iter().copied().collect())
+            .unwrap_or_default()
+    }
+
+    /// Most recent tick recorded for any player
+    pub fn latest_tick(&self) -> u64 {
+        self.latest_tick
+    }
+}
+
+/// Record every living player's position for this tick
+pub fn record_tick(lobby: &mut Lobby, tick: u64) {
+    let records: Vec<(u32, PositionRecord)> = lobby.players.values()
+        .filter(|p| !p.is_dead)
+        .map(|p| (p.id, PositionRecord { tick, position: p.position, rotation: p.rotation }))
+        .collect();
+    for (player_id, record) in records {
+        lobby.position_history.push(player_id, record);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(tick: u64) -> PositionRecord {
+        PositionRecord { tick, position: (tick as f32, 0.0, 0.0), rotation: (0.0, 0.0, 0.0) }
+    }
+
+    #[test]
+    fn test_history_is_bounded_per_player() {
+        let mut history = PositionHistory::default();
+        for tick in 0..(HISTORY_TICKS as u64 + 5) {
+            history.push(1, record(tick));
+        }
+        history.push(2, record(3));
+
+        let samples = history.samples(1);
+        assert_eq!(samples.len(), HISTORY_TICKS);
+        assert_eq!(samples[0].tick, 5);
+        assert_eq!(history.samples(2).len(), 1);
+        assert_eq!(history.latest_tick(), HISTORY_TICKS as u64 + 4);
+
+        history.forget(1);
+        assert!(history.samples(1).is_empty());
+    }
+}
@@ -0,0 +1,52 @@
+use crate::domain::bots;
+use crate::state::lobby::Lobby;
+
+type Vec3 = (f32, f32, f32);
+
+/// Square x/z grid cell, as (column, row); cell (0, 0) spans 0..size on both axes
+pub type GridCell = (i32, i32);
+
+/// Grid cell a position falls in, for cells of edge length `cell_size`
+pub fn grid_cell(position: Vec3, cell_size: f32) -> GridCell {
+    ((position.0 / cell_size).floor() as i32, (position.2 / cell_size).floor() as i32)
+}
+
+fn distance(a: Vec3, b: Vec3) -> f32 {
+    ((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2) + (a.2 - b.2).powi(2)).sqrt()
+}
+
+/// Connected players close enough to `origin` to care about something happening there,
+/// in ascending id order (bots have no client to tell)
+pub fn players_within(lobby: &Lobby, origin: Vec3, radius: f32, exclude: u32) -> Vec<u32> {
+    let mut ids: Vec<u32> = lobby.players.values()
+        .filter(|p| p.id != exclude && p.id != 999 && !bots::is_bot(p.id))
+        .filter(|p| distance(p.position, origin) <= radius)
+        .map(|p| p.id)
+        .collect();
+    ids.sort_unstable();
+    ids
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_players_within_radius() {
+        let mut lobby = Lobby::new("TEST".to_string(), 8, "world".to_string());
+        for (id, position) in [(1, (0.0, 0.0, 0.0)), (2, (3.0, 0.0, 4.0)), (3, (30.0, 0.0, 0.0))] {
+            let mut player = Lobby::new_player(id, format!("P{}", id), 1, 100);
+            player.position = position;
+            lobby.players.insert(id, player);
+        }
+        assert_eq!(players_within(&lobby, (0.0, 0.0, 0.0), 5.0, 1), vec![2]);
+        assert_eq!(players_within(&lobby, (0.0, 0.0, 0.0), 50.0, 1), vec![2, 3]);
+        assert!(players_within(&lobby, (100.0, 0.0, 0.0), 5.0, 1).is_empty());
+    }
+
+    #[test]
+    fn test_grid_cell_floors_on_x_and_z() {
+        assert_eq!(grid_cell((10.0, 99.0, 130.0), 125.0), (0, 1));
+        assert_eq!(grid_cell((-0.5, 0.0, -125.0), 125.0), (-1, -1));
+    }
+}
@@ -0,0 +1,86 @@
+use crate::state::lobby::Lobby;
+
+/// Weight of a new RTT sample in the smoothed estimate (EWMA)
+const RTT_SMOOTHING: f32 = 0.2;
+
+/// Largest plausible round trip; bigger samples are rejected
+pub const MAX_RTT_MS: f32 = 5000.0;
+
+/// Assumed latency to a same-region lobby with no samples yet
+const DEFAULT_LATENCY_MS: f32 = 50.0;
+
+/// Extra latency assumed when a lobby is hosted in another region
+const CROSS_REGION_PENALTY_MS: f32 = 100.0;
+
+/// Fold a client-reported RTT sample into the player's smoothed RTT
+pub fn record_rtt_sample(lobby: &mut Lobby, player_id: u32, rtt_ms: f32) -> Result<(), &'static str> {
+    if !(0.0..=MAX_RTT_MS).contains(&rtt_ms) {
+        return Err("Invalid RTT sample");
+    }
+
+    let player = lobby
+        .players
+        .get_mut(&player_id)
+        .ok_or("Player not found")?;
+
+    player.rtt_ms = Some(match player.rtt_ms {
+        Some(rtt) => rtt + RTT_SMOOTHING * (rtt_ms - rtt),
+        None => rtt_ms,
+    });
+    Ok(())
+}
+
+/// Average smoothed RTT of players that have reported one
+pub fn average_latency(lobby: &Lobby) -> Option<f32> {
+    let samples: Vec<f32> = lobby.players.values()
+        .filter_map(|p| p.rtt_ms)
+        .collect();
+    if samples.is_empty() {
+        None
+    } else {
+        Some(samples.iter().sum::<f32>() / samples.len() as f32)
+    }
+}
+
+/// Latency a client in `client_region` should expect in a lobby
+/// Without a client region the lobby's own average is used as-is
+pub fn expected_latency(lobby_region: &str, average_ms: Option<f32>, client_region: Option<&str>) -> f32 {
+    let base = average_ms.unwrap_or(DEFAULT_LATENCY_MS);
+    match client_region {
+        Some(region) if !region.eq_ignore_ascii_case(lobby_region) => base + CROSS_REGION_PENALTY_MS,
+        _ => base,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::lobbies::add_player;
+    use crate::utils::weapondb::WeaponDb;
+
+    #[test]
+    fn test_rtt_smoothing() {
+        let mut lobby = Lobby::new("TEST".to_string(), 4, "world".to_string());
+        let weapons = WeaponDb::load();
+        add_player(&mut lobby, 1, "A".to_string(), 1, &weapons).unwrap();
+        add_player(&mut lobby, 2, "B".to_string(), 1, &weapons).unwrap();
+        assert_eq!(average_latency(&lobby), None);
+
+        record_rtt_sample(&mut lobby, 1, 100.0).unwrap();
+        record_rtt_sample(&mut lobby, 1, 200.0).unwrap();
+        assert!((lobby.players.get(&1).unwrap().rtt_ms.unwrap() - 120.0).abs() < 0.001);
+
+        // Players without samples don't drag the average down
+        assert!((average_latency(&lobby).unwrap() - 120.0).abs() < 0.001);
+
+        assert_eq!(record_rtt_sample(&mut lobby, 1, -1.0), Err("Invalid RTT sample"));
+        assert_eq!(record_rtt_sample(&mut lobby, 3, 10.0), Err("Player not found"));
+    }
+
+    #[test]
+    fn test_expected_latency() {
+        assert_eq!(expected_latency("eu-west", Some(30.0), Some("eu-west")), 30.0);
+        assert_eq!(expected_latency("eu-west", Some(30.0), Some("us-east")), 130.0);
+        assert_eq!(expected_latency("eu-west", None, None), DEFAULT_LATENCY_MS);
+    }
+}
@@ -0,0 +1,117 @@
+use crate::state::lobby::{Lobby, LobbySettings};
+use crate::utils::buffers::SyncEvent;
+use crate::utils::config::Config;
+use std::time::{Duration, SystemTime};
+
+/// Shortest cap a lobby may set for itself
+pub const MIN_CAP_SECS: u64 = 60;
+
+/// Wall-clock ages the caps are measured against
+/// Paused time counts: the caps exist to clear out lobbies nobody is really playing in
+#[derive(Debug, Clone, Copy)]
+pub struct Lifetime {
+    pub created_at: SystemTime,
+    pub match_started_at: Option<SystemTime>, // Set on the first live tick seen, cleared when the match ends
+}
+
+impl Lifetime {
+    pub fn new(now: SystemTime) -> Self {
+        Self { created_at: now, match_started_at: None }
+    }
+}
+
+/// Longest a lobby's matches and the lobby itself may run
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LifetimeCaps {
+    pub max_match: Option<Duration>,
+    pub max_lobby: Option<Duration>,
+}
+
+impl LifetimeCaps {
+    /// The lobby's own caps, falling back to the server's
+    pub fn resolve(config: &Config, settings: &LobbySettings) -> Self {
+        let cap = |own: Option<u64>, default: Option<u64>| own.or(default).map(Duration::from_secs);
+        Self {
+            max_match: cap(settings.max_match_secs, config.max_match_secs),
+            max_lobby: cap(settings.max_lifetime_secs, config.max_lobby_lifetime_secs),
+        }
+    }
+}
+
+/// Which cap was reached
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Expiry {
+    /// The match is ended and the lobby goes back to the ready check
+    Match,
+    /// The match is ended and the lobby closed
+    Lobby,
+}
+
+impl Expiry {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Expiry::Match => "match",
+            Expiry::Lobby => "lobby",
+        }
+    }
+}
+
+/// Follow the match clock and report the first cap passed, the lobby's before the match's
+pub fn check(lobby: &mut Lobby, caps: &LifetimeCaps, now: SystemTime) -> Option<(Expiry, Duration)> {
+    let live = lobby.is_match_live();
+    match (live, lobby.lifetime.match_started_at) {
+        (true, None) => lobby.lifetime.match_started_at = Some(now),
+        (false, Some(_)) => lobby.lifetime.match_started_at = None,
+        _ => {}
+    }
+
+    let age = |since: SystemTime| now.duration_since(since).unwrap_or_default();
+    if let Some(max) = caps.max_lobby.filter(|max| age(lobby.lifetime.created_at) >= *max) {
+        return Some((Expiry::Lobby, max));
+    }
+    let started = lobby.lifetime.match_started_at?;
+    caps.max_match.filter(|max| age(started) >= *max).map(|max| (Expiry::Match, max))
+}
+
+/// Tell clients a cap was reached, ahead of the match ending
+/// A match cap restarts the match clock; the lobby is expected to close after a lobby cap
+pub fn expire(lobby: &mut Lobby, expiry: Expiry, cap: Duration) {
+    log::info!("Lobby {} reached its {} time cap of {}s", lobby.code, expiry.as_str(), cap.as_secs());
+    lobby.lifetime.match_started_at = None;
+    lobby.push_event(SyncEvent::TimeCapReached {
+        scope: expiry.as_str(),
+        cap_secs: cap.as_secs(),
+        closing: expiry == Expiry::Lobby,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::lobby::MatchPhase;
+
+    #[test]
+    fn test_caps_resolve_and_expire() {
+        let config = Config { max_match_secs: Some(600), max_lobby_lifetime_secs: Some(3600), ..Default::default() };
+        let settings = LobbySettings { max_match_secs: Some(120), ..Default::default() };
+        let caps = LifetimeCaps::resolve(&config, &settings);
+        assert_eq!(caps, LifetimeCaps { max_match: Some(Duration::from_secs(120)), max_lobby: Some(Duration::from_secs(3600)) });
+
+        let start = SystemTime::UNIX_EPOCH;
+        let mut lobby = Lobby::new("TEST".to_string(), 4, "world".to_string());
+        lobby.lifetime = Lifetime::new(start);
+        let at = |secs| start + Duration::from_secs(secs);
+
+        // Time spent waiting doesn't count toward the match cap
+        assert_eq!(check(&mut lobby, &caps, at(500)), None);
+        lobby.phase = MatchPhase::InProgress;
+        assert_eq!(check(&mut lobby, &caps, at(1000)), None);
+        assert_eq!(check(&mut lobby, &caps, at(1120)), Some((Expiry::Match, Duration::from_secs(120))));
+
+        expire(&mut lobby, Expiry::Match, Duration::from_secs(120));
+        assert!(matches!(lobby.pending_events.last(), Some(SyncEvent::TimeCapReached { scope: "match", closing: false, .. })));
+        assert_eq!(check(&mut lobby, &caps, at(1200)), None);
+
+        assert_eq!(check(&mut lobby, &caps, at(3600)), Some((Expiry::Lobby, Duration::from_secs(3600))));
+    }
+}
@@ -1,7 +1,16 @@
-use crate::state::lobby::{Lobby, LobbyCode, Player};
-use crate::utils::weapondb::WeaponDb;
+use crate::state::lobby::{ChangeMask, Lobby, GameMode, LobbyCode, LobbySummary, MatchPhase, MatchStanding, MatchStats, Player};
+use crate::state::global_stats::GlobalStats;
+use crate::state::server_state::ServerState;
+use crate::domain::{bots, latency, rotation, teams, validation, weapon_bans};
+use crate::domain::rating::{self, Placement};
+use crate::domain::pickups::PickupSet;
+use crate::utils::weapondb::WeaponLookup;
+use crate::utils::buffers::SyncEvent;
+use crate::utils::scenes;
+use std::collections::HashMap;
 use std::net::SocketAddr;
-use std::time::SystemTime;
+use std::time::{Duration, SystemTime};
+use crate::utils::clock;
 
 /// Create a new lobby
 pub fn create_lobby(
@@ -17,15 +26,34 @@ pub fn create_lobby(
     Ok(())
 }
 
-/// Add a player to a lobby
+/// Add a player to a lobby (reserved slots stay free)
 pub fn add_player(
     lobby: &mut Lobby,
     player_id: u32,
     name: String,
     default_weapon_id: u32,
-    weapon_data: &WeaponDb,
+    weapon_data: &impl WeaponLookup,
 ) -> Result<(), &'static str> {
-    if lobby.players.len() >= lobby.max_players as usize {
+    add_player_as(lobby, player_id, name, default_weapon_id, weapon_data, false, None)
+}
+
+/// Add a player to a lobby; VIPs may also take the reserved slots
+pub fn add_player_as(
+    lobby: &mut Lobby,
+    player_id: u32,
+    name: String,
+    default_weapon_id: u32,
+    weapon_data: &impl WeaponLookup,
+    vip: bool,
+    team: Option<u8>,
+) -> Result<(), &'static str> {
+    let capacity = if vip {
+        lobby.max_players
+    } else {
+        lobby.max_players.saturating_sub(lobby.settings.reserved_slots)
+    };
+    // Humans may bump a fill bot out of a full lobby
+    if lobby.players.len() >= capacity as usize && (bots::is_bot(player_id) || bots::make_room(lobby).is_none()) {
         return Err("Lobby is full");
     }
 
@@ -33,6 +61,12 @@ pub fn add_player(
         return Err("Player already exists");
     }
 
+    // Joiners never start with a banned weapon
+    let default_weapon_id = if weapon_bans::is_banned(&lobby.settings, default_weapon_id) {
+        weapon_bans::fallback_weapon(lobby, weapon_data).ok_or("No weapons available")?
+    } else {
+        default_weapon_id
+    };
     let weapon = weapon_data
         .get(default_weapon_id)
         .ok_or("Invalid default weapon")?;
@@ -42,9 +76,9 @@ pub fn add_player(
         name: name.clone(),
         position: (0.0, 1.0, 0.0),
         rotation: (0.0, 0.0, 0.0),
-        last_update: SystemTime::now(),
-        current_health: 100,
-        max_health: 100,
+        last_update: clock::now(),
+        current_health: lobby.settings.max_health,
+        max_health: lobby.settings.max_health,
         current_weapon_id: default_weapon_id,
         current_ammo: weapon.ammo,
         max_ammo: weapon.ammo,
@@ -58,18 +92,343 @@ pub fn add_player(
         warned_at: None,
         is_dead: false,
         respawn_time: None,
+        vertical_velocity: 0.0,
+        fall_speed: 0.0,
+        last_position_time: None,
+        last_whisper_time: SystemTime::UNIX_EPOCH,
+        changes: ChangeMask::empty(),
+        trigger_held: false,
+        fire_target: None,
+        burst_remaining: 0,
+        overheal_decay: 0.0,
+        match_stats: MatchStats::default(),
+        rtt_ms: None,
+        ready: false,
+        spawn_protection_until: None,
+        anticheat_strikes: 0,
+        pending_loadout: None,
+        quaternion_rotation: false,
+        message_keys: false,
+        last_activity: clock::now(),
+        afk_warned: false,
+        spectating: false,
+        command_last_used: Default::default(),
+        equip_end_time: None,
+        active_loadout: None,
+        preferences: Default::default(),
+        armor: 0,
+        region: None,
+        carried_weapon: None,
+        party_id: None,
+        heat: 0.0,
+        overheated_until: None,
+        client_id: None,
     };
 
     lobby.players.insert(player_id, player);
-    lobby.mark_dirty(player_id);
+    lobby.mark_dirty(player_id); // Full initial sync
+    teams::assign(lobby, player_id, team);
+
+    // First player in becomes the owner
+    if lobby.owner_id.is_none() {
+        lobby.owner_id = Some(player_id);
+    }
+    Ok(())
+}
+
+/// Most players that can join together as one party
+pub const MAX_PARTY_SIZE: usize = 8;
+
+/// Longest accepted party id
+pub const MAX_PARTY_ID_LENGTH: usize = 64;
+
+/// Add a whole party or nobody: room for every member is checked before anyone joins
+/// A VIP party may also take the reserved slots
+pub fn add_party(
+    lobby: &mut Lobby,
+    party_id: &str,
+    members: &[(u32, String)],
+    default_weapon_id: u32,
+    weapon_data: &impl WeaponLookup,
+    vip: bool,
+) -> Result<(), &'static str> {
+    if party_id.is_empty() || party_id.len() > MAX_PARTY_ID_LENGTH {
+        return Err("Invalid party id");
+    }
+    if members.is_empty() {
+        return Err("Party is empty");
+    }
+    if members.len() > MAX_PARTY_SIZE {
+        return Err("Party is too large");
+    }
+    let capacity = if vip { lobby.max_players } else { lobby.max_players.saturating_sub(lobby.settings.reserved_slots) } as usize;
+    // Fill bots give up their slots to humans
+    let bots = if lobby.settings.fill_with_bots { lobby.players.keys().filter(|id| bots::is_bot(**id)).count() } else { 0 };
+    if lobby.players.len().saturating_sub(bots) + members.len() > capacity {
+        return Err("Lobby is full");
+    }
+    if members.iter().any(|(id, _)| lobby.players.contains_key(id)) {
+        return Err("Player already exists");
+    }
+
+    for (index, (player_id, name)) in members.iter().enumerate() {
+        if let Err(e) = add_player_as(lobby, *player_id, name.clone(), default_weapon_id, weapon_data, vip, None) {
+            for (joined, _) in &members[..index] {
+                remove_player(lobby, *joined);
+            }
+            return Err(e);
+        }
+        if let Some(player) = lobby.players.get_mut(player_id) {
+            player.party_id = Some(party_id.to_string());
+        }
+    }
     Ok(())
 }
 
 /// Remove a player from a lobby
-pub fn remove_player(lobby: &mut Lobby, player_id: u32) {
-    lobby.players.remove(&player_id);
+/// Returns the removed player so their session can be recorded
+pub fn remove_player(lobby: &mut Lobby, player_id: u32) -> Option<Player> {
+    let player = lobby.players.remove(&player_id);
+    if player.is_some() {
+        validation::note_departure(lobby, player_id, clock::now());
+    }
     lobby.client_addresses.remove(&player_id);
-    lobby.last_sync_state.remove(&player_id);
+    lobby.position_history.forget(player_id);
+    lobby.emotes.forget(player_id);
+    lobby.chunks.forget(player_id);
+    lobby.rebinds.forget(player_id);
+    lobby.teams.forget(player_id);
+    lobby.connectivity.forget(player_id);
+    lobby.damage_attribution.forget(player_id);
+    crate::domain::ramp_up::reset_player(lobby, player_id);
+    crate::domain::projectiles::remove_owner(lobby, player_id);
+    crate::domain::zone_control::forget(lobby, player_id);
+    lobby.clock_stats.remove(&player_id);
+
+    // Hand ownership to the longest-standing remaining player
+    if lobby.owner_id == Some(player_id) {
+        lobby.owner_id = lobby.players.keys()
+            .filter(|id| **id != 999 && !bots::is_bot(**id)) // Exclude bots
+            .min()
+            .copied();
+    }
+
+    player
+}
+
+/// Remove a player leaving the server (leave, timeout, kick); lobby moves use `move_out`
+/// Records the session and keeps the player index in sync
+/// Only a live match's session is recorded here; end_match already recorded the last one
+pub fn leave_lobby(lobby: &mut Lobby, player_id: u32, server_state: Option<&ServerState>) -> Option<Player> {
+    let addr = lobby.client_addresses.get(&player_id).copied();
+    let in_match = lobby.is_match_live();
+    let player = remove_player(lobby, player_id);
+    if let Some(state) = server_state {
+        if let Some(addr) = addr {
+            state.bandwidth.forget(addr);
+            state.ping_echoes.forget(addr);
+        }
+        if let Some(player) = player.as_ref().filter(|p| in_match && !bots::is_bot(p.id)) {
+            state.global_stats.record_player_session(player);
+        }
+        state.unregister_player(player_id);
+    }
+    player
+}
+
+/// Take a player out to seat them in another lobby (merge, split)
+/// The session carries on there, so nothing is recorded and the player index is left to the caller
+pub fn move_out(lobby: &mut Lobby, player_id: u32) -> Option<(Player, Option<SocketAddr>)> {
+    let addr = lobby.client_addresses.get(&player_id).copied();
+    let player = remove_player(lobby, player_id)?;
+    lobby.transfers_out.push(player_id);
+    Some((player, addr))
+}
+
+/// Record the region a player's address was located in; an unlocated address keeps the last one
+pub fn locate_player(lobby: &mut Lobby, player_id: u32, region: Option<String>) {
+    if let (Some(player), Some(region)) = (lobby.players.get_mut(&player_id), region) {
+        player.region = Some(region);
+    }
+}
+
+/// Region most of the lobby's located players come from; ties go to the alphabetically first
+pub fn dominant_region(lobby: &Lobby) -> Option<String> {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for region in lobby.players.values().filter_map(|p| p.region.as_deref()) {
+        *counts.entry(region).or_default() += 1;
+    }
+    counts.into_iter()
+        .max_by(|(a, count_a), (b, count_b)| count_a.cmp(count_b).then_with(|| b.cmp(a)))
+        .map(|(region, _)| region.to_string())
+}
+
+/// Capture a listing snapshot of the lobby
+pub fn summarize(lobby: &Lobby) -> LobbySummary {
+    LobbySummary {
+        code: lobby.code.clone(),
+        players: lobby.players.values().map(|p| (p.id, p.name.clone())).collect(),
+        max_players: lobby.max_players,
+        scene: lobby.scene.clone(),
+        ranked: lobby.settings.ranked,
+        max_health: lobby.settings.max_health,
+        weapons: lobby.settings.weapons.clone(),
+        region: lobby.settings.region.clone(),
+        player_region: dominant_region(lobby),
+        average_latency_ms: latency::average_latency(lobby),
+        motd: lobby.settings.motd.clone(),
+        phase: lobby.phase,
+        ready_count: ready_counts(lobby).0,
+        match_id: lobby.timeline.as_ref().map(|t| t.match_id),
+        private: lobby.settings.private,
+        reserved_slots: lobby.settings.reserved_slots,
+        game_mode: lobby.settings.game_mode,
+        max_latency_ms: lobby.settings.max_latency_ms,
+        fill_with_bots: lobby.settings.fill_with_bots,
+        target_players: lobby.settings.target_players,
+        team_count: lobby.settings.team_count,
+    }
+}
+
+/// Mark a player ready (or not) for the next match
+pub fn set_ready(lobby: &mut Lobby, player_id: u32, ready: bool) -> Result<(), &'static str> {
+    let player = lobby.players.get_mut(&player_id).ok_or("Player not found")?;
+    if player.ready != ready {
+        player.ready = ready;
+        lobby.push_event(SyncEvent::ReadyChanged { player_id, ready });
+    }
+    Ok(())
+}
+
+/// Ready and total player counts (excluding bots)
+pub fn ready_counts(lobby: &Lobby) -> (usize, usize) {
+    let humans = lobby.players.values().filter(|p| p.id != 999 && !bots::is_bot(p.id));
+    let (ready, total) = humans.fold((0, 0), |(ready, total), p| (ready + p.ready as usize, total + 1));
+    (ready, total)
+}
+
+/// Advance the ready-check state machine by `dt` seconds
+/// Waiting -> Countdown once `quorum` (fraction of players) are ready, Countdown -> InProgress when it runs out
+pub fn update_match_phase(lobby: &mut Lobby, dt: f32, quorum: f32, countdown_secs: u32) {
+    let (ready, total) = ready_counts(lobby);
+    let needed = ((total as f32 * quorum.clamp(0.0, 1.0)).ceil() as usize).max(1);
+    let quorum_met = total > 0 && ready >= needed;
+
+    match lobby.phase {
+        MatchPhase::Waiting if quorum_met => {
+            if countdown_secs == 0 {
+                start_match(lobby);
+            } else {
+                lobby.phase = MatchPhase::Countdown;
+                lobby.countdown_remaining = countdown_secs as f32;
+                lobby.push_event(SyncEvent::CountdownTick { seconds_left: countdown_secs });
+            }
+        }
+        MatchPhase::Countdown if !quorum_met => {
+            lobby.phase = MatchPhase::Waiting;
+            lobby.countdown_remaining = 0.0;
+            lobby.push_event(SyncEvent::CountdownCancelled);
+        }
+        MatchPhase::Countdown => {
+            let before = lobby.countdown_remaining.ceil() as u32;
+            lobby.countdown_remaining -= dt;
+            if lobby.countdown_remaining <= 0.0 {
+                start_match(lobby);
+            } else {
+                // One broadcast per whole second
+                let seconds_left = lobby.countdown_remaining.ceil() as u32;
+                if seconds_left < before {
+                    lobby.push_event(SyncEvent::CountdownTick { seconds_left });
+                }
+            }
+        }
+        // Everyone left - the next group readies up again
+        MatchPhase::InProgress if total == 0 => lobby.phase = MatchPhase::Waiting,
+        _ => {}
+    }
+}
+
+fn start_match(lobby: &mut Lobby) {
+    lobby.phase = MatchPhase::InProgress;
+    lobby.zone = Default::default();
+    lobby.duel = Default::default();
+    lobby.countdown_remaining = 0.0;
+    lobby.push_event(SyncEvent::MatchStarted);
+}
+
+/// Switch the lobby to another scene and tell clients to load it
+pub fn change_scene(lobby: &mut Lobby, scene: String) {
+    lobby.scene_data = scenes::scene_data(&scene);
+    lobby.pickups = PickupSet::for_scene(&lobby.scene_data);
+    lobby.scene = scene.clone();
+    lobby.chunks.reset();
+    lobby.push_event(SyncEvent::SceneChanged { scene });
+}
+
+/// Longest MOTD/rules text a lobby may carry, in characters
+pub const MAX_MOTD_LEN: usize = 1000;
+
+/// Validate a MOTD, returning the trimmed text
+pub fn validate_motd(motd: &str) -> Result<String, &'static str> {
+    let motd = motd.trim();
+    if motd.chars().count() > MAX_MOTD_LEN {
+        return Err("MOTD too long");
+    }
+    Ok(motd.to_string())
+}
+
+/// Replace the lobby MOTD and broadcast it
+/// Only the owner may change it when `player_id` is given
+pub fn set_motd(lobby: &mut Lobby, player_id: Option<u32>, motd: &str) -> Result<(), &'static str> {
+    if player_id.is_some_and(|pid| !lobby.is_owner(pid)) {
+        return Err("Only the lobby owner can change the MOTD");
+    }
+    let motd = validate_motd(motd)?;
+    lobby.settings.motd = motd.clone();
+    lobby.push_event(SyncEvent::MotdChanged { motd, changed_by: player_id });
+    Ok(())
+}
+
+/// Pause the lobby - gameplay commands and timers freeze until resumed
+pub fn pause(lobby: &mut Lobby, now: SystemTime) -> Result<(), &'static str> {
+    if lobby.is_paused() {
+        return Err("Lobby already paused");
+    }
+    lobby.paused_at = Some(now);
+    Ok(())
+}
+
+/// Resume a paused lobby
+/// Pending timers are pushed back by the time spent paused so they resume where they froze
+/// Returns how long the lobby was paused
+pub fn resume(lobby: &mut Lobby, now: SystemTime) -> Result<Duration, &'static str> {
+    let paused_at = lobby.paused_at.take().ok_or("Lobby not paused")?;
+    let paused_for = now.duration_since(paused_at).unwrap_or_default();
+
+    for player in lobby.players.values_mut() {
+        if let Some(end) = player.reload_end_time.as_mut() {
+            *end += paused_for;
+        }
+        if let Some(end) = player.equip_end_time.as_mut() {
+            *end += paused_for;
+        }
+        if let Some(respawn) = player.respawn_time.as_mut() {
+            *respawn += paused_for;
+        }
+        if let Some(protection) = player.spawn_protection_until.as_mut() {
+            *protection += paused_for;
+        }
+    }
+    for item in &mut lobby.loot.items {
+        item.expires_at += paused_for;
+    }
+    for pickup in &mut lobby.pickups.items {
+        if let Some(at) = pickup.available_at.as_mut() {
+            *at += paused_for;
+        }
+    }
+
+    Ok(paused_for)
 }
 
 /// Update player position and rotation
@@ -84,11 +443,24 @@ pub fn update_position(
         .get_mut(&player_id)
         .ok_or("Player not found")?;
 
+    let now = clock::now();
+
+    // Derive vertical velocity from successive samples
+    if let Some(last_time) = player.last_position_time {
+        if let Ok(elapsed) = now.duration_since(last_time) {
+            let dt = elapsed.as_secs_f32();
+            if dt > 0.0 {
+                player.vertical_velocity = (position.1 - player.position.1) / dt;
+            }
+        }
+    }
+
     player.position = position;
-    player.rotation = rotation;
-    player.last_update = SystemTime::now();
+    player.rotation = rotation::normalize_euler(rotation);
+    player.last_update = now;
+    player.last_position_time = Some(now);
 
-    lobby.mark_dirty(player_id);
+    lobby.mark_changed(player_id, ChangeMask::POSITION);
     Ok(())
 }
 
@@ -105,20 +477,31 @@ pub fn set_player_address(
     Ok(())
 }
 
+/// Whether a UDP connect resumes a session the client lost (e.g. it crashed) rather than arriving fresh:
+/// the player is still connected from the same IP and presents the client id it connected with
+pub fn is_session_resume(lobby: &Lobby, player_id: u32, client_id: Option<&str>, addr: SocketAddr) -> bool {
+    let same_client = client_id.is_some()
+        && lobby.players.get(&player_id).is_some_and(|p| p.client_id.as_deref() == client_id);
+    let same_ip = lobby.client_addresses.get(&player_id).is_some_and(|known| known.ip() == addr.ip());
+    same_client && same_ip
+}
+
 /// Clean up inactive players with warning system
+/// Removed players go through `leave_lobby`
 /// Returns tuple of (removed_player_ids, warned_player_ids)
 pub fn cleanup_inactive(
     lobby: &mut Lobby,
     timeout_secs: u64,
     warning_fraction: f64,
+    server_state: Option<&ServerState>,
 ) -> (Vec<u32>, Vec<u32>) {
-    let now = SystemTime::now();
+    let now = clock::now();
     let warning_threshold = (timeout_secs as f64 * warning_fraction) as u64;
     let mut inactive_players = Vec::new();
     let mut warned_players = Vec::new();
 
     for (player_id, player) in &lobby.players {
-        if *player_id == 999 {
+        if *player_id == 999 || bots::is_bot(*player_id) {
             continue;
         }
 
@@ -134,7 +517,7 @@ pub fn cleanup_inactive(
     }
 
     for player_id in &inactive_players {
-        remove_player(lobby, *player_id);
+        leave_lobby(lobby, *player_id, server_state);
     }
 
     for player_id in &warned_players {
@@ -146,9 +529,77 @@ pub fn cleanup_inactive(
     (inactive_players, warned_players)
 }
 
+/// End the current match
+/// Ranks players by score, applies rating changes when the lobby is ranked,
+/// rolls each session into global stats, and resets per-match counters
+/// so the next match starts clean
+pub fn end_match(lobby: &mut Lobby, global_stats: Option<&GlobalStats>) -> Vec<MatchStanding> {
+    let mut standings: Vec<MatchStanding> = lobby.players.values()
+        .filter(|p| p.id != 999) // Exclude dummy bot
+        .map(|p| MatchStanding {
+            player_id: p.id,
+            name: p.name.clone(),
+            score: p.score,
+            kills: p.kills,
+            deaths: p.deaths,
+            stats: p.match_stats,
+            accuracy: p.match_stats.accuracy(),
+            rating_change: None,
+            round_wins: lobby.duel.wins.get(&p.id).copied().unwrap_or(0),
+        })
+        .collect();
+    standings.sort_by_key(|s| std::cmp::Reverse(s.score));
+    match lobby.settings.game_mode {
+        // Zone points decide the match; score breaks ties
+        GameMode::KingOfTheHill => standings.sort_by_key(|s| std::cmp::Reverse(lobby.zone.points.get(&s.player_id).copied().unwrap_or(0))),
+        GameMode::Duel => standings.sort_by_key(|s| std::cmp::Reverse(s.round_wins)),
+        GameMode::Deathmatch => {}
+    }
+
+    // Bots play the match but never get ratings or global stats
+    if let (true, Some(stats)) = (lobby.settings.ranked, global_stats) {
+        let placements: Vec<Placement> = standings.iter()
+            .filter(|s| !bots::is_bot(s.player_id))
+            .map(|s| Placement {
+                player_id: s.player_id,
+                rating: stats.get_rating(s.player_id),
+                score: s.score,
+            })
+            .collect();
+
+        let humans = standings.iter_mut().filter(|s| !bots::is_bot(s.player_id));
+        for (standing, (_, delta)) in humans.zip(rating::compute_rating_changes(&placements)) {
+            stats.record_ranked_result(standing.player_id, &standing.name, delta);
+            standing.rating_change = Some(delta);
+        }
+    }
+
+    for player in lobby.players.values_mut() {
+        if let Some(stats) = global_stats {
+            if player.id != 999 && !bots::is_bot(player.id) {
+                stats.record_player_session(player);
+            }
+        }
+        player.score = 0;
+        player.kills = 0;
+        player.deaths = 0;
+        player.killstreak = 0;
+        player.match_stats = MatchStats::default();
+        player.ready = false;
+    }
+
+    // Back to the ready check for the next match
+    lobby.phase = MatchPhase::Waiting;
+    lobby.duel = Default::default();
+    lobby.damage_attribution.clear();
+
+    standings
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::state::lobby::LobbySettings;
     use crate::utils::weapondb::WeaponDb;
 
     #[test]
@@ -162,6 +613,23 @@ mod tests {
         assert!(lobby.players.contains_key(&1));
     }
 
+    #[test]
+    fn test_session_resume_needs_same_client_and_ip() {
+        let mut lobby = Lobby::new("TEST".to_string(), 4, "world".to_string());
+        let weapons = WeaponDb::load();
+        add_player(&mut lobby, 1, "Player1".to_string(), 1, &weapons).unwrap();
+        let addr: SocketAddr = "10.0.0.5:4000".parse().unwrap();
+        let restarted: SocketAddr = "10.0.0.5:4123".parse().unwrap();
+        assert!(!is_session_resume(&lobby, 1, Some("abc"), restarted)); // Never connected
+
+        set_player_address(&mut lobby, 1, addr).unwrap();
+        lobby.players.get_mut(&1).unwrap().client_id = Some("abc".to_string());
+        assert!(is_session_resume(&lobby, 1, Some("abc"), restarted));
+        assert!(!is_session_resume(&lobby, 1, Some("xyz"), restarted));
+        assert!(!is_session_resume(&lobby, 1, None, restarted));
+        assert!(!is_session_resume(&lobby, 1, Some("abc"), "10.0.0.9:4000".parse().unwrap()));
+    }
+
     #[test]
     fn test_add_player_full_lobby() {
         let mut lobby = Lobby::new("TEST".to_string(), 2, "world".to_string());
@@ -174,6 +642,41 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_reserved_slots_only_for_vips() {
+        let settings = LobbySettings { reserved_slots: 1, ..Default::default() };
+        let mut lobby = Lobby::with_settings("TEST".to_string(), 3, "world".to_string(), settings);
+        let weapons = WeaponDb::load();
+
+        add_player(&mut lobby, 1, "Player1".to_string(), 1, &weapons).unwrap();
+        add_player(&mut lobby, 2, "Player2".to_string(), 1, &weapons).unwrap();
+        assert_eq!(add_player(&mut lobby, 3, "Player3".to_string(), 1, &weapons), Err("Lobby is full"));
+
+        add_player_as(&mut lobby, 4, "Vip".to_string(), 1, &weapons, true, None).unwrap();
+        assert_eq!(add_player_as(&mut lobby, 5, "Vip2".to_string(), 1, &weapons, true, None), Err("Lobby is full"));
+    }
+
+    #[test]
+    fn test_party_joins_together_or_not_at_all() {
+        let mut lobby = Lobby::new("TEST".to_string(), 4, "world".to_string());
+        let weapons = WeaponDb::load();
+        add_player(&mut lobby, 1, "Solo".to_string(), 1, &weapons).unwrap();
+
+        let party = |ids: &[u32]| ids.iter().map(|id| (*id, format!("Member{}", id))).collect::<Vec<_>>();
+        assert_eq!(add_party(&mut lobby, "squad", &party(&[2, 3, 4, 5]), 1, &weapons, false), Err("Lobby is full"));
+        assert_eq!(lobby.players.len(), 1); // Nobody from the rejected party got in
+
+        add_party(&mut lobby, "squad", &party(&[2, 3]), 1, &weapons, false).unwrap();
+        assert_eq!(lobby.players[&3].party_id.as_deref(), Some("squad"));
+        assert_eq!(lobby.players[&1].party_id, None);
+        assert_eq!(add_party(&mut lobby, "", &party(&[4]), 1, &weapons, false), Err("Invalid party id"));
+
+        // Reserved slots are held back from ordinary parties but open to VIP ones
+        lobby.settings.reserved_slots = 1;
+        assert_eq!(add_party(&mut lobby, "late", &party(&[4]), 1, &weapons, false), Err("Lobby is full"));
+        add_party(&mut lobby, "late", &party(&[4]), 1, &weapons, true).unwrap();
+    }
+
     #[test]
     fn test_remove_player() {
         let mut lobby = Lobby::new("TEST".to_string(), 4, "world".to_string());
@@ -201,6 +704,25 @@ mod tests {
         assert!(lobby.dirty_players.contains(&1));
     }
 
+    #[test]
+    fn test_update_position_tracks_vertical_velocity() {
+        let mut lobby = Lobby::new("TEST".to_string(), 4, "world".to_string());
+        let weapons = WeaponDb::load();
+
+        add_player(&mut lobby, 1, "Player1".to_string(), 1, &weapons).unwrap();
+
+        // First sample only establishes a baseline
+        update_position(&mut lobby, 1, (0.0, 10.0, 0.0), (0.0, 0.0, 0.0)).unwrap();
+        assert_eq!(lobby.players.get(&1).unwrap().vertical_velocity, 0.0);
+
+        lobby.players.get_mut(&1).unwrap().last_position_time =
+            Some(SystemTime::now() - std::time::Duration::from_millis(500));
+        update_position(&mut lobby, 1, (0.0, 5.0, 0.0), (0.0, 0.0, 0.0)).unwrap();
+
+        let velocity = lobby.players.get(&1).unwrap().vertical_velocity;
+        assert!(velocity < -9.0 && velocity > -11.0, "velocity was {}", velocity);
+    }
+
     #[test]
     fn test_cleanup_inactive() {
         let mut lobby = Lobby::new("TEST".to_string(), 4, "world".to_string());
@@ -213,9 +735,220 @@ mod tests {
             player.last_update = SystemTime::now() - std::time::Duration::from_secs(20);
         }
 
-        let (removed, _) = cleanup_inactive(&mut lobby, 15, 0.5);
+        let (removed, _) = cleanup_inactive(&mut lobby, 15, 0.5, None);
         assert_eq!(removed.len(), 1);
         assert_eq!(removed[0], 1);
         assert_eq!(lobby.players.len(), 0);
     }
+
+    #[test]
+    fn test_leave_lobby_unregisters_player() {
+        let mut lobby = Lobby::new("TEST".to_string(), 4, "world".to_string());
+        let weapons = WeaponDb::load();
+        let state = ServerState::new();
+
+        add_player(&mut lobby, 1, "Player1".to_string(), 1, &weapons).unwrap();
+        state.register_player_lobby(1, "TEST");
+        lobby.phase = MatchPhase::InProgress;
+        lobby.players.get_mut(&1).unwrap().match_stats.shots_fired = 3;
+
+        assert!(leave_lobby(&mut lobby, 1, Some(&state)).is_some());
+        assert!(lobby.players.is_empty());
+        assert!(state.player_lobby_index.get(&1).is_none());
+        assert_eq!(state.global_stats.get_stats(1).unwrap().total_shots_fired, 3);
+        assert!(leave_lobby(&mut lobby, 1, Some(&state)).is_none());
+    }
+
+    #[test]
+    fn test_end_ranked_match() {
+        let settings = crate::state::lobby::LobbySettings { ranked: true, ..Default::default() };
+        let mut lobby = Lobby::with_settings("TEST".to_string(), 4, "world".to_string(), settings);
+        let weapons = WeaponDb::load();
+        let stats = GlobalStats::new();
+
+        add_player(&mut lobby, 1, "Player1".to_string(), 1, &weapons).unwrap();
+        add_player(&mut lobby, 2, "Player2".to_string(), 1, &weapons).unwrap();
+        lobby.players.get_mut(&2).unwrap().score = 300;
+
+        let standings = end_match(&mut lobby, Some(&stats));
+        assert_eq!(standings[0].player_id, 2);
+        assert!(standings[0].rating_change.unwrap() > 0.0);
+        assert!(standings[1].rating_change.unwrap() < 0.0);
+        assert!(stats.get_rating(2) > stats.get_rating(1));
+        assert_eq!(lobby.players.get(&2).unwrap().score, 0);
+    }
+
+    #[test]
+    fn test_end_casual_match_keeps_ratings() {
+        let mut lobby = Lobby::new("TEST".to_string(), 4, "world".to_string());
+        let weapons = WeaponDb::load();
+        let stats = GlobalStats::new();
+
+        add_player(&mut lobby, 1, "Player1".to_string(), 1, &weapons).unwrap();
+        add_player(&mut lobby, 2, "Player2".to_string(), 1, &weapons).unwrap();
+        lobby.players.get_mut(&1).unwrap().score = 100;
+
+        let standings = end_match(&mut lobby, Some(&stats));
+        assert_eq!(standings.len(), 2);
+        assert!(standings.iter().all(|s| s.rating_change.is_none()));
+
+        // The session is recorded, but ratings are untouched
+        let player_stats = stats.get_stats(1).unwrap();
+        assert_eq!(player_stats.games_played, 1);
+        assert_eq!(player_stats.ranked_games, 0);
+        assert_eq!(player_stats.rating, rating::DEFAULT_RATING);
+        assert!(stats.get_stats(2).is_none()); // No activity
+    }
+
+    #[test]
+    fn test_end_match_then_leave_records_once() {
+        let mut lobby = Lobby::new("TEST".to_string(), 4, "world".to_string());
+        let weapons = WeaponDb::load();
+        let state = ServerState::new();
+
+        add_player(&mut lobby, 1, "Player1".to_string(), 1, &weapons).unwrap();
+        lobby.phase = MatchPhase::InProgress;
+        lobby.players.get_mut(&1).unwrap().kills = 3;
+        end_match(&mut lobby, Some(&state.global_stats));
+
+        // Falling between matches is not another game
+        lobby.players.get_mut(&1).unwrap().match_stats.damage_taken = 10;
+        leave_lobby(&mut lobby, 1, Some(&state));
+        let player_stats = state.global_stats.get_stats(1).unwrap();
+        assert_eq!(player_stats.games_played, 1);
+        assert_eq!(player_stats.total_kills, 3);
+        assert_eq!(player_stats.total_damage_taken, 0);
+    }
+
+    #[test]
+    fn test_owner_assignment_and_handover() {
+        let mut lobby = Lobby::new("TEST".to_string(), 4, "world".to_string());
+        let weapons = WeaponDb::load();
+
+        add_player(&mut lobby, 5, "First".to_string(), 1, &weapons).unwrap();
+        add_player(&mut lobby, 7, "Second".to_string(), 1, &weapons).unwrap();
+        add_player(&mut lobby, 6, "Third".to_string(), 1, &weapons).unwrap();
+        assert!(lobby.is_owner(5));
+
+        remove_player(&mut lobby, 5);
+        assert_eq!(lobby.owner_id, Some(6));
+
+        remove_player(&mut lobby, 6);
+        remove_player(&mut lobby, 7);
+        assert_eq!(lobby.owner_id, None);
+    }
+
+    #[test]
+    fn test_ready_check_countdown() {
+        let mut lobby = Lobby::new("TEST".to_string(), 4, "world".to_string());
+        let weapons = WeaponDb::load();
+        add_player(&mut lobby, 1, "Player1".to_string(), 1, &weapons).unwrap();
+        add_player(&mut lobby, 2, "Player2".to_string(), 1, &weapons).unwrap();
+
+        set_ready(&mut lobby, 1, true).unwrap();
+        update_match_phase(&mut lobby, 0.5, 1.0, 3);
+        assert_eq!(lobby.phase, MatchPhase::Waiting);
+
+        // Half the lobby is enough with a 0.5 quorum
+        update_match_phase(&mut lobby, 0.5, 0.5, 3);
+        assert_eq!(lobby.phase, MatchPhase::Countdown);
+        lobby.pending_events.clear();
+
+        // One tick per whole second
+        update_match_phase(&mut lobby, 0.5, 0.5, 3);
+        assert!(lobby.pending_events.is_empty());
+        update_match_phase(&mut lobby, 0.6, 0.5, 3);
+        assert!(matches!(lobby.pending_events[..], [SyncEvent::CountdownTick { seconds_left: 2 }]));
+
+        update_match_phase(&mut lobby, 2.0, 0.5, 3);
+        assert!(lobby.is_match_live());
+        assert!(matches!(lobby.pending_events.last(), Some(SyncEvent::MatchStarted)));
+
+        end_match(&mut lobby, None);
+        assert_eq!(lobby.phase, MatchPhase::Waiting);
+        assert_eq!(ready_counts(&lobby), (0, 2));
+    }
+
+    #[test]
+    fn test_countdown_cancelled_when_unready() {
+        let mut lobby = Lobby::new("TEST".to_string(), 4, "world".to_string());
+        let weapons = WeaponDb::load();
+        add_player(&mut lobby, 1, "Player1".to_string(), 1, &weapons).unwrap();
+
+        set_ready(&mut lobby, 1, true).unwrap();
+        update_match_phase(&mut lobby, 0.02, 1.0, 5);
+        assert_eq!(lobby.phase, MatchPhase::Countdown);
+
+        set_ready(&mut lobby, 1, false).unwrap();
+        update_match_phase(&mut lobby, 0.02, 1.0, 5);
+        assert_eq!(lobby.phase, MatchPhase::Waiting);
+        assert!(matches!(lobby.pending_events.last(), Some(SyncEvent::CountdownCancelled)));
+    }
+
+    #[test]
+    fn test_set_motd() {
+        let mut lobby = Lobby::new("TEST".to_string(), 4, "world".to_string());
+        let weapons = WeaponDb::load();
+        add_player(&mut lobby, 1, "Owner".to_string(), 1, &weapons).unwrap();
+        add_player(&mut lobby, 2, "Guest".to_string(), 1, &weapons).unwrap();
+
+        assert!(set_motd(&mut lobby, Some(2), "No camping").is_err());
+        assert!(set_motd(&mut lobby, Some(1), &"x".repeat(MAX_MOTD_LEN + 1)).is_err());
+        assert!(lobby.pending_events.is_empty());
+
+        set_motd(&mut lobby, Some(1), "  No camping  ").unwrap();
+        assert_eq!(lobby.settings.motd, "No camping");
+        assert!(matches!(lobby.pending_events.last(), Some(SyncEvent::MotdChanged { changed_by: Some(1), .. })));
+    }
+
+    #[test]
+    fn test_pause_resume_shifts_timers() {
+        let mut lobby = Lobby::new("TEST".to_string(), 4, "world".to_string());
+        let weapons = WeaponDb::load();
+        add_player(&mut lobby, 1, "Player1".to_string(), 1, &weapons).unwrap();
+
+        let start = SystemTime::now();
+        let reload_end = start + Duration::from_secs(1);
+        lobby.players.get_mut(&1).unwrap().reload_end_time = Some(reload_end);
+
+        pause(&mut lobby, start).unwrap();
+        assert!(pause(&mut lobby, start).is_err());
+
+        let paused_for = resume(&mut lobby, start + Duration::from_secs(10)).unwrap();
+        assert_eq!(paused_for, Duration::from_secs(10));
+        assert_eq!(lobby.players.get(&1).unwrap().reload_end_time, Some(reload_end + Duration::from_secs(10)));
+        assert!(!lobby.is_paused());
+        assert!(resume(&mut lobby, start).is_err());
+    }
+
+    #[test]
+    fn test_add_player_uses_lobby_max_health() {
+        let settings = crate::state::lobby::LobbySettings { max_health: 250, ..Default::default() };
+        let mut lobby = Lobby::with_settings("TEST".to_string(), 4, "world".to_string(), settings);
+        let weapons = WeaponDb::load();
+
+        add_player(&mut lobby, 1, "Tank".to_string(), 1, &weapons).unwrap();
+
+        let player = lobby.players.get(&1).unwrap();
+        assert_eq!(player.max_health, 250);
+        assert_eq!(player.current_health, 250);
+    }
+
+    #[test]
+    fn test_dominant_region() {
+        let mut lobby = Lobby::new("TEST".to_string(), 4, "world".to_string());
+        let weapons = WeaponDb::load();
+        for id in 1..=4 {
+            add_player(&mut lobby, id, format!("Player{}", id), 1, &weapons).unwrap();
+        }
+        assert_eq!(dominant_region(&lobby), None);
+
+        locate_player(&mut lobby, 1, Some("us-east".to_string()));
+        locate_player(&mut lobby, 2, Some("europe".to_string()));
+        assert_eq!(dominant_region(&lobby).as_deref(), Some("europe"));
+
+        locate_player(&mut lobby, 3, Some("us-east".to_string()));
+        locate_player(&mut lobby, 3, None);
+        assert_eq!(summarize(&lobby).player_region.as_deref(), Some("us-east"));
+    }
 }
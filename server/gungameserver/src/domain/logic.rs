@@ -1,6 +1,19 @@
-use crate::state::lobby::{Lobby, PlayerSyncState};
-use crate::utils::weapondb::WeaponDb;
-use std::time::SystemTime;
+use crate::state::lobby::{ChangeMask, Lobby, Player, PlayerSyncState};
+use crate::utils::weapondb::{DamageType, FireMode, Overheat, WeaponData, WeaponLookup};
+use crate::utils::buffers::SyncEvent;
+use crate::domain::armor;
+use crate::domain::damage_log::DamageRecord;
+use crate::domain::damage_share::{self, KILL_SCORE};
+use crate::domain::projectiles::{self, ProjectileOutcome};
+use crate::domain::interest;
+use crate::domain::loot::Death;
+use crate::domain::pellets;
+use crate::domain::validation;
+use crate::domain::ramp_up;
+use crate::domain::scripting;
+use crate::domain::weapon_bans;
+use std::time::{Duration, SystemTime};
+use crate::utils::clock;
 
 /// Kill event data for broadcasting
 #[derive(Debug, Clone)]
@@ -14,13 +27,47 @@ pub struct KillEvent {
     pub killer_new_killstreak: u32,
 }
 
+/// Why a player died without a killer
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DeathCause {
+    Fall,
+    OutOfWorld,
+    Suicide,
+}
+
+impl DeathCause {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DeathCause::Fall => "fall",
+            DeathCause::OutOfWorld => "out_of_world",
+            DeathCause::Suicide => "suicide",
+        }
+    }
+}
+
+/// Downward speed (units/sec) above which a player counts as airborne
+const FALLING_SPEED: f32 = 1.0;
+
+/// How far away a shot of loudness 1.0 is heard
+const GUNFIRE_AUDIBLE_RADIUS: f32 = 60.0;
+
+/// Largest damage one hit may deal
+pub const MAX_DAMAGE: u32 = 100;
+
+/// Overheal can raise health up to this multiple of max_health
+const OVERHEAL_CAP: f32 = 1.5;
+
+/// Health above max_health lost per second
+const OVERHEAL_DECAY_PER_SEC: f32 = 5.0;
+
 /// Try to shoot - validates ammo, fire rate, reload state
 /// Returns true if shot was successful
 pub fn try_shoot(
     lobby: &mut Lobby,
-    weapons: &WeaponDb,
+    weapons: &impl WeaponLookup,
     player_id: u32,
 ) -> Result<bool, &'static str> {
+    let blocks_shooting = lobby.settings.spawn_protection_blocks_shooting;
     let player = lobby
         .players
         .get_mut(&player_id)
@@ -31,6 +78,16 @@ pub fn try_shoot(
         return Ok(false);
     }
 
+    // Still drawing the weapon
+    if player.equip_end_time.is_some() {
+        return Ok(false);
+    }
+
+    // Cooling down after overheating
+    if player.overheated_until.is_some() {
+        return Ok(false);
+    }
+
     // Check ammo
     if player.current_ammo == 0 {
         return Ok(false);
@@ -41,7 +98,11 @@ pub fn try_shoot(
         .get(player.current_weapon_id)
         .ok_or("Invalid weapon")?;
 
-    let now = SystemTime::now();
+    let now = clock::now();
+    if blocks_shooting && player.is_spawn_protected(now) {
+        return Ok(false);
+    }
+
     let time_since_last_shot = now
         .duration_since(player.last_shot_time)
         .map_err(|_| "Time error")?;
@@ -53,34 +114,424 @@ pub fn try_shoot(
     // Consume ammo
     player.current_ammo = player.current_ammo.saturating_sub(1);
     player.last_shot_time = now;
+    if let Some(overheat) = weapon.overheat {
+        add_heat(player, &overheat, now);
+    }
+
+    lobby.mark_changed(player_id, ChangeMask::AMMO);
+    Ok(true)
+}
+
+/// Fire one shot and apply weapon damage to the target (if any)
+/// Projectile weapons launch a projectile instead; it deals damage when it lands
+/// Returns true if a shot was fired
+pub fn fire_shot(
+    lobby: &mut Lobby,
+    weapons: &impl WeaponLookup,
+    player_id: u32,
+    target_id: Option<u32>,
+) -> Result<bool, &'static str> {
+    fire(lobby, weapons, player_id, target_id, &[])
+}
+
+/// Fire one shot; `pellet_hits` are the players a multi-pellet shot's pellets hit, if the client reported them
+fn fire(
+    lobby: &mut Lobby,
+    weapons: &impl WeaponLookup,
+    player_id: u32,
+    target_id: Option<u32>,
+    pellet_hits: &[u32],
+) -> Result<bool, &'static str> {
+    if !try_shoot(lobby, weapons, player_id)? {
+        return Ok(false);
+    }
+
+    let weapon = lobby.players.get(&player_id)
+        .and_then(|p| weapons.get(p.current_weapon_id).map(|w| (p.current_weapon_id, w.range, w.projectile_speed)));
+    if let Some(shooter) = lobby.players.get_mut(&player_id) {
+        shooter.match_stats.shots_fired += 1;
+    }
+    if let Some((weapon_id, _, _)) = weapon {
+        lobby.push_event(SyncEvent::PlayerShot { player_id, weapon_id });
+    }
+    push_gunfire_hints(lobby, weapons, player_id);
+
+    match (target_id, weapon) {
+        (Some(target_id), Some((weapon_id, range, Some(speed)))) => {
+            if let Some(projectile) = projectiles::spawn(lobby, player_id, weapon_id, target_id, speed, range) {
+                lobby.push_event(SyncEvent::ProjectileSpawned {
+                    projectile_id: projectile.id,
+                    owner_id: player_id,
+                    weapon_id,
+                    position: projectile.position,
+                    velocity: projectile.velocity,
+                });
+            }
+        }
+        (Some(target_id), Some((weapon_id, _, None))) => {
+            match weapons.get(weapon_id).filter(|w| w.pellets > 1) {
+                Some(weapon) => {
+                    for (target_id, count) in pellets::resolve(lobby, weapon, player_id, target_id, pellet_hits) {
+                        land_hit(lobby, weapons, player_id, target_id, weapon_id, count)?;
+                    }
+                }
+                None => land_hit(lobby, weapons, player_id, target_id, weapon_id, 1)?,
+            }
+        }
+        _ => {}
+    }
 
-    lobby.mark_dirty(player_id);
     Ok(true)
 }
 
-/// Apply damage to a player
-pub fn apply_damage(lobby: &mut Lobby, target_id: u32, damage: u32) -> Result<(), &'static str> {
+/// Tell players within earshot where a shot came from, for directional audio of off-screen fights
+fn push_gunfire_hints(lobby: &mut Lobby, weapons: &impl WeaponLookup, shooter_id: u32) {
+    let Some((position, loudness)) = lobby.players.get(&shooter_id)
+        .and_then(|p| weapons.get(p.current_weapon_id).map(|w| (p.position, w.loudness)))
+    else {
+        return;
+    };
+    for to_id in interest::players_within(lobby, position, GUNFIRE_AUDIBLE_RADIUS * loudness, shooter_id) {
+        lobby.push_event(SyncEvent::GunfireNearby { to_id, position, loudness });
+    }
+}
+
+/// Damage a target with a weapon (`pellets` of them, for multi-pellet weapons),
+/// credit the attacker and resolve a lethal hit
+fn land_hit(
+    lobby: &mut Lobby,
+    weapons: &impl WeaponLookup,
+    attacker_id: u32,
+    target_id: u32,
+    weapon_id: u32,
+    pellets: u32,
+) -> Result<(), &'static str> {
+    let Some(weapon) = weapons.get(weapon_id) else {
+        return Ok(());
+    };
+    let (damage, ramp) = (weapon.damage.saturating_mul(pellets), weapon.ramp_up);
+    let Some(dealt) = hit_target(lobby, attacker_id, target_id, weapon, damage, ramp, pellets) else {
+        return Ok(());
+    };
+    log_damage(lobby, weapon, attacker_id, target_id, dealt);
+
+    if let Some(attacker) = lobby.players.get_mut(&attacker_id) {
+        attacker.match_stats.shots_hit += 1;
+        attacker.match_stats.damage_dealt += dealt;
+    }
+    lobby.damage_attribution.record(attacker_id, target_id, dealt);
+
+    let lethal = lobby.players.get(&target_id)
+        .is_some_and(|t| t.current_health == 0 && !t.is_dead);
+    if lethal {
+        resolve_kill(lobby, weapons, attacker_id, target_id)?;
+    }
+    Ok(())
+}
+
+/// Add a landed hit to the match's damage log; strike the attacker if it looks impossible
+fn log_damage(lobby: &mut Lobby, weapon: &WeaponData, attacker_id: u32, victim_id: u32, amount: u32) {
+    let (Some(attacker), Some(victim)) = (lobby.players.get(&attacker_id), lobby.players.get(&victim_id)) else {
+        return;
+    };
+    let (dx, dy, dz) = (
+        attacker.position.0 - victim.position.0,
+        attacker.position.1 - victim.position.1,
+        attacker.position.2 - victim.position.2,
+    );
+    let health_after = victim.current_health;
+    let ticks_per_sec = 1000 / lobby.tick_interval_ms.max(1);
+    let tick = lobby.current_tick;
+    let Some(timeline) = lobby.timeline.as_mut() else {
+        return; // Only matches in progress are logged
+    };
+    let record = DamageRecord {
+        tick: timeline.match_tick(tick),
+        attacker_id,
+        victim_id,
+        weapon_id: weapon.id,
+        amount,
+        health_after,
+        distance: (dx * dx + dy * dy + dz * dz).sqrt(),
+    };
+    timeline.damage.record(record);
+    if let Some(kind) = timeline.damage.check(&record, weapon, ticks_per_sec) {
+        validation::record_violation(lobby, attacker_id, kind);
+    }
+}
+
+/// Advance projectiles in flight by one tick and apply any hits
+pub fn update_projectiles(lobby: &mut Lobby, weapons: &impl WeaponLookup, dt: f32) {
+    for end in projectiles::step(lobby, dt) {
+        let target_id = match end.outcome {
+            ProjectileOutcome::Hit { target_id } => Some(target_id),
+            ProjectileOutcome::Expired => None,
+        };
+        lobby.push_event(SyncEvent::ProjectileEnded { projectile_id: end.projectile_id, target_id });
+        // A shooter who left has no projectiles left; one who died still lands theirs
+        if let Some(target_id) = target_id {
+            if let Err(e) = land_hit(lobby, weapons, end.owner_id, target_id, end.weapon_id, 1) {
+                log::debug!("Projectile {} hit failed: {}", end.projectile_id, e);
+            }
+        }
+    }
+}
+
+/// Apply one hit, ramped up by the attacker's streak on this target
+/// Returns the health removed; confirms the hit, split per pellet, to the attacker
+fn hit_target(
+    lobby: &mut Lobby,
+    attacker_id: u32,
+    target_id: u32,
+    weapon: &WeaponData,
+    damage: u32,
+    ramp: Option<crate::utils::weapondb::RampUp>,
+    pellets: u32,
+) -> Option<u32> {
+    let (weapon_id, now) = (weapon.id, clock::now());
+    let streak = ramp.map(|r| ramp_up::next_streak(lobby, attacker_id, target_id, weapon_id, &r, now));
+    let multiplier = match (ramp, streak) {
+        (Some(r), Some(hits)) => r.multiplier(hits),
+        _ => 1.0,
+    };
+    let damage = ((damage as f32 * multiplier).round() as u32).min(MAX_DAMAGE);
+    let damage = scripting::modify_damage(lobby, attacker_id, target_id, weapon_id, damage).min(MAX_DAMAGE);
+
+    let armor_before = lobby.players.get(&target_id).map_or(0, |t| t.armor);
+    let dealt = apply_damage(lobby, target_id, damage, weapon.damage_type).ok()?;
+    if let Some(hits) = streak {
+        ramp_up::record_hit(lobby, attacker_id, target_id, weapon_id, hits, now);
+    }
+    let pellets = if pellets > 1 { pellets::split(dealt, pellets) } else { Vec::new() };
+    let target_armor = lobby.players.get(&target_id).map_or(0, |t| t.armor);
+    let armor_absorbed = armor_before - target_armor;
+    lobby.push_event(SyncEvent::HitConfirmed { attacker_id, target_id, damage: dealt, multiplier, pellets, armor_absorbed, target_armor });
+    let health = lobby.players.get(&target_id).map_or(0, |t| t.current_health);
+    lobby.push_event(SyncEvent::PlayerDamaged { player_id: target_id, attacker_id, damage: dealt, health });
+    Some(dealt)
+}
+
+/// Register a lethal hit and raise the kill feed and the victim's kill cam
+fn resolve_kill(
+    lobby: &mut Lobby,
+    weapons: &impl WeaponLookup,
+    killer_id: u32,
+    victim_id: u32,
+) -> Result<(), &'static str> {
+    let kill = register_kill(lobby, weapons, killer_id, victim_id)?;
+    let samples = lobby.position_history.samples(killer_id);
+    lobby.push_event(SyncEvent::KillcamData {
+        victim_id,
+        killer_id,
+        weapon_id: kill.weapon_id,
+        samples,
+    });
+    lobby.push_event(SyncEvent::PlayerKilled {
+        killer_id: kill.killer_id,
+        killer_name: kill.killer_name,
+        victim_id: kill.victim_id,
+        victim_name: kill.victim_name,
+        weapon_id: kill.weapon_id,
+        weapon_name: kill.weapon_name,
+        killer_killstreak: kill.killer_new_killstreak,
+    });
+    scripting::on_kill(lobby, killer_id, victim_id, kill.weapon_id);
+    Ok(())
+}
+
+/// Single trigger pull - fires one shot, and queues the rest of the burst
+/// for burst weapons (fired by `update_automatic_fire`)
+pub fn pull_trigger(
+    lobby: &mut Lobby,
+    weapons: &impl WeaponLookup,
+    player_id: u32,
+    target_id: Option<u32>,
+    pellet_hits: &[u32],
+) -> Result<bool, &'static str> {
+    let fired = fire(lobby, weapons, player_id, target_id, pellet_hits)?;
+
+    if fired {
+        let player = lobby.players.get_mut(&player_id).ok_or("Player not found")?;
+        if let Some(FireMode::Burst(count)) = weapons.get(player.current_weapon_id).map(|w| w.fire_mode) {
+            player.burst_remaining = count.saturating_sub(1);
+            player.fire_target = target_id;
+        }
+    }
+
+    Ok(fired)
+}
+
+/// Start or stop holding the trigger
+pub fn set_trigger_held(
+    lobby: &mut Lobby,
+    player_id: u32,
+    held: bool,
+    target_id: Option<u32>,
+) -> Result<(), &'static str> {
+    let player = lobby
+        .players
+        .get_mut(&player_id)
+        .ok_or("Player not found")?;
+
+    player.trigger_held = held;
+    if held {
+        player.fire_target = target_id;
+    } else if player.burst_remaining == 0 {
+        player.fire_target = None;
+    }
+
+    Ok(())
+}
+
+/// Simulate held triggers and queued burst rounds for one tick
+/// Fires at most one shot per player per tick, gated by fire_rate
+/// Returns list of player_ids that fired
+pub fn update_automatic_fire(lobby: &mut Lobby, weapons: &impl WeaponLookup) -> Vec<u32> {
+    let shooters: Vec<(u32, Option<u32>, bool)> = lobby
+        .players
+        .values()
+        .filter(|p| !p.is_dead)
+        .filter_map(|p| {
+            let mode = weapons.get(p.current_weapon_id)?.fire_mode;
+            let bursting = p.burst_remaining > 0;
+            if bursting || (p.trigger_held && mode == FireMode::Auto) {
+                Some((p.id, p.fire_target, bursting))
+            } else {
+                None
+            }
+        })
+        .collect();
+
+    let mut fired = Vec::new();
+    for (player_id, target_id, bursting) in shooters {
+        let shot = fire_shot(lobby, weapons, player_id, target_id).unwrap_or(false);
+        if shot {
+            fired.push(player_id);
+        }
+
+        let Some(player) = lobby.players.get_mut(&player_id) else {
+            continue;
+        };
+        if bursting {
+            if shot {
+                player.burst_remaining -= 1;
+            } else if player.current_ammo == 0 || player.is_reloading {
+                // Burst is cut short when the magazine runs dry
+                player.burst_remaining = 0;
+            }
+            if player.burst_remaining == 0 && !player.trigger_held {
+                player.fire_target = None;
+            }
+        }
+    }
+
+    fired
+}
+
+/// Reset trigger and burst state
+fn clear_fire_state(player: &mut crate::state::lobby::Player) {
+    player.trigger_held = false;
+    player.fire_target = None;
+    player.burst_remaining = 0;
+}
+
+/// Apply damage to a player, part of it soaked up by their armor according to `damage_type`
+/// Returns the health actually removed (less than `damage` with armor or near zero health)
+pub fn apply_damage(lobby: &mut Lobby, target_id: u32, damage: u32, damage_type: DamageType) -> Result<u32, &'static str> {
     let player = lobby
         .players
         .get_mut(&target_id)
         .ok_or("Player not found")?;
 
     // Validate damage is reasonable
-    if damage == 0 || damage > 100 {
+    if damage == 0 || damage > MAX_DAMAGE {
         return Err("Invalid damage amount");
     }
 
+    // Freshly respawned players can't be hurt
+    if player.is_spawn_protected(clock::now()) {
+        return Err("Target is spawn protected");
+    }
+    if player.spectating {
+        return Err("Target is spectating");
+    }
+
+    let absorbed = armor::absorbed(player.armor, damage, damage_type);
+    let armor_left = player.armor - absorbed;
+
     // Apply damage with underflow protection
-    player.current_health = player.current_health.saturating_sub(damage);
+    let before = player.current_health;
+    player.current_health = player.current_health.saturating_sub(damage - absorbed);
+    let dealt = before - player.current_health;
+    player.match_stats.damage_taken += dealt;
+
+    armor::set(lobby, target_id, armor_left)?;
+    lobby.mark_changed(target_id, ChangeMask::HEALTH);
+    Ok(dealt)
+}
+
+/// Heal a player; with `overheal`, health may exceed max_health up to the overheal cap
+pub fn heal_player(
+    lobby: &mut Lobby,
+    player_id: u32,
+    amount: u32,
+    overheal: bool,
+) -> Result<(), &'static str> {
+    let player = lobby
+        .players
+        .get_mut(&player_id)
+        .ok_or("Player not found")?;
+
+    if player.is_dead {
+        return Err("Player is dead");
+    }
 
-    lobby.mark_dirty(target_id);
+    let cap = if overheal {
+        (player.max_health as f32 * OVERHEAL_CAP) as u32
+    } else {
+        player.max_health
+    };
+    // Never take away overheal the player already has
+    let healed = player.current_health.saturating_add(amount).min(cap);
+    if healed <= player.current_health {
+        return Ok(());
+    }
+    player.current_health = healed;
+
+    lobby.mark_changed(player_id, ChangeMask::HEALTH);
     Ok(())
 }
 
+/// Decay health above max_health back toward max_health
+/// Call once per tick with the tick duration
+pub fn decay_overheal(lobby: &mut Lobby, dt_secs: f32) {
+    let mut changed = Vec::new();
+
+    for player in lobby.players.values_mut() {
+        if player.current_health <= player.max_health {
+            player.overheal_decay = 0.0;
+            continue;
+        }
+
+        player.overheal_decay += OVERHEAL_DECAY_PER_SEC * dt_secs;
+        let whole = player.overheal_decay as u32;
+        if whole == 0 {
+            continue;
+        }
+        player.overheal_decay -= whole as f32;
+        player.current_health = player.current_health.saturating_sub(whole).max(player.max_health);
+        changed.push(player.id);
+    }
+
+    for player_id in changed {
+        lobby.mark_changed(player_id, ChangeMask::HEALTH);
+    }
+}
+
 /// Start player reload
 pub fn start_reload(
     lobby: &mut Lobby,
-    weapons: &WeaponDb,
+    weapons: &impl WeaponLookup,
     player_id: u32,
 ) -> Result<(), &'static str> {
     let player = lobby
@@ -97,47 +548,127 @@ pub fn start_reload(
         .get(player.current_weapon_id)
         .ok_or("Weapon not found")?;
 
+    let end_time = clock::now() + std::time::Duration::from_secs_f32(weapon.reload_time);
     player.is_reloading = true;
-    player.reload_end_time =
-        Some(SystemTime::now() + std::time::Duration::from_secs_f32(weapon.reload_time));
+    player.reload_end_time = Some(end_time);
 
-    lobby.mark_dirty(player_id);
+    lobby.timers.reloads.schedule(player_id, end_time);
+    lobby.mark_changed(player_id, ChangeMask::RELOAD);
     Ok(())
 }
 
 /// Update reload states - check and complete finished reloads
 /// Returns list of (player_id) that completed reload
 pub fn update_reload_states(lobby: &mut Lobby) -> Vec<u32> {
-    let now = SystemTime::now();
-    let mut completed_reloads = Vec::new();
+    let now = clock::now();
+    let players = &lobby.players;
+    let completed_reloads = lobby.timers.reloads.due(now, |id| {
+        players.get(&id).filter(|p| p.is_reloading).and_then(|p| p.reload_end_time)
+    });
+
+    for player_id in &completed_reloads {
+        if let Some(player) = lobby.players.get_mut(player_id) {
+            player.current_ammo = player.max_ammo;
+            player.is_reloading = false;
+            player.reload_end_time = None;
+        }
+        lobby.mark_changed(*player_id, ChangeMask::AMMO | ChangeMask::RELOAD);
+    }
 
-    // First pass: update reload states
+    completed_reloads
+}
+
+/// Clear equip timers that have run out, letting those players fire
+/// Returns players whose weapon is now ready
+pub fn update_equip_states(lobby: &mut Lobby, now: SystemTime) -> Vec<u32> {
+    let mut ready = Vec::new();
     for player in lobby.players.values_mut() {
-        if player.is_reloading {
-            if let Some(end_time) = player.reload_end_time {
-                if now >= end_time {
-                    // Reload complete
-                    player.current_ammo = player.max_ammo;
-                    player.is_reloading = false;
-                    player.reload_end_time = None;
-                    completed_reloads.push(player.id);
-                }
-            }
+        if player.equip_end_time.is_some_and(|end| now >= end) {
+            player.equip_end_time = None;
+            ready.push(player.id);
         }
     }
+    ready
+}
 
-    // Second pass: mark dirty (after mutable borrow is released)
-    for player_id in &completed_reloads {
-        lobby.mark_dirty(*player_id);
+/// Heat up from one shot; full heat starts the lockout
+fn add_heat(player: &mut Player, overheat: &Overheat, now: SystemTime) {
+    player.heat = (player.heat + overheat.heat_per_shot).min(1.0);
+    if player.heat >= 1.0 {
+        player.overheated_until = Some(now + Duration::from_secs_f32(overheat.lockout_secs));
     }
+}
 
-    completed_reloads
+/// Cool every player's weapon by one tick and end finished lockouts
+/// Weapons without an overheat model shed heat at once; a lockout runs its course regardless
+/// Heat is only resynced in steps of HEAT_SYNC_STEP to keep HUD updates off the wire most ticks
+pub fn update_heat(lobby: &mut Lobby, weapons: &impl WeaponLookup, dt: f32, now: SystemTime) {
+    let mut changed = Vec::new();
+    for player in lobby.players.values_mut() {
+        if player.heat <= 0.0 && player.overheated_until.is_none() {
+            continue;
+        }
+        let cool_per_sec = weapons.get(player.current_weapon_id)
+            .and_then(|w| w.overheat)
+            .map_or(f32::INFINITY, |o| o.cool_per_sec);
+        let before = heat_step(player.heat);
+        player.heat = (player.heat - cool_per_sec * dt).max(0.0);
+        let unlocked = player.overheated_until.is_some_and(|until| now >= until);
+        if unlocked {
+            player.overheated_until = None;
+        }
+        if unlocked || heat_step(player.heat) != before {
+            changed.push(player.id);
+        }
+    }
+    for player_id in changed {
+        lobby.mark_changed(player_id, ChangeMask::AMMO);
+    }
+}
+
+/// Heat change smaller than this isn't worth a packet of its own
+const HEAT_SYNC_STEP: f32 = 0.1;
+
+fn heat_step(heat: f32) -> u32 {
+    (heat / HEAT_SYNC_STEP).ceil() as u32
+}
+
+/// Seconds until a player's weapon is ready to fire
+pub fn equip_remaining(player: &Player, now: SystemTime) -> f32 {
+    player.equip_end_time
+        .and_then(|end| end.duration_since(now).ok())
+        .map_or(0.0, |remaining| remaining.as_secs_f32())
 }
 
 /// Switch player weapon
+/// The new weapon can't fire until its equip time has passed
 pub fn switch_weapon(
     lobby: &mut Lobby,
-    weapons: &WeaponDb,
+    weapons: &impl WeaponLookup,
+    player_id: u32,
+    weapon_id: u32,
+) -> Result<(), &'static str> {
+    if !lobby.players.contains_key(&player_id) {
+        return Err("Player not found");
+    }
+    if !weapons.contains(weapon_id) {
+        return Err("Invalid weapon");
+    }
+    // Players own the weapons on the lobby's ladder, and one weapon they picked up
+    let carried = lobby.players.get(&player_id).and_then(|p| p.carried_weapon);
+    if !lobby.settings.weapon_ladder.contains(&weapon_id) && carried != Some(weapon_id) {
+        return Err("Weapon not owned");
+    }
+    if weapon_bans::is_banned(&lobby.settings, weapon_id) {
+        return Err("Weapon is banned");
+    }
+    equip_weapon(lobby, weapons, player_id, weapon_id)
+}
+
+/// Put a weapon in the player's hands whether or not the ladder has it (e.g. picked up loot)
+pub fn equip_weapon(
+    lobby: &mut Lobby,
+    weapons: &impl WeaponLookup,
     player_id: u32,
     weapon_id: u32,
 ) -> Result<(), &'static str> {
@@ -156,12 +687,114 @@ pub fn switch_weapon(
     player.current_weapon_id = weapon_id;
     player.current_ammo = weapon.ammo;
     player.max_ammo = weapon.ammo;
+    player.equip_end_time = (weapon.equip_time > 0.0)
+        .then(|| clock::now() + Duration::from_secs_f32(weapon.equip_time));
 
     // Cancel any ongoing reload
     player.is_reloading = false;
     player.reload_end_time = None;
 
-    lobby.mark_dirty(player_id);
+    // Queued burst rounds belong to the previous weapon
+    player.burst_remaining = 0;
+
+    lobby.mark_changed(player_id, ChangeMask::WEAPON | ChangeMask::AMMO | ChangeMask::MAX_AMMO | ChangeMask::RELOAD);
+    Ok(())
+}
+
+/// Switch to the next (or previous) owned weapon in ladder order, wrapping around
+/// Returns the weapon switched to
+pub fn cycle_weapon(
+    lobby: &mut Lobby,
+    weapons: &impl WeaponLookup,
+    player_id: u32,
+    forward: bool,
+) -> Result<u32, &'static str> {
+    let current = lobby.players.get(&player_id).ok_or("Player not found")?.current_weapon_id;
+    // Weapons disabled in this lobby are skipped; a carried weapon comes after the ladder
+    let carried = lobby.players.get(&player_id).and_then(|p| p.carried_weapon);
+    let ladder: Vec<u32> = lobby.settings.weapon_ladder.iter()
+        .copied()
+        .chain(carried.filter(|id| !lobby.settings.weapon_ladder.contains(id)))
+        .filter(|id| weapons.contains(*id) && !weapon_bans::is_banned(&lobby.settings, *id))
+        .collect();
+    if ladder.is_empty() {
+        return Err("No weapons available");
+    }
+
+    let next = match ladder.iter().position(|id| *id == current) {
+        Some(index) if forward => ladder[(index + 1) % ladder.len()],
+        Some(index) => ladder[(index + ladder.len() - 1) % ladder.len()],
+        // Holding a weapon outside the ladder: start from either end
+        None if forward => ladder[0],
+        None => ladder[ladder.len() - 1],
+    };
+    if next == current {
+        return Err("No other weapon owned");
+    }
+    switch_weapon(lobby, weapons, player_id, next)?;
+    Ok(next)
+}
+
+/// Put an owned weapon back in the hands of a player respawning with one they no longer carry
+/// Returns the weapon equipped, if the held one had to go
+pub fn rearm(lobby: &mut Lobby, weapons: &impl WeaponLookup, player_id: u32) -> Result<Option<u32>, &'static str> {
+    let player = lobby.players.get(&player_id).ok_or("Player not found")?;
+    let current = player.current_weapon_id;
+    if lobby.settings.weapon_ladder.contains(&current) || player.carried_weapon == Some(current) {
+        return Ok(None);
+    }
+    let weapon_id = weapon_bans::fallback_weapon(lobby, weapons).ok_or("No weapons available")?;
+    equip_weapon(lobby, weapons, player_id, weapon_id)?;
+    // Drawn during the respawn delay
+    if let Some(player) = lobby.players.get_mut(&player_id) {
+        player.equip_end_time = None;
+    }
+    Ok(Some(weapon_id))
+}
+
+/// Equip the player's pending loadout: its primary, or the secondary if the ladder lacks the primary
+/// Returns the weapon equipped; the loadout is used up either way
+pub fn apply_pending_loadout(
+    lobby: &mut Lobby,
+    weapons: &impl WeaponLookup,
+    player_id: u32,
+) -> Result<Option<u32>, &'static str> {
+    let player = lobby.players.get_mut(&player_id).ok_or("Player not found")?;
+    let Some(loadout) = player.pending_loadout.take() else {
+        return Ok(None);
+    };
+    player.active_loadout = Some(loadout.clone());
+    let armor = player.armor.max(armor::spawn_armor(Some(&loadout)));
+    armor::set(lobby, player_id, armor)?;
+    let weapon_id = std::iter::once(loadout.primary)
+        .chain(loadout.secondary)
+        .find(|id| switch_weapon(lobby, weapons, player_id, *id).is_ok())
+        .ok_or("No loadout weapon owned in this lobby")?;
+    // Players spawn with the loadout weapon already drawn
+    if let Some(player) = lobby.players.get_mut(&player_id) {
+        player.equip_end_time = None;
+    }
+    lobby.push_event(SyncEvent::LoadoutApplied {
+        player_id,
+        name: loadout.name,
+        weapon_id,
+        attachments: loadout.attachments,
+    });
+    Ok(Some(weapon_id))
+}
+
+/// Check a lobby creator's weapon ladder: known weapons, each listed once
+pub fn validate_weapon_ladder(ladder: &[u32], weapons: &impl WeaponLookup) -> Result<(), &'static str> {
+    if ladder.is_empty() {
+        return Err("Weapon ladder is empty");
+    }
+    if ladder.iter().any(|id| !weapons.contains(*id)) {
+        return Err("Unknown weapon in ladder");
+    }
+    let unique: std::collections::HashSet<&u32> = ladder.iter().collect();
+    if unique.len() != ladder.len() {
+        return Err("Duplicate weapon in ladder");
+    }
     Ok(())
 }
 
@@ -184,7 +817,7 @@ pub fn get_lobby_state_sync(lobby: &Lobby) -> Vec<PlayerSyncState> {
 /// Returns KillEvent for broadcasting
 pub fn register_kill(
     lobby: &mut Lobby,
-    weapons: &WeaponDb,
+    weapons: &impl WeaponLookup,
     killer_id: u32,
     victim_id: u32,
 ) -> Result<KillEvent, &'static str> {
@@ -204,31 +837,49 @@ pub fn register_kill(
         )
     };
 
+    // Damage-share lobbies split the kill score among everyone who hurt the victim this life
+    let damage = lobby.damage_attribution.take(victim_id);
+    let shares = if lobby.settings.damage_share_scoring {
+        let present: Vec<(u32, u32)> = damage.into_iter().filter(|(id, _)| lobby.players.contains_key(id)).collect();
+        damage_share::split(killer_id, KILL_SCORE, &present)
+    } else {
+        vec![(killer_id, KILL_SCORE)]
+    };
+
     {
         let killer = lobby
             .players
             .get_mut(&killer_id)
             .ok_or("Killer not found")?;
-        let base_score = 100;
         let killstreak_bonus = std::cmp::min(killer_killstreak, 5) * 25;
 
         killer.kills += 1;
         killer.killstreak = killer_killstreak + 1;
-        killer.score += base_score + killstreak_bonus;
+        killer.match_stats.best_killstreak = killer.match_stats.best_killstreak.max(killer.killstreak);
+        killer.score += killstreak_bonus;
     }
-
-    {
-        let victim = lobby
-            .players
-            .get_mut(&victim_id)
-            .ok_or("Victim not found")?;
-        victim.deaths += 1;
-        victim.killstreak = 0;
-        victim.current_health = 0;
-        victim.is_dead = true;
-        victim.respawn_time = Some(SystemTime::now() + std::time::Duration::from_secs(3));
+    for (player_id, points) in shares {
+        let Some(player) = lobby.players.get_mut(&player_id) else {
+            continue;
+        };
+        player.score += points;
+        if player_id != killer_id {
+            player.match_stats.assist_score += points;
+        }
+        if lobby.settings.damage_share_scoring {
+            let event = SyncEvent::ScoreChanged {
+                player_id,
+                score: player.score,
+                kills: player.kills,
+                deaths: player.deaths,
+                killstreak: player.killstreak,
+            };
+            lobby.push_event(event);
+        }
     }
 
+    kill_player(lobby, victim_id)?;
+
     let event = KillEvent {
         killer_id,
         killer_name,
@@ -239,12 +890,87 @@ pub fn register_kill(
         killer_new_killstreak: killer_killstreak + 1,
     };
 
-    lobby.mark_dirty(killer_id);
-    lobby.mark_dirty(victim_id);
-
     Ok(event)
 }
 
+/// Kill a player - count the death and schedule respawn
+pub fn kill_player(lobby: &mut Lobby, victim_id: u32) -> Result<(), &'static str> {
+    let victim = lobby
+        .players
+        .get_mut(&victim_id)
+        .ok_or("Victim not found")?;
+
+    // Rolled for loot drops on the next tick
+    lobby.loot.pending_deaths.push(Death {
+        victim_id,
+        position: victim.position,
+        weapon_id: victim.current_weapon_id,
+        ammo: victim.current_ammo,
+    });
+    // Weapons picked up are lost with the life they were picked up in
+    victim.carried_weapon = None;
+    victim.deaths += 1;
+    victim.killstreak = 0;
+    victim.current_health = 0;
+    victim.is_dead = true;
+    let respawn_time = clock::now() + std::time::Duration::from_secs(3);
+    victim.respawn_time = Some(respawn_time);
+    victim.fall_speed = 0.0;
+    clear_fire_state(victim);
+
+    // Pre-death positions shouldn't show up in a later kill cam, nor earlier damage in the next kill's shares
+    lobby.position_history.forget(victim_id);
+    lobby.damage_attribution.take(victim_id);
+    lobby.timers.respawns.schedule(victim_id, respawn_time);
+    ramp_up::reset_player(lobby, victim_id);
+    armor::set(lobby, victim_id, 0)?;
+    lobby.mark_changed(victim_id, ChangeMask::HEALTH);
+    Ok(())
+}
+
+/// Server-side fall checks against the lobby's scene rules
+/// Call after each position update; returns the cause if the player died
+pub fn apply_fall_checks(lobby: &mut Lobby, player_id: u32) -> Option<DeathCause> {
+    let scene = &lobby.scene_data;
+    let player = lobby.players.get_mut(&player_id)?;
+
+    if player.is_dead {
+        return None;
+    }
+
+    if player.position.1 < scene.kill_plane_y {
+        kill_player(lobby, player_id).ok()?;
+        return Some(DeathCause::OutOfWorld);
+    }
+
+    if player.vertical_velocity < -FALLING_SPEED {
+        // Still falling - remember the fastest speed reached
+        player.fall_speed = player.fall_speed.max(-player.vertical_velocity);
+        return None;
+    }
+
+    // Landed (or never left the ground)
+    let landing_speed = std::mem::take(&mut player.fall_speed);
+    if landing_speed <= scene.safe_fall_speed {
+        return None;
+    }
+
+    let damage = (((landing_speed - scene.safe_fall_speed) * scene.fall_damage_per_speed) as u32).min(MAX_DAMAGE);
+    if damage == 0 {
+        return None;
+    }
+
+    // Same path as hits, so armor and spawn protection apply; the player is their own attacker
+    let dealt = apply_damage(lobby, player_id, damage, DamageType::Fall).ok()?;
+    let health = lobby.players.get(&player_id).map_or(0, |p| p.current_health);
+    lobby.push_event(SyncEvent::PlayerDamaged { player_id, attacker_id: player_id, damage: dealt, health });
+    if health == 0 {
+        kill_player(lobby, player_id).ok()?;
+        return Some(DeathCause::Fall);
+    }
+    None
+}
+
 /// Respawn a player at default position
 pub fn respawn_player(lobby: &mut Lobby, player_id: u32) -> Result<(), &'static str> {
     let player = lobby
@@ -258,11 +984,58 @@ pub fn respawn_player(lobby: &mut Lobby, player_id: u32) -> Result<(), &'static
     player.current_ammo = player.max_ammo;
     player.is_reloading = false;
     player.reload_end_time = None;
+    player.equip_end_time = None;
+    player.heat = 0.0;
+    player.overheated_until = None;
+    player.is_dead = false;
+    player.respawn_time = None;
+
+    // Teleporting to spawn must not register as a fall
+    player.vertical_velocity = 0.0;
+    player.fall_speed = 0.0;
+    player.last_position_time = None;
+    // A loadout about to be equipped decides the new life's armor over the last one
+    let spawn_armor = armor::spawn_armor(player.pending_loadout.as_ref().or(player.active_loadout.as_ref()));
 
-    lobby.mark_dirty(player_id);
+    let protection_secs = lobby.settings.spawn_protection_secs;
+    if protection_secs > 0.0 {
+        let until = clock::now() + Duration::from_secs_f32(protection_secs);
+        player.spawn_protection_until = Some(until);
+        lobby.timers.spawn_protection.schedule(player_id, until);
+        lobby.push_event(SyncEvent::SpawnProtectionStarted { player_id, seconds: protection_secs });
+    }
+
+    armor::set(lobby, player_id, spawn_armor)?;
+    lobby.mark_changed(player_id, ChangeMask::HEALTH | ChangeMask::AMMO | ChangeMask::RELOAD);
     Ok(())
 }
 
+/// Seconds of spawn protection a player has left
+pub fn spawn_protection_remaining(lobby: &Lobby, player_id: u32, now: SystemTime) -> f32 {
+    lobby.players.get(&player_id)
+        .and_then(|p| p.spawn_protection_until)
+        .and_then(|until| until.duration_since(now).ok())
+        .map_or(0.0, |left| left.as_secs_f32())
+}
+
+/// End spawn protection for players whose window has run out
+pub fn update_spawn_protection(lobby: &mut Lobby, now: SystemTime) {
+    let players = &lobby.players;
+    let expired = lobby.timers.spawn_protection.due(now, |id| players.get(&id).and_then(|p| p.spawn_protection_until));
+    for player_id in expired {
+        if let Some(player) = lobby.players.get_mut(&player_id) {
+            player.spawn_protection_until = None;
+        }
+        lobby.push_event(SyncEvent::SpawnProtectionEnded { player_id });
+    }
+}
+
+/// Dead players whose respawn time has come
+pub fn due_respawns(lobby: &mut Lobby, now: SystemTime) -> Vec<u32> {
+    let players = &lobby.players;
+    lobby.timers.respawns.due(now, |id| players.get(&id).filter(|p| p.is_dead).and_then(|p| p.respawn_time))
+}
+
 /// Check if player is dead
 pub fn is_player_alive(lobby: &Lobby, player_id: u32) -> bool {
     if let Some(player) = lobby.players.get(&player_id) {
@@ -289,7 +1062,7 @@ mod tests {
         let weapons = WeaponDb::load();
 
         // Add player with ammo
-        let mut player = crate::state::lobby::Player {
+        let player = crate::state::lobby::Player {
             id: 1,
             name: "Test".to_string(),
             position: (0.0, 1.0, 0.0),
@@ -310,12 +1083,43 @@ mod tests {
             warned_at: None,
             is_dead: false,
             respawn_time: None,
+            vertical_velocity: 0.0,
+            fall_speed: 0.0,
+            last_position_time: None,
+            last_whisper_time: SystemTime::UNIX_EPOCH,
+            changes: crate::state::lobby::ChangeMask::empty(),
+            trigger_held: false,
+            fire_target: None,
+            burst_remaining: 0,
+            overheal_decay: 0.0,
+            match_stats: crate::state::lobby::MatchStats::default(),
+            rtt_ms: None,
+            ready: false,
+            spawn_protection_until: None,
+            anticheat_strikes: 0,
+            pending_loadout: None,
+            quaternion_rotation: false,
+            message_keys: false,
+            last_activity: SystemTime::now(),
+            afk_warned: false,
+            spectating: false,
+            command_last_used: Default::default(),
+            equip_end_time: None,
+            active_loadout: None,
+            preferences: Default::default(),
+            armor: 0,
+            region: None,
+            carried_weapon: None,
+            party_id: None,
+            heat: 0.0,
+            overheated_until: None,
+            client_id: None,
         };
         lobby.players.insert(1, player);
 
         let result = try_shoot(&mut lobby, &weapons, 1);
         assert!(result.is_ok());
-        assert_eq!(result.unwrap(), true);
+        assert!(result.unwrap());
 
         let player = lobby.players.get(&1).unwrap();
         assert_eq!(player.current_ammo, 19);
@@ -324,9 +1128,8 @@ mod tests {
     #[test]
     fn test_try_shoot_no_ammo() {
         let mut lobby = Lobby::new("TEST".to_string(), 4, "world".to_string());
-        let weapons = WeaponDb::load();
 
-        let mut player = crate::state::lobby::Player {
+        let player = crate::state::lobby::Player {
             id: 1,
             name: "Test".to_string(),
             position: (0.0, 1.0, 0.0),
@@ -347,10 +1150,41 @@ mod tests {
             warned_at: None,
             is_dead: false,
             respawn_time: None,
+            vertical_velocity: 0.0,
+            fall_speed: 0.0,
+            last_position_time: None,
+            last_whisper_time: SystemTime::UNIX_EPOCH,
+            changes: crate::state::lobby::ChangeMask::empty(),
+            trigger_held: false,
+            fire_target: None,
+            burst_remaining: 0,
+            overheal_decay: 0.0,
+            match_stats: crate::state::lobby::MatchStats::default(),
+            rtt_ms: None,
+            ready: false,
+            spawn_protection_until: None,
+            anticheat_strikes: 0,
+            pending_loadout: None,
+            quaternion_rotation: false,
+            message_keys: false,
+            last_activity: SystemTime::now(),
+            afk_warned: false,
+            spectating: false,
+            command_last_used: Default::default(),
+            equip_end_time: None,
+            active_loadout: None,
+            preferences: Default::default(),
+            armor: 0,
+            region: None,
+            carried_weapon: None,
+            party_id: None,
+            heat: 0.0,
+            overheated_until: None,
+            client_id: None,
         };
         lobby.players.insert(1, player);
 
-        let result = apply_damage(&mut lobby, 1, 25);
+        let result = apply_damage(&mut lobby, 1, 25, DamageType::Ballistic);
         assert!(result.is_ok());
     }
 
@@ -358,7 +1192,7 @@ mod tests {
     fn test_apply_damage() {
         let mut lobby = Lobby::new("TEST".to_string(), 4, "world".to_string());
 
-        let mut player = crate::state::lobby::Player {
+        let player = crate::state::lobby::Player {
             id: 1,
             name: "Test".to_string(),
             position: (0.0, 1.0, 0.0),
@@ -379,10 +1213,41 @@ mod tests {
             warned_at: None,
             is_dead: false,
             respawn_time: None,
+            vertical_velocity: 0.0,
+            fall_speed: 0.0,
+            last_position_time: None,
+            last_whisper_time: SystemTime::UNIX_EPOCH,
+            changes: crate::state::lobby::ChangeMask::empty(),
+            trigger_held: false,
+            fire_target: None,
+            burst_remaining: 0,
+            overheal_decay: 0.0,
+            match_stats: crate::state::lobby::MatchStats::default(),
+            rtt_ms: None,
+            ready: false,
+            spawn_protection_until: None,
+            anticheat_strikes: 0,
+            pending_loadout: None,
+            quaternion_rotation: false,
+            message_keys: false,
+            last_activity: SystemTime::now(),
+            afk_warned: false,
+            spectating: false,
+            command_last_used: Default::default(),
+            equip_end_time: None,
+            active_loadout: None,
+            preferences: Default::default(),
+            armor: 0,
+            region: None,
+            carried_weapon: None,
+            party_id: None,
+            heat: 0.0,
+            overheated_until: None,
+            client_id: None,
         };
         lobby.players.insert(1, player);
 
-        let result = apply_damage(&mut lobby, 1, 25);
+        let result = apply_damage(&mut lobby, 1, 25, DamageType::Ballistic);
         assert!(result.is_ok());
 
         let player = lobby.players.get(&1).unwrap();
@@ -394,7 +1259,7 @@ mod tests {
         let mut lobby = Lobby::new("TEST".to_string(), 4, "world".to_string());
         let weapons = WeaponDb::load();
 
-        let mut player = crate::state::lobby::Player {
+        let player = crate::state::lobby::Player {
             id: 1,
             name: "Test".to_string(),
             position: (0.0, 1.0, 0.0),
@@ -415,6 +1280,37 @@ mod tests {
             warned_at: None,
             is_dead: false,
             respawn_time: None,
+            vertical_velocity: 0.0,
+            fall_speed: 0.0,
+            last_position_time: None,
+            last_whisper_time: SystemTime::UNIX_EPOCH,
+            changes: crate::state::lobby::ChangeMask::empty(),
+            trigger_held: false,
+            fire_target: None,
+            burst_remaining: 0,
+            overheal_decay: 0.0,
+            match_stats: crate::state::lobby::MatchStats::default(),
+            rtt_ms: None,
+            ready: false,
+            spawn_protection_until: None,
+            anticheat_strikes: 0,
+            pending_loadout: None,
+            quaternion_rotation: false,
+            message_keys: false,
+            last_activity: SystemTime::now(),
+            afk_warned: false,
+            spectating: false,
+            command_last_used: Default::default(),
+            equip_end_time: None,
+            active_loadout: None,
+            preferences: Default::default(),
+            armor: 0,
+            region: None,
+            carried_weapon: None,
+            party_id: None,
+            heat: 0.0,
+            overheated_until: None,
+            client_id: None,
         };
         lobby.players.insert(1, player);
 
@@ -431,7 +1327,7 @@ mod tests {
         let mut lobby = Lobby::new("TEST".to_string(), 4, "world".to_string());
         let weapons = WeaponDb::load();
 
-        let mut player = crate::state::lobby::Player {
+        let player = crate::state::lobby::Player {
             id: 1,
             name: "Test".to_string(),
             position: (0.0, 1.0, 0.0),
@@ -452,6 +1348,37 @@ mod tests {
             warned_at: None,
             is_dead: false,
             respawn_time: None,
+            vertical_velocity: 0.0,
+            fall_speed: 0.0,
+            last_position_time: None,
+            last_whisper_time: SystemTime::UNIX_EPOCH,
+            changes: crate::state::lobby::ChangeMask::empty(),
+            trigger_held: false,
+            fire_target: None,
+            burst_remaining: 0,
+            overheal_decay: 0.0,
+            match_stats: crate::state::lobby::MatchStats::default(),
+            rtt_ms: None,
+            ready: false,
+            spawn_protection_until: None,
+            anticheat_strikes: 0,
+            pending_loadout: None,
+            quaternion_rotation: false,
+            message_keys: false,
+            last_activity: SystemTime::now(),
+            afk_warned: false,
+            spectating: false,
+            command_last_used: Default::default(),
+            equip_end_time: None,
+            active_loadout: None,
+            preferences: Default::default(),
+            armor: 0,
+            region: None,
+            carried_weapon: None,
+            party_id: None,
+            heat: 0.0,
+            overheated_until: None,
+            client_id: None,
         };
         lobby.players.insert(1, player);
 
@@ -462,4 +1389,536 @@ mod tests {
         assert_eq!(player.current_weapon_id, 2);
         assert_eq!(player.current_ammo, 8); // Prototype ammo
     }
+
+    #[test]
+    fn test_cycle_weapon_follows_ladder() {
+        let mut lobby = Lobby::new("TEST".to_string(), 4, "world".to_string());
+        lobby.settings.weapon_ladder = vec![3, 1, 2];
+        let weapons = WeaponDb::load();
+        lobby.players.insert(1, crate::state::lobby::Player::new_player(1, "Test".to_string(), 1, 20));
+
+        assert_eq!(cycle_weapon(&mut lobby, &weapons, 1, true), Ok(2));
+        assert_eq!(cycle_weapon(&mut lobby, &weapons, 1, true), Ok(3)); // Wraps around
+        assert_eq!(cycle_weapon(&mut lobby, &weapons, 1, false), Ok(2));
+
+        // Weapons off the ladder are not owned
+        lobby.settings.weapon_ladder = vec![2];
+        assert_eq!(switch_weapon(&mut lobby, &weapons, 1, 1), Err("Weapon not owned"));
+        assert!(cycle_weapon(&mut lobby, &weapons, 1, true).is_err());
+    }
+
+    #[test]
+    fn test_pending_loadout_falls_back_to_secondary() {
+        use crate::state::loadouts::Loadout;
+
+        let mut lobby = Lobby::new("TEST".to_string(), 4, "world".to_string());
+        lobby.settings.weapon_ladder = vec![1, 3];
+        let weapons = WeaponDb::load();
+        lobby.players.insert(1, crate::state::lobby::Player::new_player(1, "Test".to_string(), 1, 20));
+        assert_eq!(apply_pending_loadout(&mut lobby, &weapons, 1), Ok(None));
+
+        // The Prototype isn't on this ladder, so the knife is equipped
+        let loadout = Loadout { name: "close".to_string(), primary: 2, secondary: Some(3), attachments: vec![] };
+        lobby.players.get_mut(&1).unwrap().pending_loadout = Some(loadout.clone());
+        assert_eq!(apply_pending_loadout(&mut lobby, &weapons, 1), Ok(Some(3)));
+        assert_eq!(lobby.players[&1].current_weapon_id, 3);
+        assert!(lobby.players[&1].pending_loadout.is_none());
+
+        let unusable = Loadout { secondary: None, ..loadout };
+        lobby.players.get_mut(&1).unwrap().pending_loadout = Some(unusable);
+        assert!(apply_pending_loadout(&mut lobby, &weapons, 1).is_err());
+        assert_eq!(lobby.players[&1].current_weapon_id, 3);
+    }
+
+    #[test]
+    fn test_validate_weapon_ladder() {
+        let weapons = WeaponDb::load();
+        assert!(validate_weapon_ladder(&[2, 1], &weapons).is_ok());
+        assert!(validate_weapon_ladder(&[], &weapons).is_err());
+        assert!(validate_weapon_ladder(&[1, 99], &weapons).is_err());
+        assert!(validate_weapon_ladder(&[1, 1], &weapons).is_err());
+    }
+
+    fn falling_player(lobby: &mut Lobby, y: f32, vertical_velocity: f32, fall_speed: f32) {
+        let weapons = WeaponDb::load();
+        crate::domain::lobbies::add_player(lobby, 1, "Faller".to_string(), 1, &weapons).unwrap();
+        let player = lobby.players.get_mut(&1).unwrap();
+        player.position.1 = y;
+        player.vertical_velocity = vertical_velocity;
+        player.fall_speed = fall_speed;
+    }
+
+    #[test]
+    fn test_fall_below_kill_plane() {
+        let mut lobby = Lobby::new("TEST".to_string(), 4, "world".to_string());
+        falling_player(&mut lobby, -60.0, -30.0, 30.0);
+
+        assert_eq!(apply_fall_checks(&mut lobby, 1), Some(DeathCause::OutOfWorld));
+        let player = lobby.players.get(&1).unwrap();
+        assert!(player.is_dead);
+        assert_eq!(player.current_health, 0);
+        assert_eq!(player.deaths, 1);
+
+        // Dead players are not checked again
+        assert_eq!(apply_fall_checks(&mut lobby, 1), None);
+    }
+
+    #[test]
+    fn test_falling_tracks_peak_speed() {
+        let mut lobby = Lobby::new("TEST".to_string(), 4, "world".to_string());
+        falling_player(&mut lobby, 10.0, -20.0, 5.0);
+
+        assert_eq!(apply_fall_checks(&mut lobby, 1), None);
+        let player = lobby.players.get(&1).unwrap();
+        assert_eq!(player.fall_speed, 20.0);
+        assert_eq!(player.current_health, 100);
+    }
+
+    #[test]
+    fn test_hard_landing_applies_fall_damage() {
+        let mut lobby = Lobby::new("TEST".to_string(), 4, "world".to_string());
+        falling_player(&mut lobby, 1.0, 0.0, 20.0);
+
+        assert_eq!(apply_fall_checks(&mut lobby, 1), None);
+        let player = lobby.players.get(&1).unwrap();
+        assert_eq!(player.current_health, 80); // (20 - 15) * 4
+        assert_eq!(player.fall_speed, 0.0);
+        assert!(lobby.pending_events.iter().any(|e| matches!(e, SyncEvent::PlayerDamaged { player_id: 1, attacker_id: 1, damage: 20, health: 80 })));
+    }
+
+    #[test]
+    fn test_armor_cushions_fall_damage() {
+        let mut lobby = Lobby::new("TEST".to_string(), 4, "world".to_string());
+        falling_player(&mut lobby, 1.0, 0.0, 20.0);
+        lobby.players.get_mut(&1).unwrap().armor = 50;
+
+        assert_eq!(apply_fall_checks(&mut lobby, 1), None);
+        let player = lobby.players.get(&1).unwrap();
+        assert_eq!(player.current_health, 85); // A quarter of 20 absorbed
+        assert_eq!(player.armor, 45);
+        assert_eq!(player.match_stats.damage_taken, 15);
+    }
+
+    #[test]
+    fn test_fatal_landing() {
+        let mut lobby = Lobby::new("TEST".to_string(), 4, "world".to_string());
+        falling_player(&mut lobby, 1.0, 0.0, 60.0);
+
+        assert_eq!(apply_fall_checks(&mut lobby, 1), Some(DeathCause::Fall));
+        assert!(lobby.players.get(&1).unwrap().is_dead);
+    }
+
+    #[test]
+    fn test_respawn_clears_death_and_fall_state() {
+        let mut lobby = Lobby::new("TEST".to_string(), 4, "world".to_string());
+        falling_player(&mut lobby, -60.0, -30.0, 30.0);
+        apply_fall_checks(&mut lobby, 1);
+
+        respawn_player(&mut lobby, 1).unwrap();
+        let player = lobby.players.get(&1).unwrap();
+        assert!(!player.is_dead);
+        assert!(player.respawn_time.is_none());
+        assert_eq!(player.current_health, 100);
+        assert!(player.last_position_time.is_none());
+    }
+
+    #[test]
+    fn test_spawn_protection() {
+        let (mut lobby, weapons) = armed_lobby(1);
+        respawn_player(&mut lobby, 2).unwrap();
+        assert!(matches!(lobby.pending_events.last(), Some(SyncEvent::SpawnProtectionStarted { player_id: 2, .. })));
+
+        // Protected: no damage taken, no shots fired
+        assert!(apply_damage(&mut lobby, 2, 10, DamageType::Ballistic).is_err());
+        lobby.players.get_mut(&2).unwrap().last_shot_time = SystemTime::UNIX_EPOCH;
+        assert_eq!(try_shoot(&mut lobby, &weapons, 2), Ok(false));
+
+        let later = SystemTime::now() + Duration::from_secs(60);
+        update_spawn_protection(&mut lobby, later);
+        assert!(matches!(lobby.pending_events.last(), Some(SyncEvent::SpawnProtectionEnded { player_id: 2 })));
+        assert_eq!(spawn_protection_remaining(&lobby, 2, later), 0.0);
+        assert_eq!(apply_damage(&mut lobby, 2, 10, DamageType::Ballistic), Ok(10));
+        assert_eq!(try_shoot(&mut lobby, &weapons, 2), Ok(true));
+    }
+
+    #[test]
+    fn test_respawn_due_from_timer_queue_after_pause() {
+        let (mut lobby, _) = armed_lobby(1);
+        kill_player(&mut lobby, 2).unwrap();
+        let now = SystemTime::now();
+        assert!(due_respawns(&mut lobby, now).is_empty());
+
+        // Paused for 10s: the queued time comes up first and is moved back
+        crate::domain::lobbies::pause(&mut lobby, now).unwrap();
+        crate::domain::lobbies::resume(&mut lobby, now + Duration::from_secs(10)).unwrap();
+        assert!(due_respawns(&mut lobby, now + Duration::from_secs(4)).is_empty());
+        assert_eq!(lobby.timers.respawns.len(), 1);
+        assert_eq!(due_respawns(&mut lobby, now + Duration::from_secs(14)), vec![2]);
+        assert!(lobby.timers.respawns.is_empty());
+    }
+
+    fn armed_lobby(weapon_id: u32) -> (Lobby, WeaponDb) {
+        let mut lobby = Lobby::new("TEST".to_string(), 4, "world".to_string());
+        let weapons = WeaponDb::load();
+        crate::domain::lobbies::add_player(&mut lobby, 1, "Shooter".to_string(), 1, &weapons).unwrap();
+        crate::domain::lobbies::add_player(&mut lobby, 2, "Target".to_string(), 1, &weapons).unwrap();
+        switch_weapon(&mut lobby, &weapons, 1, weapon_id).unwrap();
+        // Weapon drawn and off fire-rate cooldown
+        let shooter = lobby.players.get_mut(&1).unwrap();
+        shooter.last_shot_time = SystemTime::UNIX_EPOCH;
+        shooter.equip_end_time = None;
+        (lobby, weapons)
+    }
+
+    /// Allow the next shot immediately (skip the fire-rate wait)
+    fn ready_to_fire(lobby: &mut Lobby) {
+        lobby.players.get_mut(&1).unwrap().last_shot_time = SystemTime::UNIX_EPOCH;
+    }
+
+    #[test]
+    fn test_switch_waits_for_equip_time() {
+        let (mut lobby, weapons) = armed_lobby(1);
+        switch_weapon(&mut lobby, &weapons, 1, 2).unwrap();
+        let now = SystemTime::now();
+        let remaining = equip_remaining(&lobby.players[&1], now);
+        assert!(remaining > 0.5 && remaining <= 0.6);
+        assert!(!fire_shot(&mut lobby, &weapons, 1, Some(2)).unwrap());
+
+        assert!(update_equip_states(&mut lobby, now).is_empty());
+        assert_eq!(update_equip_states(&mut lobby, now + Duration::from_secs(1)), vec![1]);
+        assert_eq!(equip_remaining(&lobby.players[&1], now), 0.0);
+        assert!(fire_shot(&mut lobby, &weapons, 1, Some(2)).unwrap());
+    }
+
+    #[test]
+    fn test_untargeted_shot_is_a_counted_miss() {
+        let (mut lobby, weapons) = armed_lobby(1);
+        let ammo = lobby.players[&1].current_ammo;
+
+        assert!(pull_trigger(&mut lobby, &weapons, 1, None, &[]).unwrap());
+        // Fire rate still applies
+        assert!(!pull_trigger(&mut lobby, &weapons, 1, None, &[]).unwrap());
+
+        let shooter = &lobby.players[&1];
+        assert_eq!(shooter.current_ammo, ammo - 1);
+        assert_eq!((shooter.match_stats.shots_fired, shooter.match_stats.shots_hit), (1, 0));
+        assert_eq!(lobby.players[&2].current_health, lobby.players[&2].max_health);
+        assert!(lobby.pending_events.iter().any(|e| matches!(e, SyncEvent::PlayerShot { player_id: 1, weapon_id: 1 })));
+        assert!(!lobby.pending_events.iter().any(|e| matches!(e, SyncEvent::HitConfirmed { .. })));
+    }
+
+    #[test]
+    fn test_burst_fires_queued_rounds() {
+        let (mut lobby, weapons) = armed_lobby(2);
+
+        assert!(pull_trigger(&mut lobby, &weapons, 1, Some(2), &[]).unwrap());
+        assert_eq!(lobby.players.get(&1).unwrap().burst_remaining, 2);
+
+        // Tick right after the first shot is still gated by fire rate
+        assert!(update_automatic_fire(&mut lobby, &weapons).is_empty());
+
+        ready_to_fire(&mut lobby);
+        assert_eq!(update_automatic_fire(&mut lobby, &weapons), vec![1]);
+        ready_to_fire(&mut lobby);
+        assert_eq!(update_automatic_fire(&mut lobby, &weapons), vec![1]);
+        ready_to_fire(&mut lobby);
+        assert!(update_automatic_fire(&mut lobby, &weapons).is_empty());
+
+        let shooter = lobby.players.get(&1).unwrap();
+        assert_eq!(shooter.current_ammo, 5);
+        assert_eq!(shooter.fire_target, None);
+        assert_eq!(lobby.players.get(&2).unwrap().current_health, 10); // 3 x 30 damage
+    }
+
+    #[test]
+    fn test_auto_fire_while_held() {
+        let (mut lobby, weapons) = armed_lobby(1);
+
+        set_trigger_held(&mut lobby, 1, true, Some(2)).unwrap();
+        assert_eq!(update_automatic_fire(&mut lobby, &weapons), vec![1]);
+        ready_to_fire(&mut lobby);
+        assert_eq!(update_automatic_fire(&mut lobby, &weapons), vec![1]);
+
+        set_trigger_held(&mut lobby, 1, false, None).unwrap();
+        ready_to_fire(&mut lobby);
+        assert!(update_automatic_fire(&mut lobby, &weapons).is_empty());
+
+        assert_eq!(lobby.players.get(&1).unwrap().current_ammo, 18);
+        assert_eq!(lobby.players.get(&2).unwrap().current_health, 60);
+    }
+
+    #[test]
+    fn test_held_trigger_ignored_for_semi_auto() {
+        let (mut lobby, weapons) = armed_lobby(3);
+
+        set_trigger_held(&mut lobby, 1, true, Some(2)).unwrap();
+        assert!(update_automatic_fire(&mut lobby, &weapons).is_empty());
+    }
+
+    #[test]
+    fn test_overheal_decays_to_max_health() {
+        let mut lobby = Lobby::new("TEST".to_string(), 4, "world".to_string());
+        let weapons = WeaponDb::load();
+        crate::domain::lobbies::add_player(&mut lobby, 1, "Test".to_string(), 1, &weapons).unwrap();
+
+        heal_player(&mut lobby, 1, 500, true).unwrap();
+        assert_eq!(lobby.players.get(&1).unwrap().current_health, 150); // Capped at 1.5x
+
+        // Fractional decay accumulates across ticks
+        decay_overheal(&mut lobby, 0.1);
+        assert_eq!(lobby.players.get(&1).unwrap().current_health, 150);
+        decay_overheal(&mut lobby, 0.1);
+        assert_eq!(lobby.players.get(&1).unwrap().current_health, 149);
+
+        decay_overheal(&mut lobby, 60.0);
+        assert_eq!(lobby.players.get(&1).unwrap().current_health, 100);
+    }
+
+    #[test]
+    fn test_fire_shot_tracks_match_stats() {
+        let (mut lobby, weapons) = armed_lobby(1);
+
+        assert!(fire_shot(&mut lobby, &weapons, 1, Some(2)).unwrap());
+        ready_to_fire(&mut lobby);
+        assert!(fire_shot(&mut lobby, &weapons, 1, None).unwrap());
+
+        let shooter = lobby.players.get(&1).unwrap().match_stats;
+        assert_eq!(shooter.shots_fired, 2);
+        assert_eq!(shooter.shots_hit, 1);
+        assert_eq!(shooter.damage_dealt, 20);
+        assert_eq!(lobby.players.get(&2).unwrap().match_stats.damage_taken, 20);
+    }
+
+    #[test]
+    fn test_pellet_shot_totals_and_confirms_per_pellet() {
+        let (mut lobby, weapons) = armed_lobby(4);
+        crate::domain::lobbies::add_player(&mut lobby, 3, "Bystander".to_string(), 1, &weapons).unwrap();
+        for (id, x) in [(1, 0.0), (2, 2.0), (3, 3.0)] {
+            lobby.players.get_mut(&id).unwrap().position = (x, 1.0, 0.0);
+        }
+
+        // Point blank, the server lands all eight pellets: 8 x 12 damage
+        assert!(pull_trigger(&mut lobby, &weapons, 1, Some(2), &[]).unwrap());
+        assert_eq!(lobby.players[&2].current_health, 4);
+        let pellets = lobby.pending_events.iter().find_map(|e| match e {
+            SyncEvent::HitConfirmed { target_id: 2, damage, pellets, .. } => Some((*damage, pellets.clone())),
+            _ => None,
+        });
+        assert_eq!(pellets, Some((96, vec![12; 8])));
+        assert_eq!(lobby.players[&1].match_stats.shots_hit, 1);
+
+        // Reported pellets split over two players; each target's total is capped
+        lobby.players.get_mut(&2).unwrap().current_health = 100;
+        lobby.pending_events.clear();
+        ready_to_fire(&mut lobby);
+        assert!(pull_trigger(&mut lobby, &weapons, 1, Some(2), &[2, 2, 2, 3, 3, 2, 2, 2]).unwrap());
+        assert_eq!(lobby.players[&2].current_health, 28);
+        assert_eq!(lobby.players[&3].current_health, 76);
+        let confirmed = lobby.pending_events.iter().filter(|e| matches!(e, SyncEvent::HitConfirmed { .. })).count();
+        assert_eq!(confirmed, 2);
+    }
+
+    #[test]
+    fn test_damage_taken_capped_by_remaining_health() {
+        let (mut lobby, _) = armed_lobby(1);
+        lobby.players.get_mut(&2).unwrap().current_health = 10;
+
+        assert_eq!(apply_damage(&mut lobby, 2, 50, DamageType::Ballistic), Ok(10));
+        assert_eq!(lobby.players.get(&2).unwrap().match_stats.damage_taken, 10);
+    }
+
+    #[test]
+    fn test_victim_told_server_health_and_killed_only_at_zero() {
+        let (mut lobby, weapons) = armed_lobby(1);
+        lobby.players.get_mut(&2).unwrap().current_health = 30;
+
+        assert!(fire_shot(&mut lobby, &weapons, 1, Some(2)).unwrap());
+        let damaged: Vec<(u32, u32)> = lobby.pending_events.iter().filter_map(|e| match e {
+            SyncEvent::PlayerDamaged { player_id: 2, attacker_id: 1, damage, health } => Some((*damage, *health)),
+            _ => None,
+        }).collect();
+        assert_eq!(damaged, vec![(20, 10)]);
+        assert!(!lobby.players[&2].is_dead);
+        assert!(!lobby.pending_events.iter().any(|e| matches!(e, SyncEvent::PlayerKilled { .. })));
+
+        ready_to_fire(&mut lobby);
+        assert!(fire_shot(&mut lobby, &weapons, 1, Some(2)).unwrap());
+        assert!(lobby.pending_events.iter().any(|e| matches!(e, SyncEvent::PlayerDamaged { damage: 10, health: 0, .. })));
+        assert!(lobby.pending_events.iter().any(|e| matches!(e, SyncEvent::PlayerKilled { victim_id: 2, .. })));
+    }
+
+    #[test]
+    fn test_damage_share_scoring_splits_kill_score() {
+        let (mut lobby, weapons) = armed_lobby(1);
+        lobby.settings.damage_share_scoring = true;
+        crate::domain::lobbies::add_player(&mut lobby, 3, "Softener".to_string(), 1, &weapons).unwrap();
+        // 3 took the target down to 20 before 1 finished it
+        lobby.damage_attribution.record(3, 2, 60);
+        lobby.players.get_mut(&2).unwrap().current_health = 20;
+        lobby.pending_events.clear();
+
+        assert!(fire_shot(&mut lobby, &weapons, 1, Some(2)).unwrap());
+        assert_eq!((lobby.players[&1].score, lobby.players[&1].kills), (25, 1));
+        assert_eq!((lobby.players[&3].score, lobby.players[&3].match_stats.assist_score), (75, 75));
+        let updates: Vec<(u32, u32)> = lobby.pending_events.iter().filter_map(|e| match e {
+            SyncEvent::ScoreChanged { player_id, score, .. } => Some((*player_id, *score)),
+            _ => None,
+        }).collect();
+        assert_eq!(updates, vec![(1, 25), (3, 75)]);
+        assert!(lobby.damage_attribution.take(2).is_empty());
+    }
+
+    #[test]
+    fn test_armor_soaks_hits_and_resets_each_life() {
+        let (mut lobby, weapons) = armed_lobby(1);
+        lobby.players.get_mut(&2).unwrap().armor = 30;
+        lobby.pending_events.clear();
+
+        assert!(fire_shot(&mut lobby, &weapons, 1, Some(2)).unwrap());
+        assert_eq!((lobby.players[&2].current_health, lobby.players[&2].armor), (90, 20));
+        assert!(lobby.pending_events.iter().any(|e| matches!(e,
+            SyncEvent::HitConfirmed { target_id: 2, damage: 10, armor_absorbed: 10, target_armor: 20, .. })));
+        assert!(lobby.pending_events.iter().any(|e| matches!(e, SyncEvent::ArmorChanged { player_id: 2, armor: 20 })));
+        // Knives mostly ignore armor
+        assert_eq!(apply_damage(&mut lobby, 2, 50, DamageType::Melee), Ok(40));
+
+        kill_player(&mut lobby, 2).unwrap();
+        assert_eq!(lobby.players[&2].armor, 0);
+        lobby.players.get_mut(&2).unwrap().active_loadout = Some(crate::state::loadouts::Loadout {
+            name: "tank".to_string(),
+            primary: 1,
+            secondary: None,
+            attachments: vec![armor::ARMOR_ATTACHMENT.to_string()],
+        });
+        respawn_player(&mut lobby, 2).unwrap();
+        assert_eq!(lobby.players[&2].armor, armor::LOADOUT_ARMOR);
+    }
+
+    #[test]
+    fn test_lethal_shot_sends_killcam_to_victim() {
+        let (mut lobby, weapons) = armed_lobby(1);
+        for tick in 0..3 {
+            lobby.players.get_mut(&1).unwrap().position = (tick as f32, 1.0, 0.0);
+            crate::domain::history::record_tick(&mut lobby, tick);
+        }
+        lobby.players.get_mut(&2).unwrap().current_health = 10;
+
+        assert!(fire_shot(&mut lobby, &weapons, 1, Some(2)).unwrap());
+        assert!(lobby.players.get(&2).unwrap().is_dead);
+        assert_eq!(lobby.players.get(&1).unwrap().kills, 1);
+
+        let killcam = lobby.pending_events.iter().find_map(|e| match e {
+            SyncEvent::KillcamData { killer_id: 1, weapon_id, samples, .. } => Some((*weapon_id, samples.clone())),
+            _ => None,
+        });
+        let (weapon_id, samples) = killcam.expect("killcam raised");
+        assert_eq!(weapon_id, 1);
+        assert_eq!(samples.iter().map(|s| s.position.0).collect::<Vec<_>>(), vec![0.0, 1.0, 2.0]);
+        assert!(lobby.pending_events.iter().any(|e| matches!(e, SyncEvent::PlayerKilled { victim_id: 2, .. })));
+        assert!(lobby.pending_events.iter().any(|e| e.recipient() == Some(2)));
+        assert!(lobby.position_history.samples(2).is_empty());
+    }
+
+    #[test]
+    fn test_ramp_up_on_consecutive_hits() {
+        use crate::utils::weapondb::{RampUp, WeaponOverlay, WeaponOverride, WeaponView};
+        let (mut lobby, base) = armed_lobby(1);
+        let ramp_up = RampUp { factor: 1.5, max_multiplier: 2.0, window_secs: 5.0 };
+        let overrides = [(1, WeaponOverride { ramp_up: Some(ramp_up), ..Default::default() })].into_iter().collect();
+        let overlay = WeaponOverlay::resolve(&base, overrides).unwrap();
+        let weapons = WeaponView::new(&base, &overlay);
+
+        for _ in 0..3 {
+            ready_to_fire(&mut lobby);
+            assert!(fire_shot(&mut lobby, &weapons, 1, Some(2)).unwrap());
+        }
+        // 20 + 30 + 40 (capped at 2x)
+        assert_eq!(lobby.players.get(&2).unwrap().current_health, 10);
+        let multipliers: Vec<f32> = lobby.pending_events.iter().filter_map(|e| match e {
+            SyncEvent::HitConfirmed { multiplier, .. } => Some(*multiplier),
+            _ => None,
+        }).collect();
+        assert_eq!(multipliers, vec![1.0, 1.5, 2.0]);
+        // Besides gunfire hints and the victim's damage notices, targeted events go to the attacker only
+        assert!(lobby.pending_events.iter()
+            .filter(|e| !matches!(e, SyncEvent::GunfireNearby { .. } | SyncEvent::PlayerDamaged { .. }))
+            .all(|e| e.recipient().is_none_or(|id| id == 1)));
+    }
+
+    #[test]
+    fn test_gunfire_heard_within_loudness_radius() {
+        let (mut lobby, weapons) = armed_lobby(1);
+        let mut far = Lobby::new_player(3, "Far".to_string(), 1, 20);
+        far.position = (GUNFIRE_AUDIBLE_RADIUS + 1.0, 0.0, 0.0);
+        lobby.players.insert(3, far);
+        lobby.players.get_mut(&2).unwrap().position = (10.0, 0.0, 0.0);
+
+        ready_to_fire(&mut lobby);
+        assert!(fire_shot(&mut lobby, &weapons, 1, None).unwrap());
+        let heard: Vec<u32> = lobby.pending_events.iter().filter_map(|e| match e {
+            SyncEvent::GunfireNearby { to_id, loudness, .. } if *loudness == 1.0 => Some(*to_id),
+            _ => None,
+        }).collect();
+        assert_eq!(heard, vec![2]);
+
+        // Louder weapons carry further
+        let (mut lobby, weapons) = armed_lobby(2);
+        lobby.players.get_mut(&2).unwrap().position = (GUNFIRE_AUDIBLE_RADIUS + 1.0, 0.0, 0.0);
+        assert!(fire_shot(&mut lobby, &weapons, 1, None).unwrap());
+        assert!(lobby.pending_events.iter().any(|e| matches!(e, SyncEvent::GunfireNearby { to_id: 2, .. })));
+    }
+
+    #[test]
+    fn test_sustained_fire_overheats_and_cools() {
+        use crate::utils::weapondb::{Overheat, WeaponOverlay, WeaponOverride, WeaponView};
+        let (mut lobby, base) = armed_lobby(1);
+        let overheat = Overheat { heat_per_shot: 0.5, cool_per_sec: 0.25, lockout_secs: 1.0 };
+        let overrides = [(1, WeaponOverride { overheat: Some(overheat), ..Default::default() })].into_iter().collect();
+        let overlay = WeaponOverlay::resolve(&base, overrides).unwrap();
+        let weapons = WeaponView::new(&base, &overlay);
+
+        for _ in 0..2 {
+            ready_to_fire(&mut lobby);
+            assert!(fire_shot(&mut lobby, &weapons, 1, None).unwrap());
+        }
+        let until = lobby.players[&1].overheated_until.expect("locked out at full heat");
+        ready_to_fire(&mut lobby);
+        assert!(!fire_shot(&mut lobby, &weapons, 1, None).unwrap());
+        assert_eq!(lobby.players[&1].current_ammo, 18); // Not a reload: ammo is untouched
+
+        lobby.clear_dirty();
+        update_heat(&mut lobby, &weapons, 1.0, until);
+        let player = &lobby.players[&1];
+        assert_eq!((player.heat, player.overheated_until), (0.75, None));
+        assert!(player.changes.contains(ChangeMask::AMMO));
+        ready_to_fire(&mut lobby);
+        assert!(fire_shot(&mut lobby, &weapons, 1, None).unwrap());
+    }
+
+    #[test]
+    fn test_projectile_weapon_hits_after_travel() {
+        use crate::utils::weapondb::WeaponData;
+        struct Launcher(WeaponData);
+        impl WeaponLookup for Launcher {
+            fn get(&self, id: u32) -> Option<&WeaponData> {
+                (id == self.0.id).then_some(&self.0)
+            }
+        }
+        let (mut lobby, base) = armed_lobby(1);
+        let mut weapon = base.get(1).unwrap().clone();
+        weapon.projectile_speed = Some(50.0);
+        let weapons = Launcher(weapon);
+        lobby.players.get_mut(&1).unwrap().position = (0.0, 1.0, 0.0);
+        lobby.players.get_mut(&2).unwrap().position = (10.0, 1.0, 0.0);
+
+        assert!(fire_shot(&mut lobby, &weapons, 1, Some(2)).unwrap());
+        assert_eq!(lobby.players.get(&2).unwrap().current_health, 100); // Still in flight
+        assert_eq!(lobby.projectiles.active.len(), 1);
+
+        update_projectiles(&mut lobby, &weapons, 0.1);
+        update_projectiles(&mut lobby, &weapons, 0.1);
+        assert_eq!(lobby.players.get(&2).unwrap().current_health, 80);
+        assert_eq!(lobby.players.get(&1).unwrap().match_stats.shots_hit, 1);
+        assert!(lobby.pending_events.iter().any(|e| matches!(e, SyncEvent::ProjectileEnded { target_id: Some(2), .. })));
+    }
 }
@@ -0,0 +1,336 @@
+use crate::domain::{logic, weapon_bans};
+use crate::domain::pickups::PickupKind;
+use crate::state::lobby::{ChangeMask, Lobby};
+use crate::utils::buffers::SyncEvent;
+use crate::utils::weapondb::WeaponLookup;
+use std::time::{Duration, SystemTime};
+use crate::utils::clock;
+
+/// How close a player must be to a dropped item to pick it up
+pub const PICKUP_RADIUS: f32 = 2.0;
+
+/// Most dropped items lying around per lobby; further drops are skipped
+pub const MAX_LOOT_ITEMS: usize = 64;
+
+type Vec3 = (f32, f32, f32);
+
+/// What a drop table entry spawns
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DropKind {
+    /// Refills the current weapon's magazine
+    Ammo,
+    Health,
+    /// The weapon the victim was holding
+    VictimWeapon,
+}
+
+/// One weighted entry of a drop table
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DropEntry {
+    pub kind: DropKind,
+    pub weight: u32,
+}
+
+/// Chance of a drop on each death, and what it may be
+#[derive(Debug, Clone, PartialEq)]
+pub struct DropTable {
+    pub drop_chance: f32,
+    pub entries: Vec<DropEntry>,
+}
+
+impl DropTable {
+    /// Even odds of a drop, mostly ammo and health
+    pub fn standard() -> Self {
+        Self {
+            drop_chance: 0.5,
+            entries: vec![
+                DropEntry { kind: DropKind::Ammo, weight: 5 },
+                DropEntry { kind: DropKind::Health, weight: 4 },
+                DropEntry { kind: DropKind::VictimWeapon, weight: 1 },
+            ],
+        }
+    }
+}
+
+/// A dropped item as it lies in the world
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LootKind {
+    Ammo,
+    Health,
+    /// Rounds left in its magazine, loaded when picked up
+    Weapon { weapon_id: u32, ammo: u32 },
+}
+
+impl LootKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LootKind::Ammo => "ammo",
+            LootKind::Health => "health",
+            LootKind::Weapon { .. } => "weapon",
+        }
+    }
+}
+
+/// An item dropped where a player died
+#[derive(Debug, Clone, PartialEq)]
+pub struct LootItem {
+    pub id: u32,
+    pub kind: LootKind,
+    pub position: Vec3,
+    pub expires_at: SystemTime,
+}
+
+/// A death still to roll drops for, recorded by `kill_player`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Death {
+    pub victim_id: u32,
+    pub position: Vec3,
+    pub weapon_id: u32,
+    pub ammo: u32,
+}
+
+/// Dropped items in one lobby, and deaths still to roll drops for
+#[derive(Debug, Clone, Default)]
+pub struct LootSet {
+    pub items: Vec<LootItem>,
+    pub pending_deaths: Vec<Death>,
+    /// How long items last, as of the last `update`; also used for weapons swapped out at pickup
+    lifetime: Duration,
+    next_id: u32,
+}
+
+/// Put an item on the ground and tell the lobby, unless too many are lying around already
+fn spawn(lobby: &mut Lobby, kind: LootKind, position: Vec3, now: SystemTime) {
+    let set = &mut lobby.loot;
+    if set.items.len() >= MAX_LOOT_ITEMS {
+        return;
+    }
+    set.next_id = set.next_id.wrapping_add(1);
+    let item_id = set.next_id;
+    set.items.push(LootItem { id: item_id, kind, position, expires_at: now + set.lifetime });
+    let expires_in = set.lifetime.as_secs_f32();
+    lobby.push_event(SyncEvent::LootSpawned { item_id, kind, position, expires_in });
+}
+
+fn distance(a: Vec3, b: Vec3) -> f32 {
+    ((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2) + (a.2 - b.2).powi(2)).sqrt()
+}
+
+/// Pick an entry by weight with the lobby's RNG
+fn roll_entry(lobby: &mut Lobby, table: &DropTable) -> Option<DropKind> {
+    let total: u32 = table.entries.iter().map(|e| e.weight).sum();
+    let mut pick = lobby.rng.below(total);
+    for entry in &table.entries {
+        if pick < entry.weight {
+            return Some(entry.kind);
+        }
+        pick -= entry.weight;
+    }
+    None
+}
+
+/// Drop weapons and roll drops for this tick's deaths, and remove expired items
+/// Lobbies with weapon drops always drop the victim's weapon; with no table nothing else drops
+pub fn update(lobby: &mut Lobby, table: Option<&DropTable>, lifetime: Duration, now: SystemTime) {
+    lobby.loot.lifetime = lifetime;
+    let weapon_drops = lobby.settings.weapon_drops;
+    for death in std::mem::take(&mut lobby.loot.pending_deaths) {
+        let weapon = LootKind::Weapon { weapon_id: death.weapon_id, ammo: death.ammo };
+        if weapon_drops {
+            spawn(lobby, weapon, death.position, now);
+        }
+        let Some(table) = table else {
+            continue;
+        };
+        if lobby.loot.items.len() >= MAX_LOOT_ITEMS || lobby.rng.next_f32() >= table.drop_chance {
+            continue;
+        }
+        let kind = match roll_entry(lobby, table) {
+            Some(DropKind::Ammo) => LootKind::Ammo,
+            Some(DropKind::Health) => LootKind::Health,
+            // Already on the ground when the lobby drops weapons
+            Some(DropKind::VictimWeapon) if weapon_drops => continue,
+            Some(DropKind::VictimWeapon) => weapon,
+            None => continue,
+        };
+        spawn(lobby, kind, death.position, now);
+    }
+
+    let (expired, kept) = std::mem::take(&mut lobby.loot.items).into_iter().partition(|item| item.expires_at <= now);
+    lobby.loot.items = kept;
+    for item in expired {
+        lobby.push_event(SyncEvent::LootRemoved { item_id: item.id, picked_up_by: None });
+    }
+}
+
+/// Pick up a dropped item the player is standing at
+pub fn pick_up(lobby: &mut Lobby, weapons: &impl WeaponLookup, player_id: u32, item_id: u32) -> Result<LootKind, &'static str> {
+    let player = lobby.players.get(&player_id).ok_or("Player not found")?;
+    if player.is_dead || player.spectating {
+        return Err("Player can't pick up items");
+    }
+    let index = lobby.loot.items.iter().position(|item| item.id == item_id).ok_or("Item not found")?;
+    let item = &lobby.loot.items[index];
+    if distance(player.position, item.position) > PICKUP_RADIUS {
+        return Err("Too far from item");
+    }
+
+    let kind = item.kind;
+    match kind {
+        LootKind::Ammo => {
+            let player = lobby.players.get_mut(&player_id).ok_or("Player not found")?;
+            if player.current_ammo >= player.max_ammo {
+                return Err("Ammo already full");
+            }
+            player.current_ammo = player.max_ammo;
+            player.is_reloading = false;
+            player.reload_end_time = None;
+            lobby.mark_changed(player_id, ChangeMask::AMMO | ChangeMask::RELOAD);
+        }
+        LootKind::Health => logic::heal_player(lobby, player_id, PickupKind::Health.amount(), false)?,
+        LootKind::Weapon { weapon_id, ammo } => pick_up_weapon(lobby, weapons, player_id, weapon_id, ammo)?,
+    }
+
+    lobby.loot.items.remove(index);
+    lobby.push_event(SyncEvent::LootRemoved { item_id, picked_up_by: Some(player_id) });
+    Ok(kind)
+}
+
+/// Draw a picked-up weapon with the ammo it was dropped with
+/// Weapons off the lobby's ladder are carried for the rest of this life: one at a time,
+/// so a weapon already carried is dropped in exchange
+fn pick_up_weapon(lobby: &mut Lobby, weapons: &impl WeaponLookup, player_id: u32, weapon_id: u32, ammo: u32) -> Result<(), &'static str> {
+    let player = lobby.players.get(&player_id).ok_or("Player not found")?;
+    let (position, carried) = (player.position, player.carried_weapon);
+    if weapon_bans::is_banned(&lobby.settings, weapon_id) {
+        return Err("Weapon is banned");
+    }
+    let on_ladder = lobby.settings.weapon_ladder.contains(&weapon_id);
+    logic::equip_weapon(lobby, weapons, player_id, weapon_id)?;
+
+    let player = lobby.players.get_mut(&player_id).ok_or("Player not found")?;
+    player.current_ammo = ammo.min(player.max_ammo);
+    if on_ladder {
+        return Ok(());
+    }
+    player.carried_weapon = Some(weapon_id);
+    // Weapons not in hand are kept loaded
+    if let Some(swapped) = carried.filter(|id| *id != weapon_id).and_then(|id| weapons.get(id)) {
+        let kind = LootKind::Weapon { weapon_id: swapped.id, ammo: swapped.ammo };
+        spawn(lobby, kind, position, clock::now());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::lobbies::add_player;
+    use crate::state::lobby::LobbySettings;
+    use crate::utils::rng::SeededRng;
+    use crate::utils::weapondb::WeaponDb;
+
+    const LIFETIME: Duration = Duration::from_secs(30);
+
+    fn lobby_with_players(weapons: &WeaponDb) -> Lobby {
+        let mut lobby = Lobby::new("LOOT".to_string(), 4, "world".to_string());
+        lobby.rng = SeededRng::new(7);
+        add_player(&mut lobby, 1, "Alice".to_string(), 1, weapons).unwrap();
+        add_player(&mut lobby, 2, "Bob".to_string(), 1, weapons).unwrap();
+        lobby
+    }
+
+    fn always(kind: DropKind) -> DropTable {
+        DropTable { drop_chance: 1.0, entries: vec![DropEntry { kind, weight: 1 }] }
+    }
+
+    #[test]
+    fn test_death_drops_victim_weapon_then_expires() {
+        let weapons = WeaponDb::load();
+        let mut lobby = lobby_with_players(&weapons);
+        lobby.players.get_mut(&2).unwrap().position = (4.0, 1.0, 0.0);
+        logic::switch_weapon(&mut lobby, &weapons, 2, 3).unwrap();
+        logic::kill_player(&mut lobby, 2).unwrap();
+
+        let now = SystemTime::now();
+        update(&mut lobby, Some(&always(DropKind::VictimWeapon)), LIFETIME, now);
+        assert!(lobby.loot.pending_deaths.is_empty());
+        assert_eq!(lobby.loot.items.len(), 1);
+        let ammo = lobby.players[&2].current_ammo;
+        assert_eq!(lobby.loot.items[0].kind, LootKind::Weapon { weapon_id: 3, ammo });
+        assert_eq!(lobby.loot.items[0].position, (4.0, 1.0, 0.0));
+        assert!(lobby.pending_events.iter().any(|e| matches!(e, SyncEvent::LootSpawned { kind: LootKind::Weapon { weapon_id: 3, .. }, .. })));
+
+        update(&mut lobby, None, LIFETIME, now + LIFETIME);
+        assert!(lobby.loot.items.is_empty());
+        assert!(matches!(lobby.pending_events.last(), Some(SyncEvent::LootRemoved { picked_up_by: None, .. })));
+    }
+
+    #[test]
+    fn test_dropped_weapon_carried_then_swapped() {
+        let weapons = WeaponDb::load();
+        let settings = LobbySettings { weapon_ladder: vec![1], weapon_drops: true, ..Default::default() };
+        let mut lobby = Lobby::with_settings("LOOT".to_string(), 4, "world".to_string(), settings);
+        add_player(&mut lobby, 1, "Alice".to_string(), 1, &weapons).unwrap();
+        add_player(&mut lobby, 2, "Bob".to_string(), 1, &weapons).unwrap();
+        logic::equip_weapon(&mut lobby, &weapons, 2, 2).unwrap();
+        lobby.players.get_mut(&2).unwrap().current_ammo = 5;
+        logic::kill_player(&mut lobby, 2).unwrap();
+
+        let now = SystemTime::now();
+        update(&mut lobby, None, LIFETIME, now);
+        let item_id = lobby.loot.items[0].id;
+        assert_eq!(lobby.loot.items[0].kind, LootKind::Weapon { weapon_id: 2, ammo: 5 });
+        assert_eq!(pick_up(&mut lobby, &weapons, 1, item_id), Ok(LootKind::Weapon { weapon_id: 2, ammo: 5 }));
+        assert_eq!((lobby.players[&1].current_weapon_id, lobby.players[&1].current_ammo), (2, 5));
+        assert_eq!(logic::cycle_weapon(&mut lobby, &weapons, 1, true), Ok(1));
+        assert_eq!(logic::cycle_weapon(&mut lobby, &weapons, 1, true), Ok(2));
+
+        // Only one weapon is carried: picking up another drops the first, loaded
+        lobby.loot.pending_deaths.push(Death { victim_id: 2, position: (0.0, 1.0, 0.0), weapon_id: 4, ammo: 3 });
+        update(&mut lobby, None, LIFETIME, now);
+        let item_id = lobby.loot.items[0].id;
+        pick_up(&mut lobby, &weapons, 1, item_id).unwrap();
+        assert_eq!(lobby.players[&1].carried_weapon, Some(4));
+        assert_eq!(lobby.loot.items.last().unwrap().kind, LootKind::Weapon { weapon_id: 2, ammo: weapons.get(2).unwrap().ammo });
+        assert_eq!(logic::switch_weapon(&mut lobby, &weapons, 1, 2), Err("Weapon not owned"));
+
+        logic::kill_player(&mut lobby, 1).unwrap();
+        logic::respawn_player(&mut lobby, 1).unwrap();
+        assert_eq!(logic::rearm(&mut lobby, &weapons, 1), Ok(Some(1)));
+    }
+
+    #[test]
+    fn test_pick_up_needs_proximity() {
+        let weapons = WeaponDb::load();
+        let mut lobby = lobby_with_players(&weapons);
+        lobby.players.get_mut(&2).unwrap().position = (10.0, 1.0, 0.0);
+        logic::kill_player(&mut lobby, 2).unwrap();
+        update(&mut lobby, Some(&always(DropKind::Ammo)), LIFETIME, SystemTime::now());
+        let item_id = lobby.loot.items[0].id;
+
+        lobby.players.get_mut(&1).unwrap().current_ammo = 2;
+        assert_eq!(pick_up(&mut lobby, &weapons, 1, item_id), Err("Too far from item"));
+        lobby.players.get_mut(&1).unwrap().position = (9.0, 1.0, 0.5);
+        assert_eq!(pick_up(&mut lobby, &weapons, 1, item_id), Ok(LootKind::Ammo));
+        assert_eq!(lobby.players[&1].current_ammo, lobby.players[&1].max_ammo);
+        assert_eq!(pick_up(&mut lobby, &weapons, 1, item_id), Err("Item not found"));
+    }
+
+    #[test]
+    fn test_drops_follow_the_seed() {
+        let weapons = WeaponDb::load();
+        let rolls = |seed| {
+            let mut lobby = lobby_with_players(&weapons);
+            lobby.rng = SeededRng::new(seed);
+            for _ in 0..20 {
+                lobby.loot.pending_deaths.push(Death { victim_id: 2, position: (0.0, 1.0, 0.0), weapon_id: 1, ammo: 20 });
+            }
+            update(&mut lobby, Some(&DropTable::standard()), LIFETIME, SystemTime::now());
+            lobby.loot.items.iter().map(|item| item.kind).collect::<Vec<_>>()
+        };
+        assert_eq!(rolls(99), rolls(99));
+        let dropped = rolls(99).len();
+        assert!(dropped > 0 && dropped < 20);
+    }
+}
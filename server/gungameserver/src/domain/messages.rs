@@ -0,0 +1,120 @@
+use crate::domain::{afk, votes};
+use serde_json::{json, Value};
+
+/// Capability clients list at join once they show `<field>_key` messages themselves
+/// Without it, packets also carry the English text in the old field
+pub const MESSAGE_KEYS_CAPABILITY: &str = "message_keys";
+
+/// Reason sent when an admin kicks without giving one
+pub const ADMIN_KICK_REASON: &str = "Kicked by admin";
+
+/// Suffixes of the fields a message is written to, next to its fallback field
+const KEY_SUFFIX: &str = "_key";
+const PARAMS_SUFFIX: &str = "_params";
+
+/// Server-generated text as a localization key and parameters,
+/// with the English wording for clients that can't localize
+#[derive(Debug, Clone, PartialEq)]
+pub struct Message {
+    pub key: &'static str,
+    pub params: Value,
+    pub fallback: String,
+}
+
+impl Message {
+    pub fn new(key: &'static str, fallback: impl Into<String>) -> Self {
+        Self { key, params: json!({}), fallback: fallback.into() }
+    }
+
+    pub fn with_param(mut self, name: &str, value: impl Into<Value>) -> Self {
+        self.params[name] = value.into();
+        self
+    }
+
+    /// Add to `packet` as `<field>_key` and `<field>_params`, and as English text in `field`
+    /// for clients without the message keys capability
+    pub fn write(&self, packet: &mut Value, field: &str, fallback: bool) {
+        packet[format!("{}{}", field, KEY_SUFFIX)] = json!(self.key);
+        packet[format!("{}{}", field, PARAMS_SUFFIX)] = self.params.clone();
+        if fallback {
+            packet[field] = json!(self.fallback);
+        }
+    }
+}
+
+/// Copy of `packet` with the English fallback text removed, for clients that localize
+/// None if it has none to remove
+pub fn without_fallback(packet: &Value) -> Option<Value> {
+    let fields = packet.as_object()?;
+    let fallbacks: Vec<&str> = fields.keys()
+        .filter_map(|name| name.strip_suffix(KEY_SUFFIX))
+        .filter(|field| fields.contains_key(*field) && fields.contains_key(&format!("{}{}", field, PARAMS_SUFFIX)))
+        .collect();
+    if fallbacks.is_empty() {
+        return None;
+    }
+    let mut stripped = packet.clone();
+    let object = stripped.as_object_mut()?;
+    for field in fallbacks {
+        object.remove(field);
+    }
+    Some(stripped)
+}
+
+/// Whether a client's capabilities leave it needing English fallback text
+pub fn needs_fallback(capabilities: &[&str]) -> bool {
+    !capabilities.contains(&MESSAGE_KEYS_CAPABILITY)
+}
+
+/// Greeting sent once a client is attached to its lobby
+pub fn welcome() -> Message {
+    Message::new("welcome.connected", "Connected to lobby")
+}
+
+/// Why a player was removed; reasons given by admins travel as a parameter
+pub fn kick(reason: &str) -> Message {
+    match reason {
+        afk::AFK_KICK_REASON => Message::new("kick.afk", reason),
+        votes::VOTE_KICK_REASON => Message::new("kick.vote", reason),
+        ADMIN_KICK_REASON => Message::new("kick.admin", reason),
+        _ => Message::new("kick.admin_reason", reason).with_param("reason", reason),
+    }
+}
+
+/// An admin announcement; its text is shown as written
+pub fn announcement(text: &str) -> Message {
+    Message::new("announcement", text).with_param("text", text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_message_written_with_optional_fallback() {
+        let mut packet = json!({ "type": "player_kicked", "player_id": 2 });
+        kick("griefing").write(&mut packet, "reason", true);
+        assert_eq!(packet["reason_key"], "kick.admin_reason");
+        assert_eq!(packet["reason_params"]["reason"], "griefing");
+        assert_eq!(packet["reason"], "griefing");
+
+        let stripped = without_fallback(&packet).unwrap();
+        assert!(stripped.get("reason").is_none());
+        assert_eq!(stripped["reason_key"], "kick.admin_reason");
+        assert!(without_fallback(&stripped).is_none());
+        assert!(without_fallback(&json!({ "type": "pong" })).is_none());
+
+        let mut packet = json!({ "type": "welcome" });
+        welcome().write(&mut packet, "message", false);
+        assert_eq!(packet, json!({ "type": "welcome", "message_key": "welcome.connected", "message_params": {} }));
+    }
+
+    #[test]
+    fn test_known_kick_reasons_have_their_own_keys() {
+        assert_eq!(kick(afk::AFK_KICK_REASON).key, "kick.afk");
+        assert_eq!(kick(votes::VOTE_KICK_REASON).key, "kick.vote");
+        assert_eq!(kick(ADMIN_KICK_REASON).params, json!({}));
+        assert!(needs_fallback(&[]));
+        assert!(!needs_fallback(&[MESSAGE_KEYS_CAPABILITY]));
+    }
+}
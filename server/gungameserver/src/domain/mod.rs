@@ -1,4 +1,43 @@
 pub mod lobbies;
 pub mod logic;
 pub mod simulator;
+pub mod rating;
+pub mod chat;
+pub mod chat_commands;
+pub mod pickups;
+pub mod loot;
+pub mod latency;
+pub mod votes;
+pub mod analytics;
+pub mod history;
+pub mod ramp_up;
+pub mod clock_sync;
+pub mod projectiles;
+pub mod zone_control;
+pub mod duel;
+pub mod timeline;
+pub mod damage_log;
+pub mod validation;
+pub mod bots;
+pub mod tournament;
+pub mod interest;
+pub mod emotes;
+pub mod pellets;
+pub mod scripting;
+pub mod awards;
+pub mod falloff;
+pub mod streaming;
+pub mod messages;
+pub mod timers;
+pub mod observers;
+pub mod teams;
+pub mod connectivity;
+pub mod damage_share;
+pub mod weapon_bans;
+pub mod preferences;
+pub mod armor;
 
+pub mod rotation;
+pub mod afk;
+pub mod lifetime;
+pub mod rebalance;
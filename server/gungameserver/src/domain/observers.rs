@@ -0,0 +1,143 @@
+use crate::state::lobby::Lobby;
+use serde_json::json;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::time::{Duration, SystemTime};
+
+/// Snapshots per second sent to each observer
+pub const OBSERVER_SNAPSHOT_HZ: u32 = 10;
+
+/// Observers one lobby will feed at once
+pub const MAX_OBSERVERS: usize = 16;
+
+/// Observers that haven't re-sent `observe` for this long stop getting snapshots
+pub const OBSERVER_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Observer clients of a lobby: overlays and big-screen spectator displays that get a summary
+/// snapshot at `OBSERVER_SNAPSHOT_HZ` instead of the per-event stream players get
+#[derive(Debug, Default)]
+pub struct Observers {
+    last_seen: HashMap<SocketAddr, SystemTime>,
+    last_snapshot: Option<SystemTime>,
+    // Answers to `observe` requests, sent by the tick loop
+    replies: Vec<(SocketAddr, Result<(), &'static str>)>,
+}
+
+impl Observers {
+    /// Start or keep feeding an observer; the answer goes out with the next snapshot round
+    pub fn add(&mut self, addr: SocketAddr, now: SystemTime) {
+        let result = if self.last_seen.contains_key(&addr) || self.last_seen.len() < MAX_OBSERVERS {
+            self.last_seen.insert(addr, now);
+            Ok(())
+        } else {
+            Err("Too many observers")
+        };
+        self.replies.push((addr, result));
+    }
+
+    pub fn remove(&mut self, addr: SocketAddr) -> bool {
+        self.last_seen.remove(&addr).is_some()
+    }
+
+    pub fn len(&self) -> usize {
+        self.last_seen.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.last_seen.is_empty()
+    }
+
+    /// Answers to `observe` requests since the last call
+    pub fn take_replies(&mut self) -> Vec<(SocketAddr, Result<(), &'static str>)> {
+        std::mem::take(&mut self.replies)
+    }
+
+    /// Drop observers that timed out, then the addresses to send a snapshot now, if one is due
+    pub fn due(&mut self, now: SystemTime) -> Vec<SocketAddr> {
+        self.last_seen.retain(|_, seen| now.duration_since(*seen).unwrap_or_default() < OBSERVER_TIMEOUT);
+        let interval = Duration::from_secs(1) / OBSERVER_SNAPSHOT_HZ;
+        let waiting = self.last_snapshot.is_some_and(|at| now.duration_since(at).unwrap_or_default() < interval);
+        if self.last_seen.is_empty() || waiting {
+            return Vec::new();
+        }
+        self.last_snapshot = Some(now);
+        self.last_seen.keys().copied().collect()
+    }
+}
+
+/// One packet summing up the lobby: every player's position and score, and the match clock
+pub fn snapshot(lobby: &Lobby) -> serde_json::Value {
+    let mut players: Vec<_> = lobby.players.values().collect();
+    players.sort_by_key(|p| p.id);
+    let players: Vec<serde_json::Value> = players.iter()
+        .map(|p| json!({
+            "id": p.id,
+            "name": p.name,
+            "position": [p.position.0, p.position.1, p.position.2],
+            "rotation": [p.rotation.0, p.rotation.1, p.rotation.2],
+            "health": p.current_health,
+            "score": p.score,
+            "kills": p.kills,
+            "deaths": p.deaths,
+            "is_dead": p.is_dead
+        }))
+        .collect();
+    // Seconds into the match; matches still waiting or counting down have none
+    let match_time = lobby.timeline.as_ref()
+        .map(|timeline| (timeline.match_tick(lobby.current_tick) * lobby.tick_interval_ms) as f32 / 1000.0);
+    json!({
+        "type": "observer_snapshot",
+        "tick": lobby.current_tick,
+        "phase": lobby.phase,
+        "countdown_remaining": lobby.countdown_remaining,
+        "match_time": match_time,
+        "paused": lobby.is_paused(),
+        "players": players
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addr(port: u16) -> SocketAddr {
+        format!("127.0.0.1:{}", port).parse().unwrap()
+    }
+
+    #[test]
+    fn test_snapshots_limited_to_rate_and_capacity() {
+        let now = SystemTime::now();
+        let mut observers = Observers::default();
+        for port in 0..=MAX_OBSERVERS as u16 {
+            observers.add(addr(7000 + port), now);
+        }
+        let replies = observers.take_replies();
+        assert_eq!(replies.iter().filter(|(_, r)| r.is_err()).count(), 1);
+        assert_eq!(observers.len(), MAX_OBSERVERS);
+
+        assert_eq!(observers.due(now).len(), MAX_OBSERVERS);
+        assert!(observers.due(now + Duration::from_millis(50)).is_empty());
+        assert_eq!(observers.due(now + Duration::from_millis(100)).len(), MAX_OBSERVERS);
+
+        // Only the one that kept re-sending `observe` is still fed
+        observers.add(addr(7000), now + Duration::from_secs(20));
+        assert_eq!(observers.due(now + Duration::from_secs(31)), vec![addr(7000)]);
+        assert!(observers.remove(addr(7000)));
+        assert!(observers.is_empty());
+    }
+
+    #[test]
+    fn test_snapshot_lists_players_in_order() {
+        let mut lobby = Lobby::new("TEST".to_string(), 4, "world".to_string());
+        for id in [2, 1] {
+            lobby.players.insert(id, Lobby::new_player(id, format!("P{}", id), 1, 20));
+        }
+        lobby.players.get_mut(&2).unwrap().score = 300;
+        let packet = snapshot(&lobby);
+        assert_eq!(packet["type"], "observer_snapshot");
+        assert_eq!(packet["phase"], "waiting");
+        assert!(packet["match_time"].is_null());
+        assert_eq!(packet["players"][0]["id"], 1);
+        assert_eq!(packet["players"][1]["score"], 300);
+    }
+}
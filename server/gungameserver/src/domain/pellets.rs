@@ -0,0 +1,109 @@
+use crate::state::lobby::Lobby;
+use crate::utils::weapondb::WeaponData;
+
+type Vec3 = (f32, f32, f32);
+
+/// Radius around a player's position a pellet has to land within
+const HITBOX_RADIUS: f32 = 0.5;
+
+fn distance(a: Vec3, b: Vec3) -> f32 {
+    ((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2) + (a.2 - b.2).powi(2)).sqrt()
+}
+
+/// Chance one pellet aimed at a target this far away lands, with pellets spread
+/// evenly over a cone of `spread` degrees (half-angle)
+pub fn hit_chance(distance: f32, spread: f32) -> f32 {
+    let cone_radius = distance * spread.to_radians().tan();
+    if cone_radius <= HITBOX_RADIUS {
+        1.0
+    } else {
+        (HITBOX_RADIUS / cone_radius).powi(2)
+    }
+}
+
+/// Pellets of one shot that landed, as (target_id, pellets) in order of first hit
+///
+/// Clients may report which player each pellet hit; reports are capped at the weapon's
+/// pellet count and only count for players in range. Without a report the server rolls
+/// each pellet aimed at `target_id` against the spread.
+pub fn resolve(lobby: &mut Lobby, weapon: &WeaponData, shooter_id: u32, target_id: u32, reported: &[u32]) -> Vec<(u32, u32)> {
+    let Some(origin) = lobby.players.get(&shooter_id).map(|p| p.position) else {
+        return Vec::new();
+    };
+    let in_range = |lobby: &Lobby, id: u32| {
+        id != shooter_id && lobby.players.get(&id).is_some_and(|p| distance(origin, p.position) <= weapon.range)
+    };
+
+    let mut hits: Vec<(u32, u32)> = Vec::new();
+    if !reported.is_empty() {
+        for &id in reported.iter().take(weapon.pellets as usize) {
+            if !in_range(lobby, id) {
+                continue;
+            }
+            match hits.iter_mut().find(|(target, _)| *target == id) {
+                Some((_, count)) => *count += 1,
+                None => hits.push((id, 1)),
+            }
+        }
+        return hits;
+    }
+
+    if !in_range(lobby, target_id) {
+        return hits;
+    }
+    let chance = hit_chance(distance(origin, lobby.players[&target_id].position), weapon.spread);
+    let landed = (0..weapon.pellets).filter(|_| lobby.rng.next_f32() < chance).count() as u32;
+    if landed > 0 {
+        hits.push((target_id, landed));
+    }
+    hits
+}
+
+/// Split the damage one target took from a shot over the pellets that hit it
+/// (the first pellets carry any remainder)
+pub fn split(damage: u32, pellets: u32) -> Vec<u32> {
+    let pellets = pellets.max(1);
+    (0..pellets).map(|i| damage / pellets + u32::from(i < damage % pellets)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::weapondb::WeaponDb;
+
+    fn lobby() -> Lobby {
+        let mut lobby = Lobby::new("TEST".to_string(), 8, "world".to_string());
+        for (id, x) in [(1, 0.0), (2, 2.0), (3, 4.0), (4, 200.0)] {
+            let mut player = Lobby::new_player(id, format!("P{}", id), 4, 6);
+            player.position = (x, 0.0, 0.0);
+            lobby.players.insert(id, player);
+        }
+        lobby
+    }
+
+    #[test]
+    fn test_reported_pellets_capped_and_range_checked() {
+        let mut lobby = lobby();
+        let scattergun = WeaponDb::load().get(4).unwrap().clone();
+        let reported = [2, 3, 2, 1, 4, 99, 2, 3, 2, 2, 2];
+        // Self, out of range and unknown ids are dropped; pellets past the eighth are ignored
+        assert_eq!(resolve(&mut lobby, &scattergun, 1, 2, &reported), vec![(2, 3), (3, 2)]);
+    }
+
+    #[test]
+    fn test_simulated_spread_falls_off_with_distance() {
+        assert_eq!(hit_chance(2.0, 6.0), 1.0);
+        assert!(hit_chance(20.0, 6.0) < 0.3);
+
+        let mut lobby = lobby();
+        let scattergun = WeaponDb::load().get(4).unwrap().clone();
+        assert_eq!(resolve(&mut lobby, &scattergun, 1, 2, &[]), vec![(2, 8)]);
+        assert!(resolve(&mut lobby, &scattergun, 1, 4, &[]).is_empty());
+    }
+
+    #[test]
+    fn test_split_damage() {
+        assert_eq!(split(100, 8), vec![13, 13, 13, 13, 12, 12, 12, 12]);
+        assert_eq!(split(20, 1), vec![20]);
+    }
+}
@@ -0,0 +1,254 @@
+use crate::domain::{armor, logic};
+use crate::domain::loot::PICKUP_RADIUS;
+use crate::state::lobby::Lobby;
+use crate::utils::buffers::SyncEvent;
+use crate::utils::scenes::SceneData;
+use std::time::{Duration, SystemTime};
+
+/// Items a player can pick up in the world
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum PickupKind {
+    /// Restores health up to max_health
+    Health,
+    /// Grants temporary health above max_health that decays over time
+    Overheal,
+    /// Adds armor, up to the armor cap
+    Armor,
+}
+
+impl PickupKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PickupKind::Health => "health",
+            PickupKind::Overheal => "overheal",
+            PickupKind::Armor => "armor",
+        }
+    }
+
+    /// Health (or armor) granted by the pickup
+    pub fn amount(&self) -> u32 {
+        match self {
+            PickupKind::Health => 25,
+            PickupKind::Overheal => 50,
+            PickupKind::Armor => 50,
+        }
+    }
+}
+
+/// A pickup placed by the scene; it comes back `respawn` after being taken
+#[derive(Debug, Clone, PartialEq)]
+pub struct WorldPickup {
+    pub id: u32,
+    pub kind: PickupKind,
+    pub position: (f32, f32, f32),
+    pub respawn: Duration,
+    /// When a taken pickup comes back; None while it can be picked up
+    pub available_at: Option<SystemTime>,
+}
+
+impl WorldPickup {
+    pub fn is_available(&self) -> bool {
+        self.available_at.is_none()
+    }
+}
+
+/// The scene's pickups in one lobby
+#[derive(Debug, Clone, Default)]
+pub struct PickupSet {
+    pub items: Vec<WorldPickup>,
+}
+
+impl PickupSet {
+    /// Every pickup the scene places, all available, numbered from 1 in scene order
+    pub fn for_scene(scene: &SceneData) -> Self {
+        let items = scene.pickups.iter().zip(1..)
+            .map(|(spawn, id)| WorldPickup {
+                id,
+                kind: spawn.kind,
+                position: spawn.position,
+                respawn: Duration::from_secs(spawn.respawn_secs),
+                available_at: None,
+            })
+            .collect();
+        Self { items }
+    }
+}
+
+/// Apply a pickup's effect to a player; only `take` grants pickups, after checking the item
+fn apply_pickup(lobby: &mut Lobby, player_id: u32, kind: PickupKind) -> Result<(), &'static str> {
+    if kind == PickupKind::Armor {
+        return armor::grant(lobby, player_id, kind.amount());
+    }
+    let overheal = kind == PickupKind::Overheal;
+    logic::heal_player(lobby, player_id, kind.amount(), overheal)
+}
+
+/// Take a scene pickup the player is standing at; it goes on cooldown until it respawns
+/// Pickups that would not change anything are left for someone else
+pub fn take(lobby: &mut Lobby, player_id: u32, pickup_id: u32, now: SystemTime) -> Result<PickupKind, &'static str> {
+    let player = lobby.players.get(&player_id).ok_or("Player not found")?;
+    if player.is_dead || player.spectating {
+        return Err("Player can't pick up items");
+    }
+    let before = (player.current_health, player.armor);
+    let position = player.position;
+    let pickup = lobby.pickups.items.iter().find(|p| p.id == pickup_id).ok_or("Pickup not found")?;
+    if !pickup.is_available() {
+        return Err("Pickup not available");
+    }
+    if distance(position, pickup.position) > PICKUP_RADIUS {
+        return Err("Too far from pickup");
+    }
+
+    let kind = pickup.kind;
+    apply_pickup(lobby, player_id, kind)?;
+    let player = lobby.players.get(&player_id).ok_or("Player not found")?;
+    if (player.current_health, player.armor) == before {
+        return Err("Already full");
+    }
+
+    let pickup = lobby.pickups.items.iter_mut().find(|p| p.id == pickup_id).ok_or("Pickup not found")?;
+    pickup.available_at = Some(now + pickup.respawn);
+    let respawn_in = pickup.respawn.as_secs_f32();
+    lobby.push_event(SyncEvent::PickupTaken { pickup_id, player_id, respawn_in });
+    Ok(kind)
+}
+
+/// Bring back pickups whose cooldown has run out
+pub fn update(lobby: &mut Lobby, now: SystemTime) {
+    let mut respawned = Vec::new();
+    for pickup in &mut lobby.pickups.items {
+        if pickup.available_at.is_some_and(|at| at <= now) {
+            pickup.available_at = None;
+            respawned.push(pickup.id);
+        }
+    }
+    for pickup_id in respawned {
+        lobby.push_event(SyncEvent::PickupRespawned { pickup_id });
+    }
+}
+
+/// The lobby's pickups as sent to clients on join
+pub fn list_json(lobby: &Lobby, now: SystemTime) -> Vec<serde_json::Value> {
+    lobby.pickups.items.iter()
+        .map(|pickup| serde_json::json!({
+            "pickup_id": pickup.id,
+            "kind": pickup.kind.as_str(),
+            "position": { "x": pickup.position.0, "y": pickup.position.1, "z": pickup.position.2 },
+            "respawn_in": pickup.available_at
+                .map(|at| at.duration_since(now).unwrap_or_default().as_secs_f32())
+                .unwrap_or(0.0)
+        }))
+        .collect()
+}
+
+fn distance(a: (f32, f32, f32), b: (f32, f32, f32)) -> f32 {
+    ((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2) + (a.2 - b.2).powi(2)).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::lobbies::add_player;
+    use crate::utils::weapondb::WeaponDb;
+
+    #[test]
+    fn test_pickup_kind_names() {
+        assert_eq!(PickupKind::Health.as_str(), "health");
+        assert_eq!(PickupKind::Overheal.as_str(), "overheal");
+        assert_eq!(PickupKind::Armor.as_str(), "armor");
+    }
+
+    #[test]
+    fn test_apply_pickup() {
+        let mut lobby = Lobby::new("TEST".to_string(), 4, "world".to_string());
+        let weapons = WeaponDb::load();
+        add_player(&mut lobby, 1, "Test".to_string(), 1, &weapons).unwrap();
+        lobby.players.get_mut(&1).unwrap().current_health = 90;
+
+        // Health pickups never exceed max health
+        apply_pickup(&mut lobby, 1, PickupKind::Health).unwrap();
+        assert_eq!(lobby.players.get(&1).unwrap().current_health, 100);
+
+        apply_pickup(&mut lobby, 1, PickupKind::Overheal).unwrap();
+        assert_eq!(lobby.players.get(&1).unwrap().current_health, 150);
+
+        for _ in 0..3 {
+            apply_pickup(&mut lobby, 1, PickupKind::Armor).unwrap();
+        }
+        assert_eq!(lobby.players.get(&1).unwrap().armor, armor::MAX_ARMOR);
+    }
+
+    /// One pickup of `kind` at the origin, and player 1 standing on it
+    fn lobby_with_pickup(kind: PickupKind) -> Lobby {
+        let mut lobby = Lobby::new("TEST".to_string(), 4, "world".to_string());
+        let weapons = WeaponDb::load();
+        add_player(&mut lobby, 1, "Test".to_string(), 1, &weapons).unwrap();
+        lobby.players.get_mut(&1).unwrap().position = (0.0, 0.0, 0.0);
+        lobby.pickups.items = vec![WorldPickup {
+            id: 1,
+            kind,
+            position: (0.5, 0.0, 0.5),
+            respawn: Duration::from_secs(20),
+            available_at: None,
+        }];
+        lobby
+    }
+
+    #[test]
+    fn test_take_consumes_until_respawn() {
+        let mut lobby = lobby_with_pickup(PickupKind::Health);
+        lobby.players.get_mut(&1).unwrap().current_health = 50;
+        let now = SystemTime::now();
+
+        assert_eq!(take(&mut lobby, 1, 1, now), Ok(PickupKind::Health));
+        assert_eq!(lobby.players[&1].current_health, 75);
+        assert!(lobby.pending_events.iter().any(|e| matches!(e, SyncEvent::PickupTaken { pickup_id: 1, player_id: 1, .. })));
+
+        // Taking it again before it respawns does nothing
+        assert_eq!(take(&mut lobby, 1, 1, now), Err("Pickup not available"));
+        assert_eq!(lobby.players[&1].current_health, 75);
+
+        update(&mut lobby, now + Duration::from_secs(19));
+        assert!(!lobby.pickups.items[0].is_available());
+        update(&mut lobby, now + Duration::from_secs(20));
+        assert!(lobby.pickups.items[0].is_available());
+        assert!(lobby.pending_events.iter().any(|e| matches!(e, SyncEvent::PickupRespawned { pickup_id: 1 })));
+        assert_eq!(take(&mut lobby, 1, 1, now + Duration::from_secs(20)), Ok(PickupKind::Health));
+    }
+
+    #[test]
+    fn test_take_rejects_far_missing_and_useless_pickups() {
+        let mut lobby = lobby_with_pickup(PickupKind::Armor);
+        let now = SystemTime::now();
+        lobby.players.get_mut(&1).unwrap().position = (10.0, 0.0, 0.0);
+        assert_eq!(take(&mut lobby, 1, 1, now), Err("Too far from pickup"));
+        assert_eq!(take(&mut lobby, 1, 2, now), Err("Pickup not found"));
+        assert_eq!(lobby.players[&1].armor, 0);
+        assert!(lobby.pickups.items[0].is_available());
+
+        // A full player leaves the pickup where it is
+        lobby.players.get_mut(&1).unwrap().position = (0.0, 0.0, 0.0);
+        lobby.players.get_mut(&1).unwrap().armor = armor::MAX_ARMOR;
+        assert_eq!(take(&mut lobby, 1, 1, now), Err("Already full"));
+        assert!(lobby.pickups.items[0].is_available());
+    }
+
+    #[test]
+    fn test_armor_pickup_granted_once_per_respawn() {
+        let mut lobby = lobby_with_pickup(PickupKind::Armor);
+        let now = SystemTime::now();
+        assert_eq!(take(&mut lobby, 1, 1, now), Ok(PickupKind::Armor));
+        for _ in 0..5 {
+            assert_eq!(take(&mut lobby, 1, 1, now), Err("Pickup not available"));
+        }
+        assert_eq!(lobby.players[&1].armor, PickupKind::Armor.amount());
+    }
+
+    #[test]
+    fn test_scene_pickups_numbered_in_order() {
+        let set = PickupSet::for_scene(&crate::utils::scenes::scene_data("arena"));
+        assert!(!set.items.is_empty());
+        assert!(set.items.iter().zip(1..).all(|(pickup, id)| pickup.id == id && pickup.is_available()));
+    }
+}
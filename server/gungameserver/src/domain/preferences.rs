@@ -0,0 +1,98 @@
+use crate::domain::chat;
+use crate::utils::buffers::SyncEvent;
+
+/// Packet classes a client asked at join not to be sent
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Preferences(u8);
+
+impl Preferences {
+    /// No chat, whispers or channel messages from other players
+    pub const NO_CHAT: Preferences = Preferences(1 << 0);
+    /// No emotes played by other players
+    pub const NO_EMOTES: Preferences = Preferences(1 << 1);
+    /// Kill feed entries only for kills the client was part of
+    pub const MINIMAL_KILL_FEED: Preferences = Preferences(1 << 2);
+
+    const NAMES: [(&'static str, Preferences); 3] = [
+        ("no_chat", Preferences::NO_CHAT),
+        ("no_emotes", Preferences::NO_EMOTES),
+        ("minimal_kill_feed", Preferences::MINIMAL_KILL_FEED),
+    ];
+
+    /// Preferences from a join packet's `preferences` list; unknown names are ignored
+    pub fn from_packet(packet: &serde_json::Value) -> Self {
+        let requested = packet.get("preferences").and_then(|v| v.as_array());
+        let mut preferences = Preferences::default();
+        for (name, flag) in Self::NAMES {
+            if requested.is_some_and(|list| list.iter().any(|p| p.as_str() == Some(name))) {
+                preferences.0 |= flag.0;
+            }
+        }
+        preferences
+    }
+
+    /// Names of the preferences set, as echoed back in the welcome
+    pub fn names(&self) -> Vec<&'static str> {
+        Self::NAMES.into_iter()
+            .filter(|(_, flag)| self.contains(*flag))
+            .map(|(name, _)| name)
+            .collect()
+    }
+
+    pub fn contains(&self, other: Preferences) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+/// Whether a client with `preferences` still gets `event`
+/// What the client itself said or did, and anything from the server, always gets through
+pub fn wants(preferences: Preferences, recipient_id: u32, event: &SyncEvent) -> bool {
+    let from_other = |from_id: u32| from_id != recipient_id && from_id != chat::SERVER_SENDER_ID;
+    match event {
+        SyncEvent::ChatMessage { from_id, .. }
+        | SyncEvent::Whisper { from_id, .. }
+        | SyncEvent::ChannelMessage { from_id, .. } => {
+            !(preferences.contains(Preferences::NO_CHAT) && from_other(*from_id))
+        }
+        SyncEvent::EmotePlayed { player_id, .. } => {
+            !(preferences.contains(Preferences::NO_EMOTES) && *player_id != recipient_id)
+        }
+        SyncEvent::PlayerKilled { killer_id, victim_id, .. } => {
+            !preferences.contains(Preferences::MINIMAL_KILL_FEED) || recipient_id == *killer_id || recipient_id == *victim_id
+        }
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_preferences_filter_their_packet_classes() {
+        let preferences = Preferences::from_packet(&serde_json::json!({ "preferences": ["no_chat", "minimal_kill_feed", "no_ads"] }));
+        assert_eq!(preferences.names(), vec!["no_chat", "minimal_kill_feed"]);
+
+        let chat = |from_id| SyncEvent::ChatMessage { from_id, from_name: "A".to_string(), text: "hi".to_string() };
+        assert!(!wants(preferences, 1, &chat(2)));
+        assert!(wants(preferences, 1, &chat(1)));
+        assert!(wants(preferences, 1, &chat(chat::SERVER_SENDER_ID)));
+
+        let kill = |killer_id, victim_id| SyncEvent::PlayerKilled {
+            killer_id,
+            killer_name: "A".to_string(),
+            victim_id,
+            victim_name: "B".to_string(),
+            weapon_id: 1,
+            weapon_name: "Pistol".to_string(),
+            killer_killstreak: 1,
+        };
+        assert!(!wants(preferences, 1, &kill(2, 3)));
+        assert!(wants(preferences, 1, &kill(2, 1)));
+
+        let emote = SyncEvent::EmotePlayed { to_id: 1, player_id: 2, emote: "wave", duration: 1.0 };
+        assert!(wants(preferences, 1, &emote));
+        assert!(!wants(Preferences::NO_EMOTES, 1, &emote));
+        assert!(wants(Preferences::default(), 1, &kill(2, 3)));
+    }
+}
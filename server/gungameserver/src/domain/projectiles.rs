@@ -0,0 +1,201 @@
+use crate::state::lobby::Lobby;
+
+/// Downward acceleration applied to projectiles (units/s²)
+pub const GRAVITY: f32 = 9.81;
+
+/// Distance from a player's position that counts as a projectile hit
+pub const HIT_RADIUS: f32 = 1.0;
+
+/// Most projectiles in flight per lobby; further shots fizzle
+pub const MAX_PROJECTILES: usize = 256;
+
+type Vec3 = (f32, f32, f32);
+
+/// A server-simulated shot from a weapon with a `projectile_speed`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Projectile {
+    pub id: u32,
+    pub owner_id: u32,
+    pub weapon_id: u32,
+    pub position: Vec3,
+    pub velocity: Vec3,
+    pub travelled: f32,
+    pub max_range: f32,
+}
+
+/// Projectiles in flight in one lobby
+#[derive(Debug, Clone, Default)]
+pub struct ProjectileSet {
+    pub active: Vec<Projectile>,
+    next_id: u32,
+}
+
+/// How a projectile's flight ended this tick
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ProjectileOutcome {
+    Hit { target_id: u32 },
+    Expired,
+}
+
+/// A projectile that stopped this tick
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProjectileEnd {
+    pub projectile_id: u32,
+    pub owner_id: u32,
+    pub weapon_id: u32,
+    pub outcome: ProjectileOutcome,
+}
+
+fn add(a: Vec3, b: Vec3) -> Vec3 {
+    (a.0 + b.0, a.1 + b.1, a.2 + b.2)
+}
+
+fn sub(a: Vec3, b: Vec3) -> Vec3 {
+    (a.0 - b.0, a.1 - b.1, a.2 - b.2)
+}
+
+fn scale(a: Vec3, s: f32) -> Vec3 {
+    (a.0 * s, a.1 * s, a.2 * s)
+}
+
+fn dot(a: Vec3, b: Vec3) -> f32 {
+    a.0 * b.0 + a.1 * b.1 + a.2 * b.2
+}
+
+fn length(a: Vec3) -> f32 {
+    dot(a, a).sqrt()
+}
+
+/// Closest distance from `point` to the segment `from`..`to`
+fn distance_to_segment(point: Vec3, from: Vec3, to: Vec3) -> f32 {
+    let segment = sub(to, from);
+    let len_sq = dot(segment, segment);
+    let t = if len_sq > 0.0 {
+        (dot(sub(point, from), segment) / len_sq).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    length(sub(point, add(from, scale(segment, t))))
+}
+
+/// Launch a projectile from the owner toward the aimed-at target's current position
+/// Returns the new projectile, or None if there is nothing to aim at
+pub fn spawn(
+    lobby: &mut Lobby,
+    owner_id: u32,
+    weapon_id: u32,
+    target_id: u32,
+    speed: f32,
+    max_range: f32,
+) -> Option<Projectile> {
+    if lobby.projectiles.active.len() >= MAX_PROJECTILES {
+        return None;
+    }
+    let origin = lobby.players.get(&owner_id)?.position;
+    let aim = lobby.players.get(&target_id)?.position;
+    let direction = sub(aim, origin);
+    let distance = length(direction);
+    if distance <= f32::EPSILON {
+        return None;
+    }
+
+    let set = &mut lobby.projectiles;
+    set.next_id = set.next_id.wrapping_add(1);
+    let projectile = Projectile {
+        id: set.next_id,
+        owner_id,
+        weapon_id,
+        position: origin,
+        velocity: scale(direction, speed / distance),
+        travelled: 0.0,
+        max_range,
+    };
+    set.active.push(projectile.clone());
+    Some(projectile)
+}
+
+/// Move every projectile one step under gravity and collect those that hit or ran out of range
+/// A hit is the first living player (other than the owner) within `HIT_RADIUS` of the path
+pub fn step(lobby: &mut Lobby, dt: f32) -> Vec<ProjectileEnd> {
+    let targets: Vec<(u32, Vec3)> = lobby.players.values()
+        .filter(|p| !p.is_dead)
+        .map(|p| (p.id, p.position))
+        .collect();
+
+    let mut ended = Vec::new();
+    lobby.projectiles.active.retain_mut(|projectile| {
+        let from = projectile.position;
+        projectile.velocity.1 -= GRAVITY * dt;
+        let to = add(from, scale(projectile.velocity, dt));
+        projectile.position = to;
+        projectile.travelled += length(sub(to, from));
+
+        let hit = targets.iter()
+            .filter(|(id, _)| *id != projectile.owner_id)
+            .map(|(id, position)| (*id, distance_to_segment(*position, from, to)))
+            .filter(|(_, distance)| *distance <= HIT_RADIUS)
+            .min_by(|a, b| a.1.total_cmp(&b.1));
+        let outcome = match hit {
+            Some((target_id, _)) => ProjectileOutcome::Hit { target_id },
+            None if projectile.travelled >= projectile.max_range => ProjectileOutcome::Expired,
+            None => return true,
+        };
+        ended.push(ProjectileEnd {
+            projectile_id: projectile.id,
+            owner_id: projectile.owner_id,
+            weapon_id: projectile.weapon_id,
+            outcome,
+        });
+        false
+    });
+    ended
+}
+
+/// Drop a leaving player's projectiles
+pub fn remove_owner(lobby: &mut Lobby, owner_id: u32) {
+    lobby.projectiles.active.retain(|p| p.owner_id != owner_id);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::lobby::Player;
+
+    fn lobby_with(positions: &[(u32, Vec3)]) -> Lobby {
+        let mut lobby = Lobby::new("TEST".to_string(), 4, "world".to_string());
+        for (id, position) in positions {
+            let mut player = Player::new_player(*id, format!("P{}", id), 1, 20);
+            player.position = *position;
+            lobby.players.insert(*id, player);
+        }
+        lobby
+    }
+
+    #[test]
+    fn test_projectile_travels_then_hits() {
+        let mut lobby = lobby_with(&[(1, (0.0, 0.0, 0.0)), (2, (10.0, 0.0, 0.0))]);
+        let projectile = spawn(&mut lobby, 1, 1, 2, 50.0, 100.0).unwrap();
+        assert_eq!(projectile.velocity, (50.0, 0.0, 0.0));
+
+        // 10 units at 50/s takes ~0.2s
+        assert!(step(&mut lobby, 0.1).is_empty());
+        let ended = step(&mut lobby, 0.1);
+        assert_eq!(ended.len(), 1);
+        assert_eq!(ended[0].outcome, ProjectileOutcome::Hit { target_id: 2 });
+        assert!(lobby.projectiles.active.is_empty());
+    }
+
+    #[test]
+    fn test_gravity_drops_slow_projectiles() {
+        let mut lobby = lobby_with(&[(1, (0.0, 0.0, 0.0)), (2, (30.0, 0.0, 0.0))]);
+        spawn(&mut lobby, 1, 1, 2, 10.0, 35.0).unwrap();
+
+        // Drops ~20 units in the 2s it needs to cover 20 units, far below the target
+        let mut ended = Vec::new();
+        for _ in 0..40 {
+            ended.extend(step(&mut lobby, 0.1));
+        }
+        assert_eq!(ended.len(), 1);
+        assert_eq!(ended[0].outcome, ProjectileOutcome::Expired);
+    }
+}
@@ -0,0 +1,58 @@
+use crate::state::lobby::Lobby;
+use crate::utils::weapondb::RampUp;
+use std::time::{Duration, SystemTime};
+
+/// Consecutive hits by one attacker on their current target
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HitStreak {
+    pub target_id: u32,
+    pub weapon_id: u32,
+    pub hits: u32,
+    pub last_hit: SystemTime,
+}
+
+/// Streak length the next hit on `target_id` would have
+/// Hits on another target, with another weapon or after the window start over
+pub fn next_streak(lobby: &Lobby, attacker_id: u32, target_id: u32, weapon_id: u32, ramp_up: &RampUp, now: SystemTime) -> u32 {
+    let window = Duration::from_secs_f32(ramp_up.window_secs);
+    match lobby.hit_streaks.get(&attacker_id) {
+        Some(streak) if streak.target_id == target_id
+            && streak.weapon_id == weapon_id
+            && now.duration_since(streak.last_hit).is_ok_and(|elapsed| elapsed <= window) => streak.hits + 1,
+        _ => 1,
+    }
+}
+
+/// Record a landed hit as the attacker's current streak
+pub fn record_hit(lobby: &mut Lobby, attacker_id: u32, target_id: u32, weapon_id: u32, hits: u32, now: SystemTime) {
+    lobby.hit_streaks.insert(attacker_id, HitStreak { target_id, weapon_id, hits, last_hit: now });
+}
+
+/// Drop streaks by or against a player (death, leave)
+pub fn reset_player(lobby: &mut Lobby, player_id: u32) {
+    lobby.hit_streaks.retain(|attacker_id, streak| *attacker_id != player_id && streak.target_id != player_id);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RAMP_UP: RampUp = RampUp { factor: 1.5, max_multiplier: 3.0, window_secs: 1.0 };
+
+    #[test]
+    fn test_streak_resets_on_target_change_and_timeout() {
+        let mut lobby = Lobby::new("TEST".to_string(), 4, "world".to_string());
+        let now = SystemTime::now();
+        assert_eq!(next_streak(&lobby, 1, 2, 1, &RAMP_UP, now), 1);
+
+        record_hit(&mut lobby, 1, 2, 1, 1, now);
+        let soon = now + Duration::from_millis(500);
+        assert_eq!(next_streak(&lobby, 1, 2, 1, &RAMP_UP, soon), 2);
+        assert_eq!(next_streak(&lobby, 1, 3, 1, &RAMP_UP, soon), 1); // Other target
+        assert_eq!(next_streak(&lobby, 1, 2, 2, &RAMP_UP, soon), 1); // Other weapon
+        assert_eq!(next_streak(&lobby, 1, 2, 1, &RAMP_UP, now + Duration::from_secs(2)), 1);
+
+        reset_player(&mut lobby, 2);
+        assert!(lobby.hit_streaks.is_empty());
+    }
+}
@@ -0,0 +1,109 @@
+/// Rating assigned to players who have never finished a ranked match
+pub const DEFAULT_RATING: f32 = 1000.0;
+
+/// Maximum rating a player can gain or lose in a single match
+const K_FACTOR: f32 = 32.0;
+
+/// Rating distance considered a fair match for matchmaking
+pub const MATCHMAKING_BAND: f32 = 200.0;
+
+/// Final placement of a player in a ranked match
+#[derive(Debug, Clone)]
+pub struct Placement {
+    pub player_id: u32,
+    pub rating: f32,
+    pub score: u32,
+}
+
+/// Expected score of a player rated `rating` against `opponent` (Elo)
+fn expected_score(rating: f32, opponent: f32) -> f32 {
+    1.0 / (1.0 + 10f32.powf((opponent - rating) / 400.0))
+}
+
+/// Compute rating changes from final placements
+/// Every player is treated as having played a 1v1 against every other player:
+/// a higher score is a win, an equal score is a draw.
+/// Returns (player_id, rating_delta) in the same order as `placements`
+pub fn compute_rating_changes(placements: &[Placement]) -> Vec<(u32, f32)> {
+    if placements.len() < 2 {
+        return placements.iter().map(|p| (p.player_id, 0.0)).collect();
+    }
+
+    // Scale K so a match against many opponents moves rating as much as a 1v1
+    let k = K_FACTOR / (placements.len() - 1) as f32;
+
+    placements
+        .iter()
+        .map(|player| {
+            let delta: f32 = placements
+                .iter()
+                .filter(|other| other.player_id != player.player_id)
+                .map(|other| {
+                    let actual = match player.score.cmp(&other.score) {
+                        std::cmp::Ordering::Greater => 1.0,
+                        std::cmp::Ordering::Equal => 0.5,
+                        std::cmp::Ordering::Less => 0.0,
+                    };
+                    k * (actual - expected_score(player.rating, other.rating))
+                })
+                .sum();
+            (player.player_id, delta)
+        })
+        .collect()
+}
+
+/// Check if two ratings are close enough to be matched together
+pub fn within_band(rating: f32, other: f32) -> bool {
+    (rating - other).abs() <= MATCHMAKING_BAND
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn placement(player_id: u32, rating: f32, score: u32) -> Placement {
+        Placement { player_id, rating, score }
+    }
+
+    #[test]
+    fn test_winner_gains_loser_loses() {
+        let changes = compute_rating_changes(&[
+            placement(1, DEFAULT_RATING, 500),
+            placement(2, DEFAULT_RATING, 100),
+        ]);
+        assert_eq!(changes[0].0, 1);
+        assert!((changes[0].1 - 16.0).abs() < 0.001);
+        assert!((changes[1].1 + 16.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_rating_changes_are_zero_sum() {
+        let changes = compute_rating_changes(&[
+            placement(1, 1200.0, 900),
+            placement(2, 1000.0, 400),
+            placement(3, 800.0, 400),
+            placement(4, 1100.0, 0),
+        ]);
+        let total: f32 = changes.iter().map(|(_, d)| d).sum();
+        assert!(total.abs() < 0.001);
+    }
+
+    #[test]
+    fn test_upset_moves_more_rating() {
+        let expected = compute_rating_changes(&[placement(1, 1400.0, 10), placement(2, 1000.0, 0)]);
+        let upset = compute_rating_changes(&[placement(1, 1000.0, 10), placement(2, 1400.0, 0)]);
+        assert!(upset[0].1 > expected[0].1);
+    }
+
+    #[test]
+    fn test_single_player_unchanged() {
+        let changes = compute_rating_changes(&[placement(1, DEFAULT_RATING, 100)]);
+        assert_eq!(changes, vec![(1, 0.0)]);
+    }
+
+    #[test]
+    fn test_within_band() {
+        assert!(within_band(1000.0, 1150.0));
+        assert!(!within_band(1000.0, 1300.0));
+    }
+}
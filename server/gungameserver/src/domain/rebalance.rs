@@ -0,0 +1,161 @@
+use crate::domain::{bots, lobbies, logic, teams};
+use crate::state::lobby::{Lobby, Player};
+use crate::utils::buffers::SyncEvent;
+use crate::utils::weapondb::{WeaponDb, WeaponLookup};
+use std::net::SocketAddr;
+use std::time::Duration;
+use crate::utils::clock;
+
+/// A player on their way from one lobby to another, with their match state
+#[derive(Debug, Clone)]
+pub struct Transfer {
+    pub player: Player,
+    pub addr: Option<SocketAddr>,
+    pub zone_points: u32,
+}
+
+/// Humans in the lobby, by id
+fn human_ids(lobby: &Lobby) -> Vec<u32> {
+    let mut ids: Vec<u32> = lobby.players.keys().copied().filter(|id| *id != 999 && !bots::is_bot(*id)).collect();
+    ids.sort_unstable();
+    ids
+}
+
+/// Whether `into` can seat every human from `from` (its bots make way as needed)
+pub fn can_merge(from: &Lobby, into: &Lobby) -> Result<(), &'static str> {
+    if from.code == into.code {
+        return Err("Cannot merge a lobby into itself");
+    }
+    if from.settings.game_mode != into.settings.game_mode || from.settings.ranked != into.settings.ranked {
+        return Err("Lobbies have different game modes");
+    }
+    if bots::human_count(from) + bots::human_count(into) > into.max_players as usize {
+        return Err("Target lobby lacks room");
+    }
+    Ok(())
+}
+
+/// Half of a lobby's humans to move out when splitting it, alternating down the scoreboard
+/// so both halves keep a similar mix of strong and weak players
+pub fn split_selection(lobby: &Lobby) -> Result<Vec<u32>, &'static str> {
+    if lobby.settings.ranked {
+        return Err("Ranked lobbies can't be split");
+    }
+    let mut ids = human_ids(lobby);
+    if ids.len() < 2 {
+        return Err("Not enough players to split");
+    }
+    ids.sort_by_key(|id| std::cmp::Reverse(lobby.players[id].score));
+    Ok(ids.into_iter().skip(1).step_by(2).collect())
+}
+
+/// Take players (every human when `player_ids` is None) out of a lobby to move them elsewhere
+/// Goes through `lobbies::move_out`, so they're announced as leaving on the next tick
+pub fn transfer_out(lobby: &mut Lobby, player_ids: Option<&[u32]>) -> Vec<Transfer> {
+    let ids = player_ids.map(<[u32]>::to_vec).unwrap_or_else(|| human_ids(lobby));
+    let mut moved = Vec::new();
+    for player_id in ids {
+        let zone_points = lobby.zone.points.get(&player_id).copied().unwrap_or(0);
+        if let Some((player, addr)) = lobbies::move_out(lobby, player_id) {
+            moved.push(Transfer { player, addr, zone_points });
+        }
+    }
+    moved
+}
+
+/// Seat transferred players, scores and stats intact, and tell each client where they now are
+/// Players have `grace` to reconnect before inactivity cleanup applies
+pub fn transfer_in(lobby: &mut Lobby, from_code: &str, moved: Vec<Transfer>, weapons: &impl WeaponLookup, grace: Duration) {
+    let now = clock::now();
+    for Transfer { mut player, addr, zone_points } in moved {
+        let player_id = player.id;
+        if lobby.players.len() >= lobby.max_players as usize {
+            bots::make_room(lobby);
+        }
+
+        // The target lobby's health and weapon overrides apply from here on
+        let weapon = weapons.get(player.current_weapon_id)
+            .or_else(|| weapons.get(WeaponDb::default_weapon_id()));
+        if let Some(weapon) = weapon {
+            player.current_weapon_id = weapon.id;
+            player.max_ammo = weapon.ammo;
+        }
+        player.max_health = lobby.settings.max_health;
+        player.ready = false;
+        player.spectating = false;
+        player.last_activity = now;
+        player.last_update = now + grace;
+
+        lobby.transfers_in.push((player_id, player.name.clone()));
+        lobby.players.insert(player_id, player);
+        if let Some(addr) = addr {
+            lobby.client_addresses.insert(player_id, addr);
+        }
+        if zone_points > 0 {
+            lobby.zone.points.insert(player_id, zone_points);
+        }
+        // Teams don't carry over; the target lobby's balancer places them
+        teams::assign(lobby, player_id, None);
+        let _ = logic::respawn_player(lobby, player_id);
+        lobby.push_event(SyncEvent::LobbyTransfer { player_id, from_code: from_code.to_string() });
+    }
+    if lobby.owner_id.is_none() {
+        lobby.owner_id = human_ids(lobby).first().copied();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::lobbies::add_player;
+    use crate::state::lobby::LobbySettings;
+
+    fn lobby_with(code: &str, max_players: u32, ids: std::ops::RangeInclusive<u32>, weapons: &WeaponDb) -> Lobby {
+        let mut lobby = Lobby::with_settings(code.to_string(), max_players, "world".to_string(), LobbySettings::default());
+        for id in ids {
+            add_player(&mut lobby, id, format!("P{}", id), 1, weapons).unwrap();
+            lobby.client_addresses.insert(id, format!("127.0.0.1:{}", 7000 + id).parse().unwrap());
+            lobby.players.get_mut(&id).unwrap().score = id * 10;
+        }
+        lobby
+    }
+
+    #[test]
+    fn test_merge_keeps_scores_and_addresses() {
+        let weapons = WeaponDb::load();
+        let mut from = lobby_with("FROM", 4, 1..=2, &weapons);
+        let mut into = lobby_with("INTO", 4, 3..=4, &weapons);
+        let mut full = lobby_with("FULL", 3, 5..=6, &weapons);
+        assert_eq!(can_merge(&from, &full), Err("Target lobby lacks room"));
+        assert_eq!(can_merge(&from, &into), Ok(()));
+
+        let moved = transfer_out(&mut from, None);
+        assert!(from.players.is_empty() && from.owner_id.is_none());
+        assert_eq!(from.transfers_out, vec![1, 2]);
+        transfer_in(&mut into, "FROM", moved, &weapons, Duration::from_secs(30));
+
+        assert_eq!(into.players.len(), 4);
+        assert_eq!(into.players[&2].score, 20);
+        assert_eq!(into.client_addresses[&2].port(), 7002);
+        assert_eq!(into.owner_id, Some(3));
+        assert_eq!(into.transfers_in.len(), 2);
+        let notified = into.pending_events.iter()
+            .filter(|e| matches!(e, SyncEvent::LobbyTransfer { from_code, .. } if from_code == "FROM"))
+            .count();
+        assert_eq!(notified, 2);
+        full.settings.ranked = true;
+        assert_eq!(can_merge(&into, &full), Err("Lobbies have different game modes"));
+    }
+
+    #[test]
+    fn test_split_alternates_down_the_scoreboard() {
+        let weapons = WeaponDb::load();
+        let mut lobby = lobby_with("BIG1", 8, 1..=5, &weapons);
+        assert_eq!(split_selection(&lobby), Ok(vec![4, 2]));
+
+        lobby.settings.ranked = true;
+        assert_eq!(split_selection(&lobby), Err("Ranked lobbies can't be split"));
+        let lone = lobby_with("LONE", 8, 1..=1, &weapons);
+        assert_eq!(split_selection(&lone), Err("Not enough players to split"));
+    }
+}
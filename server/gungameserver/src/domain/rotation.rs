@@ -0,0 +1,120 @@
+use std::f32::consts::{PI, TAU};
+
+/// Unit quaternion as (x, y, z, w)
+pub type Quat = (f32, f32, f32, f32);
+
+/// Capability clients list at join to send and receive quaternion orientations
+pub const QUATERNION_CAPABILITY: &str = "quaternion_rotation";
+
+/// Quaternions shorter than this have no usable direction
+const MIN_QUAT_LENGTH: f32 = 1e-6;
+
+/// Wrap an angle in radians into [-PI, PI)
+/// Angles already in range are returned untouched, so wrapping never drifts them
+fn wrap_angle(angle: f32) -> f32 {
+    if (-PI..PI).contains(&angle) {
+        return angle;
+    }
+    (angle + PI).rem_euclid(TAU) - PI
+}
+
+/// Wrap every Euler angle into [-PI, PI) so equal rotations compare equal
+pub fn normalize_euler(rotation: (f32, f32, f32)) -> (f32, f32, f32) {
+    (wrap_angle(rotation.0), wrap_angle(rotation.1), wrap_angle(rotation.2))
+}
+
+/// Scale to unit length, with w >= 0 so the same rotation always has the same sign
+/// Returns None for quaternions too short to normalize
+pub fn normalize_quat(q: Quat) -> Option<Quat> {
+    let length = (q.0 * q.0 + q.1 * q.1 + q.2 * q.2 + q.3 * q.3).sqrt();
+    if !length.is_finite() || length < MIN_QUAT_LENGTH {
+        return None;
+    }
+    let sign = if q.3 < 0.0 { -1.0 } else { 1.0 };
+    let scale = sign / length;
+    Some((q.0 * scale, q.1 * scale, q.2 * scale, q.3 * scale))
+}
+
+fn multiply(a: Quat, b: Quat) -> Quat {
+    (
+        a.3 * b.0 + a.0 * b.3 + a.1 * b.2 - a.2 * b.1,
+        a.3 * b.1 - a.0 * b.2 + a.1 * b.3 + a.2 * b.0,
+        a.3 * b.2 + a.0 * b.1 - a.1 * b.0 + a.2 * b.3,
+        a.3 * b.3 - a.0 * b.0 - a.1 * b.1 - a.2 * b.2,
+    )
+}
+
+/// Quaternion for Euler angles in radians, applied in the client's YXZ order (yaw, pitch, roll)
+pub fn euler_to_quat(rotation: (f32, f32, f32)) -> Quat {
+    let (x, y, z) = (rotation.0 * 0.5, rotation.1 * 0.5, rotation.2 * 0.5);
+    let yaw = (0.0, y.sin(), 0.0, y.cos());
+    let pitch = (x.sin(), 0.0, 0.0, x.cos());
+    let roll = (0.0, 0.0, z.sin(), z.cos());
+    normalize_quat(multiply(multiply(yaw, pitch), roll)).unwrap_or((0.0, 0.0, 0.0, 1.0))
+}
+
+/// YXZ Euler angles for a unit quaternion, for clients without quaternion support
+/// At straight up or down pitch, roll folds into yaw
+pub fn quat_to_euler(q: Quat) -> (f32, f32, f32) {
+    let (x, y, z, w) = q;
+    let m02 = 2.0 * (x * z + w * y);
+    let m10 = 2.0 * (x * y + w * z);
+    let m11 = 1.0 - 2.0 * (x * x + z * z);
+    let m12 = 2.0 * (y * z - w * x);
+    let m20 = 2.0 * (x * z - w * y);
+    let m00 = 1.0 - 2.0 * (y * y + z * z);
+    let m22 = 1.0 - 2.0 * (x * x + y * y);
+
+    let pitch = (-m12).clamp(-1.0, 1.0).asin();
+    let euler = if m12.abs() < 0.99999 {
+        (pitch, m02.atan2(m22), m10.atan2(m11))
+    } else {
+        (pitch, (-m20).atan2(m00), 0.0)
+    };
+    normalize_euler(euler)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::FRAC_PI_2;
+
+    fn assert_close(a: (f32, f32, f32), b: (f32, f32, f32)) {
+        assert!((a.0 - b.0).abs() < 1e-4 && (a.1 - b.1).abs() < 1e-4 && (a.2 - b.2).abs() < 1e-4, "{:?} != {:?}", a, b);
+    }
+
+    /// Rotate a vector by a unit quaternion
+    fn rotate(q: Quat, v: (f32, f32, f32)) -> (f32, f32, f32) {
+        let r = multiply(multiply(q, (v.0, v.1, v.2, 0.0)), (-q.0, -q.1, -q.2, q.3));
+        (r.0, r.1, r.2)
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert_close(normalize_euler((TAU + 0.5, -PI - 0.25, 3.0 * PI)), (0.5, PI - 0.25, -PI));
+        assert_eq!(normalize_quat((0.0, 0.0, 0.0, -2.0)), Some((0.0, 0.0, 0.0, 1.0)));
+        assert_eq!(normalize_quat((0.0, 0.0, 0.0, 0.0)), None);
+    }
+
+    #[test]
+    fn test_euler_round_trip() {
+        for euler in [(0.0, 0.0, 0.0), (0.3, 1.2, -0.4), (-1.0, -2.9, 0.1), (0.0, PI - 0.01, 0.0)] {
+            let q = euler_to_quat(euler);
+            assert!(q.3 >= 0.0);
+            assert_close(quat_to_euler(q), euler);
+        }
+
+        // Pure yaw turns the forward axis around Y
+        let forward = rotate(euler_to_quat((0.0, FRAC_PI_2, 0.0)), (0.0, 0.0, -1.0));
+        assert_close(forward, (-1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_gimbal_lock_keeps_orientation() {
+        let q = euler_to_quat((FRAC_PI_2, 0.7, 0.2));
+        let back = euler_to_quat(quat_to_euler(q));
+        for v in [(1.0, 0.0, 0.0), (0.0, 1.0, 0.0), (0.0, 0.0, 1.0)] {
+            assert_close(rotate(q, v), rotate(back, v));
+        }
+    }
+}
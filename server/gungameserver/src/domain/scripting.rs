@@ -0,0 +1,215 @@
+use crate::state::lobby::Lobby;
+use crate::utils::buffers::SyncEvent;
+use rhai::module_resolvers::DummyModuleResolver;
+use rhai::{CallFnOptions, Dynamic, Engine, FuncArgs, Scope, AST};
+use std::sync::{Arc, Mutex};
+
+/// Largest rules script accepted for upload
+pub const MAX_SCRIPT_BYTES: usize = 16 * 1024;
+
+/// Operations one hook call may run before it's aborted
+pub const MAX_OPERATIONS: u64 = 10_000;
+
+/// Score one award_score call may add or remove
+const MAX_AWARD: i64 = 1_000;
+
+/// Which optional hooks a script defines
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Hooks {
+    on_kill: bool,
+    on_tick: bool,
+    modify_damage: bool,
+}
+
+/// Compiled per-lobby game rules, written in Rhai
+///
+/// Scripts may define any of:
+/// - `on_kill(killer_id, victim_id, weapon_id)`
+/// - `on_tick(dt)`, during a live match
+/// - `modify_damage(attacker_id, target_id, weapon_id, damage)`, returning the damage to deal
+///
+/// and call `award_score(player_id, points)` from any of them. There's no file, module
+/// or network access, and every call is capped at `MAX_OPERATIONS`.
+pub struct RulesScript {
+    pub name: String,
+    engine: Engine,
+    ast: AST,
+    hooks: Hooks,
+    awards: Arc<Mutex<Vec<(i64, i64)>>>, // award_score calls from the running hook
+}
+
+impl std::fmt::Debug for RulesScript {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RulesScript").field("name", &self.name).field("hooks", &self.hooks).finish()
+    }
+}
+
+impl RulesScript {
+    pub fn compile(name: &str, source: &str) -> Result<Self, &'static str> {
+        if source.len() > MAX_SCRIPT_BYTES {
+            return Err("Script too large");
+        }
+        let awards = Arc::new(Mutex::new(Vec::new()));
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+        engine.set_max_call_levels(16);
+        engine.set_max_expr_depths(32, 16);
+        engine.set_max_string_size(1024);
+        engine.set_max_array_size(256);
+        engine.set_max_map_size(256);
+        engine.set_module_resolver(DummyModuleResolver::new());
+        let script_name = name.to_string();
+        engine.on_print(move |text| log::debug!("Rules script {}: {}", script_name, text));
+        engine.on_debug(|_, _, _| {});
+        let sink = awards.clone();
+        engine.register_fn("award_score", move |player_id: i64, points: i64| {
+            if let Ok(mut awards) = sink.lock() {
+                awards.push((player_id, points));
+            }
+        });
+
+        let ast = engine.compile(source).map_err(|e| {
+            log::debug!("Rules script {} does not compile: {}", name, e);
+            "Script does not compile"
+        })?;
+        let defines = |hook: &str, params: usize| ast.iter_functions().any(|f| f.name == hook && f.params.len() == params);
+        let hooks = Hooks {
+            on_kill: defines("on_kill", 3),
+            on_tick: defines("on_tick", 1),
+            modify_damage: defines("modify_damage", 4),
+        };
+        if hooks == Hooks::default() {
+            return Err("Script defines no hooks");
+        }
+        Ok(Self { name: name.to_string(), engine, ast, hooks, awards })
+    }
+
+    /// Run a hook; failures (including hitting the operation limit) are logged and yield None
+    fn call(&self, hook: &str, args: impl FuncArgs) -> Option<Dynamic> {
+        let options = CallFnOptions::new().eval_ast(false);
+        match self.engine.call_fn_with_options::<Dynamic>(options, &mut Scope::new(), &self.ast, hook, args) {
+            Ok(value) => Some(value),
+            Err(e) => {
+                log::warn!("Rules script {} failed in {}: {}", self.name, hook, e);
+                None
+            }
+        }
+    }
+
+    fn take_awards(&self) -> Vec<(i64, i64)> {
+        self.awards.lock().map(|mut awards| std::mem::take(&mut *awards)).unwrap_or_default()
+    }
+}
+
+/// Apply the scores a hook awarded and tell clients
+fn apply_awards(lobby: &mut Lobby, script: &RulesScript) {
+    for (player_id, points) in script.take_awards() {
+        let Ok(player_id) = u32::try_from(player_id) else { continue };
+        let Some(player) = lobby.players.get_mut(&player_id) else { continue };
+        let points = points.clamp(-MAX_AWARD, MAX_AWARD);
+        player.score = (player.score as i64 + points).clamp(0, u32::MAX as i64) as u32;
+        let event = SyncEvent::ScoreChanged {
+            player_id,
+            score: player.score,
+            kills: player.kills,
+            deaths: player.deaths,
+            killstreak: player.killstreak,
+        };
+        lobby.push_event(event);
+    }
+}
+
+/// Damage a hit deals under the lobby's rules script (unchanged without one)
+pub fn modify_damage(lobby: &mut Lobby, attacker_id: u32, target_id: u32, weapon_id: u32, damage: u32) -> u32 {
+    let Some(script) = lobby.rules_script.clone().filter(|s| s.hooks.modify_damage) else {
+        return damage;
+    };
+    let args = (attacker_id as i64, target_id as i64, weapon_id as i64, damage as i64);
+    let modified = script.call("modify_damage", args).and_then(|value| value.as_int().ok());
+    apply_awards(lobby, &script);
+    modified.map_or(damage, |d| d.clamp(0, u32::MAX as i64) as u32)
+}
+
+pub fn on_kill(lobby: &mut Lobby, killer_id: u32, victim_id: u32, weapon_id: u32) {
+    let Some(script) = lobby.rules_script.clone().filter(|s| s.hooks.on_kill) else {
+        return;
+    };
+    script.call("on_kill", (killer_id as i64, victim_id as i64, weapon_id as i64));
+    apply_awards(lobby, &script);
+}
+
+pub fn on_tick(lobby: &mut Lobby, dt: f32) {
+    let Some(script) = lobby.rules_script.clone().filter(|s| s.hooks.on_tick) else {
+        return;
+    };
+    script.call("on_tick", (dt as f64,));
+    apply_awards(lobby, &script);
+}
+
+/// Install or remove a lobby's rules script; a match in progress keeps its rules until it ends
+pub fn set_script(lobby: &mut Lobby, script: Option<Arc<RulesScript>>) {
+    if lobby.is_match_live() {
+        lobby.staged_script = Some(script);
+    } else {
+        lobby.rules_script = script;
+    }
+}
+
+/// Swap in a script staged during the match that just ended
+pub fn apply_staged(lobby: &mut Lobby) {
+    if let Some(script) = lobby.staged_script.take() {
+        lobby.rules_script = script;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lobby_with(source: &str) -> Lobby {
+        let mut lobby = Lobby::new("TEST".to_string(), 4, "world".to_string());
+        lobby.players.insert(1, Lobby::new_player(1, "A".to_string(), 1, 20));
+        lobby.players.insert(2, Lobby::new_player(2, "B".to_string(), 1, 20));
+        lobby.rules_script = Some(Arc::new(RulesScript::compile("test", source).unwrap()));
+        lobby
+    }
+
+    #[test]
+    fn test_hooks_modify_damage_and_award_score() {
+        let mut lobby = lobby_with(r#"
+            fn modify_damage(attacker, target, weapon, damage) { if weapon == 2 { damage * 2 } else { damage } }
+            fn on_kill(killer, victim, weapon) { award_score(killer, 50); award_score(victim, -5000); }
+        "#);
+        assert_eq!(modify_damage(&mut lobby, 1, 2, 2, 30), 60);
+        assert_eq!(modify_damage(&mut lobby, 1, 2, 1, 30), 30);
+
+        lobby.players.get_mut(&2).unwrap().score = 200;
+        on_kill(&mut lobby, 1, 2, 1);
+        assert_eq!(lobby.players[&1].score, 50);
+        assert_eq!(lobby.players[&2].score, 0); // Awards are capped and scores never go negative
+        assert!(matches!(lobby.pending_events[0], SyncEvent::ScoreChanged { player_id: 1, score: 50, .. }));
+    }
+
+    #[test]
+    fn test_runaway_and_broken_scripts_are_contained() {
+        let mut lobby = lobby_with("fn modify_damage(a, t, w, d) { loop {} }");
+        assert_eq!(modify_damage(&mut lobby, 1, 2, 1, 30), 30);
+
+        assert_eq!(RulesScript::compile("bad", "fn on_tick(dt) {").err(), Some("Script does not compile"));
+        assert_eq!(RulesScript::compile("empty", "let x = 1;").err(), Some("Script defines no hooks"));
+        assert_eq!(RulesScript::compile("import", r#"import "fs" as fs; fn on_tick(dt) {}"#).ok().map(|_| ()), Some(()));
+        let mut lobby = lobby_with(r#"fn on_tick(dt) { import "fs" as fs; award_score(1, 1); }"#);
+        on_tick(&mut lobby, 0.05);
+        assert_eq!(lobby.players[&1].score, 0); // Imports fail at run time, so nothing is awarded
+    }
+
+    #[test]
+    fn test_script_swaps_between_matches() {
+        let mut lobby = lobby_with("fn on_tick(dt) { award_score(1, 1); }");
+        lobby.phase = crate::state::lobby::MatchPhase::InProgress;
+        set_script(&mut lobby, None);
+        assert!(lobby.rules_script.is_some());
+        apply_staged(&mut lobby);
+        assert!(lobby.rules_script.is_none());
+    }
+}
@@ -0,0 +1,76 @@
+use crate::domain::interest::{self, GridCell};
+use crate::state::lobby::Lobby;
+use crate::utils::buffers::SyncEvent;
+use std::collections::HashMap;
+
+/// Scene chunk each player was last told they are in
+#[derive(Debug, Default)]
+pub struct ChunkTracker {
+    current: HashMap<u32, GridCell>,
+}
+
+impl ChunkTracker {
+    pub fn chunk_of(&self, player_id: u32) -> Option<GridCell> {
+        self.current.get(&player_id).copied()
+    }
+
+    pub fn forget(&mut self, player_id: u32) {
+        self.current.remove(&player_id);
+    }
+
+    /// Start over, e.g. for a new scene; every player gets a fresh `chunk_enter`
+    pub fn reset(&mut self) {
+        self.current.clear();
+    }
+}
+
+/// Tell a player which scene chunk they moved into (and out of), so the client can stream its assets
+/// Scenes without a chunk size are loaded whole and get no hints
+pub fn update(lobby: &mut Lobby, player_id: u32) {
+    let Some(chunk_size) = lobby.scene_data.chunk_size else {
+        return;
+    };
+    let Some(position) = lobby.players.get(&player_id).map(|p| p.position) else {
+        return;
+    };
+    let chunk = interest::grid_cell(position, chunk_size);
+    let previous = lobby.chunks.current.insert(player_id, chunk);
+    if previous == Some(chunk) {
+        return;
+    }
+    if let Some(left) = previous {
+        lobby.push_event(SyncEvent::ChunkExit { to_id: player_id, chunk: left });
+    }
+    lobby.push_event(SyncEvent::ChunkEnter { to_id: player_id, chunk, size: chunk_size });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hints_only_when_chunk_changes() {
+        let mut lobby = Lobby::new("TEST".to_string(), 4, "world".to_string());
+        lobby.players.insert(1, Lobby::new_player(1, "Alice".to_string(), 1, 20));
+
+        lobby.players.get_mut(&1).unwrap().position = (10.0, 0.0, 10.0);
+        update(&mut lobby, 1);
+        update(&mut lobby, 1);
+        lobby.players.get_mut(&1).unwrap().position = (130.0, 0.0, 10.0);
+        update(&mut lobby, 1);
+
+        assert_eq!(lobby.pending_events.len(), 3);
+        assert!(matches!(lobby.pending_events[0], SyncEvent::ChunkEnter { to_id: 1, chunk: (0, 0), .. }));
+        assert!(matches!(lobby.pending_events[1], SyncEvent::ChunkExit { to_id: 1, chunk: (0, 0) }));
+        assert!(matches!(lobby.pending_events[2], SyncEvent::ChunkEnter { to_id: 1, chunk: (1, 0), .. }));
+        assert_eq!(lobby.chunks.chunk_of(1), Some((1, 0)));
+    }
+
+    #[test]
+    fn test_small_scenes_get_no_hints() {
+        let mut lobby = Lobby::new("TEST".to_string(), 4, "arena".to_string());
+        lobby.players.insert(1, Lobby::new_player(1, "Alice".to_string(), 1, 20));
+        update(&mut lobby, 1);
+        assert!(lobby.pending_events.is_empty());
+    }
+}
@@ -0,0 +1,150 @@
+use crate::state::lobby::Lobby;
+use crate::utils::buffers::SyncEvent;
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime};
+
+/// Most teams a lobby can be split into
+pub const MAX_TEAMS: u8 = 4;
+
+/// Largest gap in player count allowed between any two teams after a pick or switch
+pub const MAX_TEAM_IMBALANCE: usize = 1;
+
+/// Minimum time between team switches by the same player
+pub const TEAM_SWITCH_COOLDOWN: Duration = Duration::from_secs(10);
+
+/// Team each player is on, and when each last switched
+#[derive(Debug, Default)]
+pub struct Teams {
+    pub assignments: HashMap<u32, u8>,
+    last_switch: HashMap<u32, SystemTime>,
+}
+
+impl Teams {
+    pub fn team_of(&self, player_id: u32) -> Option<u8> {
+        self.assignments.get(&player_id).copied()
+    }
+
+    pub fn forget(&mut self, player_id: u32) {
+        self.assignments.remove(&player_id);
+        self.last_switch.remove(&player_id);
+    }
+
+    /// Players on each of `count` teams, leaving out `except`
+    fn sizes(&self, count: u8, except: u32) -> Vec<usize> {
+        let mut sizes = vec![0; count as usize];
+        for (id, team) in &self.assignments {
+            if *id != except {
+                if let Some(size) = sizes.get_mut(*team as usize) {
+                    *size += 1;
+                }
+            }
+        }
+        sizes
+    }
+
+    /// Whether `player_id` joining `team` keeps every team within `MAX_TEAM_IMBALANCE`
+    fn fits(&self, count: u8, player_id: u32, team: u8) -> bool {
+        let mut sizes = self.sizes(count, player_id);
+        sizes[team as usize] += 1;
+        let (min, max) = (sizes.iter().min().copied().unwrap_or(0), sizes.iter().max().copied().unwrap_or(0));
+        max - min <= MAX_TEAM_IMBALANCE
+    }
+
+    /// The team with the fewest players, lowest number first
+    fn smallest(&self, count: u8, player_id: u32) -> u8 {
+        let sizes = self.sizes(count, player_id);
+        (0..count).min_by_key(|team| sizes[*team as usize]).unwrap_or(0)
+    }
+}
+
+/// Put a newly joined player on a team: their preference if it keeps teams even, else the smallest
+/// Lobbies without teams leave players unassigned
+pub fn assign(lobby: &mut Lobby, player_id: u32, preference: Option<u8>) -> Option<u8> {
+    let count = lobby.settings.team_count;
+    if count == 0 {
+        return None;
+    }
+    let teams = &lobby.teams;
+    let team = preference
+        .filter(|team| *team < count && teams.fits(count, player_id, *team))
+        .unwrap_or_else(|| teams.smallest(count, player_id));
+    set_team(lobby, player_id, team);
+    Some(team)
+}
+
+/// A player asking to move to another team; refused while on cooldown or if it would unbalance teams
+pub fn request_switch(lobby: &mut Lobby, player_id: u32, team: u8, now: SystemTime) -> Result<(), &'static str> {
+    let count = lobby.settings.team_count;
+    if count == 0 {
+        return Err("Lobby has no teams");
+    }
+    if team >= count {
+        return Err("No such team");
+    }
+    let current = lobby.teams.team_of(player_id).ok_or("Player not found")?;
+    if current == team {
+        return Err("Already on that team");
+    }
+    let on_cooldown = lobby.teams.last_switch.get(&player_id)
+        .is_some_and(|at| now.duration_since(*at).unwrap_or_default() < TEAM_SWITCH_COOLDOWN);
+    if on_cooldown {
+        return Err("Team switch on cooldown");
+    }
+    if !lobby.teams.fits(count, player_id, team) {
+        return Err("Teams would be unbalanced");
+    }
+    lobby.teams.last_switch.insert(player_id, now);
+    set_team(lobby, player_id, team);
+    Ok(())
+}
+
+fn set_team(lobby: &mut Lobby, player_id: u32, team: u8) {
+    lobby.teams.assignments.insert(player_id, team);
+    lobby.push_event(SyncEvent::TeamChanged { player_id, team });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::lobby::LobbySettings;
+
+    fn team_lobby(team_count: u8, players: u32) -> Lobby {
+        let settings = LobbySettings { team_count, ..Default::default() };
+        let mut lobby = Lobby::with_settings("TEST".to_string(), 8, "world".to_string(), settings);
+        for id in 1..=players {
+            lobby.players.insert(id, Lobby::new_player(id, format!("P{}", id), 1, 20));
+            assign(&mut lobby, id, None);
+        }
+        lobby
+    }
+
+    #[test]
+    fn test_joins_fill_smallest_team_unless_preference_fits() {
+        let mut lobby = team_lobby(2, 3);
+        assert_eq!(lobby.teams.team_of(1), Some(0));
+        assert_eq!(lobby.teams.team_of(2), Some(1));
+        assert_eq!(lobby.teams.team_of(3), Some(0));
+
+        // Team 0 already has one more, so 4 goes to team 1 regardless
+        assert_eq!(assign(&mut lobby, 4, Some(0)), Some(1));
+        assert_eq!(assign(&mut lobby, 5, Some(1)), Some(1));
+        assert!(matches!(lobby.pending_events.last(), Some(SyncEvent::TeamChanged { player_id: 5, team: 1 })));
+
+        assert_eq!(assign(&mut team_lobby(0, 0), 1, Some(0)), None);
+    }
+
+    #[test]
+    fn test_switch_respects_balance_and_cooldown() {
+        let now = SystemTime::now();
+        let mut lobby = team_lobby(2, 4);
+        assert_eq!(request_switch(&mut lobby, 1, 1, now), Err("Teams would be unbalanced"));
+        assert_eq!(request_switch(&mut lobby, 1, 0, now), Err("Already on that team"));
+        assert_eq!(request_switch(&mut lobby, 1, 2, now), Err("No such team"));
+
+        lobby.teams.forget(2);
+        assert_eq!(request_switch(&mut lobby, 1, 1, now), Ok(()));
+        assert_eq!(lobby.teams.team_of(1), Some(1));
+        assert_eq!(request_switch(&mut lobby, 1, 0, now + Duration::from_secs(1)), Err("Team switch on cooldown"));
+        assert_eq!(request_switch(&mut lobby, 1, 0, now + TEAM_SWITCH_COOLDOWN), Ok(()));
+    }
+}
@@ -0,0 +1,125 @@
+use crate::domain::awards::MatchAward;
+use crate::domain::damage_log::DamageLog;
+use crate::state::lobby::LobbyCode;
+
+/// Events kept per match; later events are dropped and the timeline marked truncated
+pub const MAX_TIMELINE_EVENTS: usize = 50_000;
+
+/// Finished match timelines kept for replay queries (oldest evicted first)
+pub const MAX_FINISHED_TIMELINES: usize = 100;
+
+/// One broadcast event, stamped with the match tick it happened on
+#[derive(Debug, Clone, PartialEq, serde::Serialize, utoipa::ToSchema)]
+pub struct TimelineEvent {
+    pub tick: u64,
+    /// The packet sent to clients
+    #[schema(value_type = Object)]
+    pub event: serde_json::Value,
+}
+
+/// Tick-indexed event log of one match; tick 0 is the tick the match started on
+#[derive(Debug, Clone)]
+pub struct MatchTimeline {
+    pub match_id: u64,
+    pub lobby_code: LobbyCode,
+    pub start_tick: u64, // Lobby tick the match started on
+    pub last_tick: u64,  // Latest match tick seen
+    pub truncated: bool,
+    pub damage: DamageLog, // Every hit, for admins settling disputes
+    pub awards: Vec<MatchAward>, // Set when the match ends
+    tick_offset: u64,      // Match ticks played before a crash recovery
+    events: Vec<TimelineEvent>,
+}
+
+impl MatchTimeline {
+    pub fn new(match_id: u64, lobby_code: LobbyCode, start_tick: u64) -> Self {
+        Self {
+            match_id,
+            lobby_code,
+            start_tick,
+            last_tick: 0,
+            truncated: false,
+            damage: DamageLog::default(),
+            awards: Vec::new(),
+            tick_offset: 0,
+            events: Vec::new(),
+        }
+    }
+
+    /// Timeline for a match restored from a checkpoint, continuing its clock from `played_ticks`
+    /// Events from before the restore are not carried over
+    pub fn resumed(match_id: u64, lobby_code: LobbyCode, played_ticks: u64) -> Self {
+        Self {
+            last_tick: played_ticks,
+            tick_offset: played_ticks,
+            ..Self::new(match_id, lobby_code, 0)
+        }
+    }
+
+    /// Match tick a lobby tick falls on
+    pub fn match_tick(&self, lobby_tick: u64) -> u64 {
+        lobby_tick.saturating_sub(self.start_tick) + self.tick_offset
+    }
+
+    /// Advance the timeline to a lobby tick
+    pub fn advance(&mut self, lobby_tick: u64) {
+        self.last_tick = self.match_tick(lobby_tick);
+    }
+
+    /// Record an event on the current tick
+    pub fn record(&mut self, event: serde_json::Value) {
+        if self.events.len() >= MAX_TIMELINE_EVENTS {
+            self.truncated = true;
+            return;
+        }
+        self.events.push(TimelineEvent { tick: self.last_tick, event });
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// Events with `from_tick <= tick <= to_tick`
+    /// Events are recorded in tick order, so the window is found by binary search
+    pub fn window(&self, from_tick: u64, to_tick: u64) -> &[TimelineEvent] {
+        let start = self.events.partition_point(|e| e.tick < from_tick);
+        let end = self.events.partition_point(|e| e.tick <= to_tick);
+        &self.events[start..end.max(start)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn timeline_with_events() -> MatchTimeline {
+        let mut timeline = MatchTimeline::new(1, "TEST".to_string(), 100);
+        for lobby_tick in [100, 101, 101, 150, 400] {
+            timeline.advance(lobby_tick);
+            timeline.record(json!({ "type": "test", "at": lobby_tick }));
+        }
+        timeline
+    }
+
+    #[test]
+    fn test_ticks_are_match_relative() {
+        let timeline = timeline_with_events();
+        let ticks: Vec<u64> = timeline.window(0, u64::MAX).iter().map(|e| e.tick).collect();
+        assert_eq!(ticks, vec![0, 1, 1, 50, 300]);
+        assert_eq!(timeline.last_tick, 300);
+    }
+
+    #[test]
+    fn test_window_bounds_are_inclusive() {
+        let timeline = timeline_with_events();
+        assert_eq!(timeline.window(1, 50).len(), 3);
+        assert_eq!(timeline.window(2, 49).len(), 0);
+        assert_eq!(timeline.window(301, 1000).len(), 0);
+        assert_eq!(timeline.window(50, 1).len(), 0);
+    }
+}
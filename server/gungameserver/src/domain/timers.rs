@@ -0,0 +1,93 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::time::SystemTime;
+
+/// Per-player deadlines of one kind, soonest first, so a tick only looks at those that ran out
+///
+/// Entries are never removed early: the player's own field stays the source of truth and is
+/// checked when an entry comes due. Cleared timers are dropped then, and pushed-back ones
+/// (a lobby resuming from pause) are queued again for their new time.
+#[derive(Debug, Default, Clone)]
+pub struct TimerQueue {
+    heap: BinaryHeap<Reverse<(SystemTime, u32)>>,
+}
+
+impl TimerQueue {
+    pub fn schedule(&mut self, player_id: u32, due: SystemTime) {
+        self.heap.push(Reverse((due, player_id)));
+    }
+
+    /// Players whose deadline has passed by `now`
+    /// `current` reads a player's deadline as it stands (None if cleared or the player left)
+    pub fn due(&mut self, now: SystemTime, current: impl Fn(u32) -> Option<SystemTime>) -> Vec<u32> {
+        let mut due = Vec::new();
+        while let Some(Reverse((at, player_id))) = self.heap.peek().copied() {
+            if at > now {
+                break;
+            }
+            self.heap.pop();
+            match current(player_id) {
+                Some(deadline) if deadline > now => self.schedule(player_id, deadline),
+                Some(_) if !due.contains(&player_id) => due.push(player_id),
+                _ => {}
+            }
+        }
+        due
+    }
+
+    /// Entries queued, stale ones included
+    pub fn len(&self) -> usize {
+        self.heap.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.heap.is_empty()
+    }
+}
+
+/// A lobby's timer queues, one per kind of player deadline
+#[derive(Debug, Default, Clone)]
+pub struct LobbyTimers {
+    pub reloads: TimerQueue,
+    pub respawns: TimerQueue,
+    pub spawn_protection: TimerQueue,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::time::Duration;
+
+    #[test]
+    fn test_only_expired_timers_pop() {
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1000);
+        let secs = |s| start + Duration::from_secs(s);
+        let mut deadlines: HashMap<u32, SystemTime> = [(1, secs(1)), (2, secs(2)), (3, secs(3))].into_iter().collect();
+        let mut queue = TimerQueue::default();
+        for (id, at) in &deadlines {
+            queue.schedule(*id, *at);
+        }
+
+        assert!(queue.due(start, |id| deadlines.get(&id).copied()).is_empty());
+        assert_eq!(queue.len(), 3);
+
+        // 2 was cleared and 3 pushed back before they came due
+        deadlines.remove(&2);
+        deadlines.insert(3, secs(10));
+        assert_eq!(queue.due(secs(5), |id| deadlines.get(&id).copied()), vec![1]);
+        assert_eq!(queue.len(), 1);
+        assert!(queue.due(secs(9), |id| deadlines.get(&id).copied()).is_empty());
+        assert_eq!(queue.due(secs(10), |id| deadlines.get(&id).copied()), vec![3]);
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn test_rescheduled_timer_pops_once() {
+        let now = SystemTime::now();
+        let mut queue = TimerQueue::default();
+        queue.schedule(1, now);
+        queue.schedule(1, now);
+        assert_eq!(queue.due(now, |_| Some(now)), vec![1]);
+    }
+}
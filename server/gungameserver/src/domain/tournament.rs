@@ -0,0 +1,177 @@
+use crate::state::lobby::{LobbyCode, MatchStanding};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+/// Fewest entrants a bracket can be drawn for
+pub const MIN_PARTICIPANTS: usize = 2;
+
+/// Most entrants one tournament may have
+pub const MAX_PARTICIPANTS: usize = 64;
+
+/// One pairing of a knockout round
+#[derive(Debug, Clone, Default, PartialEq, Serialize, ToSchema)]
+pub struct BracketMatch {
+    /// The two entrants by name; null while a slot waits on an earlier match
+    pub players: [Option<String>; 2],
+    /// Lobby the match is played in, once both entrants are known
+    pub lobby_code: Option<LobbyCode>,
+    pub winner: Option<String>,
+    /// Advanced without playing: the entrant had no opponent in the first round
+    pub bye: bool,
+}
+
+impl BracketMatch {
+    /// Both entrants known and not yet played or scheduled
+    pub fn is_ready(&self) -> bool {
+        self.players.iter().all(Option::is_some) && self.winner.is_none() && self.lobby_code.is_none()
+    }
+
+    fn has_player(&self, name: &str) -> bool {
+        self.players.iter().flatten().any(|player| player == name)
+    }
+}
+
+/// Single-elimination bracket; rounds[0] is the first round, the last round is the final
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct Bracket {
+    pub rounds: Vec<Vec<BracketMatch>>,
+}
+
+impl Bracket {
+    /// Draw a bracket in seed order (first listed is the top seed)
+    /// Fields that aren't a power of two give the top seeds first-round byes
+    pub fn new(participants: &[String]) -> Result<Self, &'static str> {
+        if participants.len() < MIN_PARTICIPANTS {
+            return Err("Too few participants");
+        }
+        if participants.len() > MAX_PARTICIPANTS {
+            return Err("Too many participants");
+        }
+        if participants.iter().any(|name| name.trim().is_empty()) {
+            return Err("Empty participant name");
+        }
+        let mut names: Vec<&str> = participants.iter().map(String::as_str).collect();
+        names.sort_unstable();
+        names.dedup();
+        if names.len() != participants.len() {
+            return Err("Duplicate participant");
+        }
+
+        // Seed i meets seed size-1-i, so the missing bottom seeds become the top seeds' byes
+        let size = participants.len().next_power_of_two();
+        let seed = |i: usize| participants.get(i).cloned();
+        let first_round = (0..size / 2)
+            .map(|i| BracketMatch { players: [seed(i), seed(size - 1 - i)], ..Default::default() })
+            .collect();
+        let mut rounds = vec![first_round];
+        let mut matches = size / 4;
+        while matches >= 1 {
+            rounds.push(vec![BracketMatch::default(); matches]);
+            matches /= 2;
+        }
+
+        let mut bracket = Self { rounds };
+        for index in 0..bracket.rounds[0].len() {
+            let m = &mut bracket.rounds[0][index];
+            if let [Some(player), None] = &m.players {
+                let player = player.clone();
+                m.bye = true;
+                bracket.advance(0, index, player);
+            }
+        }
+        Ok(bracket)
+    }
+
+    pub fn get(&self, round: usize, index: usize) -> Option<&BracketMatch> {
+        self.rounds.get(round)?.get(index)
+    }
+
+    /// Matches whose entrants are both known but that have no lobby yet, as (round, index)
+    pub fn ready_matches(&self) -> Vec<(usize, usize)> {
+        self.rounds.iter().enumerate()
+            .flat_map(|(round, matches)| matches.iter().enumerate()
+                .filter(|(_, m)| m.is_ready())
+                .map(move |(index, _)| (round, index)))
+            .collect()
+    }
+
+    pub fn assign_lobby(&mut self, round: usize, index: usize, lobby_code: LobbyCode) {
+        if let Some(m) = self.rounds.get_mut(round).and_then(|matches| matches.get_mut(index)) {
+            m.lobby_code = Some(lobby_code);
+        }
+    }
+
+    /// Decide a match and move the winner into their next-round slot
+    pub fn record_winner(&mut self, round: usize, index: usize, winner: &str) -> Result<(), &'static str> {
+        let m = self.rounds.get(round).and_then(|matches| matches.get(index)).ok_or("Match not found")?;
+        if m.winner.is_some() {
+            return Err("Match already decided");
+        }
+        if !m.has_player(winner) {
+            return Err("Winner is not in this match");
+        }
+        self.advance(round, index, winner.to_string());
+        Ok(())
+    }
+
+    fn advance(&mut self, round: usize, index: usize, winner: String) {
+        self.rounds[round][index].winner = Some(winner.clone());
+        if let Some(next) = self.rounds.get_mut(round + 1) {
+            next[index / 2].players[index % 2] = Some(winner);
+        }
+    }
+
+    /// Winner of the final, once it's been played
+    pub fn champion(&self) -> Option<&str> {
+        self.rounds.last()?.first()?.winner.as_deref()
+    }
+}
+
+/// Best-placed entrant of a match in the lobby's final standings (spectators and bots don't count)
+pub fn match_winner(m: &BracketMatch, standings: &[MatchStanding]) -> Option<String> {
+    standings.iter()
+        .find(|standing| m.has_player(&standing.name))
+        .map(|standing| standing.name.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(count: usize) -> Vec<String> {
+        (1..=count).map(|i| format!("P{}", i)).collect()
+    }
+
+    #[test]
+    fn test_bracket_draw_with_byes() {
+        let bracket = Bracket::new(&names(5)).unwrap();
+        assert_eq!(bracket.rounds.iter().map(Vec::len).collect::<Vec<_>>(), vec![4, 2, 1]);
+        // Seeds 1-3 have no opponent and move straight to the second round
+        assert!(bracket.rounds[0][0].bye && bracket.rounds[0][2].bye);
+        assert_eq!(bracket.rounds[0][3].players, [Some("P4".to_string()), Some("P5".to_string())]);
+        assert_eq!(bracket.rounds[1][0].players, [Some("P1".to_string()), Some("P2".to_string())]);
+        assert_eq!(bracket.rounds[1][1].players, [Some("P3".to_string()), None]);
+        assert_eq!(bracket.ready_matches(), vec![(0, 3), (1, 0)]);
+
+        assert_eq!(Bracket::new(&names(1)), Err("Too few participants"));
+        assert_eq!(Bracket::new(&["A".to_string(), "A".to_string()]), Err("Duplicate participant"));
+    }
+
+    #[test]
+    fn test_winners_advance_to_champion() {
+        let mut bracket = Bracket::new(&names(4)).unwrap();
+        assert_eq!(bracket.ready_matches(), vec![(0, 0), (0, 1)]);
+        bracket.assign_lobby(0, 0, "T1R1M1".to_string());
+        assert_eq!(bracket.ready_matches(), vec![(0, 1)]);
+
+        assert_eq!(bracket.record_winner(0, 0, "P3"), Err("Winner is not in this match"));
+        bracket.record_winner(0, 0, "P4").unwrap();
+        assert_eq!(bracket.record_winner(0, 0, "P1"), Err("Match already decided"));
+        bracket.record_winner(0, 1, "P2").unwrap();
+        assert_eq!(bracket.ready_matches(), vec![(1, 0)]);
+        assert_eq!(bracket.champion(), None);
+
+        bracket.record_winner(1, 0, "P2").unwrap();
+        assert_eq!(bracket.champion(), Some("P2"));
+    }
+}
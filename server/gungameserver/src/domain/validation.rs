@@ -0,0 +1,224 @@
+use crate::domain::rotation::{self, Quat};
+use crate::state::lobby::Lobby;
+use crate::utils::scenes::SceneData;
+use serde_json::Value;
+use std::time::{Duration, SystemTime};
+
+/// How long after a player leaves shots at them still count as an honest race
+pub const DEPARTED_GRACE: Duration = Duration::from_secs(2);
+
+/// Kinds of malformed or impossible client input
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ViolationKind {
+    /// NaN, infinite, or too large to represent
+    NonFiniteValue,
+    /// Id that doesn't fit a u32 or isn't a number
+    IdOutOfRange,
+    /// Position outside the scene bounds
+    OutOfBounds,
+    /// Shot at a player that isn't in the lobby and didn't just leave it
+    UnknownTarget,
+    /// Switch to a weapon that doesn't exist
+    UnknownWeapon,
+    /// Hit landed from well beyond the weapon's range
+    HitOutOfRange,
+    /// More hits than the weapon's fire rate allows
+    DamageRateExceeded,
+    /// Quaternion with no length to normalize
+    InvalidRotation,
+}
+
+impl ViolationKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ViolationKind::NonFiniteValue => "non_finite_value",
+            ViolationKind::IdOutOfRange => "id_out_of_range",
+            ViolationKind::OutOfBounds => "out_of_bounds",
+            ViolationKind::UnknownTarget => "unknown_target",
+            ViolationKind::UnknownWeapon => "unknown_weapon",
+            ViolationKind::HitOutOfRange => "hit_out_of_range",
+            ViolationKind::DamageRateExceeded => "damage_rate_exceeded",
+            ViolationKind::InvalidRotation => "invalid_rotation",
+        }
+    }
+}
+
+/// Read an optional id field; present values must be a u32
+pub fn read_id(packet: &Value, key: &str) -> Result<Option<u32>, ViolationKind> {
+    match packet.get(key) {
+        None | Some(Value::Null) => Ok(None),
+        Some(value) => value.as_u64()
+            .and_then(|id| u32::try_from(id).ok())
+            .map(Some)
+            .ok_or(ViolationKind::IdOutOfRange),
+    }
+}
+
+/// Read an optional list of ids (missing reads as empty), keeping at most `max`
+pub fn read_ids(packet: &Value, key: &str, max: usize) -> Result<Vec<u32>, ViolationKind> {
+    let Some(values) = packet.get(key).filter(|v| !v.is_null()) else {
+        return Ok(Vec::new());
+    };
+    values.as_array().ok_or(ViolationKind::IdOutOfRange)?
+        .iter()
+        .take(max)
+        .map(|value| value.as_u64().and_then(|id| u32::try_from(id).ok()).ok_or(ViolationKind::IdOutOfRange))
+        .collect()
+}
+
+/// Read a finite float field (missing reads as None)
+pub fn read_f32(packet: &Value, key: &str) -> Result<Option<f32>, ViolationKind> {
+    match packet.get(key).and_then(|v| v.as_f64()) {
+        None => Ok(None),
+        // Checked after narrowing: huge f64s become infinite f32s
+        Some(value) if (value as f32).is_finite() => Ok(Some(value as f32)),
+        Some(_) => Err(ViolationKind::NonFiniteValue),
+    }
+}
+
+/// Read a finite double field, for values like timestamps that don't fit an f32 (missing reads as None)
+pub fn read_f64(packet: &Value, key: &str) -> Result<Option<f64>, ViolationKind> {
+    match packet.get(key).and_then(|v| v.as_f64()) {
+        None => Ok(None),
+        Some(value) if value.is_finite() => Ok(Some(value)),
+        Some(_) => Err(ViolationKind::NonFiniteValue),
+    }
+}
+
+/// Read an {x, y, z} object; missing components read as 0
+pub fn read_vec3(value: &Value) -> Result<(f32, f32, f32), ViolationKind> {
+    Ok((
+        read_f32(value, "x")?.unwrap_or(0.0),
+        read_f32(value, "y")?.unwrap_or(0.0),
+        read_f32(value, "z")?.unwrap_or(0.0),
+    ))
+}
+
+/// Read an {x, y, z, w} quaternion, normalized; missing components read as identity
+pub fn read_quat(value: &Value) -> Result<Quat, ViolationKind> {
+    let quat = (
+        read_f32(value, "x")?.unwrap_or(0.0),
+        read_f32(value, "y")?.unwrap_or(0.0),
+        read_f32(value, "z")?.unwrap_or(0.0),
+        read_f32(value, "w")?.unwrap_or(1.0),
+    );
+    rotation::normalize_quat(quat).ok_or(ViolationKind::InvalidRotation)
+}
+
+/// Clamp a position into the scene bounds
+/// Returns the clamped position if the original was outside
+pub fn clamp_to_scene(position: (f32, f32, f32), scene: &SceneData) -> Option<(f32, f32, f32)> {
+    let extent = scene.half_extent;
+    // Allow some room below the kill plane so falling players still die
+    let min_y = scene.kill_plane_y - 10.0;
+    let clamped = (
+        position.0.clamp(-extent, extent),
+        position.1.clamp(min_y, scene.max_height),
+        position.2.clamp(-extent, extent),
+    );
+    (clamped != position).then_some(clamped)
+}
+
+/// Remember a player just removed from the lobby; departures older than the grace are forgotten
+pub fn note_departure(lobby: &mut Lobby, player_id: u32, now: SystemTime) {
+    lobby.departed.retain(|_, at| now.duration_since(*at).unwrap_or_default() < DEPARTED_GRACE);
+    lobby.departed.insert(player_id, now);
+}
+
+/// Whether a shot names a target that is neither in the lobby nor just left it
+/// Clients fire at what they last saw, so a target leaving mid-shot is a normal race
+pub fn is_unknown_target(lobby: &Lobby, target_id: u32, now: SystemTime) -> bool {
+    if lobby.players.contains_key(&target_id) {
+        return false;
+    }
+    lobby.departed.get(&target_id)
+        .is_none_or(|at| now.duration_since(*at).unwrap_or_default() >= DEPARTED_GRACE)
+}
+
+/// Count an anti-cheat strike against a player; returns their total
+pub fn record_violation(lobby: &mut Lobby, player_id: u32, kind: ViolationKind) -> u32 {
+    let Some(player) = lobby.players.get_mut(&player_id) else {
+        return 0;
+    };
+    player.anticheat_strikes += 1;
+    log::warn!(
+        "Anti-cheat strike {} for player {} in lobby {}: {}",
+        player.anticheat_strikes, player_id, lobby.code, kind.as_str()
+    );
+    player.anticheat_strikes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::scenes::scene_data;
+    use serde_json::json;
+
+    #[test]
+    fn test_read_id_rejects_out_of_range() {
+        let packet = json!({ "player_id": 7, "target_id": 5_000_000_000u64, "weapon_id": -1 });
+        assert_eq!(read_id(&packet, "player_id"), Ok(Some(7)));
+        assert_eq!(read_id(&packet, "target_id"), Err(ViolationKind::IdOutOfRange));
+        assert_eq!(read_id(&packet, "weapon_id"), Err(ViolationKind::IdOutOfRange));
+        assert_eq!(read_id(&packet, "missing"), Ok(None));
+    }
+
+    #[test]
+    fn test_read_ids() {
+        let packet = serde_json::json!({ "hits": [2, 3, 2], "bad": [1, -1], "scalar": 4 });
+        assert_eq!(read_ids(&packet, "hits", 2), Ok(vec![2, 3]));
+        assert_eq!(read_ids(&packet, "missing", 8), Ok(Vec::new()));
+        assert_eq!(read_ids(&packet, "bad", 8), Err(ViolationKind::IdOutOfRange));
+        assert_eq!(read_ids(&packet, "scalar", 8), Err(ViolationKind::IdOutOfRange));
+    }
+
+    #[test]
+    fn test_read_vec3_rejects_non_finite() {
+        assert_eq!(read_vec3(&json!({ "x": 1.5, "z": -2.0 })), Ok((1.5, 0.0, -2.0)));
+        assert_eq!(read_vec3(&json!({ "x": 1e300 })), Err(ViolationKind::NonFiniteValue));
+    }
+
+    #[test]
+    fn test_read_quat_normalizes() {
+        assert_eq!(read_quat(&json!({ "y": 2.0, "w": 0.0 })), Ok((0.0, 1.0, 0.0, 0.0)));
+        assert_eq!(read_quat(&json!({ "w": -3.0 })), Ok((0.0, 0.0, 0.0, 1.0)));
+        assert_eq!(read_quat(&json!({ "w": 0.0 })), Err(ViolationKind::InvalidRotation));
+        assert_eq!(read_quat(&json!({ "x": 1e300 })), Err(ViolationKind::NonFiniteValue));
+    }
+
+    #[test]
+    fn test_clamp_to_scene() {
+        let scene = scene_data("world");
+        assert_eq!(clamp_to_scene((10.0, 5.0, -10.0), &scene), None);
+        let clamped = clamp_to_scene((1e9, 5.0, 0.0), &scene).unwrap();
+        assert_eq!(clamped.0, scene.half_extent);
+    }
+
+    #[test]
+    fn test_record_violation_counts_strikes() {
+        let mut lobby = Lobby::new("TEST".to_string(), 4, "world".to_string());
+        lobby.players.insert(1, crate::state::lobby::Player::new_player(1, "P1".to_string(), 1, 20));
+        assert_eq!(record_violation(&mut lobby, 1, ViolationKind::OutOfBounds), 1);
+        assert_eq!(record_violation(&mut lobby, 1, ViolationKind::UnknownTarget), 2);
+        assert_eq!(record_violation(&mut lobby, 9, ViolationKind::UnknownTarget), 0);
+    }
+
+    #[test]
+    fn test_recently_departed_target_is_known() {
+        let mut lobby = Lobby::new("TEST".to_string(), 4, "world".to_string());
+        lobby.players.insert(1, crate::state::lobby::Player::new_player(1, "P1".to_string(), 1, 20));
+        let now = SystemTime::now();
+        assert!(!is_unknown_target(&lobby, 1, now));
+        assert!(is_unknown_target(&lobby, 2, now));
+
+        lobby.players.remove(&1);
+        note_departure(&mut lobby, 1, now);
+        assert!(!is_unknown_target(&lobby, 1, now + Duration::from_millis(500)));
+        assert!(is_unknown_target(&lobby, 1, now + DEPARTED_GRACE));
+
+        // Old departures are dropped as new ones come in
+        note_departure(&mut lobby, 3, now + DEPARTED_GRACE);
+        assert!(!lobby.departed.contains_key(&1));
+    }
+}
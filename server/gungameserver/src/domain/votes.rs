@@ -0,0 +1,213 @@
+use crate::state::lobby::Lobby;
+use crate::domain::bots;
+use crate::utils::buffers::SyncEvent;
+use std::collections::HashSet;
+
+/// How long players have to vote
+pub const VOTE_DURATION_SECS: u64 = 30;
+
+/// Reason sent with kicks decided by a vote
+pub const VOTE_KICK_REASON: &str = "Vote kick";
+
+/// Longest scene name a vote may switch to
+const MAX_SCENE_NAME_LEN: usize = 32;
+
+/// What a lobby vote decides
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "vote", rename_all = "snake_case")]
+pub enum VoteKind {
+    Kick { target_id: u32 },
+    ChangeScene { scene: String },
+    RestartMatch,
+}
+
+impl VoteKind {
+    /// Parse the vote sent by clients ("kick", "scene", "restart")
+    pub fn parse(vote: &str, target_id: Option<u32>, scene: Option<&str>) -> Option<Self> {
+        match vote {
+            "kick" => target_id.map(|target_id| VoteKind::Kick { target_id }),
+            "scene" => scene.map(|scene| VoteKind::ChangeScene { scene: scene.to_string() }),
+            "restart" => Some(VoteKind::RestartMatch),
+            _ => None,
+        }
+    }
+}
+
+/// A vote in progress
+#[derive(Debug, Clone)]
+pub struct Vote {
+    pub kind: VoteKind,
+    pub initiator: u32,
+    pub yes: HashSet<u32>,
+    pub no: HashSet<u32>,
+    pub remaining_secs: f32,
+}
+
+/// Players allowed to vote: everyone except bots and the kick target
+fn eligible_voters(lobby: &Lobby, kind: &VoteKind) -> HashSet<u32> {
+    lobby.players.keys()
+        .copied()
+        .filter(|id| *id != 999 && !bots::is_bot(*id) && *kind != VoteKind::Kick { target_id: *id })
+        .collect()
+}
+
+/// Current (yes, no, needed, eligible) counts - votes of players who left are ignored
+fn tally(lobby: &Lobby, vote: &Vote) -> (usize, usize, usize, usize) {
+    let eligible = eligible_voters(lobby, &vote.kind);
+    let yes = vote.yes.intersection(&eligible).count();
+    let no = vote.no.intersection(&eligible).count();
+    // Strict majority of eligible voters
+    let needed = eligible.len() / 2 + 1;
+    (yes, no, needed, eligible.len())
+}
+
+fn push_progress(lobby: &mut Lobby) {
+    let Some(vote) = &lobby.active_vote else {
+        return;
+    };
+    let (yes, no, needed, _) = tally(lobby, vote);
+    lobby.push_event(SyncEvent::VoteProgress { yes, no, needed });
+}
+
+/// Start a vote; the initiator votes yes
+pub fn start_vote(lobby: &mut Lobby, player_id: u32, kind: VoteKind, duration_secs: u64) -> Result<(), &'static str> {
+    if lobby.active_vote.is_some() {
+        return Err("A vote is already running");
+    }
+    if !lobby.players.contains_key(&player_id) {
+        return Err("Player not found");
+    }
+    match &kind {
+        VoteKind::Kick { target_id } if *target_id == player_id => return Err("Cannot vote-kick yourself"),
+        VoteKind::Kick { target_id } if !lobby.players.contains_key(target_id) => return Err("Vote target not found"),
+        VoteKind::ChangeScene { scene } if !valid_scene_name(scene) => return Err("Invalid scene name"),
+        _ => {}
+    }
+
+    lobby.push_event(SyncEvent::VoteStarted {
+        initiator: player_id,
+        kind: kind.clone(),
+        seconds: duration_secs,
+    });
+    lobby.active_vote = Some(Vote {
+        kind,
+        initiator: player_id,
+        yes: HashSet::from([player_id]),
+        no: HashSet::new(),
+        remaining_secs: duration_secs as f32,
+    });
+    push_progress(lobby);
+    Ok(())
+}
+
+/// Cast (or change) a player's vote
+pub fn cast_vote(lobby: &mut Lobby, player_id: u32, yes: bool) -> Result<(), &'static str> {
+    let vote = lobby.active_vote.as_ref().ok_or("No vote running")?;
+    if !eligible_voters(lobby, &vote.kind).contains(&player_id) {
+        return Err("Player cannot vote");
+    }
+
+    let vote = lobby.active_vote.as_mut().ok_or("No vote running")?;
+    if yes {
+        vote.no.remove(&player_id);
+        vote.yes.insert(player_id);
+    } else {
+        vote.yes.remove(&player_id);
+        vote.no.insert(player_id);
+    }
+    push_progress(lobby);
+    Ok(())
+}
+
+/// Advance the running vote by `dt` seconds
+/// Returns the outcome to execute once a vote passes
+pub fn update_vote(lobby: &mut Lobby, dt: f32) -> Option<VoteKind> {
+    let vote = lobby.active_vote.as_mut()?;
+    vote.remaining_secs -= dt;
+    let timed_out = vote.remaining_secs <= 0.0;
+
+    let vote = lobby.active_vote.as_ref()?;
+    let (yes, no, needed, eligible) = tally(lobby, vote);
+    let passed = yes >= needed;
+    // Fails early once the remaining voters can no longer reach a majority
+    let impossible = eligible - no < needed;
+    if !passed && !impossible && !timed_out {
+        return None;
+    }
+
+    let vote = lobby.active_vote.take()?;
+    lobby.push_event(SyncEvent::VoteEnded { kind: vote.kind.clone(), passed });
+    passed.then_some(vote.kind)
+}
+
+fn valid_scene_name(scene: &str) -> bool {
+    !scene.is_empty()
+        && scene.len() <= MAX_SCENE_NAME_LEN
+        && scene.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::lobbies::add_player;
+    use crate::utils::weapondb::WeaponDb;
+
+    fn lobby_with_players(count: u32) -> Lobby {
+        let mut lobby = Lobby::new("TEST".to_string(), 8, "world".to_string());
+        let weapons = WeaponDb::load();
+        for id in 1..=count {
+            add_player(&mut lobby, id, format!("Player{}", id), 1, &weapons).unwrap();
+        }
+        lobby
+    }
+
+    #[test]
+    fn test_parse_vote_kind() {
+        assert_eq!(VoteKind::parse("kick", Some(3), None), Some(VoteKind::Kick { target_id: 3 }));
+        assert_eq!(VoteKind::parse("kick", None, None), None);
+        assert_eq!(VoteKind::parse("scene", None, Some("arena")), Some(VoteKind::ChangeScene { scene: "arena".to_string() }));
+        assert_eq!(VoteKind::parse("restart", None, None), Some(VoteKind::RestartMatch));
+        assert_eq!(VoteKind::parse("ban", Some(3), None), None);
+    }
+
+    #[test]
+    fn test_kick_vote_passes_with_majority() {
+        let mut lobby = lobby_with_players(4);
+        start_vote(&mut lobby, 1, VoteKind::Kick { target_id: 4 }, 30).unwrap();
+        assert!(start_vote(&mut lobby, 2, VoteKind::RestartMatch, 30).is_err());
+        assert!(cast_vote(&mut lobby, 4, false).is_err()); // Target can't vote
+
+        // 3 eligible voters: 2 needed
+        assert_eq!(update_vote(&mut lobby, 0.1), None);
+        cast_vote(&mut lobby, 2, true).unwrap();
+        assert_eq!(update_vote(&mut lobby, 0.1), Some(VoteKind::Kick { target_id: 4 }));
+        assert!(lobby.active_vote.is_none());
+        assert!(matches!(lobby.pending_events.last(), Some(SyncEvent::VoteEnded { passed: true, .. })));
+    }
+
+    #[test]
+    fn test_vote_fails_when_impossible_or_timed_out() {
+        let mut lobby = lobby_with_players(3);
+        start_vote(&mut lobby, 1, VoteKind::RestartMatch, 30).unwrap();
+        cast_vote(&mut lobby, 2, false).unwrap();
+        cast_vote(&mut lobby, 3, false).unwrap();
+        assert_eq!(update_vote(&mut lobby, 0.1), None);
+        assert!(matches!(lobby.pending_events.last(), Some(SyncEvent::VoteEnded { passed: false, .. })));
+
+        start_vote(&mut lobby, 1, VoteKind::ChangeScene { scene: "arena".to_string() }, 1).unwrap();
+        assert_eq!(update_vote(&mut lobby, 0.5), None);
+        assert!(lobby.active_vote.is_some());
+        assert_eq!(update_vote(&mut lobby, 0.6), None);
+        assert!(lobby.active_vote.is_none());
+    }
+
+    #[test]
+    fn test_invalid_votes_rejected() {
+        let mut lobby = lobby_with_players(2);
+        assert!(start_vote(&mut lobby, 1, VoteKind::Kick { target_id: 1 }, 30).is_err());
+        assert!(start_vote(&mut lobby, 1, VoteKind::Kick { target_id: 9 }, 30).is_err());
+        assert!(start_vote(&mut lobby, 1, VoteKind::ChangeScene { scene: "../etc".to_string() }, 30).is_err());
+        assert!(start_vote(&mut lobby, 7, VoteKind::RestartMatch, 30).is_err());
+        assert!(cast_vote(&mut lobby, 1, true).is_err());
+    }
+}
@@ -0,0 +1,87 @@
+use crate::domain::logic;
+use crate::state::lobby::{Lobby, LobbySettings};
+use crate::utils::buffers::SyncEvent;
+use crate::utils::weapondb::WeaponLookup;
+
+pub fn is_banned(settings: &LobbySettings, weapon_id: u32) -> bool {
+    settings.banned_weapons.contains(&weapon_id)
+}
+
+/// First ladder weapon still allowed, for players whose weapon was banned
+pub fn fallback_weapon(lobby: &Lobby, weapons: &impl WeaponLookup) -> Option<u32> {
+    lobby.settings.weapon_ladder.iter()
+        .copied()
+        .find(|id| weapons.contains(*id) && !is_banned(&lobby.settings, *id))
+}
+
+/// Ban a weapon mid-match; everyone holding it is switched to the fallback weapon
+/// Returns the players that were switched
+pub fn ban(lobby: &mut Lobby, weapons: &impl WeaponLookup, weapon_id: u32) -> Result<Vec<u32>, &'static str> {
+    if !weapons.contains(weapon_id) {
+        return Err("Invalid weapon");
+    }
+    if is_banned(&lobby.settings, weapon_id) {
+        return Err("Weapon already banned");
+    }
+    lobby.settings.banned_weapons.push(weapon_id);
+    let Some(fallback) = fallback_weapon(lobby, weapons) else {
+        lobby.settings.banned_weapons.pop();
+        return Err("Cannot ban every weapon");
+    };
+    lobby.push_event(SyncEvent::WeaponBanned { weapon_id, banned: true });
+
+    let mut holders: Vec<u32> = lobby.players.values()
+        .filter(|p| p.current_weapon_id == weapon_id)
+        .map(|p| p.id)
+        .collect();
+    holders.sort_unstable();
+    for player in lobby.players.values_mut().filter(|p| p.carried_weapon == Some(weapon_id)) {
+        player.carried_weapon = None;
+    }
+    for player_id in &holders {
+        logic::equip_weapon(lobby, weapons, *player_id, fallback)?;
+    }
+    Ok(holders)
+}
+
+pub fn unban(lobby: &mut Lobby, weapon_id: u32) -> Result<(), &'static str> {
+    let index = lobby.settings.banned_weapons.iter()
+        .position(|id| *id == weapon_id)
+        .ok_or("Weapon not banned")?;
+    lobby.settings.banned_weapons.remove(index);
+    lobby.push_event(SyncEvent::WeaponBanned { weapon_id, banned: false });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::lobbies::add_player;
+    use crate::utils::weapondb::WeaponDb;
+
+    #[test]
+    fn test_ban_switches_holders_and_blocks_switches() {
+        let mut lobby = Lobby::new("BANS".to_string(), 4, "world".to_string());
+        let weapons = WeaponDb::load();
+        add_player(&mut lobby, 1, "P1".to_string(), 1, &weapons).unwrap();
+        add_player(&mut lobby, 2, "P2".to_string(), 1, &weapons).unwrap();
+        logic::switch_weapon(&mut lobby, &weapons, 2, 2).unwrap();
+
+        assert_eq!(ban(&mut lobby, &weapons, 1), Ok(vec![1]));
+        assert_eq!(lobby.players[&1].current_weapon_id, 2);
+        assert_eq!(lobby.players[&2].current_weapon_id, 2);
+        assert_eq!(logic::switch_weapon(&mut lobby, &weapons, 2, 1), Err("Weapon is banned"));
+        assert_eq!(ban(&mut lobby, &weapons, 1), Err("Weapon already banned"));
+
+        for id in [2, 3] {
+            ban(&mut lobby, &weapons, id).unwrap();
+        }
+        assert_eq!(ban(&mut lobby, &weapons, 4), Err("Cannot ban every weapon"));
+        assert_eq!(lobby.players[&1].current_weapon_id, 4);
+
+        assert_eq!(unban(&mut lobby, 1), Ok(()));
+        assert!(matches!(lobby.pending_events.last(), Some(SyncEvent::WeaponBanned { weapon_id: 1, banned: false })));
+        assert!(logic::switch_weapon(&mut lobby, &weapons, 2, 1).is_ok());
+        assert_eq!(unban(&mut lobby, 1), Err("Weapon not banned"));
+    }
+}
@@ -0,0 +1,166 @@
+use crate::state::lobby::{GameMode, Lobby};
+use crate::utils::buffers::SyncEvent;
+use std::collections::HashMap;
+
+/// Points earned per second of holding the zone alone
+pub const POINTS_PER_SEC: f32 = 1.0;
+
+/// How often `zone_state` repeats while nothing changes but points accrue
+const BROADCAST_INTERVAL_SECS: f32 = 1.0;
+
+/// Who is in the capture zone and the points each player has earned this match
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ZoneControl {
+    /// Only player in the zone, earning points
+    pub holder: Option<u32>,
+    /// More than one player in the zone; nobody earns
+    pub contested: bool,
+    pub occupants: Vec<u32>,
+    pub points: HashMap<u32, u32>,
+    progress: HashMap<u32, f32>, // Fraction of the next point
+    since_broadcast: f32,
+}
+
+impl ZoneControl {
+    /// Points by player, highest first
+    pub fn standings(&self) -> Vec<(u32, u32)> {
+        let mut standings: Vec<(u32, u32)> = self.points.iter().map(|(id, points)| (*id, *points)).collect();
+        standings.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        standings
+    }
+}
+
+/// Advance zone control by one tick of a live king-of-the-hill match
+/// Zone points also count toward the holder's score; returns the winner once someone reaches the limit
+pub fn update(lobby: &mut Lobby, dt: f32) -> Option<u32> {
+    if lobby.settings.game_mode != GameMode::KingOfTheHill || !lobby.is_match_live() {
+        return None;
+    }
+    let zone = lobby.scene_data.capture_zone?;
+
+    let mut occupants: Vec<u32> = lobby.players.values()
+        .filter(|p| p.id != 999 && !p.is_dead && zone.contains(p.position)) // Exclude dummy bot
+        .map(|p| p.id)
+        .collect();
+    occupants.sort_unstable();
+    let holder = match occupants.as_slice() {
+        [only] => Some(*only),
+        _ => None,
+    };
+    let contested = occupants.len() > 1;
+
+    let control = &mut lobby.zone;
+    let changed = control.holder != holder || control.contested != contested || control.occupants != occupants;
+    control.holder = holder;
+    control.contested = contested;
+    control.occupants = occupants;
+    control.since_broadcast += dt;
+
+    let mut earned = 0;
+    if let Some(holder) = holder {
+        let progress = control.progress.entry(holder).or_default();
+        *progress += dt * POINTS_PER_SEC;
+        earned = progress.floor() as u32;
+        *progress -= earned as f32;
+        *control.points.entry(holder).or_default() += earned;
+    }
+
+    if changed || (holder.is_some() && control.since_broadcast >= BROADCAST_INTERVAL_SECS) {
+        control.since_broadcast = 0.0;
+        let event = SyncEvent::ZoneState {
+            holder,
+            contested,
+            occupants: control.occupants.clone(),
+            points: control.standings(),
+            score_limit: lobby.settings.zone_score_limit,
+        };
+        lobby.push_event(event);
+    }
+
+    let holder = holder?;
+    if earned > 0 {
+        if let Some(player) = lobby.players.get_mut(&holder) {
+            player.score += earned;
+        }
+    }
+    let points = lobby.zone.points.get(&holder).copied().unwrap_or(0);
+    (points >= lobby.settings.zone_score_limit).then_some(holder)
+}
+
+/// Drop a leaving player from the zone
+pub fn forget(lobby: &mut Lobby, player_id: u32) {
+    let control = &mut lobby.zone;
+    control.occupants.retain(|id| *id != player_id);
+    control.points.remove(&player_id);
+    control.progress.remove(&player_id);
+    if control.holder == Some(player_id) {
+        control.holder = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::lobby::{LobbySettings, MatchPhase, Player};
+
+    fn koth_lobby(score_limit: u32) -> Lobby {
+        let settings = LobbySettings { game_mode: GameMode::KingOfTheHill, zone_score_limit: score_limit, ..Default::default() };
+        let mut lobby = Lobby::with_settings("TEST".to_string(), 4, "arena".to_string(), settings);
+        lobby.phase = MatchPhase::InProgress;
+        for id in [1, 2] {
+            let mut player = Player::new_player(id, format!("P{}", id), 1, 20);
+            player.position = (50.0, 1.0, 50.0);
+            lobby.players.insert(id, player);
+        }
+        lobby
+    }
+
+    fn zone_events(lobby: &mut Lobby) -> usize {
+        let count = lobby.pending_events.iter().filter(|e| matches!(e, SyncEvent::ZoneState { .. })).count();
+        lobby.pending_events.clear();
+        count
+    }
+
+    #[test]
+    fn test_holder_earns_and_contest_freezes() {
+        let mut lobby = koth_lobby(100);
+        assert_eq!(update(&mut lobby, 0.5), None);
+        assert_eq!(zone_events(&mut lobby), 0); // Empty zone, nothing to report
+
+        lobby.players.get_mut(&1).unwrap().position = (1.0, 1.0, 1.0);
+        for _ in 0..4 {
+            update(&mut lobby, 0.5);
+        }
+        assert_eq!(lobby.zone.holder, Some(1));
+        assert_eq!(lobby.zone.points[&1], 2);
+        assert_eq!(lobby.players[&1].score, 2);
+        assert_eq!(zone_events(&mut lobby), 2); // Capture, then one repeat per second
+
+        lobby.players.get_mut(&2).unwrap().position = (-1.0, 1.0, 0.0);
+        update(&mut lobby, 5.0);
+        assert!(lobby.zone.contested && lobby.zone.holder.is_none());
+        assert_eq!(lobby.zone.points[&1], 2);
+        assert_eq!(zone_events(&mut lobby), 1);
+
+        // The dead don't hold zones
+        lobby.players.get_mut(&1).unwrap().is_dead = true;
+        update(&mut lobby, 1.0);
+        assert_eq!(lobby.zone.holder, Some(2));
+        assert_eq!(lobby.zone.standings(), vec![(1, 2), (2, 1)]);
+    }
+
+    #[test]
+    fn test_reaching_limit_wins_only_in_koth() {
+        let mut lobby = koth_lobby(3);
+        lobby.players.get_mut(&2).unwrap().position = (0.0, 0.0, 0.0);
+        assert_eq!(update(&mut lobby, 2.0), None);
+        assert_eq!(update(&mut lobby, 1.0), Some(2));
+
+        forget(&mut lobby, 2);
+        assert!(lobby.zone.holder.is_none() && lobby.zone.points.is_empty());
+
+        lobby.settings.game_mode = GameMode::Deathmatch;
+        assert_eq!(update(&mut lobby, 10.0), None);
+        assert!(lobby.zone.points.is_empty());
+    }
+}
@@ -0,0 +1,267 @@
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::OwnedRwLockWriteGuard;
+use crate::domain::chat::MAX_MESSAGE_LENGTH;
+use crate::domain::{messages, rebalance};
+use crate::handlers::http::AppState;
+use crate::state::commands::LobbyCommand;
+use crate::state::announcements::MAX_SCHEDULED_ANNOUNCEMENTS;
+use crate::state::lobby::{Lobby, LobbyCode, LobbySummary, MatchPhase};
+use crate::state::server_state::ServerState;
+use crate::utils::log_context;
+use crate::utils::weapondb::WeaponView;
+
+/// Rejection for admin operations naming a lobby that doesn't exist
+pub const LOBBY_NOT_FOUND: &str = "Lobby not found";
+
+/// The lobby's tick loop has stopped taking commands
+pub const LOBBY_UNAVAILABLE: &str = "Lobby not accepting commands";
+
+/// Rejection for a lobby code no lobby can have
+pub const INVALID_LOBBY_CODE: &str = "Invalid lobby code";
+
+/// Rejection for an address that keeps trying bad or unknown lobby codes
+pub const TOO_MANY_CODE_FAILURES: &str = "Too many invalid lobby codes, try again later";
+
+/// How long players moved by a merge or split have to reconnect to their new lobby
+pub const TRANSFER_GRACE: Duration = Duration::from_secs(30);
+
+/// Whether a supplied admin token matches the configured one
+/// Every byte is compared so the time taken doesn't leak how much matched
+pub fn token_matches(supplied: &str, expected: &str) -> bool {
+    supplied.len() == expected.len()
+        && supplied.bytes().zip(expected.bytes()).fold(0u8, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+/// A lobby code as given by an operator, in the stored (upper-cased) form
+pub fn lobby_code(code: &str) -> Result<LobbyCode, &'static str> {
+    ServerState::canonical_lobby_code(code).ok_or(INVALID_LOBBY_CODE)
+}
+
+/// Queue a command on a lobby's tick as an administrator
+pub async fn send_command(state: &ServerState, code: &str, cmd: LobbyCommand) -> Result<(), &'static str> {
+    let code = &lobby_code(code)?;
+    let command_tx = state.get_lobby_tx(code).ok_or(LOBBY_NOT_FOUND)?;
+    command_tx.send(log_context::traced(cmd)).await.map_err(|e| {
+        log::error!("Failed to send admin command to lobby {}: {}", code, e);
+        LOBBY_UNAVAILABLE
+    })
+}
+
+/// Every lobby's listing snapshot, by code
+pub fn lobbies(state: &ServerState) -> Vec<Arc<LobbySummary>> {
+    let mut summaries = state.lobby_summaries();
+    summaries.sort_by(|a, b| a.code.cmp(&b.code));
+    summaries
+}
+
+/// Remove a player from a lobby, telling the rest why
+pub async fn kick(state: &ServerState, code: &str, player_id: u32, reason: &str) -> Result<(), &'static str> {
+    let code = &lobby_code(code)?;
+    let summary = state.lobby_summary(code).ok_or(LOBBY_NOT_FOUND)?;
+    if !summary.players.iter().any(|(id, _)| *id == player_id) {
+        return Err("Player not in lobby");
+    }
+    let reason = if reason.is_empty() { messages::ADMIN_KICK_REASON } else { reason };
+    send_command(state, code, LobbyCommand::Kick { player_id, reason: reason.to_string() }).await
+}
+
+/// Trimmed announcement text, if it's neither empty nor over the chat limit
+fn announcement_text(message: &str) -> Result<&str, &'static str> {
+    let message = message.trim();
+    if message.is_empty() || message.chars().count() > MAX_MESSAGE_LENGTH {
+        return Err("Invalid message");
+    }
+    Ok(message)
+}
+
+/// Broadcast a server message to everyone in a lobby
+pub async fn say(state: &ServerState, code: &str, message: &str) -> Result<(), &'static str> {
+    let message = announcement_text(message)?;
+    send_command(state, code, LobbyCommand::Announce { message: message.to_string() }).await
+}
+
+/// Broadcast a server message to every lobby; returns how many lobbies took it
+pub async fn announce_all(state: &ServerState, message: &str) -> Result<usize, &'static str> {
+    let message = announcement_text(message)?;
+    let mut reached = 0;
+    for summary in state.lobby_summaries() {
+        let cmd = LobbyCommand::Announce { message: message.to_string() };
+        if send_command(state, &summary.code, cmd).await.is_ok() {
+            reached += 1;
+        }
+    }
+    log::info!("Server announcement sent to {} lobbies: {}", reached, message);
+    Ok(reached)
+}
+
+/// Announce to every lobby after `delay`, then every `repeat` until cancelled
+/// Returns the schedule id for `state.announcements.cancel`
+pub fn schedule_announcement(
+    state: Arc<ServerState>,
+    message: &str,
+    delay: Duration,
+    repeat: Option<Duration>,
+) -> Result<u64, &'static str> {
+    let message = announcement_text(message)?.to_string();
+    if repeat.is_some_and(|every| every.is_zero()) {
+        return Err("Invalid repeat interval");
+    }
+    if state.announcements.len() >= MAX_SCHEDULED_ANNOUNCEMENTS {
+        return Err("Too many scheduled announcements");
+    }
+
+    let id = state.announcements.next_id();
+    let task_state = state.clone();
+    let task = tokio::spawn(async move {
+        tokio::time::sleep(delay).await;
+        loop {
+            let _ = announce_all(&task_state, &message).await;
+            let Some(every) = repeat else { break };
+            tokio::time::sleep(every).await;
+        }
+        task_state.announcements.finish(id);
+    });
+    state.announcements.insert(id, task.abort_handle());
+    log::info!("Announcement {} scheduled in {}s (repeat: {:?})", id, delay.as_secs(), repeat);
+    Ok(id)
+}
+
+/// Write-lock two lobbies, always in code order so concurrent merges can't deadlock
+async fn lock_pair(
+    state: &ServerState,
+    from: &str,
+    into: &str,
+) -> Result<(OwnedRwLockWriteGuard<Lobby>, OwnedRwLockWriteGuard<Lobby>), &'static str> {
+    if from == into {
+        return Err("Cannot merge a lobby into itself");
+    }
+    let from_lobby = state.get_lobby(from).ok_or(LOBBY_NOT_FOUND)?;
+    let into_lobby = state.get_lobby(into).ok_or(LOBBY_NOT_FOUND)?;
+    if from < into {
+        let from_guard = from_lobby.write_owned().await;
+        Ok((from_guard, into_lobby.write_owned().await))
+    } else {
+        let into_guard = into_lobby.write_owned().await;
+        Ok((from_lobby.write_owned().await, into_guard))
+    }
+}
+
+/// Move players between two locked lobbies and point the player index at their new lobby
+fn transfer(
+    app: &AppState,
+    from: &mut Lobby,
+    into: &mut Lobby,
+    player_ids: Option<&[u32]>,
+) -> usize {
+    let moved = rebalance::transfer_out(from, player_ids);
+    let ids: Vec<u32> = moved.iter().map(|t| t.player.id).collect();
+    let overlay = into.settings.weapons.clone();
+    let from_code = from.code.clone();
+    rebalance::transfer_in(into, &from_code, moved, &WeaponView::new(&app.weapons, &overlay), TRANSFER_GRACE);
+    for player_id in &ids {
+        app.state.register_player_lobby(*player_id, &into.code);
+    }
+    ids.len()
+}
+
+/// Move every human from one lobby into another with room for them, scores intact
+/// Returns how many players moved; their clients are told to reconnect to `into`
+pub async fn merge(app: &AppState, from: &str, into: &str) -> Result<usize, &'static str> {
+    let (from, into) = (&lobby_code(from)?, &lobby_code(into)?);
+    let (mut from_guard, mut into_guard) = lock_pair(&app.state, from, into).await?;
+    rebalance::can_merge(&from_guard, &into_guard)?;
+    let moved = transfer(app, &mut from_guard, &mut into_guard, None);
+    log::info!("Merged lobby {} into {} ({} players)", from, into, moved);
+    Ok(moved)
+}
+
+/// Move half of a casual lobby's players into a new lobby with the same settings
+/// The match carries on in both; returns how many players moved
+pub async fn split(app: &AppState, code: &str, new_code: &str) -> Result<usize, &'static str> {
+    let (code, new_code) = (&lobby_code(code)?, &lobby_code(new_code)?);
+    if app.state.lobby_exists(new_code) {
+        return Err("Lobby already exists");
+    }
+    if app.state.is_draining() {
+        return Err("Server is draining");
+    }
+    let source = app.state.get_lobby(code).ok_or(LOBBY_NOT_FOUND)?;
+    let (max_players, scene, settings) = {
+        let lobby = source.read().await;
+        rebalance::split_selection(&lobby)?;
+        (lobby.max_players, lobby.scene.clone(), lobby.settings.clone())
+    };
+
+    // The new lobby starts empty; players move once both lobbies are locked
+    let lobby = Lobby::with_settings(new_code.to_string(), max_players, scene, settings);
+    crate::server::spawn_lobby(app.state.clone(), lobby, app.weapons.clone(), app.config.clone(), app.udp_socket.clone())
+        .await
+        .map_err(|_| "Lobby already exists")?;
+
+    let (mut from_guard, mut into_guard) = lock_pair(&app.state, code, new_code).await?;
+    // Picked again under the lock in case players came or went meanwhile
+    let selection = rebalance::split_selection(&from_guard)?;
+    into_guard.phase = match from_guard.phase {
+        MatchPhase::Countdown => MatchPhase::Waiting,
+        phase => phase,
+    };
+    let moved = transfer(app, &mut from_guard, &mut into_guard, Some(&selection));
+    log::info!("Split {} players from lobby {} into {}", moved, code, new_code);
+    Ok(moved)
+}
+
+/// Refuse new joins and lobbies; returns false if already draining
+pub fn drain(state: &ServerState) -> bool {
+    let started = state.start_draining();
+    if started {
+        log::info!("Drain mode entered by admin");
+    }
+    started
+}
+
+/// Drain, then exit once lobbies empty or after `grace` at the latest
+pub fn shutdown(state: &ServerState, grace: Duration) {
+    state.schedule_shutdown(grace);
+    log::info!("Shutdown scheduled by admin (grace {}s)", grace.as_secs());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_matches() {
+        assert!(token_matches("secret", "secret"));
+        assert!(!token_matches("secreT", "secret"));
+        assert!(!token_matches("secret1", "secret"));
+        assert!(!token_matches("", "secret"));
+    }
+
+    #[tokio::test]
+    async fn test_unknown_lobby_and_bad_input_rejected() {
+        let state = ServerState::new();
+        assert_eq!(kick(&state, "NOPE", 1, "").await, Err(LOBBY_NOT_FOUND));
+        assert_eq!(say(&state, "NOPE", "  ").await, Err("Invalid message"));
+        assert_eq!(say(&state, "NOPE", "Restarting soon").await, Err(LOBBY_NOT_FOUND));
+        assert!(drain(&state));
+        assert!(!drain(&state));
+    }
+
+    #[tokio::test]
+    async fn test_scheduled_announcement_cancelled() {
+        let state = Arc::new(ServerState::new());
+        assert_eq!(announce_all(&state, "").await, Err("Invalid message"));
+        assert_eq!(announce_all(&state, "Maintenance at noon").await, Ok(0));
+        assert_eq!(
+            schedule_announcement(state.clone(), "Hi", Duration::ZERO, Some(Duration::ZERO)),
+            Err("Invalid repeat interval")
+        );
+
+        let id = schedule_announcement(state.clone(), "Event starts soon", Duration::from_secs(60), None).unwrap();
+        assert_eq!(state.announcements.len(), 1);
+        assert!(state.announcements.cancel(id));
+        assert!(!state.announcements.cancel(id));
+        assert!(state.announcements.is_empty());
+    }
+}
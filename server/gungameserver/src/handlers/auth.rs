@@ -0,0 +1,234 @@
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use crate::handlers::admin;
+use crate::state::failure_limiter::FailureLimiter;
+use crate::utils::config::Config;
+
+/// Failed authentications from one address before it is refused for the rest of the window
+pub const MAX_AUTH_FAILURES: u32 = 10;
+
+/// Window failed authentications are counted over
+pub const AUTH_FAILURE_WINDOW: Duration = Duration::from_secs(60);
+
+/// What a caller may do; each role can do everything the roles below it can
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Public,  // Read-only listings and stats
+    Player,  // Creating, joining and configuring lobbies, friends and loadouts
+    Service, // Trusted backends such as a matchmaker or orchestrator
+    Admin,   // Moderation, match control and exports
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Public => "public",
+            Role::Player => "player",
+            Role::Service => "service",
+            Role::Admin => "admin",
+        }
+    }
+}
+
+/// An API key and the role it grants
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct ApiKey {
+    pub key: String,
+    pub role: Role,
+    /// Player account the key belongs to; player-scoped routes only act for it
+    #[serde(default)]
+    pub account: Option<String>,
+}
+
+/// Who a request acts as: its role and, for account keys, the account
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Caller {
+    pub role: Role,
+    pub account: Option<String>,
+}
+
+impl Caller {
+    fn anonymous(role: Role) -> Self {
+        Self { role, account: None }
+    }
+
+    /// Whether the caller may act for a player who joined with `account`
+    /// Services and admins act for any player; players only for their own account
+    pub fn acts_for(&self, account: Option<&str>) -> bool {
+        self.role >= Role::Service || (self.account.is_some() && self.account.as_deref() == account)
+    }
+}
+
+/// Who a request acts as, from its `Authorization: Bearer` header
+/// The admin token counts as an admin key; requests without one get the anonymous role
+pub fn resolve(config: &Config, headers: &HeaderMap) -> Result<Caller, StatusCode> {
+    let Some(value) = headers.get(header::AUTHORIZATION) else {
+        return Ok(Caller::anonymous(config.anonymous_role));
+    };
+    let supplied = value.to_str().ok()
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+    // Every key is checked so the time taken doesn't reveal which one matched
+    let admin_token = config.admin_token.as_deref()
+        .filter(|token| !token.is_empty())
+        .map(|token| (token, Role::Admin, None));
+    config.api_keys.iter()
+        .filter(|key| !key.key.is_empty())
+        .map(|key| (key.key.as_str(), key.role, key.account.as_deref()))
+        .chain(admin_token)
+        .fold(None, |found: Option<Caller>, (key, role, account)| match admin::token_matches(supplied, key) {
+            true if found.as_ref().is_none_or(|caller| role > caller.role) => {
+                Some(Caller { role, account: account.map(str::to_string) })
+            }
+            _ => found,
+        })
+        .ok_or(StatusCode::UNAUTHORIZED)
+}
+
+/// Whether the request may act as `required`
+/// Routes no configured key could reach are disabled (403) rather than asking for credentials
+pub fn authorize(config: &Config, headers: &HeaderMap, required: Role) -> Result<Caller, StatusCode> {
+    let reachable = config.anonymous_role >= required
+        || config.admin_token.as_deref().is_some_and(|token| !token.is_empty())
+        || config.api_keys.iter().any(|key| !key.key.is_empty() && key.role >= required);
+    if !reachable {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    let caller = resolve(config, headers)?;
+    if caller.role >= required {
+        Ok(caller)
+    } else if headers.contains_key(header::AUTHORIZATION) {
+        Err(StatusCode::FORBIDDEN)
+    } else {
+        Err(StatusCode::UNAUTHORIZED)
+    }
+}
+
+/// Failed authentications per client address, so keys can't be guessed at speed
+pub fn auth_failures() -> FailureLimiter {
+    FailureLimiter::new(MAX_AUTH_FAILURES, AUTH_FAILURE_WINDOW)
+}
+
+/// State for one route group's auth layer
+#[derive(Clone)]
+pub struct Gate {
+    pub config: Arc<Config>,
+    pub required: Role,
+    pub failures: Arc<FailureLimiter>,
+}
+
+/// 429 telling the caller when it may try again
+pub fn too_many_requests(left: Duration) -> Response {
+    let mut response = StatusCode::TOO_MANY_REQUESTS.into_response();
+    response.headers_mut().insert(header::RETRY_AFTER, HeaderValue::from(left.as_secs().max(1)));
+    response
+}
+
+/// Middleware refusing requests below the group's role; addresses that fail too often get 429
+pub async fn enforce(
+    State(gate): State<Gate>,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let addr = connect_info.map(|ConnectInfo(addr)| addr.ip());
+    let now = Instant::now();
+    if let Some(left) = gate.failures.blocked_for(addr, now) {
+        return too_many_requests(left);
+    }
+    match authorize(&gate.config, request.headers(), gate.required) {
+        Ok(_) => next.run(request).await,
+        Err(status) => {
+            if status == StatusCode::UNAUTHORIZED || request.headers().contains_key(header::AUTHORIZATION) {
+                gate.failures.record(addr, now);
+            }
+            log::debug!("Refused {} {} from {:?}: needs {} role", request.method(), request.uri().path(), addr, gate.required.as_str());
+            status.into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::IpAddr;
+
+    fn bearer(token: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::AUTHORIZATION, format!("Bearer {}", token).parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_roles_from_keys() {
+        let config = Config {
+            admin_token: Some("root".to_string()),
+            api_keys: vec![
+                ApiKey { key: "mm".to_string(), role: Role::Service, account: None },
+                ApiKey { key: "client".to_string(), role: Role::Player, account: None },
+            ],
+            anonymous_role: Role::Public,
+            ..Default::default()
+        };
+        let role = |headers: &HeaderMap| resolve(&config, headers).map(|caller| caller.role);
+        assert_eq!(role(&HeaderMap::new()), Ok(Role::Public));
+        assert_eq!(role(&bearer("mm")), Ok(Role::Service));
+        assert_eq!(role(&bearer("root")), Ok(Role::Admin));
+        assert_eq!(role(&bearer("nope")), Err(StatusCode::UNAUTHORIZED));
+
+        let required = |headers: &HeaderMap, required| authorize(&config, headers, required).map(|caller| caller.role);
+        assert_eq!(required(&HeaderMap::new(), Role::Player), Err(StatusCode::UNAUTHORIZED));
+        assert_eq!(required(&bearer("client"), Role::Player), Ok(Role::Player));
+        assert_eq!(required(&bearer("client"), Role::Service), Err(StatusCode::FORBIDDEN));
+        assert_eq!(required(&bearer("mm"), Role::Service), Ok(Role::Service));
+        assert!(required(&bearer("root"), Role::Service).is_ok());
+
+        // Nothing can reach admin routes until some admin credential exists
+        assert_eq!(authorize(&Config::default(), &bearer("root"), Role::Admin), Err(StatusCode::FORBIDDEN));
+        assert_eq!(authorize(&Config::default(), &HeaderMap::new(), Role::Player).map(|caller| caller.role), Ok(Role::Player));
+    }
+
+    #[test]
+    fn test_account_keys_act_only_for_their_account() {
+        let config = Config {
+            api_keys: vec![
+                ApiKey { key: "alice-key".to_string(), role: Role::Player, account: Some("alice".to_string()) },
+                ApiKey { key: "client".to_string(), role: Role::Player, account: None },
+                ApiKey { key: "mm".to_string(), role: Role::Service, account: None },
+            ],
+            ..Default::default()
+        };
+        let alice = resolve(&config, &bearer("alice-key")).unwrap();
+        assert_eq!(alice.account.as_deref(), Some("alice"));
+        assert!(alice.acts_for(Some("alice")));
+        assert!(!alice.acts_for(Some("bob")));
+        assert!(!alice.acts_for(None));
+
+        // Keys without an account (and anonymous callers) act for nobody
+        assert!(!resolve(&config, &bearer("client")).unwrap().acts_for(None));
+        assert!(!resolve(&config, &HeaderMap::new()).unwrap().acts_for(None));
+        assert!(resolve(&config, &bearer("mm")).unwrap().acts_for(Some("bob")));
+    }
+
+    #[test]
+    fn test_repeated_failures_are_blocked_for_the_window() {
+        let failures = auth_failures();
+        let addr = Some(IpAddr::from([10, 0, 0, 1]));
+        let now = Instant::now();
+        for _ in 0..MAX_AUTH_FAILURES {
+            assert_eq!(failures.blocked_for(addr, now), None);
+            failures.record(addr, now);
+        }
+        assert_eq!(failures.blocked_for(addr, now + Duration::from_secs(20)), Some(Duration::from_secs(40)));
+        assert_eq!(failures.blocked_for(Some(IpAddr::from([10, 0, 0, 2])), now), None);
+        assert_eq!(failures.blocked_for(addr, now + AUTH_FAILURE_WINDOW), None);
+    }
+}
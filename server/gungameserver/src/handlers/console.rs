@@ -0,0 +1,314 @@
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::TcpListener;
+use crate::handlers::admin;
+use crate::handlers::http::AppState;
+use crate::state::commands::LobbyCommand;
+
+/// Grace period `shutdown` gives lobbies to empty when none is passed
+const DEFAULT_SHUTDOWN_GRACE_SECS: u64 = 60;
+
+/// Longest console line accepted before the session is closed
+const MAX_LINE_LENGTH: usize = 1024;
+
+const HELP: &str = "commands: lobbies | kick <lobby> <player> [reason] | say <lobby> <message> | \
+pause <lobby> | resume <lobby> | end <lobby> | merge <lobby> <into> | split <lobby> <new_code> | \
+drain | shutdown [--grace <secs>] | quit";
+
+/// A parsed admin console line
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConsoleCommand {
+    Help,
+    Lobbies,
+    Kick { code: String, player_id: u32, reason: String },
+    Say { code: String, message: String },
+    Pause { code: String },
+    Resume { code: String },
+    End { code: String },
+    Merge { code: String, into: String },
+    Split { code: String, new_code: String },
+    Drain,
+    Shutdown { grace: Duration },
+    Quit,
+}
+
+/// Parse one console line
+pub fn parse(line: &str) -> Result<ConsoleCommand, &'static str> {
+    let line = line.trim();
+    let (name, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+    let rest = rest.trim();
+    let lobby_arg = |rest: &str| -> Result<String, &'static str> {
+        match rest.split_whitespace().collect::<Vec<_>>().as_slice() {
+            [code] => Ok(code.to_uppercase()),
+            _ => Err("Expected a lobby code"),
+        }
+    };
+    let lobby_pair = |rest: &str| -> Result<(String, String), &'static str> {
+        match rest.split_whitespace().collect::<Vec<_>>().as_slice() {
+            [first, second] => Ok((first.to_uppercase(), second.to_uppercase())),
+            _ => Err("Expected two lobby codes"),
+        }
+    };
+
+    match name.to_lowercase().as_str() {
+        "help" | "?" => Ok(ConsoleCommand::Help),
+        "lobbies" => Ok(ConsoleCommand::Lobbies),
+        "kick" => {
+            let mut args = rest.splitn(3, char::is_whitespace);
+            let code = args.next().filter(|code| !code.is_empty()).ok_or("Usage: kick <lobby> <player> [reason]")?;
+            let player_id = args.next()
+                .and_then(|id| id.parse().ok())
+                .ok_or("Usage: kick <lobby> <player> [reason]")?;
+            let reason = args.next().unwrap_or("").trim().to_string();
+            Ok(ConsoleCommand::Kick { code: code.to_uppercase(), player_id, reason })
+        }
+        "say" => {
+            let (code, message) = rest.split_once(char::is_whitespace).ok_or("Usage: say <lobby> <message>")?;
+            Ok(ConsoleCommand::Say { code: code.to_uppercase(), message: message.trim().to_string() })
+        }
+        "pause" => Ok(ConsoleCommand::Pause { code: lobby_arg(rest)? }),
+        "resume" => Ok(ConsoleCommand::Resume { code: lobby_arg(rest)? }),
+        "end" => Ok(ConsoleCommand::End { code: lobby_arg(rest)? }),
+        "merge" => lobby_pair(rest).map(|(code, into)| ConsoleCommand::Merge { code, into }),
+        "split" => lobby_pair(rest).map(|(code, new_code)| ConsoleCommand::Split { code, new_code }),
+        "drain" => Ok(ConsoleCommand::Drain),
+        "shutdown" => {
+            let secs = match rest.split_whitespace().collect::<Vec<_>>().as_slice() {
+                [] => DEFAULT_SHUTDOWN_GRACE_SECS,
+                ["--grace", secs] => secs.parse().map_err(|_| "Grace must be whole seconds")?,
+                _ => return Err("Usage: shutdown [--grace <secs>]"),
+            };
+            Ok(ConsoleCommand::Shutdown { grace: Duration::from_secs(secs) })
+        }
+        "quit" | "exit" => Ok(ConsoleCommand::Quit),
+        _ => Err("Unknown command (try `help`)"),
+    }
+}
+
+/// Run a parsed command against the admin API and describe the outcome
+pub async fn execute(app: &AppState, command: ConsoleCommand) -> String {
+    let state = app.state.as_ref();
+    let result = match command {
+        ConsoleCommand::Help => return HELP.to_string(),
+        ConsoleCommand::Quit => return "bye".to_string(),
+        ConsoleCommand::Lobbies => {
+            let summaries = admin::lobbies(state);
+            if summaries.is_empty() {
+                return "no lobbies".to_string();
+            }
+            return summaries.iter()
+                .map(|s| format!("{} {}/{} {} {:?}", s.code, s.players.len(), s.max_players, s.scene, s.phase))
+                .collect::<Vec<_>>()
+                .join("\n");
+        }
+        ConsoleCommand::Kick { code, player_id, reason } => admin::kick(state, &code, player_id, &reason).await,
+        ConsoleCommand::Say { code, message } => admin::say(state, &code, &message).await,
+        ConsoleCommand::Pause { code } => admin::send_command(state, &code, LobbyCommand::Pause { player_id: None }).await,
+        ConsoleCommand::Resume { code } => admin::send_command(state, &code, LobbyCommand::Resume { player_id: None }).await,
+        ConsoleCommand::End { code } => admin::send_command(state, &code, LobbyCommand::EndMatch).await,
+        ConsoleCommand::Merge { code, into } => {
+            return match admin::merge(app, &code, &into).await {
+                Ok(moved) => format!("ok: moved {} players to {}", moved, into),
+                Err(e) => format!("error: {}", e),
+            };
+        }
+        ConsoleCommand::Split { code, new_code } => {
+            return match admin::split(app, &code, &new_code).await {
+                Ok(moved) => format!("ok: moved {} players to {}", moved, new_code),
+                Err(e) => format!("error: {}", e),
+            };
+        }
+        ConsoleCommand::Drain => admin::drain(state).then_some(()).ok_or("Already draining"),
+        ConsoleCommand::Shutdown { grace } => {
+            admin::shutdown(state, grace);
+            return format!("ok: shutting down once lobbies empty or in {}s", grace.as_secs());
+        }
+    };
+    match result {
+        Ok(()) => "ok".to_string(),
+        Err(e) => format!("error: {}", e),
+    }
+}
+
+/// Serve one console connection, a command per line
+/// With `token` set, nothing but `auth <token>` is accepted until it matches
+pub async fn run_session<S>(stream: S, app: AppState, token: Option<String>) -> std::io::Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let (reader, mut writer) = tokio::io::split(stream);
+    let mut lines = BufReader::new(reader);
+    let mut authenticated = token.is_none();
+    writer.write_all(if authenticated { b"gungame admin console\n> " } else { b"auth required\n> " }).await?;
+
+    let mut raw = Vec::new();
+    loop {
+        raw.clear();
+        // Never buffer more than one byte past the limit, however long the client keeps sending
+        if (&mut lines).take(MAX_LINE_LENGTH as u64 + 1).read_until(b'\n', &mut raw).await? == 0 {
+            return Ok(());
+        }
+        if raw.len() > MAX_LINE_LENGTH {
+            writer.write_all(b"error: line too long\n").await?;
+            return Ok(());
+        }
+        let line = String::from_utf8_lossy(&raw);
+
+        let reply = if !authenticated {
+            let supplied = line.trim().strip_prefix("auth ").unwrap_or("");
+            authenticated = token.as_deref().is_some_and(|token| admin::token_matches(supplied.trim(), token));
+            if !authenticated {
+                writer.write_all(b"error: unauthorized\n").await?;
+                return Ok(());
+            }
+            "ok".to_string()
+        } else if line.trim().is_empty() {
+            String::new()
+        } else {
+            match parse(&line) {
+                Ok(ConsoleCommand::Quit) => {
+                    writer.write_all(b"bye\n").await?;
+                    return Ok(());
+                }
+                Ok(command) => execute(&app, command).await,
+                Err(e) => format!("error: {}", e),
+            }
+        };
+
+        if !reply.is_empty() {
+            writer.write_all(reply.as_bytes()).await?;
+            writer.write_all(b"\n").await?;
+        }
+        writer.write_all(b"> ").await?;
+    }
+}
+
+/// Accept console sessions on a TCP address; each must authenticate with the admin token
+pub async fn serve_tcp(listener: TcpListener, app: AppState, token: String) {
+    loop {
+        match listener.accept().await {
+            Ok((stream, addr)) => {
+                log::info!("Admin console session from {}", addr);
+                let app = app.clone();
+                let token = token.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = run_session(stream, app, Some(token)).await {
+                        log::warn!("Admin console session from {} failed: {}", addr, e);
+                    }
+                });
+            }
+            Err(e) => log::warn!("Admin console accept failed: {}", e),
+        }
+    }
+}
+
+/// Accept console sessions on a Unix socket; access is governed by the socket file's permissions
+#[cfg(unix)]
+pub async fn serve_unix(listener: tokio::net::UnixListener, app: AppState) {
+    loop {
+        match listener.accept().await {
+            Ok((stream, _)) => {
+                log::info!("Admin console session on local socket");
+                let app = app.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = run_session(stream, app, None).await {
+                        log::warn!("Admin console session failed: {}", e);
+                    }
+                });
+            }
+            Err(e) => log::warn!("Admin console accept failed: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use tokio::net::UdpSocket;
+    use crate::state::server_state::ServerState;
+    use crate::utils::config::Config;
+    use crate::utils::weapondb::WeaponDb;
+
+    #[test]
+    fn test_parse_commands() {
+        assert_eq!(parse("lobbies"), Ok(ConsoleCommand::Lobbies));
+        assert_eq!(
+            parse("kick abcd 7 camping the spawn"),
+            Ok(ConsoleCommand::Kick { code: "ABCD".to_string(), player_id: 7, reason: "camping the spawn".to_string() })
+        );
+        assert_eq!(parse("kick ABCD 7"), Ok(ConsoleCommand::Kick { code: "ABCD".to_string(), player_id: 7, reason: String::new() }));
+        assert_eq!(parse("say ABCD  Restart in 5 minutes "), Ok(ConsoleCommand::Say { code: "ABCD".to_string(), message: "Restart in 5 minutes".to_string() }));
+        assert_eq!(parse("shutdown --grace 60"), Ok(ConsoleCommand::Shutdown { grace: Duration::from_secs(60) }));
+        assert_eq!(parse("shutdown"), Ok(ConsoleCommand::Shutdown { grace: Duration::from_secs(DEFAULT_SHUTDOWN_GRACE_SECS) }));
+        assert_eq!(parse("PAUSE abcd"), Ok(ConsoleCommand::Pause { code: "ABCD".to_string() }));
+    }
+
+    #[test]
+    fn test_parse_rejects_bad_input() {
+        assert!(parse("kick ABCD seven").is_err());
+        assert!(parse("kick").is_err());
+        assert!(parse("say ABCD").is_err());
+        assert!(parse("pause").is_err());
+        assert!(parse("shutdown --grace soon").is_err());
+        assert!(parse("reboot").is_err());
+    }
+
+    async fn test_app() -> AppState {
+        AppState {
+            state: Arc::new(ServerState::new()),
+            weapons: Arc::new(WeaponDb::load()),
+            config: Arc::new(Config::default()),
+            udp_socket: Arc::new(UdpSocket::bind("127.0.0.1:0").await.unwrap()),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_session_requires_auth() {
+        let app = test_app().await;
+        let state = app.state.clone();
+        let (client, server) = tokio::io::duplex(4096);
+        let session = tokio::spawn(run_session(server, app.clone(), Some("secret".to_string())));
+
+        let (reader, mut writer) = tokio::io::split(client);
+        let mut reader = BufReader::new(reader);
+        writer.write_all(b"auth secret\nlobbies\ndrain\nquit\n").await.unwrap();
+        let mut transcript = String::new();
+        tokio::io::AsyncReadExt::read_to_string(&mut reader, &mut transcript).await.unwrap();
+        session.await.unwrap().unwrap();
+        assert!(transcript.contains("ok\n> no lobbies\n> ok\n> bye"));
+        assert!(state.is_draining());
+
+        // A wrong token ends the session before any command runs
+        let (client, server) = tokio::io::duplex(4096);
+        let session = tokio::spawn(run_session(server, app, Some("secret".to_string())));
+        let (reader, mut writer) = tokio::io::split(client);
+        let mut reader = BufReader::new(reader);
+        writer.write_all(b"lobbies\n").await.unwrap();
+        let mut transcript = String::new();
+        tokio::io::AsyncReadExt::read_to_string(&mut reader, &mut transcript).await.unwrap();
+        session.await.unwrap().unwrap();
+        assert!(transcript.ends_with("error: unauthorized\n"));
+    }
+
+    #[tokio::test]
+    async fn test_session_drops_overlong_line() {
+        let (client, server) = tokio::io::duplex(4096);
+        let session = tokio::spawn(run_session(server, test_app().await, None));
+        let (reader, mut writer) = tokio::io::split(client);
+        // No newline ever arrives; the session must give up rather than keep buffering
+        let flood = tokio::spawn(async move {
+            let chunk = vec![b'a'; MAX_LINE_LENGTH];
+            for _ in 0..64 {
+                if writer.write_all(&chunk).await.is_err() {
+                    break;
+                }
+            }
+        });
+        let mut transcript = String::new();
+        BufReader::new(reader).read_to_string(&mut transcript).await.unwrap();
+        session.await.unwrap().unwrap();
+        flood.await.unwrap();
+        assert!(transcript.ends_with("error: line too long\n"));
+    }
+}
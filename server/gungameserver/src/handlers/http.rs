@@ -1,16 +1,57 @@
 use axum::{
-    extract::{Path, State},
-    http::StatusCode,
-    response::Json,
+    extract::{ConnectInfo, Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Json, Response},
 };
-use crate::handlers::models::{CreateLobbyRequest, JoinLobbyRequest, JoinLobbyResponse, LobbyInfo, PlayerInfo};
-use crate::state::server_state::ServerState;
-use crate::domain::lobbies;
-use crate::utils::weapondb::WeaponDb;
+use crate::handlers::admin;
+use crate::handlers::auth::{self, Role};
+use crate::handlers::models::{AddFriendRequest, AnnounceRequest, AnnounceResponse, CreateLobbyRequest, DamageLogQuery, FalloffQuery, JoinFriendRequest, JoinLobbyRequest, JoinLobbyResponse, JoinPartyRequest, JoinPartyResponse, ListLobbiesQuery, LobbyInfo, LobbyLogsQuery, LobbySuggestion, MergeLobbyRequest, PartyMember, PlayerInfo, QuickJoinRequest, QuickJoinResponse, SaveLoadoutRequest, SetRulesScriptRequest, SetVipRequest, SplitLobbyRequest, StatsExportQuery, SuggestLobbiesQuery, TimelineQuery, TournamentInfo, CreateTournamentRequest, UpdateLobbyRequest};
+use crate::state::server_state::{ServerState, DRAINING_ERROR};
+use crate::state::bandwidth::PlayerBandwidth;
+use crate::state::lobby::{Lobby, LobbyCode, LobbySettings, LobbySummary, GameMode, MatchPhase, DEFAULT_MAX_HEALTH, DEFAULT_SPAWN_PROTECTION_SECS, DEFAULT_DUEL_ROUNDS, DEFAULT_DUEL_ROUND_SECS, DEFAULT_WEAPON_LADDER, DEFAULT_ZONE_SCORE_LIMIT};
+use crate::state::commands::LobbyCommand;
+use crate::state::global_stats::GlobalPlayerStats;
+use crate::state::loadouts::{self, Loadout};
+use crate::state::tournaments::{Tournament, TournamentSettings};
+use crate::tick::checkpoint::SettingsCheckpoint;
+use crate::tick::replication::ReplicationRecord;
+use crate::tick::idle;
+use crate::tick::tournaments;
+use crate::domain::{analytics, bots, latency, lifetime, lobbies, logic, rating, scripting, teams};
+use crate::domain::awards::MatchAward;
+use crate::domain::damage_log::{DamageLog, DamageRecord};
+use crate::domain::falloff::{self, FalloffPoint};
+use crate::domain::timeline::{MatchTimeline, TimelineEvent};
+use crate::utils::log_context::{lobby_logs, LobbyLogEntry, LOBBY_LOG_CAPACITY};
+use crate::utils::clock;
+use crate::utils::scenes;
+use crate::utils::weapondb::{DamageType, WeaponCategory, WeaponDb, WeaponFx, WeaponLookup, WeaponOverlay, WeaponView};
 use crate::utils::config::Config;
+use crate::utils::identity::AdvertisedAddr;
+use crate::utils::udp_socket;
+use std::net::SocketAddr;
 use std::sync::Arc;
+use utoipa::ToSchema;
 use tokio::net::UdpSocket;
 
+/// Highest max_health a lobby creator may choose
+const MAX_LOBBY_HEALTH: u32 = 1000;
+
+/// Highest king-of-the-hill score limit a lobby creator may choose
+const MAX_ZONE_SCORE_LIMIT: u32 = 1000;
+
+/// Most rounds a duel may be played over
+const MAX_DUEL_ROUNDS: u32 = 15;
+
+/// Allowed duel round time limits, in seconds
+const DUEL_ROUND_SECS_RANGE: std::ops::RangeInclusive<u32> = 10..=600;
+
+/// Tightest latency gate a lobby may set (anything lower would refuse LAN players)
+const MIN_LATENCY_GATE_MS: u32 = 20;
+
+/// Longest spawn protection a lobby may configure, in seconds
+const MAX_SPAWN_PROTECTION_SECS: f32 = 10.0;
+
 /// App state for HTTP handlers (includes server state and dependencies)
 #[derive(Clone)]
 pub struct AppState {
@@ -20,24 +61,193 @@ pub struct AppState {
     pub udp_socket: Arc<UdpSocket>,
 }
 
+/// A `:code` path segment in the stored (upper-cased) form; 400 for codes no lobby can have
+fn lobby_code(code: &str) -> Result<LobbyCode, StatusCode> {
+    ServerState::canonical_lobby_code(code).ok_or(StatusCode::BAD_REQUEST)
+}
+
+/// Check the `Authorization: Bearer` header for a key with the admin role
+/// Endpoints guarded by this are disabled (403) until an admin token or key is configured
+fn require_admin(app_state: &AppState, headers: &HeaderMap) -> Result<(), StatusCode> {
+    auth::authorize(&app_state.config, headers, Role::Admin).map(|_| ())
+}
+
+/// The account of the caller's key; callers without one have no friends of their own (403)
+fn require_account(app_state: &AppState, headers: &HeaderMap) -> Result<String, StatusCode> {
+    auth::resolve(&app_state.config, headers)?.account.ok_or(StatusCode::FORBIDDEN)
+}
+
+/// Check the caller may act for `player_id`: its key's account must be the one the player joined with
+/// Service and admin keys act for any player
+fn require_player(app_state: &AppState, headers: &HeaderMap, player_id: u32) -> Result<(), StatusCode> {
+    let caller = auth::resolve(&app_state.config, headers)?;
+    if caller.acts_for(app_state.state.accounts.account_of(player_id).as_deref()) {
+        Ok(())
+    } else {
+        log::debug!("Refused acting for player {} as {:?}", player_id, caller.account);
+        Err(StatusCode::FORBIDDEN)
+    }
+}
+
+/// Host the client used to reach us (Host header), for advertising a matching address
+fn request_host(headers: &HeaderMap) -> Option<&str> {
+    headers.get(header::HOST).and_then(|value| value.to_str().ok())
+}
+
+/// Address clients should send UDP to; configured or detected at startup, else the host they reached us by
+fn advertised_addr(app_state: &AppState) -> AdvertisedAddr {
+    app_state.state.identity()
+        .map(|identity| identity.advertised.clone())
+        .unwrap_or_else(|| AdvertisedAddr::local(&app_state.config))
+}
+
+/// Build the public view of a lobby
+fn build_lobby_info(summary: &LobbySummary, app_state: &AppState, host: Option<&str>) -> LobbyInfo {
+    let ratings: Vec<f32> = summary.players.iter()
+        .filter(|(id, _)| *id != 999 && !bots::is_bot(*id)) // Exclude bots
+        .map(|(id, _)| app_state.state.global_stats.get_rating(*id))
+        .collect();
+    let average_rating = if ratings.is_empty() {
+        None
+    } else {
+        Some(ratings.iter().sum::<f32>() / ratings.len() as f32)
+    };
+    let advertised = advertised_addr(app_state);
+
+    LobbyInfo {
+        code: summary.code.clone(),
+        player_count: summary.players.len(),
+        max_players: summary.max_players,
+        players: summary.players.iter().map(|(id, name)| PlayerInfo {
+            id: *id,
+            name: name.clone(),
+            is_bot: bots::is_bot(*id),
+        }).collect(),
+        server_ip: advertised.ip_for(host),
+        udp_port: advertised.udp_port,
+        scene: summary.scene.clone(),
+        ranked: summary.ranked,
+        max_health: summary.max_health,
+        weapon_overrides: summary.weapons.overrides.clone(),
+        region: summary.region.clone(),
+        player_region: summary.player_region.clone(),
+        average_latency_ms: summary.average_latency_ms,
+        average_rating,
+        motd: summary.motd.clone(),
+        phase: summary.phase,
+        ready_count: summary.ready_count,
+        match_id: summary.match_id,
+        reserved_slots: summary.reserved_slots,
+        game_mode: summary.game_mode,
+        max_latency_ms: summary.max_latency_ms,
+        fill_with_bots: summary.fill_with_bots,
+        target_players: summary.target_players,
+        bot_count: summary.players.iter().filter(|(id, _)| bots::is_bot(*id)).count(),
+        team_count: summary.team_count,
+    }
+}
+
 /// Thin HTTP handler: Create lobby
+#[utoipa::path(
+    post,
+    path = "/lobbies",
+    request_body = CreateLobbyRequest,
+    responses(
+        (status = 200, description = "Lobby created", body = LobbyInfo),
+        (status = 400, description = "Invalid code, weapon overrides or ladder, or game mode unsupported by the scene"),
+        (status = 409, description = "Lobby code already in use"),
+        (status = 503, description = "Server is draining for maintenance"),
+    ),
+    tag = "lobbies"
+)]
 pub async fn create_lobby(
     State(app_state): State<AppState>,
+    headers: HeaderMap,
     Json(request): Json<CreateLobbyRequest>,
 ) -> Result<Json<LobbyInfo>, StatusCode> {
-    if app_state.state.lobby_exists(&request.code) {
+    if app_state.state.is_draining() {
+        log::debug!("Refused lobby {}: {}", request.code, DRAINING_ERROR);
+        return Err(StatusCode::SERVICE_UNAVAILABLE);
+    }
+    let Some(code) = ServerState::canonical_lobby_code(&request.code) else {
+        log::debug!("Refused lobby {:?}: {}", request.code, admin::INVALID_LOBBY_CODE);
+        return Err(StatusCode::BAD_REQUEST);
+    };
+    if app_state.state.lobby_exists(&code) {
         return Err(StatusCode::CONFLICT);
     }
 
-    let max_players = request.max_players.unwrap_or(4);
+    let game_mode = request.game_mode.unwrap_or_default();
+    let max_players = request.max_players.unwrap_or(if game_mode == GameMode::Duel { 2 } else { 4 });
     let scene = request.scene.unwrap_or_else(|| "world".to_string());
+    let weapons = WeaponOverlay::resolve(&app_state.weapons, request.weapon_overrides.unwrap_or_default())
+        .map_err(|e| {
+            log::debug!("Rejected weapon overrides for lobby {}: {}", code, e);
+            StatusCode::BAD_REQUEST
+        })?;
+    let motd = lobbies::validate_motd(request.motd.as_deref().unwrap_or_default())
+        .map_err(|e| {
+            log::debug!("Rejected MOTD for lobby {}: {}", code, e);
+            StatusCode::BAD_REQUEST
+        })?;
+    let weapon_ladder = request.weapon_ladder.unwrap_or_else(|| DEFAULT_WEAPON_LADDER.to_vec());
+    logic::validate_weapon_ladder(&weapon_ladder, &WeaponView::new(&app_state.weapons, &weapons))
+        .map_err(|e| {
+            log::debug!("Rejected weapon ladder for lobby {}: {}", code, e);
+            StatusCode::BAD_REQUEST
+        })?;
+    if game_mode == GameMode::Duel && max_players != 2 {
+        log::debug!("Rejected lobby {}: duels are for exactly 2 players", code);
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    if game_mode == GameMode::KingOfTheHill && scenes::scene_data(&scene).capture_zone.is_none() {
+        log::debug!("Rejected lobby {}: scene {} has no capture zone", code, scene);
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    // At least one slot stays open to everyone
+    let reserved_slots = request.reserved_slots.unwrap_or(0).min(max_players.saturating_sub(1));
+    let open_slots = max_players - reserved_slots;
+    let settings = LobbySettings {
+        ranked: request.ranked.unwrap_or(false),
+        max_health: request.max_health
+            .unwrap_or(DEFAULT_MAX_HEALTH)
+            .clamp(1, MAX_LOBBY_HEALTH),
+        weapons: Arc::new(weapons),
+        region: request.region.unwrap_or_else(|| app_state.config.region.clone()),
+        motd,
+        spawn_protection_secs: request.spawn_protection_secs
+            .unwrap_or(DEFAULT_SPAWN_PROTECTION_SECS)
+            .clamp(0.0, MAX_SPAWN_PROTECTION_SECS),
+        spawn_protection_blocks_shooting: request.spawn_protection_blocks_shooting.unwrap_or(true),
+        weapon_ladder,
+        private: request.private.unwrap_or(false),
+        reserved_slots,
+        game_mode,
+        zone_score_limit: request.zone_score_limit
+            .unwrap_or(DEFAULT_ZONE_SCORE_LIMIT)
+            .clamp(1, MAX_ZONE_SCORE_LIMIT),
+        duel_rounds: request.duel_rounds
+            .unwrap_or(DEFAULT_DUEL_ROUNDS)
+            .clamp(1, MAX_DUEL_ROUNDS),
+        duel_round_secs: request.duel_round_secs
+            .unwrap_or(DEFAULT_DUEL_ROUND_SECS)
+            .clamp(*DUEL_ROUND_SECS_RANGE.start(), *DUEL_ROUND_SECS_RANGE.end()),
+        max_latency_ms: request.max_latency_ms.map(|ms| ms.clamp(MIN_LATENCY_GATE_MS, latency::MAX_RTT_MS as u32)),
+        fill_with_bots: request.fill_with_bots.unwrap_or(false),
+        target_players: request.target_players.unwrap_or(open_slots).min(open_slots),
+        // A single team is no teams at all
+        team_count: request.team_count.filter(|count| *count >= 2).map_or(0, |count| count.min(teams::MAX_TEAMS)),
+        weapon_drops: request.weapon_drops.unwrap_or(false),
+        damage_share_scoring: request.damage_share_scoring.unwrap_or(false),
+        banned_weapons: Vec::new(),
+        max_match_secs: request.max_match_secs.map(|secs| secs.max(lifetime::MIN_CAP_SECS)),
+        max_lifetime_secs: request.max_lifetime_secs.map(|secs| secs.max(lifetime::MIN_CAP_SECS)),
+    };
 
     // Create lobby and spawn tick loop
-    if let Err(e) = crate::server::create_lobby_with_tick(
+    if let Err(e) = crate::server::spawn_lobby(
         app_state.state.clone(),
-        request.code.clone(),
-        max_players,
-        scene.clone(),
+        Lobby::with_settings(code.clone(), max_players, scene, settings),
         app_state.weapons.clone(),
         app_state.config.clone(),
         app_state.udp_socket.clone(),
@@ -47,118 +257,428 @@ pub async fn create_lobby(
     }
 
     // Get lobby info
-    let lobby_arc = app_state.state.get_lobby(&request.code)
+    let lobby_arc = app_state.state.get_lobby(&code)
         .ok_or(StatusCode::INTERNAL_SERVER_ERROR)?;
 
     let lobby = lobby_arc.read().await;
-    let lobby_info = LobbyInfo {
-        code: lobby.code.clone(),
-        player_count: lobby.players.len(),
-        max_players: lobby.max_players,
-        players: lobby.players.values().map(|p| PlayerInfo {
-            id: p.id,
-            name: p.name.clone(),
-        }).collect(),
-        server_ip: "127.0.0.1".to_string(),
-        udp_port: app_state.config.udp_port,
-        scene: lobby.scene.clone(),
-    };
+    let lobby_info = build_lobby_info(&lobbies::summarize(&lobby), &app_state, request_host(&headers));
 
     Ok(Json(lobby_info))
 }
 
 /// Thin HTTP handler: Join lobby
+#[utoipa::path(
+    post,
+    path = "/lobbies/{code}/join",
+    params(("code" = String, Path, description = "Lobby code")),
+    request_body = JoinLobbyRequest,
+    responses(
+        (status = 200, description = "Joined lobby", body = JoinLobbyResponse),
+        (status = 400, description = "Lobby is full or the code is invalid"),
+        (status = 404, description = "Lobby not found"),
+        (status = 503, description = "Server is draining for maintenance"),
+    ),
+    tag = "lobbies"
+)]
 pub async fn join_lobby(
     State(app_state): State<AppState>,
+    headers: HeaderMap,
     Path(code): Path<String>,
     Json(request): Json<JoinLobbyRequest>,
 ) -> Result<Json<JoinLobbyResponse>, StatusCode> {
+    let code = lobby_code(&code)?;
+    let caller = auth::resolve(&app_state.config, &headers)?;
+    let player_id = app_state.state.next_player_id();
+    add_to_lobby(&app_state, &code, player_id, request.player_name, request.team, caller.account.as_deref(), request_host(&headers)).await.map(Json)
+}
+
+/// Thin HTTP handler: Join a lobby as a party
+/// Everyone gets in or nobody does
+#[utoipa::path(
+    post,
+    path = "/lobbies/{code}/join-party",
+    params(("code" = String, Path, description = "Lobby code")),
+    request_body = JoinPartyRequest,
+    responses(
+        (status = 200, description = "Whole party joined", body = JoinPartyResponse),
+        (status = 400, description = "Invalid party or lobby code, or not enough room for all members"),
+        (status = 404, description = "Lobby not found"),
+        (status = 503, description = "Server is draining for maintenance"),
+    ),
+    tag = "lobbies"
+)]
+pub async fn join_party(
+    State(app_state): State<AppState>,
+    headers: HeaderMap,
+    Path(code): Path<String>,
+    Json(request): Json<JoinPartyRequest>,
+) -> Result<Json<JoinPartyResponse>, StatusCode> {
+    if app_state.state.is_draining() {
+        log::debug!("Refused party {} joining {}: {}", request.party_id, code, DRAINING_ERROR);
+        return Err(StatusCode::SERVICE_UNAVAILABLE);
+    }
+    let code = lobby_code(&code)?;
+    let caller = auth::resolve(&app_state.config, &headers)?;
+    let account = caller.account.as_deref();
     let lobby_arc = app_state.state.get_lobby(&code)
         .ok_or(StatusCode::NOT_FOUND)?;
 
-    let player_id = app_state.state.next_player_id();
-    
+    let mut lobby = lobby_arc.write().await;
+    let overlay = lobby.settings.weapons.clone();
+    let weapons = WeaponView::new(&app_state.weapons, &overlay);
+    let members: Vec<(u32, String)> = request.player_names.iter()
+        .map(|name| (app_state.state.next_player_id(), name.clone()))
+        .collect();
+    // Recorded with the join so a replay adds the party at the same time
+    let vip = account.is_some_and(|account| app_state.state.is_vip(account));
+    let joined_at = std::time::SystemTime::now();
+    let added = clock::frozen(joined_at, || lobbies::add_party(&mut lobby, &request.party_id, &members, WeaponDb::default_weapon_id(), &weapons, vip));
+    if let Err(e) = added {
+        log::debug!("Refused party {} joining {}: {}", request.party_id, code, e);
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    // Every member acts under the caller's account, like a player joining alone with that key
+    for (player_id, name) in &members {
+        app_state.state.register_player_lobby(*player_id, &code);
+        if let Some(account) = account {
+            app_state.state.accounts.bind(*player_id, account);
+        }
+        if let Some(replicator) = app_state.state.replicator() {
+            replicator.publish(ReplicationRecord::PlayerAdded {
+                code: code.clone(),
+                player_id: *player_id,
+                name: name.clone(),
+                team: lobby.teams.team_of(*player_id),
+                vip,
+                party_id: Some(request.party_id.clone()),
+                at: Some(joined_at),
+            });
+        }
+    }
+    let summary = lobbies::summarize(&lobby);
+    let lobby_info = build_lobby_info(&summary, &app_state, request_host(&headers));
+    app_state.state.publish_summary(&code, summary);
+
+    Ok(Json(JoinPartyResponse {
+        lobby: lobby_info,
+        party_id: request.party_id,
+        members: members.into_iter()
+            .map(|(player_id, player_name)| PartyMember { player_id, player_name })
+            .collect(),
+    }))
+}
+
+/// Add a player to a lobby over HTTP (their UDP address arrives with the first packet)
+/// A player joining with an account key is bound to that account
+async fn add_to_lobby(
+    app_state: &AppState,
+    code: &str,
+    player_id: u32,
+    player_name: String,
+    team: Option<u8>,
+    account: Option<&str>,
+    host: Option<&str>,
+) -> Result<JoinLobbyResponse, StatusCode> {
+    if app_state.state.is_draining() {
+        log::debug!("Refused join of player {} to {}: {}", player_id, code, DRAINING_ERROR);
+        return Err(StatusCode::SERVICE_UNAVAILABLE);
+    }
+    let lobby_arc = app_state.state.get_lobby(code)
+        .ok_or(StatusCode::NOT_FOUND)?;
+
     // Acquire lock, add player
     let mut lobby = lobby_arc.write().await;
     
     let default_weapon = WeaponDb::default_weapon_id();
     
-    match lobbies::add_player(&mut lobby, player_id, request.player_name.clone(), default_weapon, &app_state.weapons) {
+    let overlay = lobby.settings.weapons.clone();
+    let weapons = WeaponView::new(&app_state.weapons, &overlay);
+
+    let vip = account.is_some_and(|account| app_state.state.is_vip(account));
+    let joined_at = std::time::SystemTime::now();
+    match clock::frozen(joined_at, || lobbies::add_player_as(&mut lobby, player_id, player_name.clone(), default_weapon, &weapons, vip, team)) {
         Ok(()) => {
-            let lobby_info = LobbyInfo {
-                code: lobby.code.clone(),
-                player_count: lobby.players.len(),
-                max_players: lobby.max_players,
-                players: lobby.players.values().map(|p| PlayerInfo {
-                    id: p.id,
-                    name: p.name.clone(),
-                }).collect(),
-                server_ip: "127.0.0.1".to_string(),
-                udp_port: app_state.config.udp_port,
-                scene: lobby.scene.clone(),
-            };
+            app_state.state.register_player_lobby(player_id, code);
+            if let Some(account) = account {
+                app_state.state.accounts.bind(player_id, account);
+            }
+            if let Some(replicator) = app_state.state.replicator() {
+                replicator.publish(ReplicationRecord::PlayerAdded {
+                    code: code.to_string(),
+                    player_id,
+                    name: player_name,
+                    team: lobby.teams.team_of(player_id),
+                    vip,
+                    party_id: None,
+                    at: Some(joined_at),
+                });
+            }
+            let summary = lobbies::summarize(&lobby);
+            let lobby_info = build_lobby_info(&summary, app_state, host);
+            app_state.state.publish_summary(code, summary);
 
-            Ok(Json(JoinLobbyResponse {
+            Ok(JoinLobbyResponse {
                 lobby: lobby_info,
                 player_id,
-            }))
+            })
         }
         Err(_) => Err(StatusCode::BAD_REQUEST),
     }
 }
 
+/// Thin HTTP handler: Update lobby settings (owner only)
+/// The caller's key must be for the owner's account
+#[utoipa::path(
+    patch,
+    path = "/lobbies/{code}",
+    params(("code" = String, Path, description = "Lobby code")),
+    request_body = UpdateLobbyRequest,
+    responses(
+        (status = 200, description = "Updated lobby info", body = LobbyInfo),
+        (status = 400, description = "Invalid settings"),
+        (status = 403, description = "Caller's key is not for the lobby owner's account"),
+        (status = 404, description = "Lobby not found"),
+    ),
+    tag = "lobbies"
+)]
+pub async fn update_lobby(
+    State(app_state): State<AppState>,
+    headers: HeaderMap,
+    Path(code): Path<String>,
+    Json(request): Json<UpdateLobbyRequest>,
+) -> Result<Json<LobbyInfo>, StatusCode> {
+    let code = lobby_code(&code)?;
+    let lobby_arc = app_state.state.get_lobby(&code)
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let mut lobby = lobby_arc.write().await;
+    let owner_id = lobby.owner_id.ok_or(StatusCode::FORBIDDEN)?;
+    require_player(&app_state, &headers, owner_id)?;
+
+    if let Some(motd) = &request.motd {
+        lobbies::set_motd(&mut lobby, Some(owner_id), motd)
+            .map_err(|_| StatusCode::BAD_REQUEST)?;
+        if let Some(replicator) = app_state.state.replicator() {
+            replicator.publish(ReplicationRecord::SettingsChanged {
+                code: code.clone(),
+                settings: SettingsCheckpoint::capture(&lobby.settings),
+            });
+        }
+    }
+
+    let summary = lobbies::summarize(&lobby);
+    let lobby_info = build_lobby_info(&summary, &app_state, request_host(&headers));
+    app_state.state.publish_summary(&code, summary);
+
+    Ok(Json(lobby_info))
+}
+
 /// Thin HTTP handler: Get lobby info
+#[utoipa::path(
+    get,
+    path = "/lobbies/{code}",
+    params(("code" = String, Path, description = "Lobby code")),
+    responses(
+        (status = 200, description = "Lobby info", body = LobbyInfo),
+        (status = 404, description = "Lobby not found"),
+    ),
+    tag = "lobbies"
+)]
 pub async fn get_lobby(
     State(app_state): State<AppState>,
+    headers: HeaderMap,
     Path(code): Path<String>,
 ) -> Result<Json<LobbyInfo>, StatusCode> {
+    let code = lobby_code(&code)?;
     let lobby_arc = app_state.state.get_lobby(&code)
         .ok_or(StatusCode::NOT_FOUND)?;
 
     let lobby = lobby_arc.read().await;
     
-    let lobby_info = LobbyInfo {
-        code: lobby.code.clone(),
-        player_count: lobby.players.len(),
-        max_players: lobby.max_players,
-        players: lobby.players.values().map(|p| PlayerInfo {
-            id: p.id,
-            name: p.name.clone(),
-        }).collect(),
-        server_ip: "127.0.0.1".to_string(),
-        udp_port: app_state.config.udp_port,
-        scene: lobby.scene.clone(),
-    };
+    let lobby_info = build_lobby_info(&lobbies::summarize(&lobby), &app_state, request_host(&headers));
 
     Ok(Json(lobby_info))
 }
 
 /// Thin HTTP handler: List all lobbies
+/// With `?rating=`, joinable lobbies within the player's rating band sort first
+#[utoipa::path(
+    get,
+    path = "/lobbies",
+    params(ListLobbiesQuery),
+    responses((status = 200, description = "All lobbies", body = [LobbyInfo])),
+    tag = "lobbies"
+)]
 pub async fn list_lobbies(
     State(app_state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<ListLobbiesQuery>,
 ) -> Json<Vec<LobbyInfo>> {
     let mut lobbies_info = Vec::new();
 
-    for entry in app_state.state.iter_lobbies() {
-        let lobby = entry.lobby.read().await;
-        lobbies_info.push(LobbyInfo {
-            code: lobby.code.clone(),
-            player_count: lobby.players.len(),
-            max_players: lobby.max_players,
-            players: lobby.players.values().map(|p| PlayerInfo {
-                id: p.id,
-                name: p.name.clone(),
-            }).collect(),
-            server_ip: "127.0.0.1".to_string(),
-            udp_port: app_state.config.udp_port,
-            scene: lobby.scene.clone(),
-        });
+    // Published snapshots - listing never waits on a lobby's tick
+    for summary in app_state.state.lobby_summaries().iter().filter(|s| !s.private) {
+        lobbies_info.push(build_lobby_info(summary, &app_state, request_host(&headers)));
+    }
+
+    if let Some(player_rating) = query.rating {
+        sort_by_rating_match(&mut lobbies_info, player_rating);
     }
 
     Json(lobbies_info)
 }
 
-#[derive(serde::Serialize)]
+/// Order lobbies for matchmaking: joinable first, then lobbies within the
+/// rating band (populated before empty), then closest average rating
+fn sort_by_rating_match(lobbies_info: &mut [LobbyInfo], player_rating: f32) {
+    lobbies_info.sort_by(|a, b| {
+        let key = |info: &LobbyInfo| {
+            let full = info.is_full();
+            let distance = info.average_rating.map(|r| (r - player_rating).abs());
+            let in_band = info.average_rating.is_none_or(|r| rating::within_band(r, player_rating));
+            (full, !in_band, distance.is_none(), distance.unwrap_or(0.0))
+        };
+        let (ka, kb) = (key(a), key(b));
+        (ka.0, ka.1, ka.2)
+            .cmp(&(kb.0, kb.1, kb.2))
+            .then(ka.3.total_cmp(&kb.3))
+    });
+}
+
+/// Region a client named, or else the one its address locates it in
+fn client_region(app_state: &AppState, named: Option<String>, connect_info: Option<ConnectInfo<SocketAddr>>) -> Option<String> {
+    named.or_else(|| connect_info.and_then(|ConnectInfo(addr)| app_state.state.infer_region(addr.ip())))
+}
+
+/// Thin HTTP handler: Suggest joinable lobbies for a client
+/// Ranked by expected latency from the client's region, then fullness
+#[utoipa::path(
+    get,
+    path = "/lobbies/suggest",
+    params(SuggestLobbiesQuery),
+    responses((status = 200, description = "Joinable lobbies, best first", body = [LobbySuggestion])),
+    tag = "lobbies"
+)]
+pub async fn suggest_lobbies(
+    State(app_state): State<AppState>,
+    headers: HeaderMap,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    Query(query): Query<SuggestLobbiesQuery>,
+) -> Json<Vec<LobbySuggestion>> {
+    let region = client_region(&app_state, query.client_region, connect_info);
+    let mut suggestions = Vec::new();
+
+    for summary in app_state.state.lobby_summaries().iter().filter(|s| !s.private) {
+        let info = build_lobby_info(summary, &app_state, request_host(&headers));
+        if info.is_full() {
+            continue;
+        }
+        let expected_latency_ms = latency::expected_latency(
+            &info.region,
+            info.average_latency_ms,
+            region.as_deref(),
+        );
+        suggestions.push(LobbySuggestion { lobby: info, expected_latency_ms });
+    }
+
+    rank_suggestions(&mut suggestions, region.as_deref());
+    Json(suggestions)
+}
+
+/// Latency difference (ms) treated as equivalent when ranking suggestions
+const LATENCY_BUCKET_MS: f32 = 25.0;
+
+/// Order suggestions by latency bucket, then lobbies whose players mostly share the client's region,
+/// then fuller lobbies first
+fn rank_suggestions(suggestions: &mut [LobbySuggestion], client_region: Option<&str>) {
+    suggestions.sort_by_key(|s| {
+        let bucket = (s.expected_latency_ms / LATENCY_BUCKET_MS) as u32;
+        let locals = client_region.is_some_and(|client| {
+            s.lobby.player_region.as_deref().is_some_and(|region| region.eq_ignore_ascii_case(client))
+        });
+        (bucket, !locals, std::cmp::Reverse(s.lobby.player_count))
+    });
+}
+
+/// Size of lobbies opened by quick join
+const QUICKJOIN_MAX_PLAYERS: u32 = 8;
+
+/// Thin HTTP handler: Join the best public lobby in one call, opening one if none fits
+/// Candidates rank like suggestions: expected latency from the client's region, shared player region, then fullness
+#[utoipa::path(
+    post,
+    path = "/quickjoin",
+    request_body = QuickJoinRequest,
+    responses(
+        (status = 200, description = "Joined a lobby", body = QuickJoinResponse),
+        (status = 400, description = "Invalid player name"),
+        (status = 503, description = "Server is draining for maintenance"),
+    ),
+    tag = "lobbies"
+)]
+pub async fn quick_join(
+    State(app_state): State<AppState>,
+    headers: HeaderMap,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    Json(request): Json<QuickJoinRequest>,
+) -> Result<Json<QuickJoinResponse>, StatusCode> {
+    if app_state.state.is_draining() {
+        return Err(StatusCode::SERVICE_UNAVAILABLE);
+    }
+    if !ServerState::is_valid_player_name(&request.player_name) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let host = request_host(&headers);
+    let caller = auth::resolve(&app_state.config, &headers)?;
+    let account = caller.account.as_deref();
+    let region = client_region(&app_state, request.region.clone(), connect_info);
+
+    // Duels and tournament matches are arranged, not joined at random
+    let mut candidates: Vec<LobbySuggestion> = app_state.state.lobby_summaries().iter()
+        .filter(|s| !s.private && s.game_mode != GameMode::Duel)
+        .filter(|s| request.scene.as_ref().is_none_or(|scene| *scene == s.scene))
+        .map(|s| build_lobby_info(s, &app_state, host))
+        .filter(|info| !info.is_full())
+        .map(|info| {
+            let expected_latency_ms = latency::expected_latency(&info.region, info.average_latency_ms, region.as_deref());
+            LobbySuggestion { lobby: info, expected_latency_ms }
+        })
+        .collect();
+    rank_suggestions(&mut candidates, region.as_deref());
+
+    let player_id = app_state.state.next_player_id();
+    for candidate in candidates {
+        // Lost a race for the last slot; try the next one
+        if let Ok(joined) = add_to_lobby(&app_state, &candidate.lobby.code, player_id, request.player_name.clone(), None, account, host).await {
+            return Ok(Json(QuickJoinResponse { lobby: joined.lobby, player_id, created: false }));
+        }
+    }
+
+    let code = loop {
+        let code = format!("QJ{}", &uuid::Uuid::new_v4().simple().to_string()[..8].to_ascii_uppercase());
+        if !app_state.state.lobby_exists(&code) {
+            break code;
+        }
+    };
+    let scene = request.scene.unwrap_or_else(|| "world".to_string());
+    let settings = LobbySettings { region: app_state.config.region.clone(), ..Default::default() };
+    if let Err(e) = crate::server::spawn_lobby(
+        app_state.state.clone(),
+        Lobby::with_settings(code.clone(), QUICKJOIN_MAX_PLAYERS, scene, settings),
+        app_state.weapons.clone(),
+        app_state.config.clone(),
+        app_state.udp_socket.clone(),
+    ).await {
+        log::error!("Failed to open quick join lobby: {}", e);
+        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    }
+    log::info!("Opened lobby {} for quick join of {}", code, request.player_name);
+    let joined = add_to_lobby(&app_state, &code, player_id, request.player_name, None, account, host).await?;
+    Ok(Json(QuickJoinResponse { lobby: joined.lobby, player_id, created: true }))
+}
+
+#[derive(serde::Serialize, ToSchema)]
 pub struct LeaderboardEntry {
     pub player_id: u32,
     pub name: String,
@@ -166,19 +686,206 @@ pub struct LeaderboardEntry {
     pub kills: u32,
     pub deaths: u32,
     pub killstreak: u32,
+    pub best_killstreak: u32,
+    pub shots_fired: u32,
+    pub shots_hit: u32,
+    pub accuracy: f32,
+    pub damage_dealt: u32,
+    pub damage_taken: u32,
+    /// Duel rounds won this match
+    pub round_wins: u32,
 }
 
-#[derive(serde::Serialize)]
+#[derive(serde::Serialize, ToSchema)]
 pub struct LeaderboardResponse {
     pub lobby_code: String,
     pub entries: Vec<LeaderboardEntry>,
 }
 
+#[derive(serde::Serialize, ToSchema)]
+pub struct HeatmapResponse {
+    pub lobby_code: String,
+    pub cell_size: f32,
+    pub sample_count: usize,
+    pub cells: Vec<analytics::HeatmapCell>,
+}
+
+/// Thin HTTP handler: Get the position heatmap of a lobby
+/// Counts of position samples per ground-plane grid cell, busiest first
+#[utoipa::path(
+    get,
+    path = "/lobbies/{code}/analytics/heatmap",
+    params(("code" = String, Path, description = "Lobby code")),
+    responses(
+        (status = 200, description = "Lobby heatmap", body = HeatmapResponse),
+        (status = 404, description = "Lobby not found"),
+    ),
+    tag = "lobbies"
+)]
+pub async fn get_lobby_heatmap(
+    State(app_state): State<AppState>,
+    Path(code): Path<String>,
+) -> Result<Json<HeatmapResponse>, StatusCode> {
+    let code = lobby_code(&code)?;
+    let lobby_arc = app_state.state.get_lobby(&code)
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let lobby = lobby_arc.read().await;
+
+    Ok(Json(HeatmapResponse {
+        lobby_code: code,
+        cell_size: analytics::CELL_SIZE,
+        sample_count: lobby.analytics.len(),
+        cells: lobby.analytics.heatmap(),
+    }))
+}
+
+#[derive(serde::Serialize, ToSchema)]
+pub struct TimelineResponse {
+    pub match_id: u64,
+    pub lobby_code: String,
+    pub live: bool,
+    pub from_tick: u64,
+    pub to_tick: u64,
+    pub last_tick: u64,
+    pub tick_rate_hz: u32,
+    /// Events past the per-match limit were not recorded
+    pub truncated: bool,
+    /// End-of-match awards (empty while the match is live)
+    pub awards: Vec<MatchAward>,
+    pub events: Vec<TimelineEvent>,
+}
+
+fn timeline_window(timeline: &MatchTimeline, query: &TimelineQuery, live: bool, tick_rate_hz: u32) -> TimelineResponse {
+    let from_tick = query.from_tick.unwrap_or(0);
+    let to_tick = query.to_tick.unwrap_or(timeline.last_tick);
+    TimelineResponse {
+        match_id: timeline.match_id,
+        lobby_code: timeline.lobby_code.clone(),
+        live,
+        from_tick,
+        to_tick,
+        last_tick: timeline.last_tick,
+        tick_rate_hz,
+        truncated: timeline.truncated,
+        awards: timeline.awards.clone(),
+        events: timeline.window(from_tick, to_tick).to_vec(),
+    }
+}
+
+/// Thin HTTP handler: Get the events of a match within a tick window
+/// Works for the match in progress and recently finished ones
+#[utoipa::path(
+    get,
+    path = "/matches/{id}/timeline",
+    params(("id" = u64, Path, description = "Match id"), TimelineQuery),
+    responses(
+        (status = 200, description = "Match events in the window", body = TimelineResponse),
+        (status = 400, description = "from_tick is after to_tick"),
+        (status = 404, description = "Match not found"),
+    ),
+    tag = "lobbies"
+)]
+pub async fn get_match_timeline(
+    State(app_state): State<AppState>,
+    Path(match_id): Path<u64>,
+    Query(query): Query<TimelineQuery>,
+) -> Result<Json<TimelineResponse>, StatusCode> {
+    if let (Some(from), Some(to)) = (query.from_tick, query.to_tick) {
+        if from > to {
+            return Err(StatusCode::BAD_REQUEST);
+        }
+    }
+    let tick_rate_hz = app_state.config.tick_rate_hz;
+
+    if let Some(timeline) = app_state.state.finished_timeline(match_id) {
+        return Ok(Json(timeline_window(&timeline, &query, false, tick_rate_hz)));
+    }
+
+    let lobby_code = app_state.state.live_match_lobby(match_id)
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let lobby_arc = app_state.state.get_lobby(&lobby_code)
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let lobby = lobby_arc.read().await;
+    match &lobby.timeline {
+        Some(timeline) if timeline.match_id == match_id => {
+            Ok(Json(timeline_window(timeline, &query, true, tick_rate_hz)))
+        }
+        _ => Err(StatusCode::NOT_FOUND),
+    }
+}
+
+#[derive(serde::Serialize, ToSchema)]
+pub struct DamageLogResponse {
+    pub match_id: u64,
+    pub live: bool,
+    /// Hits past the per-match limit were not recorded
+    pub truncated: bool,
+    pub entries: Vec<DamageRecord>,
+}
+
+fn damage_entries(match_id: u64, log: &DamageLog, query: &DamageLogQuery, live: bool) -> DamageLogResponse {
+    let entries = match query.player_id {
+        Some(player_id) => log.involving(player_id),
+        None => log.records().to_vec(),
+    };
+    DamageLogResponse { match_id, live, truncated: log.truncated, entries }
+}
+
+/// Thin HTTP handler: Get every hit of a match for settling disputes (admin token required)
+#[utoipa::path(
+    get,
+    path = "/matches/{id}/damage",
+    params(("id" = u64, Path, description = "Match id"), DamageLogQuery),
+    responses(
+        (status = 200, description = "Damage log of the match", body = DamageLogResponse),
+        (status = 401, description = "Missing or wrong admin token"),
+        (status = 403, description = "No admin token configured"),
+        (status = 404, description = "Match not found"),
+    ),
+    tag = "admin"
+)]
+pub async fn get_match_damage(
+    State(app_state): State<AppState>,
+    Path(match_id): Path<u64>,
+    Query(query): Query<DamageLogQuery>,
+    headers: HeaderMap,
+) -> Result<Json<DamageLogResponse>, StatusCode> {
+    require_admin(&app_state, &headers)?;
+
+    if let Some(timeline) = app_state.state.finished_timeline(match_id) {
+        return Ok(Json(damage_entries(match_id, &timeline.damage, &query, false)));
+    }
+
+    let lobby_code = app_state.state.live_match_lobby(match_id)
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let lobby_arc = app_state.state.get_lobby(&lobby_code)
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let lobby = lobby_arc.read().await;
+    match &lobby.timeline {
+        Some(timeline) if timeline.match_id == match_id => {
+            Ok(Json(damage_entries(match_id, &timeline.damage, &query, true)))
+        }
+        _ => Err(StatusCode::NOT_FOUND),
+    }
+}
+
 /// Thin HTTP handler: Get lobby leaderboard
+#[utoipa::path(
+    get,
+    path = "/lobbies/{code}/leaderboard",
+    params(("code" = String, Path, description = "Lobby code")),
+    responses(
+        (status = 200, description = "Lobby leaderboard", body = LeaderboardResponse),
+        (status = 404, description = "Lobby not found"),
+    ),
+    tag = "lobbies"
+)]
 pub async fn get_lobby_leaderboard(
     State(app_state): State<AppState>,
     Path(code): Path<String>,
 ) -> Result<Json<LeaderboardResponse>, StatusCode> {
+    let code = lobby_code(&code)?;
     let lobby_arc = app_state.state.get_lobby(&code)
         .ok_or(StatusCode::NOT_FOUND)?;
 
@@ -193,10 +900,20 @@ pub async fn get_lobby_leaderboard(
             kills: p.kills,
             deaths: p.deaths,
             killstreak: p.killstreak,
+            best_killstreak: p.match_stats.best_killstreak,
+            shots_fired: p.match_stats.shots_fired,
+            shots_hit: p.match_stats.shots_hit,
+            accuracy: p.match_stats.accuracy(),
+            damage_dealt: p.match_stats.damage_dealt,
+            damage_taken: p.match_stats.damage_taken,
+            round_wins: lobby.duel.wins.get(&p.id).copied().unwrap_or(0),
         })
         .collect();
 
-    entries.sort_by(|a, b| b.score.cmp(&a.score));
+    entries.sort_by_key(|e| std::cmp::Reverse(e.score));
+    if lobby.settings.game_mode == GameMode::Duel {
+        entries.sort_by_key(|e| std::cmp::Reverse(e.round_wins));
+    }
 
     Ok(Json(LeaderboardResponse {
         lobby_code: code,
@@ -204,7 +921,7 @@ pub async fn get_lobby_leaderboard(
     }))
 }
 
-#[derive(serde::Serialize)]
+#[derive(serde::Serialize, ToSchema)]
 pub struct PlayerStats {
     pub player_id: u32,
     pub name: String,
@@ -215,11 +932,25 @@ pub struct PlayerStats {
 }
 
 /// Thin HTTP handler: Get player stats
+#[utoipa::path(
+    get,
+    path = "/lobbies/{code}/players/{id}/stats",
+    params(
+        ("code" = String, Path, description = "Lobby code"),
+        ("id" = u32, Path, description = "Player id"),
+    ),
+    responses(
+        (status = 200, description = "Player stats in this lobby", body = PlayerStats),
+        (status = 404, description = "Lobby or player not found"),
+    ),
+    tag = "players"
+)]
 pub async fn get_player_stats(
     State(app_state): State<AppState>,
-    Path((_code, player_id)): Path<(String, u32)>,
+    Path((code, player_id)): Path<(String, u32)>,
 ) -> Result<Json<PlayerStats>, StatusCode> {
-    let lobby_arc = app_state.state.get_lobby(&_code)
+    let code = lobby_code(&code)?;
+    let lobby_arc = app_state.state.get_lobby(&code)
         .ok_or(StatusCode::NOT_FOUND)?;
 
     let lobby = lobby_arc.read().await;
@@ -243,7 +974,84 @@ pub async fn get_player_stats(
     }))
 }
 
-#[derive(serde::Serialize)]
+#[derive(serde::Serialize, ToSchema)]
+pub struct PlayerStateResponse {
+    pub player_id: u32,
+    pub name: String,
+    pub current_health: u32,
+    pub max_health: u32,
+    pub current_weapon_id: u32,
+    pub current_ammo: u32,
+    pub max_ammo: u32,
+    /// Weapon heat from sustained fire, 0.0 to 1.0
+    pub heat: f32,
+    pub overheated: bool,
+    pub is_reloading: bool,
+    pub is_dead: bool,
+    #[schema(value_type = [f32; 3])]
+    pub position: (f32, f32, f32),
+    #[schema(value_type = [f32; 3])]
+    pub rotation: (f32, f32, f32),
+    pub score: u32,
+    pub kills: u32,
+    pub deaths: u32,
+    pub killstreak: u32,
+    pub anticheat_strikes: u32,
+    /// Latest lobby-wide event id; clients compare it with the last one they saw to spot gaps
+    pub last_event_id: u64,
+}
+
+/// Thin HTTP handler: Get a player's live state in a lobby
+#[utoipa::path(
+    get,
+    path = "/lobbies/{code}/players/{id}",
+    params(
+        ("code" = String, Path, description = "Lobby code"),
+        ("id" = u32, Path, description = "Player id"),
+    ),
+    responses(
+        (status = 200, description = "Live player state", body = PlayerStateResponse),
+        (status = 404, description = "Lobby or player not found"),
+    ),
+    tag = "players"
+)]
+pub async fn get_player_state(
+    State(app_state): State<AppState>,
+    Path((code, player_id)): Path<(String, u32)>,
+) -> Result<Json<PlayerStateResponse>, StatusCode> {
+    let code = lobby_code(&code)?;
+    let lobby_arc = app_state.state.get_lobby(&code)
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let lobby = lobby_arc.read().await;
+
+    let player = lobby.players.get(&player_id)
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    Ok(Json(PlayerStateResponse {
+        player_id: player.id,
+        name: player.name.clone(),
+        current_health: player.current_health,
+        max_health: player.max_health,
+        current_weapon_id: player.current_weapon_id,
+        current_ammo: player.current_ammo,
+        max_ammo: player.max_ammo,
+        heat: player.heat,
+        overheated: player.overheated_until.is_some(),
+        is_reloading: player.is_reloading,
+        is_dead: player.is_dead,
+        position: player.position,
+        rotation: player.rotation,
+        score: player.score,
+        kills: player.kills,
+        deaths: player.deaths,
+        killstreak: player.killstreak,
+        anticheat_strikes: player.anticheat_strikes,
+        last_event_id: lobby.last_event_id,
+    }))
+}
+
+#[derive(serde::Serialize, ToSchema)]
 pub struct GlobalLeaderboardEntry {
     pub player_id: u32,
     pub name: String,
@@ -252,9 +1060,16 @@ pub struct GlobalLeaderboardEntry {
     pub total_score: u32,
     pub games_played: u32,
     pub kdratio: f32,
+    pub rating: f32,
 }
 
 /// Thin HTTP handler: Get global leaderboard (across all sessions)
+#[utoipa::path(
+    get,
+    path = "/leaderboard",
+    responses((status = 200, description = "Top players across all sessions", body = [GlobalLeaderboardEntry])),
+    tag = "players"
+)]
 pub async fn get_global_leaderboard(
     State(app_state): State<AppState>,
 ) -> Json<Vec<GlobalLeaderboardEntry>> {
@@ -276,6 +1091,7 @@ pub async fn get_global_leaderboard(
                 total_score: stats.total_score,
                 games_played: stats.games_played,
                 kdratio,
+                rating: stats.rating,
             }
         })
         .collect();
@@ -283,11 +1099,1251 @@ pub async fn get_global_leaderboard(
     Json(entries)
 }
 
+#[derive(serde::Serialize, ToSchema)]
+pub struct GlobalPlayerStatsResponse {
+    pub player_id: u32,
+    pub name: String,
+    pub total_kills: u32,
+    pub total_deaths: u32,
+    pub total_score: u32,
+    pub games_played: u32,
+    pub kdratio: f32,
+    pub rating: f32,
+    pub ranked_games: u32,
+    pub total_shots_fired: u32,
+    pub total_shots_hit: u32,
+    pub accuracy: f32,
+    pub total_damage_dealt: u32,
+    pub total_damage_taken: u32,
+    pub best_killstreak: u32,
+}
+
+/// Thin HTTP handler: Get a player's stats across all sessions
+#[utoipa::path(
+    get,
+    path = "/players/{id}/stats",
+    params(("id" = u32, Path, description = "Player id")),
+    responses(
+        (status = 200, description = "Player stats across all sessions", body = GlobalPlayerStatsResponse),
+        (status = 404, description = "Player has no recorded sessions"),
+    ),
+    tag = "players"
+)]
+pub async fn get_global_player_stats(
+    State(app_state): State<AppState>,
+    Path(player_id): Path<u32>,
+) -> Result<Json<GlobalPlayerStatsResponse>, StatusCode> {
+    let stats = app_state.state.global_stats.get_stats(player_id)
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    Ok(Json(GlobalPlayerStatsResponse {
+        player_id: stats.player_id,
+        name: stats.name.clone(),
+        total_kills: stats.total_kills,
+        total_deaths: stats.total_deaths,
+        total_score: stats.total_score,
+        games_played: stats.games_played,
+        kdratio: stats.kdratio(),
+        rating: stats.rating,
+        ranked_games: stats.ranked_games,
+        total_shots_fired: stats.total_shots_fired,
+        total_shots_hit: stats.total_shots_hit,
+        accuracy: stats.accuracy(),
+        total_damage_dealt: stats.total_damage_dealt,
+        total_damage_taken: stats.total_damage_taken,
+        best_killstreak: stats.best_killstreak,
+    }))
+}
+
+/// Players per export page unless the request asks for fewer or more
+const DEFAULT_EXPORT_PAGE: usize = 1000;
+
+/// Most players one export page may hold
+const MAX_EXPORT_PAGE: usize = 10_000;
+
+/// Columns of a CSV stats export, in order
+const EXPORT_CSV_COLUMNS: &str = "player_id,name,total_kills,total_deaths,total_score,games_played,rating,ranked_games,total_shots_fired,total_shots_hit,total_damage_dealt,total_damage_taken,best_killstreak,last_seen,created_at";
+
+/// Response header carrying the cursor of the next export page, when there is one
+const EXPORT_NEXT_HEADER: &str = "x-next-after";
+
+/// One player's raw stats as exported, with times in unix seconds
+#[derive(serde::Serialize, ToSchema)]
+pub struct StatsExportRecord {
+    pub player_id: u32,
+    pub name: String,
+    pub total_kills: u32,
+    pub total_deaths: u32,
+    pub total_score: u32,
+    pub games_played: u32,
+    pub rating: f32,
+    pub ranked_games: u32,
+    pub total_shots_fired: u32,
+    pub total_shots_hit: u32,
+    pub total_damage_dealt: u32,
+    pub total_damage_taken: u32,
+    pub best_killstreak: u32,
+    pub last_seen: u64,
+    pub created_at: u64,
+}
+
+impl From<GlobalPlayerStats> for StatsExportRecord {
+    fn from(stats: GlobalPlayerStats) -> Self {
+        let unix_secs = |at: std::time::SystemTime| at.duration_since(std::time::UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+        Self {
+            player_id: stats.player_id,
+            name: stats.name,
+            total_kills: stats.total_kills,
+            total_deaths: stats.total_deaths,
+            total_score: stats.total_score,
+            games_played: stats.games_played,
+            rating: stats.rating,
+            ranked_games: stats.ranked_games,
+            total_shots_fired: stats.total_shots_fired,
+            total_shots_hit: stats.total_shots_hit,
+            total_damage_dealt: stats.total_damage_dealt,
+            total_damage_taken: stats.total_damage_taken,
+            best_killstreak: stats.best_killstreak,
+            last_seen: unix_secs(stats.last_seen),
+            created_at: unix_secs(stats.created_at),
+        }
+    }
+}
+
+#[derive(serde::Serialize, ToSchema)]
+pub struct StatsExportResponse {
+    pub players: Vec<StatsExportRecord>,
+    /// Cursor for the next page (None on the last page)
+    pub next_after: Option<u32>,
+}
+
+/// Quote a CSV field if it holds a separator, quote or line break
+fn csv_field(value: &str) -> std::borrow::Cow<'_, str> {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\"")).into()
+    } else {
+        value.into()
+    }
+}
+
+/// A page of exported stats as CSV, header row first
+fn export_csv(records: &[StatsExportRecord]) -> String {
+    let mut out = String::from(EXPORT_CSV_COLUMNS);
+    out.push('\n');
+    for r in records {
+        out.push_str(&format!(
+            "{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}\n",
+            r.player_id, csv_field(&r.name), r.total_kills, r.total_deaths, r.total_score, r.games_played,
+            r.rating, r.ranked_games, r.total_shots_fired, r.total_shots_hit, r.total_damage_dealt,
+            r.total_damage_taken, r.best_killstreak, r.last_seen, r.created_at,
+        ));
+    }
+    out
+}
+
+/// Thin HTTP handler: Dump every player's global stats, a page at a time, for offline analytics (admin token required)
+/// Pages are ordered by player id; follow `next_after` (or the `x-next-after` header for CSV) until it is absent
+#[utoipa::path(
+    get,
+    path = "/admin/stats/export",
+    params(StatsExportQuery),
+    responses(
+        (status = 200, description = "One page of player stats, as JSON or CSV", body = StatsExportResponse),
+        (status = 400, description = "Unknown format"),
+        (status = 401, description = "Missing or wrong admin token"),
+        (status = 403, description = "No admin token configured"),
+    ),
+    tag = "admin"
+)]
+pub async fn export_global_stats(
+    State(app_state): State<AppState>,
+    Query(query): Query<StatsExportQuery>,
+    headers: HeaderMap,
+) -> Result<Response, StatusCode> {
+    require_admin(&app_state, &headers)?;
+    let csv = match query.format.as_deref() {
+        None | Some("json") => false,
+        Some("csv") => true,
+        Some(_) => return Err(StatusCode::BAD_REQUEST),
+    };
+
+    let limit = query.limit.unwrap_or(DEFAULT_EXPORT_PAGE).clamp(1, MAX_EXPORT_PAGE);
+    // One extra record tells whether another page follows
+    let mut page = app_state.state.global_stats.page_by_id(query.after, limit + 1);
+    let next_after = (page.len() > limit).then(|| {
+        page.truncate(limit);
+        page[limit - 1].player_id
+    });
+    let players: Vec<StatsExportRecord> = page.into_iter().map(StatsExportRecord::from).collect();
+
+    let mut response = if csv {
+        ([(header::CONTENT_TYPE, "text/csv; charset=utf-8")], export_csv(&players)).into_response()
+    } else {
+        Json(StatsExportResponse { players, next_after }).into_response()
+    };
+    if let Some(after) = next_after {
+        response.headers_mut().insert(EXPORT_NEXT_HEADER, after.into());
+    }
+    Ok(response)
+}
+
+/// Thin HTTP handler: End the current match in a lobby
+/// Ranked lobbies apply rating changes from the final standings
+#[utoipa::path(
+    post,
+    path = "/lobbies/{code}/end",
+    params(("code" = String, Path, description = "Lobby code")),
+    responses(
+        (status = 202, description = "Command queued on the lobby tick"),
+        (status = 404, description = "Lobby not found"),
+    ),
+    tag = "admin"
+)]
+pub async fn end_match(
+    State(app_state): State<AppState>,
+    Path(code): Path<String>,
+) -> StatusCode {
+    send_admin_command(&app_state, &code, LobbyCommand::EndMatch).await
+}
+
+/// Thin HTTP handler: Pause a lobby (admin)
+#[utoipa::path(
+    post,
+    path = "/lobbies/{code}/pause",
+    params(("code" = String, Path, description = "Lobby code")),
+    responses(
+        (status = 202, description = "Command queued on the lobby tick"),
+        (status = 404, description = "Lobby not found"),
+    ),
+    tag = "admin"
+)]
+pub async fn pause_lobby(
+    State(app_state): State<AppState>,
+    Path(code): Path<String>,
+) -> StatusCode {
+    send_admin_command(&app_state, &code, LobbyCommand::Pause { player_id: None }).await
+}
+
+/// Thin HTTP handler: Resume a paused lobby (admin)
+#[utoipa::path(
+    post,
+    path = "/lobbies/{code}/resume",
+    params(("code" = String, Path, description = "Lobby code")),
+    responses(
+        (status = 202, description = "Command queued on the lobby tick"),
+        (status = 404, description = "Lobby not found"),
+    ),
+    tag = "admin"
+)]
+pub async fn resume_lobby(
+    State(app_state): State<AppState>,
+    Path(code): Path<String>,
+) -> StatusCode {
+    send_admin_command(&app_state, &code, LobbyCommand::Resume { player_id: None }).await
+}
+
+/// Thin HTTP handler: Upload a lobby's rules script (admin token required)
+/// Takes effect at once between matches, otherwise when the current match ends
+#[utoipa::path(
+    put,
+    path = "/lobbies/{code}/script",
+    params(("code" = String, Path, description = "Lobby code")),
+    request_body = SetRulesScriptRequest,
+    responses(
+        (status = 202, description = "Script compiled and queued on the lobby tick"),
+        (status = 400, description = "Script too large, does not compile or defines no hooks"),
+        (status = 401, description = "Missing or wrong admin token"),
+        (status = 403, description = "No admin token configured"),
+        (status = 404, description = "Lobby not found"),
+    ),
+    tag = "admin"
+)]
+pub async fn set_rules_script(
+    State(app_state): State<AppState>,
+    Path(code): Path<String>,
+    headers: HeaderMap,
+    Json(request): Json<SetRulesScriptRequest>,
+) -> StatusCode {
+    if let Err(status) = require_admin(&app_state, &headers) {
+        return status;
+    }
+    let name = request.name.unwrap_or_else(|| "custom".to_string());
+    if scripting::RulesScript::compile(&name, &request.source).is_err() {
+        return StatusCode::BAD_REQUEST;
+    }
+    send_admin_command(&app_state, &code, LobbyCommand::SetRulesScript { name, source: Some(request.source) }).await
+}
+
+/// Thin HTTP handler: Remove a lobby's rules script (admin token required)
+#[utoipa::path(
+    delete,
+    path = "/lobbies/{code}/script",
+    params(("code" = String, Path, description = "Lobby code")),
+    responses(
+        (status = 202, description = "Command queued on the lobby tick"),
+        (status = 401, description = "Missing or wrong admin token"),
+        (status = 403, description = "No admin token configured"),
+        (status = 404, description = "Lobby not found"),
+    ),
+    tag = "admin"
+)]
+pub async fn clear_rules_script(
+    State(app_state): State<AppState>,
+    Path(code): Path<String>,
+    headers: HeaderMap,
+) -> StatusCode {
+    if let Err(status) = require_admin(&app_state, &headers) {
+        return status;
+    }
+    send_admin_command(&app_state, &code, LobbyCommand::SetRulesScript { name: String::new(), source: None }).await
+}
+
+/// Thin HTTP handler: Ban a weapon in a lobby mid-match; holders are switched off it (admin token required)
+#[utoipa::path(
+    put,
+    path = "/lobbies/{code}/banned-weapons/{id}",
+    params(("code" = String, Path, description = "Lobby code"), ("id" = u32, Path, description = "Weapon id")),
+    responses(
+        (status = 202, description = "Command queued on the lobby tick"),
+        (status = 400, description = "Unknown weapon"),
+        (status = 401, description = "Missing or wrong admin token"),
+        (status = 403, description = "No admin token configured"),
+        (status = 404, description = "Lobby not found"),
+    ),
+    tag = "admin"
+)]
+pub async fn ban_weapon(
+    State(app_state): State<AppState>,
+    Path((code, weapon_id)): Path<(String, u32)>,
+    headers: HeaderMap,
+) -> StatusCode {
+    if let Err(status) = require_admin(&app_state, &headers) {
+        return status;
+    }
+    if !app_state.weapons.contains(weapon_id) {
+        return StatusCode::BAD_REQUEST;
+    }
+    send_admin_command(&app_state, &code, LobbyCommand::BanWeapon { player_id: None, weapon_id, banned: true }).await
+}
+
+/// Thin HTTP handler: Lift a lobby's ban on a weapon (admin token required)
+#[utoipa::path(
+    delete,
+    path = "/lobbies/{code}/banned-weapons/{id}",
+    params(("code" = String, Path, description = "Lobby code"), ("id" = u32, Path, description = "Weapon id")),
+    responses(
+        (status = 202, description = "Command queued on the lobby tick"),
+        (status = 401, description = "Missing or wrong admin token"),
+        (status = 403, description = "No admin token configured"),
+        (status = 404, description = "Lobby not found"),
+    ),
+    tag = "admin"
+)]
+pub async fn unban_weapon(
+    State(app_state): State<AppState>,
+    Path((code, weapon_id)): Path<(String, u32)>,
+    headers: HeaderMap,
+) -> StatusCode {
+    if let Err(status) = require_admin(&app_state, &headers) {
+        return status;
+    }
+    send_admin_command(&app_state, &code, LobbyCommand::BanWeapon { player_id: None, weapon_id, banned: false }).await
+}
+
+/// Status for a rejected merge or split
+fn rebalance_status(error: &'static str) -> StatusCode {
+    match error {
+        admin::LOBBY_NOT_FOUND => StatusCode::NOT_FOUND,
+        admin::INVALID_LOBBY_CODE => StatusCode::BAD_REQUEST,
+        _ => StatusCode::CONFLICT,
+    }
+}
+
+/// Thin HTTP handler: Move every player of a lobby into another (admin)
+/// Players keep their scores and are told to reconnect to the target lobby
+#[utoipa::path(
+    post,
+    path = "/lobbies/{code}/merge",
+    params(("code" = String, Path, description = "Lobby the players leave")),
+    request_body = MergeLobbyRequest,
+    responses(
+        (status = 204, description = "Players moved"),
+        (status = 404, description = "Lobby not found"),
+        (status = 409, description = "Target lacks room or has a different game mode"),
+    ),
+    tag = "admin"
+)]
+pub async fn merge_lobby(
+    State(app_state): State<AppState>,
+    Path(code): Path<String>,
+    Json(request): Json<MergeLobbyRequest>,
+) -> StatusCode {
+    match admin::merge(&app_state, &code, &request.into).await {
+        Ok(_) => StatusCode::NO_CONTENT,
+        Err(e) => rebalance_status(e),
+    }
+}
+
+/// Thin HTTP handler: Move half of a casual lobby's players into a new lobby (admin)
+#[utoipa::path(
+    post,
+    path = "/lobbies/{code}/split",
+    params(("code" = String, Path, description = "Lobby to split")),
+    request_body = SplitLobbyRequest,
+    responses(
+        (status = 201, description = "New lobby created and players moved"),
+        (status = 400, description = "Invalid lobby code"),
+        (status = 404, description = "Lobby not found"),
+        (status = 409, description = "Ranked, too few players, or the new code is taken"),
+    ),
+    tag = "admin"
+)]
+pub async fn split_lobby(
+    State(app_state): State<AppState>,
+    Path(code): Path<String>,
+    Json(request): Json<SplitLobbyRequest>,
+) -> StatusCode {
+    match admin::split(&app_state, &code, &request.new_code).await {
+        Ok(_) => StatusCode::CREATED,
+        Err(e) => rebalance_status(e),
+    }
+}
+
+/// Lines returned by the lobby log endpoint when no limit is given
+const DEFAULT_LOG_LIMIT: usize = 100;
+
+#[derive(serde::Serialize, ToSchema)]
+pub struct LobbyLogsResponse {
+    pub code: String,
+    pub entries: Vec<LobbyLogEntry>,
+}
+
+/// Thin HTTP handler: Recent log lines of one lobby, oldest first (admin)
+/// Lines logged while handling a client packet carry its correlation id
+#[utoipa::path(
+    get,
+    path = "/lobbies/{code}/logs",
+    params(("code" = String, Path, description = "Lobby code"), LobbyLogsQuery),
+    responses(
+        (status = 200, description = "Recent lobby log lines", body = LobbyLogsResponse),
+        (status = 404, description = "Lobby not found"),
+    ),
+    tag = "admin"
+)]
+pub async fn get_lobby_logs(
+    State(app_state): State<AppState>,
+    Path(code): Path<String>,
+    Query(query): Query<LobbyLogsQuery>,
+) -> Result<Json<LobbyLogsResponse>, StatusCode> {
+    let code = lobby_code(&code)?;
+    if app_state.state.get_lobby(&code).is_none() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    let limit = query.limit.unwrap_or(DEFAULT_LOG_LIMIT).min(LOBBY_LOG_CAPACITY);
+    let entries = lobby_logs().recent(&code, limit);
+    Ok(Json(LobbyLogsResponse { code, entries }))
+}
+
+/// Thin HTTP handler: Grant or revoke VIP priority joins for an account (admin)
+/// Players joining with the account's key may take a lobby's reserved slots when it is otherwise full
+#[utoipa::path(
+    put,
+    path = "/accounts/{account}/vip",
+    params(("account" = String, Path, description = "Account named by a player key")),
+    request_body = SetVipRequest,
+    responses((status = 204, description = "VIP flag updated")),
+    tag = "admin"
+)]
+pub async fn set_player_vip(
+    State(app_state): State<AppState>,
+    Path(account): Path<String>,
+    Json(request): Json<SetVipRequest>,
+) -> StatusCode {
+    app_state.state.set_vip(&account, request.vip);
+    log::info!("Account {} VIP set to {}", account, request.vip);
+    StatusCode::NO_CONTENT
+}
+
+/// Thin HTTP handler: Put the server in drain mode for maintenance (admin)
+/// Joins and new lobbies are refused; the process exits once every lobby has emptied
+#[utoipa::path(
+    post,
+    path = "/drain",
+    responses(
+        (status = 202, description = "Drain mode entered"),
+        (status = 409, description = "Already draining"),
+    ),
+    tag = "admin"
+)]
+pub async fn start_drain(State(app_state): State<AppState>) -> StatusCode {
+    if !admin::drain(&app_state.state) {
+        return StatusCode::CONFLICT;
+    }
+    StatusCode::ACCEPTED
+}
+
+/// Thin HTTP handler: Broadcast a message to every client in every lobby (admin token required)
+/// With send_at or repeat_every_mins the message is sent by a background task instead
+#[utoipa::path(
+    post,
+    path = "/announce",
+    request_body = AnnounceRequest,
+    responses(
+        (status = 200, description = "Announcement sent", body = AnnounceResponse),
+        (status = 202, description = "Announcement scheduled", body = AnnounceResponse),
+        (status = 400, description = "Empty or overlong message, or zero repeat interval"),
+        (status = 401, description = "Missing or wrong admin token"),
+        (status = 403, description = "No admin token configured"),
+        (status = 429, description = "Too many scheduled announcements"),
+    ),
+    tag = "admin"
+)]
+pub async fn announce(
+    State(app_state): State<AppState>,
+    headers: HeaderMap,
+    Json(request): Json<AnnounceRequest>,
+) -> Result<(StatusCode, Json<AnnounceResponse>), StatusCode> {
+    require_admin(&app_state, &headers)?;
+
+    let now = std::time::SystemTime::now();
+    let delay = request.send_at
+        .map(|at| std::time::UNIX_EPOCH + std::time::Duration::from_secs(at))
+        .and_then(|at| at.duration_since(now).ok())
+        .unwrap_or_default();
+    let repeat = request.repeat_every_mins.map(|mins| std::time::Duration::from_secs(mins as u64 * 60));
+    let status = |e: &'static str| match e {
+        "Too many scheduled announcements" => StatusCode::TOO_MANY_REQUESTS,
+        _ => StatusCode::BAD_REQUEST,
+    };
+
+    if delay.is_zero() && repeat.is_none() {
+        let reached = admin::announce_all(&app_state.state, &request.message).await.map_err(status)?;
+        return Ok((StatusCode::OK, Json(AnnounceResponse { lobbies_reached: Some(reached), schedule_id: None })));
+    }
+    let id = admin::schedule_announcement(app_state.state.clone(), &request.message, delay, repeat).map_err(status)?;
+    Ok((StatusCode::ACCEPTED, Json(AnnounceResponse { lobbies_reached: None, schedule_id: Some(id) })))
+}
+
+/// Thin HTTP handler: Cancel a scheduled or repeating announcement (admin token required)
+#[utoipa::path(
+    delete,
+    path = "/announce/{id}",
+    params(("id" = u64, Path, description = "Schedule id from POST /announce")),
+    responses(
+        (status = 204, description = "Announcement cancelled"),
+        (status = 401, description = "Missing or wrong admin token"),
+        (status = 403, description = "No admin token configured"),
+        (status = 404, description = "No such scheduled announcement"),
+    ),
+    tag = "admin"
+)]
+pub async fn cancel_announcement(
+    State(app_state): State<AppState>,
+    Path(id): Path<u64>,
+    headers: HeaderMap,
+) -> StatusCode {
+    if let Err(status) = require_admin(&app_state, &headers) {
+        return status;
+    }
+    if app_state.state.announcements.cancel(id) { StatusCode::NO_CONTENT } else { StatusCode::NOT_FOUND }
+}
+
+fn tournament_info(tournament: &Tournament) -> Result<TournamentInfo, StatusCode> {
+    let bracket = tournament.bracket.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok(TournamentInfo {
+        id: tournament.id,
+        name: tournament.name.clone(),
+        champion: bracket.champion().map(str::to_string),
+        rounds: bracket.rounds.clone(),
+    })
+}
+
+/// Thin HTTP handler: Start a knockout tournament (admin token required)
+/// Each match gets its own duel lobby once both entrants are known; winners advance as matches end
+#[utoipa::path(
+    post,
+    path = "/tournaments",
+    request_body = CreateTournamentRequest,
+    responses(
+        (status = 201, description = "Tournament created", body = TournamentInfo),
+        (status = 400, description = "Too few or too many participants, or empty or duplicate names"),
+        (status = 401, description = "Missing or wrong admin token"),
+        (status = 403, description = "No admin token configured"),
+        (status = 429, description = "Too many tournaments"),
+        (status = 503, description = "Server is draining for maintenance"),
+    ),
+    tag = "tournaments"
+)]
+pub async fn create_tournament(
+    State(app_state): State<AppState>,
+    headers: HeaderMap,
+    Json(request): Json<CreateTournamentRequest>,
+) -> Result<(StatusCode, Json<TournamentInfo>), StatusCode> {
+    require_admin(&app_state, &headers)?;
+    if app_state.state.is_draining() {
+        return Err(StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    let settings = TournamentSettings {
+        scene: request.scene.unwrap_or_else(|| "world".to_string()),
+        duel_rounds: request.duel_rounds.unwrap_or(DEFAULT_DUEL_ROUNDS).clamp(1, MAX_DUEL_ROUNDS),
+    };
+    let tournament = app_state.state.tournaments.create(request.name, &request.participants, settings)
+        .map_err(|e| {
+            log::debug!("Rejected tournament: {}", e);
+            match e {
+                "Too many tournaments" => StatusCode::TOO_MANY_REQUESTS,
+                _ => StatusCode::BAD_REQUEST,
+            }
+        })?;
+    log::info!("Tournament {} ({}) created with {} participants", tournament.id, tournament.name, request.participants.len());
+    tournaments::spawn_runner(
+        app_state.state.clone(),
+        tournament.clone(),
+        app_state.weapons.clone(),
+        app_state.config.clone(),
+        app_state.udp_socket.clone(),
+    );
+    Ok((StatusCode::CREATED, Json(tournament_info(&tournament)?)))
+}
+
+/// Thin HTTP handler: Get a tournament's bracket (for overlays)
+#[utoipa::path(
+    get,
+    path = "/tournaments/{id}",
+    params(("id" = u64, Path, description = "Tournament id")),
+    responses(
+        (status = 200, description = "Bracket state", body = TournamentInfo),
+        (status = 404, description = "Tournament not found"),
+    ),
+    tag = "tournaments"
+)]
+pub async fn get_tournament(
+    State(app_state): State<AppState>,
+    Path(id): Path<u64>,
+) -> Result<Json<TournamentInfo>, StatusCode> {
+    let tournament = app_state.state.tournaments.get(id).ok_or(StatusCode::NOT_FOUND)?;
+    tournament_info(&tournament).map(Json)
+}
+
+#[derive(serde::Serialize, ToSchema)]
+pub struct StatusResponse {
+    /// Stable across restarts (see Config::identity_path)
+    pub server_id: Option<String>,
+    pub lobby_count: usize,
+    pub player_count: usize,
+    /// Refusing joins ahead of a shutdown (see POST /drain)
+    pub draining: bool,
+    pub max_player_bytes_per_sec: Option<u64>,
+    pub bandwidth: Vec<PlayerBandwidth>,
+}
+
+/// Thin HTTP handler: Server status with per-player bandwidth
+#[utoipa::path(
+    get,
+    path = "/status",
+    responses((status = 200, description = "Server status", body = StatusResponse)),
+    tag = "admin"
+)]
+pub async fn get_status(
+    State(app_state): State<AppState>,
+) -> Json<StatusResponse> {
+    Json(StatusResponse {
+        server_id: app_state.state.identity().map(|identity| identity.server_id.clone()),
+        lobby_count: app_state.state.lobby_count(),
+        player_count: app_state.state.player_lobby_index.len(),
+        draining: app_state.state.is_draining(),
+        max_player_bytes_per_sec: app_state.config.max_player_bytes_per_sec,
+        bandwidth: app_state.state.bandwidth.snapshot(),
+    })
+}
+
+/// Thin HTTP handler: Prometheus metrics for diagnosing packet loss
+/// Served unversioned at /metrics for scrapers; kernel-side figures only where the OS exposes them (Linux)
+pub async fn get_metrics(
+    State(app_state): State<AppState>,
+) -> ([(header::HeaderName, &'static str); 1], String) {
+    let mut out = String::new();
+    let mut metric = |name: &str, kind: &str, help: &str, value: u64| {
+        out.push_str(&format!("# HELP {name} {help}\n# TYPE {name} {kind}\n{name} {value}\n"));
+    };
+    metric("gungame_lobbies", "gauge", "Lobbies hosted", app_state.state.lobby_count() as u64);
+    metric("gungame_players", "gauge", "Players in lobbies", app_state.state.player_lobby_index.len() as u64);
+
+    let counters = &udp_socket::COUNTERS;
+    metric("gungame_udp_send_would_block_total", "counter", "Sends that found the socket send buffer full", counters.send_would_block());
+    metric("gungame_udp_send_errors_total", "counter", "Failed UDP sends", counters.send_errors());
+    metric("gungame_udp_recv_errors_total", "counter", "Failed UDP receives", counters.recv_errors());
+    if let Some((send, recv)) = udp_socket::buffer_sizes(&app_state.udp_socket) {
+        metric("gungame_udp_send_buffer_bytes", "gauge", "Effective SO_SNDBUF", send as u64);
+        metric("gungame_udp_recv_buffer_bytes", "gauge", "Effective SO_RCVBUF", recv as u64);
+    }
+    let port = app_state.udp_socket.local_addr().map(|addr| addr.port()).ok();
+    if let Some(stats) = port.and_then(udp_socket::os_socket_stats) {
+        metric("gungame_udp_socket_drops_total", "counter", "Datagrams the kernel dropped for this socket", stats.drops);
+        metric("gungame_udp_rx_queue_bytes", "gauge", "Bytes waiting in the receive queue", stats.rx_queue_bytes);
+        metric("gungame_udp_tx_queue_bytes", "gauge", "Bytes waiting in the send queue", stats.tx_queue_bytes);
+    }
+    if let Some(host) = udp_socket::os_udp_counters() {
+        metric("gungame_host_udp_in_errors_total", "counter", "Host-wide UDP receive errors", host.in_errors);
+        metric("gungame_host_udp_rcvbuf_errors_total", "counter", "Host-wide UDP drops for full receive buffers", host.rcvbuf_errors);
+        metric("gungame_host_udp_sndbuf_errors_total", "counter", "Host-wide UDP drops for full send buffers", host.sndbuf_errors);
+    }
+    metric("gungame_ticks_total", "counter", "Lobby ticks run or skipped", idle::COUNTERS.ticks());
+    metric("gungame_calm_ticks_skipped_total", "counter", "Lobby ticks skipped because nothing was changing", idle::COUNTERS.skipped());
+    ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], out)
+}
+
+#[derive(serde::Serialize, ToSchema)]
+pub struct WeaponInfo {
+    pub id: u32,
+    pub name: String,
+    pub damage: u32,
+    pub fire_rate: f32,
+    pub range: f32,
+    pub reload_time: f32,
+    pub ammo: u32,
+    pub equip_time: f32,
+    pub category: WeaponCategory,
+    pub damage_type: DamageType,
+    /// Muzzle flash, sound and tracer assets for clients
+    pub fx: Option<WeaponFx>,
+}
+
+#[derive(serde::Serialize, ToSchema)]
+pub struct WeaponsResponse {
+    /// Also sent in the welcome packet; refetch when it differs from the cached one
+    pub version: String,
+    pub weapons: Vec<WeaponInfo>,
+}
+
+/// Thin HTTP handler: Weapon database with FX metadata and version hash
+#[utoipa::path(
+    get,
+    path = "/weapons",
+    responses((status = 200, description = "All weapons with FX metadata", body = WeaponsResponse)),
+    tag = "weapons"
+)]
+pub async fn list_weapons(
+    State(app_state): State<AppState>,
+) -> Json<WeaponsResponse> {
+    let weapons = app_state.weapons.all().into_iter()
+        .map(|w| WeaponInfo {
+            id: w.id,
+            name: w.name.clone(),
+            damage: w.damage,
+            fire_rate: w.fire_rate,
+            range: w.range,
+            reload_time: w.reload_time,
+            ammo: w.ammo,
+            equip_time: w.equip_time,
+            category: w.category,
+            damage_type: w.damage_type,
+            fx: app_state.weapons.fx(w.id).cloned(),
+        })
+        .collect();
+    Json(WeaponsResponse { version: app_state.weapons.version().to_string(), weapons })
+}
+
+#[derive(serde::Serialize, ToSchema)]
+pub struct WeaponFalloffResponse {
+    pub weapon_id: u32,
+    pub name: String,
+    pub range: f32,
+    pub pellets: u32,
+    pub spread: f32,
+    pub points: Vec<FalloffPoint>,
+}
+
+/// Thin HTTP handler: Damage a weapon deals over distance, for tuning
+/// Uses a lobby's weapon overrides when `lobby` is given; `format=csv` dumps the curve as CSV
+#[utoipa::path(
+    get,
+    path = "/debug/weapons/{id}/falloff",
+    params(("id" = u32, Path, description = "Weapon id"), FalloffQuery),
+    responses(
+        (status = 200, description = "Damage at the distance, or the whole curve", body = WeaponFalloffResponse),
+        (status = 400, description = "Unknown format"),
+        (status = 401, description = "Missing or wrong admin token"),
+        (status = 403, description = "Admin endpoints are disabled"),
+        (status = 404, description = "Weapon or lobby not found"),
+    ),
+    tag = "weapons"
+)]
+pub async fn get_weapon_falloff(
+    State(app_state): State<AppState>,
+    headers: HeaderMap,
+    Path(weapon_id): Path<u32>,
+    Query(query): Query<FalloffQuery>,
+) -> Result<Response, StatusCode> {
+    require_admin(&app_state, &headers)?;
+    let overlay = match &query.lobby {
+        Some(code) => {
+            let lobby_arc = app_state.state.get_lobby(code).ok_or(StatusCode::NOT_FOUND)?;
+            let lobby = lobby_arc.read().await;
+            lobby.settings.weapons.clone()
+        }
+        None => Arc::new(WeaponOverlay::default()),
+    };
+    let weapons = WeaponView::new(&app_state.weapons, &overlay);
+    let weapon = weapons.get(weapon_id).ok_or(StatusCode::NOT_FOUND)?;
+
+    let points = match query.distance {
+        Some(distance) => vec![falloff::at(weapon, distance)],
+        None => falloff::curve(weapon, query.step.unwrap_or(falloff::CURVE_STEP)),
+    };
+    match query.format.as_deref().unwrap_or("json") {
+        "json" => Ok(Json(WeaponFalloffResponse {
+            weapon_id,
+            name: weapon.name.clone(),
+            range: weapon.range,
+            pellets: weapon.pellets,
+            spread: weapon.spread,
+            points,
+        }).into_response()),
+        "csv" => Ok(([(header::CONTENT_TYPE, "text/csv")], falloff::to_csv(&points)).into_response()),
+        _ => Err(StatusCode::BAD_REQUEST),
+    }
+}
+
+#[derive(serde::Serialize, ToSchema)]
+pub struct FriendInfo {
+    pub account: String,
+    /// Player id of the friend's latest session, if they've joined since the server started
+    pub player_id: Option<u32>,
+    /// Current or last known name
+    pub name: Option<String>,
+    pub online: bool,
+    /// Lobby the friend is in (omitted for private lobbies)
+    pub lobby_code: Option<String>,
+}
+
+/// Listing snapshot of the lobby a player is in, if they're online
+fn current_lobby(app_state: &AppState, player_id: u32) -> Option<Arc<LobbySummary>> {
+    app_state.state.player_lobby_index.get(&player_id)
+        .and_then(|entry| app_state.state.lobby_summary(&entry.lobby_code))
+}
+
+/// Name in the player's current lobby, else the last recorded one
+fn known_name(app_state: &AppState, player_id: u32, lobby: Option<&LobbySummary>) -> Option<String> {
+    lobby.and_then(|s| s.players.iter().find(|(id, _)| *id == player_id).map(|(_, name)| name.clone()))
+        .or_else(|| app_state.state.global_stats.get_stats(player_id).map(|s| s.name))
+}
+
+/// Where a friend is right now, unless their lobby is private
+fn friend_info(app_state: &AppState, account: String) -> FriendInfo {
+    let player_id = app_state.state.accounts.player_of(&account);
+    let summary = player_id.and_then(|id| current_lobby(app_state, id));
+    FriendInfo {
+        account,
+        player_id,
+        name: player_id.and_then(|id| known_name(app_state, id, summary.as_deref())),
+        online: summary.is_some(),
+        lobby_code: summary.filter(|s| !s.private).map(|s| s.code.clone()),
+    }
+}
+
+#[derive(serde::Serialize, ToSchema)]
+pub struct PresenceResponse {
+    pub player_id: u32,
+    /// Current or last known name
+    pub name: Option<String>,
+    pub online: bool,
+    /// Lobby the player is in (omitted for private lobbies)
+    pub lobby_code: Option<String>,
+    /// Phase of the player's lobby while online
+    pub phase: Option<MatchPhase>,
+    /// Match being played (omitted for private lobbies)
+    pub match_id: Option<u64>,
+}
+
+/// Thin HTTP handler: Whether a player is online and what they're playing
+/// Meant for launchers and rich presence integrations; offline players report `online: false`
+#[utoipa::path(
+    get,
+    path = "/players/{id}/presence",
+    params(("id" = u32, Path, description = "Player id")),
+    responses((status = 200, description = "Player presence", body = PresenceResponse)),
+    tag = "players"
+)]
+pub async fn get_player_presence(
+    State(app_state): State<AppState>,
+    Path(player_id): Path<u32>,
+) -> Json<PresenceResponse> {
+    let summary = current_lobby(&app_state, player_id);
+    let public = summary.as_ref().filter(|s| !s.private);
+    Json(PresenceResponse {
+        player_id,
+        name: known_name(&app_state, player_id, summary.as_deref()),
+        online: summary.is_some(),
+        lobby_code: public.map(|s| s.code.clone()),
+        phase: summary.as_ref().map(|s| s.phase),
+        match_id: public.and_then(|s| s.match_id),
+    })
+}
+
+fn friends_response(app_state: &AppState, account: &str) -> Json<Vec<FriendInfo>> {
+    Json(app_state.state.friends.friends_of(account).into_iter()
+        .map(|friend| friend_info(app_state, friend))
+        .collect())
+}
+
+/// Thin HTTP handler: The caller's friends with online status
+#[utoipa::path(
+    get,
+    path = "/friends",
+    responses(
+        (status = 200, description = "Friends, by account", body = [FriendInfo]),
+        (status = 403, description = "Caller's key has no account"),
+    ),
+    tag = "friends"
+)]
+pub async fn list_friends(
+    State(app_state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<FriendInfo>>, StatusCode> {
+    let account = require_account(&app_state, &headers)?;
+    Ok(friends_response(&app_state, &account))
+}
+
+/// Thin HTTP handler: Accounts waiting for the caller to accept their friend request
+#[utoipa::path(
+    get,
+    path = "/friends/requests",
+    responses(
+        (status = 200, description = "Requesting accounts", body = [String]),
+        (status = 403, description = "Caller's key has no account"),
+    ),
+    tag = "friends"
+)]
+pub async fn list_friend_requests(
+    State(app_state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<String>>, StatusCode> {
+    let account = require_account(&app_state, &headers)?;
+    Ok(Json(app_state.state.friends.requests_for(&account)))
+}
+
+/// Thin HTTP handler: Ask an account for friendship, or accept its request
+#[utoipa::path(
+    post,
+    path = "/friends",
+    request_body = AddFriendRequest,
+    responses(
+        (status = 200, description = "Now friends; updated friend list", body = [FriendInfo]),
+        (status = 202, description = "Request sent; waiting for the other account to accept", body = [FriendInfo]),
+        (status = 400, description = "Adding yourself, or a friend or request list is full"),
+        (status = 403, description = "Caller's key has no account"),
+    ),
+    tag = "friends"
+)]
+pub async fn add_friend(
+    State(app_state): State<AppState>,
+    headers: HeaderMap,
+    Json(request): Json<AddFriendRequest>,
+) -> Result<(StatusCode, Json<Vec<FriendInfo>>), StatusCode> {
+    let account = require_account(&app_state, &headers)?;
+    let friends = app_state.state.friends.request(&account, &request.account)
+        .map_err(|e| {
+            log::debug!("Account {} could not befriend {}: {}", account, request.account, e);
+            StatusCode::BAD_REQUEST
+        })?;
+    let status = if friends { StatusCode::OK } else { StatusCode::ACCEPTED };
+    Ok((status, friends_response(&app_state, &account)))
+}
+
+/// Thin HTTP handler: Remove a friend on both sides, or decline or withdraw a request
+#[utoipa::path(
+    delete,
+    path = "/friends/{account}",
+    params(("account" = String, Path, description = "Friend or requesting account")),
+    responses(
+        (status = 200, description = "Updated friend list", body = [FriendInfo]),
+        (status = 403, description = "Caller's key has no account"),
+        (status = 404, description = "Not a friend and no pending request"),
+    ),
+    tag = "friends"
+)]
+pub async fn remove_friend(
+    State(app_state): State<AppState>,
+    headers: HeaderMap,
+    Path(friend): Path<String>,
+) -> Result<Json<Vec<FriendInfo>>, StatusCode> {
+    let account = require_account(&app_state, &headers)?;
+    app_state.state.friends.remove(&account, &friend)
+        .map_err(|_| StatusCode::NOT_FOUND)?;
+    Ok(friends_response(&app_state, &account))
+}
+
+/// Thin HTTP handler: Join the lobby a friend is playing in, as a new player bound to the caller's account
+#[utoipa::path(
+    post,
+    path = "/friends/{account}/join",
+    params(("account" = String, Path, description = "Friend to follow")),
+    request_body = JoinFriendRequest,
+    responses(
+        (status = 200, description = "Joined the friend's lobby", body = JoinLobbyResponse),
+        (status = 400, description = "Lobby is full"),
+        (status = 403, description = "Caller's key has no account, or the two aren't mutual friends"),
+        (status = 404, description = "Friend offline or in a private lobby"),
+        (status = 409, description = "Caller is still in a lobby"),
+        (status = 503, description = "Server is draining for maintenance"),
+    ),
+    tag = "friends"
+)]
+pub async fn join_friend(
+    State(app_state): State<AppState>,
+    headers: HeaderMap,
+    Path(friend): Path<String>,
+    Json(request): Json<JoinFriendRequest>,
+) -> Result<Json<JoinLobbyResponse>, StatusCode> {
+    let account = require_account(&app_state, &headers)?;
+    if !app_state.state.friends.is_friend(&account, &friend) {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    let code = friend_info(&app_state, friend).lobby_code
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let playing = app_state.state.accounts.player_of(&account)
+        .is_some_and(|player_id| app_state.state.player_lobby_index.contains_key(&player_id));
+    if playing {
+        return Err(StatusCode::CONFLICT);
+    }
+    let player_id = app_state.state.next_player_id();
+    add_to_lobby(&app_state, &code, player_id, request.player_name, None, Some(&account), request_host(&headers)).await.map(Json)
+}
+
+/// Queue an administrator command on a lobby's tick loop
+async fn send_admin_command(app_state: &AppState, code: &str, cmd: LobbyCommand) -> StatusCode {
+    match admin::send_command(&app_state.state, code, cmd).await {
+        Ok(()) => StatusCode::ACCEPTED,
+        Err(admin::LOBBY_NOT_FOUND) => StatusCode::NOT_FOUND,
+        Err(admin::INVALID_LOBBY_CODE) => StatusCode::BAD_REQUEST,
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::state::lobby::Lobby;
 
     // Note: HTTP handler tests would require full AppState setup
     // Integration tests are better suited for HTTP handlers
+
+    fn lobby_info(code: &str, player_count: usize, average_rating: Option<f32>) -> LobbyInfo {
+        LobbyInfo {
+            code: code.to_string(),
+            player_count,
+            max_players: 4,
+            players: Vec::new(),
+            server_ip: "127.0.0.1".to_string(),
+            udp_port: 8081,
+            scene: "world".to_string(),
+            ranked: true,
+            max_health: 100,
+            weapon_overrides: Default::default(),
+            region: "local".to_string(),
+            player_region: None,
+            average_latency_ms: None,
+            average_rating,
+            motd: String::new(),
+            phase: Default::default(),
+            ready_count: 0,
+            match_id: None,
+            reserved_slots: 0,
+            game_mode: Default::default(),
+            max_latency_ms: None,
+            fill_with_bots: false,
+            target_players: 0,
+            bot_count: 0,
+            team_count: 0,
+        }
+    }
+
+    #[test]
+    fn test_export_csv_quotes_names() {
+        let mut stats = GlobalPlayerStats::new(7, "Smith, \"Ace\"".to_string());
+        stats.total_kills = 3;
+        let csv = export_csv(&[StatsExportRecord::from(stats)]);
+        let mut lines = csv.lines();
+        assert_eq!(lines.next(), Some(EXPORT_CSV_COLUMNS));
+        assert!(lines.next().unwrap().starts_with("7,\"Smith, \"\"Ace\"\"\",3,0,"));
+        assert_eq!(lines.next(), None);
+    }
+
+    #[test]
+    fn test_sort_by_rating_match() {
+        let mut infos = vec![
+            lobby_info("FAR", 2, Some(1600.0)),
+            lobby_info("EMPTY", 0, None),
+            lobby_info("FULL", 4, Some(1000.0)),
+            lobby_info("CLOSE", 2, Some(1050.0)),
+            lobby_info("NEAR", 2, Some(1150.0)),
+        ];
+
+        sort_by_rating_match(&mut infos, 1000.0);
+
+        let codes: Vec<&str> = infos.iter().map(|i| i.code.as_str()).collect();
+        assert_eq!(codes, vec!["CLOSE", "NEAR", "EMPTY", "FAR", "FULL"]);
+    }
+
+    #[test]
+    fn test_rank_suggestions() {
+        let suggestion = |code: &str, player_count: usize, expected_latency_ms: f32| LobbySuggestion {
+            lobby: lobby_info(code, player_count, None),
+            expected_latency_ms,
+        };
+        let mut suggestions = vec![
+            suggestion("FAR", 3, 150.0),
+            suggestion("NEAR_EMPTY", 0, 30.0),
+            suggestion("NEAR_BUSY", 2, 40.0),
+        ];
+
+        rank_suggestions(&mut suggestions, None);
+
+        let codes: Vec<&str> = suggestions.iter().map(|s| s.lobby.code.as_str()).collect();
+        assert_eq!(codes, vec!["NEAR_BUSY", "NEAR_EMPTY", "FAR"]);
+
+        // Within a latency bucket, lobbies of players from the client's region go first
+        suggestions[1].lobby.player_region = Some("eu".to_string());
+        rank_suggestions(&mut suggestions, Some("EU"));
+        let codes: Vec<&str> = suggestions.iter().map(|s| s.lobby.code.as_str()).collect();
+        assert_eq!(codes, vec!["NEAR_EMPTY", "NEAR_BUSY", "FAR"]);
+    }
+}
+
+/// Save the loadout presets to their file, if one is attached
+/// A failed write only loses the change on restart, so it's logged rather than refused
+async fn persist_loadouts(app_state: &AppState) {
+    if let Err(e) = app_state.state.loadouts.persist().await {
+        log::warn!("Loadout change kept in memory only: {}", e);
+    }
+}
+
+/// Thin HTTP handler: A player's saved loadout presets
+/// Presets belong to the player's account; players without one have none
+#[utoipa::path(
+    get,
+    path = "/players/{id}/loadouts",
+    params(("id" = u32, Path, description = "Player id")),
+    responses((status = 200, description = "Presets in name order", body = [Loadout])),
+    tag = "loadouts"
+)]
+pub async fn list_loadouts(
+    State(app_state): State<AppState>,
+    Path(player_id): Path<u32>,
+) -> Json<Vec<Loadout>> {
+    let account = app_state.state.accounts.account_of(player_id);
+    Json(account.map(|account| app_state.state.loadouts.list(&account)).unwrap_or_default())
+}
+
+/// Thin HTTP handler: One saved loadout preset
+#[utoipa::path(
+    get,
+    path = "/players/{id}/loadouts/{name}",
+    params(
+        ("id" = u32, Path, description = "Player id"),
+        ("name" = String, Path, description = "Preset name"),
+    ),
+    responses(
+        (status = 200, description = "The preset", body = Loadout),
+        (status = 404, description = "No preset with that name"),
+    ),
+    tag = "loadouts"
+)]
+pub async fn get_loadout(
+    State(app_state): State<AppState>,
+    Path((player_id, name)): Path<(u32, String)>,
+) -> Result<Json<Loadout>, StatusCode> {
+    app_state.state.accounts.account_of(player_id)
+        .and_then(|account| app_state.state.loadouts.get(&account, &name))
+        .map(Json)
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+/// Thin HTTP handler: Create or replace a loadout preset
+/// Weapons must exist and be unlocked by the player's career kills
+#[utoipa::path(
+    put,
+    path = "/players/{id}/loadouts/{name}",
+    params(
+        ("id" = u32, Path, description = "Player id"),
+        ("name" = String, Path, description = "Preset name"),
+    ),
+    request_body = SaveLoadoutRequest,
+    responses(
+        (status = 201, description = "Preset created", body = Loadout),
+        (status = 200, description = "Preset replaced", body = Loadout),
+        (status = 400, description = "Invalid preset, or preset limit reached"),
+        (status = 403, description = "Weapon not unlocked yet, the player joined without an account, or the caller's key is not for this player's account"),
+    ),
+    tag = "loadouts"
+)]
+pub async fn save_loadout(
+    State(app_state): State<AppState>,
+    headers: HeaderMap,
+    Path((player_id, name)): Path<(u32, String)>,
+    Json(request): Json<SaveLoadoutRequest>,
+) -> Result<(StatusCode, Json<Loadout>), StatusCode> {
+    require_player(&app_state, &headers, player_id)?;
+    let account = app_state.state.accounts.account_of(player_id)
+        .ok_or(StatusCode::FORBIDDEN)?;
+    let loadout = Loadout {
+        name,
+        primary: request.primary,
+        secondary: request.secondary,
+        attachments: request.attachments,
+    };
+    let career_kills = app_state.state.global_stats.get_stats(player_id).map_or(0, |s| s.total_kills);
+    let result = loadouts::validate(&loadout, app_state.weapons.as_ref(), career_kills)
+        .and_then(|_| app_state.state.loadouts.save(&account, loadout.clone()));
+    match result {
+        Ok(created) => {
+            persist_loadouts(&app_state).await;
+            let status = if created { StatusCode::CREATED } else { StatusCode::OK };
+            Ok((status, Json(loadout)))
+        }
+        Err(e) => {
+            log::debug!("Player {} could not save loadout {}: {}", player_id, loadout.name, e);
+            if e == loadouts::NOT_UNLOCKED {
+                Err(StatusCode::FORBIDDEN)
+            } else {
+                Err(StatusCode::BAD_REQUEST)
+            }
+        }
+    }
+}
+
+/// Thin HTTP handler: Delete a loadout preset
+#[utoipa::path(
+    delete,
+    path = "/players/{id}/loadouts/{name}",
+    params(
+        ("id" = u32, Path, description = "Player id"),
+        ("name" = String, Path, description = "Preset name"),
+    ),
+    responses(
+        (status = 204, description = "Preset deleted"),
+        (status = 403, description = "Caller's key is not for this player's account"),
+        (status = 404, description = "No preset with that name"),
+    ),
+    tag = "loadouts"
+)]
+pub async fn delete_loadout(
+    State(app_state): State<AppState>,
+    headers: HeaderMap,
+    Path((player_id, name)): Path<(u32, String)>,
+) -> StatusCode {
+    if let Err(status) = require_player(&app_state, &headers, player_id) {
+        return status;
+    }
+    let Some(account) = app_state.state.accounts.account_of(player_id) else {
+        return StatusCode::NOT_FOUND;
+    };
+    match app_state.state.loadouts.remove(&account, &name) {
+        Ok(()) => {
+            persist_loadouts(&app_state).await;
+            StatusCode::NO_CONTENT
+        }
+        Err(_) => StatusCode::NOT_FOUND,
+    }
 }
@@ -1,3 +1,7 @@
 pub mod http;
 pub mod udp;
 pub mod models;
+pub mod openapi;
+pub mod admin;
+pub mod auth;
+pub mod console;
@@ -1,24 +1,259 @@
+use crate::utils::weapondb::WeaponOverride;
+use crate::domain::tournament::BracketMatch;
+use crate::state::lobby::{GameMode, MatchPhase};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use utoipa::{IntoParams, ToSchema};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct CreateLobbyRequest {
     pub code: String,
     pub max_players: Option<u32>,
     pub scene: Option<String>,
+    pub ranked: Option<bool>,
+    pub max_health: Option<u32>,
+    /// Weapon stat overrides by weapon id
+    pub weapon_overrides: Option<HashMap<u32, WeaponOverride>>,
+    /// Matchmaking region; defaults to the server's region
+    pub region: Option<String>,
+    /// MOTD/rules text shown to joining players
+    pub motd: Option<String>,
+    /// Invulnerability after respawning, in seconds (0 disables)
+    pub spawn_protection_secs: Option<f32>,
+    /// Whether spawn-protected players are barred from shooting
+    pub spawn_protection_blocks_shooting: Option<bool>,
+    /// Weapon ids players own, in next/previous switching order
+    pub weapon_ladder: Option<Vec<u32>>,
+    /// Hide the lobby from listings and friends (join by code only)
+    pub private: Option<bool>,
+    /// Slots held back for VIP players once the lobby is otherwise full
+    pub reserved_slots: Option<u32>,
+    /// King-of-the-hill needs a scene with a capture zone; duels need max_players of 2
+    pub game_mode: Option<GameMode>,
+    /// Zone points that win a king-of-the-hill match
+    pub zone_score_limit: Option<u32>,
+    /// Duels are best of this many rounds
+    pub duel_rounds: Option<u32>,
+    /// Duel round time limit, in seconds
+    pub duel_round_secs: Option<u32>,
+    /// Highest round trip, in ms, a client may measure when connecting (omit for no limit)
+    pub max_latency_ms: Option<u32>,
+    /// Add bots while humans are short of target_players, remove them as humans join
+    pub fill_with_bots: Option<bool>,
+    /// Population bots fill to; defaults to every unreserved slot
+    pub target_players: Option<u32>,
+    /// Split players into this many teams (2 to 4); omit for free-for-all
+    pub team_count: Option<u8>,
+    /// Dead players drop their weapon for others to pick up (off by default)
+    pub weapon_drops: Option<bool>,
+    /// Split each kill's score among the victim's attackers by damage dealt (off by default)
+    pub damage_share_scoring: Option<bool>,
+    /// Force-end matches running longer than this, in seconds (at least 60; omit for the server default)
+    pub max_match_secs: Option<u64>,
+    /// Close the lobby this many seconds after creation (at least 60; omit for the server default)
+    pub max_lifetime_secs: Option<u64>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct UpdateLobbyRequest {
+    pub motd: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, IntoParams)]
+pub struct ListLobbiesQuery {
+    /// Rating of the searching player; lobbies within the matchmaking band sort first
+    pub rating: Option<f32>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, IntoParams)]
+pub struct TimelineQuery {
+    /// First match tick to include (default: start of the match)
+    pub from_tick: Option<u64>,
+    /// Last match tick to include (default: latest tick)
+    pub to_tick: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, IntoParams)]
+pub struct DamageLogQuery {
+    /// Only hits dealt or taken by this player
+    pub player_id: Option<u32>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, IntoParams)]
+pub struct FalloffQuery {
+    /// Only this distance; the whole curve out to the weapon's range when unset
+    pub distance: Option<f32>,
+    /// Spacing of curve points (default 1)
+    pub step: Option<f32>,
+    /// Use this lobby's weapon overrides
+    pub lobby: Option<String>,
+    /// `json` (default) or `csv`
+    pub format: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SaveLoadoutRequest {
+    pub primary: u32,
+    /// Equipped when a lobby's ladder doesn't have the primary
+    pub secondary: Option<u32>,
+    #[serde(default)]
+    pub attachments: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SetVipRequest {
+    pub vip: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct MergeLobbyRequest {
+    /// Lobby the players move into
+    pub into: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SplitLobbyRequest {
+    /// Code for the new lobby half the players move into
+    pub new_code: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SetRulesScriptRequest {
+    /// Shown in logs; defaults to "custom"
+    pub name: Option<String>,
+    /// Rhai source defining on_kill, on_tick and/or modify_damage
+    pub source: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct AnnounceRequest {
+    pub message: String,
+    /// Unix time (seconds) to send at; now when omitted or past
+    pub send_at: Option<u64>,
+    /// Send again every this many minutes until cancelled
+    pub repeat_every_mins: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct AnnounceResponse {
+    /// Lobbies the message went to (immediate one-off announcements)
+    pub lobbies_reached: Option<usize>,
+    /// Schedule id for DELETE /announce/{id} (scheduled or repeating announcements)
+    pub schedule_id: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CreateTournamentRequest {
+    pub name: String,
+    /// Entrant player names in seed order (top seed first); each match is a duel lobby
+    pub participants: Vec<String>,
+    pub scene: Option<String>,
+    /// Every match is best of this many duel rounds
+    pub duel_rounds: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct TournamentInfo {
+    pub id: u64,
+    pub name: String,
+    /// Set once the final is decided
+    pub champion: Option<String>,
+    /// Knockout rounds, first round first; the last holds only the final
+    pub rounds: Vec<Vec<BracketMatch>>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, IntoParams)]
+pub struct LobbyLogsQuery {
+    /// Most recent lines to return (default 100)
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, IntoParams)]
+pub struct StatsExportQuery {
+    /// `json` (default) or `csv`
+    pub format: Option<String>,
+    /// Only players with a higher id; pass the previous page's `next_after` to continue
+    pub after: Option<u32>,
+    /// Players per page (default 1000, at most 10000)
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, IntoParams)]
+pub struct SuggestLobbiesQuery {
+    /// Region of the client; lobbies in other regions rank lower (inferred from its address when omitted and GeoIP is enabled)
+    pub client_region: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct LobbySuggestion {
+    pub lobby: LobbyInfo,
+    pub expected_latency_ms: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct JoinLobbyRequest {
     pub player_name: String,
+    /// Team to join if that keeps teams even; otherwise the smallest team
+    #[serde(default)]
+    pub team: Option<u8>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct QuickJoinRequest {
+    pub player_name: String,
+    /// Region of the client; lobbies in other regions rank lower (inferred from its address when omitted and GeoIP is enabled)
+    pub region: Option<String>,
+    /// Only join (or open) lobbies on this scene
+    pub scene: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct QuickJoinResponse {
+    pub lobby: LobbyInfo,
+    pub player_id: u32,
+    /// A new lobby was opened because none fit
+    pub created: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct JoinPartyRequest {
+    /// Client-chosen id shared by the party's members
+    pub party_id: String,
+    pub player_names: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct PartyMember {
+    pub player_id: u32,
+    pub player_name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct JoinPartyResponse {
+    pub lobby: LobbyInfo,
+    pub party_id: String,
+    /// In the order the names were given
+    pub members: Vec<PartyMember>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct AddFriendRequest {
+    /// Account to ask, or whose request to accept
+    pub account: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct JoinFriendRequest {
+    pub player_name: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct JoinLobbyResponse {
     pub lobby: LobbyInfo,
     pub player_id: u32,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct LobbyInfo {
     pub code: String,
     pub player_count: usize,
@@ -27,10 +262,43 @@ pub struct LobbyInfo {
     pub server_ip: String,
     pub udp_port: u16,
     pub scene: String,
+    pub ranked: bool,
+    pub max_health: u32,
+    pub weapon_overrides: HashMap<u32, WeaponOverride>,
+    pub region: String,
+    /// Region most of the players connect from, when GeoIP located them
+    pub player_region: Option<String>,
+    pub average_latency_ms: Option<f32>,
+    pub average_rating: Option<f32>,
+    pub motd: String,
+    pub phase: MatchPhase,
+    pub ready_count: usize,
+    /// Match in progress, for timeline queries
+    pub match_id: Option<u64>,
+    /// Of max_players, slots only VIP players can take
+    pub reserved_slots: u32,
+    pub game_mode: GameMode,
+    /// Clients measuring a higher RTT at connect are refused
+    pub max_latency_ms: Option<u32>,
+    pub fill_with_bots: bool,
+    pub target_players: u32,
+    /// Of player_count, how many are bots
+    pub bot_count: usize,
+    /// 0 when the lobby is free-for-all
+    pub team_count: u8,
+}
+
+impl LobbyInfo {
+    /// Full for normal joins (reserved slots count as taken)
+    pub fn is_full(&self) -> bool {
+        self.player_count + self.reserved_slots as usize >= self.max_players as usize
+    }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct PlayerInfo {
     pub id: u32,
     pub name: String,
+    /// Fill bot, not a human player
+    pub is_bot: bool,
 }
@@ -0,0 +1,156 @@
+use utoipa::OpenApi;
+use crate::handlers::http;
+use crate::utils::weapondb::{DamageType, Overheat, RampUp, WeaponCategory, WeaponFx, WeaponOverride};
+use crate::state::lobby::{GameMode, MatchPhase};
+use crate::domain::analytics::HeatmapCell;
+use crate::domain::awards::MatchAward;
+use crate::domain::falloff::FalloffPoint;
+use crate::domain::timeline::TimelineEvent;
+use crate::domain::tournament::BracketMatch;
+use crate::utils::log_context::LobbyLogEntry;
+use crate::state::bandwidth::PlayerBandwidth;
+use crate::handlers::models::{AddFriendRequest, AnnounceRequest, AnnounceResponse, CreateLobbyRequest, JoinFriendRequest, JoinLobbyRequest, JoinLobbyResponse, JoinPartyRequest, JoinPartyResponse, LobbyInfo, LobbySuggestion, MergeLobbyRequest, PartyMember, PlayerInfo, QuickJoinRequest, QuickJoinResponse, SaveLoadoutRequest, SetRulesScriptRequest, SetVipRequest, SplitLobbyRequest, TournamentInfo, CreateTournamentRequest, UpdateLobbyRequest};
+use crate::state::loadouts::Loadout;
+
+/// OpenAPI description of the HTTP lobby API, served at /docs
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "GunGame Server API", description = "Lobby management and stats API"),
+    servers((url = "/v1", description = "Current API version")),
+    paths(
+        http::create_lobby,
+        http::list_lobbies,
+        http::suggest_lobbies,
+        http::quick_join,
+        http::get_lobby,
+        http::update_lobby,
+        http::join_lobby,
+        http::join_party,
+        http::get_lobby_leaderboard,
+        http::get_lobby_heatmap,
+        http::get_match_timeline,
+        http::get_match_damage,
+        http::get_player_state,
+        http::get_player_stats,
+        http::end_match,
+        http::pause_lobby,
+        http::resume_lobby,
+        http::set_rules_script,
+        http::clear_rules_script,
+        http::ban_weapon,
+        http::unban_weapon,
+        http::merge_lobby,
+        http::split_lobby,
+        http::get_lobby_logs,
+        http::get_global_leaderboard,
+        http::get_global_player_stats,
+        http::export_global_stats,
+        http::get_player_presence,
+        http::get_status,
+        http::start_drain,
+        http::announce,
+        http::cancel_announcement,
+        http::create_tournament,
+        http::get_tournament,
+        http::set_player_vip,
+        http::list_friends,
+        http::list_friend_requests,
+        http::add_friend,
+        http::remove_friend,
+        http::join_friend,
+        http::list_loadouts,
+        http::get_loadout,
+        http::save_loadout,
+        http::delete_loadout,
+        http::list_weapons,
+        http::get_weapon_falloff,
+    ),
+    components(schemas(
+        CreateLobbyRequest,
+        JoinLobbyRequest,
+        JoinLobbyResponse,
+        JoinPartyRequest,
+        JoinPartyResponse,
+        PartyMember,
+        LobbyInfo,
+        LobbySuggestion,
+        QuickJoinRequest,
+        QuickJoinResponse,
+        MatchPhase,
+        GameMode,
+        PlayerInfo,
+        UpdateLobbyRequest,
+        WeaponOverride,
+        RampUp,
+        Overheat,
+        http::LeaderboardEntry,
+        http::LeaderboardResponse,
+        http::HeatmapResponse,
+        HeatmapCell,
+        http::TimelineResponse,
+        http::DamageLogResponse,
+        crate::domain::damage_log::DamageRecord,
+        TimelineEvent,
+        MatchAward,
+        http::PlayerStats,
+        http::PlayerStateResponse,
+        http::GlobalLeaderboardEntry,
+        http::GlobalPlayerStatsResponse,
+        http::StatsExportRecord,
+        http::StatsExportResponse,
+        http::PresenceResponse,
+        http::StatusResponse,
+        http::LobbyLogsResponse,
+        LobbyLogEntry,
+        PlayerBandwidth,
+        AddFriendRequest,
+        JoinFriendRequest,
+        SetVipRequest,
+        MergeLobbyRequest,
+        SplitLobbyRequest,
+        AnnounceRequest,
+        SetRulesScriptRequest,
+        CreateTournamentRequest,
+        TournamentInfo,
+        BracketMatch,
+        AnnounceResponse,
+        http::FriendInfo,
+        SaveLoadoutRequest,
+        Loadout,
+        http::WeaponInfo,
+        http::WeaponsResponse,
+        http::WeaponFalloffResponse,
+        FalloffPoint,
+        WeaponFx,
+        WeaponCategory,
+        DamageType,
+    )),
+    tags(
+        (name = "lobbies", description = "Create, list and join lobbies"),
+        (name = "players", description = "Live player state and stats"),
+        (name = "admin", description = "Administrator lobby commands"),
+        (name = "friends", description = "Friend lists and following friends into lobbies"),
+        (name = "loadouts", description = "Saved loadout presets per player"),
+        (name = "weapons", description = "Weapon stats and client FX metadata"),
+        (name = "tournaments", description = "Knockout brackets played out across lobbies"),
+    )
+)]
+pub struct ApiDoc;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_openapi_covers_routes() {
+        let doc = ApiDoc::openapi();
+        let paths = &doc.paths.paths;
+        assert!(paths.contains_key("/lobbies"));
+        assert!(paths.contains_key("/lobbies/{code}/players/{id}"));
+        assert!(paths.contains_key("/players/{id}/stats"));
+
+        let schemas = doc.components.unwrap().schemas;
+        assert!(schemas.contains_key("LobbyInfo"));
+        assert!(schemas.contains_key("CreateLobbyRequest"));
+    }
+}
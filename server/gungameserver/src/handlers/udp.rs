@@ -1,61 +1,73 @@
 use std::sync::Arc;
 use tokio::net::UdpSocket;
 use log::{info, warn, debug};
-use crate::state::server_state::ServerState;
+use crate::handlers::admin::{INVALID_LOBBY_CODE, LOBBY_NOT_FOUND, TOO_MANY_CODE_FAILURES};
+use crate::state::server_state::{ServerState, DRAINING_ERROR};
 use crate::state::commands::LobbyCommand;
+use crate::state::loadouts::{self, Loadout};
+use crate::state::latency_probes::LATENCY_TOO_HIGH;
+use crate::domain::votes::VoteKind;
+use crate::domain::clock_sync;
+use crate::domain::rotation;
+use crate::domain::messages::{self, Message};
+use crate::domain::validation::{self, ViolationKind};
+use crate::domain::preferences::Preferences;
+use crate::utils::identity;
+use crate::utils::log_context::{self, LogContext};
+use crate::utils::telemetry::{self, Span, SpanKind};
 use crate::utils::weapondb::WeaponDb;
-use std::collections::HashMap;
-use std::sync::atomic::{AtomicU64, Ordering};
+use crate::tick::outbound;
+use bytes::Bytes;
 
-const MAX_PACKET_SIZE: usize = 1024;
-const RATE_LIMIT_WINDOW_MS: u64 = 1000;
-const MAX_PACKETS_PER_WINDOW: u64 = 100;
+const MAX_CLIENT_ID_LENGTH: usize = 64;
+/// Longest pellet hit list a shoot packet may carry (more than any weapon fires)
+const MAX_PELLET_HITS: usize = 32;
 
-struct RateLimiter {
-    packet_counts: HashMap<std::net::SocketAddr, AtomicU64>,
-}
-
-impl RateLimiter {
-    fn new() -> Self {
-        Self {
-            packet_counts: HashMap::new(),
-        }
-    }
-
-    fn check_rate_limit(&mut self, addr: &std::net::SocketAddr) -> bool {
-        let count = self.packet_counts
-            .entry(addr.clone())
-            .or_insert_with(|| AtomicU64::new(0));
-        
-        let current = count.fetch_add(1, Ordering::Relaxed);
-        current < MAX_PACKETS_PER_WINDOW
-    }
-
-    fn cleanup(&mut self) {
-    }
+/// Serialize a packet into datagrams small enough to send (see `outbound::split_datagram`)
+fn datagrams(packet: &serde_json::Value) -> Vec<Bytes> {
+    serde_json::to_vec(packet)
+        .ok()
+        .and_then(|data| outbound::split_datagram(&Bytes::from(data)))
+        .unwrap_or_default()
 }
 
 async fn send_packet(socket: &UdpSocket, addr: &std::net::SocketAddr, packet: &serde_json::Value) {
-    if let Ok(data) = serde_json::to_vec(packet) {
+    for data in datagrams(packet) {
         if let Err(e) = socket.send_to(&data, addr).await {
             debug!("Failed to send packet to {}: {}", addr, e);
         }
     }
 }
 
-async fn broadcast_packet(socket: &UdpSocket, addresses: &[(u32, std::net::SocketAddr)], exclude_player: u32, packet: &serde_json::Value) {
-    if let Ok(data) = serde_json::to_vec(packet) {
-        for (player_id, addr) in addresses {
-            if *player_id != exclude_player {
-                if let Err(e) = socket.send_to(&data, addr).await {
-                    debug!("Failed to broadcast to {}: {}", addr, e);
-                }
-            }
+pub async fn handle_udp_packet(
+    packet: serde_json::Value,
+    addr: std::net::SocketAddr,
+    socket: &UdpSocket,
+    game_server: &Arc<ServerState>,
+    weapons: &Arc<WeaponDb>,
+) {
+    // Commands sent while handling this packet carry its correlation id into the lobby's logs
+    let context = LogContext {
+        correlation_id: Some(log_context::correlation_id(&packet)),
+        ..Default::default()
+    };
+    // Clients may pass on a caller's traceparent (e.g. from their matchmaker), continuing that trace
+    let parent = packet.get(telemetry::TRACEPARENT).and_then(|v| v.as_str());
+    let mut span = Span::root("udp.packet", SpanKind::Server, parent);
+    if span.is_recording() {
+        span.set("packet.type", packet.get("type").and_then(|v| v.as_str()).unwrap_or("unknown").to_string());
+        span.set("client.address", addr.to_string());
+        if let Some(player_id) = packet.get("player_id").and_then(|v| v.as_u64()) {
+            span.set("player.id", player_id as i64);
+        }
+        if let Some(code) = packet.get("lobby_code").and_then(|v| v.as_str()) {
+            span.set("lobby.code", code.to_string());
         }
     }
+    log_context::scope(context, telemetry::in_span(&span, dispatch_packet(packet, addr, socket, game_server, weapons))).await;
 }
 
-pub async fn handle_udp_packet(
+async fn dispatch_packet(
     packet: serde_json::Value,
     addr: std::net::SocketAddr,
     socket: &UdpSocket,
@@ -68,17 +80,29 @@ pub async fn handle_udp_packet(
 
     match packet_type {
         Some("join") => {
-            handle_join_packet(&packet, addr, socket, game_server).await;
+            handle_join_packet(&packet, addr, socket, game_server, weapons).await;
+        }
+        Some("latency_probe_ack") => {
+            handle_latency_probe_ack_packet(&packet, addr, socket, game_server, weapons).await;
         }
         Some("leave") => {
             handle_leave_packet(&packet, addr, socket, game_server).await;
         }
+        Some("observe") | Some("observe_stop") => {
+            handle_observe_packet(&packet, addr, socket, game_server).await;
+        }
         Some("position_update") => {
             handle_position_update_packet(&packet, addr, socket, game_server).await;
         }
         Some("shoot") => {
             handle_shoot_packet(&packet, addr, socket, game_server, weapons).await;
         }
+        Some("fire_start") | Some("fire_stop") => {
+            handle_fire_held_packet(&packet, addr, socket, game_server).await;
+        }
+        Some("pickup") => {
+            handle_pickup_packet(&packet, addr, socket, game_server).await;
+        }
         Some("reload") => {
             handle_reload_packet(&packet, addr, socket, game_server).await;
         }
@@ -88,9 +112,58 @@ pub async fn handle_udp_packet(
         Some("weapon_switch") => {
             handle_weapon_switch_packet(&packet, addr, socket, game_server).await;
         }
+        Some("select_loadout") => {
+            handle_select_loadout_packet(&packet, addr, socket, game_server, weapons).await;
+        }
+        Some("weapon_next") | Some("weapon_prev") => {
+            handle_weapon_cycle_packet(&packet, addr, socket, game_server).await;
+        }
+        Some("ping") => {
+            handle_ping_packet(&packet, addr, socket, game_server).await;
+        }
+        Some("pong_ack") => {
+            handle_pong_ack_packet(&packet, addr, game_server).await;
+        }
+        Some("time_sync") => {
+            handle_time_sync_packet(&packet, addr, socket, game_server).await;
+        }
         Some("keepalive") => {
             handle_keepalive_packet(&packet, addr, socket, game_server).await;
         }
+        Some("chat") => {
+            handle_chat_packet(&packet, addr, socket, game_server).await;
+        }
+        Some("emote") => {
+            handle_emote_packet(&packet, addr, socket, game_server).await;
+        }
+        Some("rebind_ack") => {
+            handle_rebind_ack_packet(&packet, addr, socket, game_server).await;
+        }
+        Some("whisper") => {
+            handle_whisper_packet(&packet, addr, socket, game_server).await;
+        }
+        Some("channel_subscribe") | Some("channel_unsubscribe") | Some("channel_message") => {
+            handle_channel_packet(&packet, addr, socket, game_server).await;
+        }
+        Some("vote_start") | Some("vote_cast") => {
+            handle_vote_packet(&packet, addr, socket, game_server).await;
+        }
+        Some("ready") => {
+            handle_ready_packet(&packet, addr, socket, game_server).await;
+        }
+        Some("request_team_switch") => {
+            handle_team_switch_packet(&packet, game_server).await;
+        }
+        Some("ban_weapon") | Some("unban_weapon") => {
+            handle_ban_weapon_packet(&packet, game_server).await;
+        }
+        Some("pause") | Some("resume") => {
+            handle_pause_packet(&packet, addr, socket, game_server).await;
+        }
+        Some("whoami") => {
+            // Public address echo for other servers detecting theirs
+            send_packet(socket, &addr, &identity::whoami_reply(addr)).await;
+        }
         _ => {
             debug!("Unknown packet type: {:?}", packet_type);
         }
@@ -102,6 +175,7 @@ async fn handle_join_packet(
     addr: std::net::SocketAddr,
     socket: &UdpSocket,
     game_server: &Arc<ServerState>,
+    weapons: &Arc<WeaponDb>,
 ) {
     let lobby_code = packet.get("lobby_code").and_then(|v| v.as_str());
     let player_id = packet.get("player_id").and_then(|v| v.as_u64());
@@ -112,37 +186,247 @@ async fn handle_join_packet(
     if let (Some(code), Some(pid)) = (lobby_code, player_id) {
         let pid = pid as u32;
 
+        // Nothing sizable goes to an address until it proves it receives our packets
+        let now = std::time::SystemTime::now();
+        let cookie = packet.get("cookie").and_then(|v| v.as_str());
+        if !cookie.is_some_and(|cookie| game_server.handshake.verify(addr, cookie, now)) {
+            let challenge = serde_json::json!({
+                "type": "challenge",
+                "cookie": game_server.handshake.issue(addr, now)
+            });
+            send_packet(socket, &addr, &challenge).await;
+            debug!("Challenged join of player {} to {} from {}", pid, code, addr);
+            return;
+        }
+
+        let fallback = messages::needs_fallback(&accepted_capabilities(packet));
+        if game_server.code_failures.blocked_for(Some(addr.ip()), std::time::Instant::now()).is_some() {
+            let error_response = error_packet(Message::new("error.too_many_code_failures", TOO_MANY_CODE_FAILURES), fallback);
+            send_packet(socket, &addr, &error_response).await;
+            debug!("Refused UDP join of player {} from {}: too many bad lobby codes", pid, addr);
+            return;
+        }
+        let Some(code) = ServerState::canonical_lobby_code(code) else {
+            game_server.code_failures.record(Some(addr.ip()), std::time::Instant::now());
+            let error_response = error_packet(Message::new("error.invalid_lobby_code", INVALID_LOBBY_CODE), fallback);
+            send_packet(socket, &addr, &error_response).await;
+            debug!("Refused UDP join of player {} to {:?}: invalid code", pid, code);
+            return;
+        };
+        // Later steps (latency probe, connect) read the code back from the packet
+        let mut join = packet.clone();
+        join["lobby_code"] = serde_json::json!(code);
+
+        // Players already added over HTTP may still connect to finish their match
+        let is_member = game_server.player_lobby_index.get(&pid).is_some_and(|entry| entry.lobby_code == code);
+        if game_server.is_draining() && !is_member {
+            let error_response = error_packet(Message::new("error.server_draining", DRAINING_ERROR), fallback);
+            send_packet(socket, &addr, &error_response).await;
+            info!("Refused UDP join of player {} to {}: draining", pid, code);
+            return;
+        }
+
+        // Lobbies with a latency gate time a probe round trip before letting the client in
+        if let Some(max_latency_ms) = game_server.lobby_summary(&code).and_then(|s| s.max_latency_ms) {
+            let nonce = game_server.latency_probes.start(addr, join, std::time::Instant::now());
+            let probe = serde_json::json!({
+                "type": "latency_probe",
+                "nonce": nonce,
+                "max_latency_ms": max_latency_ms
+            });
+            send_packet(socket, &addr, &probe).await;
+            debug!("Probing latency of player {} before joining {}", pid, code);
+            return;
+        }
+
+        connect_player(&join, addr, socket, game_server, weapons, None).await;
+    }
+}
+
+/// Finish a gated join once the client echoes its latency probe
+async fn handle_latency_probe_ack_packet(
+    packet: &serde_json::Value,
+    addr: std::net::SocketAddr,
+    socket: &UdpSocket,
+    game_server: &Arc<ServerState>,
+    weapons: &Arc<WeaponDb>,
+) {
+    let Some(nonce) = packet.get("nonce").and_then(|v| v.as_u64()) else {
+        return;
+    };
+    let (join, rtt_ms) = match game_server.latency_probes.finish(addr, nonce, std::time::Instant::now()) {
+        Ok(probe) => probe,
+        Err(e) => {
+            // The join, and with it the client's capabilities, is gone
+            let mut error_response = error_packet(Message::new("error.latency_probe_failed", e), true);
+            error_response["code"] = serde_json::json!("latency_probe_failed");
+            send_packet(socket, &addr, &error_response).await;
+            return;
+        }
+    };
+
+    let code = join.get("lobby_code").and_then(|v| v.as_str()).unwrap_or_default();
+    let max_latency_ms = game_server.lobby_summary(code).and_then(|s| s.max_latency_ms);
+    if let Some(max_latency_ms) = max_latency_ms.filter(|max| rtt_ms > *max as f32) {
+        let message = Message::new("error.latency_too_high", "Latency too high for this lobby")
+            .with_param("rtt_ms", rtt_ms)
+            .with_param("max_latency_ms", max_latency_ms);
+        let mut error_response = error_packet(message, messages::needs_fallback(&accepted_capabilities(&join)));
+        error_response["code"] = serde_json::json!(LATENCY_TOO_HIGH);
+        error_response["rtt_ms"] = serde_json::json!(rtt_ms);
+        error_response["max_latency_ms"] = serde_json::json!(max_latency_ms);
+        send_packet(socket, &addr, &error_response).await;
+        info!("Refused UDP join from {} to {}: RTT {:.0}ms over {}ms", addr, code, rtt_ms, max_latency_ms);
+        return;
+    }
+
+    connect_player(&join, addr, socket, game_server, weapons, Some(rtt_ms)).await;
+}
+
+/// Attach the client to its lobby; a measured RTT seeds the player's latency
+/// Capabilities from a join packet's `capabilities` list that this server supports
+fn accepted_capabilities(packet: &serde_json::Value) -> Vec<&'static str> {
+    let requested = packet.get("capabilities").and_then(|v| v.as_array());
+    [rotation::QUATERNION_CAPABILITY, messages::MESSAGE_KEYS_CAPABILITY].into_iter()
+        .filter(|known| requested.is_some_and(|list| list.iter().any(|c| c.as_str() == Some(known))))
+        .collect()
+}
+
+/// Error reply keyed for clients that localize, with the English text unless `fallback` is off
+fn error_packet(message: Message, fallback: bool) -> serde_json::Value {
+    let mut packet = serde_json::json!({ "type": "error" });
+    message.write(&mut packet, "message", fallback);
+    packet
+}
+
+/// Client install id from a join packet, used to recognise the same client rejoining after a crash
+fn client_id(packet: &serde_json::Value) -> Option<String> {
+    packet.get("client_id").and_then(|v| v.as_str())
+        .filter(|id| !id.is_empty() && id.len() <= MAX_CLIENT_ID_LENGTH)
+        .map(str::to_string)
+}
+
+async fn connect_player(
+    packet: &serde_json::Value,
+    addr: std::net::SocketAddr,
+    socket: &UdpSocket,
+    game_server: &Arc<ServerState>,
+    weapons: &Arc<WeaponDb>,
+    rtt_ms: Option<f32>,
+) {
+    let lobby_code = packet.get("lobby_code").and_then(|v| v.as_str());
+    let player_id = packet.get("player_id").and_then(|v| v.as_u64());
+    let player_name = packet.get("player_name").and_then(|v| v.as_str()).unwrap_or("Unknown");
+
+    if let (Some(code), Some(pid)) = (lobby_code, player_id) {
+        let pid = pid as u32;
+
+        let capabilities = accepted_capabilities(packet);
+        let fallback = messages::needs_fallback(&capabilities);
         if let Some(command_tx) = game_server.get_lobby_tx(code) {
+            // Applied first so the player list sent on connect already uses them
+            let cmd = LobbyCommand::SetCapabilities {
+                player_id: pid,
+                quaternion_rotation: capabilities.contains(&rotation::QUATERNION_CAPABILITY),
+                message_keys: !fallback,
+                preferences: Preferences::from_packet(packet),
+            };
+            if let Err(e) = command_tx.send(log_context::traced(cmd)).await {
+                warn!("Failed to send capabilities: {}", e);
+            }
             let cmd = LobbyCommand::UdpConnect {
                 player_id: pid,
                 name: player_name.to_string(),
                 addr,
+                client_id: client_id(packet),
             };
 
-            if let Err(e) = command_tx.send(cmd).await {
+            if let Err(e) = command_tx.send(log_context::traced(cmd)).await {
                 warn!("Failed to send UDP connect command: {}", e);
             }
+            if let Some(rtt_ms) = rtt_ms {
+                let cmd = LobbyCommand::LatencySample { player_id: pid, rtt_ms };
+                if let Err(e) = command_tx.send(log_context::traced(cmd)).await {
+                    warn!("Failed to send latency sample: {}", e);
+                }
+            }
+
+            // An optional preset is equipped as the player spawns in
+            if let Some(name) = packet.get("loadout").and_then(|v| v.as_str()) {
+                match saved_loadout(game_server, weapons, pid, name) {
+                    Ok(loadout) => {
+                        let cmd = LobbyCommand::SelectLoadout { player_id: pid, loadout, immediate: true };
+                        if let Err(e) = command_tx.send(log_context::traced(cmd)).await {
+                            warn!("Failed to send loadout selection: {}", e);
+                        }
+                    }
+                    Err(e) => send_packet(socket, &addr, &loadout_error(e, fallback)).await,
+                }
+            }
 
-            let response = serde_json::json!({
+            let mut response = serde_json::json!({
                 "type": "welcome",
-                "message": "Connected to lobby",
                 "player_id": pid,
-                "lobby_code": code
+                "lobby_code": code,
+                "capabilities": capabilities,
+                "preferences": Preferences::from_packet(packet).names(),
+                // Hint for lobby suggestions and quick join; null without GeoIP
+                "region": game_server.infer_region(addr.ip())
             });
+            messages::welcome().write(&mut response, "message", fallback);
 
             send_packet(socket, &addr, &response).await;
             info!("Player {} ({}) successfully joined lobby {}", pid, player_name, code);
         } else {
-            let error_response = serde_json::json!({
-                "type": "error",
-                "message": "Lobby not found"
-            });
+            game_server.code_failures.record(Some(addr.ip()), std::time::Instant::now());
+            let error_response = error_packet(Message::new("error.lobby_not_found", LOBBY_NOT_FOUND), fallback);
             send_packet(socket, &addr, &error_response).await;
             warn!("Lobby {} not found during UDP join", code);
         }
     }
 }
 
+/// Watch a lobby as an observer, re-sent to keep snapshots coming, or stop watching
+async fn handle_observe_packet(
+    packet: &serde_json::Value,
+    addr: std::net::SocketAddr,
+    socket: &UdpSocket,
+    game_server: &Arc<ServerState>,
+) {
+    let Some(code) = packet.get("lobby_code").and_then(|v| v.as_str()) else {
+        return;
+    };
+    let fallback = messages::needs_fallback(&accepted_capabilities(packet));
+    let Some(code) = ServerState::canonical_lobby_code(code) else {
+        send_packet(socket, &addr, &error_packet(Message::new("error.invalid_lobby_code", INVALID_LOBBY_CODE), fallback)).await;
+        return;
+    };
+    let Some(command_tx) = game_server.get_lobby_tx(&code) else {
+        send_packet(socket, &addr, &error_packet(Message::new("error.lobby_not_found", LOBBY_NOT_FOUND), fallback)).await;
+        return;
+    };
+
+    let cmd = if packet.get("type").and_then(|v| v.as_str()) == Some("observe_stop") {
+        LobbyCommand::ObserverLeave { addr }
+    } else {
+        // Snapshots are far bigger than this request, so the address has to prove it receives them
+        let now = std::time::SystemTime::now();
+        let cookie = packet.get("cookie").and_then(|v| v.as_str());
+        if !cookie.is_some_and(|cookie| game_server.handshake.verify(addr, cookie, now)) {
+            let challenge = serde_json::json!({
+                "type": "challenge",
+                "cookie": game_server.handshake.issue(addr, now)
+            });
+            send_packet(socket, &addr, &challenge).await;
+            return;
+        }
+        LobbyCommand::ObserverJoin { addr }
+    };
+    if let Err(e) = command_tx.send(log_context::traced(cmd)).await {
+        warn!("Failed to send observer command: {}", e);
+    }
+}
+
 async fn handle_leave_packet(
     packet: &serde_json::Value,
     _addr: std::net::SocketAddr,
@@ -159,7 +443,7 @@ async fn handle_leave_packet(
         if let Some(lobby_code) = game_server.find_lobby_by_player(pid).await {
             if let Some(command_tx) = game_server.get_lobby_tx(&lobby_code) {
                 let cmd = LobbyCommand::PlayerLeave { player_id: pid };
-                if let Err(e) = command_tx.send(cmd).await {
+                if let Err(e) = command_tx.send(log_context::traced(cmd)).await {
                     warn!("Failed to send player leave command: {}", e);
                 }
             }
@@ -173,39 +457,45 @@ async fn handle_position_update_packet(
     _socket: &UdpSocket,
     game_server: &Arc<ServerState>,
 ) {
-    let player_id = packet.get("player_id").and_then(|v| v.as_u64());
+    let Ok(player_id) = validation::read_id(packet, "player_id") else {
+        return;
+    };
     let pos_data = packet.get("position");
     let rot_data = packet.get("rotation");
+    let orientation_data = packet.get("orientation");
 
     // debug!("Received position update from {}: {:?}", addr, packet);
 
     if let (Some(pid), Some(pos)) = (player_id, pos_data) {
-        let pid = pid as u32;
-
-        let x = pos.get("x").and_then(|v| v.as_f64()).unwrap_or(0.0) as f32;
-        let y = pos.get("y").and_then(|v| v.as_f64()).unwrap_or(0.0) as f32;
-        let z = pos.get("z").and_then(|v| v.as_f64()).unwrap_or(0.0) as f32;
-
-        let (rx, ry, rz) = if let Some(rot) = rot_data {
-            let rx = rot.get("x").and_then(|v| v.as_f64()).unwrap_or(0.0) as f32;
-            let ry = rot.get("y").and_then(|v| v.as_f64()).unwrap_or(0.0) as f32;
-            let rz = rot.get("z").and_then(|v| v.as_f64()).unwrap_or(0.0) as f32;
-            (rx, ry, rz)
-        } else {
-            (0.0, 0.0, 0.0)
+        // Non-finite values are never stored or broadcast
+        let position = validation::read_vec3(pos);
+        // A quaternion orientation, when sent, supersedes the Euler rotation
+        let rotation = match orientation_data {
+            Some(q) => validation::read_quat(q).map(|q| Some(rotation::quat_to_euler(q))),
+            None => rot_data.map(validation::read_vec3).transpose(),
+        };
+        let (position, rotation) = match (position, rotation) {
+            (Ok(position), Ok(rotation)) => (position, rotation.unwrap_or((0.0, 0.0, 0.0))),
+            (Err(kind), _) | (_, Err(kind)) => {
+                report_violation(game_server, pid, kind).await;
+                return;
+            }
         };
 
         if let Some(lobby_code) = game_server.find_lobby_by_player(pid).await {
             // debug!("Found lobby {} for player {}, sending position update", lobby_code, pid);
+            // Address tracking (ensures HTTP-joined players get their UDP address) happens outside the lock
+            if let Some(presence) = game_server.get_lobby_presence(&lobby_code) {
+                presence.touch(pid, addr, std::time::SystemTime::now());
+            }
             if let Some(command_tx) = game_server.get_lobby_tx(&lobby_code) {
                 let cmd = LobbyCommand::PositionUpdate {
                     player_id: pid,
-                    position: (x, y, z),
-                    rotation: (rx, ry, rz),
-                    addr,
+                    position,
+                    rotation,
                 };
 
-                if let Err(e) = command_tx.send(cmd).await {
+                if let Err(e) = command_tx.send(log_context::traced(cmd)).await {
                     warn!("Failed to send position update: {}", e);
                 } else {
                     debug!("Position update command sent for player {}", pid);
@@ -223,25 +513,100 @@ async fn handle_shoot_packet(
     _socket: &UdpSocket,
     _game_server: &Arc<ServerState>,
     _weapons: &Arc<WeaponDb>,
+) {
+    let Ok(Some(pid)) = validation::read_id(packet, "player_id") else {
+        return;
+    };
+    let tid = match validation::read_id(packet, "target_id") {
+        Ok(target_id) => target_id,
+        Err(kind) => {
+            report_violation(_game_server, pid, kind).await;
+            return;
+        }
+    };
+
+    let pellet_hits = match validation::read_ids(packet, "pellet_hits", MAX_PELLET_HITS) {
+        Ok(hits) => hits,
+        Err(kind) => {
+            report_violation(_game_server, pid, kind).await;
+            return;
+        }
+    };
+
+    // A null or missing target is a miss
+    info!("UDP SHOOT: Player {} shooting at target {:?}", pid, tid);
+
+    if let Some(lobby_code) = _game_server.find_lobby_by_player(pid).await {
+        if let Some(command_tx) = _game_server.get_lobby_tx(&lobby_code) {
+            let cmd = LobbyCommand::Shoot {
+                player_id: pid,
+                target_id: tid,
+                pellet_hits,
+            };
+            if let Err(e) = command_tx.send(log_context::traced(cmd)).await {
+                warn!("Failed to send shoot command: {}", e);
+            }
+        }
+    }
+}
+
+async fn handle_fire_held_packet(
+    packet: &serde_json::Value,
+    _addr: std::net::SocketAddr,
+    _socket: &UdpSocket,
+    game_server: &Arc<ServerState>,
+) {
+    let Ok(Some(pid)) = validation::read_id(packet, "player_id") else {
+        return;
+    };
+    let target_id = match validation::read_id(packet, "target_id") {
+        Ok(target_id) => target_id,
+        Err(kind) => {
+            report_violation(game_server, pid, kind).await;
+            return;
+        }
+    };
+    let held = packet.get("type").and_then(|v| v.as_str()) == Some("fire_start");
+
+    info!("UDP FIRE {}: Player {} target {:?}", if held { "START" } else { "STOP" }, pid, target_id);
+
+    if let Some(lobby_code) = game_server.find_lobby_by_player(pid).await {
+        if let Some(command_tx) = game_server.get_lobby_tx(&lobby_code) {
+            let cmd = LobbyCommand::FireHeld { player_id: pid, held, target_id };
+            if let Err(e) = command_tx.send(log_context::traced(cmd)).await {
+                warn!("Failed to send fire held command: {}", e);
+            }
+        }
+    }
+}
+
+async fn handle_pickup_packet(
+    packet: &serde_json::Value,
+    _addr: std::net::SocketAddr,
+    _socket: &UdpSocket,
+    game_server: &Arc<ServerState>,
 ) {
     let player_id = packet.get("player_id").and_then(|v| v.as_u64());
-    let target_id = packet.get("target_id").and_then(|v| v.as_u64());
+    let pickup_id = packet.get("pickup_id").and_then(|v| v.as_u64());
+    // Items dropped where players died are picked up by item id
+    let item_id = packet.get("item_id").and_then(|v| v.as_u64());
 
-    info!("UDP SHOOT: Player {:?} shooting at target {:?}", player_id, target_id);
+    info!("UDP PICKUP: Player {:?} picked up {:?} (item {:?})", player_id, pickup_id, item_id);
 
-    if let (Some(pid), Some(tid)) = (player_id, target_id) {
-        let pid = pid as u32;
-        let tid = tid as u32;
+    let Some(pid) = player_id else {
+        return;
+    };
+    let pid = pid as u32;
+    let cmd = match (item_id, pickup_id) {
+        (Some(item_id), _) => LobbyCommand::LootPickup { player_id: pid, item_id: item_id as u32 },
+        (None, Some(pickup_id)) => LobbyCommand::Pickup { player_id: pid, pickup_id: pickup_id as u32 },
+        (None, None) => return,
+    };
 
-        if let Some(lobby_code) = _game_server.find_lobby_by_player(pid).await {
-            if let Some(command_tx) = _game_server.get_lobby_tx(&lobby_code) {
-                let cmd = LobbyCommand::Shoot {
-                    player_id: pid,
-                    target_id: tid,
-                };
-                if let Err(e) = command_tx.send(cmd).await {
-                    warn!("Failed to send shoot command: {}", e);
-                }
+    if let Some(lobby_code) = game_server.find_lobby_by_player(pid).await {
+        if let Some(command_tx) = game_server.get_lobby_tx(&lobby_code) {
+            if let Err(e) = command_tx.send(log_context::traced(cmd)).await {
+                warn!("Failed to send pickup command: {}", e);
             }
         }
     }
@@ -263,7 +628,7 @@ async fn handle_reload_packet(
         if let Some(lobby_code) = game_server.find_lobby_by_player(pid).await {
             if let Some(command_tx) = game_server.get_lobby_tx(&lobby_code) {
                 let cmd = LobbyCommand::Reload { player_id: pid };
-                if let Err(e) = command_tx.send(cmd).await {
+                if let Err(e) = command_tx.send(log_context::traced(cmd)).await {
                     warn!("Failed to send reload command: {}", e);
                 }
             }
@@ -288,7 +653,9 @@ async fn handle_request_state_packet(
             if let Some(lobby_handle) = game_server.get_lobby_handle(&lobby_code) {
                 let lobby = lobby_handle.read().await;
 
-                if let Some(player) = lobby.players.get(&pid) {
+                // Only the connected client gets the state, never a spoofed source address
+                let connected = lobby.client_addresses.get(&pid) == Some(&addr);
+                if let Some(player) = lobby.players.get(&pid).filter(|_| connected) {
                     let state_packet = serde_json::json!({
                         "type": "player_state_update",
                         "player_id": pid,
@@ -296,10 +663,13 @@ async fn handle_request_state_packet(
                         "max_health": player.max_health,
                         "ammo": player.current_ammo,
                         "max_ammo": player.max_ammo,
+                        "heat": player.heat,
+                        "overheated": player.overheated_until.is_some(),
                         "is_reloading": player.is_reloading,
                         "weapon_id": player.current_weapon_id,
                         "lobby_code": lobby_code,
-                        "lobby_players": lobby.players.len()
+                        "lobby_players": lobby.players.len(),
+                        "last_event_id": lobby.last_event_id
                     });
 
                     send_packet(socket, &addr, &state_packet).await;
@@ -315,22 +685,27 @@ async fn handle_weapon_switch_packet(
     _socket: &UdpSocket,
     game_server: &Arc<ServerState>,
 ) {
-    let player_id = packet.get("player_id").and_then(|v| v.as_u64());
-    let weapon_id = packet.get("weapon_id").and_then(|v| v.as_u64());
-
-    info!("UDP WEAPON SWITCH: Player {:?} switching to weapon {:?}", player_id, weapon_id);
+    let Ok(Some(pid)) = validation::read_id(packet, "player_id") else {
+        return;
+    };
+    let weapon_id = match validation::read_id(packet, "weapon_id") {
+        Ok(weapon_id) => weapon_id,
+        Err(kind) => {
+            report_violation(game_server, pid, kind).await;
+            return;
+        }
+    };
 
-    if let (Some(pid), Some(wid)) = (player_id, weapon_id) {
-        let pid = pid as u32;
-        let wid = wid as u32;
+    info!("UDP WEAPON SWITCH: Player {} switching to weapon {:?}", pid, weapon_id);
 
+    if let Some(wid) = weapon_id {
         if let Some(lobby_code) = game_server.find_lobby_by_player(pid).await {
             if let Some(command_tx) = game_server.get_lobby_tx(&lobby_code) {
                 let cmd = LobbyCommand::WeaponSwitch {
                     player_id: pid,
                     weapon_id: wid,
                 };
-                if let Err(e) = command_tx.send(cmd).await {
+                if let Err(e) = command_tx.send(log_context::traced(cmd)).await {
                     warn!("Failed to send weapon switch command: {}", e);
                 }
             }
@@ -338,25 +713,436 @@ async fn handle_weapon_switch_packet(
     }
 }
 
-async fn handle_keepalive_packet(
+/// A player's saved preset, checked against their unlocks as they stand now
+fn saved_loadout(game_server: &ServerState, weapons: &WeaponDb, player_id: u32, name: &str) -> Result<Loadout, &'static str> {
+    let loadout = game_server.accounts.account_of(player_id)
+        .and_then(|account| game_server.loadouts.get(&account, name))
+        .ok_or("Loadout not found")?;
+    let career_kills = game_server.global_stats.get_stats(player_id).map_or(0, |s| s.total_kills);
+    loadouts::validate(&loadout, weapons, career_kills)?;
+    Ok(loadout)
+}
+
+fn loadout_error(reason: &'static str, fallback: bool) -> serde_json::Value {
+    let mut packet = serde_json::json!({ "type": "loadout_rejected" });
+    Message::new("loadout.rejected", reason).with_param("reason", reason).write(&mut packet, "message", fallback);
+    packet
+}
+
+/// Pick a saved loadout preset; the lobby equips it at the player's next respawn
+async fn handle_select_loadout_packet(
+    packet: &serde_json::Value,
+    addr: std::net::SocketAddr,
+    socket: &UdpSocket,
+    game_server: &Arc<ServerState>,
+    weapons: &Arc<WeaponDb>,
+) {
+    let Ok(Some(pid)) = validation::read_id(packet, "player_id") else {
+        return;
+    };
+    let Some(name) = packet.get("name").and_then(|v| v.as_str()) else {
+        return;
+    };
+    let loadout = match saved_loadout(game_server, weapons, pid, name) {
+        Ok(loadout) => loadout,
+        Err(e) => {
+            // Capabilities aren't known outside the lobby, so old clients keep working
+            send_packet(socket, &addr, &loadout_error(e, true)).await;
+            return;
+        }
+    };
+
+    if let Some(lobby_code) = game_server.find_lobby_by_player(pid).await {
+        if let Some(command_tx) = game_server.get_lobby_tx(&lobby_code) {
+            let cmd = LobbyCommand::SelectLoadout { player_id: pid, loadout, immediate: false };
+            if let Err(e) = command_tx.send(log_context::traced(cmd)).await {
+                warn!("Failed to send loadout selection: {}", e);
+            }
+        }
+    }
+}
+
+async fn handle_weapon_cycle_packet(
     packet: &serde_json::Value,
     _addr: std::net::SocketAddr,
     _socket: &UdpSocket,
     game_server: &Arc<ServerState>,
 ) {
     let player_id = packet.get("player_id").and_then(|v| v.as_u64());
+    let forward = packet.get("type").and_then(|v| v.as_str()) == Some("weapon_next");
 
     if let Some(pid) = player_id {
         let pid = pid as u32;
 
         if let Some(lobby_code) = game_server.find_lobby_by_player(pid).await {
             if let Some(command_tx) = game_server.get_lobby_tx(&lobby_code) {
-                let cmd = LobbyCommand::Heartbeat {
+                let cmd = LobbyCommand::WeaponCycle {
                     player_id: pid,
-                    addr: _addr,
+                    forward,
+                };
+                if let Err(e) = command_tx.send(log_context::traced(cmd)).await {
+                    warn!("Failed to send weapon cycle command: {}", e);
+                }
+            }
+        }
+    }
+}
+
+/// Count a rejected packet against the sender in their lobby
+async fn report_violation(game_server: &Arc<ServerState>, player_id: u32, kind: ViolationKind) {
+    debug!("Rejected packet from player {}: {}", player_id, kind.as_str());
+    if let Some(lobby_code) = game_server.find_lobby_by_player(player_id).await {
+        if let Some(command_tx) = game_server.get_lobby_tx(&lobby_code) {
+            let _ = command_tx.send(log_context::traced(LobbyCommand::Violation { player_id, kind })).await;
+        }
+    }
+}
+
+/// Keepalives only refresh the sender's address and activity, so they skip the tick queue
+async fn handle_keepalive_packet(
+    packet: &serde_json::Value,
+    addr: std::net::SocketAddr,
+    _socket: &UdpSocket,
+    game_server: &Arc<ServerState>,
+) {
+    let player_id = packet.get("player_id").and_then(|v| v.as_u64());
+
+    if let Some(pid) = player_id {
+        let pid = pid as u32;
+
+        if let Some(lobby_code) = game_server.find_lobby_by_player(pid).await {
+            if let Some(presence) = game_server.get_lobby_presence(&lobby_code) {
+                presence.touch(pid, addr, std::time::SystemTime::now());
+            }
+        }
+    }
+}
+
+async fn handle_ping_packet(
+    packet: &serde_json::Value,
+    addr: std::net::SocketAddr,
+    socket: &UdpSocket,
+    game_server: &Arc<ServerState>,
+) {
+    // Echo immediately so the client can time the round trip
+    let mut pong = serde_json::json!({
+        "type": "pong",
+        "seq": packet.get("seq"),
+        "client_time": packet.get("client_time")
+    });
+    // The server keeps its own clock on players: they answer the nonce with `pong_ack` straight away
+    if let Ok(Some(pid)) = validation::read_id(packet, "player_id") {
+        pong["nonce"] = serde_json::json!(game_server.ping_echoes.start(addr, pid, std::time::Instant::now()));
+    }
+    send_packet(socket, &addr, &pong).await;
+}
+
+/// A pong echoed back: the server-measured round trip becomes a latency sample
+async fn handle_pong_ack_packet(
+    packet: &serde_json::Value,
+    addr: std::net::SocketAddr,
+    game_server: &Arc<ServerState>,
+) {
+    let Some(nonce) = packet.get("nonce").and_then(|v| v.as_u64()) else {
+        return;
+    };
+    let Some((pid, rtt_ms)) = game_server.ping_echoes.finish(addr, nonce, std::time::Instant::now()) else {
+        return;
+    };
+    if let Some(lobby_code) = game_server.find_lobby_by_player(pid).await {
+        if let Some(command_tx) = game_server.get_lobby_tx(&lobby_code) {
+            let cmd = LobbyCommand::LatencySample { player_id: pid, rtt_ms };
+            if let Err(e) = command_tx.send(log_context::traced(cmd)).await {
+                warn!("Failed to send latency sample: {}", e);
+            }
+        }
+    }
+}
+
+/// Clock sync: the lobby answers with server time, its current tick and smoothing advice
+async fn handle_time_sync_packet(
+    packet: &serde_json::Value,
+    _addr: std::net::SocketAddr,
+    _socket: &UdpSocket,
+    game_server: &Arc<ServerState>,
+) {
+    // Stamp arrival before queueing so the tick's delay doesn't skew the offset
+    let received_at_ms = clock_sync::now_ms();
+    let Ok(Some(pid)) = validation::read_id(packet, "player_id") else {
+        return;
+    };
+    let client_time_ms = match validation::read_f64(packet, "client_time") {
+        Ok(Some(client_time_ms)) => client_time_ms,
+        Ok(None) => return,
+        Err(kind) => {
+            report_violation(game_server, pid, kind).await;
+            return;
+        }
+    };
+    let seq = packet.get("seq").and_then(|v| v.as_u64());
+
+    if let Some(lobby_code) = game_server.find_lobby_by_player(pid).await {
+        if let Some(command_tx) = game_server.get_lobby_tx(&lobby_code) {
+            let cmd = LobbyCommand::TimeSync { player_id: pid, seq, client_time_ms, received_at_ms };
+            if let Err(e) = command_tx.send(log_context::traced(cmd)).await {
+                warn!("Failed to send time sync: {}", e);
+            }
+        }
+    }
+}
+
+async fn handle_chat_packet(
+    packet: &serde_json::Value,
+    _addr: std::net::SocketAddr,
+    _socket: &UdpSocket,
+    game_server: &Arc<ServerState>,
+) {
+    let player_id = packet.get("player_id").and_then(|v| v.as_u64());
+    let text = packet.get("text").and_then(|v| v.as_str());
+
+    if let (Some(pid), Some(text)) = (player_id, text) {
+        let pid = pid as u32;
+        if let Some(lobby_code) = game_server.find_lobby_by_player(pid).await {
+            if let Some(command_tx) = game_server.get_lobby_tx(&lobby_code) {
+                let cmd = LobbyCommand::Chat { player_id: pid, text: text.to_string() };
+                if let Err(e) = command_tx.send(log_context::traced(cmd)).await {
+                    warn!("Failed to send chat command: {}", e);
+                }
+            }
+        }
+    }
+}
+
+async fn handle_emote_packet(
+    packet: &serde_json::Value,
+    _addr: std::net::SocketAddr,
+    _socket: &UdpSocket,
+    game_server: &Arc<ServerState>,
+) {
+    let player_id = packet.get("player_id").and_then(|v| v.as_u64());
+    let emote = packet.get("emote").and_then(|v| v.as_str());
+
+    if let (Some(pid), Some(emote)) = (player_id, emote) {
+        let pid = pid as u32;
+        if let Some(lobby_code) = game_server.find_lobby_by_player(pid).await {
+            if let Some(command_tx) = game_server.get_lobby_tx(&lobby_code) {
+                let cmd = LobbyCommand::Emote { player_id: pid, emote: emote.to_string() };
+                if let Err(e) = command_tx.send(log_context::traced(cmd)).await {
+                    warn!("Failed to send emote command: {}", e);
+                }
+            }
+        }
+    }
+}
+
+async fn handle_rebind_ack_packet(
+    packet: &serde_json::Value,
+    addr: std::net::SocketAddr,
+    _socket: &UdpSocket,
+    game_server: &Arc<ServerState>,
+) {
+    let player_id = packet.get("player_id").and_then(|v| v.as_u64());
+    let generation = packet.get("generation").and_then(|v| v.as_u64());
+
+    if let (Some(pid), Some(generation)) = (player_id, generation) {
+        let pid = pid as u32;
+        if let Some(lobby_code) = game_server.find_lobby_by_player(pid).await {
+            if let Some(command_tx) = game_server.get_lobby_tx(&lobby_code) {
+                let cmd = LobbyCommand::RebindAck { player_id: pid, addr, generation };
+                if let Err(e) = command_tx.send(log_context::traced(cmd)).await {
+                    warn!("Failed to send rebind ack command: {}", e);
+                }
+            }
+        }
+    }
+}
+
+async fn handle_whisper_packet(
+    packet: &serde_json::Value,
+    _addr: std::net::SocketAddr,
+    _socket: &UdpSocket,
+    game_server: &Arc<ServerState>,
+) {
+    let player_id = packet.get("player_id").and_then(|v| v.as_u64());
+    let target_id = packet.get("to").and_then(|v| v.as_u64());
+    let text = packet.get("text").and_then(|v| v.as_str());
+
+    if let (Some(pid), Some(tid), Some(text)) = (player_id, target_id, text) {
+        let pid = pid as u32;
+        let tid = tid as u32;
+
+        // Recipient is validated by the sender's lobby, so only same-lobby whispers are delivered
+        if let Some(lobby_code) = game_server.find_lobby_by_player(pid).await {
+            if let Some(command_tx) = game_server.get_lobby_tx(&lobby_code) {
+                let cmd = LobbyCommand::Whisper {
+                    player_id: pid,
+                    target_id: tid,
+                    text: text.to_string(),
+                };
+                if let Err(e) = command_tx.send(log_context::traced(cmd)).await {
+                    warn!("Failed to send whisper command: {}", e);
+                }
+            }
+        }
+    }
+}
+
+async fn handle_channel_packet(
+    packet: &serde_json::Value,
+    _addr: std::net::SocketAddr,
+    _socket: &UdpSocket,
+    game_server: &Arc<ServerState>,
+) {
+    let player_id = packet.get("player_id").and_then(|v| v.as_u64());
+    let channel = packet.get("channel").and_then(|v| v.as_str());
+
+    if let (Some(pid), Some(channel)) = (player_id, channel) {
+        let pid = pid as u32;
+        let channel = channel.to_string();
+        let cmd = match packet.get("type").and_then(|v| v.as_str()) {
+            Some("channel_message") => {
+                let Some(text) = packet.get("text").and_then(|v| v.as_str()) else { return };
+                LobbyCommand::ChannelChat { player_id: pid, channel, text: text.to_string() }
+            }
+            kind => LobbyCommand::ChannelSubscribe { player_id: pid, channel, subscribe: kind == Some("channel_subscribe") },
+        };
+
+        // The sender's lobby resolves the channel and fans the message out to other lobbies
+        if let Some(lobby_code) = game_server.find_lobby_by_player(pid).await {
+            if let Some(command_tx) = game_server.get_lobby_tx(&lobby_code) {
+                if let Err(e) = command_tx.send(log_context::traced(cmd)).await {
+                    warn!("Failed to send channel command: {}", e);
+                }
+            }
+        }
+    }
+}
+
+async fn handle_vote_packet(
+    packet: &serde_json::Value,
+    _addr: std::net::SocketAddr,
+    _socket: &UdpSocket,
+    game_server: &Arc<ServerState>,
+) {
+    let player_id = packet.get("player_id").and_then(|v| v.as_u64());
+    let cmd = match (packet.get("type").and_then(|v| v.as_str()), player_id) {
+        (Some("vote_start"), Some(pid)) => {
+            let vote = packet.get("vote").and_then(|v| v.as_str()).unwrap_or_default();
+            let target_id = packet.get("target_id").and_then(|v| v.as_u64()).map(|id| id as u32);
+            let scene = packet.get("scene").and_then(|v| v.as_str());
+            VoteKind::parse(vote, target_id, scene)
+                .map(|kind| LobbyCommand::VoteStart { player_id: pid as u32, kind })
+        }
+        (Some("vote_cast"), Some(pid)) => {
+            let yes = packet.get("yes").and_then(|v| v.as_bool()).unwrap_or(true);
+            Some(LobbyCommand::VoteCast { player_id: pid as u32, yes })
+        }
+        _ => None,
+    };
+
+    info!("UDP VOTE: Player {:?} {:?}", player_id, cmd);
+
+    if let (Some(pid), Some(cmd)) = (player_id, cmd) {
+        if let Some(lobby_code) = game_server.find_lobby_by_player(pid as u32).await {
+            if let Some(command_tx) = game_server.get_lobby_tx(&lobby_code) {
+                if let Err(e) = command_tx.send(log_context::traced(cmd)).await {
+                    warn!("Failed to send vote command: {}", e);
+                }
+            }
+        }
+    }
+}
+
+async fn handle_ready_packet(
+    packet: &serde_json::Value,
+    _addr: std::net::SocketAddr,
+    _socket: &UdpSocket,
+    game_server: &Arc<ServerState>,
+) {
+    let player_id = packet.get("player_id").and_then(|v| v.as_u64());
+    // Omitted "ready" means the player is readying up
+    let ready = packet.get("ready").and_then(|v| v.as_bool()).unwrap_or(true);
+
+    info!("UDP READY: Player {:?} ready={}", player_id, ready);
+
+    if let Some(pid) = player_id {
+        let pid = pid as u32;
+
+        if let Some(lobby_code) = game_server.find_lobby_by_player(pid).await {
+            if let Some(command_tx) = game_server.get_lobby_tx(&lobby_code) {
+                let cmd = LobbyCommand::Ready { player_id: pid, ready };
+                if let Err(e) = command_tx.send(log_context::traced(cmd)).await {
+                    warn!("Failed to send ready command: {}", e);
+                }
+            }
+        }
+    }
+}
+
+async fn handle_team_switch_packet(packet: &serde_json::Value, game_server: &Arc<ServerState>) {
+    let player_id = packet.get("player_id").and_then(|v| v.as_u64());
+    let team = packet.get("team").and_then(|v| v.as_u64()).and_then(|t| u8::try_from(t).ok());
+
+    if let (Some(pid), Some(team)) = (player_id, team) {
+        let pid = pid as u32;
+        info!("UDP TEAM SWITCH: Player {} to team {}", pid, team);
+
+        if let Some(lobby_code) = game_server.find_lobby_by_player(pid).await {
+            if let Some(command_tx) = game_server.get_lobby_tx(&lobby_code) {
+                let cmd = LobbyCommand::RequestTeamSwitch { player_id: pid, team };
+                if let Err(e) = command_tx.send(log_context::traced(cmd)).await {
+                    warn!("Failed to send team switch command: {}", e);
+                }
+            }
+        }
+    }
+}
+
+async fn handle_ban_weapon_packet(packet: &serde_json::Value, game_server: &Arc<ServerState>) {
+    let player_id = packet.get("player_id").and_then(|v| v.as_u64());
+    let weapon_id = packet.get("weapon_id").and_then(|v| v.as_u64());
+    let banned = packet.get("type").and_then(|v| v.as_str()) == Some("ban_weapon");
+
+    if let (Some(pid), Some(weapon_id)) = (player_id, weapon_id) {
+        let (pid, weapon_id) = (pid as u32, weapon_id as u32);
+        info!("UDP {}: Player {} weapon {}", if banned { "BAN WEAPON" } else { "UNBAN WEAPON" }, pid, weapon_id);
+
+        if let Some(lobby_code) = game_server.find_lobby_by_player(pid).await {
+            if let Some(command_tx) = game_server.get_lobby_tx(&lobby_code) {
+                // Ownership is checked by the lobby tick
+                let cmd = LobbyCommand::BanWeapon { player_id: Some(pid), weapon_id, banned };
+                if let Err(e) = command_tx.send(log_context::traced(cmd)).await {
+                    warn!("Failed to send weapon ban command: {}", e);
+                }
+            }
+        }
+    }
+}
+
+async fn handle_pause_packet(
+    packet: &serde_json::Value,
+    _addr: std::net::SocketAddr,
+    _socket: &UdpSocket,
+    game_server: &Arc<ServerState>,
+) {
+    let player_id = packet.get("player_id").and_then(|v| v.as_u64());
+    let pause = packet.get("type").and_then(|v| v.as_str()) == Some("pause");
+
+    info!("UDP {}: Player {:?}", if pause { "PAUSE" } else { "RESUME" }, player_id);
+
+    if let Some(pid) = player_id {
+        let pid = pid as u32;
+
+        if let Some(lobby_code) = game_server.find_lobby_by_player(pid).await {
+            if let Some(command_tx) = game_server.get_lobby_tx(&lobby_code) {
+                // Ownership is checked by the lobby tick
+                let cmd = if pause {
+                    LobbyCommand::Pause { player_id: Some(pid) }
+                } else {
+                    LobbyCommand::Resume { player_id: Some(pid) }
                 };
-                if let Err(e) = command_tx.send(cmd).await {
-                    warn!("Failed to send heartbeat: {}", e);
+                if let Err(e) = command_tx.send(log_context::traced(cmd)).await {
+                    warn!("Failed to send pause command: {}", e);
                 }
             }
         }
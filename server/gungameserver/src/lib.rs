@@ -0,0 +1,6 @@
+pub mod handlers;
+pub mod state;
+pub mod domain;
+pub mod tick;
+pub mod utils;
+pub mod server;
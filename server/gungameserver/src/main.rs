@@ -1,27 +1,71 @@
-mod handlers;
-mod state;
-mod domain;
-mod tick;
-mod utils;
-mod server;
-
-use fern;
-use chrono;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
 use tokio::signal;
-use crate::utils::weapondb::WeaponDb;
-use crate::utils::config::Config;
-use crate::state::server_state::ServerState;
+use gungameserver::server;
+use gungameserver::utils::weapondb::WeaponDb;
+use gungameserver::utils::config::Config;
+use gungameserver::utils::geoip::GeoIp;
+use gungameserver::utils::identity::ServerIdentity;
+use gungameserver::utils::udp_socket;
+use gungameserver::utils::log_context::{self, LobbyLogCapture};
+use gungameserver::utils::telemetry;
+use gungameserver::state::server_state::ServerState;
+use gungameserver::state::stats_store::{self, StatsSync};
+use gungameserver::tick::checkpoint::{self, CheckpointFile};
+use gungameserver::tick::replay;
+use gungameserver::tick::replication::Replicator;
 
 static SHUTDOWN_REQUESTED: AtomicBool = AtomicBool::new(false);
 
+/// How often a draining server checks whether its lobbies have emptied
+const DRAIN_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
+
 async fn shutdown_signal() {
     signal::ctrl_c().await.unwrap();
     SHUTDOWN_REQUESTED.store(true, Ordering::Relaxed);
     log::info!("Shutdown signal received, initiating graceful shutdown...");
 }
 
+/// Enter drain mode on SIGTERM; resolves once drain mode (SIGTERM or admin API) has emptied every lobby
+async fn drained(state: Arc<ServerState>) {
+    #[cfg(unix)]
+    {
+        let sigterm_state = state.clone();
+        tokio::spawn(async move {
+            match signal::unix::signal(signal::unix::SignalKind::terminate()) {
+                Ok(mut sigterm) => {
+                    sigterm.recv().await;
+                    if sigterm_state.start_draining() {
+                        log::info!("SIGTERM received, draining lobbies before shutdown...");
+                    }
+                }
+                Err(e) => log::warn!("Failed to listen for SIGTERM: {}", e),
+            }
+        });
+    }
+    state.wait_drained(DRAIN_POLL_INTERVAL).await;
+}
+
+/// Replay a recorded match and report the first tick whose state hash differs; exits 1 on divergence
+fn verify_replay(path: &str, weapons: &WeaponDb, config: Arc<Config>) -> Result<(), Box<dyn std::error::Error>> {
+    let file = std::io::BufReader::new(std::fs::File::open(path)?);
+    let report = replay::verify(file, weapons, config)?;
+    match report.divergence {
+        None => {
+            println!("{}: {} records, {} state hashes match", path, report.records, report.hashes_checked);
+            Ok(())
+        }
+        Some(divergence) => {
+            let replayed = divergence.replayed.map_or("no lobby".to_string(), |hash| format!("{:016x}", hash));
+            println!(
+                "{}: lobby {} diverges at tick {} (recorded {:016x}, replayed {}) after {} matching hashes",
+                path, divergence.code, divergence.tick, divergence.recorded, replayed, report.hashes_checked - 1,
+            );
+            std::process::exit(1);
+        }
+    }
+}
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     setup_logging()?;
@@ -31,31 +75,107 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Load immutable globals (zero contention)
     let weapons = Arc::new(WeaponDb::load());
     let config = Arc::new(Config::default());
+
+    // `--verify-replay <file>` checks a recorded replication stream instead of starting the server
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(index) = args.iter().position(|arg| arg == "--verify-replay") {
+        let path = args.get(index + 1).ok_or("--verify-replay needs a replay file")?;
+        verify_replay(path, &weapons, config.clone())?;
+        return Ok(());
+    }
+    if let Some(endpoint) = &config.otlp_endpoint {
+        telemetry::install(endpoint, config.trace_sample_ratio)?;
+        log::info!("Exporting traces to {} (sampling {} of new traces)", endpoint, config.trace_sample_ratio);
+    }
     
     // Create server state (partitioned by lobby)
     let state = Arc::new(ServerState::new());
+    if config.replication_standby.is_some() || config.replay_record_path.is_some() {
+        let mut replicator = Replicator::new();
+        if let Some(addr) = &config.replication_standby {
+            replicator = replicator.with_standby(addr.clone(), &state);
+            log::info!("Replicating lobbies to standby {}", addr);
+        }
+        if let Some(path) = &config.replay_record_path {
+            replicator = replicator.with_recording(path.clone());
+            log::info!("Recording lobby replays to {}", path);
+        }
+        state.set_replicator(replicator)?;
+    }
+    if let Some(path) = &config.geoip_db {
+        state.set_geoip(GeoIp::open(path, config.geoip_regions.clone())?)?;
+        log::info!("Inferring player regions from {}", path);
+    }
+
+    // Persist global stats through a write-behind cache
+    let stats_sync = match &config.stats_backend {
+        Some(url) => {
+            let sync = Arc::new(StatsSync::new(state.global_stats.clone(), stats_store::backend_from_url(url)?)
+                .with_player_ids(state.player_ids.clone()));
+            // Ids handed out before the first block arrives could repeat ones from a previous run
+            if let Err(e) = sync.reserve_player_ids().await {
+                log::warn!("Could not reserve player ids, they are only unique until restart: {}", e);
+            }
+            sync.clone().spawn(std::time::Duration::from_secs(config.stats_flush_interval_secs));
+            log::info!("Global stats stored in {}", url);
+            Some(sync)
+        }
+        None => None,
+    };
+    if let Some(path) = &config.loadouts_path {
+        let accounts = state.loadouts.attach(path).await?;
+        log::info!("Loaded saved loadouts of {} accounts from {}", accounts, path);
+    }
     
     // Create UDP socket for lobby tick loops
     let udp_socket = Arc::new(
-        tokio::net::UdpSocket::bind(format!("0.0.0.0:{}", config.udp_port)).await?
+        udp_socket::bind(std::net::SocketAddr::from(([0, 0, 0, 0], config.udp_port)), &config)?
     );
     
     log::info!("UDP socket bound to port {}", config.udp_port);
+
+    // Resolve what clients are told to connect to before the socket is shared with lobbies
+    state.set_identity(ServerIdentity::establish(&config, &udp_socket).await)?;
+
+    // `--recover` brings back the lobbies checkpointed before a crash, paused until players reconnect
+    if std::env::args().any(|arg| arg == "--recover") {
+        match &config.checkpoint_path {
+            Some(path) => {
+                let restored = checkpoint::recover(
+                    &CheckpointFile::new(path),
+                    state.clone(),
+                    weapons.clone(),
+                    config.clone(),
+                    udp_socket.clone(),
+                ).await?;
+                log::info!("Recovered {} lobbies from {}", restored, path);
+            }
+            None => log::warn!("--recover given but no checkpoint path is configured"),
+        }
+    }
+    if let Some(path) = &config.checkpoint_path {
+        let interval = std::time::Duration::from_secs(config.checkpoint_interval_secs);
+        checkpoint::spawn_checkpointer(state.clone(), CheckpointFile::new(path), interval);
+        log::info!("Checkpointing lobbies to {} every {:?}", path, interval);
+    }
     
     // Create default test lobby
-    server::create_lobby_with_tick(
-        state.clone(),
-        "test".to_string(),
-        8,
-        "test_world".to_string(),
-        weapons.clone(),
-        config.clone(),
-        udp_socket.clone(),
-    ).await?;
-    
-    log::info!("Created test lobby 'test'");
+    if !state.lobby_exists(server::DEFAULT_LOBBY_CODE) {
+        server::create_lobby_with_tick(
+            state.clone(),
+            server::DEFAULT_LOBBY_CODE.to_string(),
+            8,
+            "test_world".to_string(),
+            weapons.clone(),
+            config.clone(),
+            udp_socket.clone(),
+        ).await?;
+
+        log::info!("Created test lobby '{}'", server::DEFAULT_LOBBY_CODE);
+    }
     
     // Start HTTP and UDP servers
+    let drain_state = state.clone();
     let server_result = server::start_servers(state, weapons, config, udp_socket);
     
     // Wait for shutdown signal
@@ -70,26 +190,46 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             log::info!("Shutting down servers...");
             // The servers will be dropped and their tasks will be cancelled
         }
+        _ = drained(drain_state) => {
+            log::info!("Drain complete or shutdown grace elapsed, shutting down...");
+        }
     }
+
+    if let Some(sync) = stats_sync {
+        match sync.flush().await {
+            Ok(count) => log::info!("Flushed stats for {} players", count),
+            Err(e) => log::warn!("Final stats flush failed: {}", e),
+        }
+    }
+    telemetry::shutdown().await;
     
     log::info!("Server shutdown complete");
     Ok(())
 }
 
 fn setup_logging() -> Result<(), Box<dyn std::error::Error>> {
-    fern::Dispatch::new()
+    let output = fern::Dispatch::new()
         .format(|out, message, record| {
+            let context = log_context::current().map(|c| c.prefix()).unwrap_or_default();
             out.finish(format_args!(
-                "{}[{}][{}] {}",
+                "{}[{}][{}] {}{}",
                 chrono::Utc::now().format("[%Y-%m-%d][%H:%M:%S]"),
                 record.target(),
                 record.level(),
+                context,
                 message
             ))
         })
         .level(log::LevelFilter::Info)
         .chain(std::io::stdout())
-        .chain(fern::log_file("gungame.log")?)
+        .chain(fern::log_file("gungame.log")?);
+    // Lobby buffers also keep debug lines, served by GET /lobbies/:code/logs
+    let lobby_capture = fern::Dispatch::new()
+        .level(log::LevelFilter::Debug)
+        .chain(Box::new(LobbyLogCapture) as Box<dyn log::Log>);
+    fern::Dispatch::new()
+        .chain(output)
+        .chain(lobby_capture)
         .apply()?;
     Ok(())
 }
@@ -1,19 +1,38 @@
 use axum::{
-    routing::{get, post},
+    extract::{ConnectInfo, MatchedPath, RawPathParams, Request, State},
+    http::{HeaderValue, StatusCode},
+    middleware::{from_fn, from_fn_with_state, map_response, Next},
+    response::Response,
+    routing::{delete, get, patch, post, put},
     Router,
 };
 use tower_http::cors::CorsLayer;
+use utoipa::OpenApi;
+use utoipa_swagger_ui::SwaggerUi;
 use log::info;
 use tokio::net::{TcpListener, UdpSocket};
 use std::sync::Arc;
 use tokio::sync::{mpsc, RwLock};
-use crate::state::server_state::{ServerState, LobbyHandle};
+use arc_swap::ArcSwap;
+use crate::state::server_state::{ServerState, LobbyHandle, IndexRepair, PLAYER_INDEX_TTL};
 use crate::state::lobby::Lobby;
-use crate::handlers::http::{create_lobby, list_lobbies, join_lobby, get_lobby, get_lobby_leaderboard, get_global_leaderboard, AppState};
+use crate::domain::lobbies;
+use crate::handlers::http::{create_lobby, list_lobbies, join_lobby, join_party, get_lobby, get_lobby_leaderboard, get_player_state, get_player_stats, get_global_leaderboard, end_match, pause_lobby, suggest_lobbies, resume_lobby, get_lobby_logs, merge_lobby, split_lobby, get_global_player_stats, export_global_stats, get_player_presence, update_lobby, get_lobby_heatmap, get_match_timeline, get_match_damage, get_status, get_metrics, start_drain, set_player_vip, list_friends, list_friend_requests, add_friend, remove_friend, join_friend, list_loadouts, get_loadout, save_loadout, delete_loadout, list_weapons, get_weapon_falloff, announce, cancel_announcement, create_tournament, get_tournament, set_rules_script, clear_rules_script, ban_weapon, unban_weapon, quick_join, AppState};
+use crate::handlers::auth::{self, Gate, Role};
+use crate::state::failure_limiter::FailureLimiter;
+use crate::handlers::console;
+use crate::handlers::openapi::ApiDoc;
 use crate::handlers::udp::handle_udp_packet;
+use crate::tick::checkpoint::LobbyCheckpoint;
 use crate::tick::lobby_tick::lobby_tick_loop;
+use crate::tick::net_sim::{NetSim, NetSimConfig};
+use crate::tick::replication::{self, ReplicationRecord};
 use crate::utils::weapondb::WeaponDb;
 use crate::utils::config::Config;
+use crate::utils::log_context;
+use crate::utils::telemetry::{self, Span, SpanKind};
+use crate::utils::rng::SeededRng;
+use crate::utils::udp_socket;
 
 /// Start HTTP and UDP servers
 pub async fn start_servers(
@@ -23,12 +42,148 @@ pub async fn start_servers(
     udp_socket: Arc<UdpSocket>,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let http_server = init_http_server(state.clone(), weapons.clone(), config.clone(), udp_socket.clone());
-    let udp_server = init_udp_server(state.clone(), weapons.clone(), udp_socket.clone()).await?;
+    let udp_server = init_udp_server(state.clone(), weapons.clone(), udp_socket.clone(), config.net_sim).await?;
+    init_index_sweeper(state.clone(), config.clone());
+    if let Some(addr) = &config.replication_listen {
+        init_standby(addr, state.clone(), weapons.clone(), config.clone(), udp_socket.clone()).await?;
+    }
+    let console_app = AppState { state: state.clone(), weapons: weapons.clone(), config: config.clone(), udp_socket: udp_socket.clone() };
+    init_admin_console(console_app).await?;
 
     tokio::try_join!(http_server, udp_server)?;
     Ok(())
 }
 
+/// Current HTTP API version, reported on every response
+pub const API_VERSION: &str = "1";
+
+/// HTTP routes of the current API version (mounted under /v1), grouped by the role they need
+fn api_routes(app_state: &AppState, failures: &Arc<FailureLimiter>) -> Router<AppState> {
+    let config = &app_state.config;
+    let gate = |required| from_fn_with_state(Gate { config: config.clone(), required, failures: failures.clone() }, auth::enforce);
+    let public = Router::new()
+        .route("/lobbies", get(list_lobbies))
+        .route("/lobbies/suggest", get(suggest_lobbies))
+        .route("/lobbies/:code", get(get_lobby))
+        .route("/lobbies/:code/leaderboard", get(get_lobby_leaderboard))
+        .route("/lobbies/:code/analytics/heatmap", get(get_lobby_heatmap))
+        .route("/matches/:id/timeline", get(get_match_timeline))
+        .route("/lobbies/:code/players/:id", get(get_player_state))
+        .route("/lobbies/:code/players/:id/stats", get(get_player_stats))
+        .route("/leaderboard", get(get_global_leaderboard))
+        .route("/players/:id/stats", get(get_global_player_stats))
+        .route("/players/:id/presence", get(get_player_presence))
+        .route("/weapons", get(list_weapons))
+        .route("/tournaments/:id", get(get_tournament));
+    let by_code = Router::new()
+        .route("/lobbies", post(create_lobby))
+        .route("/lobbies/:code/join", post(join_lobby))
+        .route("/lobbies/:code/join-party", post(join_party))
+        .route_layer(from_fn_with_state(app_state.state.clone(), limit_code_failures));
+    let player = Router::new()
+        .merge(by_code)
+        .route("/quickjoin", post(quick_join))
+        .route("/lobbies/:code", patch(update_lobby))
+        .route("/friends", get(list_friends).post(add_friend))
+        .route("/friends/requests", get(list_friend_requests))
+        .route("/friends/:account", delete(remove_friend))
+        .route("/friends/:account/join", post(join_friend))
+        .route("/players/:id/loadouts", get(list_loadouts))
+        .route("/players/:id/loadouts/:name", get(get_loadout).put(save_loadout).delete(delete_loadout))
+        .route_layer(gate(Role::Player));
+    let service = Router::new()
+        .route("/accounts/:account/vip", put(set_player_vip))
+        .route("/drain", post(start_drain))
+        .route_layer(gate(Role::Service));
+    let admin = Router::new()
+        .route("/matches/:id/damage", get(get_match_damage))
+        .route("/lobbies/:code/end", post(end_match))
+        .route("/lobbies/:code/pause", post(pause_lobby))
+        .route("/lobbies/:code/resume", post(resume_lobby))
+        .route("/lobbies/:code/script", put(set_rules_script).delete(clear_rules_script))
+        .route("/lobbies/:code/banned-weapons/:id", put(ban_weapon).delete(unban_weapon))
+        .route("/lobbies/:code/merge", post(merge_lobby))
+        .route("/lobbies/:code/split", post(split_lobby))
+        .route("/lobbies/:code/logs", get(get_lobby_logs))
+        .route("/admin/stats/export", get(export_global_stats))
+        .route("/status", get(get_status))
+        .route("/debug/weapons/:id/falloff", get(get_weapon_falloff))
+        .route("/announce", post(announce))
+        .route("/announce/:id", delete(cancel_announcement))
+        .route("/tournaments", post(create_tournament))
+        .route_layer(gate(Role::Admin));
+    public.merge(player).merge(service).merge(admin)
+        .route_layer(from_fn(trace_request))
+}
+
+/// Turn away addresses that keep creating or joining with refused (400) or unknown (404) lobby codes
+async fn limit_code_failures(
+    State(state): State<Arc<ServerState>>,
+    connect_info: Option<ConnectInfo<std::net::SocketAddr>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let addr = connect_info.map(|ConnectInfo(addr)| addr.ip());
+    let now = std::time::Instant::now();
+    if let Some(left) = state.code_failures.blocked_for(addr, now) {
+        return auth::too_many_requests(left);
+    }
+    let response = next.run(request).await;
+    if matches!(response.status(), StatusCode::BAD_REQUEST | StatusCode::NOT_FOUND) {
+        state.code_failures.record(addr, now);
+    }
+    response
+}
+
+/// One server span per request, continuing the caller's trace when it sends a traceparent
+async fn trace_request(matched: MatchedPath, params: RawPathParams, request: Request, next: Next) -> Response {
+    let parent = request.headers().get(telemetry::TRACEPARENT)
+        .and_then(|v| v.to_str().ok());
+    let mut span = Span::root("http.request", SpanKind::Server, parent);
+    if span.is_recording() {
+        span.set("http.request.method", request.method().to_string());
+        span.set("http.route", matched.as_str().to_string());
+        for (name, value) in &params {
+            match name {
+                "code" => span.set("lobby.code", value.to_string()),
+                "id" => span.set("path.id", value.to_string()),
+                _ => {}
+            }
+        }
+    }
+    let response = telemetry::in_span(&span, next.run(request)).await;
+    span.set("http.response.status_code", response.status().as_u16() as i64);
+    if response.status().is_server_error() {
+        span.fail(response.status().to_string());
+    }
+    response
+}
+
+/// Tag every response with the API version it was served by
+async fn add_version_header(mut response: Response) -> Response {
+    response.headers_mut().insert("x-api-version", HeaderValue::from_static(API_VERSION));
+    response
+}
+
+/// Unversioned paths are a compatibility shim for existing clients
+async fn add_legacy_headers(mut response: Response) -> Response {
+    response.headers_mut().insert("deprecation", HeaderValue::from_static("true"));
+    response
+}
+
+/// Build the HTTP router: /v1 routes, the legacy unversioned shim and API docs
+fn build_router(app_state: AppState) -> Router {
+    let failures = Arc::new(auth::auth_failures());
+    Router::new()
+        .nest("/v1", api_routes(&app_state, &failures))
+        .merge(api_routes(&app_state, &failures).layer(map_response(add_legacy_headers)))
+        .route("/metrics", get(get_metrics))
+        .merge(SwaggerUi::new("/docs").url("/api-docs/openapi.json", ApiDoc::openapi()))
+        .layer(map_response(add_version_header))
+        .layer(CorsLayer::permissive())
+        .with_state(app_state)
+}
+
 /// Initialize HTTP server
 fn init_http_server(
     state: Arc<ServerState>,
@@ -43,15 +198,7 @@ fn init_http_server(
         udp_socket,
     };
     
-    let app = Router::new()
-        .route("/lobbies", post(create_lobby))
-        .route("/lobbies", get(list_lobbies))
-        .route("/lobbies/:code/join", post(join_lobby))
-        .route("/lobbies/:code", get(get_lobby))
-        .route("/lobbies/:code/leaderboard", get(get_lobby_leaderboard))
-        .route("/leaderboard", get(get_global_leaderboard))
-        .layer(CorsLayer::permissive())
-        .with_state(app_state);
+    let app = build_router(app_state);
 
     let http_addr = format!("0.0.0.0:{}", 8080);
     info!("Starting HTTP server on {}", http_addr);
@@ -68,21 +215,84 @@ fn init_http_server(
             }
         };
 
-        if let Err(e) = axum::serve(listener, app).await {
+        if let Err(e) = axum::serve(listener, app.into_make_service_with_connect_info::<std::net::SocketAddr>()).await {
             eprintln!("HTTP server error: {}", e);
         }
     })
 }
 
+/// Periodically repair the player -> lobby index
+fn init_index_sweeper(state: Arc<ServerState>, config: Arc<Config>) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut timer = tokio::time::interval(std::time::Duration::from_secs(config.index_sweep_interval_secs));
+        loop {
+            timer.tick().await;
+            let repair = state.repair_player_index(PLAYER_INDEX_TTL).await;
+            if repair != IndexRepair::default() {
+                log::info!("Player index repaired: {:?}", repair);
+            }
+        }
+    })
+}
+
+/// Listen for a primary's replication stream and take over its lobbies when it stops
+async fn init_standby(
+    addr: &str,
+    state: Arc<ServerState>,
+    weapons: Arc<WeaponDb>,
+    config: Arc<Config>,
+    socket: Arc<UdpSocket>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let listener = TcpListener::bind(addr).await?;
+    info!("Standby listening for replication on {}", addr);
+    tokio::spawn(replication::run_standby(listener, state, weapons, config, socket));
+    Ok(())
+}
+
+/// Open the admin console on the configured local socket and/or TCP address
+async fn init_admin_console(app: AppState) -> Result<(), Box<dyn std::error::Error>> {
+    let config = app.config.clone();
+    if let Some(addr) = &config.admin_console_addr {
+        // Over TCP the console is only as safe as its token; refuse to expose it without one
+        let token = config.admin_token.clone()
+            .filter(|token| !token.is_empty())
+            .ok_or("admin_console_addr requires admin_token to be set")?;
+        let listener = TcpListener::bind(addr).await?;
+        info!("Admin console listening on {}", addr);
+        tokio::spawn(console::serve_tcp(listener, app.clone(), token));
+    }
+    #[cfg(unix)]
+    if let Some(path) = &config.admin_console_socket {
+        // Clear a socket left behind by a previous run, but never some other file at that path
+        use std::os::unix::fs::FileTypeExt;
+        match std::fs::symlink_metadata(path) {
+            Ok(meta) if meta.file_type().is_socket() => std::fs::remove_file(path)?,
+            Ok(_) => return Err(format!("admin_console_socket {} exists and is not a socket", path).into()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+        let listener = tokio::net::UnixListener::bind(path)?;
+        info!("Admin console listening on {}", path);
+        tokio::spawn(console::serve_unix(listener, app));
+    }
+    Ok(())
+}
+
 /// Initialize UDP server
+/// With a network simulation, incoming packets are delayed, dropped or duplicated before handling
 async fn init_udp_server(
     state: Arc<ServerState>,
     weapons: Arc<WeaponDb>,
     socket: Arc<UdpSocket>,
+    net_sim: Option<NetSimConfig>,
 ) -> Result<tokio::task::JoinHandle<()>, Box<dyn std::error::Error>> {
     let socket_clone = socket.clone();
     let state_clone = state.clone();
     let weapons_clone = weapons.clone();
+    if let Some(sim) = &net_sim {
+        log::warn!("Network simulation enabled: {:?}", sim);
+    }
+    let mut net_sim = net_sim.map(|config| NetSim::new(config, SeededRng::from_clock()));
 
     Ok(tokio::spawn(async move {
         let mut buf = [0u8; 1024];
@@ -90,12 +300,25 @@ async fn init_udp_server(
         loop {
             match socket_clone.recv_from(&mut buf).await {
                 Ok((len, addr)) => {
+                    state_clone.bandwidth.record_received(addr, len);
                     let data = &buf[..len];
-                    if let Ok(packet) = serde_json::from_slice::<serde_json::Value>(data) {
+                    let Ok(packet) = serde_json::from_slice::<serde_json::Value>(data) else {
+                        continue;
+                    };
+                    let Some(sim) = net_sim.as_mut() else {
                         handle_udp_packet(packet, addr, &socket_clone, &state_clone, &weapons_clone).await;
+                        continue;
+                    };
+                    for delay in sim.roll() {
+                        let (packet, socket, state, weapons) = (packet.clone(), socket_clone.clone(), state_clone.clone(), weapons_clone.clone());
+                        tokio::spawn(async move {
+                            tokio::time::sleep(delay).await;
+                            handle_udp_packet(packet, addr, &socket, &state, &weapons).await;
+                        });
                     }
                 }
                 Err(e) => {
+                    udp_socket::COUNTERS.record_recv_error();
                     log::error!("UDP recv error: {}", e);
                 }
             }
@@ -103,6 +326,9 @@ async fn init_udp_server(
     }))
 }
 
+/// Lobby every server starts with; stored canonical like any other code
+pub const DEFAULT_LOBBY_CODE: &str = "TEST";
+
 /// Create a new lobby and spawn its tick loop
 pub async fn create_lobby_with_tick(
     state: Arc<ServerState>,
@@ -113,12 +339,28 @@ pub async fn create_lobby_with_tick(
     config: Arc<Config>,
     socket: Arc<UdpSocket>,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    if state.lobby_exists(&code) {
+    spawn_lobby(state, Lobby::new(code, max_players, scene), weapons, config, socket).await
+}
+
+/// Register an already configured lobby and spawn its tick loop
+pub async fn spawn_lobby(
+    state: Arc<ServerState>,
+    lobby: Lobby,
+    weapons: Arc<WeaponDb>,
+    config: Arc<Config>,
+    socket: Arc<UdpSocket>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if state.lobby_exists(&lobby.code) {
         return Err("Lobby already exists".into());
     }
 
-    // Create lobby
-    let lobby = Arc::new(RwLock::new(Lobby::new(code.clone(), max_players, scene.clone())));
+    let code = lobby.code.clone();
+    if let Some(replicator) = state.replicator() {
+        replicator.publish(ReplicationRecord::LobbyCreated { lobby: LobbyCheckpoint::capture(&lobby), at: Some(lobby.lifetime.created_at) });
+    }
+    let summary = ArcSwap::from_pointee(lobbies::summarize(&lobby));
+    let presence = lobby.presence.clone();
+    let lobby = Arc::new(RwLock::new(lobby));
 
     // Create command channel
     let (tx, rx) = mpsc::channel::<crate::state::commands::LobbyCommand>(1000);
@@ -129,15 +371,18 @@ pub async fn create_lobby_with_tick(
     let tick_socket = socket.clone();
     let tick_lobby = lobby.clone();
     let tick_state = state.clone();
-    let task_handle = tokio::spawn(async move {
+    let tick_code = code.clone();
+    let task_handle = tokio::spawn(log_context::with_lobby(tick_code, async move {
         lobby_tick_loop(tick_lobby, rx, tick_socket, tick_weapons, tick_config, Some(tick_state)).await;
-    });
+    }));
 
     // Create handle
     let handle = LobbyHandle {
         lobby,
         command_tx: tx,
         task_handle,
+        summary,
+        presence,
     };
 
     // Insert into state
@@ -150,20 +395,37 @@ pub async fn create_lobby_with_tick(
 mod integration_tests {
     use std::sync::Arc;
     use std::time::Duration;
-    use tokio::sync::mpsc;
     use tokio::net::UdpSocket;
     use crate::state::server_state::ServerState;
-    use crate::state::lobby::Lobby;
     use crate::state::commands::LobbyCommand;
     use crate::utils::weapondb::WeaponDb;
     use crate::utils::config::Config;
+    use crate::handlers::auth::{ApiKey, Role};
+
+    /// Config with a player key for each account, named `<account>-key`
+    fn account_config(accounts: &[&str]) -> Config {
+        Config {
+            api_keys: accounts.iter()
+                .map(|account| ApiKey { key: format!("{}-key", account), role: Role::Player, account: Some(account.to_string()) })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    /// Headers authenticating with `account`'s key from `account_config`
+    fn as_account(account: &str) -> axum::http::HeaderMap {
+        let mut headers = axum::http::HeaderMap::new();
+        headers.insert(axum::http::header::AUTHORIZATION, format!("Bearer {}-key", account).parse().unwrap());
+        headers
+    }
 
     #[tokio::test]
     async fn test_full_lobby_lifecycle() {
         let state = Arc::new(ServerState::new());
         let udp_socket = Arc::new(UdpSocket::bind("127.0.0.1:0").await.unwrap());
         let weapons = Arc::new(WeaponDb::load());
-        let config = Arc::new(Config::default());
+        // Start the match as soon as everyone is ready
+        let config = Arc::new(Config { countdown_secs: 0, ..Default::default() });
 
         // Create lobby
         let create_result = super::create_lobby_with_tick(
@@ -222,7 +484,6 @@ mod integration_tests {
             player_id: 1,
             position: (10.0, 5.0, 20.0),
             rotation: (0.0, 1.0, 0.0),
-            addr: player1_addr,
         }).await.unwrap();
 
         tokio::time::sleep(Duration::from_millis(50)).await;
@@ -246,9 +507,16 @@ mod integration_tests {
             assert_eq!(p2.current_health, 100, "Player 2 should start with 100 health");
         }
 
+        for player_id in [1, 2] {
+            command_tx.send(LobbyCommand::Ready { player_id, ready: true }).await.unwrap();
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(lobby_arc.read().await.is_match_live());
+
         command_tx.send(LobbyCommand::Shoot {
             player_id: 1,
-            target_id: 2,
+            target_id: Some(2),
+            pellet_hits: Vec::new(),
         }).await.unwrap();
 
         // Wait for tick to process (tick interval is 20ms, wait 2 ticks)
@@ -294,7 +562,8 @@ mod integration_tests {
         let state = Arc::new(ServerState::new());
         let udp_socket = Arc::new(UdpSocket::bind("127.0.0.1:0").await.unwrap());
         let weapons = Arc::new(WeaponDb::load());
-        let config = Arc::new(Config::default());
+        // Start the match as soon as everyone is ready
+        let config = Arc::new(Config { countdown_secs: 0, ..Default::default() });
 
         super::create_lobby_with_tick(
             state.clone(),
@@ -317,14 +586,18 @@ mod integration_tests {
                 addr: format!("127.0.0.1:{}", 9000 + i).parse().unwrap(),
             }).await.unwrap();
         }
+        for player_id in 1..=3 {
+            command_tx.send(LobbyCommand::Ready { player_id, ready: true }).await.unwrap();
+        }
         tokio::time::sleep(Duration::from_millis(100)).await;
 
         // Combat: Player 1 attacks Player 2 multiple times with proper fire rate
         // Golden Friend: 4 shots/sec = 250ms between shots
-        for i in 0..5 {
+        for _i in 0..5 {
             command_tx.send(LobbyCommand::Shoot {
                 player_id: 1,
-                target_id: 2,
+                target_id: Some(2),
+                pellet_hits: Vec::new(),
             }).await.unwrap();
             // Wait for fire rate limit (250ms per shot for 4 shots/sec)
             tokio::time::sleep(Duration::from_millis(260)).await;
@@ -345,7 +618,8 @@ mod integration_tests {
         let state = Arc::new(ServerState::new());
         let udp_socket = Arc::new(UdpSocket::bind("127.0.0.1:0").await.unwrap());
         let weapons = Arc::new(WeaponDb::load());
-        let config = Arc::new(Config::default());
+        // Start the match as soon as everyone is ready
+        let config = Arc::new(Config { countdown_secs: 0, ..Default::default() });
 
         super::create_lobby_with_tick(
             state.clone(),
@@ -366,13 +640,15 @@ mod integration_tests {
             name: "Shooter".to_string(),
             addr: "127.0.0.1:9999".parse().unwrap(),
         }).await.unwrap();
+        command_tx.send(LobbyCommand::Ready { player_id: 1, ready: true }).await.unwrap();
         tokio::time::sleep(Duration::from_millis(50)).await;
 
         // Fire enough shots to empty ammo (20 shots with proper timing)
-        for i in 0..20 {
+        for _i in 0..20 {
             command_tx.send(LobbyCommand::Shoot {
                 player_id: 1,
-                target_id: 999,
+                target_id: Some(999),
+                pellet_hits: Vec::new(),
             }).await.unwrap();
             // Wait for fire rate limit (250ms per shot for 4 shots/sec)
             tokio::time::sleep(Duration::from_millis(300)).await;
@@ -486,7 +762,6 @@ mod integration_tests {
                 player_id: 1,
                 position: (x, y, z),
                 rotation: (0.0, 1.0, 0.0),
-                addr: "127.0.0.1:7777".parse().unwrap(),
             }).await.unwrap();
             // Wait for tick to process (tick interval is 20ms)
             tokio::time::sleep(Duration::from_millis(30)).await;
@@ -540,17 +815,31 @@ mod integration_tests {
 
         tokio::time::sleep(Duration::from_millis(100)).await;
 
-        // Send heartbeat
-        command_tx.send(LobbyCommand::Heartbeat {
+        // Keepalives are recorded outside the lobby lock and applied by the next tick
+        let presence = state.get_lobby_presence("HEARTBEAT_TEST").unwrap();
+        presence.touch(1, "127.0.0.1:6667".parse().unwrap(), std::time::SystemTime::now());
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        {
+            let lobby = lobby_arc.read().await;
+            let player = lobby.players.get(&1).unwrap();
+            assert!(player.last_update > initial_update);
+            // The new address only gets traffic once the client confirms it
+            assert!(!lobby.client_addresses.contains_key(&1));
+            assert_eq!(lobby.rebinds.pending_addr(1), Some("127.0.0.1:6667".parse().unwrap()));
+            assert!(presence.is_empty());
+        }
+
+        command_tx.send(LobbyCommand::RebindAck {
             player_id: 1,
-            addr: "127.0.0.1:6666".parse().unwrap(),
+            addr: "127.0.0.1:6667".parse().unwrap(),
+            generation: 1,
         }).await.unwrap();
-
         tokio::time::sleep(Duration::from_millis(50)).await;
 
         let lobby = lobby_arc.read().await;
-        let player = lobby.players.get(&1).unwrap();
-        assert!(player.last_update > initial_update);
+        assert_eq!(lobby.client_addresses[&1], "127.0.0.1:6667".parse().unwrap());
     }
 
     #[tokio::test]
@@ -591,6 +880,7 @@ mod integration_tests {
             player_id: 1,
             name: "TestPlayer".to_string(),
             addr: "192.168.1.100:5000".parse().unwrap(),
+            client_id: None,
         }).await.unwrap();
 
         tokio::time::sleep(Duration::from_millis(50)).await;
@@ -698,7 +988,6 @@ mod integration_tests {
             player_id: 1,
             position: (100.0, 50.0, 100.0),
             rotation: (0.0, 0.0, 0.0),
-            addr: "127.0.0.1:5555".parse().unwrap(),
         }).await.unwrap();
         tokio::time::sleep(Duration::from_millis(50)).await;
 
@@ -708,4 +997,1600 @@ mod integration_tests {
         assert_ne!(player.position, initial_position, "Position should have changed");
         assert_eq!(player.position, (100.0, 50.0, 100.0), "Position should be new value");
     }
+
+    #[tokio::test]
+    async fn test_player_state_endpoints() {
+        use axum::extract::{Path, State};
+        use axum::http::StatusCode;
+        use crate::handlers::http::{get_player_state, get_player_stats, AppState};
+
+        let state = Arc::new(ServerState::new());
+        let udp_socket = Arc::new(UdpSocket::bind("127.0.0.1:0").await.unwrap());
+        let weapons = Arc::new(WeaponDb::load());
+        let config = Arc::new(Config::default());
+
+        super::create_lobby_with_tick(
+            state.clone(),
+            "STATETEST".to_string(),
+            4,
+            "world".to_string(),
+            weapons.clone(),
+            config.clone(),
+            udp_socket.clone(),
+        ).await.unwrap();
+
+        let command_tx = state.get_lobby_tx("STATETEST").unwrap();
+        command_tx.send(LobbyCommand::PlayerJoin {
+            player_id: 1,
+            name: "StatePlayer".to_string(),
+            addr: "127.0.0.1:5556".parse().unwrap(),
+        }).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let app_state = AppState { state, weapons, config, udp_socket };
+
+        let player = get_player_state(State(app_state.clone()), Path(("STATETEST".to_string(), 1)))
+            .await
+            .unwrap();
+        assert_eq!(player.name, "StatePlayer");
+        assert_eq!(player.current_health, player.max_health);
+        assert_eq!(player.current_weapon_id, 1);
+
+        let stats = get_player_stats(State(app_state.clone()), Path(("STATETEST".to_string(), 1)))
+            .await
+            .unwrap();
+        assert_eq!(stats.total_kills, 0);
+
+        // Unknown player and unknown lobby are both 404
+        let missing = get_player_state(State(app_state.clone()), Path(("STATETEST".to_string(), 42))).await;
+        assert_eq!(missing.err(), Some(StatusCode::NOT_FOUND));
+        let missing = get_player_stats(State(app_state), Path(("NOPE".to_string(), 1))).await;
+        assert_eq!(missing.err(), Some(StatusCode::NOT_FOUND));
+    }
+
+    #[tokio::test]
+    async fn test_update_lobby_motd() {
+        use axum::extract::{Path, State};
+        use axum::http::{HeaderMap, StatusCode};
+        use axum::Json;
+        use crate::handlers::http::{join_lobby, update_lobby, AppState};
+        use crate::handlers::models::{JoinLobbyRequest, UpdateLobbyRequest};
+
+        let state = Arc::new(ServerState::new());
+        let udp_socket = Arc::new(UdpSocket::bind("127.0.0.1:0").await.unwrap());
+        let weapons = Arc::new(WeaponDb::load());
+        let config = Arc::new(account_config(&["owner", "guest"]));
+
+        super::create_lobby_with_tick(
+            state.clone(),
+            "MOTDTEST".to_string(),
+            4,
+            "world".to_string(),
+            weapons.clone(),
+            config.clone(),
+            udp_socket.clone(),
+        ).await.unwrap();
+
+        let app_state = AppState { state, weapons, config, udp_socket };
+        let join = |account: &str| join_lobby(
+            State(app_state.clone()),
+            as_account(account),
+            Path("MOTDTEST".to_string()),
+            Json(JoinLobbyRequest { player_name: account.to_string(), team: None }),
+        );
+        let owner = join("owner").await.unwrap().player_id;
+        let guest = join("guest").await.unwrap().player_id;
+
+        let update = |headers: HeaderMap, motd: String| update_lobby(
+            State(app_state.clone()),
+            headers,
+            Path("MOTDTEST".to_string()),
+            Json(UpdateLobbyRequest { motd: Some(motd) }),
+        );
+        // Only the owner's key may change settings; nothing in the body says who is asking
+        assert_eq!(update(as_account("guest"), "Guest rules".to_string()).await.err(), Some(StatusCode::FORBIDDEN));
+        assert_eq!(update(HeaderMap::new(), "Guest rules".to_string()).await.err(), Some(StatusCode::FORBIDDEN));
+        assert_eq!(update(as_account("owner"), "x".repeat(5000)).await.err(), Some(StatusCode::BAD_REQUEST));
+
+        let info = update(as_account("owner"), "Best of three".to_string()).await.unwrap();
+        assert_eq!(info.motd, "Best of three");
+        let lobby = app_state.state.get_lobby("MOTDTEST").unwrap();
+        assert!(lobby.read().await.is_owner(owner));
+        assert!(!lobby.read().await.is_owner(guest));
+    }
+
+    #[tokio::test]
+    async fn test_versioned_and_legacy_routes() {
+        use crate::handlers::http::AppState;
+        use std::io::{Read, Write};
+
+        let app_state = AppState {
+            state: Arc::new(ServerState::new()),
+            weapons: Arc::new(WeaponDb::load()),
+            config: Arc::new(Config::default()),
+            udp_socket: Arc::new(UdpSocket::bind("127.0.0.1:0").await.unwrap()),
+        };
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, super::build_router(app_state)).await.unwrap();
+        });
+
+        let get = |path: &'static str| async move {
+            tokio::task::spawn_blocking(move || {
+                let mut stream = std::net::TcpStream::connect(addr).unwrap();
+                let request = format!("GET {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n", path);
+                stream.write_all(request.as_bytes()).unwrap();
+                let mut response = String::new();
+                stream.read_to_string(&mut response).unwrap();
+                response.to_lowercase()
+            }).await.unwrap()
+        };
+
+        let versioned = get("/v1/lobbies").await;
+        assert!(versioned.starts_with("http/1.1 200"));
+        assert!(versioned.contains("x-api-version: 1"));
+        assert!(!versioned.contains("deprecation"));
+
+        let legacy = get("/lobbies").await;
+        assert!(legacy.starts_with("http/1.1 200"));
+        assert!(legacy.contains("x-api-version: 1"));
+        assert!(legacy.contains("deprecation: true"));
+
+        assert!(get("/v1/lobbies/NOPE").await.starts_with("http/1.1 404"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_admin_console_socket_only_replaces_sockets() {
+        use crate::handlers::http::AppState;
+
+        let path = std::env::temp_dir().join(format!("gungame_console_{}.sock", std::process::id()));
+        let path_str = path.to_str().unwrap().to_string();
+        let app = |path: String| async move {
+            AppState {
+                state: Arc::new(ServerState::new()),
+                weapons: Arc::new(WeaponDb::load()),
+                config: Arc::new(Config { admin_console_socket: Some(path), ..Default::default() }),
+                udp_socket: Arc::new(UdpSocket::bind("127.0.0.1:0").await.unwrap()),
+            }
+        };
+
+        // A stale socket from an earlier run is cleared
+        drop(std::os::unix::net::UnixListener::bind(&path).unwrap());
+        assert!(super::init_admin_console(app(path_str.clone()).await).await.is_ok());
+
+        // Any other file is left alone
+        std::fs::remove_file(&path).unwrap();
+        std::fs::write(&path, "keep me").unwrap();
+        assert!(super::init_admin_console(app(path_str.clone()).await).await.is_err());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "keep me");
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_route_groups_enforce_roles() {
+        use crate::handlers::auth::{ApiKey, Role, MAX_AUTH_FAILURES};
+        use crate::handlers::http::AppState;
+        use std::io::{Read, Write};
+
+        let config = Config {
+            api_keys: vec![
+                ApiKey { key: "client".to_string(), role: Role::Player, account: None },
+                ApiKey { key: "ops".to_string(), role: Role::Admin, account: None },
+            ],
+            anonymous_role: Role::Public,
+            ..Default::default()
+        };
+        let app_state = AppState {
+            state: Arc::new(ServerState::new()),
+            weapons: Arc::new(WeaponDb::load()),
+            config: Arc::new(config),
+            udp_socket: Arc::new(UdpSocket::bind("127.0.0.1:0").await.unwrap()),
+        };
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let app = super::build_router(app_state).into_make_service_with_connect_info::<std::net::SocketAddr>();
+            axum::serve(listener, app).await.unwrap();
+        });
+
+        let send = |request_line: &'static str, key: Option<&'static str>| async move {
+            tokio::task::spawn_blocking(move || {
+                let mut stream = std::net::TcpStream::connect(addr).unwrap();
+                let auth = key.map(|key| format!("Authorization: Bearer {}\r\n", key)).unwrap_or_default();
+                let request = format!("{} HTTP/1.1\r\nHost: localhost\r\n{}Content-Length: 0\r\nConnection: close\r\n\r\n", request_line, auth);
+                stream.write_all(request.as_bytes()).unwrap();
+                let mut response = String::new();
+                stream.read_to_string(&mut response).unwrap();
+                response.to_lowercase()
+            }).await.unwrap()
+        };
+
+        assert!(send("GET /v1/lobbies", None).await.starts_with("http/1.1 200"));
+        assert!(send("GET /v1/players/1/loadouts", None).await.starts_with("http/1.1 401"));
+        assert!(send("GET /v1/players/1/loadouts", Some("client")).await.starts_with("http/1.1 200"));
+        assert!(send("GET /v1/lobbies/NOPE/logs", Some("client")).await.starts_with("http/1.1 403"));
+        assert!(send("GET /v1/lobbies/NOPE/logs", Some("ops")).await.starts_with("http/1.1 404"));
+        // Status names live lobbies, so only admins see it
+        assert!(send("GET /v1/status", Some("client")).await.starts_with("http/1.1 403"));
+        assert!(send("GET /v1/status", Some("ops")).await.starts_with("http/1.1 200"));
+        // Admin keys can do everything service keys can
+        assert!(send("POST /drain", Some("ops")).await.starts_with("http/1.1 202"));
+
+        // Guessing keys gets the address refused, even once it sends a good one
+        for _ in 3..MAX_AUTH_FAILURES {
+            assert!(send("GET /v1/lobbies/NOPE/logs", Some("guess")).await.starts_with("http/1.1 401"));
+        }
+        let blocked = send("GET /v1/lobbies/NOPE/logs", Some("ops")).await;
+        assert!(blocked.starts_with("http/1.1 429"));
+        assert!(blocked.contains("retry-after:"));
+        assert!(send("GET /v1/lobbies", None).await.starts_with("http/1.1 200"));
+    }
+
+    #[tokio::test]
+    async fn test_lobby_code_guessing_is_rate_limited() {
+        use crate::handlers::http::AppState;
+        use crate::state::server_state::MAX_LOBBY_CODE_FAILURES;
+        use std::io::{Read, Write};
+
+        let state = Arc::new(ServerState::new());
+        let app_state = AppState {
+            state: state.clone(),
+            weapons: Arc::new(WeaponDb::load()),
+            config: Arc::new(Config::default()),
+            udp_socket: Arc::new(UdpSocket::bind("127.0.0.1:0").await.unwrap()),
+        };
+        super::create_lobby_with_tick(
+            state.clone(), "REAL".to_string(), 4, "world".to_string(),
+            app_state.weapons.clone(), app_state.config.clone(), app_state.udp_socket.clone(),
+        ).await.unwrap();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let router_state = app_state.clone();
+        tokio::spawn(async move {
+            let app = super::build_router(router_state).into_make_service_with_connect_info::<std::net::SocketAddr>();
+            axum::serve(listener, app).await.unwrap();
+        });
+
+        let join = |code: &'static str| async move {
+            tokio::task::spawn_blocking(move || {
+                let mut stream = std::net::TcpStream::connect(addr).unwrap();
+                let body = r#"{"player_name":"Ann"}"#;
+                let request = format!(
+                    "POST /v1/lobbies/{}/join HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    code, body.len(), body
+                );
+                stream.write_all(request.as_bytes()).unwrap();
+                let mut response = String::new();
+                stream.read_to_string(&mut response).unwrap();
+                response.to_lowercase()
+            }).await.unwrap()
+        };
+
+        assert!(join("REAL").await.starts_with("http/1.1 200"));
+        // Unknown and malformed codes both count; the address is then refused even for a real lobby
+        for i in 0..MAX_LOBBY_CODE_FAILURES {
+            let expected = if i % 2 == 0 { "http/1.1 404" } else { "http/1.1 400" };
+            assert!(join(if i % 2 == 0 { "NOPE" } else { "no" }).await.starts_with(expected));
+        }
+        let blocked = join("REAL").await;
+        assert!(blocked.starts_with("http/1.1 429"));
+        assert!(blocked.contains("retry-after:"));
+
+        // UDP joins from that address are turned away the same way
+        let client = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let client_addr = client.local_addr().unwrap();
+        let cookie = state.handshake.issue(client_addr, std::time::SystemTime::now());
+        let packet = serde_json::json!({ "type": "join", "lobby_code": "REAL", "player_id": 77, "cookie": cookie });
+        super::handle_udp_packet(packet, client_addr, &app_state.udp_socket, &state, &app_state.weapons).await;
+        let mut buf = [0u8; 2048];
+        let (len, _) = tokio::time::timeout(Duration::from_secs(1), client.recv_from(&mut buf)).await.unwrap().unwrap();
+        let reply: serde_json::Value = serde_json::from_slice(&buf[..len]).unwrap();
+        assert_eq!(reply["message"], crate::handlers::admin::TOO_MANY_CODE_FAILURES);
+    }
+
+    #[tokio::test]
+    async fn test_follow_friend_into_lobby() {
+        use axum::extract::{Path, State};
+        use axum::http::{HeaderMap, StatusCode};
+        use axum::Json;
+        use crate::handlers::http::{add_friend, join_friend, join_lobby, list_friend_requests, list_friends, AppState};
+        use crate::handlers::models::{AddFriendRequest, JoinFriendRequest, JoinLobbyRequest};
+        use crate::state::lobby::{Lobby, LobbySettings};
+
+        let state = Arc::new(ServerState::new());
+        let udp_socket = Arc::new(UdpSocket::bind("127.0.0.1:0").await.unwrap());
+        let weapons = Arc::new(WeaponDb::load());
+        let config = Arc::new(account_config(&["follower", "host", "hidden"]));
+        for (code, private) in [("FRIENDTEST", false), ("SECRET", true)] {
+            let settings = LobbySettings { private, ..Default::default() };
+            super::spawn_lobby(
+                state.clone(),
+                Lobby::with_settings(code.to_string(), 4, "world".to_string(), settings),
+                weapons.clone(),
+                config.clone(),
+                udp_socket.clone(),
+            ).await.unwrap();
+        }
+
+        let app_state = AppState { state: state.clone(), weapons, config, udp_socket };
+        let join = |account: &str, code: &str, name: &str| join_lobby(
+            State(app_state.clone()),
+            as_account(account),
+            Path(code.to_string()),
+            Json(JoinLobbyRequest { player_name: name.to_string(), team: None }),
+        );
+        let host = join("host", "FRIENDTEST", "Host").await.unwrap().player_id;
+        assert!(join("hidden", "SECRET", "Hidden").await.is_ok());
+
+        let follow = |friend: &str| join_friend(
+            State(app_state.clone()),
+            as_account("follower"),
+            Path(friend.to_string()),
+            Json(JoinFriendRequest { player_name: "Follower".to_string() }),
+        );
+        let befriend = |from: &str, to: &str| add_friend(
+            State(app_state.clone()),
+            as_account(from),
+            Json(AddFriendRequest { account: to.to_string() }),
+        );
+        assert_eq!(follow("host").await.err(), Some(StatusCode::FORBIDDEN));
+
+        // Asking isn't enough: the friend has to accept
+        for friend in ["host", "hidden"] {
+            assert_eq!(befriend("follower", friend).await.unwrap().0, StatusCode::ACCEPTED);
+        }
+        assert_eq!(follow("host").await.err(), Some(StatusCode::FORBIDDEN));
+        let requests = list_friend_requests(State(app_state.clone()), as_account("host")).await.unwrap();
+        assert_eq!(requests.0, vec!["follower"]);
+        for friend in ["host", "hidden"] {
+            assert_eq!(befriend(friend, "follower").await.unwrap().0, StatusCode::OK);
+        }
+
+        let friends = list_friends(State(app_state.clone()), as_account("follower")).await.unwrap();
+        assert!(friends.iter().all(|f| f.online));
+        assert_eq!(friends[0].account, "hidden");
+        assert_eq!(friends[0].lobby_code, None); // Private lobby
+        assert_eq!(friends[1].player_id, Some(host));
+        assert_eq!(friends[1].lobby_code.as_deref(), Some("FRIENDTEST"));
+        assert_eq!(friends[1].name.as_deref(), Some("Host"));
+        assert_eq!(follow("hidden").await.err(), Some(StatusCode::NOT_FOUND));
+
+        // Followers join as a new player bound to their account
+        let joined = follow("host").await.unwrap();
+        assert_eq!(joined.lobby.code, "FRIENDTEST");
+        assert_eq!(state.accounts.account_of(joined.player_id).as_deref(), Some("follower"));
+        assert_eq!(follow("host").await.err(), Some(StatusCode::CONFLICT));
+        // Without an account there's no friend list to check
+        let anonymous = list_friends(State(app_state.clone()), HeaderMap::new()).await;
+        assert_eq!(anonymous.err(), Some(StatusCode::FORBIDDEN));
+    }
+
+    #[tokio::test]
+    async fn test_players_cannot_modify_each_others_resources() {
+        use axum::extract::{Path, State};
+        use axum::http::{HeaderMap, StatusCode};
+        use axum::Json;
+        use crate::handlers::http::{add_friend, delete_loadout, join_lobby, remove_friend, save_loadout, AppState};
+        use crate::handlers::models::{AddFriendRequest, JoinLobbyRequest, SaveLoadoutRequest};
+
+        let state = Arc::new(ServerState::new());
+        let udp_socket = Arc::new(UdpSocket::bind("127.0.0.1:0").await.unwrap());
+        let weapons = Arc::new(WeaponDb::load());
+        let mut config = account_config(&["alice", "bob", "carol"]);
+        config.api_keys.push(ApiKey { key: "mm".to_string(), role: Role::Service, account: None });
+        let config = Arc::new(config);
+        super::create_lobby_with_tick(state.clone(), "OWNTEST".to_string(), 4, "world".to_string(), weapons.clone(), config.clone(), udp_socket.clone()).await.unwrap();
+        let app_state = AppState { state: state.clone(), weapons, config, udp_socket };
+        let join = |account: &str| join_lobby(
+            State(app_state.clone()),
+            as_account(account),
+            Path("OWNTEST".to_string()),
+            Json(JoinLobbyRequest { player_name: account.to_string(), team: None }),
+        );
+        assert!(join("alice").await.is_ok());
+        let bob = join("bob").await.unwrap().player_id;
+        let preset = || SaveLoadoutRequest { primary: 1, secondary: None, attachments: Vec::new() };
+        let save = |headers: HeaderMap, player_id: u32| save_loadout(State(app_state.clone()), headers, Path((player_id, "rush".to_string())), Json(preset()));
+
+        // Alice can't touch Bob's loadouts, even with his id in the path
+        assert_eq!(save(as_account("alice"), bob).await.err(), Some(StatusCode::FORBIDDEN));
+        assert!(state.loadouts.list("bob").is_empty());
+        assert!(save(as_account("bob"), bob).await.is_ok());
+        assert_eq!(delete_loadout(State(app_state.clone()), as_account("alice"), Path((bob, "rush".to_string()))).await, StatusCode::FORBIDDEN);
+        assert_eq!(state.loadouts.list("bob").len(), 1);
+
+        // Friend lists are the caller's own: a third account can't end Bob and Alice's friendship
+        let befriend = |from: &str, to: &str| add_friend(State(app_state.clone()), as_account(from), Json(AddFriendRequest { account: to.to_string() }));
+        assert!(befriend("bob", "alice").await.is_ok());
+        assert!(befriend("alice", "bob").await.is_ok());
+        let unfriend = remove_friend(State(app_state.clone()), as_account("carol"), Path("alice".to_string())).await;
+        assert_eq!(unfriend.err(), Some(StatusCode::NOT_FOUND));
+        assert!(state.friends.is_friend("bob", "alice"));
+        let anonymous = add_friend(State(app_state.clone()), HeaderMap::new(), Json(AddFriendRequest { account: "bob".to_string() })).await;
+        assert_eq!(anonymous.err(), Some(StatusCode::FORBIDDEN));
+
+        // Trusted services act for any player
+        let mut service = HeaderMap::new();
+        service.insert(axum::http::header::AUTHORIZATION, "Bearer mm".parse().unwrap());
+        assert_eq!(delete_loadout(State(app_state.clone()), service, Path((bob, "rush".to_string()))).await, StatusCode::NO_CONTENT);
+    }
+
+    #[tokio::test]
+    async fn test_player_presence() {
+        use axum::extract::{Path, State};
+        use axum::http::HeaderMap;
+        use axum::Json;
+        use crate::handlers::http::{get_player_presence, join_lobby, AppState};
+        use crate::handlers::models::JoinLobbyRequest;
+        use crate::state::lobby::{Lobby, LobbySettings, MatchPhase};
+
+        let state = Arc::new(ServerState::new());
+        let udp_socket = Arc::new(UdpSocket::bind("127.0.0.1:0").await.unwrap());
+        let weapons = Arc::new(WeaponDb::load());
+        let config = Arc::new(Config::default());
+        for (code, private) in [("PRESENCE", false), ("HIDDEN", true)] {
+            let settings = LobbySettings { private, ..Default::default() };
+            super::spawn_lobby(
+                state.clone(),
+                Lobby::with_settings(code.to_string(), 4, "world".to_string(), settings),
+                weapons.clone(),
+                config.clone(),
+                udp_socket.clone(),
+            ).await.unwrap();
+        }
+        let app_state = AppState { state: state.clone(), weapons, config, udp_socket };
+        let join = |code: &str| join_lobby(
+            State(app_state.clone()),
+            HeaderMap::new(),
+            Path(code.to_string()),
+            Json(JoinLobbyRequest { player_name: code.to_string(), team: None }),
+        );
+        let public_id = join("PRESENCE").await.unwrap().player_id;
+        let private_id = join("HIDDEN").await.unwrap().player_id;
+        let presence = |player_id: u32| get_player_presence(State(app_state.clone()), Path(player_id));
+
+        let public = presence(public_id).await;
+        assert!(public.online);
+        assert_eq!(public.lobby_code.as_deref(), Some("PRESENCE"));
+        assert_eq!(public.phase, Some(MatchPhase::Waiting));
+
+        let private = presence(private_id).await;
+        assert!(private.online);
+        assert_eq!(private.lobby_code, None);
+        assert_eq!(private.name.as_deref(), Some("HIDDEN"));
+
+        let offline = presence(9000).await;
+        assert!(!offline.online && offline.phase.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_vip_takes_reserved_slot() {
+        use axum::extract::{Path, State};
+        use axum::http::{HeaderMap, StatusCode};
+        use axum::Json;
+        use crate::handlers::http::{create_lobby, join_lobby, set_player_vip, AppState};
+        use crate::handlers::models::{JoinLobbyRequest, SetVipRequest};
+
+        let app_state = AppState {
+            state: Arc::new(ServerState::new()),
+            weapons: Arc::new(WeaponDb::load()),
+            config: Arc::new(account_config(&["vip", "regular"])),
+            udp_socket: Arc::new(UdpSocket::bind("127.0.0.1:0").await.unwrap()),
+        };
+        let request = serde_json::from_value(serde_json::json!({ "code": "VIPTEST", "max_players": 2, "reserved_slots": 1 })).unwrap();
+        let info = create_lobby(State(app_state.clone()), HeaderMap::new(), Json(request)).await.unwrap();
+        assert_eq!(info.reserved_slots, 1);
+
+        let join = |headers: HeaderMap| join_lobby(
+            State(app_state.clone()),
+            headers,
+            Path("VIPTEST".to_string()),
+            Json(JoinLobbyRequest { player_name: "Player".to_string(), team: None }),
+        );
+        let first = join(HeaderMap::new()).await.unwrap();
+        assert!(first.lobby.is_full());
+        assert_eq!(join(HeaderMap::new()).await.err(), Some(StatusCode::BAD_REQUEST));
+        assert_eq!(join(as_account("regular")).await.err(), Some(StatusCode::BAD_REQUEST));
+
+        // VIP belongs to the account, so a plain join with its key gets the reserved slot
+        let granted = set_player_vip(State(app_state.clone()), Path("vip".to_string()), Json(SetVipRequest { vip: true })).await;
+        assert_eq!(granted, StatusCode::NO_CONTENT);
+        let joined = join(as_account("vip")).await.unwrap();
+        assert_eq!(joined.lobby.player_count, 2);
+        assert!(app_state.state.is_vip_player(joined.player_id));
+    }
+
+    #[tokio::test]
+    async fn test_drain_refuses_new_players() {
+        use axum::extract::{Path, State};
+        use axum::http::{HeaderMap, StatusCode};
+        use axum::Json;
+        use crate::handlers::http::{create_lobby, join_lobby, start_drain, AppState};
+        use crate::handlers::models::JoinLobbyRequest;
+
+        let state = Arc::new(ServerState::new());
+        let udp_socket = Arc::new(UdpSocket::bind("127.0.0.1:0").await.unwrap());
+        let weapons = Arc::new(WeaponDb::load());
+        let config = Arc::new(Config::default());
+        super::create_lobby_with_tick(state.clone(), "DRAINTEST".to_string(), 4, "world".to_string(), weapons.clone(), config.clone(), udp_socket.clone()).await.unwrap();
+        let app_state = AppState { state: state.clone(), weapons, config, udp_socket };
+        let join = || join_lobby(
+            State(app_state.clone()),
+            HeaderMap::new(),
+            Path("DRAINTEST".to_string()),
+            Json(JoinLobbyRequest { player_name: "Late".to_string(), team: None }),
+        );
+        assert!(join().await.is_ok());
+
+        assert_eq!(start_drain(State(app_state.clone())).await, StatusCode::ACCEPTED);
+        assert_eq!(start_drain(State(app_state.clone())).await, StatusCode::CONFLICT);
+        assert_eq!(join().await.err(), Some(StatusCode::SERVICE_UNAVAILABLE));
+        let request = serde_json::from_value(serde_json::json!({ "code": "NEW" })).unwrap();
+        assert_eq!(create_lobby(State(app_state.clone()), HeaderMap::new(), Json(request)).await.err(), Some(StatusCode::SERVICE_UNAVAILABLE));
+        assert!(!state.lobby_exists("NEW"));
+        assert!(!state.is_drained()); // The earlier player is still playing
+    }
+
+    #[tokio::test]
+    async fn test_bandwidth_tracked_per_player() {
+        let state = Arc::new(ServerState::new());
+        let udp_socket = Arc::new(UdpSocket::bind("127.0.0.1:0").await.unwrap());
+        let client = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let config = Arc::new(Config { max_player_bytes_per_sec: Some(64_000), ..Default::default() });
+
+        super::create_lobby_with_tick(
+            state.clone(),
+            "BW_TEST".to_string(),
+            4,
+            "world".to_string(),
+            Arc::new(WeaponDb::load()),
+            config,
+            udp_socket,
+        ).await.unwrap();
+
+        let command_tx = state.get_lobby_tx("BW_TEST").unwrap();
+        command_tx.send(LobbyCommand::PlayerJoin {
+            player_id: 1,
+            name: "P1".to_string(),
+            addr: client.local_addr().unwrap(),
+        }).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        // The welcome packet counts against the player
+        let report = state.bandwidth.snapshot();
+        assert_eq!(report.len(), 1);
+        assert_eq!(report[0].player_id, 1);
+        assert_eq!(report[0].lobby_code, "BW_TEST");
+        assert!(report[0].total_bytes_sent > 0);
+
+        command_tx.send(LobbyCommand::PlayerLeave { player_id: 1 }).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(state.bandwidth.snapshot().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_no_combat_before_ready_check() {
+        let state = Arc::new(ServerState::new());
+        let udp_socket = Arc::new(UdpSocket::bind("127.0.0.1:0").await.unwrap());
+        let weapons = Arc::new(WeaponDb::load());
+        let config = Arc::new(Config { countdown_secs: 1, ..Default::default() });
+
+        super::create_lobby_with_tick(
+            state.clone(),
+            "READY_TEST".to_string(),
+            4,
+            "world".to_string(),
+            weapons.clone(),
+            config.clone(),
+            udp_socket.clone(),
+        ).await.unwrap();
+
+        let command_tx = state.get_lobby_tx("READY_TEST").unwrap();
+        let lobby_arc = state.get_lobby("READY_TEST").unwrap();
+        for player_id in [1, 2] {
+            command_tx.send(LobbyCommand::PlayerJoin {
+                player_id,
+                name: format!("Player{}", player_id),
+                addr: format!("127.0.0.1:{}", 7100 + player_id).parse().unwrap(),
+            }).await.unwrap();
+        }
+        command_tx.send(LobbyCommand::Shoot { player_id: 1, target_id: Some(2), pellet_hits: Vec::new() }).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(lobby_arc.read().await.players[&2].current_health, 100);
+
+        for player_id in [1, 2] {
+            command_tx.send(LobbyCommand::Ready { player_id, ready: true }).await.unwrap();
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(lobby_arc.read().await.phase, crate::state::lobby::MatchPhase::Countdown);
+
+        tokio::time::sleep(Duration::from_millis(1100)).await;
+        assert!(lobby_arc.read().await.is_match_live());
+
+        command_tx.send(LobbyCommand::Shoot { player_id: 1, target_id: Some(2), pellet_hits: Vec::new() }).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(lobby_arc.read().await.players[&2].current_health < 100);
+    }
+
+    #[tokio::test]
+    async fn test_match_timeline_recorded_and_archived() {
+        let state = Arc::new(ServerState::new());
+        let udp_socket = Arc::new(UdpSocket::bind("127.0.0.1:0").await.unwrap());
+        let config = Arc::new(Config { countdown_secs: 0, ..Default::default() });
+
+        super::create_lobby_with_tick(
+            state.clone(),
+            "TIMELINE_TEST".to_string(),
+            4,
+            "world".to_string(),
+            Arc::new(WeaponDb::load()),
+            config,
+            udp_socket,
+        ).await.unwrap();
+
+        let command_tx = state.get_lobby_tx("TIMELINE_TEST").unwrap();
+        let lobby_arc = state.get_lobby("TIMELINE_TEST").unwrap();
+        for player_id in [1, 2] {
+            command_tx.send(LobbyCommand::PlayerJoin {
+                player_id,
+                name: format!("Player{}", player_id),
+                addr: format!("127.0.0.1:{}", 7200 + player_id).parse().unwrap(),
+            }).await.unwrap();
+            command_tx.send(LobbyCommand::Ready { player_id, ready: true }).await.unwrap();
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let match_id = lobby_arc.read().await.timeline.as_ref().map(|t| t.match_id).expect("match started");
+        assert_eq!(state.live_match_lobby(match_id).as_deref(), Some("TIMELINE_TEST"));
+
+        command_tx.send(LobbyCommand::Shoot { player_id: 1, target_id: Some(2), pellet_hits: Vec::new() }).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        command_tx.send(LobbyCommand::EndMatch).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        assert!(lobby_arc.read().await.timeline.is_none());
+        assert!(state.live_match_lobby(match_id).is_none());
+        let timeline = state.finished_timeline(match_id).expect("timeline archived");
+        let events = timeline.window(0, timeline.last_tick);
+        // Events of the tick the match started on are at tick 0
+        assert!(events.iter().any(|e| e.event["type"] == "match_started" && e.tick == 0));
+        assert_eq!(events.last().unwrap().event["type"], "match_ended");
+        assert!(events.iter().any(|e| e.event["type"] == "player_state_update" && e.tick > 0));
+        // Player 1 did all the damage
+        assert!(timeline.awards.iter().any(|a| a.award == "most_damage" && a.player_id == 1));
+    }
+
+    #[tokio::test]
+    async fn test_match_damage_log_requires_admin_token() {
+        use axum::extract::{Path, Query, State};
+        use axum::http::{header, HeaderMap, StatusCode};
+        use crate::handlers::http::{get_match_damage, AppState};
+        use crate::handlers::models::DamageLogQuery;
+
+        let state = Arc::new(ServerState::new());
+        let udp_socket = Arc::new(UdpSocket::bind("127.0.0.1:0").await.unwrap());
+        let weapons = Arc::new(WeaponDb::load());
+        let config = Arc::new(Config { countdown_secs: 0, admin_token: Some("secret".to_string()), ..Default::default() });
+
+        super::create_lobby_with_tick(
+            state.clone(),
+            "DAMAGE_TEST".to_string(),
+            4,
+            "world".to_string(),
+            weapons.clone(),
+            config.clone(),
+            udp_socket.clone(),
+        ).await.unwrap();
+
+        let command_tx = state.get_lobby_tx("DAMAGE_TEST").unwrap();
+        let lobby_arc = state.get_lobby("DAMAGE_TEST").unwrap();
+        for player_id in [1, 2] {
+            command_tx.send(LobbyCommand::PlayerJoin {
+                player_id,
+                name: format!("Player{}", player_id),
+                addr: format!("127.0.0.1:{}", 7300 + player_id).parse().unwrap(),
+            }).await.unwrap();
+            command_tx.send(LobbyCommand::Ready { player_id, ready: true }).await.unwrap();
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let match_id = lobby_arc.read().await.timeline.as_ref().map(|t| t.match_id).expect("match started");
+        command_tx.send(LobbyCommand::Shoot { player_id: 1, target_id: Some(2), pellet_hits: Vec::new() }).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let app_state = AppState { state: state.clone(), weapons, config, udp_socket };
+        let fetch = |token: Option<&str>, player_id: Option<u32>| {
+            let mut headers = HeaderMap::new();
+            if let Some(token) = token {
+                headers.insert(header::AUTHORIZATION, format!("Bearer {}", token).parse().unwrap());
+            }
+            get_match_damage(State(app_state.clone()), Path(match_id), Query(DamageLogQuery { player_id }), headers)
+        };
+        assert_eq!(fetch(None, None).await.err(), Some(StatusCode::UNAUTHORIZED));
+        assert_eq!(fetch(Some("wrong"), None).await.err(), Some(StatusCode::UNAUTHORIZED));
+
+        let live = fetch(Some("secret"), None).await.unwrap();
+        assert!(live.live);
+        assert_eq!(live.entries.len(), 1);
+        let hit = live.entries[0];
+        assert_eq!((hit.attacker_id, hit.victim_id), (1, 2));
+        assert_eq!(hit.health_after, lobby_arc.read().await.players[&2].current_health);
+        assert!(fetch(Some("secret"), Some(3)).await.unwrap().entries.is_empty());
+
+        command_tx.send(LobbyCommand::EndMatch).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let archived = fetch(Some("secret"), Some(2)).await.unwrap();
+        assert!(!archived.live);
+        assert_eq!(archived.entries, live.entries);
+
+        // Without a configured token the endpoint is off
+        let app_state = AppState { config: Arc::new(Config::default()), ..app_state };
+        let headers = HeaderMap::new();
+        let result = get_match_damage(State(app_state), Path(match_id), Query(DamageLogQuery::default()), headers).await;
+        assert_eq!(result.err(), Some(StatusCode::FORBIDDEN));
+    }
+
+    #[tokio::test]
+    async fn test_stats_export_pages_through_every_player() {
+        use axum::body::to_bytes;
+        use axum::extract::{Query, State};
+        use axum::http::{header, HeaderMap, StatusCode};
+        use crate::handlers::http::{export_global_stats, AppState};
+        use crate::handlers::models::StatsExportQuery;
+
+        let state = Arc::new(ServerState::new());
+        for player_id in 1..=3 {
+            state.global_stats.record_session(player_id, &format!("Player{}", player_id), player_id, 0, 10);
+        }
+        let app_state = AppState {
+            state,
+            weapons: Arc::new(WeaponDb::load()),
+            config: Arc::new(Config { admin_token: Some("secret".to_string()), ..Default::default() }),
+            udp_socket: Arc::new(UdpSocket::bind("127.0.0.1:0").await.unwrap()),
+        };
+        let mut headers = HeaderMap::new();
+        headers.insert(header::AUTHORIZATION, "Bearer secret".parse().unwrap());
+        let export = |format: &str, after: Option<u32>| export_global_stats(
+            State(app_state.clone()),
+            Query(StatsExportQuery { format: Some(format.to_string()), after, limit: Some(2) }),
+            headers.clone(),
+        );
+
+        let first = export("json", None).await.unwrap();
+        assert_eq!(first.headers()["x-next-after"], "2");
+        let body: serde_json::Value = serde_json::from_slice(&to_bytes(first.into_body(), usize::MAX).await.unwrap()).unwrap();
+        assert_eq!(body["players"].as_array().unwrap().len(), 2);
+        assert_eq!(body["next_after"], 2);
+
+        let last = export("csv", Some(2)).await.unwrap();
+        assert!(!last.headers().contains_key("x-next-after"));
+        let csv = String::from_utf8(to_bytes(last.into_body(), usize::MAX).await.unwrap().to_vec()).unwrap();
+        assert_eq!(csv.lines().count(), 2);
+        assert!(csv.lines().nth(1).unwrap().starts_with("3,Player3,3,0,10,1,"));
+
+        assert_eq!(export("xml", None).await.err(), Some(StatusCode::BAD_REQUEST));
+        let unauthorized = export_global_stats(State(app_state.clone()), Query(StatsExportQuery::default()), HeaderMap::new()).await;
+        assert_eq!(unauthorized.err(), Some(StatusCode::UNAUTHORIZED));
+    }
+
+    #[tokio::test]
+    async fn test_latency_gate_probes_before_connect() {
+        use axum::extract::{Path, State};
+        use axum::http::HeaderMap;
+        use axum::Json;
+        use crate::handlers::udp::handle_udp_packet;
+        use crate::handlers::http::{create_lobby, join_lobby, AppState};
+        use crate::handlers::models::JoinLobbyRequest;
+
+        let state = Arc::new(ServerState::new());
+        let udp_socket = Arc::new(UdpSocket::bind("127.0.0.1:0").await.unwrap());
+        let client = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let client_addr = client.local_addr().unwrap();
+        let weapons = Arc::new(WeaponDb::load());
+        let app_state = AppState { state: state.clone(), weapons: weapons.clone(), config: Arc::new(Config::default()), udp_socket: udp_socket.clone() };
+
+        let request = serde_json::from_value(serde_json::json!({ "code": "PINGGATE", "max_latency_ms": 1 })).unwrap();
+        let info = create_lobby(State(app_state.clone()), HeaderMap::new(), Json(request)).await.unwrap();
+        assert_eq!(info.max_latency_ms, Some(20)); // Clamped to the tightest allowed gate
+        let joined = join_lobby(
+            State(app_state.clone()),
+            HeaderMap::new(),
+            Path("PINGGATE".to_string()),
+            Json(JoinLobbyRequest { player_name: "Far".to_string(), team: None }),
+        ).await.unwrap();
+
+        let recv = || async {
+            let mut buf = [0u8; 1024];
+            let (len, _) = tokio::time::timeout(Duration::from_secs(1), client.recv_from(&mut buf)).await.unwrap().unwrap();
+            serde_json::from_slice::<serde_json::Value>(&buf[..len]).unwrap()
+        };
+        let mut join = serde_json::json!({ "type": "join", "lobby_code": "PINGGATE", "player_id": joined.player_id, "player_name": "Far" });
+        let lobby_arc = state.get_lobby("PINGGATE").unwrap();
+        join["cookie"] = state.handshake.issue(client_addr, std::time::SystemTime::now()).into();
+
+        // A slow echo is refused with a structured error
+        handle_udp_packet(join.clone(), client_addr, &udp_socket, &state, &weapons).await;
+        let probe = recv().await;
+        assert_eq!(probe["type"], "latency_probe");
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        handle_udp_packet(serde_json::json!({ "type": "latency_probe_ack", "nonce": probe["nonce"] }), client_addr, &udp_socket, &state, &weapons).await;
+        let refused = recv().await;
+        assert_eq!(refused["code"], "latency_too_high");
+        assert!(refused["rtt_ms"].as_f64().unwrap() >= 60.0);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(!lobby_arc.read().await.client_addresses.contains_key(&joined.player_id));
+
+        // A prompt echo connects and seeds the player's RTT
+        handle_udp_packet(join, client_addr, &udp_socket, &state, &weapons).await;
+        let probe = recv().await;
+        handle_udp_packet(serde_json::json!({ "type": "latency_probe_ack", "nonce": probe["nonce"] }), client_addr, &udp_socket, &state, &weapons).await;
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let lobby = lobby_arc.read().await;
+        assert!(lobby.client_addresses.contains_key(&joined.player_id));
+        assert!(lobby.players[&joined.player_id].rtt_ms.is_some_and(|rtt| rtt <= 20.0));
+        assert!(state.latency_probes.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_pong_ack_measures_rtt_server_side() {
+        use crate::handlers::udp::handle_udp_packet;
+
+        let state = Arc::new(ServerState::new());
+        let udp_socket = Arc::new(UdpSocket::bind("127.0.0.1:0").await.unwrap());
+        let client = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let client_addr = client.local_addr().unwrap();
+        let weapons = Arc::new(WeaponDb::load());
+        super::create_lobby_with_tick(state.clone(), "PONGACK".to_string(), 4, "world".to_string(), weapons.clone(), Arc::new(Config::default()), udp_socket.clone()).await.unwrap();
+        state.get_lobby_tx("PONGACK").unwrap().send(LobbyCommand::PlayerJoin { player_id: 1, name: "P1".to_string(), addr: client_addr }).await.unwrap();
+        state.register_player_lobby(1, "PONGACK");
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        // A client's own RTT claim is ignored
+        handle_udp_packet(serde_json::json!({ "type": "ping", "player_id": 1, "seq": 1, "rtt_ms": 1.0 }), client_addr, &udp_socket, &state, &weapons).await;
+        let mut buf = [0u8; 2048];
+        let pong = loop {
+            let (len, _) = tokio::time::timeout(Duration::from_secs(1), client.recv_from(&mut buf)).await.unwrap().unwrap();
+            let packet: serde_json::Value = serde_json::from_slice(&buf[..len]).unwrap();
+            if packet["type"] == "pong" {
+                break packet;
+            }
+        };
+        assert_eq!(pong["seq"], 1);
+        tokio::time::sleep(Duration::from_millis(40)).await;
+        let lobby_arc = state.get_lobby("PONGACK").unwrap();
+        assert!(lobby_arc.read().await.players[&1].rtt_ms.is_none());
+
+        handle_udp_packet(serde_json::json!({ "type": "pong_ack", "nonce": pong["nonce"] }), client_addr, &udp_socket, &state, &weapons).await;
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(lobby_arc.read().await.players[&1].rtt_ms.is_some_and(|rtt| rtt >= 40.0));
+        assert!(state.ping_echoes.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_merge_and_split_move_players_with_scores() {
+        use crate::handlers::admin;
+        use crate::handlers::http::AppState;
+
+        let state = Arc::new(ServerState::new());
+        let udp_socket = Arc::new(UdpSocket::bind("127.0.0.1:0").await.unwrap());
+        let weapons = Arc::new(WeaponDb::load());
+        let config = Arc::new(Config::default());
+        let app = AppState { state: state.clone(), weapons: weapons.clone(), config: config.clone(), udp_socket: udp_socket.clone() };
+        for code in ["MERGEA", "MERGEB"] {
+            super::create_lobby_with_tick(state.clone(), code.to_string(), 4, "world".to_string(), weapons.clone(), config.clone(), udp_socket.clone()).await.unwrap();
+        }
+
+        let client = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let joins = [("MERGEA", 1, client.local_addr().unwrap()), ("MERGEB", 2, "127.0.0.1:9102".parse().unwrap()), ("MERGEB", 3, "127.0.0.1:9103".parse().unwrap())];
+        for (code, player_id, addr) in joins {
+            let command_tx = state.get_lobby_tx(code).unwrap();
+            command_tx.send(LobbyCommand::PlayerJoin { player_id, name: format!("P{}", player_id), addr }).await.unwrap();
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        state.get_lobby("MERGEA").unwrap().write().await.players.get_mut(&1).unwrap().score = 42;
+
+        assert_eq!(admin::merge(&app, "MERGEA", "MERGEA").await, Err("Cannot merge a lobby into itself"));
+        assert_eq!(admin::merge(&app, "MERGEA", "MERGEB").await, Ok(1));
+        assert_eq!(state.find_lobby_by_player(1).await.as_deref(), Some("MERGEB"));
+        assert_eq!(state.get_lobby("MERGEB").unwrap().read().await.players[&1].score, 42);
+
+        // The moved client hears where to reconnect
+        let mut buf = [0u8; 2048];
+        let notice = loop {
+            let (len, _) = tokio::time::timeout(Duration::from_secs(1), client.recv_from(&mut buf)).await.unwrap().unwrap();
+            let packet: serde_json::Value = serde_json::from_slice(&buf[..len]).unwrap();
+            if packet["type"] == "lobby_transfer" {
+                break packet;
+            }
+        };
+        assert_eq!(notice["lobby_code"], "MERGEB");
+        assert_eq!(notice["from_lobby_code"], "MERGEA");
+
+        assert_eq!(admin::split(&app, "MERGEB", "MERGEB").await, Err("Lobby already exists"));
+        assert_eq!(admin::split(&app, "MERGEB", "MERGEC").await, Ok(1));
+        let (b, c) = (state.get_lobby("MERGEB").unwrap(), state.get_lobby("MERGEC").unwrap());
+        assert!(b.read().await.players.contains_key(&1)); // Top scorer stays put
+        assert_eq!(b.read().await.players.len() + c.read().await.players.len(), 3);
+        assert!(state.get_lobby("MERGEA").unwrap().read().await.players.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_weapon_fx_served_and_sent_on_welcome() {
+        use axum::extract::State;
+        use crate::handlers::http::{list_weapons, AppState};
+
+        let state = Arc::new(ServerState::new());
+        let udp_socket = Arc::new(UdpSocket::bind("127.0.0.1:0").await.unwrap());
+        let client = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let weapons = Arc::new(WeaponDb::load());
+        let config = Arc::new(Config::default());
+
+        super::create_lobby_with_tick(
+            state.clone(),
+            "FX_TEST".to_string(),
+            4,
+            "world".to_string(),
+            weapons.clone(),
+            config.clone(),
+            udp_socket.clone(),
+        ).await.unwrap();
+
+        let app_state = AppState { state: state.clone(), weapons, config, udp_socket };
+        let listing = list_weapons(State(app_state)).await;
+        assert_eq!(listing.weapons.len(), 4);
+        assert!(listing.weapons.iter().all(|w| w.fx.is_some()));
+        let version = listing.version.clone();
+
+        let command_tx = state.get_lobby_tx("FX_TEST").unwrap();
+        command_tx.send(LobbyCommand::PlayerJoin {
+            player_id: 1,
+            name: "P1".to_string(),
+            addr: client.local_addr().unwrap(),
+        }).await.unwrap();
+
+        let mut buf = [0u8; 4096];
+        let welcome = loop {
+            let (len, _) = tokio::time::timeout(Duration::from_secs(1), client.recv_from(&mut buf)).await.unwrap().unwrap();
+            let packet: serde_json::Value = serde_json::from_slice(&buf[..len]).unwrap();
+            if packet["type"] == "welcome" {
+                break packet;
+            }
+        };
+        assert_eq!(welcome["weapon_fx"]["1"]["tracer_color"], "#ffd700");
+        assert_eq!(welcome["weapon_fx"]["3"]["sound_id"], "sfx_knife_swing");
+        assert_eq!(welcome["weapons_version"], version.as_str());
+    }
+
+    #[tokio::test]
+    async fn test_announce_reaches_every_lobby() {
+        use axum::extract::State;
+        use axum::http::{header, HeaderMap, StatusCode};
+        use axum::Json;
+        use crate::handlers::admin;
+        use crate::handlers::http::{announce, AppState};
+        use crate::handlers::models::AnnounceRequest;
+
+        let state = Arc::new(ServerState::new());
+        let udp_socket = Arc::new(UdpSocket::bind("127.0.0.1:0").await.unwrap());
+        let weapons = Arc::new(WeaponDb::load());
+        let config = Arc::new(Config { admin_token: Some("secret".to_string()), ..Default::default() });
+
+        let mut clients = Vec::new();
+        for (player_id, code) in [(1, "ANNA"), (2, "ANNB")] {
+            super::create_lobby_with_tick(state.clone(), code.to_string(), 4, "world".to_string(), weapons.clone(), config.clone(), udp_socket.clone()).await.unwrap();
+            let client = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+            let command_tx = state.get_lobby_tx(code).unwrap();
+            command_tx.send(LobbyCommand::PlayerJoin { player_id, name: format!("P{}", player_id), addr: client.local_addr().unwrap() }).await.unwrap();
+            clients.push(client);
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let app_state = AppState { state: state.clone(), weapons, config, udp_socket };
+        let request = |message: &str| Json(AnnounceRequest { message: message.to_string(), send_at: None, repeat_every_mins: None });
+        let unauthorized = announce(State(app_state.clone()), HeaderMap::new(), request("Hi")).await;
+        assert_eq!(unauthorized.err(), Some(StatusCode::UNAUTHORIZED));
+
+        let mut headers = HeaderMap::new();
+        headers.insert(header::AUTHORIZATION, "Bearer secret".parse().unwrap());
+        let (status, response) = announce(State(app_state), headers, request("Maintenance in 10 minutes")).await.unwrap();
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(response.lobbies_reached, Some(2));
+
+        async fn announcement(client: &UdpSocket) -> String {
+            let mut buf = [0u8; 4096];
+            loop {
+                let (len, _) = tokio::time::timeout(Duration::from_secs(1), client.recv_from(&mut buf)).await.unwrap().unwrap();
+                let packet: serde_json::Value = serde_json::from_slice(&buf[..len]).unwrap();
+                if packet["type"] == "announcement" {
+                    break packet["message"].as_str().unwrap().to_string();
+                }
+            }
+        }
+        for client in &clients {
+            assert_eq!(announcement(client).await, "Maintenance in 10 minutes");
+        }
+
+        // Repeating announcements come from the background task until cancelled
+        let id = admin::schedule_announcement(state.clone(), "Event live", Duration::from_millis(10), Some(Duration::from_millis(100))).unwrap();
+        assert_eq!(announcement(&clients[0]).await, "Event live");
+        assert_eq!(announcement(&clients[0]).await, "Event live");
+        assert!(state.announcements.cancel(id));
+    }
+
+    #[tokio::test]
+    async fn test_lobby_info_advertises_reachable_address() {
+        use axum::extract::{Query, State};
+        use axum::http::{header, HeaderMap, HeaderValue};
+        use axum::Json;
+        use crate::handlers::http::{create_lobby, get_status, list_lobbies, AppState};
+        use crate::utils::identity::{AdvertisedAddr, ServerIdentity};
+
+        let state = Arc::new(ServerState::new());
+        let udp_socket = Arc::new(UdpSocket::bind("127.0.0.1:0").await.unwrap());
+        let app_state = AppState { state: state.clone(), weapons: Arc::new(WeaponDb::load()), config: Arc::new(Config::default()), udp_socket };
+
+        // Nothing configured: clients are pointed at the host they reached us by
+        let mut headers = HeaderMap::new();
+        headers.insert(header::HOST, HeaderValue::from_static("192.168.1.20:8080"));
+        let request = serde_json::from_value(serde_json::json!({ "code": "ADDRTEST" })).unwrap();
+        let info = create_lobby(State(app_state.clone()), headers.clone(), Json(request)).await.unwrap();
+        assert_eq!(info.server_ip, "192.168.1.20");
+        assert_eq!(info.udp_port, 8081);
+
+        let identity = ServerIdentity {
+            server_id: "srv-1".to_string(),
+            advertised: AdvertisedAddr { ip: Some("203.0.113.7".to_string()), udp_port: 40000 },
+        };
+        state.set_identity(identity).unwrap();
+        let listed = list_lobbies(State(app_state.clone()), headers, Query(Default::default())).await;
+        assert_eq!((listed[0].server_ip.as_str(), listed[0].udp_port), ("203.0.113.7", 40000));
+        assert_eq!(get_status(State(app_state)).await.server_id.as_deref(), Some("srv-1"));
+    }
+
+    #[tokio::test]
+    async fn test_party_join_is_all_or_nothing() {
+        use axum::extract::{Path, State};
+        use axum::http::{HeaderMap, StatusCode};
+        use axum::Json;
+        use crate::handlers::http::{create_lobby, join_party, AppState};
+        use crate::handlers::models::JoinPartyRequest;
+
+        let state = Arc::new(ServerState::new());
+        let udp_socket = Arc::new(UdpSocket::bind("127.0.0.1:0").await.unwrap());
+        let app_state = AppState { state: state.clone(), weapons: Arc::new(WeaponDb::load()), config: Arc::new(Config::default()), udp_socket };
+        let request = serde_json::from_value(serde_json::json!({ "code": "PARTYTEST", "max_players": 3 })).unwrap();
+        let _ = create_lobby(State(app_state.clone()), HeaderMap::new(), Json(request)).await.unwrap();
+
+        let join = |names: &[&str]| join_party(
+            State(app_state.clone()),
+            HeaderMap::new(),
+            Path("PARTYTEST".to_string()),
+            Json(JoinPartyRequest { party_id: "p1".to_string(), player_names: names.iter().map(|n| n.to_string()).collect() }),
+        );
+        assert_eq!(join(&["A", "B", "C", "D"]).await.err(), Some(StatusCode::BAD_REQUEST));
+        assert!(state.get_lobby("PARTYTEST").unwrap().read().await.players.is_empty());
+
+        let joined = join(&["A", "B"]).await.unwrap();
+        assert_eq!(joined.lobby.player_count, 2);
+        assert_eq!(joined.members.iter().map(|m| m.player_name.as_str()).collect::<Vec<_>>(), ["A", "B"]);
+        for member in &joined.members {
+            assert!(state.player_lobby_index.contains_key(&member.player_id));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_party_members_act_for_callers_account() {
+        use axum::extract::{Path, State};
+        use axum::http::{HeaderMap, StatusCode};
+        use axum::Json;
+        use crate::handlers::http::{create_lobby, join_party, save_loadout, AppState};
+        use crate::handlers::models::{JoinPartyRequest, SaveLoadoutRequest};
+
+        let state = Arc::new(ServerState::new());
+        let udp_socket = Arc::new(UdpSocket::bind("127.0.0.1:0").await.unwrap());
+        let config = Arc::new(account_config(&["squad", "other"]));
+        let app_state = AppState { state: state.clone(), weapons: Arc::new(WeaponDb::load()), config, udp_socket };
+        let request = serde_json::from_value(serde_json::json!({ "code": "VIPPARTY", "max_players": 3, "reserved_slots": 1 })).unwrap();
+        let _ = create_lobby(State(app_state.clone()), HeaderMap::new(), Json(request)).await.unwrap();
+
+        let join = |account: &str, party_id: &str, names: &[&str]| join_party(
+            State(app_state.clone()),
+            as_account(account),
+            Path("VIPPARTY".to_string()),
+            Json(JoinPartyRequest { party_id: party_id.to_string(), player_names: names.iter().map(|n| n.to_string()).collect() }),
+        );
+        // Three members only fit once the reserved slot is open to them
+        assert_eq!(join("squad", "p1", &["A", "B", "C"]).await.err(), Some(StatusCode::BAD_REQUEST));
+        state.set_vip("squad", true);
+        let joined = join("squad", "p1", &["A", "B", "C"]).await.unwrap();
+
+        let preset = || SaveLoadoutRequest { primary: 1, secondary: None, attachments: Vec::new() };
+        let save = |headers: HeaderMap, player_id: u32| save_loadout(State(app_state.clone()), headers, Path((player_id, "rush".to_string())), Json(preset()));
+        for member in &joined.members {
+            assert_eq!(state.accounts.account_of(member.player_id).as_deref(), Some("squad"));
+            assert_eq!(save(as_account("other"), member.player_id).await.err(), Some(StatusCode::FORBIDDEN));
+            assert!(save(as_account("squad"), member.player_id).await.is_ok());
+        }
+        assert!(state.is_vip_player(joined.members[0].player_id));
+    }
+
+    #[tokio::test]
+    async fn test_metrics_expose_socket_stats() {
+        use axum::extract::State;
+        use crate::handlers::http::{get_metrics, AppState};
+
+        let state = Arc::new(ServerState::new());
+        let udp_socket = Arc::new(UdpSocket::bind("127.0.0.1:0").await.unwrap());
+        let app_state = AppState { state, weapons: Arc::new(WeaponDb::load()), config: Arc::new(Config::default()), udp_socket };
+
+        let (headers, body) = get_metrics(State(app_state)).await;
+        assert!(headers[0].1.starts_with("text/plain"));
+        assert!(body.contains("# TYPE gungame_udp_send_would_block_total counter"));
+        assert!(body.lines().any(|line| line.starts_with("gungame_lobbies 0")));
+        if cfg!(target_os = "linux") {
+            assert!(body.contains("gungame_udp_socket_drops_total 0"));
+            assert!(body.contains("gungame_udp_recv_buffer_bytes "));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_crash_rejoin_resumes_silently() {
+        let state = Arc::new(ServerState::new());
+        let udp_socket = Arc::new(UdpSocket::bind("127.0.0.1:0").await.unwrap());
+        let weapons = Arc::new(WeaponDb::load());
+        let config = Arc::new(Config::default());
+        super::create_lobby_with_tick(state.clone(), "RESUME".to_string(), 4, "world".to_string(), weapons, config, udp_socket).await.unwrap();
+
+        let observer = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let crashed = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let command_tx = state.get_lobby_tx("RESUME").unwrap();
+        command_tx.send(LobbyCommand::PlayerJoin { player_id: 1, name: "Observer".to_string(), addr: observer.local_addr().unwrap() }).await.unwrap();
+        command_tx.send(LobbyCommand::PlayerJoin { player_id: 2, name: "Crasher".to_string(), addr: crashed.local_addr().unwrap() }).await.unwrap();
+        let connect = |addr| LobbyCommand::UdpConnect { player_id: 2, name: "Crasher".to_string(), addr, client_id: Some("install-42".to_string()) };
+        command_tx.send(connect(crashed.local_addr().unwrap())).await.unwrap();
+
+        // Packet types a client receives until it goes quiet
+        async fn received(client: &UdpSocket) -> Vec<serde_json::Value> {
+            let mut buf = [0u8; 4096];
+            let mut packets = Vec::new();
+            while let Ok(Ok((len, _))) = tokio::time::timeout(Duration::from_millis(200), client.recv_from(&mut buf)).await {
+                packets.push(serde_json::from_slice(&buf[..len]).unwrap());
+            }
+            packets
+        }
+        let joined_twice = |packets: &[serde_json::Value]| packets.iter().any(|p| p["type"] == "player_joined" && p["player"]["id"] == 2);
+        assert!(joined_twice(&received(&observer).await)); // The first connect is announced
+
+        // Restarted client: same IP and client id, new port
+        let restarted = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        command_tx.send(connect(restarted.local_addr().unwrap())).await.unwrap();
+        let resync = received(&restarted).await;
+        assert!(resync.iter().any(|p| p["type"] == "welcome"));
+        assert!(resync.iter().any(|p| p["type"] == "player_state_update" && p["resumed"] == true));
+        assert_eq!(state.get_lobby("RESUME").unwrap().read().await.client_addresses[&2], restarted.local_addr().unwrap());
+        assert!(!joined_twice(&received(&observer).await));
+    }
+
+    #[tokio::test]
+    async fn test_tournament_advances_winners_through_lobbies() {
+        use axum::extract::{Path, State};
+        use axum::http::{header, HeaderMap, StatusCode};
+        use axum::Json;
+        use crate::handlers::http::{create_tournament, get_tournament, join_lobby, AppState};
+        use crate::handlers::models::{CreateTournamentRequest, JoinLobbyRequest};
+        use crate::state::lobby::MatchPhase;
+
+        let state = Arc::new(ServerState::new());
+        let udp_socket = Arc::new(UdpSocket::bind("127.0.0.1:0").await.unwrap());
+        let weapons = Arc::new(WeaponDb::load());
+        let config = Arc::new(Config { admin_token: Some("secret".to_string()), ..Default::default() });
+        let app_state = AppState { state: state.clone(), weapons, config, udp_socket };
+        let mut headers = HeaderMap::new();
+        headers.insert(header::AUTHORIZATION, "Bearer secret".parse().unwrap());
+        let request = |participants: &[&str]| Json(CreateTournamentRequest {
+            name: "Cup".to_string(),
+            participants: participants.iter().map(|name| name.to_string()).collect(),
+            scene: None,
+            duel_rounds: None,
+        });
+        assert_eq!(create_tournament(State(app_state.clone()), headers.clone(), request(&["Solo"])).await.err(), Some(StatusCode::BAD_REQUEST));
+
+        let (status, Json(info)) = create_tournament(State(app_state.clone()), headers, request(&["Ann", "Bo", "Cy"])).await.unwrap();
+        assert_eq!(status, StatusCode::CREATED);
+        assert!(info.rounds[0][0].bye); // Top seed skips the first round
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(!state.lobby_exists("T1R1M1"));
+
+        // Entrants join bracket lobbies by code like any other
+        let joined = join_lobby(
+            State(app_state.clone()),
+            HeaderMap::new(),
+            Path("t1r1m2".to_string()),
+            Json(JoinLobbyRequest { player_name: "Bo".to_string(), team: None }),
+        ).await.unwrap();
+        assert_eq!(joined.lobby.code, "T1R1M2");
+
+        // Play out a match: both entrants join, the match goes live and `winner` outscores the other
+        async fn play(state: &ServerState, code: &str, players: &[(u32, &str)], winner: u32) {
+            let command_tx = state.get_lobby_tx(code).unwrap();
+            for &(player_id, name) in players {
+                let addr = format!("127.0.0.1:{}", 9000 + player_id).parse().unwrap();
+                command_tx.send(LobbyCommand::PlayerJoin { player_id, name: name.to_string(), addr }).await.unwrap();
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            {
+                let lobby = state.get_lobby(code).unwrap();
+                let mut lobby = lobby.write().await;
+                lobby.phase = MatchPhase::InProgress;
+                lobby.players.get_mut(&winner).unwrap().score = 100;
+            }
+            command_tx.send(LobbyCommand::EndMatch).await.unwrap();
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        play(&state, "T1R1M2", &[(3, "Cy")], 3).await;
+
+        let Json(info) = get_tournament(State(app_state.clone()), Path(1)).await.unwrap();
+        assert_eq!(info.rounds[0][1].winner.as_deref(), Some("Cy"));
+        assert_eq!(info.rounds[1][0].players, [Some("Ann".to_string()), Some("Cy".to_string())]);
+        assert_eq!(info.rounds[1][0].lobby_code.as_deref(), Some("T1R2M1"));
+        assert_eq!(info.champion, None);
+
+        play(&state, "T1R2M1", &[(1, "Ann"), (4, "Cy")], 1).await;
+        let Json(info) = get_tournament(State(app_state.clone()), Path(1)).await.unwrap();
+        assert_eq!(info.champion.as_deref(), Some("Ann"));
+        assert_eq!(get_tournament(State(app_state), Path(2)).await.err(), Some(StatusCode::NOT_FOUND));
+    }
+
+    #[tokio::test]
+    async fn test_rules_script_upload_waits_for_match_end() {
+        use axum::extract::{Path, State};
+        use axum::http::{header, HeaderMap, StatusCode};
+        use axum::Json;
+        use crate::handlers::http::{clear_rules_script, set_rules_script, AppState};
+        use crate::handlers::models::SetRulesScriptRequest;
+        use crate::state::lobby::MatchPhase;
+
+        let state = Arc::new(ServerState::new());
+        let udp_socket = Arc::new(UdpSocket::bind("127.0.0.1:0").await.unwrap());
+        let weapons = Arc::new(WeaponDb::load());
+        let config = Arc::new(Config { admin_token: Some("secret".to_string()), ..Default::default() });
+        super::create_lobby_with_tick(state.clone(), "RULES".to_string(), 4, "world".to_string(), weapons.clone(), config.clone(), udp_socket.clone()).await.unwrap();
+        let app_state = AppState { state: state.clone(), weapons, config, udp_socket };
+        let mut headers = HeaderMap::new();
+        headers.insert(header::AUTHORIZATION, "Bearer secret".parse().unwrap());
+        let upload = |source: &str| Json(SetRulesScriptRequest { name: Some("double".to_string()), source: source.to_string() });
+        let source = "fn modify_damage(a, t, w, d) { d * 2 }";
+
+        let status = set_rules_script(State(app_state.clone()), Path("RULES".to_string()), HeaderMap::new(), upload(source)).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let status = set_rules_script(State(app_state.clone()), Path("RULES".to_string()), headers.clone(), upload("fn on_kill(")).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let status = set_rules_script(State(app_state.clone()), Path("NOPE".to_string()), headers.clone(), upload(source)).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let lobby = state.get_lobby("RULES").unwrap();
+        lobby.write().await.phase = MatchPhase::InProgress;
+        let status = set_rules_script(State(app_state.clone()), Path("RULES".to_string()), headers.clone(), upload(source)).await;
+        assert_eq!(status, StatusCode::ACCEPTED);
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert!(lobby.read().await.rules_script.is_none()); // Staged until the match ends
+
+        state.get_lobby_tx("RULES").unwrap().send(LobbyCommand::EndMatch).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert_eq!(lobby.read().await.rules_script.as_ref().map(|s| s.name.as_str()), Some("double"));
+
+        assert_eq!(clear_rules_script(State(app_state), Path("RULES".to_string()), headers).await, StatusCode::ACCEPTED);
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert!(lobby.read().await.rules_script.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_udp_join_requires_handshake_cookie() {
+        use crate::handlers::udp::handle_udp_packet;
+
+        let state = Arc::new(ServerState::new());
+        let udp_socket = Arc::new(UdpSocket::bind("127.0.0.1:0").await.unwrap());
+        let client = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let client_addr = client.local_addr().unwrap();
+        let weapons = Arc::new(WeaponDb::load());
+        super::create_lobby_with_tick(state.clone(), "COOKIE".to_string(), 4, "world".to_string(), weapons.clone(), Arc::new(Config::default()), udp_socket.clone()).await.unwrap();
+        let lobby_arc = state.get_lobby("COOKIE").unwrap();
+
+        let recv = || async {
+            let mut buf = [0u8; 1024];
+            let (len, _) = tokio::time::timeout(Duration::from_secs(1), client.recv_from(&mut buf)).await.unwrap().unwrap();
+            (len, serde_json::from_slice::<serde_json::Value>(&buf[..len]).unwrap())
+        };
+        let mut join = serde_json::json!({ "type": "join", "lobby_code": "COOKIE", "player_id": 1, "player_name": "Ann" });
+        let request_len = serde_json::to_vec(&join).unwrap().len();
+
+        // Without a cookie (or with one issued to another address) only a small challenge comes back
+        handle_udp_packet(join.clone(), client_addr, &udp_socket, &state, &weapons).await;
+        let (len, challenge) = recv().await;
+        assert_eq!(challenge["type"], "challenge");
+        assert!(len <= request_len);
+        join["cookie"] = state.handshake.issue("127.0.0.1:1".parse().unwrap(), std::time::SystemTime::now()).into();
+        handle_udp_packet(join.clone(), client_addr, &udp_socket, &state, &weapons).await;
+        assert_eq!(recv().await.1["type"], "challenge");
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(lobby_arc.read().await.players.is_empty());
+
+        join["cookie"] = challenge["cookie"].clone();
+        handle_udp_packet(join, client_addr, &udp_socket, &state, &weapons).await;
+        assert_eq!(recv().await.1["type"], "welcome");
+    }
+
+    #[tokio::test]
+    async fn test_quick_join_prefers_region_then_opens_lobby() {
+        use axum::extract::State;
+        use axum::http::{HeaderMap, StatusCode};
+        use axum::Json;
+        use crate::handlers::http::{create_lobby, quick_join, AppState};
+        use crate::handlers::models::QuickJoinRequest;
+
+        let state = Arc::new(ServerState::new());
+        let udp_socket = Arc::new(UdpSocket::bind("127.0.0.1:0").await.unwrap());
+        let weapons = Arc::new(WeaponDb::load());
+        let app_state = AppState { state: state.clone(), weapons, config: Arc::new(Config::default()), udp_socket };
+        for (code, region, private) in [("QJUS", "us", false), ("QJEU", "eu", false), ("QJHIDDEN", "eu", true)] {
+            let request = serde_json::from_value(serde_json::json!({ "code": code, "region": region, "private": private })).unwrap();
+            let _ = create_lobby(State(app_state.clone()), HeaderMap::new(), Json(request)).await.unwrap();
+        }
+        let request = |name: &str, scene: Option<&str>| Json(QuickJoinRequest {
+            player_name: name.to_string(),
+            region: Some("eu".to_string()),
+            scene: scene.map(str::to_string),
+        });
+
+        let Json(joined) = quick_join(State(app_state.clone()), HeaderMap::new(), None, request("Ann", None)).await.unwrap();
+        assert_eq!(joined.lobby.code, "QJEU");
+        assert!(!joined.created);
+        assert_eq!(state.find_lobby_by_player(joined.player_id).await.as_deref(), Some("QJEU"));
+
+        // No public lobby on that scene, so one is opened
+        let Json(opened) = quick_join(State(app_state.clone()), HeaderMap::new(), None, request("Bo", Some("arena"))).await.unwrap();
+        assert!(opened.created);
+        assert_eq!(opened.lobby.scene, "arena");
+        assert!(state.lobby_exists(&opened.lobby.code));
+
+        let invalid = quick_join(State(app_state), HeaderMap::new(), None, request("", None)).await;
+        assert_eq!(invalid.err(), Some(StatusCode::BAD_REQUEST));
+    }
+
+    #[tokio::test]
+    async fn test_lobby_codes_validated_and_case_insensitive() {
+        use axum::extract::{Path, State};
+        use axum::http::{HeaderMap, StatusCode};
+        use axum::Json;
+        use crate::handlers::http::{create_lobby, get_lobby, get_lobby_leaderboard, join_lobby, pause_lobby, AppState};
+        use crate::handlers::models::JoinLobbyRequest;
+
+        let app_state = AppState {
+            state: Arc::new(ServerState::new()),
+            weapons: Arc::new(WeaponDb::load()),
+            config: Arc::new(Config::default()),
+            udp_socket: Arc::new(UdpSocket::bind("127.0.0.1:0").await.unwrap()),
+        };
+        let create = |code: &str| {
+            let request = serde_json::from_value(serde_json::json!({ "code": code })).unwrap();
+            create_lobby(State(app_state.clone()), HeaderMap::new(), Json(request))
+        };
+        assert_eq!(create("Casing1").await.unwrap().code, "CASING1");
+        assert_eq!(create("CASING1").await.err(), Some(StatusCode::CONFLICT));
+        for invalid in ["abc", "has space", "dash-code", "SEVENTEENCHARSXYZ"] {
+            assert_eq!(create(invalid).await.err(), Some(StatusCode::BAD_REQUEST));
+        }
+
+        let join = |code: &str| join_lobby(
+            State(app_state.clone()),
+            HeaderMap::new(),
+            Path(code.to_string()),
+            Json(JoinLobbyRequest { player_name: "Ann".to_string(), team: None }),
+        );
+        assert_eq!(join("casing1").await.unwrap().lobby.code, "CASING1");
+        assert_eq!(join("cas!ng1").await.err(), Some(StatusCode::BAD_REQUEST));
+
+        // Every other `:code` route finds the lobby under whatever casing it was created with
+        let lobby = get_lobby(State(app_state.clone()), HeaderMap::new(), Path("Casing1".to_string())).await.unwrap();
+        assert_eq!(lobby.code, "CASING1");
+        assert!(get_lobby_leaderboard(State(app_state.clone()), Path("casing1".to_string())).await.is_ok());
+        assert_eq!(pause_lobby(State(app_state.clone()), Path("casing1".to_string())).await, StatusCode::ACCEPTED);
+        assert_eq!(pause_lobby(State(app_state.clone()), Path("cas!ng1".to_string())).await, StatusCode::BAD_REQUEST);
+        assert_eq!(crate::handlers::admin::kick(&app_state.state, "casing1", 12345, "").await, Err("Player not in lobby"));
+
+        // UDP joins are canonicalized too, and bad codes get an error back
+        let socket = app_state.udp_socket.clone();
+        let client = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let addr = client.local_addr().unwrap();
+        let cookie = app_state.state.handshake.issue(addr, std::time::SystemTime::now());
+        let packet = serde_json::json!({ "type": "join", "lobby_code": "no", "player_id": 1, "cookie": cookie });
+        super::handle_udp_packet(packet, addr, &socket, &app_state.state, &app_state.weapons).await;
+        let mut buf = [0u8; 2048];
+        let (len, _) = tokio::time::timeout(Duration::from_secs(1), client.recv_from(&mut buf)).await.unwrap().unwrap();
+        let reply: serde_json::Value = serde_json::from_slice(&buf[..len]).unwrap();
+        assert_eq!(reply["message"], crate::handlers::admin::INVALID_LOBBY_CODE);
+    }
+
+    #[tokio::test]
+    async fn test_startup_lobby_joinable_over_http_and_udp() {
+        use axum::extract::{Path, State};
+        use axum::http::HeaderMap;
+        use axum::Json;
+        use crate::handlers::http::{join_lobby, AppState};
+        use crate::handlers::models::JoinLobbyRequest;
+
+        let app_state = AppState {
+            state: Arc::new(ServerState::new()),
+            weapons: Arc::new(WeaponDb::load()),
+            config: Arc::new(Config::default()),
+            udp_socket: Arc::new(UdpSocket::bind("127.0.0.1:0").await.unwrap()),
+        };
+        // Created the way main does; clients may still send the old lowercase code
+        super::create_lobby_with_tick(
+            app_state.state.clone(), super::DEFAULT_LOBBY_CODE.to_string(), 8, "test_world".to_string(),
+            app_state.weapons.clone(), app_state.config.clone(), app_state.udp_socket.clone(),
+        ).await.unwrap();
+        assert!(app_state.state.lobby_exists("TEST"));
+
+        let joined = join_lobby(
+            State(app_state.clone()),
+            HeaderMap::new(),
+            Path("test".to_string()),
+            Json(JoinLobbyRequest { player_name: "Ann".to_string(), team: None }),
+        ).await.unwrap();
+        assert_eq!(joined.lobby.code, "TEST");
+
+        let client = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let addr = client.local_addr().unwrap();
+        let cookie = app_state.state.handshake.issue(addr, std::time::SystemTime::now());
+        let packet = serde_json::json!({ "type": "join", "lobby_code": "test", "player_id": joined.player_id, "cookie": cookie });
+        super::handle_udp_packet(packet, addr, &app_state.udp_socket, &app_state.state, &app_state.weapons).await;
+        let mut buf = [0u8; 2048];
+        let (len, _) = tokio::time::timeout(Duration::from_secs(1), client.recv_from(&mut buf)).await.unwrap().unwrap();
+        let reply: serde_json::Value = serde_json::from_slice(&buf[..len]).unwrap();
+        assert_eq!(reply["type"], "welcome");
+    }
+
+    #[tokio::test]
+    async fn test_weapon_falloff_debug_endpoint() {
+        use axum::body::to_bytes;
+        use axum::extract::{Path, Query, State};
+        use axum::http::{header, HeaderMap, StatusCode};
+        use crate::handlers::http::{get_weapon_falloff, AppState};
+        use crate::handlers::models::FalloffQuery;
+
+        let app_state = AppState {
+            state: Arc::new(ServerState::new()),
+            weapons: Arc::new(WeaponDb::load()),
+            config: Arc::new(Config { admin_token: Some("secret".to_string()), ..Default::default() }),
+            udp_socket: Arc::new(UdpSocket::bind("127.0.0.1:0").await.unwrap()),
+        };
+        let mut headers = HeaderMap::new();
+        let falloff = |headers: HeaderMap, weapon_id: u32, query: FalloffQuery| {
+            get_weapon_falloff(State(app_state.clone()), headers, Path(weapon_id), Query(query))
+        };
+        assert_eq!(falloff(headers.clone(), 4, FalloffQuery::default()).await.err(), Some(StatusCode::UNAUTHORIZED));
+        headers.insert(header::AUTHORIZATION, "Bearer secret".parse().unwrap());
+        assert_eq!(falloff(headers.clone(), 99, FalloffQuery::default()).await.err(), Some(StatusCode::NOT_FOUND));
+
+        let at = FalloffQuery { distance: Some(10.0), ..Default::default() };
+        let body = to_bytes(falloff(headers.clone(), 4, at).await.unwrap().into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["name"], "Scattergun");
+        assert_eq!(json["points"].as_array().unwrap().len(), 1);
+        assert!(json["points"][0]["expected_damage"].as_f64().unwrap() < json["points"][0]["max_damage"].as_f64().unwrap());
+
+        let csv = FalloffQuery { format: Some("csv".to_string()), step: Some(10.0), ..Default::default() };
+        let response = falloff(headers.clone(), 4, csv).await.unwrap();
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "text/csv");
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(std::str::from_utf8(&body).unwrap().lines().count(), 5); // Header, 0, 10, 20, 30
+
+        let bad = FalloffQuery { format: Some("xml".to_string()), ..Default::default() };
+        assert_eq!(falloff(headers, 4, bad).await.err(), Some(StatusCode::BAD_REQUEST));
+    }
+
+    #[tokio::test]
+    async fn test_observer_gets_snapshots_after_handshake() {
+        use axum::extract::{Path, State};
+        use axum::http::HeaderMap;
+        use axum::Json;
+        use crate::handlers::http::{create_lobby, join_lobby, AppState};
+        use crate::handlers::models::JoinLobbyRequest;
+
+        let app_state = AppState {
+            state: Arc::new(ServerState::new()),
+            weapons: Arc::new(WeaponDb::load()),
+            config: Arc::new(Config::default()),
+            udp_socket: Arc::new(UdpSocket::bind("127.0.0.1:0").await.unwrap()),
+        };
+        let request = serde_json::from_value(serde_json::json!({ "code": "WATCHME" })).unwrap();
+        let _ = create_lobby(State(app_state.clone()), HeaderMap::new(), Json(request)).await.unwrap();
+        let joined = join_lobby(
+            State(app_state.clone()),
+            HeaderMap::new(),
+            Path("WATCHME".to_string()),
+            Json(JoinLobbyRequest { player_name: "Ann".to_string(), team: None }),
+        ).await.unwrap();
+
+        let socket = app_state.udp_socket.clone();
+        let observer = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let addr = observer.local_addr().unwrap();
+        async fn recv(observer: &UdpSocket) -> serde_json::Value {
+            let mut buf = [0u8; 4096];
+            let (len, _) = tokio::time::timeout(Duration::from_secs(1), observer.recv_from(&mut buf)).await.unwrap().unwrap();
+            serde_json::from_slice(&buf[..len]).unwrap()
+        }
+
+        let observe = serde_json::json!({ "type": "observe", "lobby_code": "watchme" });
+        super::handle_udp_packet(observe.clone(), addr, &socket, &app_state.state, &app_state.weapons).await;
+        let challenge = recv(&observer).await;
+        assert_eq!(challenge["type"], "challenge");
+
+        let mut observe = observe;
+        observe["cookie"] = challenge["cookie"].clone();
+        super::handle_udp_packet(observe, addr, &socket, &app_state.state, &app_state.weapons).await;
+        let reply = recv(&observer).await;
+        assert_eq!(reply["type"], "observing");
+        assert_eq!(reply["snapshot_hz"], crate::domain::observers::OBSERVER_SNAPSHOT_HZ);
+
+        // Only summaries follow, never the per-event stream
+        for _ in 0..2 {
+            let snapshot = recv(&observer).await;
+            assert_eq!(snapshot["type"], "observer_snapshot");
+            let players = snapshot["players"].as_array().unwrap();
+            assert!(players.iter().any(|p| p["id"] == joined.player_id && p["name"] == "Ann"));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_time_caps_end_match_then_close_lobby() {
+        use std::time::SystemTime;
+
+        let state = Arc::new(ServerState::new());
+        let udp_socket = Arc::new(UdpSocket::bind("127.0.0.1:0").await.unwrap());
+        let config = Arc::new(Config { countdown_secs: 0, ..Default::default() });
+
+        super::create_lobby_with_tick(
+            state.clone(),
+            "CAP_TEST".to_string(),
+            4,
+            "world".to_string(),
+            Arc::new(WeaponDb::load()),
+            config.clone(),
+            udp_socket,
+        ).await.unwrap();
+
+        let command_tx = state.get_lobby_tx("CAP_TEST").unwrap();
+        let lobby_arc = state.get_lobby("CAP_TEST").unwrap();
+        command_tx.send(LobbyCommand::PlayerJoin {
+            player_id: 1,
+            name: "Player1".to_string(),
+            addr: "127.0.0.1:7301".parse().unwrap(),
+        }).await.unwrap();
+        command_tx.send(LobbyCommand::Ready { player_id: 1, ready: true }).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(lobby_arc.read().await.is_match_live());
+
+        // A match running past the cap is ended; the lobby carries on
+        let max_match = Duration::from_secs(config.max_match_secs.unwrap());
+        lobby_arc.write().await.lifetime.match_started_at = Some(SystemTime::now() - max_match);
+        // Calm lobbies only check on full ticks
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        {
+            let lobby = lobby_arc.read().await;
+            assert_eq!(lobby.phase, crate::state::lobby::MatchPhase::Waiting);
+            assert!(lobby.players.contains_key(&1));
+        }
+        assert!(state.lobby_exists("CAP_TEST"));
+
+        // Past the lobby's own lifetime it is closed and unregistered
+        let max_lobby = Duration::from_secs(config.max_lobby_lifetime_secs.unwrap());
+        lobby_arc.write().await.lifetime.created_at = SystemTime::now() - max_lobby;
+        // Calm lobbies only check on full ticks
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert!(!state.lobby_exists("CAP_TEST"));
+        assert!(lobby_arc.read().await.players.is_empty());
+        assert!(command_tx.is_closed());
+    }
 }
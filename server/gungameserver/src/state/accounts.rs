@@ -0,0 +1,49 @@
+use dashmap::DashMap;
+
+/// The account each player id joined with, from the caller's API key
+/// Players joined without an account key have none; bindings outlive the session,
+/// so routes for a past player id still check the right account
+#[derive(Debug, Default)]
+pub struct PlayerAccounts {
+    by_player: DashMap<u32, String>,
+    /// Latest player id each account joined as
+    latest: DashMap<String, u32>,
+}
+
+impl PlayerAccounts {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn bind(&self, player_id: u32, account: &str) {
+        self.by_player.insert(player_id, account.to_string());
+        self.latest.insert(account.to_string(), player_id);
+    }
+
+    /// The player id of the account's latest session (whether or not it's still online)
+    pub fn player_of(&self, account: &str) -> Option<u32> {
+        self.latest.get(account).map(|player_id| *player_id)
+    }
+
+    pub fn account_of(&self, player_id: u32) -> Option<String> {
+        self.by_player.get(&player_id).map(|account| account.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bind_player_to_account() {
+        let accounts = PlayerAccounts::new();
+        accounts.bind(7, "alice");
+        assert_eq!(accounts.account_of(7).as_deref(), Some("alice"));
+        assert_eq!(accounts.account_of(8), None);
+
+        accounts.bind(9, "alice");
+        assert_eq!(accounts.player_of("alice"), Some(9));
+        assert_eq!(accounts.account_of(7).as_deref(), Some("alice"));
+        assert_eq!(accounts.player_of("bob"), None);
+    }
+}
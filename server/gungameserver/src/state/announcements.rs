@@ -0,0 +1,58 @@
+use dashmap::DashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::task::AbortHandle;
+
+/// Most server-wide announcements that may be waiting or repeating at once
+pub const MAX_SCHEDULED_ANNOUNCEMENTS: usize = 32;
+
+/// Background tasks sending scheduled server-wide announcements, by schedule id
+#[derive(Debug)]
+pub struct AnnouncementSchedule {
+    next_id: AtomicU64,
+    tasks: DashMap<u64, AbortHandle>,
+}
+
+impl Default for AnnouncementSchedule {
+    fn default() -> Self {
+        Self { next_id: AtomicU64::new(1), tasks: DashMap::new() }
+    }
+}
+
+impl AnnouncementSchedule {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reserve an id for a task about to be spawned
+    pub fn next_id(&self) -> u64 {
+        self.next_id.fetch_add(1, Ordering::Relaxed)
+    }
+
+    pub fn insert(&self, id: u64, task: AbortHandle) {
+        self.tasks.insert(id, task);
+    }
+
+    /// Forget a task that finished on its own
+    pub fn finish(&self, id: u64) {
+        self.tasks.remove(&id);
+    }
+
+    /// Stop a scheduled announcement; false when the id is unknown or already sent
+    pub fn cancel(&self, id: u64) -> bool {
+        match self.tasks.remove(&id) {
+            Some((_, task)) => {
+                task.abort();
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.tasks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+}
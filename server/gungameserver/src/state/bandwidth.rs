@@ -0,0 +1,189 @@
+use dashmap::DashMap;
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+use crate::state::lobby::LobbyCode;
+
+/// Length of one accounting window
+const WINDOW: Duration = Duration::from_secs(1);
+
+/// Traffic counters for one client address
+#[derive(Debug, Clone)]
+struct ClientBandwidth {
+    player_id: u32,
+    lobby_code: LobbyCode,
+    window_start: Instant,
+    sent: u64,     // Bytes sent in the current window
+    received: u64, // Bytes received in the current window
+    sent_last: u64,
+    received_last: u64,
+    total_sent: u64,
+    total_received: u64,
+    shed: u64, // Non-critical packets dropped by the outbound cap
+}
+
+impl ClientBandwidth {
+    fn new(player_id: u32, lobby_code: LobbyCode, now: Instant) -> Self {
+        Self {
+            player_id,
+            lobby_code,
+            window_start: now,
+            sent: 0,
+            received: 0,
+            sent_last: 0,
+            received_last: 0,
+            total_sent: 0,
+            total_received: 0,
+            shed: 0,
+        }
+    }
+
+    /// Close the current window once it has elapsed
+    fn roll(&mut self, now: Instant) {
+        let elapsed = now.duration_since(self.window_start);
+        if elapsed < WINDOW {
+            return;
+        }
+        // A silent gap of more than one window means the last second was idle
+        let idle = elapsed >= WINDOW * 2;
+        self.sent_last = if idle { 0 } else { self.sent };
+        self.received_last = if idle { 0 } else { self.received };
+        self.sent = 0;
+        self.received = 0;
+        self.window_start = now;
+    }
+}
+
+/// Per-player bandwidth report (last full second)
+#[derive(Debug, Clone, PartialEq, serde::Serialize, utoipa::ToSchema)]
+pub struct PlayerBandwidth {
+    pub player_id: u32,
+    pub lobby_code: String,
+    pub bytes_sent_per_sec: u64,
+    pub bytes_received_per_sec: u64,
+    pub total_bytes_sent: u64,
+    pub total_bytes_received: u64,
+    pub packets_shed: u64,
+}
+
+/// Bytes sent and received per client, shared by every lobby on the socket
+/// Only addresses bound to a player are tracked, so stray traffic can't grow the table
+#[derive(Debug, Default)]
+pub struct BandwidthTracker {
+    clients: DashMap<SocketAddr, ClientBandwidth>,
+}
+
+impl BandwidthTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start tracking an address for a player (call when the player connects)
+    pub fn bind(&self, addr: SocketAddr, player_id: u32, lobby_code: &str) {
+        self.clients.insert(addr, ClientBandwidth::new(player_id, lobby_code.to_string(), Instant::now()));
+    }
+
+    /// Stop tracking an address (call when the player leaves)
+    pub fn forget(&self, addr: SocketAddr) {
+        self.clients.remove(&addr);
+    }
+
+    pub fn record_sent(&self, addr: SocketAddr, bytes: usize) {
+        if let Some(mut client) = self.clients.get_mut(&addr) {
+            client.roll(Instant::now());
+            client.sent += bytes as u64;
+            client.total_sent += bytes as u64;
+        }
+    }
+
+    pub fn record_received(&self, addr: SocketAddr, bytes: usize) {
+        if let Some(mut client) = self.clients.get_mut(&addr) {
+            client.roll(Instant::now());
+            client.received += bytes as u64;
+            client.total_received += bytes as u64;
+        }
+    }
+
+    /// Whether a non-critical packet still fits in this second's outbound budget
+    /// Packets that don't fit are counted as shed
+    pub fn within_cap(&self, addr: SocketAddr, bytes: usize, cap_bytes_per_sec: u64) -> bool {
+        let Some(mut client) = self.clients.get_mut(&addr) else {
+            return true;
+        };
+        client.roll(Instant::now());
+        if client.sent + bytes as u64 > cap_bytes_per_sec {
+            client.shed += 1;
+            return false;
+        }
+        true
+    }
+
+    /// Report for every tracked player, busiest first
+    pub fn snapshot(&self) -> Vec<PlayerBandwidth> {
+        let now = Instant::now();
+        let mut report: Vec<PlayerBandwidth> = self.clients.iter_mut()
+            .map(|mut client| {
+                client.roll(now);
+                PlayerBandwidth {
+                    player_id: client.player_id,
+                    lobby_code: client.lobby_code.clone(),
+                    bytes_sent_per_sec: client.sent_last,
+                    bytes_received_per_sec: client.received_last,
+                    total_bytes_sent: client.total_sent,
+                    total_bytes_received: client.total_received,
+                    packets_shed: client.shed,
+                }
+            })
+            .collect();
+        report.sort_by_key(|p| (std::cmp::Reverse(p.bytes_sent_per_sec), p.player_id));
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addr() -> SocketAddr {
+        "127.0.0.1:9000".parse().unwrap()
+    }
+
+    #[test]
+    fn test_only_bound_addresses_tracked() {
+        let tracker = BandwidthTracker::new();
+        tracker.record_sent(addr(), 100);
+        assert!(tracker.snapshot().is_empty());
+
+        tracker.bind(addr(), 1, "TEST");
+        tracker.record_sent(addr(), 100);
+        tracker.record_received(addr(), 40);
+        let report = tracker.snapshot();
+        assert_eq!(report[0].total_bytes_sent, 100);
+        assert_eq!(report[0].total_bytes_received, 40);
+
+        tracker.forget(addr());
+        assert!(tracker.snapshot().is_empty());
+    }
+
+    #[test]
+    fn test_window_rolls_into_rate() {
+        let now = Instant::now();
+        let mut client = ClientBandwidth::new(1, "TEST".to_string(), now);
+        client.sent = 500;
+        client.roll(now + Duration::from_millis(1200));
+        assert_eq!((client.sent_last, client.sent), (500, 0));
+
+        client.sent = 200;
+        client.roll(now + Duration::from_secs(5));
+        assert_eq!(client.sent_last, 0); // Idle gap
+    }
+
+    #[test]
+    fn test_cap_sheds_non_critical_packets() {
+        let tracker = BandwidthTracker::new();
+        tracker.bind(addr(), 1, "TEST");
+        assert!(tracker.within_cap(addr(), 60, 100));
+        tracker.record_sent(addr(), 60);
+        assert!(!tracker.within_cap(addr(), 60, 100));
+        assert_eq!(tracker.snapshot()[0].packets_shed, 1);
+    }
+}
@@ -0,0 +1,134 @@
+use dashmap::DashMap;
+use std::collections::BTreeSet;
+use std::time::{Duration, Instant};
+
+/// Channel every player on the server may join
+pub const GLOBAL_CHANNEL: &str = "global";
+
+/// Clients ask for "region"; it resolves to the lobby's region channel
+pub const REGION_CHANNEL: &str = "region";
+
+/// Most channels one player may be subscribed to
+pub const MAX_SUBSCRIPTIONS: usize = 4;
+
+/// Minimum time between channel messages from one player (on top of the lobby chat limit)
+const CHANNEL_COOLDOWN: Duration = Duration::from_secs(2);
+
+/// Opt-in chat channels spanning lobbies: subscribers by channel name
+#[derive(Debug, Default)]
+pub struct ChatChannels {
+    subscribers: DashMap<String, BTreeSet<u32>>,
+    last_message: DashMap<u32, Instant>,
+}
+
+/// Full channel name for a client-requested channel, e.g. "region" -> "region:eu-west"
+pub fn resolve(requested: &str, lobby_region: &str) -> Result<String, &'static str> {
+    match requested {
+        GLOBAL_CHANNEL => Ok(GLOBAL_CHANNEL.to_string()),
+        REGION_CHANNEL => Ok(format!("{}:{}", REGION_CHANNEL, lobby_region)),
+        _ => Err("Unknown channel"),
+    }
+}
+
+impl ChatChannels {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn subscribe(&self, channel: &str, player_id: u32) -> Result<(), &'static str> {
+        if self.is_subscribed(channel, player_id) {
+            return Ok(());
+        }
+        if self.channels_of(player_id).len() >= MAX_SUBSCRIPTIONS {
+            return Err("Too many channels");
+        }
+        self.subscribers.entry(channel.to_string()).or_default().insert(player_id);
+        Ok(())
+    }
+
+    pub fn unsubscribe(&self, channel: &str, player_id: u32) {
+        if let Some(mut members) = self.subscribers.get_mut(channel) {
+            members.remove(&player_id);
+        }
+        self.subscribers.remove_if(channel, |_, members| members.is_empty());
+    }
+
+    /// Drop every subscription of a player who left the server
+    pub fn forget(&self, player_id: u32) {
+        for channel in self.channels_of(player_id) {
+            self.unsubscribe(&channel, player_id);
+        }
+        self.last_message.remove(&player_id);
+    }
+
+    pub fn is_subscribed(&self, channel: &str, player_id: u32) -> bool {
+        self.subscribers.get(channel).is_some_and(|members| members.contains(&player_id))
+    }
+
+    pub fn channels_of(&self, player_id: u32) -> Vec<String> {
+        let mut channels: Vec<String> = self.subscribers.iter()
+            .filter(|entry| entry.value().contains(&player_id))
+            .map(|entry| entry.key().clone())
+            .collect();
+        channels.sort();
+        channels
+    }
+
+    /// Subscribers in ascending id order
+    pub fn subscribers(&self, channel: &str) -> Vec<u32> {
+        self.subscribers.get(channel)
+            .map(|members| members.iter().copied().collect())
+            .unwrap_or_default()
+    }
+
+    /// Check a subscriber may post now and start their cooldown
+    pub fn consume_rate_limit(&self, channel: &str, player_id: u32, now: Instant) -> Result<(), &'static str> {
+        if !self.is_subscribed(channel, player_id) {
+            return Err("Not subscribed");
+        }
+        let recent = self.last_message.get(&player_id)
+            .is_some_and(|last| now.duration_since(*last) < CHANNEL_COOLDOWN);
+        if recent {
+            return Err("Rate limited");
+        }
+        self.last_message.insert(player_id, now);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_subscriptions_and_rate_limit() {
+        let channels = ChatChannels::new();
+        assert_eq!(resolve("region", "eu-west"), Ok("region:eu-west".to_string()));
+        assert_eq!(resolve("lobby", "eu-west"), Err("Unknown channel"));
+
+        let now = Instant::now();
+        assert_eq!(channels.consume_rate_limit(GLOBAL_CHANNEL, 1, now), Err("Not subscribed"));
+        channels.subscribe(GLOBAL_CHANNEL, 1).unwrap();
+        channels.subscribe(GLOBAL_CHANNEL, 2).unwrap();
+        channels.subscribe("region:eu-west", 1).unwrap();
+        assert_eq!(channels.subscribers(GLOBAL_CHANNEL), vec![1, 2]);
+
+        assert!(channels.consume_rate_limit(GLOBAL_CHANNEL, 1, now).is_ok());
+        assert_eq!(channels.consume_rate_limit("region:eu-west", 1, now + Duration::from_secs(1)), Err("Rate limited"));
+        assert!(channels.consume_rate_limit(GLOBAL_CHANNEL, 2, now).is_ok());
+
+        channels.forget(1);
+        assert!(channels.channels_of(1).is_empty());
+        assert_eq!(channels.subscribers("region:eu-west"), Vec::<u32>::new());
+    }
+
+    #[test]
+    fn test_subscription_cap() {
+        let channels = ChatChannels::new();
+        for i in 0..MAX_SUBSCRIPTIONS {
+            channels.subscribe(&format!("region:r{}", i), 1).unwrap();
+        }
+        assert_eq!(channels.subscribe(GLOBAL_CHANNEL, 1), Err("Too many channels"));
+        assert!(channels.subscribe("region:r0", 1).is_ok()); // Already subscribed
+    }
+}
@@ -1,9 +1,11 @@
 use std::collections::HashMap;
+use crate::state::loadouts::Loadout;
 use std::net::SocketAddr;
 use tokio::sync::mpsc;
 
 /// Command sent from network handlers to lobby tick loop
-#[derive(Debug, Clone)]
+/// Serializable so applied commands can be replicated to a standby
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub enum LobbyCommand {
     // Player management
     PlayerJoin {
@@ -21,6 +23,7 @@ pub enum LobbyCommand {
         player_id: u32,
         name: String,
         addr: SocketAddr,
+        client_id: Option<String>,
     },
     
     // Position (only latest kept per player)
@@ -28,13 +31,21 @@ pub enum LobbyCommand {
         player_id: u32,
         position: (f32, f32, f32),
         rotation: (f32, f32, f32),
-        addr: SocketAddr,  // Track UDP address for broadcasting
     },
     
     // Combat
     Shoot {
         player_id: u32,
-        target_id: u32,
+        target_id: Option<u32>, // None: a miss, still using ammo and counting as a shot fired
+        // Player each pellet hit, as reported by the client (multi-pellet weapons; empty = server rolls the spread)
+        #[serde(default)]
+        pellet_hits: Vec<u32>,
+    },
+    // Start (held) or stop holding the trigger; the tick loop fires automatic weapons
+    FireHeld {
+        player_id: u32,
+        held: bool,
+        target_id: Option<u32>,
     },
     Reload {
         player_id: u32,
@@ -43,14 +54,243 @@ pub enum LobbyCommand {
         player_id: u32,
         weapon_id: u32,
     },
+    // Loadout preset (already validated against the player's unlocks) for the next respawn
+    SelectLoadout {
+        player_id: u32,
+        loadout: Loadout,
+        immediate: bool, // Equip now instead (picked with the join)
+    },
+    // Next/previous owned weapon, resolved against the lobby's ladder
+    WeaponCycle {
+        player_id: u32,
+        forward: bool,
+    },
     
-    // Keepalive
-    Heartbeat {
+    // Items
+    // Pickup the scene placed, checked against the player's position
+    Pickup {
+        player_id: u32,
+        pickup_id: u32,
+    },
+    // Dropped item the server placed in the world
+    LootPickup {
+        player_id: u32,
+        item_id: u32,
+    },
+
+    // Chat and emotes
+    Chat {
+        player_id: u32,
+        text: String,
+    },
+    Emote {
+        player_id: u32,
+        emote: String,
+    },
+    // Move to another team, if balance and the switch cooldown allow
+    RequestTeamSwitch {
+        player_id: u32,
+        team: u8,
+    },
+    // Client confirmed the address generation it was challenged with from its new address
+    RebindAck {
+        player_id: u32,
+        addr: SocketAddr,
+        generation: u64,
+    },
+    Whisper {
+        player_id: u32,
+        target_id: u32,
+        text: String,
+    },
+    // Observer clients (not players) asking for, or done with, summary snapshots
+    ObserverJoin {
+        addr: SocketAddr,
+    },
+    ObserverLeave {
+        addr: SocketAddr,
+    },
+    // Cross-lobby chat channels ("global", "region")
+    ChannelSubscribe {
+        player_id: u32,
+        channel: String,
+        subscribe: bool,
+    },
+    ChannelChat {
+        player_id: u32,
+        channel: String,
+        text: String,
+    },
+    // A channel message fanned out to this lobby's subscribers
+    ChannelDeliver {
+        channel: String,
+        from_id: u32,
+        from_name: String,
+        text: String,
+        recipients: Vec<u32>,
+    },
+
+    // Protocol capabilities the client declared at join
+    SetCapabilities {
+        player_id: u32,
+        quaternion_rotation: bool,
+        message_keys: bool,
+        preferences: crate::domain::preferences::Preferences,
+    },
+
+    // Latency
+    LatencySample {
+        player_id: u32,
+        rtt_ms: f32,
+    },
+    TimeSync {
+        player_id: u32,
+        seq: Option<u64>,
+        client_time_ms: f64,
+        received_at_ms: u64, // Server clock when the request arrived
+    },
+
+    // Votes
+    VoteStart {
+        player_id: u32,
+        kind: crate::domain::votes::VoteKind,
+    },
+    VoteCast {
+        player_id: u32,
+        yes: bool,
+    },
+
+    // Anti-cheat: input rejected before it reached the lobby
+    Violation {
+        player_id: u32,
+        kind: crate::domain::validation::ViolationKind,
+    },
+
+    // Match lifecycle
+    Ready {
+        player_id: u32,
+        ready: bool,
+    },
+    EndMatch,
+    // player_id is None when issued by an administrator (HTTP)
+    Pause {
+        player_id: Option<u32>,
+    },
+    Resume {
+        player_id: Option<u32>,
+    },
+    // Ban (or lift the ban on) a weapon for the rest of the lobby's life
+    BanWeapon {
+        player_id: Option<u32>,
+        weapon_id: u32,
+        banned: bool,
+    },
+
+    // Moderation (admin console)
+    Kick {
         player_id: u32,
-        addr: SocketAddr,  // Track UDP address for broadcasting
+        reason: String,
+    },
+    Announce {
+        message: String,
+    },
+    // Operator rules script; None removes it. Carries the source so it replicates
+    SetRulesScript {
+        name: String,
+        source: Option<String>,
+    },
+
+    // Command tagged with the correlation id of the packet that caused it
+    Traced {
+        correlation_id: String,
+        command: Box<LobbyCommand>,
     },
 }
 
+impl LobbyCommand {
+    /// Split off the correlation id, if the command carries one
+    pub fn untrace(self) -> (Option<String>, LobbyCommand) {
+        match self {
+            LobbyCommand::Traced { correlation_id, command } => (Some(correlation_id), command.untrace().1),
+            command => (None, command),
+        }
+    }
+
+    /// The command without its correlation id
+    pub fn inner(&self) -> &LobbyCommand {
+        match self {
+            LobbyCommand::Traced { command, .. } => command.inner(),
+            command => command,
+        }
+    }
+
+    /// Short name for logs
+    pub fn name(&self) -> &'static str {
+        match self.inner() {
+            LobbyCommand::PlayerJoin { .. } => "player_join",
+            LobbyCommand::PlayerLeave { .. } => "player_leave",
+            LobbyCommand::UdpConnect { .. } => "udp_connect",
+            LobbyCommand::PositionUpdate { .. } => "position_update",
+            LobbyCommand::Shoot { .. } => "shoot",
+            LobbyCommand::FireHeld { .. } => "fire_held",
+            LobbyCommand::Reload { .. } => "reload",
+            LobbyCommand::WeaponSwitch { .. } => "weapon_switch",
+            LobbyCommand::SelectLoadout { .. } => "select_loadout",
+            LobbyCommand::WeaponCycle { .. } => "weapon_cycle",
+            LobbyCommand::Pickup { .. } => "pickup",
+            LobbyCommand::LootPickup { .. } => "loot_pickup",
+            LobbyCommand::Chat { .. } => "chat",
+            LobbyCommand::Emote { .. } => "emote",
+            LobbyCommand::RequestTeamSwitch { .. } => "request_team_switch",
+            LobbyCommand::RebindAck { .. } => "rebind_ack",
+            LobbyCommand::Whisper { .. } => "whisper",
+            LobbyCommand::ObserverJoin { .. } => "observer_join",
+            LobbyCommand::ObserverLeave { .. } => "observer_leave",
+            LobbyCommand::ChannelSubscribe { .. } => "channel_subscribe",
+            LobbyCommand::ChannelChat { .. } => "channel_chat",
+            LobbyCommand::ChannelDeliver { .. } => "channel_deliver",
+            LobbyCommand::SetCapabilities { .. } => "set_capabilities",
+            LobbyCommand::LatencySample { .. } => "latency_sample",
+            LobbyCommand::TimeSync { .. } => "time_sync",
+            LobbyCommand::VoteStart { .. } => "vote_start",
+            LobbyCommand::VoteCast { .. } => "vote_cast",
+            LobbyCommand::Violation { .. } => "violation",
+            LobbyCommand::Ready { .. } => "ready",
+            LobbyCommand::EndMatch => "end_match",
+            LobbyCommand::Pause { .. } => "pause",
+            LobbyCommand::Resume { .. } => "resume",
+            LobbyCommand::BanWeapon { .. } => "ban_weapon",
+            LobbyCommand::Kick { .. } => "kick",
+            LobbyCommand::Announce { .. } => "announce",
+            LobbyCommand::SetRulesScript { .. } => "set_rules_script",
+            LobbyCommand::Traced { .. } => "traced",
+        }
+    }
+
+    /// Combat commands are dropped until the match is in progress
+    pub fn is_combat(&self) -> bool {
+        matches!(
+            self.inner(),
+            LobbyCommand::Shoot { .. } | LobbyCommand::FireHeld { .. } | LobbyCommand::Pickup { .. } | LobbyCommand::LootPickup { .. }
+        )
+    }
+
+    /// Gameplay commands are dropped while the lobby is paused
+    pub fn is_gameplay(&self) -> bool {
+        matches!(
+            self.inner(),
+            LobbyCommand::PositionUpdate { .. }
+                | LobbyCommand::Shoot { .. }
+                | LobbyCommand::FireHeld { .. }
+                | LobbyCommand::Reload { .. }
+                | LobbyCommand::Pickup { .. }
+                | LobbyCommand::LootPickup { .. }
+                | LobbyCommand::WeaponSwitch { .. }
+                | LobbyCommand::WeaponCycle { .. }
+        )
+    }
+}
+
 /// Coalesce commands from queue, keeping only latest position per player
 /// This drops stale position packets and prevents queue overflow
 pub fn drain_and_coalesce(
@@ -61,8 +301,8 @@ pub fn drain_and_coalesce(
     
     // Drain all available commands
     while let Ok(cmd) = rx.try_recv() {
-        match cmd {
-            LobbyCommand::PositionUpdate { player_id, .. } => {
+        match cmd.inner() {
+            &LobbyCommand::PositionUpdate { player_id, .. } => {
                 // Keep only the LATEST position per player
                 latest_positions.insert(player_id, cmd);
             }
@@ -78,37 +318,28 @@ pub fn drain_and_coalesce(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::net::{IpAddr, Ipv4Addr};
-
-    fn test_addr() -> SocketAddr {
-        SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080)
-    }
 
     #[tokio::test]
     async fn test_position_coalescing() {
         let (tx, mut rx) = mpsc::channel(100);
-        let addr = test_addr();
         
         // Send multiple position updates for same player
         tx.send(LobbyCommand::PositionUpdate {
             player_id: 1,
             position: (1.0, 1.0, 1.0),
             rotation: (0.0, 0.0, 0.0),
-            addr,
         }).await.unwrap();
         
         tx.send(LobbyCommand::PositionUpdate {
             player_id: 1,
             position: (2.0, 2.0, 2.0),
             rotation: (0.0, 0.0, 0.0),
-            addr,
         }).await.unwrap();
         
         tx.send(LobbyCommand::PositionUpdate {
             player_id: 1,
             position: (3.0, 3.0, 3.0),
             rotation: (0.0, 0.0, 0.0),
-            addr,
         }).await.unwrap();
         
         let commands = drain_and_coalesce(&mut rx);
@@ -125,21 +356,18 @@ mod tests {
     #[tokio::test]
     async fn test_mixed_commands() {
         let (tx, mut rx) = mpsc::channel(100);
-        let addr = test_addr();
         
-        tx.send(LobbyCommand::Shoot { player_id: 1, target_id: 2 }).await.unwrap();
+        tx.send(LobbyCommand::Shoot { player_id: 1, target_id: Some(2), pellet_hits: Vec::new() }).await.unwrap();
         tx.send(LobbyCommand::PositionUpdate {
             player_id: 1,
             position: (1.0, 1.0, 1.0),
             rotation: (0.0, 0.0, 0.0),
-            addr,
         }).await.unwrap();
         tx.send(LobbyCommand::Reload { player_id: 1 }).await.unwrap();
         tx.send(LobbyCommand::PositionUpdate {
             player_id: 1,
             position: (2.0, 2.0, 2.0),
             rotation: (0.0, 0.0, 0.0),
-            addr,
         }).await.unwrap();
         
         let commands = drain_and_coalesce(&mut rx);
@@ -154,25 +382,21 @@ mod tests {
     #[tokio::test]
     async fn test_multiple_players_positions() {
         let (tx, mut rx) = mpsc::channel(100);
-        let addr = test_addr();
         
         tx.send(LobbyCommand::PositionUpdate {
             player_id: 1,
             position: (1.0, 1.0, 1.0),
             rotation: (0.0, 0.0, 0.0),
-            addr,
         }).await.unwrap();
         tx.send(LobbyCommand::PositionUpdate {
             player_id: 2,
             position: (2.0, 2.0, 2.0),
             rotation: (0.0, 0.0, 0.0),
-            addr,
         }).await.unwrap();
         tx.send(LobbyCommand::PositionUpdate {
             player_id: 1,
             position: (3.0, 3.0, 3.0),
             rotation: (0.0, 0.0, 0.0),
-            addr,
         }).await.unwrap();
         
         let commands = drain_and_coalesce(&mut rx);
@@ -191,5 +415,25 @@ mod tests {
         player_ids.sort();
         assert_eq!(player_ids, vec![1, 2]);
     }
-}
 
+    #[tokio::test]
+    async fn test_traced_positions_coalesced() {
+        let (tx, mut rx) = mpsc::channel(100);
+        for (i, correlation_id) in ["a", "b"].into_iter().enumerate() {
+            let command = LobbyCommand::PositionUpdate {
+                player_id: 1,
+                position: (i as f32, 0.0, 0.0),
+                rotation: (0.0, 0.0, 0.0),
+            };
+            tx.send(LobbyCommand::Traced { correlation_id: correlation_id.to_string(), command: Box::new(command) }).await.unwrap();
+        }
+
+        let mut commands = drain_and_coalesce(&mut rx);
+        assert_eq!(commands.len(), 1);
+        assert!(commands[0].is_gameplay());
+        assert_eq!(commands[0].name(), "position_update");
+        let (correlation_id, command) = commands.remove(0).untrace();
+        assert_eq!(correlation_id.as_deref(), Some("b"));
+        assert!(matches!(command, LobbyCommand::PositionUpdate { position: (1.0, _, _), .. }));
+    }
+}
@@ -0,0 +1,32 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Failures per client address over a fixed window; addresses past the limit are refused until it ends
+#[derive(Debug)]
+pub struct FailureLimiter {
+    limit: u32,
+    window: Duration,
+    by_addr: Mutex<HashMap<Option<IpAddr>, (Instant, u32)>>, // addr -> (window start, failures)
+}
+
+impl FailureLimiter {
+    pub fn new(limit: u32, window: Duration) -> Self {
+        Self { limit, window, by_addr: Mutex::new(HashMap::new()) }
+    }
+
+    /// Time left until `addr` may try again, if it has failed too often
+    pub fn blocked_for(&self, addr: Option<IpAddr>, now: Instant) -> Option<Duration> {
+        let by_addr = self.by_addr.lock().unwrap();
+        let (start, failures) = by_addr.get(&addr)?;
+        let left = self.window.checked_sub(now.saturating_duration_since(*start))?;
+        (*failures >= self.limit && !left.is_zero()).then_some(left)
+    }
+
+    pub fn record(&self, addr: Option<IpAddr>, now: Instant) {
+        let mut by_addr = self.by_addr.lock().unwrap();
+        by_addr.retain(|_, (start, _)| now.saturating_duration_since(*start) < self.window);
+        by_addr.entry(addr).or_insert((now, 0)).1 += 1;
+    }
+}
@@ -0,0 +1,138 @@
+use dashmap::DashMap;
+use std::collections::BTreeSet;
+
+/// Most friends (and pending requests) one account may have
+pub const MAX_FRIENDS: usize = 100;
+
+/// Mutual friend lists by account
+/// Friendship needs consent: one account asks, the other accepts by asking back
+#[derive(Debug, Default)]
+pub struct FriendLists {
+    lists: DashMap<String, BTreeSet<String>>,
+    /// Pending requests by the account they were sent to
+    requests: DashMap<String, BTreeSet<String>>,
+}
+
+impl FriendLists {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ask `to` for friendship, or accept the request `to` already sent
+    /// Returns whether the two are now friends (false while the request is pending)
+    pub fn request(&self, from: &str, to: &str) -> Result<bool, &'static str> {
+        if from == to {
+            return Err("Cannot befriend yourself");
+        }
+        if self.is_friend(from, to) {
+            return Ok(true);
+        }
+        let accepting = self.requests.get(from).is_some_and(|pending| pending.contains(to));
+        if !accepting {
+            let mut pending = self.requests.entry(to.to_string()).or_default();
+            if pending.len() >= MAX_FRIENDS && !pending.contains(from) {
+                return Err("Too many pending requests");
+            }
+            pending.insert(from.to_string());
+            return Ok(false);
+        }
+        if self.count(from) >= MAX_FRIENDS || self.count(to) >= MAX_FRIENDS {
+            return Err("Friend list full");
+        }
+        if let Some(mut pending) = self.requests.get_mut(from) {
+            pending.remove(to);
+        }
+        self.lists.entry(from.to_string()).or_default().insert(to.to_string());
+        self.lists.entry(to.to_string()).or_default().insert(from.to_string());
+        Ok(true)
+    }
+
+    /// End a friendship on both sides, or decline or withdraw a pending request
+    pub fn remove(&self, account: &str, other: &str) -> Result<(), &'static str> {
+        let unlink = |map: &DashMap<String, BTreeSet<String>>, owner: &str, entry: &str| {
+            map.get_mut(owner).is_some_and(|mut set| set.remove(entry))
+        };
+        let friends = unlink(&self.lists, account, other) | unlink(&self.lists, other, account);
+        let requests = unlink(&self.requests, account, other) | unlink(&self.requests, other, account);
+        if friends || requests {
+            Ok(())
+        } else {
+            Err("Not a friend")
+        }
+    }
+
+    /// Whether both accounts count each other as friends
+    pub fn is_friend(&self, account: &str, other: &str) -> bool {
+        let lists = |owner: &str, entry: &str| self.lists.get(owner).is_some_and(|set| set.contains(entry));
+        lists(account, other) && lists(other, account)
+    }
+
+    /// Friend accounts in ascending order
+    pub fn friends_of(&self, account: &str) -> Vec<String> {
+        self.lists.get(account)
+            .map(|set| set.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Accounts waiting for `account` to accept, in ascending order
+    pub fn requests_for(&self, account: &str) -> Vec<String> {
+        self.requests.get(account)
+            .map(|set| set.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    fn count(&self, account: &str) -> usize {
+        self.lists.get(account).map_or(0, |set| set.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_friendship_needs_both_sides() {
+        let friends = FriendLists::new();
+        assert_eq!(friends.request("alice", "alice"), Err("Cannot befriend yourself"));
+        assert_eq!(friends.request("alice", "bob"), Ok(false));
+        assert!(!friends.is_friend("alice", "bob"));
+        assert!(friends.friends_of("alice").is_empty());
+        assert_eq!(friends.requests_for("bob"), vec!["alice"]);
+
+        // Bob accepts by asking back
+        assert_eq!(friends.request("bob", "alice"), Ok(true));
+        assert!(friends.is_friend("alice", "bob"));
+        assert!(friends.is_friend("bob", "alice"));
+        assert!(friends.requests_for("bob").is_empty());
+        assert_eq!(friends.request("alice", "bob"), Ok(true));
+
+        // Either side ends it for both
+        friends.remove("bob", "alice").unwrap();
+        assert!(!friends.is_friend("alice", "bob"));
+        assert!(friends.friends_of("alice").is_empty());
+        assert_eq!(friends.remove("alice", "bob"), Err("Not a friend"));
+    }
+
+    #[test]
+    fn test_declined_request_is_dropped() {
+        let friends = FriendLists::new();
+        friends.request("alice", "bob").unwrap();
+        friends.remove("bob", "alice").unwrap();
+        assert!(friends.requests_for("bob").is_empty());
+        // Asking again starts over rather than accepting
+        assert_eq!(friends.request("alice", "bob"), Ok(false));
+    }
+
+    #[test]
+    fn test_friend_list_is_capped() {
+        let friends = FriendLists::new();
+        for n in 0..MAX_FRIENDS {
+            let account = format!("fan{}", n);
+            friends.request(&account, "star").unwrap();
+            assert_eq!(friends.request("star", &account), Ok(true));
+        }
+        friends.request("late", "star").unwrap();
+        assert_eq!(friends.request("star", "late"), Err("Friend list full"));
+        assert_eq!(friends.request("star", "fan0"), Ok(true));
+    }
+}
@@ -1,5 +1,8 @@
-use dashmap::DashMap;
+use dashmap::{DashMap, DashSet};
+use std::sync::Arc;
 use std::time::SystemTime;
+use crate::domain::rating::DEFAULT_RATING;
+use crate::state::lobby::{MatchStats, Player};
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct GlobalPlayerStats {
@@ -9,6 +12,13 @@ pub struct GlobalPlayerStats {
     pub total_deaths: u32,
     pub total_score: u32,
     pub games_played: u32,
+    pub rating: f32,
+    pub ranked_games: u32,
+    pub total_shots_fired: u32,
+    pub total_shots_hit: u32,
+    pub total_damage_dealt: u32,
+    pub total_damage_taken: u32,
+    pub best_killstreak: u32,
     pub last_seen: SystemTime,
     pub created_at: SystemTime,
 }
@@ -22,6 +32,13 @@ impl GlobalPlayerStats {
             total_deaths: 0,
             total_score: 0,
             games_played: 0,
+            rating: DEFAULT_RATING,
+            ranked_games: 0,
+            total_shots_fired: 0,
+            total_shots_hit: 0,
+            total_damage_dealt: 0,
+            total_damage_taken: 0,
+            best_killstreak: 0,
             last_seen: SystemTime::now(),
             created_at: SystemTime::now(),
         }
@@ -35,6 +52,28 @@ impl GlobalPlayerStats {
         self.last_seen = SystemTime::now();
     }
 
+    pub fn record_match_stats(&mut self, stats: &MatchStats) {
+        self.total_shots_fired += stats.shots_fired;
+        self.total_shots_hit += stats.shots_hit;
+        self.total_damage_dealt += stats.damage_dealt;
+        self.total_damage_taken += stats.damage_taken;
+        self.best_killstreak = self.best_killstreak.max(stats.best_killstreak);
+    }
+
+    pub fn accuracy(&self) -> f32 {
+        if self.total_shots_fired > 0 {
+            self.total_shots_hit as f32 / self.total_shots_fired as f32
+        } else {
+            0.0
+        }
+    }
+
+    pub fn record_ranked_result(&mut self, rating_delta: f32) {
+        self.rating = (self.rating + rating_delta).max(0.0);
+        self.ranked_games += 1;
+        self.last_seen = SystemTime::now();
+    }
+
     pub fn kdratio(&self) -> f32 {
         if self.total_deaths > 0 {
             self.total_kills as f32 / self.total_deaths as f32
@@ -44,18 +83,43 @@ impl GlobalPlayerStats {
     }
 }
 
+/// A player changed since the last flush, with the copy the backend had before
+/// Shared backends add `stats - stored` rather than overwrite other instances' counts
+#[derive(Debug, Clone)]
+pub struct StatsChange {
+    pub stats: GlobalPlayerStats,
+    pub stored: Option<GlobalPlayerStats>,
+}
+
+/// In-memory player stats; doubles as the write-behind cache of a `StatsBackend`
 #[derive(Debug, Clone)]
 pub struct GlobalStats {
     players: DashMap<u32, GlobalPlayerStats>,
+    dirty: DashSet<u32>, // Players changed since the last flush to the backend
+    stored: DashMap<u32, GlobalPlayerStats>, // What the backend last had for each player, as far as this instance knows
+    flush_requested: Arc<tokio::sync::Notify>, // Wakes the write-behind flush ahead of its interval
 }
 
 impl GlobalStats {
     pub fn new() -> Self {
         Self {
             players: DashMap::new(),
+            dirty: DashSet::new(),
+            stored: DashMap::new(),
+            flush_requested: Arc::new(tokio::sync::Notify::new()),
         }
     }
 
+    /// Ask the stats store to flush now rather than at its next interval
+    pub fn request_flush(&self) {
+        self.flush_requested.notify_one();
+    }
+
+    /// Resolves once a flush is requested
+    pub async fn flush_requested(&self) {
+        self.flush_requested.notified().await;
+    }
+
     pub fn record_session(&self, player_id: u32, name: &str, kills: u32, deaths: u32, score: u32) {
         let mut stats = self
             .players
@@ -63,19 +127,95 @@ impl GlobalStats {
             .or_insert_with(|| GlobalPlayerStats::new(player_id, name.to_string()));
         stats.name = name.to_string();
         stats.record_session(kills, deaths, score);
+        self.dirty.insert(player_id);
+    }
+
+    /// Roll a player's session (counters and match stats) into their global stats
+    /// Sessions without any activity are skipped
+    pub fn record_player_session(&self, player: &Player) {
+        if !player.has_match_activity() {
+            return;
+        }
+        let mut stats = self
+            .players
+            .entry(player.id)
+            .or_insert_with(|| GlobalPlayerStats::new(player.id, player.name.clone()));
+        stats.name = player.name.clone();
+        stats.record_session(player.kills, player.deaths, player.score);
+        stats.record_match_stats(&player.match_stats);
+        self.dirty.insert(player.id);
     }
 
     pub fn get_stats(&self, player_id: u32) -> Option<GlobalPlayerStats> {
         self.players.get(&player_id).map(|s| s.clone())
     }
 
+    /// Current rating for a player (default rating if never seen)
+    pub fn get_rating(&self, player_id: u32) -> f32 {
+        self.players
+            .get(&player_id)
+            .map(|s| s.rating)
+            .unwrap_or(DEFAULT_RATING)
+    }
+
+    pub fn record_ranked_result(&self, player_id: u32, name: &str, rating_delta: f32) {
+        let mut stats = self
+            .players
+            .entry(player_id)
+            .or_insert_with(|| GlobalPlayerStats::new(player_id, name.to_string()));
+        stats.name = name.to_string();
+        stats.record_ranked_result(rating_delta);
+        self.dirty.insert(player_id);
+    }
+
+    /// Snapshot of every player changed since the last call, clearing the dirty set
+    pub fn take_dirty(&self) -> Vec<StatsChange> {
+        let ids: Vec<u32> = self.dirty.iter().map(|id| *id).collect();
+        ids.into_iter()
+            .filter_map(|id| {
+                self.dirty.remove(&id);
+                let stats = self.get_stats(id)?;
+                Some(StatsChange { stats, stored: self.stored.get(&id).map(|s| s.clone()) })
+            })
+            .collect()
+    }
+
+    /// The backend now has these copies; later flushes send changes relative to them
+    pub fn mark_stored(&self, stats: impl IntoIterator<Item = GlobalPlayerStats>) {
+        for stats in stats {
+            self.stored.insert(stats.player_id, stats);
+        }
+    }
+
+    /// Re-queue players whose flush failed
+    pub fn mark_dirty(&self, player_ids: impl IntoIterator<Item = u32>) {
+        for player_id in player_ids {
+            self.dirty.insert(player_id);
+        }
+    }
+
+    /// Adopt stats loaded from the backend (written by this or another instance)
+    /// Players with unflushed local changes keep the local copy
+    pub fn merge_remote(&self, remote: Vec<GlobalPlayerStats>) -> usize {
+        let mut merged = 0;
+        for stats in remote {
+            if self.dirty.contains(&stats.player_id) {
+                continue;
+            }
+            self.stored.insert(stats.player_id, stats.clone());
+            self.players.insert(stats.player_id, stats);
+            merged += 1;
+        }
+        merged
+    }
+
     pub fn get_top_players(&self, limit: usize) -> Vec<GlobalPlayerStats> {
         let mut all: Vec<_> = self
             .players
             .iter()
             .map(|entry| entry.value().clone())
             .collect();
-        all.sort_by(|a, b| b.total_score.cmp(&a.total_score));
+        all.sort_by_key(|s| std::cmp::Reverse(s.total_score));
         all.into_iter().take(limit).collect()
     }
 
@@ -85,10 +225,23 @@ impl GlobalStats {
             .iter()
             .map(|entry| entry.value().clone())
             .collect();
-        all.sort_by(|a, b| b.total_kills.cmp(&a.total_kills));
+        all.sort_by_key(|s| std::cmp::Reverse(s.total_kills));
         all.into_iter().take(limit).collect()
     }
 
+    /// Up to `limit` players with ids above `after`, lowest id first, for paging through every record
+    pub fn page_by_id(&self, after: Option<u32>, limit: usize) -> Vec<GlobalPlayerStats> {
+        let mut page: Vec<_> = self
+            .players
+            .iter()
+            .filter(|entry| after.is_none_or(|after| *entry.key() > after))
+            .map(|entry| entry.value().clone())
+            .collect();
+        page.sort_by_key(|s| s.player_id);
+        page.truncate(limit);
+        page
+    }
+
     pub fn cleanup_old_entries(&self, max_age_secs: u64) -> usize {
         let now = SystemTime::now();
         let mut removed = 0;
@@ -167,4 +320,84 @@ mod tests {
         assert_eq!(top[0].player_id, 3);
         assert_eq!(top[1].player_id, 1);
     }
+
+    #[test]
+    fn test_ranked_result_updates_rating() {
+        let stats = GlobalStats::new();
+        assert_eq!(stats.get_rating(1), DEFAULT_RATING);
+
+        stats.record_ranked_result(1, "Player1", 16.0);
+        stats.record_ranked_result(1, "Player1", -4.0);
+
+        let player_stats = stats.get_stats(1).unwrap();
+        assert!((player_stats.rating - (DEFAULT_RATING + 12.0)).abs() < 0.001);
+        assert_eq!(player_stats.ranked_games, 2);
+        assert_eq!(player_stats.games_played, 0);
+    }
+
+    #[test]
+    fn test_record_player_session() {
+        let stats = GlobalStats::new();
+        let mut player = Player::new_player(1, "Player1".to_string(), 1, 20);
+
+        // Idle sessions are not recorded
+        stats.record_player_session(&player);
+        assert!(stats.get_stats(1).is_none());
+
+        player.kills = 2;
+        player.match_stats = MatchStats { shots_fired: 10, shots_hit: 4, damage_dealt: 80, damage_taken: 30, best_killstreak: 2, assist_score: 0 };
+        stats.record_player_session(&player);
+        player.match_stats.best_killstreak = 1;
+        stats.record_player_session(&player);
+
+        let player_stats = stats.get_stats(1).unwrap();
+        assert_eq!(player_stats.games_played, 2);
+        assert_eq!(player_stats.total_shots_fired, 20);
+        assert_eq!(player_stats.total_damage_dealt, 160);
+        assert_eq!(player_stats.best_killstreak, 2);
+        assert!((player_stats.accuracy() - 0.4).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_dirty_players_flushed_once() {
+        let stats = GlobalStats::new();
+        stats.record_session(1, "Player1", 1, 0, 100);
+        stats.record_ranked_result(2, "Player2", 8.0);
+
+        let mut flushed: Vec<u32> = stats.take_dirty().iter().map(|c| c.stats.player_id).collect();
+        flushed.sort();
+        assert_eq!(flushed, vec![1, 2]);
+        assert!(stats.take_dirty().is_empty());
+
+        // Unflushed local changes win over the remote copy
+        stats.record_session(1, "Player1", 1, 0, 100);
+        let mut remote = GlobalPlayerStats::new(1, "Player1".to_string());
+        remote.total_score = 5;
+        let other = GlobalPlayerStats::new(3, "Player3".to_string());
+        assert_eq!(stats.merge_remote(vec![remote, other]), 1);
+        assert_eq!(stats.get_stats(1).unwrap().total_score, 200);
+        assert!(stats.get_stats(3).is_some());
+
+        // Changes carry the copy the backend last had
+        let changes = stats.take_dirty();
+        assert!(changes[0].stored.is_none());
+        stats.mark_stored(changes.into_iter().map(|c| c.stats));
+        stats.record_session(1, "Player1", 1, 0, 100);
+        let change = stats.take_dirty().remove(0);
+        assert_eq!(change.stored.unwrap().total_score, 200);
+        assert_eq!(change.stats.total_score, 300);
+    }
+
+    #[test]
+    fn test_page_by_id_walks_every_player_once() {
+        let stats = GlobalStats::new();
+        for player_id in [5, 1, 4, 2, 3] {
+            stats.record_session(player_id, &format!("Player{}", player_id), 0, 0, 0);
+        }
+        let ids = |page: Vec<GlobalPlayerStats>| page.iter().map(|s| s.player_id).collect::<Vec<_>>();
+        assert_eq!(ids(stats.page_by_id(None, 2)), vec![1, 2]);
+        assert_eq!(ids(stats.page_by_id(Some(2), 2)), vec![3, 4]);
+        assert_eq!(ids(stats.page_by_id(Some(4), 2)), vec![5]);
+        assert!(stats.page_by_id(Some(5), 2).is_empty());
+    }
 }
@@ -0,0 +1,69 @@
+use std::collections::hash_map::RandomState;
+use std::hash::BuildHasher;
+use std::net::SocketAddr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Cookies are issued per window and accepted for the window after too,
+/// so a client always has at least this long to echo one
+pub const COOKIE_WINDOW_SECS: u64 = 30;
+
+/// Stateless join cookies: a keyed hash of the client address and time window
+///
+/// A join is only answered in full once it echoes the cookie sent to its source
+/// address, so spoofed joins get back a reply no bigger than the request.
+/// Nothing is stored per client; the key is random per process.
+#[derive(Debug, Default)]
+pub struct HandshakeCookies {
+    key: RandomState,
+}
+
+impl HandshakeCookies {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cookie for an address in the current window
+    pub fn issue(&self, addr: SocketAddr, now: SystemTime) -> String {
+        format!("{:016x}", self.cookie(addr, window(now)))
+    }
+
+    /// Whether a cookie was issued to this address in this window or the last
+    pub fn verify(&self, addr: SocketAddr, cookie: &str, now: SystemTime) -> bool {
+        let Ok(cookie) = u64::from_str_radix(cookie, 16) else {
+            return false;
+        };
+        let current = window(now);
+        cookie == self.cookie(addr, current) || cookie == self.cookie(addr, current.saturating_sub(1))
+    }
+
+    fn cookie(&self, addr: SocketAddr, window: u64) -> u64 {
+        self.key.hash_one((addr, window))
+    }
+}
+
+fn window(now: SystemTime) -> u64 {
+    now.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs() / COOKIE_WINDOW_SECS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_cookie_bound_to_address_and_window() {
+        let cookies = HandshakeCookies::new();
+        let addr: SocketAddr = "127.0.0.1:9000".parse().unwrap();
+        let now = UNIX_EPOCH + Duration::from_secs(COOKIE_WINDOW_SECS * 1000);
+        let cookie = cookies.issue(addr, now);
+
+        assert!(cookies.verify(addr, &cookie, now));
+        assert!(cookies.verify(addr, &cookie, now + Duration::from_secs(COOKIE_WINDOW_SECS)));
+        assert!(!cookies.verify(addr, &cookie, now + Duration::from_secs(COOKIE_WINDOW_SECS * 2)));
+        assert!(!cookies.verify("127.0.0.1:9001".parse().unwrap(), &cookie, now));
+        assert!(!cookies.verify(addr, "not-hex", now));
+
+        // Another server process holds another key
+        assert!(!HandshakeCookies::new().verify(addr, &cookie, now));
+    }
+}
@@ -0,0 +1,160 @@
+use dashmap::DashMap;
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+
+/// How long a client has to answer a latency probe
+pub const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Error code sent to clients refused for measuring above a lobby's limit
+pub const LATENCY_TOO_HIGH: &str = "latency_too_high";
+
+/// A join waiting on its round-trip measurement
+#[derive(Debug, Clone)]
+struct PendingProbe {
+    nonce: u64,
+    sent_at: Instant,
+    join: serde_json::Value,
+}
+
+/// Joins held back until the client echoes a `latency_probe`, by client address
+#[derive(Debug, Default)]
+pub struct LatencyProbes {
+    pending: DashMap<SocketAddr, PendingProbe>,
+}
+
+impl LatencyProbes {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Hold a join packet and return the nonce the client must echo
+    /// A newer join from the same address replaces the older probe
+    pub fn start(&self, addr: SocketAddr, join: serde_json::Value, now: Instant) -> u64 {
+        self.pending.retain(|_, probe| now.duration_since(probe.sent_at) < PROBE_TIMEOUT);
+        let nonce = uuid::Uuid::new_v4().as_u64_pair().0;
+        self.pending.insert(addr, PendingProbe { nonce, sent_at: now, join });
+        nonce
+    }
+
+    /// Match an echo to its probe; returns the held join packet and the measured RTT in ms
+    pub fn finish(&self, addr: SocketAddr, nonce: u64, now: Instant) -> Result<(serde_json::Value, f32), &'static str> {
+        let matches = self.pending.get(&addr).is_some_and(|probe| probe.nonce == nonce);
+        if !matches {
+            return Err("No matching latency probe");
+        }
+        let (_, probe) = self.pending.remove(&addr).ok_or("No matching latency probe")?;
+        let elapsed = now.duration_since(probe.sent_at);
+        if elapsed >= PROBE_TIMEOUT {
+            return Err("Latency probe expired");
+        }
+        Ok((probe.join, elapsed.as_secs_f32() * 1000.0))
+    }
+
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+}
+
+/// A pong waiting for the client's `pong_ack`
+#[derive(Debug, Clone, Copy)]
+struct PendingEcho {
+    player_id: u32,
+    nonce: u64,
+    sent_at: Instant,
+}
+
+/// Server-timed round trips of connected players: each pong carries a nonce the client echoes at once
+/// Keyed by client address, so only the address that pinged can complete the measurement
+#[derive(Debug, Default)]
+pub struct PingEchoes {
+    pending: DashMap<SocketAddr, PendingEcho>,
+}
+
+impl PingEchoes {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Nonce for the pong about to go to `addr`; replaces any echo still outstanding
+    pub fn start(&self, addr: SocketAddr, player_id: u32, now: Instant) -> u64 {
+        let nonce = uuid::Uuid::new_v4().as_u64_pair().0;
+        self.pending.insert(addr, PendingEcho { player_id, nonce, sent_at: now });
+        nonce
+    }
+
+    /// Match an echo to its pong; returns the player and the measured RTT in ms
+    pub fn finish(&self, addr: SocketAddr, nonce: u64, now: Instant) -> Option<(u32, f32)> {
+        let (_, echo) = self.pending.remove_if(&addr, |_, echo| echo.nonce == nonce)?;
+        let elapsed = now.duration_since(echo.sent_at);
+        (elapsed < PROBE_TIMEOUT).then_some((echo.player_id, elapsed.as_secs_f32() * 1000.0))
+    }
+
+    /// Drop an address's outstanding echo (call when the player leaves)
+    pub fn forget(&self, addr: SocketAddr) {
+        self.pending.remove(&addr);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_probe_round_trip() {
+        let probes = LatencyProbes::new();
+        let addr: SocketAddr = "127.0.0.1:9000".parse().unwrap();
+        let start = Instant::now();
+        let nonce = probes.start(addr, serde_json::json!({ "type": "join" }), start);
+
+        // A wrong nonce (or another address) leaves the probe waiting
+        assert_eq!(probes.finish(addr, nonce.wrapping_add(1), start).unwrap_err(), "No matching latency probe");
+        assert!(probes.finish("127.0.0.1:9001".parse().unwrap(), nonce, start).is_err());
+
+        let (join, rtt_ms) = probes.finish(addr, nonce, start + Duration::from_millis(80)).unwrap();
+        assert_eq!(join["type"], "join");
+        assert!((rtt_ms - 80.0).abs() < 0.01);
+        assert!(probes.is_empty());
+        assert!(probes.finish(addr, nonce, start).is_err()); // Answered once only
+    }
+
+    #[test]
+    fn test_ping_echo_timed_by_server() {
+        let echoes = PingEchoes::new();
+        let addr: SocketAddr = "127.0.0.1:9000".parse().unwrap();
+        let start = Instant::now();
+        let nonce = echoes.start(addr, 7, start);
+
+        // Only the right nonce from the pinging address counts, and only once
+        assert!(echoes.finish(addr, nonce.wrapping_add(1), start).is_none());
+        assert!(echoes.finish("127.0.0.1:9001".parse().unwrap(), nonce, start).is_none());
+        let (player_id, rtt_ms) = echoes.finish(addr, nonce, start + Duration::from_millis(45)).unwrap();
+        assert_eq!(player_id, 7);
+        assert!((rtt_ms - 45.0).abs() < 0.01);
+        assert!(echoes.finish(addr, nonce, start).is_none());
+
+        let nonce = echoes.start(addr, 7, start);
+        assert!(echoes.finish(addr, nonce, start + PROBE_TIMEOUT).is_none());
+        assert!(echoes.is_empty());
+    }
+
+    #[test]
+    fn test_stale_probes_expire() {
+        let probes = LatencyProbes::new();
+        let addr: SocketAddr = "127.0.0.1:9000".parse().unwrap();
+        let start = Instant::now();
+        let nonce = probes.start(addr, serde_json::json!({}), start);
+        assert_eq!(probes.finish(addr, nonce, start + PROBE_TIMEOUT).unwrap_err(), "Latency probe expired");
+
+        probes.start(addr, serde_json::json!({}), start);
+        probes.start("127.0.0.1:9001".parse().unwrap(), serde_json::json!({}), start + PROBE_TIMEOUT);
+        assert_eq!(probes.len(), 1); // Unanswered probes are swept as new ones start
+    }
+}
@@ -0,0 +1,218 @@
+use dashmap::DashMap;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use crate::utils::weapondb::WeaponLookup;
+
+/// Most presets one player may save
+pub const MAX_LOADOUTS: usize = 10;
+
+/// Most attachments on one preset
+pub const MAX_ATTACHMENTS: usize = 3;
+
+/// Longest preset name
+const MAX_NAME_LEN: usize = 32;
+
+/// Rejection for presets using a weapon the player hasn't earned
+pub const NOT_UNLOCKED: &str = "Weapon not unlocked";
+
+/// Attachments a preset may list (cosmetic until weapons model them)
+pub const ATTACHMENTS: [&str; 4] = ["red_dot", "suppressor", "extended_mag", "grip"];
+
+/// A named weapon setup a player picks at join or before respawning
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+pub struct Loadout {
+    pub name: String,
+    pub primary: u32,
+    /// Equipped instead when the lobby's ladder doesn't have the primary
+    pub secondary: Option<u32>,
+    pub attachments: Vec<String>,
+}
+
+/// Check a preset against the weapons and the player's unlocks
+/// A weapon is unlocked once the player's career kills reach its `unlock_kills`
+pub fn validate(loadout: &Loadout, weapons: &impl WeaponLookup, career_kills: u32) -> Result<(), &'static str> {
+    let name = loadout.name.trim();
+    if name.is_empty() || name.len() > MAX_NAME_LEN || name.len() != loadout.name.len() {
+        return Err("Invalid loadout name");
+    }
+    if loadout.secondary == Some(loadout.primary) {
+        return Err("Secondary must differ from primary");
+    }
+    for weapon_id in std::iter::once(loadout.primary).chain(loadout.secondary) {
+        let weapon = weapons.get(weapon_id).ok_or("Unknown weapon in loadout")?;
+        if career_kills < weapon.unlock_kills {
+            return Err(NOT_UNLOCKED);
+        }
+    }
+    if loadout.attachments.len() > MAX_ATTACHMENTS {
+        return Err("Too many attachments");
+    }
+    for (index, attachment) in loadout.attachments.iter().enumerate() {
+        if !ATTACHMENTS.contains(&attachment.as_str()) {
+            return Err("Unknown attachment");
+        }
+        if loadout.attachments[..index].contains(attachment) {
+            return Err("Duplicate attachment");
+        }
+    }
+    Ok(())
+}
+
+/// Saved loadout presets by account, so they follow the player across sessions
+#[derive(Debug, Default)]
+pub struct LoadoutStore {
+    presets: DashMap<String, BTreeMap<String, Loadout>>,
+    /// JSON file the presets are kept in across restarts, once attached
+    file: OnceLock<PathBuf>,
+    /// Serializes file writes so an older snapshot never replaces a newer one
+    writing: tokio::sync::Mutex<()>,
+}
+
+impl LoadoutStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Presets in name order
+    pub fn list(&self, account: &str) -> Vec<Loadout> {
+        self.presets.get(account)
+            .map(|presets| presets.values().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    pub fn get(&self, account: &str, name: &str) -> Option<Loadout> {
+        self.presets.get(account)?.get(name).cloned()
+    }
+
+    /// Create or replace a preset (validate it first)
+    /// Returns true if the preset is new
+    pub fn save(&self, account: &str, loadout: Loadout) -> Result<bool, &'static str> {
+        let mut presets = self.presets.entry(account.to_string()).or_default();
+        if presets.len() >= MAX_LOADOUTS && !presets.contains_key(&loadout.name) {
+            return Err("Too many loadouts");
+        }
+        Ok(presets.insert(loadout.name.clone(), loadout).is_none())
+    }
+
+    pub fn remove(&self, account: &str, name: &str) -> Result<(), &'static str> {
+        let removed = self.presets.get_mut(account)
+            .is_some_and(|mut presets| presets.remove(name).is_some());
+        if removed {
+            Ok(())
+        } else {
+            Err("Loadout not found")
+        }
+    }
+
+    /// Load the presets saved in `path` and keep writing changes there
+    /// Returns how many accounts had presets; a missing file starts empty
+    pub async fn attach(&self, path: impl Into<PathBuf>) -> Result<usize, &'static str> {
+        let path = path.into();
+        let saved: BTreeMap<String, Vec<Loadout>> = match tokio::fs::read(&path).await {
+            Ok(data) => serde_json::from_slice(&data).map_err(|_| "Corrupt loadouts file")?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => {
+                log::warn!("Failed to read loadouts file {}: {}", path.display(), e);
+                return Err("Failed to read loadouts file");
+            }
+        };
+        let accounts = saved.len();
+        for (account, presets) in saved {
+            self.presets.insert(account, presets.into_iter().map(|l| (l.name.clone(), l)).collect());
+        }
+        self.file.set(path).map_err(|_| "Loadouts file already attached")?;
+        Ok(accounts)
+    }
+
+    /// Write every preset to the attached file (no-op without one)
+    pub async fn persist(&self) -> Result<(), &'static str> {
+        let Some(path) = self.file.get() else {
+            return Ok(());
+        };
+        let _writing = self.writing.lock().await;
+        let snapshot: BTreeMap<String, Vec<Loadout>> = self.presets.iter()
+            .filter(|entry| !entry.value().is_empty())
+            .map(|entry| (entry.key().clone(), entry.value().values().cloned().collect()))
+            .collect();
+        let data = serde_json::to_vec(&snapshot).map_err(|_| "Failed to encode loadouts")?;
+
+        // Write then rename so a crash never leaves a half-written file
+        let tmp = path.with_extension("tmp");
+        let result = async {
+            tokio::fs::write(&tmp, &data).await?;
+            tokio::fs::rename(&tmp, path).await
+        }.await;
+        result.map_err(|e| {
+            log::warn!("Failed to write loadouts file {}: {}", path.display(), e);
+            "Failed to write loadouts file"
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::weapondb::WeaponDb;
+
+    fn loadout(name: &str, primary: u32, secondary: Option<u32>, attachments: &[&str]) -> Loadout {
+        Loadout {
+            name: name.to_string(),
+            primary,
+            secondary,
+            attachments: attachments.iter().map(|a| a.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_validate_against_unlocks() {
+        let weapons = WeaponDb::load();
+        assert!(validate(&loadout("rush", 1, Some(3), &["grip"]), &weapons, 0).is_ok());
+        // The Prototype needs career kills
+        assert_eq!(validate(&loadout("long", 2, None, &[]), &weapons, 0), Err("Weapon not unlocked"));
+        assert!(validate(&loadout("long", 2, None, &[]), &weapons, 100).is_ok());
+
+        assert_eq!(validate(&loadout(" ", 1, None, &[]), &weapons, 0), Err("Invalid loadout name"));
+        assert_eq!(validate(&loadout("a", 1, Some(1), &[]), &weapons, 0), Err("Secondary must differ from primary"));
+        assert_eq!(validate(&loadout("a", 99, None, &[]), &weapons, 0), Err("Unknown weapon in loadout"));
+        assert_eq!(validate(&loadout("a", 1, None, &["laser"]), &weapons, 0), Err("Unknown attachment"));
+        assert_eq!(validate(&loadout("a", 1, None, &["grip", "grip"]), &weapons, 0), Err("Duplicate attachment"));
+    }
+
+    #[test]
+    fn test_store_crud_and_cap() {
+        let store = LoadoutStore::new();
+        assert!(store.save("alice", loadout("b", 1, None, &[])).unwrap());
+        assert!(store.save("alice", loadout("a", 3, None, &[])).unwrap());
+        assert!(!store.save("alice", loadout("a", 1, None, &[])).unwrap()); // Replaced
+        let names: Vec<String> = store.list("alice").into_iter().map(|l| l.name).collect();
+        assert_eq!(names, vec!["a", "b"]);
+        assert_eq!(store.get("alice", "a").unwrap().primary, 1);
+        assert!(store.list("bob").is_empty());
+
+        store.remove("alice", "b").unwrap();
+        assert_eq!(store.remove("alice", "b"), Err("Loadout not found"));
+
+        for i in 1..MAX_LOADOUTS {
+            store.save("alice", loadout(&format!("l{}", i), 1, None, &[])).unwrap();
+        }
+        assert_eq!(store.save("alice", loadout("extra", 1, None, &[])), Err("Too many loadouts"));
+        assert!(store.save("alice", loadout("a", 3, None, &[])).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_presets_survive_restart() {
+        let path = std::env::temp_dir().join(format!("gungame_loadouts_{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let store = LoadoutStore::new();
+        assert_eq!(store.attach(&path).await, Ok(0));
+        store.save("alice", loadout("rush", 1, Some(3), &["grip"])).unwrap();
+        store.persist().await.unwrap();
+
+        let restarted = LoadoutStore::new();
+        assert_eq!(restarted.attach(&path).await, Ok(1));
+        assert_eq!(restarted.get("alice", "rush"), store.get("alice", "rush"));
+        assert!(restarted.list("bob").is_empty());
+        let _ = std::fs::remove_file(&path);
+    }
+}
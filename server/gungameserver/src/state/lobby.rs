@@ -1,7 +1,13 @@
-use crate::utils::buffers::SmallPlayerVec;
+use crate::state::loadouts::Loadout;
+use crate::utils::buffers::{SmallEventVec, SmallPlayerVec, SyncEvent};
+use crate::utils::scenes::{self, SceneData};
+use crate::utils::weapondb::WeaponOverlay;
+use crate::utils::config::DEFAULT_REGION;
 use std::collections::HashMap;
 use std::net::SocketAddr;
+use std::sync::Arc;
 use std::time::SystemTime;
+use crate::utils::clock;
 
 pub type LobbyCode = String;
 
@@ -26,6 +32,7 @@ pub struct Player {
     // Reload state
     pub is_reloading: bool,
     pub reload_end_time: Option<SystemTime>,
+    pub equip_end_time: Option<SystemTime>, // Can't fire until then after switching weapons
 
     // Combat timing
     pub last_shot_time: SystemTime,
@@ -42,9 +49,124 @@ pub struct Player {
     // Respawn state
     pub is_dead: bool,
     pub respawn_time: Option<SystemTime>,
+    pub spawn_protection_until: Option<SystemTime>, // Invulnerable until then after respawning
+    pub anticheat_strikes: u32, // Malformed or impossible inputs seen from this player
+
+    // Fall tracking (derived from the position stream)
+    pub vertical_velocity: f32,
+    pub fall_speed: f32, // Fastest downward speed since leaving the ground
+    pub last_position_time: Option<SystemTime>,
+
+    // Automatic fire state (simulated by the tick loop)
+    pub trigger_held: bool,
+    pub fire_target: Option<u32>,
+    pub burst_remaining: u32, // Rounds left in the current burst
+
+    // Health above max_health (from pickups) decays; fractional HP carried here
+    pub overheal_decay: f32,
+
+    // Combat stats for the current match
+    pub match_stats: MatchStats,
+
+    // Smoothed round-trip time reported by the client's pings
+    pub rtt_ms: Option<f32>,
+
+    // Ready check for the next match
+    pub ready: bool,
+
+    // Chat rate limiting
+    pub last_whisper_time: SystemTime,
+    pub command_last_used: HashMap<&'static str, SystemTime>, // Per chat command, for cooldowns
+
+    // Loadout picked with `select_loadout`, equipped at the next respawn
+    pub pending_loadout: Option<Loadout>,
+    pub active_loadout: Option<Loadout>, // Last equipped, re-applied at each duel round start
+    pub preferences: crate::domain::preferences::Preferences, // Packet classes the client opted out of at join
+    pub armor: u32, // Soaks up part of each hit until used up; lost on death
+    pub region: Option<String>, // Inferred from the joining address when GeoIP is enabled
+    pub carried_weapon: Option<u32>, // Picked up this life from outside the lobby's ladder
+    pub party_id: Option<String>, // Set when joined as part of a party
+    pub client_id: Option<String>, // Client install id from the UDP join; recognises crash rejoins
+    pub heat: f32, // Weapon heat from sustained fire, 0.0 to 1.0
+    pub overheated_until: Option<SystemTime>, // Can't fire until then (separate from reloading)
+
+    // Protocol capabilities negotiated at join
+    pub quaternion_rotation: bool, // Receives `orientation` quaternions alongside Euler rotations
+    pub message_keys: bool, // Localizes message keys itself, so gets no English fallback text
+
+    // AFK detection (gameplay input, unlike `last_update` which any packet refreshes)
+    pub last_activity: SystemTime,
+    pub afk_warned: bool,
+    pub spectating: bool, // Moved out of play for being AFK until the next input
+
+    // Synced fields changed since the last delta sync
+    pub changes: ChangeMask,
 }
 
-/// Player sync state for delta tracking
+/// Bitmask of synced player fields changed since the last delta sync
+/// Set at mutation sites so the sync never has to diff snapshots
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ChangeMask(u8);
+
+impl ChangeMask {
+    pub const HEALTH: ChangeMask = ChangeMask(1 << 0);
+    pub const AMMO: ChangeMask = ChangeMask(1 << 1);
+    pub const MAX_AMMO: ChangeMask = ChangeMask(1 << 2);
+    pub const WEAPON: ChangeMask = ChangeMask(1 << 3);
+    pub const RELOAD: ChangeMask = ChangeMask(1 << 4);
+    pub const POSITION: ChangeMask = ChangeMask(1 << 5); // Broadcast separately via position updates
+    pub const ALL: ChangeMask = ChangeMask(0b11_1111);
+
+    pub fn empty() -> Self {
+        ChangeMask(0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn contains(&self, other: ChangeMask) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn insert(&mut self, other: ChangeMask) {
+        self.0 |= other.0;
+    }
+}
+
+impl std::ops::BitOr for ChangeMask {
+    type Output = ChangeMask;
+
+    fn bitor(self, rhs: ChangeMask) -> ChangeMask {
+        ChangeMask(self.0 | rhs.0)
+    }
+}
+
+/// Per-match combat stats, reset when a match ends
+#[derive(Debug, Clone, Copy, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct MatchStats {
+    pub shots_fired: u32,
+    pub shots_hit: u32,
+    pub damage_dealt: u32,
+    pub damage_taken: u32,
+    pub best_killstreak: u32,
+    /// Kill score earned from kills someone else finished (damage-share scoring)
+    #[serde(default)]
+    pub assist_score: u32,
+}
+
+impl MatchStats {
+    /// Fraction of shots fired that hit (0 when no shots were fired)
+    pub fn accuracy(&self) -> f32 {
+        if self.shots_fired > 0 {
+            self.shots_hit as f32 / self.shots_fired as f32
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Player sync state snapshot (full state requests)
 #[derive(Debug, Clone, PartialEq)]
 pub struct PlayerSyncState {
     pub id: u32,
@@ -57,6 +179,10 @@ pub struct PlayerSyncState {
 }
 
 impl Player {
+    pub fn is_spawn_protected(&self, now: SystemTime) -> bool {
+        self.spawn_protection_until.is_some_and(|until| now < until)
+    }
+
     pub fn to_sync_state(&self) -> PlayerSyncState {
         PlayerSyncState {
             id: self.id,
@@ -69,13 +195,18 @@ impl Player {
         }
     }
 
+    /// Whether the player did anything worth recording this match
+    pub fn has_match_activity(&self) -> bool {
+        self.kills > 0 || self.deaths > 0 || self.score > 0 || self.match_stats != MatchStats::default()
+    }
+
     pub fn new_player(id: u32, name: String, current_weapon_id: u32, ammo: u32) -> Self {
         Player {
             id,
             name,
             position: (0.0, 1.0, 0.0),
             rotation: (0.0, 0.0, 0.0),
-            last_update: SystemTime::now(),
+            last_update: clock::now(),
             current_health: 100,
             max_health: 100,
             current_weapon_id,
@@ -91,34 +222,341 @@ impl Player {
             warned_at: None,
             is_dead: false,
             respawn_time: None,
+            vertical_velocity: 0.0,
+            fall_speed: 0.0,
+            last_position_time: None,
+            last_whisper_time: SystemTime::UNIX_EPOCH,
+            changes: ChangeMask::empty(),
+            trigger_held: false,
+            fire_target: None,
+            burst_remaining: 0,
+            overheal_decay: 0.0,
+            match_stats: MatchStats::default(),
+            rtt_ms: None,
+            ready: false,
+            spawn_protection_until: None,
+            anticheat_strikes: 0,
+            pending_loadout: None,
+            quaternion_rotation: false,
+            message_keys: false,
+            last_activity: clock::now(),
+            afk_warned: false,
+            spectating: false,
+            command_last_used: HashMap::new(),
+            equip_end_time: None,
+            active_loadout: None,
+            preferences: Default::default(),
+            armor: 0,
+            region: None,
+            carried_weapon: None,
+            party_id: None,
+            heat: 0.0,
+            overheated_until: None,
+            client_id: None,
+        }
+    }
+}
+
+/// Final standing of a player when a match ends
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct MatchStanding {
+    pub player_id: u32,
+    pub name: String,
+    pub score: u32,
+    pub kills: u32,
+    pub deaths: u32,
+    #[serde(flatten)]
+    pub stats: MatchStats,
+    pub accuracy: f32,
+    pub rating_change: Option<f32>,
+    /// Duel rounds won (0 in other modes)
+    pub round_wins: u32,
+}
+
+/// Match lifecycle: players ready up while Waiting, a countdown runs, then combat is live
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchPhase {
+    #[default]
+    Waiting,
+    Countdown,
+    InProgress,
+}
+
+/// What a match is won by
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum GameMode {
+    /// Highest score when the match is ended
+    #[default]
+    Deathmatch,
+    /// Hold the scene's capture zone alone to earn points; first to the limit wins
+    KingOfTheHill,
+    /// Two players, best of `duel_rounds` rounds; each round ends on a death or the round timer
+    Duel,
+}
+
+/// Default zone points needed to win a king-of-the-hill match
+pub const DEFAULT_ZONE_SCORE_LIMIT: u32 = 100;
+
+/// Default best-of round count for duels
+pub const DEFAULT_DUEL_ROUNDS: u32 = 5;
+
+/// Default duel round time limit
+pub const DEFAULT_DUEL_ROUND_SECS: u32 = 90;
+
+/// Default max health for players in a lobby
+pub const DEFAULT_MAX_HEALTH: u32 = 100;
+
+/// Default invulnerability window after respawning
+pub const DEFAULT_SPAWN_PROTECTION_SECS: f32 = 3.0;
+
+/// Default order weapons are cycled through with next/previous
+pub const DEFAULT_WEAPON_LADDER: [u32; 4] = [1, 2, 3, 4];
+
+/// Options chosen by the lobby creator
+#[derive(Debug, Clone)]
+pub struct LobbySettings {
+    pub ranked: bool,
+    pub max_health: u32,
+    pub weapons: Arc<WeaponOverlay>, // Weapon balance overrides for this lobby
+    pub region: String,               // Matchmaking region the lobby is hosted in
+    pub motd: String,                 // Message of the day / rules shown to joining players
+    pub spawn_protection_secs: f32,   // Invulnerability after respawn (0 = off)
+    pub spawn_protection_blocks_shooting: bool, // Protected players can't shoot either
+    pub weapon_ladder: Vec<u32>,      // Weapons players own, in next/previous order
+    pub private: bool,                // Hidden from listings and friend lookups
+    pub reserved_slots: u32,          // Slots past the normal cap that only VIPs may take
+    pub game_mode: GameMode,
+    pub zone_score_limit: u32,        // King-of-the-hill points that win the match
+    pub duel_rounds: u32,             // Duel matches are best of this many rounds
+    pub duel_round_secs: u32,         // Duel round time limit
+    pub max_latency_ms: Option<u32>,  // UDP connects measuring a higher RTT are refused (None = no gate)
+    pub fill_with_bots: bool,         // Keep humans plus bots at target_players
+    pub target_players: u32,
+    pub team_count: u8,               // Teams players are split into (0 = free-for-all)
+    pub weapon_drops: bool,           // Dead players drop their weapon, with the ammo left in it
+    pub damage_share_scoring: bool,   // Kill score is split among attackers by damage dealt
+    pub banned_weapons: Vec<u32>,     // Weapons no one may use, banned by the owner or an admin mid-match
+    pub max_match_secs: Option<u64>,  // Live matches are force-ended after this (None = server default)
+    pub max_lifetime_secs: Option<u64>, // The lobby is closed this long after creation (None = server default)
+}
+
+impl Default for LobbySettings {
+    fn default() -> Self {
+        Self {
+            ranked: false,
+            max_health: DEFAULT_MAX_HEALTH,
+            weapons: Arc::default(),
+            region: DEFAULT_REGION.to_string(),
+            motd: String::new(),
+            spawn_protection_secs: DEFAULT_SPAWN_PROTECTION_SECS,
+            spawn_protection_blocks_shooting: true,
+            weapon_ladder: DEFAULT_WEAPON_LADDER.to_vec(),
+            private: false,
+            reserved_slots: 0,
+            game_mode: GameMode::default(),
+            zone_score_limit: DEFAULT_ZONE_SCORE_LIMIT,
+            duel_rounds: DEFAULT_DUEL_ROUNDS,
+            duel_round_secs: DEFAULT_DUEL_ROUND_SECS,
+            max_latency_ms: None,
+            fill_with_bots: false,
+            target_players: 0,
+            team_count: 0,
+            weapon_drops: false,
+            damage_share_scoring: false,
+            banned_weapons: Vec::new(),
+            max_match_secs: None,
+            max_lifetime_secs: None,
         }
     }
 }
 
+/// Read-only snapshot of a lobby, published by its tick loop so listings never take the lobby lock
+#[derive(Debug, Clone, Default)]
+pub struct LobbySummary {
+    pub code: LobbyCode,
+    pub players: Vec<(u32, String)>,
+    pub max_players: u32,
+    pub scene: String,
+    pub ranked: bool,
+    pub max_health: u32,
+    pub weapons: Arc<WeaponOverlay>,
+    pub region: String,
+    pub player_region: Option<String>, // Where most located players connect from
+    pub average_latency_ms: Option<f32>,
+    pub motd: String,
+    pub phase: MatchPhase,
+    pub ready_count: usize,
+    pub match_id: Option<u64>,
+    pub private: bool,
+    pub reserved_slots: u32,
+    pub game_mode: GameMode,
+    pub max_latency_ms: Option<u32>,
+    pub fill_with_bots: bool,
+    pub target_players: u32,
+    pub team_count: u8,
+}
+
 /// Lobby state - per-lobby partitioned state
 #[derive(Debug)]
 pub struct Lobby {
     pub code: LobbyCode,
     pub players: HashMap<u32, Player>,
     pub client_addresses: HashMap<u32, SocketAddr>,
+    pub departed: HashMap<u32, SystemTime>, // Recently removed players, so shots already in flight at them aren't strikes
+    pub presence: Arc<crate::state::presence::PresenceTracker>, // Shared with the UDP handler, applied each tick
+    pub rebinds: crate::state::presence::AddressRebinds, // Address changes awaiting `rebind_ack`
     pub max_players: u32,
     pub scene: String,
+    pub scene_data: SceneData,
+    pub settings: LobbySettings,
+    pub owner_id: Option<u32>, // Player allowed to run owner commands (pause, ...)
+
+    // Pause state - gameplay and timers are frozen while set
+    pub paused_at: Option<SystemTime>,
+
+    // Ready check / countdown state machine
+    pub phase: MatchPhase,
+    pub countdown_remaining: f32, // Seconds left while in Countdown
+
+    // Kick / scene / restart vote in progress
+    pub active_vote: Option<crate::domain::votes::Vote>,
+
+    // Coarse position samples for heatmaps
+    pub analytics: crate::domain::analytics::AnalyticsBuffer,
+
+    // Per-tick positions of living players (kill cams)
+    pub position_history: crate::domain::history::PositionHistory,
+
+    // Current consecutive-hit streak per attacker (damage ramp-up)
+    pub hit_streaks: HashMap<u32, crate::domain::ramp_up::HitStreak>,
+
+    // Shots from projectile weapons still in flight
+    pub projectiles: crate::domain::projectiles::ProjectileSet,
+
+    // Items dropped where players died
+    pub loot: crate::domain::loot::LootSet,
+    // The scene's pickups and their respawn cooldowns
+    pub pickups: crate::domain::pickups::PickupSet,
+
+    // Seeded generator for gameplay rolls (loot drops)
+    pub rng: crate::utils::rng::SeededRng,
+
+    // Fill bots removed to seat a joining human, announced as leaving next tick
+    pub evicted_bots: Vec<u32>,
+
+    // Players moved in or out by a merge or split, announced as joining or leaving next tick
+    pub transfers_in: Vec<(u32, String)>,
+    pub transfers_out: Vec<u32>,
+
+    // Emotes playing, and emote cooldowns
+    pub emotes: crate::domain::emotes::EmoteState,
+
+    // Scene chunk each player is in, for streaming hints
+    pub chunks: crate::domain::streaming::ChunkTracker,
+
+    // Pending reload, respawn and spawn protection deadlines, soonest first
+    pub timers: crate::domain::timers::LobbyTimers,
+
+    // Overlay/spectator-display clients fed summary snapshots instead of events
+    pub observers: crate::domain::observers::Observers,
+
+    // Team assignments and switch cooldowns (team lobbies)
+    pub teams: crate::domain::teams::Teams,
+
+    // Damage taken this life, per attacker (damage-share scoring)
+    pub damage_attribution: crate::domain::damage_share::DamageAttribution,
+
+    // Clients gone quiet for several heartbeat intervals
+    pub connectivity: crate::domain::connectivity::Connectivity,
+
+    // Capture zone control (king-of-the-hill)
+    pub zone: crate::domain::zone_control::ZoneControl,
+
+    // Round progress and round wins (duel)
+    pub duel: crate::domain::duel::DuelState,
+
+    // When the lobby was created and its match went live, for the time caps
+    pub lifetime: crate::domain::lifetime::Lifetime,
+
+    // Tick clock, set by the tick loop; clocks of clients that sync to it
+    pub current_tick: u64,
+    pub tick_interval_ms: u64,
+    pub heartbeat_interval_ms: u64, // Keepalive interval clients are asked to keep, from config
+    pub clock_stats: HashMap<u32, crate::domain::clock_sync::ClockStats>,
+
+    // Event timeline of the match in progress (replay/observer queries)
+    pub timeline: Option<crate::domain::timeline::MatchTimeline>,
+
+    // Operator rules script, and one uploaded mid-match waiting for the match to end (Some(None) removes it)
+    pub rules_script: Option<Arc<crate::domain::scripting::RulesScript>>,
+    pub staged_script: Option<Option<Arc<crate::domain::scripting::RulesScript>>>,
+
+    // Id of the last event broadcast to the whole lobby (clients dedupe and detect gaps with it)
+    pub last_event_id: u64,
 
     // Delta tracking for efficient state sync
-    pub dirty_players: SmallPlayerVec, // Players with state changes
-    pub last_sync_state: HashMap<u32, PlayerSyncState>,
+    pub dirty_players: SmallPlayerVec, // Players with a non-empty change mask
+
+    // Events raised while processing commands, flushed with the delta sync
+    pub pending_events: SmallEventVec,
 }
 
 impl Lobby {
     pub fn new(code: LobbyCode, max_players: u32, scene: String) -> Self {
+        Self::with_settings(code, max_players, scene, LobbySettings::default())
+    }
+
+    pub fn with_settings(code: LobbyCode, max_players: u32, scene: String, settings: LobbySettings) -> Self {
+        let rng = crate::utils::rng::SeededRng::for_lobby(&code);
+        let scene_data = scenes::scene_data(&scene);
         Self {
             code,
             players: HashMap::new(),
             client_addresses: HashMap::new(),
+            departed: HashMap::new(),
+            presence: Default::default(),
+            rebinds: Default::default(),
             max_players,
+            pickups: crate::domain::pickups::PickupSet::for_scene(&scene_data),
+            scene_data,
             scene,
+            settings,
+            owner_id: None,
+            paused_at: None,
+            phase: MatchPhase::Waiting,
+            countdown_remaining: 0.0,
+            active_vote: None,
+            analytics: Default::default(),
+            position_history: Default::default(),
+            hit_streaks: HashMap::new(),
+            projectiles: Default::default(),
+            loot: Default::default(),
+            rng,
+            evicted_bots: Vec::new(),
+            transfers_in: Vec::new(),
+            transfers_out: Vec::new(),
+            emotes: Default::default(),
+            chunks: Default::default(),
+            timers: Default::default(),
+            observers: Default::default(),
+            teams: Default::default(),
+            damage_attribution: Default::default(),
+            connectivity: Default::default(),
+            zone: Default::default(),
+            duel: Default::default(),
+            lifetime: crate::domain::lifetime::Lifetime::new(clock::now()),
+            current_tick: 0,
+            tick_interval_ms: 20,
+            heartbeat_interval_ms: 2000,
+            clock_stats: HashMap::new(),
+            timeline: None,
+            rules_script: None,
+            staged_script: None,
+            last_event_id: 0,
             dirty_players: SmallPlayerVec::new(),
-            last_sync_state: HashMap::new(),
+            pending_events: SmallEventVec::new(),
         }
     }
 
@@ -126,16 +564,65 @@ impl Lobby {
         Player::new_player(id, name, current_weapon_id, ammo)
     }
 
-    /// Mark a player as dirty (state changed)
+    /// Id for the next broadcast to the whole lobby
+    pub fn next_event_id(&mut self) -> u64 {
+        self.last_event_id += 1;
+        self.last_event_id
+    }
+
+    /// Mark a player as dirty - every synced field is resent
     pub fn mark_dirty(&mut self, player_id: u32) {
+        if let Some(player) = self.players.get_mut(&player_id) {
+            player.changes = ChangeMask::ALL;
+        }
         if !self.dirty_players.contains(&player_id) {
             self.dirty_players.push(player_id);
         }
     }
 
+    /// Record that specific synced fields of a player changed
+    pub fn mark_changed(&mut self, player_id: u32, mask: ChangeMask) {
+        if let Some(player) = self.players.get_mut(&player_id) {
+            // A clean mask doesn't mean unqueued: collecting consumes masks but keeps the list
+            let was_clean = player.changes.is_empty();
+            player.changes.insert(mask);
+            if was_clean && !self.dirty_players.contains(&player_id) {
+                self.dirty_players.push(player_id);
+            }
+        }
+    }
+
     /// Clear all dirty flags
     pub fn clear_dirty(&mut self) {
-        self.dirty_players.clear();
+        for player_id in self.dirty_players.drain(..) {
+            if let Some(player) = self.players.get_mut(&player_id) {
+                player.changes = ChangeMask::empty();
+            }
+        }
+    }
+
+    /// Check if a player may run owner commands
+    pub fn is_owner(&self, player_id: u32) -> bool {
+        self.owner_id == Some(player_id)
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused_at.is_some()
+    }
+
+    /// Combat only counts once the countdown has finished
+    pub fn is_match_live(&self) -> bool {
+        self.phase == MatchPhase::InProgress
+    }
+
+    /// Whether shots may be fired: a live match, and in duels only during a round
+    pub fn combat_allowed(&self) -> bool {
+        self.is_match_live() && (self.settings.game_mode != GameMode::Duel || self.duel.round_live)
+    }
+
+    /// Queue an event for broadcast at the end of the tick
+    pub fn push_event(&mut self, event: SyncEvent) {
+        self.pending_events.push(event);
     }
 }
 
@@ -174,6 +661,37 @@ mod tests {
             warned_at: None,
             is_dead: false,
             respawn_time: None,
+            vertical_velocity: 0.0,
+            fall_speed: 0.0,
+            last_position_time: None,
+            last_whisper_time: SystemTime::UNIX_EPOCH,
+            changes: ChangeMask::empty(),
+            trigger_held: false,
+            fire_target: None,
+            burst_remaining: 0,
+            overheal_decay: 0.0,
+            match_stats: MatchStats::default(),
+            rtt_ms: None,
+            ready: false,
+            spawn_protection_until: None,
+            anticheat_strikes: 0,
+            pending_loadout: None,
+            quaternion_rotation: false,
+            message_keys: false,
+            last_activity: SystemTime::now(),
+            afk_warned: false,
+            spectating: false,
+            command_last_used: HashMap::new(),
+            equip_end_time: None,
+            active_loadout: None,
+            preferences: Default::default(),
+            armor: 0,
+            region: None,
+            carried_weapon: None,
+            party_id: None,
+            heat: 0.0,
+            overheated_until: None,
+            client_id: None,
         };
 
         let sync = player.to_sync_state();
@@ -198,4 +716,50 @@ mod tests {
         lobby.clear_dirty();
         assert_eq!(lobby.dirty_players.len(), 0);
     }
+
+    #[test]
+    fn test_change_mask_tracking() {
+        let mut lobby = Lobby::new("TEST".to_string(), 4, "world".to_string());
+        lobby.players.insert(1, Player::new_player(1, "Test".to_string(), 1, 20));
+
+        lobby.mark_changed(1, ChangeMask::AMMO);
+        lobby.mark_changed(1, ChangeMask::HEALTH);
+        assert_eq!(lobby.dirty_players.len(), 1);
+
+        let changes = lobby.players.get(&1).unwrap().changes;
+        assert!(changes.contains(ChangeMask::AMMO | ChangeMask::HEALTH));
+        assert!(!changes.contains(ChangeMask::WEAPON));
+
+        // Unknown players are ignored
+        lobby.mark_changed(2, ChangeMask::AMMO);
+        assert_eq!(lobby.dirty_players.len(), 1);
+
+        // Changes after a collect in the same tick don't queue the player twice
+        lobby.players.get_mut(&1).unwrap().changes = ChangeMask::empty();
+        lobby.mark_changed(1, ChangeMask::HEALTH);
+        assert_eq!(lobby.dirty_players.len(), 1);
+
+        lobby.clear_dirty();
+        assert!(lobby.players.get(&1).unwrap().changes.is_empty());
+        assert!(lobby.dirty_players.is_empty());
+    }
+
+    #[test]
+    fn test_lobby_with_settings() {
+        let settings = LobbySettings { ranked: true, max_health: 150, ..Default::default() };
+        let lobby = Lobby::with_settings("RANKED".to_string(), 4, "world".to_string(), settings);
+        assert!(lobby.settings.ranked);
+        assert_eq!(lobby.settings.max_health, 150);
+        assert!(!Lobby::new("CASUAL".to_string(), 4, "world".to_string()).settings.ranked);
+    }
+
+    #[test]
+    fn test_match_stats_accuracy() {
+        let mut stats = MatchStats::default();
+        assert_eq!(stats.accuracy(), 0.0);
+
+        stats.shots_fired = 4;
+        stats.shots_hit = 3;
+        assert!((stats.accuracy() - 0.75).abs() < 0.001);
+    }
 }
@@ -2,4 +2,18 @@ pub mod lobby;
 pub mod commands;
 pub mod server_state;
 pub mod global_stats;
+pub mod bandwidth;
 
+pub mod stats_store;
+pub mod redis_backend;
+pub mod accounts;
+pub mod friends;
+pub mod loadouts;
+pub mod latency_probes;
+pub mod handshake;
+pub mod failure_limiter;
+pub mod player_ids;
+pub mod presence;
+pub mod announcements;
+pub mod chat_channels;
+pub mod tournaments;
@@ -0,0 +1,123 @@
+use std::sync::Mutex;
+
+/// Ids reserved from the stats store at a time
+pub const PLAYER_ID_BLOCK: u32 = 10_000;
+
+/// Id the dummy bot always uses; never handed to a player
+const DUMMY_BOT_ID: u32 = 999;
+
+#[derive(Debug)]
+struct Blocks {
+    next: u32,
+    end: Option<u32>, // First id past the current block; None = unbounded (no store)
+    spare: Option<(u32, u32)>, // Next reserved block as (start, end), taken over when the current runs out
+}
+
+/// Hands out player ids from blocks reserved in the stats store,
+/// so ids stay unique across restarts and between instances sharing a store
+///
+/// Without a store ids just count up from 1 and are only unique for this process.
+#[derive(Debug)]
+pub struct PlayerIds {
+    blocks: Mutex<Blocks>,
+}
+
+impl Default for PlayerIds {
+    fn default() -> Self {
+        Self { blocks: Mutex::new(Blocks { next: 1, end: None, spare: None }) }
+    }
+}
+
+impl PlayerIds {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Next unused player id
+    pub fn next(&self) -> u32 {
+        let mut blocks = self.blocks.lock().unwrap();
+        loop {
+            if blocks.end.is_some_and(|end| blocks.next >= end) {
+                match blocks.spare.take() {
+                    Some((start, end)) => {
+                        blocks.next = start;
+                        blocks.end = Some(end);
+                    }
+                    // Better a possible reuse than refusing players; the store is reserved ahead to avoid this
+                    None => {
+                        log::warn!("Player id block exhausted before the next was reserved; ids may repeat after a restart");
+                        blocks.end = None;
+                    }
+                }
+            }
+            let id = blocks.next;
+            blocks.next += 1;
+            if id != DUMMY_BOT_ID {
+                return id;
+            }
+        }
+    }
+
+    /// Whether another block should be reserved: none is waiting and the current one is half used
+    /// (or ids aren't backed by a store yet)
+    pub fn wants_block(&self) -> bool {
+        let blocks = self.blocks.lock().unwrap();
+        blocks.spare.is_none() && blocks.end.is_none_or(|end| end - blocks.next < PLAYER_ID_BLOCK / 2)
+    }
+
+    /// Never hand out `max_id` or anything below it, e.g. ids of players restored from a checkpoint
+    pub fn skip_past(&self, max_id: u32) {
+        let mut blocks = self.blocks.lock().unwrap();
+        blocks.next = blocks.next.max(max_id.saturating_add(1));
+    }
+
+    /// Add a block reserved from the store, starting at `start`
+    /// The first block replaces the unbounded process-local counter right away (keeping any skipped ids skipped)
+    pub fn add_block(&self, start: u32, count: u32) {
+        let mut blocks = self.blocks.lock().unwrap();
+        let end = start.saturating_add(count);
+        if blocks.end.is_none() {
+            blocks.next = start.max(blocks.next);
+            blocks.end = Some(end);
+        } else {
+            blocks.spare = Some((start, end));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ids_come_from_reserved_blocks() {
+        let ids = PlayerIds::new();
+        assert_eq!(ids.next(), 1);
+        assert!(ids.wants_block());
+
+        ids.add_block(990, 12);
+        assert!(ids.wants_block()); // Only 12 left
+        assert_eq!((0..9).map(|_| ids.next()).collect::<Vec<_>>(), vec![990, 991, 992, 993, 994, 995, 996, 997, 998]);
+        assert_eq!(ids.next(), 1000); // Skips the dummy bot
+
+        ids.add_block(50_000, PLAYER_ID_BLOCK);
+        assert!(!ids.wants_block());
+        assert_eq!(ids.next(), 1001);
+        assert_eq!(ids.next(), 50_000);
+    }
+
+    #[test]
+    fn test_skip_past_restored_ids() {
+        let ids = PlayerIds::new();
+        ids.skip_past(41);
+        assert_eq!(ids.next(), 42);
+        ids.skip_past(10); // Never moves back
+        assert_eq!(ids.next(), 43);
+
+        // A store block below the skipped ids is only used past them
+        let ids = PlayerIds::new();
+        ids.skip_past(500);
+        ids.add_block(1, PLAYER_ID_BLOCK);
+        assert_eq!(ids.next(), 501);
+    }
+}
@@ -0,0 +1,202 @@
+use crate::state::lobby::Lobby;
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::time::{Duration, SystemTime};
+
+/// How often an unconfirmed address is sent the `rebind` challenge again
+pub const REBIND_RESEND_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Latest UDP address and packet time of one client
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Presence {
+    pub addr: SocketAddr,
+    pub last_seen: SystemTime,
+}
+
+/// Client liveness reported straight from the UDP handler, without taking the lobby lock
+/// Many packets per tick collapse into one entry per player; the tick applies and clears them
+#[derive(Debug, Default)]
+pub struct PresenceTracker {
+    clients: DashMap<u32, Presence>,
+}
+
+impl PresenceTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a packet from a player (keepalive, position update, ...)
+    pub fn touch(&self, player_id: u32, addr: SocketAddr, now: SystemTime) {
+        self.clients.insert(player_id, Presence { addr, last_seen: now });
+    }
+
+    /// Players with reports not yet applied
+    pub fn len(&self) -> usize {
+        self.clients.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.clients.is_empty()
+    }
+
+    /// Copy reported addresses and activity into the lobby's players, then forget them
+    /// Reports for players not in the lobby (left, or join still queued) are dropped
+    /// Returns every report taken, by player id, for the replication stream to replay
+    pub fn apply(&self, lobby: &mut Lobby) -> Vec<(u32, Presence)> {
+        let mut reports = Vec::with_capacity(self.clients.len());
+        self.clients.retain(|player_id, presence| {
+            reports.push((*player_id, *presence));
+            if let Some(player) = lobby.players.get_mut(player_id) {
+                player.last_update = player.last_update.max(presence.last_seen);
+                let known = lobby.client_addresses.get(player_id).copied();
+                let pending = lobby.rebinds.pending_addr(*player_id);
+                // Compare against the address awaiting confirmation, if any, else the confirmed one
+                let moved = pending.or(known).is_some_and(|current| current != presence.addr);
+                if moved {
+                    // The old path is dead to us until the new one answers
+                    lobby.client_addresses.remove(player_id);
+                    lobby.rebinds.begin(*player_id, presence.addr);
+                } else if known.is_none() && pending.is_none() {
+                    lobby.client_addresses.insert(*player_id, presence.addr);
+                }
+            }
+            false
+        });
+        reports.sort_unstable_by_key(|(player_id, _)| *player_id);
+        reports
+    }
+}
+
+/// Address a player moved to, waiting for the client to confirm it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PendingRebind {
+    pub addr: SocketAddr,
+    pub generation: u64,
+    pub last_sent: Option<SystemTime>,
+}
+
+/// Per-player address generations
+///
+/// Every address change bumps the player's generation and takes them out of
+/// `client_addresses`, so nothing more goes to the old path. The new address
+/// only gets traffic again once the client echoes the generation in `rebind_ack`.
+#[derive(Debug, Default)]
+pub struct AddressRebinds {
+    generations: HashMap<u32, u64>,
+    pending: HashMap<u32, PendingRebind>,
+}
+
+impl AddressRebinds {
+    /// Current address generation of a player (0 until their address first changes)
+    pub fn generation(&self, player_id: u32) -> u64 {
+        self.generations.get(&player_id).copied().unwrap_or(0)
+    }
+
+    pub fn pending_addr(&self, player_id: u32) -> Option<SocketAddr> {
+        self.pending.get(&player_id).map(|pending| pending.addr)
+    }
+
+    /// Start a new generation for a player seen at a new address
+    pub fn begin(&mut self, player_id: u32, addr: SocketAddr) -> u64 {
+        let generation = self.generations.entry(player_id).or_insert(0);
+        *generation += 1;
+        self.pending.insert(player_id, PendingRebind { addr, generation: *generation, last_sent: None });
+        *generation
+    }
+
+    /// Challenges to send now as (player_id, addr, generation); each is marked sent
+    pub fn due(&mut self, now: SystemTime) -> Vec<(u32, SocketAddr, u64)> {
+        let mut due: Vec<_> = self.pending.iter_mut()
+            .filter(|(_, pending)| pending.last_sent.is_none_or(|sent| {
+                now.duration_since(sent).is_ok_and(|elapsed| elapsed >= REBIND_RESEND_INTERVAL)
+            }))
+            .map(|(player_id, pending)| {
+                pending.last_sent = Some(now);
+                (*player_id, pending.addr, pending.generation)
+            })
+            .collect();
+        due.sort_by_key(|(player_id, _, _)| *player_id);
+        due
+    }
+
+    /// Accept an ack if it comes from the pending address for the current generation
+    pub fn confirm(&mut self, player_id: u32, addr: SocketAddr, generation: u64) -> bool {
+        let matches = self.pending.get(&player_id)
+            .is_some_and(|pending| pending.addr == addr && pending.generation == generation);
+        if matches {
+            self.pending.remove(&player_id);
+        }
+        matches
+    }
+
+    /// Drop a pending rebind (the client proved the address another way, e.g. a fresh UDP connect)
+    pub fn settle(&mut self, player_id: u32) {
+        self.pending.remove(&player_id);
+    }
+
+    pub fn forget(&mut self, player_id: u32) {
+        self.generations.remove(&player_id);
+        self.pending.remove(&player_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_reports_coalesce_and_apply_once() {
+        let mut lobby = Lobby::new("PRES".to_string(), 4, "world".to_string());
+        lobby.players.insert(1, Lobby::new_player(1, "Alice".to_string(), 1, 20));
+        let joined = lobby.players[&1].last_update;
+        let tracker = PresenceTracker::new();
+
+        let later = joined + Duration::from_secs(5);
+        tracker.touch(1, "127.0.0.1:7001".parse().unwrap(), later - Duration::from_secs(1));
+        tracker.touch(1, "127.0.0.1:7002".parse().unwrap(), later);
+        tracker.touch(9, "127.0.0.1:7009".parse().unwrap(), later);
+        assert_eq!(tracker.len(), 2);
+
+        tracker.apply(&mut lobby);
+        assert!(tracker.is_empty());
+        assert_eq!(lobby.players[&1].last_update, later);
+        assert_eq!(lobby.client_addresses[&1].port(), 7002);
+        assert!(!lobby.client_addresses.contains_key(&9));
+    }
+
+    #[test]
+    fn test_address_change_waits_for_rebind_ack() {
+        let mut lobby = Lobby::new("PRES".to_string(), 4, "world".to_string());
+        lobby.players.insert(1, Lobby::new_player(1, "Alice".to_string(), 1, 20));
+        let old: SocketAddr = "127.0.0.1:7001".parse().unwrap();
+        let new: SocketAddr = "127.0.0.1:7002".parse().unwrap();
+        lobby.client_addresses.insert(1, old);
+        let tracker = PresenceTracker::new();
+        let now = SystemTime::now();
+
+        tracker.touch(1, new, now);
+        tracker.apply(&mut lobby);
+        assert!(!lobby.client_addresses.contains_key(&1));
+        assert_eq!(lobby.rebinds.generation(1), 1);
+
+        // Challenge goes out once, then again after the resend interval
+        assert_eq!(lobby.rebinds.due(now), vec![(1, new, 1)]);
+        assert!(lobby.rebinds.due(now).is_empty());
+        assert_eq!(lobby.rebinds.due(now + REBIND_RESEND_INTERVAL), vec![(1, new, 1)]);
+
+        // Stale generations and other addresses don't confirm
+        assert!(!lobby.rebinds.confirm(1, new, 0));
+        assert!(!lobby.rebinds.confirm(1, old, 1));
+
+        // Moving again before the ack starts another generation
+        let newer: SocketAddr = "127.0.0.1:7003".parse().unwrap();
+        tracker.touch(1, newer, now);
+        tracker.apply(&mut lobby);
+        assert!(!lobby.rebinds.confirm(1, new, 1));
+        assert!(lobby.rebinds.confirm(1, newer, 2));
+        assert!(lobby.rebinds.due(now).is_empty());
+    }
+}
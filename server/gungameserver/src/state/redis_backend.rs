@@ -0,0 +1,414 @@
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime};
+use redis::aio::{ConnectionManager, ConnectionManagerConfig};
+use redis::AsyncCommands;
+use tokio::sync::OnceCell;
+use crate::state::global_stats::{GlobalPlayerStats, StatsChange};
+use crate::state::stats_store::{BackendFuture, StatsBackend};
+
+/// Set of every stored player id
+const PLAYERS_KEY: &str = "gungame:players";
+
+/// Per-player hash (`gungame:stats:<id>`): counters, rating, name and creation time
+const STATS_PREFIX: &str = "gungame:stats:";
+
+/// Highest killstreak and latest visit per player; `ZADD GT` keeps the largest any instance sent
+const BEST_KILLSTREAK_KEY: &str = "gungame:best_killstreak";
+const LAST_SEEN_KEY: &str = "gungame:last_seen";
+
+/// Last player id reserved by any instance
+const PLAYER_ID_KEY: &str = "gungame:player_ids";
+
+/// Reconnect attempts before a command gives up
+const MAX_ATTEMPTS: usize = 3;
+
+/// Limit on connecting, and on each reply
+const IO_TIMEOUT: Duration = Duration::from_secs(2);
+
+type Counter = (&'static str, fn(&GlobalPlayerStats) -> u32);
+
+/// Counters flushed with `HINCRBY`, so instances sharing the store add up rather than overwrite
+const COUNTERS: [Counter; 9] = [
+    ("total_kills", |s| s.total_kills),
+    ("total_deaths", |s| s.total_deaths),
+    ("total_score", |s| s.total_score),
+    ("games_played", |s| s.games_played),
+    ("ranked_games", |s| s.ranked_games),
+    ("total_shots_fired", |s| s.total_shots_fired),
+    ("total_shots_hit", |s| s.total_shots_hit),
+    ("total_damage_dealt", |s| s.total_damage_dealt),
+    ("total_damage_taken", |s| s.total_damage_taken),
+];
+
+fn stats_key(player_id: u32) -> String {
+    format!("{}{}", STATS_PREFIX, player_id)
+}
+
+fn unix_secs(time: SystemTime) -> u64 {
+    time.duration_since(SystemTime::UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_secs())
+}
+
+fn from_unix_secs(secs: f64) -> SystemTime {
+    SystemTime::UNIX_EPOCH + Duration::from_secs(secs.max(0.0) as u64)
+}
+
+fn redis_error(action: &'static str) -> impl Fn(redis::RedisError) -> &'static str {
+    move |e| {
+        log::warn!("Redis {} failed: {}", action, e);
+        "Redis command failed"
+    }
+}
+
+/// Rebuild a player from their hash and sorted set scores
+fn parse_player(player_id: u32, fields: &HashMap<String, String>, best_killstreak: Option<f64>, last_seen: Option<f64>) -> GlobalPlayerStats {
+    let mut stats = GlobalPlayerStats::new(player_id, fields.get("name").cloned().unwrap_or_default());
+    let counter = |field: &str| fields.get(field).and_then(|v| v.parse::<i64>().ok()).unwrap_or(0).clamp(0, u32::MAX as i64) as u32;
+    stats.total_kills = counter("total_kills");
+    stats.total_deaths = counter("total_deaths");
+    stats.total_score = counter("total_score");
+    stats.games_played = counter("games_played");
+    stats.ranked_games = counter("ranked_games");
+    stats.total_shots_fired = counter("total_shots_fired");
+    stats.total_shots_hit = counter("total_shots_hit");
+    stats.total_damage_dealt = counter("total_damage_dealt");
+    stats.total_damage_taken = counter("total_damage_taken");
+    if let Some(rating) = fields.get("rating").and_then(|v| v.parse::<f32>().ok()) {
+        stats.rating = rating.max(0.0);
+    }
+    if let Some(created_at) = fields.get("created_at").and_then(|v| v.parse::<f64>().ok()) {
+        stats.created_at = from_unix_secs(created_at);
+    }
+    stats.best_killstreak = best_killstreak.unwrap_or(0.0).max(0.0) as u32;
+    if let Some(last_seen) = last_seen {
+        stats.last_seen = from_unix_secs(last_seen);
+    }
+    stats
+}
+
+/// Stats shared by every server instance pointed at the same Redis
+///
+/// The URL carries credentials and database (`redis://:password@host:6379/2`);
+/// `rediss://` connects over TLS. The connection reconnects on its own after failures.
+pub struct RedisBackend {
+    client: redis::Client,
+    connection: OnceCell<ConnectionManager>,
+}
+
+impl RedisBackend {
+    pub fn open(url: &str) -> Result<Self, &'static str> {
+        let client = redis::Client::open(url).map_err(|e| {
+            log::warn!("Invalid Redis URL: {}", e);
+            "Invalid Redis URL"
+        })?;
+        Ok(Self { client, connection: OnceCell::new() })
+    }
+
+    /// The shared connection, opened on first use
+    async fn connection(&self) -> Result<ConnectionManager, &'static str> {
+        let manager = self.connection.get_or_try_init(|| async {
+            let config = ConnectionManagerConfig::new()
+                .set_number_of_retries(MAX_ATTEMPTS)
+                .set_connection_timeout(IO_TIMEOUT)
+                .set_response_timeout(IO_TIMEOUT);
+            ConnectionManager::new_with_config(self.client.clone(), config).await
+        }).await;
+        manager.cloned().map_err(|e| {
+            log::warn!("Redis unavailable: {}", e);
+            "Redis unavailable"
+        })
+    }
+
+    async fn load(&self) -> Result<Vec<GlobalPlayerStats>, &'static str> {
+        let mut conn = self.connection().await?;
+        let ids: Vec<u32> = conn.smembers(PLAYERS_KEY).await.map_err(redis_error("load"))?;
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut hashes = redis::pipe();
+        let mut best = redis::pipe();
+        let mut seen = redis::pipe();
+        for player_id in &ids {
+            hashes.hgetall(stats_key(*player_id));
+            best.zscore(BEST_KILLSTREAK_KEY, *player_id);
+            seen.zscore(LAST_SEEN_KEY, *player_id);
+        }
+        let fields: Vec<HashMap<String, String>> = hashes.query_async(&mut conn).await.map_err(redis_error("load"))?;
+        let best: Vec<Option<f64>> = best.query_async(&mut conn).await.map_err(redis_error("load"))?;
+        let seen: Vec<Option<f64>> = seen.query_async(&mut conn).await.map_err(redis_error("load"))?;
+        Ok(ids.iter().enumerate()
+            .map(|(i, player_id)| parse_player(*player_id, &fields[i], best[i], seen[i]))
+            .collect())
+    }
+
+    /// One transaction adding each change's difference from what the store had
+    async fn store(&self, changes: Vec<StatsChange>) -> Result<(), &'static str> {
+        if changes.is_empty() {
+            return Ok(());
+        }
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+        for StatsChange { stats, stored } in &changes {
+            let key = stats_key(stats.player_id);
+            pipe.sadd(PLAYERS_KEY, stats.player_id).ignore();
+            for (field, value) in COUNTERS {
+                let delta = i64::from(value(stats)) - stored.as_ref().map_or(0, |s| i64::from(value(s)));
+                if delta != 0 {
+                    pipe.hincr(&key, field, delta).ignore();
+                }
+            }
+            let rating_delta = stats.rating - stored.as_ref().map_or(0.0, |s| s.rating);
+            if rating_delta != 0.0 {
+                pipe.cmd("HINCRBYFLOAT").arg(&key).arg("rating").arg(rating_delta).ignore();
+            }
+            pipe.hset(&key, "name", &stats.name).ignore();
+            pipe.hset_nx(&key, "created_at", unix_secs(stats.created_at)).ignore();
+            pipe.cmd("ZADD").arg(BEST_KILLSTREAK_KEY).arg("GT").arg(stats.best_killstreak).arg(stats.player_id).ignore();
+            pipe.cmd("ZADD").arg(LAST_SEEN_KEY).arg("GT").arg(unix_secs(stats.last_seen)).arg(stats.player_id).ignore();
+        }
+        let mut conn = self.connection().await?;
+        pipe.query_async::<()>(&mut conn).await.map_err(redis_error("save"))
+    }
+
+    /// INCRBY is atomic, so instances sharing the store never get overlapping blocks
+    /// (a retried command may skip a block; gaps are harmless)
+    async fn reserve_ids(&self, count: u32) -> Result<u32, &'static str> {
+        let mut conn = self.connection().await?;
+        let last: i64 = conn.incr(PLAYER_ID_KEY, count).await.map_err(redis_error("player id reservation"))?;
+        u32::try_from(last - i64::from(count) + 1).map_err(|_| "Player ids exhausted")
+    }
+}
+
+impl StatsBackend for RedisBackend {
+    fn load_all(&self) -> BackendFuture<'_, Vec<GlobalPlayerStats>> {
+        Box::pin(self.load())
+    }
+
+    fn save(&self, changes: Vec<StatsChange>) -> BackendFuture<'_, ()> {
+        Box::pin(self.store(changes))
+    }
+
+    fn reserve_player_ids(&self, count: u32) -> BackendFuture<'_, u32> {
+        Box::pin(self.reserve_ids(count))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeSet;
+    use std::sync::Arc;
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::{TcpListener, TcpStream};
+    use tokio::sync::Mutex;
+    use crate::state::global_stats::GlobalStats;
+    use crate::state::stats_store::StatsSync;
+
+    /// Enough of a Redis server for this backend: strings, hashes, sets, sorted sets and MULTI/EXEC
+    #[derive(Default)]
+    struct FakeRedis {
+        strings: HashMap<Vec<u8>, i64>,
+        hashes: HashMap<Vec<u8>, HashMap<Vec<u8>, Vec<u8>>>,
+        sets: HashMap<Vec<u8>, BTreeSet<Vec<u8>>>,
+        zsets: HashMap<Vec<u8>, HashMap<Vec<u8>, f64>>,
+        /// Commands seen before any other, per connection (AUTH, SELECT)
+        handshakes: Vec<Vec<u8>>,
+    }
+
+    fn bulk(data: &[u8]) -> Vec<u8> {
+        let mut out = format!("${}\r\n", data.len()).into_bytes();
+        out.extend_from_slice(data);
+        out.extend_from_slice(b"\r\n");
+        out
+    }
+
+    fn array(items: Vec<Vec<u8>>) -> Vec<u8> {
+        let mut out = format!("*{}\r\n", items.len()).into_bytes();
+        items.into_iter().for_each(|item| out.extend(item));
+        out
+    }
+
+    fn text(arg: &[u8]) -> String {
+        String::from_utf8_lossy(arg).into_owned()
+    }
+
+    impl FakeRedis {
+        fn run(&mut self, args: &[Vec<u8>]) -> Vec<u8> {
+            let name = text(&args[0]).to_uppercase();
+            match name.as_str() {
+                "AUTH" | "SELECT" => {
+                    self.handshakes.push(args.concat());
+                    b"+OK\r\n".to_vec()
+                }
+                "SADD" => {
+                    let added = self.sets.entry(args[1].clone()).or_default().insert(args[2].clone());
+                    format!(":{}\r\n", added as u8).into_bytes()
+                }
+                "SMEMBERS" => array(self.sets.get(&args[1]).map(|s| s.iter().map(|m| bulk(m)).collect()).unwrap_or_default()),
+                "HSET" => {
+                    self.hashes.entry(args[1].clone()).or_default().insert(args[2].clone(), args[3].clone());
+                    b":1\r\n".to_vec()
+                }
+                "HSETNX" => {
+                    let hash = self.hashes.entry(args[1].clone()).or_default();
+                    let set = !hash.contains_key(&args[2]);
+                    if set {
+                        hash.insert(args[2].clone(), args[3].clone());
+                    }
+                    format!(":{}\r\n", set as u8).into_bytes()
+                }
+                "HINCRBY" | "HINCRBYFLOAT" => {
+                    let hash = self.hashes.entry(args[1].clone()).or_default();
+                    let current: f64 = hash.get(&args[2]).map_or(0.0, |v| text(v).parse().unwrap());
+                    let value = current + text(&args[3]).parse::<f64>().unwrap();
+                    let value = if name == "HINCRBY" { (value as i64).to_string() } else { value.to_string() };
+                    hash.insert(args[2].clone(), value.clone().into_bytes());
+                    if name == "HINCRBY" { format!(":{}\r\n", value).into_bytes() } else { bulk(value.as_bytes()) }
+                }
+                "HGETALL" => array(self.hashes.get(&args[1])
+                    .map(|h| h.iter().flat_map(|(k, v)| [bulk(k), bulk(v)]).collect())
+                    .unwrap_or_default()),
+                "ZADD" => {
+                    // ZADD key GT score member
+                    let score: f64 = text(&args[3]).parse().unwrap();
+                    let zset = self.zsets.entry(args[1].clone()).or_default();
+                    let current = zset.entry(args[4].clone()).or_insert(score);
+                    *current = current.max(score);
+                    b":1\r\n".to_vec()
+                }
+                "ZSCORE" => match self.zsets.get(&args[1]).and_then(|z| z.get(&args[2])) {
+                    Some(score) => bulk(score.to_string().as_bytes()),
+                    None => b"$-1\r\n".to_vec(),
+                },
+                "INCRBY" => {
+                    let counter = self.strings.entry(args[1].clone()).or_insert(0);
+                    *counter += text(&args[2]).parse::<i64>().unwrap();
+                    format!(":{}\r\n", counter).into_bytes()
+                }
+                _ => b"-ERR unknown command\r\n".to_vec(),
+            }
+        }
+    }
+
+    /// One command from the client (RESP arrays of bulk strings)
+    async fn read_command(conn: &mut BufReader<TcpStream>) -> Option<Vec<Vec<u8>>> {
+        let mut line = String::new();
+        conn.read_line(&mut line).await.ok().filter(|n| *n > 0)?;
+        let count: usize = line.trim_end().strip_prefix('*')?.parse().ok()?;
+        let mut args = Vec::with_capacity(count);
+        for _ in 0..count {
+            line.clear();
+            conn.read_line(&mut line).await.ok()?;
+            let len: usize = line.trim_end().strip_prefix('$')?.parse().ok()?;
+            let mut data = vec![0u8; len + 2];
+            conn.read_exact(&mut data).await.ok()?;
+            data.truncate(len);
+            args.push(data);
+        }
+        Some(args)
+    }
+
+    /// Serve `store` over TCP; the first connection is dropped unanswered to exercise reconnects
+    async fn fake_redis(store: Arc<Mutex<FakeRedis>>) -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let (first, _) = listener.accept().await.unwrap();
+            drop(first);
+            loop {
+                let (stream, _) = listener.accept().await.unwrap();
+                let store = store.clone();
+                tokio::spawn(async move {
+                    let mut conn = BufReader::new(stream);
+                    let mut queued: Option<Vec<Vec<Vec<u8>>>> = None;
+                    while let Some(args) = read_command(&mut conn).await {
+                        let reply = match (text(&args[0]).to_uppercase().as_str(), queued.as_mut()) {
+                            ("MULTI", _) => {
+                                queued = Some(Vec::new());
+                                b"+OK\r\n".to_vec()
+                            }
+                            ("EXEC", Some(_)) => {
+                                let mut store = store.lock().await;
+                                array(queued.take().unwrap().iter().map(|cmd| store.run(cmd)).collect())
+                            }
+                            (_, Some(queue)) => {
+                                queue.push(args);
+                                b"+QUEUED\r\n".to_vec()
+                            }
+                            (_, None) => store.lock().await.run(&args),
+                        };
+                        if conn.get_mut().write_all(&reply).await.is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+        });
+        port
+    }
+
+    #[tokio::test]
+    async fn test_round_trip_with_auth_and_reconnect() {
+        let store = Arc::new(Mutex::new(FakeRedis::default()));
+        let port = fake_redis(store.clone()).await;
+        let backend = RedisBackend::open(&format!("redis://:secret@127.0.0.1:{}/3", port)).unwrap();
+        let mut stats = GlobalPlayerStats::new(1, "P1".to_string());
+        stats.total_kills = 4;
+        stats.best_killstreak = 3;
+        let changes = vec![stats, GlobalPlayerStats::new(2, "P2".to_string())].into_iter()
+            .map(|stats| StatsChange { stats, stored: None })
+            .collect();
+        backend.save(changes).await.unwrap();
+
+        let mut loaded = backend.load_all().await.unwrap();
+        loaded.sort_by_key(|s| s.player_id);
+        assert_eq!(loaded.len(), 2);
+        assert_eq!(loaded[0].total_kills, 4);
+        assert_eq!(loaded[0].best_killstreak, 3);
+        assert_eq!(loaded[1].name, "P2");
+        assert!((loaded[1].rating - GlobalPlayerStats::new(2, String::new()).rating).abs() < 0.001);
+        let handshakes = store.lock().await.handshakes.clone();
+        assert!(handshakes.contains(&b"AUTHsecret".to_vec()));
+        assert!(handshakes.contains(&b"SELECT3".to_vec()));
+    }
+
+    #[tokio::test]
+    async fn test_instances_add_up_instead_of_overwriting() {
+        let port = fake_redis(Arc::new(Mutex::new(FakeRedis::default()))).await;
+        let url = format!("redis://127.0.0.1:{}", port);
+        let instance = || {
+            let stats = Arc::new(GlobalStats::new());
+            (stats.clone(), StatsSync::new(stats, Arc::new(RedisBackend::open(&url).unwrap())))
+        };
+        let (first_stats, first) = instance();
+        let (second_stats, second) = instance();
+
+        first_stats.record_session(1, "P1", 2, 0, 100);
+        first.flush().await.unwrap();
+        second.refresh().await.unwrap();
+        // The player plays on both servers before either flushes again
+        first_stats.record_session(1, "P1", 1, 1, 50);
+        second_stats.record_session(1, "P1", 3, 0, 150);
+        first.flush().await.unwrap();
+        second.flush().await.unwrap();
+
+        first.refresh().await.unwrap();
+        let merged = first_stats.get_stats(1).unwrap();
+        assert_eq!(merged.total_kills, 6);
+        assert_eq!(merged.total_deaths, 1);
+        assert_eq!(merged.total_score, 300);
+        assert_eq!(merged.games_played, 3);
+    }
+
+    #[tokio::test]
+    async fn test_player_id_blocks_do_not_overlap() {
+        let port = fake_redis(Arc::new(Mutex::new(FakeRedis::default()))).await;
+        let backend = RedisBackend::open(&format!("redis://127.0.0.1:{}", port)).unwrap();
+        assert_eq!(backend.reserve_player_ids(100).await, Ok(1));
+        assert_eq!(backend.reserve_player_ids(100).await, Ok(101));
+    }
+
+    #[test]
+    fn test_bad_url_is_refused() {
+        assert_eq!(RedisBackend::open("redis://:bad port/").err(), Some("Invalid Redis URL"));
+    }
+}
@@ -1,46 +1,173 @@
-use dashmap::DashMap;
-use std::sync::Arc;
-use std::sync::atomic::{AtomicU32, Ordering};
+use arc_swap::ArcSwap;
+use dashmap::{DashMap, DashSet};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
 use tokio::sync::{RwLock, mpsc};
 use tokio::task::JoinHandle;
-use crate::state::lobby::{Lobby, LobbyCode};
+use crate::state::lobby::{Lobby, LobbyCode, LobbySummary};
 use crate::state::global_stats::GlobalStats;
+use crate::state::announcements::AnnouncementSchedule;
+use crate::state::bandwidth::BandwidthTracker;
+use crate::state::chat_channels::ChatChannels;
+use crate::state::accounts::PlayerAccounts;
+use crate::state::friends::FriendLists;
+use crate::state::loadouts::LoadoutStore;
+use crate::state::latency_probes::{LatencyProbes, PingEchoes};
+use crate::state::handshake::HandshakeCookies;
+use crate::state::failure_limiter::FailureLimiter;
+use crate::state::player_ids::PlayerIds;
+use crate::state::presence::PresenceTracker;
+use crate::state::tournaments::Tournaments;
+use crate::tick::replication::Replicator;
+use crate::utils::geoip::GeoIp;
+use crate::utils::identity::ServerIdentity;
+use crate::domain::bots;
+use crate::domain::timeline::{MatchTimeline, MAX_FINISHED_TIMELINES};
 
-/// Maximum allowed lobby code length
-const MAX_LOBBY_CODE_LENGTH: usize = 32;
+/// Lock shards of the lobby map (power of two); more than the default so creates and lookups rarely contend
+pub const LOBBY_SHARDS: usize = 64;
+
+/// Allowed lobby code lengths
+const MIN_LOBBY_CODE_LENGTH: usize = 4;
+const MAX_LOBBY_CODE_LENGTH: usize = 16;
+
+/// Refused or unknown lobby codes one address may try before it is turned away for the rest of the window
+pub const MAX_LOBBY_CODE_FAILURES: u32 = 20;
+
+/// Window lobby code failures are counted over
+pub const LOBBY_CODE_FAILURE_WINDOW: Duration = Duration::from_secs(60);
 
 /// Maximum allowed player name length
 const MAX_PLAYER_NAME_LENGTH: usize = 64;
 
+/// Index entries younger than this are left alone by the consistency sweep
+/// (a join may still be on its way through the lobby's command queue)
+pub const PLAYER_INDEX_TTL: Duration = Duration::from_secs(10);
+
+/// Error for joins and lobby creations refused while draining
+pub const DRAINING_ERROR: &str = "Server is draining for maintenance";
+
+/// Player -> lobby index entry
+#[derive(Debug, Clone)]
+pub struct PlayerIndexEntry {
+    pub lobby_code: LobbyCode,
+    pub registered_at: Instant,
+}
+
+/// Outcome of a player index consistency sweep
+#[derive(Debug, Default, PartialEq)]
+pub struct IndexRepair {
+    pub orphans_removed: usize, // Entries for players no longer in their lobby
+    pub entries_fixed: usize,   // Members missing from the index or pointing at the wrong lobby
+}
+
 /// Handle to a lobby with its command queue and tick task
 pub struct LobbyHandle {
     pub lobby: Arc<RwLock<Lobby>>,
     pub command_tx: mpsc::Sender<crate::state::commands::LobbyCommand>,
     pub task_handle: JoinHandle<()>,
+    pub summary: ArcSwap<LobbySummary>, // Latest listing snapshot, swapped in by the tick loop
+    pub presence: Arc<PresenceTracker>, // Same tracker as the lobby's, so UDP packets skip the lock
 }
 
 /// Server state partitioned by lobby
-/// Uses DashMap for concurrent access without global locks
 pub struct ServerState {
     lobbies: DashMap<LobbyCode, LobbyHandle>,
-    next_player_id: AtomicU32,
+    pub player_ids: Arc<PlayerIds>, // Backed by blocks reserved in the stats store when one is configured
     pub global_stats: Arc<GlobalStats>,
-    pub player_lobby_index: DashMap<u32, LobbyCode>,  // Player ID -> Lobby Code index for O(1) lookup
+    pub bandwidth: Arc<BandwidthTracker>, // Per-player traffic on the shared UDP socket
+    pub accounts: PlayerAccounts, // Account each player id joined with, for player-scoped routes
+    pub friends: FriendLists,
+    pub loadouts: LoadoutStore, // Saved loadout presets per account
+    pub latency_probes: LatencyProbes, // UDP joins waiting on an RTT measurement
+    pub ping_echoes: PingEchoes, // Pongs waiting to be echoed, timing connected players' RTT
+    pub handshake: HandshakeCookies, // Proof a UDP joiner owns its source address
+    pub code_failures: FailureLimiter, // Bad or unknown lobby codes per address, so private codes can't be guessed at speed
+    pub player_lobby_index: DashMap<u32, PlayerIndexEntry>,  // Player ID -> Lobby Code index for O(1) lookup
+    replicator: OnceLock<Replicator>, // Set when streaming to a hot standby (experimental)
+    identity: OnceLock<ServerIdentity>, // Server id and advertised address, set at startup
+    geoip: OnceLock<GeoIp>, // Locates joining addresses, when a database is configured
+    next_match_id: AtomicU64,
+    live_matches: DashMap<u64, LobbyCode>, // Match ID -> lobby playing it
+    finished_timelines: DashMap<u64, Arc<MatchTimeline>>,
+    draining: AtomicBool, // Set for maintenance: refuse newcomers, exit once lobbies empty
+    shutdown_deadline: Mutex<Option<Instant>>, // Exit at this point even if lobbies haven't emptied
+    vip_players: DashSet<String>, // Accounts that may take a lobby's reserved slots
+    pub announcements: AnnouncementSchedule, // Pending and repeating server-wide announcements
+    pub chat_channels: ChatChannels, // Opt-in global and region chat subscriptions
+    pub tournaments: Tournaments, // Brackets and the lobbies playing their matches
 }
 
 impl ServerState {
     pub fn new() -> Self {
         Self {
-            lobbies: DashMap::new(),
-            next_player_id: AtomicU32::new(1),
+            lobbies: DashMap::with_shard_amount(LOBBY_SHARDS),
+            player_ids: Arc::new(PlayerIds::new()),
             global_stats: Arc::new(GlobalStats::new()),
+            bandwidth: Arc::new(BandwidthTracker::new()),
+            accounts: PlayerAccounts::new(),
+            friends: FriendLists::new(),
+            loadouts: LoadoutStore::new(),
+            latency_probes: LatencyProbes::new(),
+            ping_echoes: PingEchoes::new(),
+            handshake: HandshakeCookies::new(),
+            code_failures: FailureLimiter::new(MAX_LOBBY_CODE_FAILURES, LOBBY_CODE_FAILURE_WINDOW),
             player_lobby_index: DashMap::new(),
+            replicator: OnceLock::new(),
+            identity: OnceLock::new(),
+            geoip: OnceLock::new(),
+            next_match_id: AtomicU64::new(1),
+            live_matches: DashMap::new(),
+            finished_timelines: DashMap::new(),
+            draining: AtomicBool::new(false),
+            shutdown_deadline: Mutex::new(None),
+            vip_players: DashSet::new(),
+            announcements: AnnouncementSchedule::new(),
+            chat_channels: ChatChannels::new(),
+            tournaments: Tournaments::new(),
         }
     }
 
+    /// Stream lobby state to a standby; set once before lobbies are created
+    pub fn set_replicator(&self, replicator: Replicator) -> Result<(), &'static str> {
+        self.replicator.set(replicator).map_err(|_| "Replicator already set")
+    }
+
+    /// Replication stream to the standby, if enabled
+    pub fn replicator(&self) -> Option<&Replicator> {
+        self.replicator.get()
+    }
+
+    /// Record the server's id and advertised address; set once at startup
+    pub fn set_identity(&self, identity: ServerIdentity) -> Result<(), &'static str> {
+        self.identity.set(identity).map_err(|_| "Identity already set")
+    }
+
+    /// Server id and advertised address, once established
+    pub fn identity(&self) -> Option<&ServerIdentity> {
+        self.identity.get()
+    }
+
+    /// Infer player regions from their addresses; set once at startup
+    pub fn set_geoip(&self, geoip: GeoIp) -> Result<(), &'static str> {
+        self.geoip.set(geoip).map_err(|_| "GeoIP database already set")
+    }
+
+    /// Region a client at `ip` likely plays from, when GeoIP is enabled
+    pub fn infer_region(&self, ip: std::net::IpAddr) -> Option<String> {
+        self.geoip.get()?.region(ip)
+    }
+
     /// Validate lobby code
     pub fn is_valid_lobby_code(code: &str) -> bool {
-        !code.is_empty() && code.len() <= MAX_LOBBY_CODE_LENGTH && code.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+        (MIN_LOBBY_CODE_LENGTH..=MAX_LOBBY_CODE_LENGTH).contains(&code.len()) && code.chars().all(|c| c.is_ascii_alphanumeric())
+    }
+
+    /// Lobby code as stored: validated and upper-cased, so "Test" and "TEST" are the same lobby
+    pub fn canonical_lobby_code(code: &str) -> Option<LobbyCode> {
+        Self::is_valid_lobby_code(code).then(|| code.to_ascii_uppercase())
     }
 
     /// Validate player name
@@ -50,17 +177,49 @@ impl ServerState {
 
     /// Register a player in the lobby index (call when player joins lobby)
     pub fn register_player_lobby(&self, player_id: u32, lobby_code: &str) {
-        self.player_lobby_index.insert(player_id, lobby_code.to_string());
+        self.player_lobby_index.insert(player_id, PlayerIndexEntry {
+            lobby_code: lobby_code.to_string(),
+            registered_at: Instant::now(),
+        });
     }
 
     /// Unregister a player from the lobby index (call when player leaves)
     pub fn unregister_player(&self, player_id: u32) {
         self.player_lobby_index.remove(&player_id);
+        self.chat_channels.forget(player_id);
+    }
+
+    /// Fan a channel message out to its subscribers, one delivery command per lobby
+    /// Returns how many subscribers it was queued for; full lobby queues drop their share
+    pub fn publish_channel_message(&self, channel: &str, from_id: u32, from_name: &str, text: &str) -> usize {
+        let mut by_lobby: HashMap<LobbyCode, Vec<u32>> = HashMap::new();
+        for player_id in self.chat_channels.subscribers(channel) {
+            if let Some(entry) = self.player_lobby_index.get(&player_id) {
+                by_lobby.entry(entry.lobby_code.clone()).or_default().push(player_id);
+            }
+        }
+        let mut queued = 0;
+        for (lobby_code, recipients) in by_lobby {
+            let Some(command_tx) = self.get_lobby_tx(&lobby_code) else { continue };
+            let count = recipients.len();
+            let cmd = crate::state::commands::LobbyCommand::ChannelDeliver {
+                channel: channel.to_string(),
+                from_id,
+                from_name: from_name.to_string(),
+                text: text.to_string(),
+                recipients,
+            };
+            match command_tx.try_send(cmd) {
+                Ok(()) => queued += count,
+                Err(e) => log::warn!("Dropped {} message for lobby {}: {}", channel, lobby_code, e),
+            }
+        }
+        queued
     }
 
     /// Find lobby code containing a specific player (O(1) lookup using index)
     pub async fn find_lobby_by_player(&self, player_id: u32) -> Option<String> {
-        self.player_lobby_index.get(&player_id).map(|entry| entry.value().clone())
+        self.player_lobby_index.get(&player_id).map(|entry| entry.lobby_code.clone())
     }
 
     /// Get command sender for a lobby (for UDP handlers)
@@ -71,6 +230,11 @@ impl ServerState {
     }
 
     /// Get lobby handle (for HTTP handlers)
+    /// Where the UDP handler records a lobby's client addresses and keepalives
+    pub fn get_lobby_presence(&self, lobby_code: &str) -> Option<Arc<PresenceTracker>> {
+        self.lobbies.get(lobby_code).map(|entry| entry.presence.clone())
+    }
+
     pub fn get_lobby(&self, lobby_code: &str) -> Option<Arc<RwLock<Lobby>>> {
         self.lobbies.get(lobby_code)
             .map(|entry| entry.lobby.clone())
@@ -81,9 +245,9 @@ impl ServerState {
         self.lobbies.contains_key(lobby_code)
     }
 
-    /// Generate next player ID (lock-free)
+    /// Generate next player ID
     pub fn next_player_id(&self) -> u32 {
-        self.next_player_id.fetch_add(1, Ordering::Relaxed)
+        self.player_ids.next()
     }
 
     /// Insert a new lobby handle
@@ -92,15 +256,173 @@ impl ServerState {
     }
 
     /// Remove a lobby (graceful shutdown)
+    /// Index entries pointing at the lobby are dropped with it
     pub fn remove_lobby(&self, lobby_code: &str) -> Option<LobbyHandle> {
-        self.lobbies.remove(lobby_code).map(|(_, handle)| handle)
+        let handle = self.lobbies.remove(lobby_code).map(|(_, handle)| handle);
+        self.player_lobby_index.retain(|_, entry| entry.lobby_code != lobby_code);
+        self.live_matches.retain(|_, code| code != lobby_code);
+        crate::utils::log_context::lobby_logs().remove(lobby_code);
+        handle
+    }
+
+    /// Allocate an ID for a match starting in a lobby
+    pub fn begin_match(&self, lobby_code: &str) -> u64 {
+        let match_id = self.next_match_id.fetch_add(1, Ordering::Relaxed);
+        self.live_matches.insert(match_id, lobby_code.to_string());
+        match_id
+    }
+
+    /// Keep a finished match's timeline for replay queries, evicting the oldest
+    pub fn finish_match(&self, timeline: MatchTimeline) {
+        self.live_matches.remove(&timeline.match_id);
+        self.finished_timelines.insert(timeline.match_id, Arc::new(timeline));
+        while self.finished_timelines.len() > MAX_FINISHED_TIMELINES {
+            let Some(oldest) = self.finished_timelines.iter().map(|entry| *entry.key()).min() else {
+                break;
+            };
+            self.finished_timelines.remove(&oldest);
+        }
+    }
+
+    /// Lobby currently playing a match
+    pub fn live_match_lobby(&self, match_id: u64) -> Option<LobbyCode> {
+        self.live_matches.get(&match_id).map(|code| code.clone())
+    }
+
+    /// Timeline of a finished match
+    pub fn finished_timeline(&self, match_id: u64) -> Option<Arc<MatchTimeline>> {
+        self.finished_timelines.get(&match_id).map(|timeline| timeline.clone())
+    }
+
+    /// Validate the player index against actual lobby membership and repair it
+    /// Entries younger than `ttl` are only trusted, never removed
+    pub async fn repair_player_index(&self, ttl: Duration) -> IndexRepair {
+        // Snapshot first so no map guard is held across the lobby locks
+        let lobbies: Vec<(LobbyCode, Arc<RwLock<Lobby>>)> = self.lobbies.iter()
+            .map(|entry| (entry.key().clone(), entry.lobby.clone()))
+            .collect();
+
+        let mut membership: HashMap<u32, LobbyCode> = HashMap::new();
+        for (code, lobby) in lobbies {
+            for player_id in lobby.read().await.players.keys() {
+                membership.insert(*player_id, code.clone());
+            }
+        }
+
+        let mut repair = IndexRepair::default();
+
+        // Stale or orphaned entries
+        self.player_lobby_index.retain(|player_id, entry| {
+            if membership.get(player_id) == Some(&entry.lobby_code) || entry.registered_at.elapsed() < ttl {
+                return true;
+            }
+            match membership.get(player_id) {
+                Some(actual) => {
+                    log::warn!("Player index mismatch: player {} indexed in {} but is in {}", player_id, entry.lobby_code, actual);
+                    entry.lobby_code = actual.clone();
+                    entry.registered_at = Instant::now();
+                    repair.entries_fixed += 1;
+                    true
+                }
+                None => {
+                    log::warn!("Player index orphan: player {} indexed in {} but in no lobby", player_id, entry.lobby_code);
+                    repair.orphans_removed += 1;
+                    false
+                }
+            }
+        });
+
+        // Members the index doesn't know about
+        for (player_id, code) in membership {
+            if !self.player_lobby_index.contains_key(&player_id) {
+                log::warn!("Player index missing: player {} is in {} but not indexed", player_id, code);
+                self.register_player_lobby(player_id, &code);
+                repair.entries_fixed += 1;
+            }
+        }
+
+        repair
     }
 
     /// Iterate over all lobbies (for cleanup tasks)
-    pub fn iter_lobbies(&self) -> dashmap::iter::Iter<'_, LobbyCode, LobbyHandle> {
+    pub fn iter_lobbies(&self) -> impl Iterator<Item = dashmap::mapref::multiple::RefMulti<'_, LobbyCode, LobbyHandle>> {
         self.lobbies.iter()
     }
 
+    /// Listing snapshots of every lobby (lock-free, may lag a few ticks)
+    pub fn lobby_summaries(&self) -> Vec<Arc<LobbySummary>> {
+        self.lobbies.iter().map(|entry| entry.summary.load_full()).collect()
+    }
+
+    /// Latest listing snapshot of one lobby
+    pub fn lobby_summary(&self, lobby_code: &str) -> Option<Arc<LobbySummary>> {
+        self.lobbies.get(lobby_code).map(|entry| entry.summary.load_full())
+    }
+
+    /// Replace a lobby's listing snapshot
+    pub fn publish_summary(&self, lobby_code: &str, summary: LobbySummary) {
+        if let Some(entry) = self.lobbies.get(lobby_code) {
+            entry.summary.store(Arc::new(summary));
+        }
+    }
+
+    /// Grant or revoke an account's VIP priority joins (reserved slots)
+    pub fn set_vip(&self, account: &str, vip: bool) {
+        if vip {
+            self.vip_players.insert(account.to_string());
+        } else {
+            self.vip_players.remove(account);
+        }
+    }
+
+    pub fn is_vip(&self, account: &str) -> bool {
+        self.vip_players.contains(account)
+    }
+
+    /// Whether a joined player's account is a VIP
+    pub fn is_vip_player(&self, player_id: u32) -> bool {
+        self.accounts.account_of(player_id).is_some_and(|account| self.is_vip(&account))
+    }
+
+    /// Enter drain mode; returns false if already draining
+    /// New joins and lobby creations are refused, running matches carry on
+    pub fn start_draining(&self) -> bool {
+        !self.draining.swap(true, Ordering::Relaxed)
+    }
+
+    pub fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::Relaxed)
+    }
+
+    /// Draining with no players left in any lobby (bots don't count)
+    pub fn is_drained(&self) -> bool {
+        self.is_draining() && self.lobby_summaries().iter()
+            .all(|summary| summary.players.iter().all(|(id, _)| *id == 999 || bots::is_bot(*id)))
+    }
+
+    /// Drain, then shut down once lobbies empty or `grace` runs out, whichever is first
+    /// A later request can only bring the deadline forward
+    pub fn schedule_shutdown(&self, grace: Duration) {
+        self.start_draining();
+        let deadline = Instant::now() + grace;
+        let mut scheduled = self.shutdown_deadline.lock().unwrap_or_else(|e| e.into_inner());
+        if scheduled.is_none_or(|current| deadline < current) {
+            *scheduled = Some(deadline);
+        }
+    }
+
+    /// When a scheduled shutdown stops waiting for lobbies to empty
+    pub fn shutdown_deadline(&self) -> Option<Instant> {
+        *self.shutdown_deadline.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Resolve once drain mode is on and every lobby has emptied, or a scheduled shutdown is due
+    pub async fn wait_drained(&self, poll: Duration) {
+        while !self.is_drained() && self.shutdown_deadline().is_none_or(|deadline| Instant::now() < deadline) {
+            tokio::time::sleep(poll).await;
+        }
+    }
+
     /// Get lobby handle by code
     pub fn get_lobby_handle(&self, lobby_code: &str) -> Option<std::sync::Arc<tokio::sync::RwLock<crate::state::lobby::Lobby>>> {
         self.lobbies.get(lobby_code)
@@ -123,6 +445,12 @@ impl ServerState {
     }
 }
 
+impl Default for ServerState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -148,12 +476,14 @@ mod tests {
     async fn test_lobby_handle_creation() {
         let lobby = Arc::new(RwLock::new(Lobby::new("TEST".to_string(), 4, "world".to_string())));
         let (tx, _rx) = mpsc::channel::<LobbyCommand>(100);
-        let handle = JoinHandle::from(tokio::spawn(async {}));
+        let handle = tokio::spawn(async {});
         
         let lobby_handle = LobbyHandle {
             lobby: lobby.clone(),
             command_tx: tx,
             task_handle: handle,
+            summary: ArcSwap::default(),
+            presence: Default::default(),
         };
         
         let state = ServerState::new();
@@ -163,16 +493,44 @@ mod tests {
         assert_eq!(state.lobby_count(), 1);
     }
 
+    #[tokio::test]
+    async fn test_many_lobbies_found_and_listed() {
+        let state = ServerState::new();
+        for i in 0..500 {
+            let code = format!("LOBBY{}", i);
+            let (tx, _rx) = mpsc::channel::<LobbyCommand>(1);
+            state.insert_lobby(code.clone(), LobbyHandle {
+                lobby: Arc::new(RwLock::new(Lobby::new(code.clone(), 4, "world".to_string()))),
+                command_tx: tx,
+                task_handle: tokio::spawn(async {}),
+                summary: ArcSwap::from_pointee(LobbySummary { code, ..Default::default() }),
+                presence: Default::default(),
+            });
+        }
+
+        assert_eq!(state.lobby_count(), 500);
+        assert!(state.lobby_exists("LOBBY42"));
+        assert_eq!(state.lobby_summary("LOBBY42").unwrap().code, "LOBBY42");
+        assert_eq!(state.iter_lobbies().count(), 500);
+        assert_eq!(state.lobby_summaries().len(), 500);
+
+        assert!(state.remove_lobby("LOBBY42").is_some());
+        assert!(!state.lobby_exists("LOBBY42"));
+        assert_eq!(state.lobby_count(), 499);
+    }
+
     #[tokio::test]
     async fn test_get_lobby_tx() {
         let lobby = Arc::new(RwLock::new(Lobby::new("TEST".to_string(), 4, "world".to_string())));
         let (tx, _rx) = mpsc::channel::<LobbyCommand>(100);
-        let handle = JoinHandle::from(tokio::spawn(async {}));
+        let handle = tokio::spawn(async {});
         
         let lobby_handle = LobbyHandle {
             lobby,
             command_tx: tx.clone(),
             task_handle: handle,
+            summary: ArcSwap::default(),
+            presence: Default::default(),
         };
         
         let state = ServerState::new();
@@ -183,16 +541,21 @@ mod tests {
         
         // Can send command
         let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080);
-        retrieved_tx.unwrap().send(LobbyCommand::Heartbeat { player_id: 1, addr }).await.unwrap();
+        retrieved_tx.unwrap().send(LobbyCommand::UdpConnect { player_id: 1, name: "Test".to_string(), addr, client_id: None }).await.unwrap();
     }
 
     #[test]
     fn test_valid_lobby_code() {
         assert!(ServerState::is_valid_lobby_code("TEST123"));
-        assert!(ServerState::is_valid_lobby_code("lobby-name_123"));
+        assert!(!ServerState::is_valid_lobby_code("lobby-name_123"));
         assert!(!ServerState::is_valid_lobby_code(""));
-        let long_code = "a".repeat(33);
+        assert!(!ServerState::is_valid_lobby_code("ABC"));
+        assert!(!ServerState::is_valid_lobby_code("ÄBCD"));
+        let long_code = "a".repeat(17);
         assert!(!ServerState::is_valid_lobby_code(&long_code));
+
+        assert_eq!(ServerState::canonical_lobby_code("Test").as_deref(), Some("TEST"));
+        assert_eq!(ServerState::canonical_lobby_code("te st"), None);
     }
 
     #[test]
@@ -214,11 +577,102 @@ mod tests {
         let entry2 = state.player_lobby_index.get(&2);
         assert!(entry1.is_some());
         assert!(entry2.is_some());
-        assert_eq!(entry1.unwrap().value(), "LOBBY1");
-        assert_eq!(entry2.unwrap().value(), "LOBBY2");
+        assert_eq!(entry1.unwrap().lobby_code, "LOBBY1");
+        assert_eq!(entry2.unwrap().lobby_code, "LOBBY2");
         
         state.unregister_player(1);
         assert!(state.player_lobby_index.get(&1).is_none());
     }
-}
 
+    #[tokio::test]
+    async fn test_repair_player_index() {
+        let mut lobby = Lobby::new("LOBBY1".to_string(), 4, "world".to_string());
+        lobby.players.insert(1, Lobby::new_player(1, "InLobby".to_string(), 1, 20));
+        lobby.players.insert(2, Lobby::new_player(2, "Unindexed".to_string(), 1, 20));
+        let (tx, _rx) = mpsc::channel::<LobbyCommand>(100);
+
+        let state = ServerState::new();
+        state.insert_lobby("LOBBY1".to_string(), LobbyHandle {
+            lobby: Arc::new(RwLock::new(lobby)),
+            command_tx: tx,
+            task_handle: tokio::spawn(async {}),
+            summary: ArcSwap::default(),
+            presence: Default::default(),
+        });
+        state.register_player_lobby(1, "LOBBY1");
+        state.register_player_lobby(3, "GONE");  // Orphan
+        state.register_player_lobby(4, "LOBBY1"); // Fresh join, not applied yet
+
+        let repair = state.repair_player_index(Duration::from_secs(60)).await;
+        assert_eq!(repair, IndexRepair { orphans_removed: 0, entries_fixed: 1 });
+        assert_eq!(state.find_lobby_by_player(2).await.as_deref(), Some("LOBBY1"));
+        assert!(state.player_lobby_index.contains_key(&3)); // Within TTL
+
+        let repair = state.repair_player_index(Duration::ZERO).await;
+        assert_eq!(repair, IndexRepair { orphans_removed: 2, entries_fixed: 0 });
+        assert!(state.find_lobby_by_player(3).await.is_none());
+        assert_eq!(state.find_lobby_by_player(1).await.as_deref(), Some("LOBBY1"));
+    }
+
+    #[test]
+    fn test_remove_lobby_drops_index_entries() {
+        let state = ServerState::new();
+        state.register_player_lobby(1, "LOBBY1");
+        state.register_player_lobby(2, "LOBBY2");
+
+        state.remove_lobby("LOBBY1");
+        assert!(state.player_lobby_index.get(&1).is_none());
+        assert!(state.player_lobby_index.get(&2).is_some());
+    }
+
+    #[tokio::test]
+    async fn test_drained_once_lobbies_empty() {
+        let state = ServerState::new();
+        let (tx, _rx) = mpsc::channel::<LobbyCommand>(100);
+        state.insert_lobby("TEST".to_string(), LobbyHandle {
+            lobby: Arc::new(RwLock::new(Lobby::new("TEST".to_string(), 4, "world".to_string()))),
+            command_tx: tx,
+            task_handle: tokio::spawn(async {}),
+            summary: ArcSwap::default(),
+            presence: Default::default(),
+        });
+        let players = vec![(1, "P1".to_string()), (999, "Bot".to_string())];
+        state.publish_summary("TEST", LobbySummary { players, ..Default::default() });
+        assert!(!state.is_drained());
+
+        assert!(state.start_draining());
+        assert!(!state.start_draining());
+        assert!(!state.is_drained());
+
+        let players = vec![(999, "Bot".to_string())];
+        state.publish_summary("TEST", LobbySummary { players, ..Default::default() });
+        assert!(state.is_drained());
+    }
+
+    #[tokio::test]
+    async fn test_scheduled_shutdown_stops_waiting() {
+        let state = ServerState::new();
+        let (tx, _rx) = mpsc::channel::<LobbyCommand>(100);
+        state.insert_lobby("TEST".to_string(), LobbyHandle {
+            lobby: Arc::new(RwLock::new(Lobby::new("TEST".to_string(), 4, "world".to_string()))),
+            command_tx: tx,
+            task_handle: tokio::spawn(async {}),
+            summary: ArcSwap::default(),
+            presence: Default::default(),
+        });
+        state.publish_summary("TEST", LobbySummary { players: vec![(1, "P1".to_string())], ..Default::default() });
+
+        state.schedule_shutdown(Duration::from_secs(60));
+        assert!(state.is_draining());
+        let first = state.shutdown_deadline().unwrap();
+        state.schedule_shutdown(Duration::from_secs(600)); // Never pushed back
+        assert_eq!(state.shutdown_deadline(), Some(first));
+        let wait = state.wait_drained(Duration::from_millis(10));
+        assert!(tokio::time::timeout(Duration::from_millis(50), wait).await.is_err());
+
+        // Once due, the wait gives up on the player still connected
+        state.schedule_shutdown(Duration::ZERO);
+        assert!(!state.is_drained());
+        tokio::time::timeout(Duration::from_secs(1), state.wait_drained(Duration::from_millis(10))).await.unwrap();
+    }
+}
@@ -0,0 +1,285 @@
+use std::future::Future;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+use crate::state::global_stats::{GlobalPlayerStats, GlobalStats, StatsChange};
+use crate::state::player_ids::{PlayerIds, PLAYER_ID_BLOCK};
+use crate::state::redis_backend::RedisBackend;
+
+/// Future returned by `StatsBackend` methods
+pub type BackendFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T, &'static str>> + Send + 'a>>;
+
+/// Durable storage for global player stats
+///
+/// `GlobalStats` stays the source of truth for reads; backends only see
+/// batched writes (write-behind) and periodic reloads.
+pub trait StatsBackend: Send + Sync {
+    /// Every stored player
+    fn load_all(&self) -> BackendFuture<'_, Vec<GlobalPlayerStats>>;
+
+    /// Store the given changes; backends shared between instances add each change's
+    /// difference from `stored` so concurrent writers don't overwrite each other
+    fn save(&self, changes: Vec<StatsChange>) -> BackendFuture<'_, ()>;
+
+    /// Reserve `count` player ids no other reservation gets; returns the first
+    fn reserve_player_ids(&self, count: u32) -> BackendFuture<'_, u32>;
+}
+
+/// Stats kept in a single JSON file (one server instance, so players are simply replaced)
+pub struct FileBackend {
+    path: PathBuf,
+}
+
+impl FileBackend {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    async fn read_file(&self) -> Result<Vec<GlobalPlayerStats>, &'static str> {
+        let data = match tokio::fs::read(&self.path).await {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => {
+                log::warn!("Failed to read stats file {}: {}", self.path.display(), e);
+                return Err("Failed to read stats file");
+            }
+        };
+        serde_json::from_slice(&data).map_err(|_| "Corrupt stats file")
+    }
+
+    async fn write_file(&self, stats: Vec<GlobalPlayerStats>) -> Result<(), &'static str> {
+        // Merge into what's on disk so a partial flush doesn't drop other players
+        let mut all = self.read_file().await?;
+        for updated in stats {
+            match all.iter_mut().find(|s| s.player_id == updated.player_id) {
+                Some(existing) => *existing = updated,
+                None => all.push(updated),
+            }
+        }
+        let data = serde_json::to_vec(&all).map_err(|_| "Failed to encode stats")?;
+
+        // Write then rename so a crash never leaves a half-written file
+        let tmp = self.path.with_extension("tmp");
+        let result = async {
+            tokio::fs::write(&tmp, &data).await?;
+            tokio::fs::rename(&tmp, &self.path).await
+        }.await;
+        result.map_err(|e| {
+            log::warn!("Failed to write stats file {}: {}", self.path.display(), e);
+            "Failed to write stats file"
+        })
+    }
+
+    /// Next unreserved player id lives next to the stats, in `<path>.ids`
+    async fn reserve_ids(&self, count: u32) -> Result<u32, &'static str> {
+        let path = self.path.with_extension("ids");
+        let start: u32 = match tokio::fs::read_to_string(&path).await {
+            Ok(text) => text.trim().parse().map_err(|_| "Corrupt player id file")?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => 1,
+            Err(e) => {
+                log::warn!("Failed to read player id file {}: {}", path.display(), e);
+                return Err("Failed to read player id file");
+            }
+        };
+        let tmp = path.with_extension("ids.tmp");
+        let result = async {
+            tokio::fs::write(&tmp, start.saturating_add(count).to_string()).await?;
+            tokio::fs::rename(&tmp, &path).await
+        }.await;
+        result.map_err(|e| {
+            log::warn!("Failed to write player id file {}: {}", path.display(), e);
+            "Failed to write player id file"
+        })?;
+        Ok(start)
+    }
+}
+
+impl StatsBackend for FileBackend {
+    fn load_all(&self) -> BackendFuture<'_, Vec<GlobalPlayerStats>> {
+        Box::pin(self.read_file())
+    }
+
+    fn save(&self, changes: Vec<StatsChange>) -> BackendFuture<'_, ()> {
+        Box::pin(self.write_file(changes.into_iter().map(|change| change.stats).collect()))
+    }
+
+    fn reserve_player_ids(&self, count: u32) -> BackendFuture<'_, u32> {
+        Box::pin(self.reserve_ids(count))
+    }
+}
+
+/// Build a backend from a config string: `file:<path>`, `redis://[:password@]host:port[/db]` or `rediss://` for TLS
+pub fn backend_from_url(url: &str) -> Result<Arc<dyn StatsBackend>, &'static str> {
+    if let Some(path) = url.strip_prefix("file:") {
+        return Ok(Arc::new(FileBackend::new(path)));
+    }
+    if let Some(addr) = url.strip_prefix("redis://").or_else(|| url.strip_prefix("rediss://")) {
+        if addr.is_empty() {
+            return Err("Missing Redis address");
+        }
+        return Ok(Arc::new(RedisBackend::open(url)?));
+    }
+    Err("Unknown stats backend")
+}
+
+/// Write-behind link between the in-memory stats and a backend
+pub struct StatsSync {
+    stats: Arc<GlobalStats>,
+    backend: Arc<dyn StatsBackend>,
+    player_ids: Option<Arc<PlayerIds>>, // Kept supplied with id blocks from the backend
+}
+
+impl StatsSync {
+    pub fn new(stats: Arc<GlobalStats>, backend: Arc<dyn StatsBackend>) -> Self {
+        Self { stats, backend, player_ids: None }
+    }
+
+    /// Also reserve player id blocks in the backend, so ids survive restarts
+    pub fn with_player_ids(mut self, player_ids: Arc<PlayerIds>) -> Self {
+        self.player_ids = Some(player_ids);
+        self
+    }
+
+    /// Reserve the next id block if the allocator is running low
+    pub async fn reserve_player_ids(&self) -> Result<(), &'static str> {
+        let Some(player_ids) = self.player_ids.as_ref().filter(|ids| ids.wants_block()) else {
+            return Ok(());
+        };
+        let start = self.backend.reserve_player_ids(PLAYER_ID_BLOCK).await?;
+        player_ids.add_block(start, PLAYER_ID_BLOCK);
+        log::info!("Reserved player ids {}..{}", start, start.saturating_add(PLAYER_ID_BLOCK));
+        Ok(())
+    }
+
+    /// Write every changed player; failed writes are retried on the next flush
+    pub async fn flush(&self) -> Result<usize, &'static str> {
+        let dirty = self.stats.take_dirty();
+        if dirty.is_empty() {
+            return Ok(0);
+        }
+        let count = dirty.len();
+        let ids: Vec<u32> = dirty.iter().map(|c| c.stats.player_id).collect();
+        let sent: Vec<GlobalPlayerStats> = dirty.iter().map(|c| c.stats.clone()).collect();
+        if let Err(e) = self.backend.save(dirty).await {
+            self.stats.mark_dirty(ids);
+            return Err(e);
+        }
+        self.stats.mark_stored(sent);
+        Ok(count)
+    }
+
+    /// Pull the backend's copy (picks up other instances' writes)
+    pub async fn refresh(&self) -> Result<usize, &'static str> {
+        let remote = self.backend.load_all().await?;
+        Ok(self.stats.merge_remote(remote))
+    }
+
+    /// Load once, then flush and refresh on an interval
+    pub fn spawn(self: Arc<Self>, interval: Duration) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            match self.refresh().await {
+                Ok(count) => log::info!("Loaded stats for {} players", count),
+                Err(e) => log::warn!("Initial stats load failed: {}", e),
+            }
+            let mut timer = tokio::time::interval(interval);
+            timer.tick().await; // First tick fires immediately
+            loop {
+                tokio::select! {
+                    _ = timer.tick() => {}
+                    _ = self.stats.flush_requested() => {}
+                }
+                if let Err(e) = self.flush().await {
+                    log::warn!("Stats flush failed: {}", e);
+                }
+                if let Err(e) = self.refresh().await {
+                    log::warn!("Stats refresh failed: {}", e);
+                }
+                if let Err(e) = self.reserve_player_ids().await {
+                    log::warn!("Player id reservation failed: {}", e);
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn changes(stats: Vec<GlobalPlayerStats>) -> Vec<StatsChange> {
+        stats.into_iter().map(|stats| StatsChange { stats, stored: None }).collect()
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("gungame-{}-{}.json", name, std::process::id()))
+    }
+
+    #[tokio::test]
+    async fn test_file_backend_round_trip() {
+        let path = temp_path("stats-round-trip");
+        let _ = std::fs::remove_file(&path);
+        let backend = FileBackend::new(&path);
+        assert!(backend.load_all().await.unwrap().is_empty());
+
+        let mut first = GlobalPlayerStats::new(1, "P1".to_string());
+        first.total_kills = 3;
+        backend.save(changes(vec![first, GlobalPlayerStats::new(2, "P2".to_string())])).await.unwrap();
+        let mut updated = GlobalPlayerStats::new(1, "P1".to_string());
+        updated.total_kills = 5;
+        backend.save(changes(vec![updated])).await.unwrap();
+
+        let mut loaded = backend.load_all().await.unwrap();
+        loaded.sort_by_key(|s| s.player_id);
+        assert_eq!(loaded.len(), 2);
+        assert_eq!(loaded[0].total_kills, 5);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn test_sync_flushes_and_shares_between_instances() {
+        let path = temp_path("stats-shared");
+        let _ = std::fs::remove_file(&path);
+        let backend: Arc<dyn StatsBackend> = Arc::new(FileBackend::new(&path));
+        let first = StatsSync::new(Arc::new(GlobalStats::new()), backend.clone());
+        let second_stats = Arc::new(GlobalStats::new());
+        let second = StatsSync::new(second_stats.clone(), backend);
+
+        first.stats.record_session(1, "P1", 2, 1, 200);
+        assert_eq!(first.flush().await.unwrap(), 1);
+        assert_eq!(first.flush().await.unwrap(), 0);
+
+        assert_eq!(second.refresh().await.unwrap(), 1);
+        assert_eq!(second_stats.get_stats(1).unwrap().total_score, 200);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn test_player_ids_survive_restart() {
+        let path = temp_path("stats-ids");
+        let _ = std::fs::remove_file(path.with_extension("ids"));
+        let backend: Arc<dyn StatsBackend> = Arc::new(FileBackend::new(&path));
+
+        let ids = Arc::new(PlayerIds::new());
+        let sync = StatsSync::new(Arc::new(GlobalStats::new()), backend.clone()).with_player_ids(ids.clone());
+        sync.reserve_player_ids().await.unwrap();
+        assert_eq!(ids.next(), 1);
+        sync.reserve_player_ids().await.unwrap(); // Plenty left, nothing reserved
+
+        // A restarted server starts past everything the last one reserved
+        let restarted = Arc::new(PlayerIds::new());
+        let sync = StatsSync::new(Arc::new(GlobalStats::new()), backend).with_player_ids(restarted.clone());
+        sync.reserve_player_ids().await.unwrap();
+        assert_eq!(restarted.next(), PLAYER_ID_BLOCK + 1);
+        let _ = std::fs::remove_file(path.with_extension("ids"));
+    }
+
+    #[test]
+    fn test_backend_from_url() {
+        assert!(backend_from_url("file:/tmp/stats.json").is_ok());
+        assert!(backend_from_url("redis://127.0.0.1:6379").is_ok());
+        assert!(backend_from_url("rediss://:secret@cache.internal:6380/2").is_ok());
+        assert_eq!(backend_from_url("redis://").err(), Some("Missing Redis address"));
+        assert_eq!(backend_from_url("postgres://db").err(), Some("Unknown stats backend"));
+    }
+}
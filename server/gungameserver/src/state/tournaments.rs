@@ -0,0 +1,129 @@
+use crate::domain::tournament::{self, Bracket};
+use crate::state::lobby::{LobbyCode, MatchStanding};
+use dashmap::DashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::Notify;
+
+/// Most tournaments the server keeps, running or finished
+pub const MAX_TOURNAMENTS: usize = 32;
+
+/// Match settings shared by every lobby of a tournament
+#[derive(Debug, Clone)]
+pub struct TournamentSettings {
+    pub scene: String,
+    pub duel_rounds: u32,
+}
+
+/// A bracket and the wakeup its runner task waits on
+#[derive(Debug)]
+pub struct Tournament {
+    pub id: u64,
+    pub name: String,
+    pub settings: TournamentSettings,
+    pub bracket: Mutex<Bracket>,
+    /// Signalled whenever a result is recorded
+    pub changed: Notify,
+}
+
+impl Tournament {
+    /// Lobby code for a bracket match, e.g. "T3R1M2" (canonical, so players can join it by code)
+    pub fn lobby_code(&self, round: usize, index: usize) -> LobbyCode {
+        format!("T{}R{}M{}", self.id, round + 1, index + 1)
+    }
+}
+
+/// Tournaments by id, and which bracket match each tournament lobby plays
+#[derive(Debug)]
+pub struct Tournaments {
+    next_id: AtomicU64,
+    tournaments: DashMap<u64, Arc<Tournament>>,
+    lobbies: DashMap<LobbyCode, (u64, usize, usize)>, // Lobby code -> tournament id, round, match index
+}
+
+impl Default for Tournaments {
+    fn default() -> Self {
+        Self { next_id: AtomicU64::new(1), tournaments: DashMap::new(), lobbies: DashMap::new() }
+    }
+}
+
+impl Tournaments {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Draw a bracket and register the tournament; its lobbies are created by the runner
+    pub fn create(&self, name: String, participants: &[String], settings: TournamentSettings) -> Result<Arc<Tournament>, &'static str> {
+        if self.tournaments.len() >= MAX_TOURNAMENTS {
+            return Err("Too many tournaments");
+        }
+        let bracket = Bracket::new(participants)?;
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let tournament = Arc::new(Tournament { id, name, settings, bracket: Mutex::new(bracket), changed: Notify::new() });
+        self.tournaments.insert(id, tournament.clone());
+        Ok(tournament)
+    }
+
+    pub fn get(&self, id: u64) -> Option<Arc<Tournament>> {
+        self.tournaments.get(&id).map(|entry| entry.clone())
+    }
+
+    /// Record that a lobby now hosts a bracket match
+    pub fn assign_lobby(&self, tournament: &Tournament, round: usize, index: usize, lobby_code: LobbyCode) {
+        if let Ok(mut bracket) = tournament.bracket.lock() {
+            bracket.assign_lobby(round, index, lobby_code.clone());
+        }
+        self.lobbies.insert(lobby_code, (tournament.id, round, index));
+    }
+
+    /// Settle the bracket match played in a lobby from its final standings
+    /// Returns the winner; None for lobbies outside tournaments or matches no entrant finished
+    pub fn record_result(&self, lobby_code: &str, standings: &[MatchStanding]) -> Option<String> {
+        let (id, round, index) = *self.lobbies.get(lobby_code)?;
+        let tournament = self.get(id)?;
+        let winner = {
+            let mut bracket = tournament.bracket.lock().ok()?;
+            let winner = tournament::match_winner(bracket.get(round, index)?, standings)?;
+            bracket.record_winner(round, index, &winner).ok()?;
+            winner
+        };
+        self.lobbies.remove(lobby_code);
+        tournament.changed.notify_one();
+        Some(winner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn standing(name: &str) -> MatchStanding {
+        MatchStanding {
+            player_id: 1,
+            name: name.to_string(),
+            score: 0,
+            kills: 0,
+            deaths: 0,
+            stats: Default::default(),
+            accuracy: 0.0,
+            rating_change: None,
+            round_wins: 0,
+        }
+    }
+
+    #[test]
+    fn test_results_settle_assigned_lobbies_once() {
+        let tournaments = Tournaments::new();
+        let settings = TournamentSettings { scene: "world".to_string(), duel_rounds: 3 };
+        let tournament = tournaments.create("Cup".to_string(), &["A".to_string(), "B".to_string()], settings).unwrap();
+        let code = tournament.lobby_code(0, 0);
+        assert_eq!(code, "T1R1M1");
+        assert_eq!(tournaments.record_result(&code, &[standing("B")]), None); // Not assigned yet
+
+        tournaments.assign_lobby(&tournament, 0, 0, code.clone());
+        // A spectator topping the standings doesn't count
+        assert_eq!(tournaments.record_result(&code, &[standing("Guest"), standing("B"), standing("A")]), Some("B".to_string()));
+        assert_eq!(tournament.bracket.lock().unwrap().champion(), Some("B"));
+        assert_eq!(tournaments.record_result(&code, &[standing("A")]), None);
+    }
+}
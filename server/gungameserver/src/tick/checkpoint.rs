@@ -0,0 +1,462 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use tokio::net::UdpSocket;
+use crate::domain::bots;
+use crate::domain::timeline::MatchTimeline;
+use crate::state::lobby::{GameMode, Lobby, LobbyCode, LobbySettings, MatchPhase, MatchStats, Player, DEFAULT_DUEL_ROUNDS, DEFAULT_DUEL_ROUND_SECS};
+use crate::state::server_state::ServerState;
+use crate::utils::config::Config;
+use crate::utils::weapondb::{WeaponDb, WeaponLookup, WeaponOverlay, WeaponOverride, WeaponView};
+use crate::utils::clock;
+use crate::utils::rng::SeededRng;
+
+/// Essential state of one player, enough to carry their match on after a crash
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlayerCheckpoint {
+    pub id: u32,
+    pub name: String,
+    pub score: u32,
+    pub kills: u32,
+    pub deaths: u32,
+    pub killstreak: u32,
+    pub weapon_id: u32,
+    pub match_stats: MatchStats,
+    pub zone_points: u32,
+    #[serde(default)]
+    pub round_wins: u32,
+    #[serde(default)]
+    pub team: Option<u8>,
+    #[serde(default)]
+    pub party_id: Option<String>,
+}
+
+/// Every lobby setting, in the form checkpoints and the replication stream carry it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SettingsCheckpoint {
+    pub ranked: bool,
+    pub max_health: u32,
+    pub weapon_overrides: HashMap<u32, WeaponOverride>,
+    pub region: String,
+    pub motd: String,
+    pub spawn_protection_secs: f32,
+    pub spawn_protection_blocks_shooting: bool,
+    pub weapon_ladder: Vec<u32>,
+    pub private: bool,
+    pub reserved_slots: u32,
+    pub game_mode: GameMode,
+    pub zone_score_limit: u32,
+    #[serde(default = "default_duel_rounds")]
+    pub duel_rounds: u32,
+    #[serde(default = "default_duel_round_secs")]
+    pub duel_round_secs: u32,
+    pub max_latency_ms: Option<u32>,
+    #[serde(default)]
+    pub fill_with_bots: bool,
+    #[serde(default)]
+    pub target_players: u32,
+    #[serde(default)]
+    pub team_count: u8,
+    #[serde(default)]
+    pub weapon_drops: bool,
+    #[serde(default)]
+    pub damage_share_scoring: bool,
+    #[serde(default)]
+    pub banned_weapons: Vec<u32>,
+    #[serde(default)]
+    pub max_match_secs: Option<u64>,
+    #[serde(default)]
+    pub max_lifetime_secs: Option<u64>,
+}
+
+/// Essential state of one lobby: settings, players and how far the match has got
+///
+/// Positions, timers and projectiles are not kept; players respawn where they
+/// reconnect and the restored lobby starts paused.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LobbyCheckpoint {
+    pub code: LobbyCode,
+    pub max_players: u32,
+    pub scene: String,
+    #[serde(flatten)]
+    pub settings: SettingsCheckpoint,
+    pub owner_id: Option<u32>,
+    pub phase: MatchPhase,
+    /// Match ticks played so far (the match clock), if a match is in progress
+    pub match_ticks: Option<u64>,
+    /// Event ids carry on from here so reconnecting clients don't see them repeat
+    #[serde(default)]
+    pub last_event_id: u64,
+    /// Gameplay rolls carry on from here, so a replayed lobby rolls what the original did
+    #[serde(default)]
+    pub rng: Option<u64>,
+    pub players: Vec<PlayerCheckpoint>,
+}
+
+fn default_duel_rounds() -> u32 {
+    DEFAULT_DUEL_ROUNDS
+}
+
+fn default_duel_round_secs() -> u32 {
+    DEFAULT_DUEL_ROUND_SECS
+}
+
+impl SettingsCheckpoint {
+    pub fn capture(settings: &LobbySettings) -> Self {
+        Self {
+            ranked: settings.ranked,
+            max_health: settings.max_health,
+            weapon_overrides: settings.weapons.overrides.clone(),
+            region: settings.region.clone(),
+            motd: settings.motd.clone(),
+            spawn_protection_secs: settings.spawn_protection_secs,
+            spawn_protection_blocks_shooting: settings.spawn_protection_blocks_shooting,
+            weapon_ladder: settings.weapon_ladder.clone(),
+            private: settings.private,
+            reserved_slots: settings.reserved_slots,
+            game_mode: settings.game_mode,
+            zone_score_limit: settings.zone_score_limit,
+            duel_rounds: settings.duel_rounds,
+            duel_round_secs: settings.duel_round_secs,
+            max_latency_ms: settings.max_latency_ms,
+            fill_with_bots: settings.fill_with_bots,
+            target_players: settings.target_players,
+            team_count: settings.team_count,
+            weapon_drops: settings.weapon_drops,
+            damage_share_scoring: settings.damage_share_scoring,
+            banned_weapons: settings.banned_weapons.clone(),
+            max_match_secs: settings.max_match_secs,
+            max_lifetime_secs: settings.max_lifetime_secs,
+        }
+    }
+
+    /// Rebuild the settings, resolving weapon overrides against the loaded weapons
+    pub fn restore(self, weapons: &WeaponDb) -> Result<LobbySettings, &'static str> {
+        Ok(LobbySettings {
+            ranked: self.ranked,
+            max_health: self.max_health,
+            weapons: Arc::new(WeaponOverlay::resolve(weapons, self.weapon_overrides)?),
+            region: self.region,
+            motd: self.motd,
+            spawn_protection_secs: self.spawn_protection_secs,
+            spawn_protection_blocks_shooting: self.spawn_protection_blocks_shooting,
+            weapon_ladder: self.weapon_ladder,
+            private: self.private,
+            reserved_slots: self.reserved_slots,
+            game_mode: self.game_mode,
+            zone_score_limit: self.zone_score_limit,
+            duel_rounds: self.duel_rounds,
+            duel_round_secs: self.duel_round_secs,
+            max_latency_ms: self.max_latency_ms,
+            fill_with_bots: self.fill_with_bots,
+            target_players: self.target_players,
+            team_count: self.team_count,
+            weapon_drops: self.weapon_drops,
+            damage_share_scoring: self.damage_share_scoring,
+            banned_weapons: self.banned_weapons,
+            max_match_secs: self.max_match_secs,
+            max_lifetime_secs: self.max_lifetime_secs,
+        })
+    }
+}
+
+impl LobbyCheckpoint {
+    pub fn capture(lobby: &Lobby) -> Self {
+        let mut players: Vec<PlayerCheckpoint> = lobby.players.values()
+            .filter(|p| p.id != 999 && !bots::is_bot(p.id)) // Bots are refilled after restore
+            .map(|p| PlayerCheckpoint {
+                id: p.id,
+                name: p.name.clone(),
+                score: p.score,
+                kills: p.kills,
+                deaths: p.deaths,
+                killstreak: p.killstreak,
+                weapon_id: p.current_weapon_id,
+                match_stats: p.match_stats,
+                zone_points: lobby.zone.points.get(&p.id).copied().unwrap_or(0),
+                round_wins: lobby.duel.wins.get(&p.id).copied().unwrap_or(0),
+                team: lobby.teams.team_of(p.id),
+                party_id: p.party_id.clone(),
+            })
+            .collect();
+        players.sort_by_key(|p| p.id);
+
+        Self {
+            code: lobby.code.clone(),
+            max_players: lobby.max_players,
+            scene: lobby.scene.clone(),
+            settings: SettingsCheckpoint::capture(&lobby.settings),
+            owner_id: lobby.owner_id,
+            phase: lobby.phase,
+            match_ticks: lobby.timeline.as_ref().map(|t| t.last_tick),
+            last_event_id: lobby.last_event_id,
+            rng: Some(lobby.rng.state()),
+            players,
+        }
+    }
+
+    /// Rebuild the lobby, paused, with players waiting to reconnect
+    /// Restored players may stay disconnected for `grace` before inactivity cleanup applies
+    pub fn restore(self, weapons: &WeaponDb, grace: Duration) -> Result<Lobby, &'static str> {
+        let settings = self.settings.restore(weapons)?;
+        let overlay = settings.weapons.clone();
+        let view = WeaponView::new(weapons, &overlay);
+        let mut lobby = Lobby::with_settings(self.code, self.max_players, self.scene, settings);
+
+        let now = clock::now();
+        for saved in self.players {
+            let weapon = view.get(saved.weapon_id)
+                .or_else(|| view.get(WeaponDb::default_weapon_id()))
+                .ok_or("Invalid weapon")?;
+            let mut player = Player::new_player(saved.id, saved.name, weapon.id, weapon.ammo);
+            player.max_ammo = weapon.ammo;
+            player.max_health = lobby.settings.max_health;
+            player.current_health = lobby.settings.max_health;
+            player.score = saved.score;
+            player.kills = saved.kills;
+            player.deaths = saved.deaths;
+            player.killstreak = saved.killstreak;
+            player.match_stats = saved.match_stats;
+            player.party_id = saved.party_id;
+            // Counted as active until the grace period runs out
+            player.last_update = now + grace;
+            if saved.zone_points > 0 {
+                lobby.zone.points.insert(saved.id, saved.zone_points);
+            }
+            if let Some(team) = saved.team {
+                lobby.teams.assignments.insert(saved.id, team);
+            }
+            if saved.round_wins > 0 {
+                lobby.duel.wins.insert(saved.id, saved.round_wins);
+                // The interrupted round is replayed; numbering carries on
+                lobby.duel.round += saved.round_wins;
+            }
+            lobby.players.insert(saved.id, player);
+        }
+        lobby.owner_id = self.owner_id.filter(|id| lobby.players.contains_key(id));
+        lobby.last_event_id = self.last_event_id;
+        if let Some(state) = self.rng {
+            lobby.rng = SeededRng::new(state);
+        }
+        // A countdown in progress starts over once the lobby resumes
+        lobby.phase = match self.phase {
+            MatchPhase::Countdown => MatchPhase::Waiting,
+            phase => phase,
+        };
+        lobby.paused_at = Some(now);
+        Ok(lobby)
+    }
+}
+
+/// Lobby checkpoints kept in a single JSON file, rewritten whole each time
+pub struct CheckpointFile {
+    path: PathBuf,
+}
+
+impl CheckpointFile {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    pub async fn load(&self) -> Result<Vec<LobbyCheckpoint>, &'static str> {
+        let data = match tokio::fs::read(&self.path).await {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => {
+                log::warn!("Failed to read checkpoint file {}: {}", self.path.display(), e);
+                return Err("Failed to read checkpoint file");
+            }
+        };
+        serde_json::from_slice(&data).map_err(|_| "Corrupt checkpoint file")
+    }
+
+    pub async fn save(&self, checkpoints: &[LobbyCheckpoint]) -> Result<(), &'static str> {
+        let data = serde_json::to_vec(checkpoints).map_err(|_| "Failed to encode checkpoints")?;
+
+        // Write then rename so a crash mid-write keeps the previous checkpoint
+        let tmp = self.path.with_extension("tmp");
+        let result = async {
+            tokio::fs::write(&tmp, &data).await?;
+            tokio::fs::rename(&tmp, &self.path).await
+        }.await;
+        result.map_err(|e| {
+            log::warn!("Failed to write checkpoint file {}: {}", self.path.display(), e);
+            "Failed to write checkpoint file"
+        })
+    }
+}
+
+/// Checkpoint every lobby that has players
+pub async fn capture_all(state: &ServerState) -> Vec<LobbyCheckpoint> {
+    let lobbies: Vec<_> = state.iter_lobbies().map(|entry| entry.lobby.clone()).collect();
+    let mut checkpoints = Vec::with_capacity(lobbies.len());
+    for lobby in lobbies {
+        let lobby = lobby.read().await;
+        let checkpoint = LobbyCheckpoint::capture(&lobby);
+        if !checkpoint.players.is_empty() {
+            checkpoints.push(checkpoint);
+        }
+    }
+    checkpoints.sort_by(|a, b| a.code.cmp(&b.code));
+    checkpoints
+}
+
+/// Periodically write every lobby's checkpoint to disk
+pub fn spawn_checkpointer(state: Arc<ServerState>, file: CheckpointFile, interval: Duration) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut timer = tokio::time::interval(interval);
+        loop {
+            timer.tick().await;
+            let checkpoints = capture_all(&state).await;
+            if let Err(e) = file.save(&checkpoints).await {
+                log::warn!("Lobby checkpoint failed: {}", e);
+            }
+        }
+    })
+}
+
+/// Restore checkpointed lobbies (paused) and spawn their tick loops
+/// Returns the number of lobbies restored
+pub async fn recover(
+    file: &CheckpointFile,
+    state: Arc<ServerState>,
+    weapons: Arc<WeaponDb>,
+    config: Arc<Config>,
+    socket: Arc<UdpSocket>,
+) -> Result<usize, &'static str> {
+    let grace = Duration::from_secs(config.recovery_grace_secs);
+    let mut restored = 0;
+    for checkpoint in file.load().await? {
+        let code = checkpoint.code.clone();
+        let match_ticks = checkpoint.match_ticks;
+        let mut lobby = match checkpoint.restore(&weapons, grace) {
+            Ok(lobby) => lobby,
+            Err(e) => {
+                log::warn!("Could not restore lobby {}: {}", code, e);
+                continue;
+            }
+        };
+        // The match keeps its clock under a new match id
+        if let (Some(ticks), true) = (match_ticks, lobby.is_match_live()) {
+            lobby.timeline = Some(MatchTimeline::resumed(state.begin_match(&code), code.clone(), ticks));
+        }
+        let player_ids: Vec<u32> = lobby.players.keys().copied().collect();
+        if let Err(e) = crate::server::spawn_lobby(state.clone(), lobby, weapons.clone(), config.clone(), socket.clone()).await {
+            log::warn!("Could not restore lobby {}: {}", code, e);
+            continue;
+        }
+        // Without a stats store the id counter restarted at 1; new joins must not reuse restored ids
+        if let Some(max_id) = player_ids.iter().copied().filter(|id| !bots::is_bot(*id)).max() {
+            state.player_ids.skip_past(max_id);
+        }
+        for player_id in player_ids {
+            state.register_player_lobby(player_id, &code);
+        }
+        log::info!("Restored lobby {} from checkpoint, paused until players reconnect", code);
+        restored += 1;
+    }
+    Ok(restored)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::lobbies::add_player;
+
+    #[test]
+    fn test_checkpoint_round_trip() {
+        let weapons = WeaponDb::load();
+        let settings = LobbySettings { game_mode: GameMode::KingOfTheHill, motd: "Finals".to_string(), ..Default::default() };
+        let mut lobby = Lobby::with_settings("CKPT".to_string(), 6, "arena".to_string(), settings);
+        add_player(&mut lobby, 1, "P1".to_string(), 1, &weapons).unwrap();
+        add_player(&mut lobby, 2, "P2".to_string(), 1, &weapons).unwrap();
+        lobby.phase = MatchPhase::InProgress;
+        lobby.timeline = Some(MatchTimeline::new(7, "CKPT".to_string(), 0));
+        lobby.timeline.as_mut().unwrap().advance(1500);
+        {
+            let player = lobby.players.get_mut(&1).unwrap();
+            player.score = 300;
+            player.kills = 3;
+            player.current_weapon_id = 3;
+        }
+        lobby.zone.points.insert(2, 40);
+        lobby.last_event_id = 42;
+
+        let checkpoint = LobbyCheckpoint::capture(&lobby);
+        assert_eq!(checkpoint.match_ticks, Some(1500));
+        let json = serde_json::to_string(&checkpoint).unwrap();
+        let decoded: LobbyCheckpoint = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, checkpoint);
+
+        let mut restored = decoded.restore(&weapons, Duration::from_secs(60)).unwrap();
+        assert!(restored.is_paused());
+        assert!(restored.is_match_live());
+        assert_eq!(restored.settings.motd, "Finals");
+        assert_eq!(restored.owner_id, lobby.owner_id);
+        assert_eq!(restored.players[&1].score, 300);
+        assert_eq!(restored.players[&1].current_weapon_id, 3);
+        assert_eq!(restored.zone.points[&2], 40);
+        assert_eq!(restored.next_event_id(), 43);
+        assert!(restored.client_addresses.is_empty()); // Waiting for reconnects
+        assert!(restored.players[&1].last_update > std::time::SystemTime::now());
+    }
+
+    #[tokio::test]
+    async fn test_checkpoint_file_round_trip() {
+        let path = std::env::temp_dir().join(format!("gungame_checkpoint_{}.json", std::process::id()));
+        let file = CheckpointFile::new(&path);
+        assert!(file.load().await.unwrap().is_empty());
+
+        let mut lobby = Lobby::new("FILE".to_string(), 4, "world".to_string());
+        add_player(&mut lobby, 1, "P1".to_string(), 1, &WeaponDb::load()).unwrap();
+        let checkpoints = vec![LobbyCheckpoint::capture(&lobby)];
+        file.save(&checkpoints).await.unwrap();
+        assert_eq!(file.load().await.unwrap(), checkpoints);
+        let _ = tokio::fs::remove_file(&path).await;
+    }
+
+    #[tokio::test]
+    async fn test_recover_spawns_paused_lobbies() {
+        use axum::extract::{Path, State};
+        use axum::http::HeaderMap;
+        use axum::Json;
+        use crate::handlers::http::{join_lobby, AppState};
+        use crate::handlers::models::JoinLobbyRequest;
+
+        let path = std::env::temp_dir().join(format!("gungame_recover_{}.json", std::process::id()));
+        let file = CheckpointFile::new(&path);
+        let weapons = Arc::new(WeaponDb::load());
+        let mut lobby = Lobby::new("RECOVER".to_string(), 4, "world".to_string());
+        add_player(&mut lobby, 5, "P5".to_string(), 1, weapons.as_ref()).unwrap();
+        lobby.phase = MatchPhase::InProgress;
+        let mut timeline = MatchTimeline::new(1, "RECOVER".to_string(), 0);
+        timeline.advance(250);
+        lobby.timeline = Some(timeline);
+        file.save(&[LobbyCheckpoint::capture(&lobby)]).await.unwrap();
+
+        let state = Arc::new(ServerState::new());
+        let socket = Arc::new(UdpSocket::bind("127.0.0.1:0").await.unwrap());
+        let restored = recover(&file, state.clone(), weapons.clone(), Arc::new(Config::default()), socket.clone()).await.unwrap();
+        assert_eq!(restored, 1);
+        assert_eq!(state.find_lobby_by_player(5).await.as_deref(), Some("RECOVER"));
+
+        let lobby_arc = state.get_lobby("RECOVER").unwrap();
+        let lobby = lobby_arc.read().await;
+        assert!(lobby.is_paused());
+        let timeline = lobby.timeline.as_ref().unwrap();
+        assert!(timeline.last_tick >= 250); // The match clock carries on
+        assert_eq!(state.live_match_lobby(timeline.match_id).as_deref(), Some("RECOVER"));
+        drop(lobby);
+
+        // A player joining after the restart gets a fresh id, leaving the restored player's reconnect intact
+        let app_state = AppState { state: state.clone(), weapons, config: Arc::new(Config::default()), udp_socket: socket };
+        let request = JoinLobbyRequest { player_name: "New".to_string(), team: None };
+        let joined = join_lobby(State(app_state), HeaderMap::new(), Path("RECOVER".to_string()), Json(request)).await.unwrap();
+        assert!(joined.player_id > 5);
+        assert_eq!(lobby_arc.read().await.players.len(), 2);
+        assert_eq!(state.find_lobby_by_player(5).await.as_deref(), Some("RECOVER"));
+        let _ = tokio::fs::remove_file(&path).await;
+    }
+}
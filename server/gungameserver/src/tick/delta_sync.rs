@@ -1,64 +1,55 @@
-use crate::state::lobby::Lobby;
-use crate::utils::buffers::{SmallEventVec, SyncEvent};
+use crate::domain::logic;
+use crate::state::lobby::{ChangeMask, Lobby};
+use crate::utils::buffers::{HitMarker, SmallEventVec, SyncEvent};
+use crate::utils::clock;
 
 /// Collect dirty events for delta-based state sync
-/// Only includes changed fields compared to last sync state
+/// Only includes fields flagged in each dirty player's change mask
+/// Events queued during command processing are flushed first
 pub fn collect_dirty_events(lobby: &mut Lobby) -> SmallEventVec {
-    let mut events = SmallEventVec::new();
+    let mut events: SmallEventVec = lobby.pending_events.drain(..).collect();
+    let markers = hit_markers(&events);
+    events.extend(markers);
+    let now = clock::now();
 
     for &player_id in &lobby.dirty_players {
-        if let Some(player) = lobby.players.get(&player_id) {
-            let last = lobby.last_sync_state.get(&player_id);
+        if let Some(player) = lobby.players.get_mut(&player_id) {
+            // Consume the mask so a second collect in the same tick sends nothing
+            let changes = std::mem::take(&mut player.changes);
 
-            // Only include changed fields
-            if last
-                .map(|l| l.health != player.current_health)
-                .unwrap_or(true)
-            {
+            if changes.contains(ChangeMask::HEALTH) {
                 events.push(SyncEvent::HealthChanged {
                     player_id,
                     health: player.current_health,
+                    max_health: player.max_health,
                 });
             }
 
-            if last
-                .map(|l| l.max_health != player.max_health)
-                .unwrap_or(true)
-            {
-                // Max health rarely changes, but include if it does
-            }
-
-            if last
-                .map(|l| l.current_ammo != player.current_ammo)
-                .unwrap_or(true)
-            {
+            if changes.contains(ChangeMask::AMMO) {
                 events.push(SyncEvent::AmmoChanged {
                     player_id,
                     ammo: player.current_ammo,
+                    heat: player.heat,
+                    overheated: player.overheated_until.is_some(),
                 });
             }
 
-            if last.map(|l| l.max_ammo != player.max_ammo).unwrap_or(true) {
+            if changes.contains(ChangeMask::MAX_AMMO) {
                 events.push(SyncEvent::MaxAmmoChanged {
                     player_id,
                     max_ammo: player.max_ammo,
                 });
             }
 
-            if last
-                .map(|l| l.current_weapon_id != player.current_weapon_id)
-                .unwrap_or(true)
-            {
+            if changes.contains(ChangeMask::WEAPON) {
                 events.push(SyncEvent::WeaponChanged {
                     player_id,
                     weapon_id: player.current_weapon_id,
+                    equip_time: logic::equip_remaining(player, now),
                 });
             }
 
-            if last
-                .map(|l| l.is_reloading != player.is_reloading)
-                .unwrap_or(true)
-            {
+            if changes.contains(ChangeMask::RELOAD) {
                 events.push(SyncEvent::ReloadStateChanged {
                     player_id,
                     is_reloading: player.is_reloading,
@@ -66,18 +57,51 @@ pub fn collect_dirty_events(lobby: &mut Lobby) -> SmallEventVec {
             }
 
             // Position changes are handled separately (more frequent)
-            // Only sync position if it's a new player or significant change
-
-            // Update last sync state
-            lobby
-                .last_sync_state
-                .insert(player_id, player.to_sync_state());
         }
     }
 
     events
 }
 
+/// One `HitMarkers` per attacker, summing up the hits and kills among a tick's events
+/// Automatic weapons land several hits a tick; shooters get them as one marker per target
+fn hit_markers(events: &[SyncEvent]) -> Vec<SyncEvent> {
+    let mut markers: Vec<(u32, Vec<HitMarker>)> = Vec::new();
+    for event in events {
+        let (attacker_id, target_id, damage, killed) = match event {
+            SyncEvent::HitConfirmed { attacker_id, target_id, damage, .. } => (*attacker_id, *target_id, Some(*damage), false),
+            SyncEvent::PlayerKilled { killer_id, victim_id, .. } => (*killer_id, *victim_id, None, true),
+            _ => continue,
+        };
+        let hits = match markers.iter_mut().find(|(id, _)| *id == attacker_id) {
+            Some((_, hits)) => hits,
+            None => {
+                markers.push((attacker_id, Vec::new()));
+                &mut markers.last_mut().unwrap().1
+            }
+        };
+        let hit = match hits.iter_mut().position(|h| h.target_id == target_id) {
+            Some(index) => &mut hits[index],
+            None => {
+                hits.push(HitMarker { target_id, damage: 0, hits: 0, killed: false });
+                hits.last_mut().unwrap()
+            }
+        };
+        if let Some(damage) = damage {
+            hit.damage += damage;
+            hit.hits += 1;
+        }
+        hit.killed |= killed;
+    }
+    // Kills without a hit this tick (from projectiles landed earlier, say) don't make a marker
+    markers.into_iter()
+        .filter_map(|(attacker_id, hits)| {
+            let hits: Vec<HitMarker> = hits.into_iter().filter(|h| h.hits > 0).collect();
+            (!hits.is_empty()).then_some(SyncEvent::HitMarkers { attacker_id, hits })
+        })
+        .collect()
+}
+
 /// Collect position updates for players (separate from state sync)
 pub fn collect_position_events(lobby: &Lobby, player_ids: &[u32]) -> SmallEventVec {
     let mut events = SmallEventVec::new();
@@ -106,7 +130,7 @@ mod tests {
         let mut lobby = Lobby::new("TEST".to_string(), 4, "world".to_string());
 
         // Add player
-        let mut player = crate::state::lobby::Player {
+        let player = crate::state::lobby::Player {
             id: 1,
             name: "Test".to_string(),
             position: (0.0, 1.0, 0.0),
@@ -127,6 +151,37 @@ mod tests {
             warned_at: None,
             is_dead: false,
             respawn_time: None,
+            vertical_velocity: 0.0,
+            fall_speed: 0.0,
+            last_position_time: None,
+            last_whisper_time: SystemTime::UNIX_EPOCH,
+            changes: crate::state::lobby::ChangeMask::empty(),
+            trigger_held: false,
+            fire_target: None,
+            burst_remaining: 0,
+            overheal_decay: 0.0,
+            match_stats: crate::state::lobby::MatchStats::default(),
+            rtt_ms: None,
+            ready: false,
+            spawn_protection_until: None,
+            anticheat_strikes: 0,
+            pending_loadout: None,
+            quaternion_rotation: false,
+            message_keys: false,
+            last_activity: SystemTime::now(),
+            afk_warned: false,
+            spectating: false,
+            command_last_used: Default::default(),
+            equip_end_time: None,
+            active_loadout: None,
+            preferences: Default::default(),
+            armor: 0,
+            region: None,
+            carried_weapon: None,
+            party_id: None,
+            heat: 0.0,
+            overheated_until: None,
+            client_id: None,
         };
         lobby.players.insert(1, player);
         lobby.mark_dirty(1);
@@ -140,7 +195,7 @@ mod tests {
     fn test_collect_dirty_events_no_changes() {
         let mut lobby = Lobby::new("TEST".to_string(), 4, "world".to_string());
 
-        let mut player = crate::state::lobby::Player {
+        let player = crate::state::lobby::Player {
             id: 1,
             name: "Test".to_string(),
             position: (0.0, 1.0, 0.0),
@@ -161,22 +216,106 @@ mod tests {
             warned_at: None,
             is_dead: false,
             respawn_time: None,
+            vertical_velocity: 0.0,
+            fall_speed: 0.0,
+            last_position_time: None,
+            last_whisper_time: SystemTime::UNIX_EPOCH,
+            changes: crate::state::lobby::ChangeMask::empty(),
+            trigger_held: false,
+            fire_target: None,
+            burst_remaining: 0,
+            overheal_decay: 0.0,
+            match_stats: crate::state::lobby::MatchStats::default(),
+            rtt_ms: None,
+            ready: false,
+            spawn_protection_until: None,
+            anticheat_strikes: 0,
+            pending_loadout: None,
+            quaternion_rotation: false,
+            message_keys: false,
+            last_activity: SystemTime::now(),
+            afk_warned: false,
+            spectating: false,
+            command_last_used: Default::default(),
+            equip_end_time: None,
+            active_loadout: None,
+            preferences: Default::default(),
+            armor: 0,
+            region: None,
+            carried_weapon: None,
+            party_id: None,
+            heat: 0.0,
+            overheated_until: None,
+            client_id: None,
         };
         lobby.players.insert(1, player);
 
-        // Set last sync state to match current
-        lobby
-            .last_sync_state
-            .insert(1, lobby.players.get(&1).unwrap().to_sync_state());
-
-        // Mark dirty but no actual changes
+        // First collect sends the full initial state
         lobby.mark_dirty(1);
+        assert!(!collect_dirty_events(&mut lobby).is_empty());
 
+        // Still listed as dirty but the mask was consumed
         let events = collect_dirty_events(&mut lobby);
         // Should have no events since nothing changed
         assert!(events.is_empty());
     }
 
+    #[test]
+    fn test_collect_dirty_events_only_changed_fields() {
+        let mut lobby = Lobby::new("TEST".to_string(), 4, "world".to_string());
+        lobby.players.insert(1, crate::state::lobby::Player::new_player(1, "Test".to_string(), 1, 20));
+
+        lobby.mark_changed(1, ChangeMask::AMMO);
+        lobby.mark_changed(1, ChangeMask::POSITION);
+
+        let events = collect_dirty_events(&mut lobby);
+        assert_eq!(events.len(), 1);
+        assert!(matches!(events[0], SyncEvent::AmmoChanged { player_id: 1, ammo: 20, overheated: false, .. }));
+    }
+
+    #[test]
+    fn test_collect_dirty_events_flushes_pending() {
+        let mut lobby = Lobby::new("TEST".to_string(), 4, "world".to_string());
+        lobby.push_event(SyncEvent::MatchEnded { ranked: false, standings: Vec::new(), awards: Vec::new() });
+
+        let events = collect_dirty_events(&mut lobby);
+        assert_eq!(events.len(), 1);
+        assert!(matches!(events[0], SyncEvent::MatchEnded { .. }));
+        assert!(lobby.pending_events.is_empty());
+    }
+
+    #[test]
+    fn test_hits_aggregated_per_shooter_and_target() {
+        let mut lobby = Lobby::new("TEST".to_string(), 4, "world".to_string());
+        let hit = |attacker_id, target_id, damage| SyncEvent::HitConfirmed { attacker_id, target_id, damage, multiplier: 1.0, pellets: Vec::new(), armor_absorbed: 0, target_armor: 0 };
+        for event in [hit(1, 2, 10), hit(1, 3, 10), hit(1, 2, 15), hit(4, 2, 5)] {
+            lobby.push_event(event);
+        }
+        lobby.push_event(SyncEvent::PlayerKilled {
+            killer_id: 1,
+            killer_name: "A".to_string(),
+            victim_id: 2,
+            victim_name: "B".to_string(),
+            weapon_id: 1,
+            weapon_name: "Pistol".to_string(),
+            killer_killstreak: 1,
+        });
+
+        let events = collect_dirty_events(&mut lobby);
+        let markers: Vec<(u32, Vec<HitMarker>)> = events.iter().filter_map(|e| match e {
+            SyncEvent::HitMarkers { attacker_id, hits } => Some((*attacker_id, hits.clone())),
+            _ => None,
+        }).collect();
+        assert_eq!(markers, vec![
+            (1, vec![
+                HitMarker { target_id: 2, damage: 25, hits: 2, killed: true },
+                HitMarker { target_id: 3, damage: 10, hits: 1, killed: false },
+            ]),
+            (4, vec![HitMarker { target_id: 2, damage: 5, hits: 1, killed: false }]),
+        ]);
+        assert_eq!(events[5].recipient(), Some(1));
+    }
+
     #[test]
     fn test_collect_position_events() {
         let lobby = Lobby::new("TEST".to_string(), 4, "world".to_string());
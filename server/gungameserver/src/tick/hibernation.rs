@@ -0,0 +1,86 @@
+use crate::state::commands::LobbyCommand;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+
+/// How often a hibernating lobby still ticks (cleanup, listing refresh, HTTP joins)
+pub const IDLE_TICK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Tracks how long a lobby has been empty and whether it has dropped to idle ticking
+#[derive(Debug, Clone)]
+pub struct Hibernation {
+    /// None: never hibernate
+    after: Option<Duration>,
+    empty_since: Option<Instant>,
+    hibernating: bool,
+}
+
+impl Hibernation {
+    pub fn new(after: Option<Duration>) -> Self {
+        Self { after, empty_since: None, hibernating: false }
+    }
+
+    pub fn is_hibernating(&self) -> bool {
+        self.hibernating
+    }
+
+    /// Record whether the lobby had players this tick
+    /// Hibernates once it has been empty long enough; players wake it
+    pub fn observe(&mut self, occupied: bool, now: Instant) {
+        if occupied {
+            self.empty_since = None;
+            self.hibernating = false;
+            return;
+        }
+        let empty_since = *self.empty_since.get_or_insert(now);
+        if let Some(after) = self.after {
+            self.hibernating = now.duration_since(empty_since) >= after;
+        }
+    }
+
+    /// A command arrived; tick at full rate until the lobby is seen empty again
+    pub fn wake(&mut self) {
+        self.hibernating = false;
+    }
+}
+
+/// Wait out one idle tick; returns early with the first command that arrives
+pub async fn idle_wait(command_rx: &mut mpsc::Receiver<LobbyCommand>) -> Option<LobbyCommand> {
+    tokio::select! {
+        Some(cmd) = command_rx.recv() => Some(cmd),
+        _ = tokio::time::sleep(IDLE_TICK_INTERVAL) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hibernates_after_empty_period_and_wakes_on_players() {
+        let start = Instant::now();
+        let mut hibernation = Hibernation::new(Some(Duration::from_secs(30)));
+        hibernation.observe(false, start);
+        hibernation.observe(false, start + Duration::from_secs(29));
+        assert!(!hibernation.is_hibernating());
+        hibernation.observe(false, start + Duration::from_secs(30));
+        assert!(hibernation.is_hibernating());
+
+        hibernation.observe(true, start + Duration::from_secs(31));
+        assert!(!hibernation.is_hibernating());
+        // The empty period starts over after players were seen
+        hibernation.observe(false, start + Duration::from_secs(32));
+        assert!(!hibernation.is_hibernating());
+
+        let mut never = Hibernation::new(None);
+        never.observe(false, start);
+        never.observe(false, start + Duration::from_secs(3600));
+        assert!(!never.is_hibernating());
+    }
+
+    #[tokio::test]
+    async fn test_idle_wait_returns_first_command() {
+        let (tx, mut rx) = mpsc::channel(4);
+        tx.send(LobbyCommand::EndMatch).await.unwrap();
+        assert!(matches!(idle_wait(&mut rx).await, Some(LobbyCommand::EndMatch)));
+    }
+}
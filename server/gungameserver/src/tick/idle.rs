@@ -0,0 +1,99 @@
+use crate::domain::bots;
+use crate::state::lobby::{GameMode, Lobby, MatchPhase};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Calm lobbies still run every this many ticks in full, so time-based housekeeping
+/// (inactivity cleanup, AFK, votes, loot expiry, pickup respawns, time caps, listing refresh) keeps going
+pub const FULL_TICK_EVERY: u64 = 10;
+
+/// Ticks run and calm ticks skipped across all lobbies, for /metrics
+#[derive(Debug)]
+pub struct TickCounters {
+    ticks: AtomicU64,
+    skipped: AtomicU64,
+}
+
+impl TickCounters {
+    const fn new() -> Self {
+        Self { ticks: AtomicU64::new(0), skipped: AtomicU64::new(0) }
+    }
+
+    pub fn record(&self, skipped: bool) {
+        self.ticks.fetch_add(1, Ordering::Relaxed);
+        if skipped {
+            self.skipped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub fn ticks(&self) -> u64 {
+        self.ticks.load(Ordering::Relaxed)
+    }
+
+    pub fn skipped(&self) -> u64 {
+        self.skipped.load(Ordering::Relaxed)
+    }
+}
+
+pub static COUNTERS: TickCounters = TickCounters::new();
+
+/// Whether a tick with no commands would change nothing: nothing to broadcast, nothing in flight,
+/// no timers or clocks running down
+/// On such ticks the loop only records kill cam history and moves on
+pub fn is_calm(lobby: &Lobby) -> bool {
+    let nothing_pending = lobby.dirty_players.is_empty()
+        && lobby.pending_events.is_empty()
+        && lobby.evicted_bots.is_empty()
+        && lobby.transfers_in.is_empty()
+        && lobby.transfers_out.is_empty()
+        && lobby.loot.pending_deaths.is_empty();
+    let nothing_in_flight = lobby.projectiles.active.is_empty()
+        && lobby.timers.reloads.is_empty()
+        && lobby.timers.respawns.is_empty()
+        && lobby.timers.spawn_protection.is_empty();
+    // Votes and countdowns run down every tick; zone scoring, duel rounds and scripts tick while live
+    let live_per_tick = lobby.is_match_live()
+        && (lobby.settings.game_mode != GameMode::Deathmatch || lobby.rules_script.is_some());
+    let nothing_counting = lobby.phase != MatchPhase::Countdown
+        && lobby.active_vote.is_none()
+        && !live_per_tick
+        && lobby.observers.is_empty();
+    // Bots, held triggers, cooling barrels, overheal and weapon draws change players without input
+    let players_still = lobby.players.values().all(|p| {
+        !bots::is_bot(p.id)
+            && !p.trigger_held
+            && p.burst_remaining == 0
+            && p.heat == 0.0
+            && p.overheated_until.is_none()
+            && p.equip_end_time.is_none()
+            && p.current_health <= p.max_health
+    });
+    nothing_pending && nothing_in_flight && nothing_counting && players_still
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::buffers::SyncEvent;
+
+    #[test]
+    fn test_calm_until_something_moves() {
+        let mut lobby = Lobby::new("IDLE".to_string(), 4, "world".to_string());
+        lobby.players.insert(1, Lobby::new_player(1, "P1".to_string(), 1, 20));
+        assert!(is_calm(&lobby));
+
+        lobby.players.get_mut(&1).unwrap().trigger_held = true;
+        assert!(!is_calm(&lobby));
+        lobby.players.get_mut(&1).unwrap().trigger_held = false;
+
+        lobby.push_event(SyncEvent::CountdownCancelled);
+        assert!(!is_calm(&lobby));
+        lobby.pending_events.clear();
+
+        lobby.phase = MatchPhase::Countdown;
+        assert!(!is_calm(&lobby));
+        lobby.phase = MatchPhase::InProgress;
+        assert!(is_calm(&lobby));
+        lobby.settings.game_mode = GameMode::KingOfTheHill;
+        assert!(!is_calm(&lobby));
+    }
+}
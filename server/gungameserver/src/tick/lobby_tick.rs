@@ -2,16 +2,47 @@ use std::sync::Arc;
 use tokio::sync::{RwLock, mpsc};
 use tokio::net::UdpSocket;
 use tokio::time::{interval, Duration};
-use crate::state::lobby::Lobby;
+use crate::state::lobby::{Lobby, Player};
 use crate::state::commands::{LobbyCommand, drain_and_coalesce};
 use crate::state::server_state::ServerState;
+use crate::state::bandwidth::BandwidthTracker;
+use crate::state::chat_channels;
 use crate::domain::lobbies;
 use crate::domain::logic;
+use crate::domain::{chat, chat_commands};
+use crate::domain::{loot, pickups};
+use crate::domain::latency;
+use crate::domain::analytics;
+use crate::domain::history;
+use crate::domain::clock_sync::{self, TimeSyncReply};
+use crate::domain::{duel, zone_control};
+use crate::domain::{afk, bots, lifetime};
+use crate::domain::emotes;
+use crate::domain::streaming;
+use crate::domain::messages;
+use crate::domain::observers;
+use crate::domain::armor;
+use crate::domain::preferences::{self, Preferences};
+use crate::domain::{teams, weapon_bans};
+use crate::domain::connectivity::{self, DEGRADED_AFTER_MISSED, LOST_AFTER_MISSED};
+use crate::domain::rotation;
+use crate::domain::{awards, scripting};
+use crate::utils::log_context;
+use crate::utils::telemetry::{Span, SpanKind};
+use crate::domain::timeline::MatchTimeline;
+use crate::domain::validation::{self, ViolationKind};
+use crate::domain::votes::{self, VoteKind};
 use crate::tick::delta_sync;
-use crate::utils::weapondb::WeaponDb;
+use crate::tick::hibernation::{self, Hibernation};
+use crate::tick::idle;
+use crate::tick::outbound::{Datagrams, Outbox};
+use crate::tick::replication::ReplicationRecord;
+use crate::tick::replay;
+use crate::utils::weapondb::{WeaponDb, WeaponLookup, WeaponView};
 use crate::utils::config::Config;
 use crate::utils::buffers::{SyncEvent, PacketBuffer};
 use serde_json::json;
+use crate::utils::clock;
 
 /// Per-lobby tick loop - processes commands and broadcasts updates
 /// Runs at fixed tick rate (50Hz by default)
@@ -25,178 +56,554 @@ pub async fn lobby_tick_loop(
 ) {
     let tick_interval = Duration::from_millis(config.tick_interval_ms());
     let mut tick_timer = interval(tick_interval);
-    let mut send_buffer = PacketBuffer::default();
+    // Socket I/O happens on a separate sender task, never inside the tick
+    let (outbox, mut unreachable_rx) = Outbox::spawn_with_net_sim(socket, config.max_send_failures, config.net_sim);
+    let bandwidth = server_state.as_ref().map(|state| state.bandwidth.clone()).unwrap_or_default();
+    let outbox = outbox.with_bandwidth(bandwidth.clone(), config.max_player_bytes_per_sec);
+    let mut io = TickIo { outbox, bandwidth, send_buffer: PacketBuffer::default() };
     let lobby_code = lobby.read().await.code.clone();
+    let mut tick_count: u64 = 0;
+    let mut hibernation = Hibernation::new(config.hibernate_after_secs.map(Duration::from_secs));
     
     loop {
-        tick_timer.tick().await;
+        // Empty lobbies tick slowly until a command (or an HTTP join) wakes them
+        let woken_by = if hibernation.is_hibernating() {
+            let woken_by = hibernation::idle_wait(&mut command_rx).await;
+            if woken_by.is_some() {
+                hibernation.wake();
+                tick_timer.reset();
+                log::info!("Lobby {} woke from hibernation", lobby_code);
+            }
+            woken_by
+        } else {
+            tick_timer.tick().await;
+            None
+        };
         
         // 1. Drain commands (coalesce positions - keep only latest)
-        let commands = drain_and_coalesce(&mut command_rx);
+        let mut commands: Vec<LobbyCommand> = woken_by.into_iter().collect();
+        commands.extend(drain_and_coalesce(&mut command_rx));
         
         // 2. Acquire lock ONCE per tick
         let mut lobby_guard = lobby.write().await;
         
-        // Track players that joined/left this tick
-        let mut players_joined: Vec<(u32, String)> = Vec::new();
-        let mut players_left: Vec<u32> = Vec::new();
-        let mut position_updates: Vec<u32> = Vec::new();
-        let kill_events: Vec<logic::KillEvent> = Vec::new();
-        let mut respawn_events: Vec<u32> = Vec::new();
-        
-        // 3. Process all commands
-        for cmd in commands {
-            // Extract info before processing (to avoid borrow issues)
-            let join_info = if let LobbyCommand::PlayerJoin { player_id, ref name, addr } = &cmd {
-                Some((*player_id, name.clone(), *addr))
-            } else {
-                None
-            };
-            
-            let udp_connect_info = if let LobbyCommand::UdpConnect { player_id, ref name, addr } = &cmd {
-                Some((*player_id, name.clone(), *addr))
-            } else {
-                None
-            };
-            
-            let leave_id = if let LobbyCommand::PlayerLeave { player_id } = &cmd {
-                Some(*player_id)
-            } else {
-                None
-            };
-            
-            let position_id = if let LobbyCommand::PositionUpdate { player_id, .. } = &cmd {
-                Some(*player_id)
-            } else {
-                None
-            };
-            
-            // Process the command
-            process_command(&mut lobby_guard, &weapons, cmd, server_state.as_deref());
-            
-            // Handle special cases that need broadcasting
-            if let Some((player_id, name, addr)) = join_info {
-                players_joined.push((player_id, name.clone()));
-                // Send welcome message to new player with current lobby state
-                send_welcome_message(&lobby_guard, &socket, player_id, addr).await;
-            }
-            
-            if let Some((player_id, name, addr)) = udp_connect_info {
-                players_joined.push((player_id, name.clone()));
-                // For UDP connect, player already has scene info from HTTP join
-                // Just send acknowledgment without scene info to avoid scene reload
-                send_udp_connected_message(&lobby_guard, &socket, player_id, addr).await;
-                log::debug!("Player {} ({}) UDP connected, broadcasting join to lobby", player_id, name);
-            }
-            
-            if let Some(player_id) = leave_id {
-                players_left.push(player_id);
-            }
-            
-            if let Some(player_id) = position_id {
-                position_updates.push(player_id);
+        lobby_guard.current_tick = tick_count;
+        lobby_guard.tick_interval_ms = tick_interval.as_millis() as u64;
+        lobby_guard.heartbeat_interval_ms = config.heartbeat_interval_ms;
+
+        // Calm ticks skip straight to the next one; every FULL_TICK_EVERY still runs in full
+        let calm = commands.is_empty()
+            && !tick_count.is_multiple_of(idle::FULL_TICK_EVERY)
+            && unreachable_rx.is_empty()
+            && idle::is_calm(&lobby_guard);
+        idle::COUNTERS.record(calm);
+        if calm {
+            if !lobby_guard.is_paused() {
+                history::record_tick(&mut lobby_guard, tick_count);
             }
+            tick_count += 1;
+            continue;
         }
+
+        // Clients the sender gave up on leave like any other player
+        commands.extend(unreachable_leaves(&lobby_guard, &mut unreachable_rx));
+        // Gameplay reads one time for the whole tick, the one the replication stream records
+        let outcome = clock::frozen(std::time::SystemTime::now(), || {
+            run_tick(&mut lobby_guard, &weapons, &config, server_state.as_deref(), &mut io, commands)
+        });
         
-        // 4. Update reload timers
-        logic::update_reload_states(&mut lobby_guard);
-        
-        // 5. Check respawn timers for dead players
-        let now = std::time::SystemTime::now();
-        let mut players_to_respawn: Vec<u32> = Vec::new();
-        for (player_id, player) in &lobby_guard.players {
-            if player.is_dead {
-                if let Some(respawn_time) = player.respawn_time {
-                    if now >= respawn_time {
-                        players_to_respawn.push(*player_id);
-                    }
-                }
+        // 13. Refresh the listing snapshot periodically, and right away when membership changes
+        tick_count += 1;
+        if let Some(state) = &server_state {
+            if tick_count.is_multiple_of(SUMMARY_REFRESH_TICKS) || outcome.membership_changed {
+                state.publish_summary(&lobby_code, lobbies::summarize(&lobby_guard));
             }
         }
-        
-        // Respawn players and track events
-        for player_id in players_to_respawn {
-            if let Err(e) = logic::respawn_player(&mut lobby_guard, player_id) {
-                log::debug!("Respawn failed for player {}: {}", player_id, e);
-            } else {
-                respawn_events.push(player_id);
-                log::debug!("Player {} respawned in lobby {}", player_id, lobby_code);
-            }
+
+        // Clients were told with this tick's broadcast; the lobby stops ticking here
+        if outcome.closing {
+            close_lobby(&mut lobby_guard, server_state.as_deref());
+            return;
         }
-        
-        // 6. Cleanup inactive players periodically (every 5 seconds worth of ticks)
-        // Use a local counter that persists across ticks via closure
-        // For MVP, we'll do cleanup every tick (can be optimized later)
-        let (removed, _warned) = lobbies::cleanup_inactive(
-            &mut lobby_guard,
-            config.player_inactivity_timeout_secs,
-            0.5, // Warn at 50% of timeout
-        );
-        if !removed.is_empty() {
-            for player_id in &removed {
-                players_left.push(*player_id);
+
+        // 14. Drop to idle ticking once nobody has been here for a while
+        let was_hibernating = hibernation.is_hibernating();
+        let occupied = lobby_guard.players.keys().any(|id| *id != 999 && !bots::is_bot(*id)); // Exclude bots
+        hibernation.observe(occupied, std::time::Instant::now());
+        match (was_hibernating, hibernation.is_hibernating()) {
+            (false, true) => log::info!("Lobby {} hibernating while empty", lobby_code),
+            (true, false) => {
+                tick_timer.reset();
+                log::info!("Lobby {} woke from hibernation", lobby_code);
             }
+            _ => {}
+        }
+    }
+}
+
+/// Buffers and send handles one lobby's ticks share
+pub struct TickIo {
+    pub outbox: Outbox,
+    pub bandwidth: Arc<BandwidthTracker>,
+    pub send_buffer: PacketBuffer,
+}
+
+/// What a tick changed that the loop around it acts on
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TickOutcome {
+    pub membership_changed: bool,
+    pub closing: bool, // A lifetime cap closed the lobby; it stops ticking
+}
+
+/// One full (non-calm) tick: apply commands, simulate, and queue this tick's packets
+/// Expects `current_tick` and the tick and heartbeat intervals already set on the lobby
+pub fn run_tick(
+    lobby: &mut Lobby,
+    weapons: &WeaponDb,
+    config: &Config,
+    server_state: Option<&ServerState>,
+    io: &mut TickIo,
+    commands: Vec<LobbyCommand>,
+) -> TickOutcome {
+    let tick_count = lobby.current_tick;
+    let tick_interval = Duration::from_millis(config.tick_interval_ms());
+    let lobby_code = lobby.code.clone();
+    let replicator = server_state.and_then(|state| state.replicator());
+
+    // Sampled ticks are traced phase by phase
+    let mut tick_span = Span::root("lobby.tick", SpanKind::Internal, None);
+    if tick_span.is_recording() {
+        tick_span.set("lobby.code", lobby_code.to_string());
+        tick_span.set("lobby.tick", tick_count as i64);
+        tick_span.set("lobby.players", lobby.players.len() as i64);
+        tick_span.set("lobby.commands", commands.len() as i64);
+    }
+    
+    // Track players that joined/left this tick
+    let mut players_joined: Vec<(u32, String)> = Vec::new();
+    let mut players_left: Vec<u32> = Vec::new();
+    let mut position_updates: Vec<u32> = Vec::new();
+    let kill_events: Vec<logic::KillEvent> = Vec::new();
+    let mut respawn_events: Vec<u32> = Vec::new();
+    // Commands actually applied this tick, streamed to the standby
+    let mut applied: Vec<LobbyCommand> = Vec::new();
+    
+    // Paused lobbies resume on their own after the configured maximum
+    check_auto_resume(lobby, config.max_pause_secs);
+    
+    // 3. Process all commands
+    let phase = tick_span.child("tick.commands");
+    for cmd in commands {
+        let (correlation_id, cmd) = cmd.untrace();
+        // Gameplay is frozen while paused (chat and joins still flow)
+        if lobby.is_paused() && cmd.is_gameplay() {
+            continue;
+        }
+        // No combat before the ready check and countdown have finished
+        if !lobby.combat_allowed() && cmd.is_combat() {
+            continue;
         }
         
-        // 6. Broadcast player join/leave events
-        log::debug!("Lobby {} has {} players and {} addresses", 
-            lobby_code, lobby_guard.players.len(), lobby_guard.client_addresses.len());
-        log::debug!("Players: {:?}", lobby_guard.players.keys().collect::<Vec<_>>());
-        log::debug!("Addresses: {:?}", lobby_guard.client_addresses.iter()
-            .map(|(k, v)| (k, format!("{}", v)))
-            .collect::<Vec<_>>());
+        // Extract info before processing (to avoid borrow issues)
+        let join_info = if let LobbyCommand::PlayerJoin { player_id, ref name, addr } = &cmd {
+            Some((*player_id, name.clone(), *addr))
+        } else {
+            None
+        };
         
-        if !players_joined.is_empty() {
-            log::debug!("Broadcasting player joins: {:?}", players_joined);
-            broadcast_player_join_events(&lobby_guard, &socket, &players_joined).await;
-        }
-        if !players_left.is_empty() {
-            log::debug!("Broadcasting player leaves: {:?}", players_left);
-            broadcast_player_leave_events(&lobby_guard, &socket, &players_left).await;
+        let udp_connect_info = if let LobbyCommand::UdpConnect { player_id, ref name, addr, ref client_id } = &cmd {
+            // Checked before the command rebinds the player's address
+            let resumed = lobbies::is_session_resume(lobby, *player_id, client_id.as_deref(), *addr);
+            Some((*player_id, name.clone(), *addr, resumed))
+        } else {
+            None
+        };
+        
+        let leave_id = if let LobbyCommand::PlayerLeave { player_id } | LobbyCommand::Kick { player_id, .. } = &cmd {
+            Some(*player_id)
+        } else {
+            None
+        };
+        
+        let position_id = if let LobbyCommand::PositionUpdate { player_id, .. } = &cmd {
+            Some(*player_id)
+        } else {
+            None
+        };
+        
+        if replicator.is_some() {
+            applied.push(cmd.clone());
         }
         
-        // 7. Broadcast position updates (every tick for players that moved)
-        if !position_updates.is_empty() {
-            // log::debug!("Broadcasting position updates for {} players: {:?}", position_updates.len(), position_updates);
-            broadcast_position_updates(&lobby_guard, &socket, &position_updates).await;
+        // Process the command (its logs carry the originating packet's correlation id)
+        let name = cmd.name();
+        log_context::in_command(correlation_id, name, || {
+            process_command(lobby, weapons, cmd, server_state);
+        });
+        
+        // Handle special cases that need broadcasting
+        if let Some((player_id, name, addr)) = join_info {
+            io.outbox.reset(addr);
+            io.bandwidth.bind(addr, player_id, &lobby_code);
+            players_joined.push((player_id, name.clone()));
+            // Send welcome message to new player with current lobby state
+            send_welcome_message(lobby, weapons, &io.outbox, player_id, addr);
         }
         
-        // 8. Broadcast kill events
-        if !kill_events.is_empty() {
-            for kill_event in &kill_events {
-                broadcast_kill_event(&lobby_guard, &socket, kill_event).await;
+        if let Some((player_id, name, addr, resumed)) = udp_connect_info {
+            io.outbox.reset(addr);
+            io.bandwidth.bind(addr, player_id, &lobby_code);
+            if resumed {
+                // Same client back from a crash: resync it in full without announcing a second join
+                send_welcome_message(lobby, weapons, &io.outbox, player_id, addr);
+                send_player_state(lobby, &io.outbox, player_id, addr);
+                log::info!("Player {} ({}) resumed their session from {}", player_id, name, addr);
+            } else {
+                players_joined.push((player_id, name.clone()));
+                // For UDP connect, player already has scene info from HTTP join
+                // Just send acknowledgment without scene info to avoid scene reload
+                send_udp_connected_message(lobby, &io.outbox, player_id, addr);
+                log::debug!("Player {} ({}) UDP connected, broadcasting join to lobby", player_id, name);
             }
         }
         
-        // 9. Broadcast respawn events
-        if !respawn_events.is_empty() {
-            broadcast_respawn_events(&lobby_guard, &socket, &respawn_events).await;
+        if let Some(player_id) = leave_id {
+            players_left.push(player_id);
         }
         
-        // 10. Delta sync - only send changes (health, ammo, weapon, reload)
-        let state_events = delta_sync::collect_dirty_events(&mut lobby_guard);
-        
-        // 11. Broadcast state events (reuse buffer)
-        if !state_events.is_empty() {
-            broadcast_state_events(&lobby_guard, &socket, &state_events, &mut send_buffer).await;
+        if let Some(player_id) = position_id {
+            position_updates.push(player_id);
         }
-        
-        // 12. Record stats to global stats and clear dirty flags
-        if let Some(ref state) = server_state {
-            for player_id in &players_left {
-                if let Some(player) = lobby_guard.players.get(player_id) {
-                    state.global_stats.record_session(
-                        player.id,
-                        &player.name,
-                        player.kills,
-                        player.deaths,
-                        player.score,
-                    );
-                }
+    }
+    
+    // Addresses and keepalives the UDP handler recorded since the last tick
+    let presence = lobby.presence.clone();
+    let reports = presence.apply(lobby);
+    send_rebind_challenges(lobby, &io.outbox, clock::now());
+    phase.end();
+    
+    // Everything else the tick does follows from these and the clock, so this is enough to replay it
+    if let Some(replicator) = replicator {
+        replicator.publish(ReplicationRecord::Tick {
+            code: lobby_code.clone(),
+            tick: tick_count,
+            at: Some(clock::now()),
+            commands: applied,
+            presence: reports,
+        });
+    }
+    
+    // 4. Update match phase and reload timers (frozen while paused)
+    let phase = tick_span.child("tick.simulate");
+    let paused = lobby.is_paused();
+    if !paused {
+        lobbies::update_match_phase(lobby, tick_interval.as_secs_f32(), config.ready_quorum, config.countdown_secs);
+        logic::update_reload_states(lobby);
+        logic::update_equip_states(lobby, clock::now());
+        logic::update_spawn_protection(lobby, clock::now());
+        // Held triggers and queued burst rounds fire across ticks
+        let overlay = lobby.settings.weapons.clone();
+        let weapon_view = WeaponView::new(weapons, &overlay);
+        logic::update_heat(lobby, &weapon_view, tick_interval.as_secs_f32(), clock::now());
+        logic::update_automatic_fire(lobby, &weapon_view);
+        logic::update_projectiles(lobby, &weapon_view, tick_interval.as_secs_f32());
+        loot::update(lobby, config.loot_drops.as_ref(), Duration::from_secs(config.loot_lifetime_secs), clock::now());
+        pickups::update(lobby, clock::now());
+        logic::decay_overheal(lobby, tick_interval.as_secs_f32());
+        if lobby.is_match_live() {
+            scripting::on_tick(lobby, tick_interval.as_secs_f32());
+        }
+        if let Some(winner) = zone_control::update(lobby, tick_interval.as_secs_f32()) {
+            log::info!("Player {} reached the zone score limit in lobby {}", winner, lobby_code);
+            process_command(lobby, weapons, LobbyCommand::EndMatch, server_state);
+        }
+        if let Some(winner) = duel::update(lobby, &weapon_view, tick_interval.as_secs_f32()) {
+            log::info!("Player {} won the duel in lobby {}", winner, lobby_code);
+            process_command(lobby, weapons, LobbyCommand::EndMatch, server_state);
+        }
+    }
+
+    // Hard caps on match and lobby lifetime run on wall-clock time, paused or not
+    let caps = lifetime::LifetimeCaps::resolve(config, &lobby.settings);
+    let closing = match lifetime::check(lobby, &caps, clock::now()) {
+        Some((expiry, cap)) => {
+            expire_at_cap(lobby, weapons, expiry, cap, server_state);
+            expiry == lifetime::Expiry::Lobby
+        }
+        None => false,
+    };
+    
+    // 5. Check respawn timers for dead players
+    let now = clock::now();
+    let players_to_respawn = if paused { Vec::new() } else { logic::due_respawns(lobby, now) };
+    
+    // Respawn players and track events
+    let overlay = lobby.settings.weapons.clone();
+    let weapon_view = WeaponView::new(weapons, &overlay);
+    for player_id in players_to_respawn {
+        if let Err(e) = logic::respawn_player(lobby, player_id) {
+            log::debug!("Respawn failed for player {}: {}", player_id, e);
+        } else {
+            if let Err(e) = logic::rearm(lobby, &weapon_view, player_id) {
+                log::debug!("Player {} respawned unarmed: {}", player_id, e);
+            }
+            if let Err(e) = logic::apply_pending_loadout(lobby, &weapon_view, player_id) {
+                log::debug!("Loadout not applied for player {}: {}", player_id, e);
             }
+            respawn_events.push(player_id);
+            log::debug!("Player {} respawned in lobby {}", player_id, lobby_code);
         }
-        
-        lobby_guard.clear_dirty();
+    }
+    
+    // Heatmap samples (only while the match is live)
+    if !paused && tick_count.is_multiple_of(analytics::SAMPLE_EVERY_TICKS) {
+        analytics::record_positions(lobby, tick_count);
+    }
+
+    // Recent positions for kill cams
+    if !paused {
+        history::record_tick(lobby, tick_count);
+    }
+    
+    // Votes keep running while paused so a stuck lobby can still vote to restart
+    if let Some(outcome) = votes::update_vote(lobby, tick_interval.as_secs_f32()) {
+        if let Some(kicked) = execute_vote(lobby, weapons, outcome, server_state) {
+            players_left.push(kicked);
+        }
+    }
+    
+    phase.end();
+
+    // 6. Cleanup inactive players periodically (every 5 seconds worth of ticks)
+    let phase = tick_span.child("tick.cleanup");
+    // Use a local counter that persists across ticks via closure
+    // For MVP, we'll do cleanup every tick (can be optimized later)
+    let (removed, _warned) = lobbies::cleanup_inactive(
+        lobby,
+        config.player_inactivity_timeout_secs,
+        0.5, // Warn at 50% of timeout
+        server_state,
+    );
+    if !removed.is_empty() {
+        for player_id in &removed {
+            players_left.push(*player_id);
+        }
+    }
+
+    connectivity::update(lobby, Duration::from_millis(config.heartbeat_interval_ms), now);
+
+    if let Some(spectate_secs) = config.afk_spectate_secs {
+        let limits = afk::AfkLimits {
+            spectate_after: Duration::from_secs(spectate_secs),
+            kick_after: Duration::from_secs(config.afk_kick_secs),
+            warning: Duration::from_secs(config.afk_warning_secs),
+        };
+        let kicked = afk::update(lobby, &limits, now, server_state);
+        players_left.extend(kicked);
+    }
+
+    // Merges and splits move players between lobbies outside the command queue
+    players_joined.extend(std::mem::take(&mut lobby.transfers_in));
+    players_left.extend(std::mem::take(&mut lobby.transfers_out));
+
+    // Top bots up (or thin them out) after this tick's joins and leaves
+    let (bots_joined, bots_left) = bots::update(lobby, &weapon_view);
+    players_joined.extend(bots_joined);
+    players_left.extend(bots_left);
+    phase.end();
+    
+    // 6. Broadcast player join/leave events
+    let phase = tick_span.child("tick.broadcast");
+    log::debug!("Lobby {} has {} players and {} addresses", 
+        lobby_code, lobby.players.len(), lobby.client_addresses.len());
+    log::debug!("Players: {:?}", lobby.players.keys().collect::<Vec<_>>());
+    log::debug!("Addresses: {:?}", lobby.client_addresses.iter()
+        .map(|(k, v)| (k, format!("{}", v)))
+        .collect::<Vec<_>>());
+    
+    if !players_joined.is_empty() {
+        log::debug!("Broadcasting player joins: {:?}", players_joined);
+        broadcast_player_join_events(lobby, &io.outbox, &players_joined);
+    }
+    if !players_left.is_empty() {
+        log::debug!("Broadcasting player leaves: {:?}", players_left);
+        broadcast_player_leave_events(lobby, &io.outbox, &players_left);
+    }
+    
+    // 7. Broadcast position updates (every tick for players that moved)
+    if !position_updates.is_empty() {
+        // log::debug!("Broadcasting position updates for {} players: {:?}", position_updates.len(), position_updates);
+        broadcast_position_updates(lobby, &io.outbox, &position_updates);
+    }
+    
+    // 8. Broadcast kill events
+    if !kill_events.is_empty() {
+        for kill_event in &kill_events {
+            broadcast_kill_event(lobby, &io.outbox, kill_event);
+        }
+    }
+    
+    // 9. Broadcast respawn events
+    if !respawn_events.is_empty() {
+        broadcast_respawn_events(lobby, &io.outbox, &respawn_events);
+    }
+    
+    // 10. Delta sync - only send changes (health, ammo, weapon, reload)
+    let state_events = delta_sync::collect_dirty_events(lobby);
+    
+    // Casters can scrub through the match while it's live
+    if let Some(state) = server_state {
+        record_timeline(lobby, state, tick_count, &state_events);
+    }
+    
+    // 11. Broadcast state events (reuse buffer)
+    if !state_events.is_empty() {
+        broadcast_state_events(lobby, &io.outbox, &state_events, &mut io.send_buffer);
+    }
+    
+    // Observers get one summary packet at their own, lower rate
+    send_observer_snapshots(lobby, &io.outbox, clock::now());
+    phase.end();
+
+    // 12. Clear dirty flags (sessions are recorded as players are removed)
+    lobby.clear_dirty();
+
+    if let Some(replicator) = replicator {
+        if tick_count.is_multiple_of(replay::STATE_HASH_EVERY) {
+            replicator.publish(ReplicationRecord::StateHash { code: lobby_code.clone(), tick: tick_count, hash: replay::state_hash(lobby) });
+        }
+    }
+
+    TickOutcome { membership_changed: !players_joined.is_empty() || !players_left.is_empty(), closing }
+}
+
+/// Force-end the match at a time cap with the standings so far, and have the stats written out
+fn expire_at_cap(
+    lobby: &mut Lobby,
+    weapons: &WeaponDb,
+    expiry: lifetime::Expiry,
+    cap: Duration,
+    server_state: Option<&ServerState>,
+) {
+    lifetime::expire(lobby, expiry, cap);
+    if lobby.is_match_live() {
+        process_command(lobby, weapons, LobbyCommand::EndMatch, server_state);
+    }
+    if let Some(state) = server_state {
+        state.global_stats.request_flush();
+    }
+}
+
+/// Remove everyone and unregister a lobby past its lifetime cap
+fn close_lobby(lobby: &mut Lobby, server_state: Option<&ServerState>) {
+    let player_ids: Vec<u32> = lobby.players.keys().copied().collect();
+    for player_id in player_ids {
+        lobbies::leave_lobby(lobby, player_id, server_state);
+    }
+    if let Some(state) = server_state {
+        state.remove_lobby(&lobby.code);
+        if let Some(replicator) = state.replicator() {
+            replicator.publish(ReplicationRecord::LobbyRemoved { code: lobby.code.clone() });
+        }
+    }
+    log::info!("Lobby {} closed at its lifetime cap", lobby.code);
+}
+
+/// Ticks between listing snapshot refreshes (~200ms at 50Hz)
+const SUMMARY_REFRESH_TICKS: u64 = 10;
+
+/// Resume a lobby that has been paused longer than the allowed maximum
+fn check_auto_resume(lobby: &mut Lobby, max_pause_secs: u64) {
+    let Some(paused_at) = lobby.paused_at else {
+        return;
+    };
+    let now = clock::now();
+    let elapsed = now.duration_since(paused_at).unwrap_or_default();
+    if elapsed.as_secs() < max_pause_secs {
+        return;
+    }
+
+    if let Ok(paused_for) = lobbies::resume(lobby, now) {
+        log::info!("Lobby {} auto-resumed after {:.0}s pause", lobby.code, paused_for.as_secs_f32());
+        lobby.push_event(SyncEvent::GameResumed { resumed_by: None, paused_secs: paused_for.as_secs_f32() });
+    }
+}
+
+/// Carry out a passed vote through the regular lobby APIs
+/// Returns the kicked player, if any
+fn execute_vote(
+    lobby: &mut Lobby,
+    weapons: &WeaponDb,
+    outcome: VoteKind,
+    server_state: Option<&ServerState>,
+) -> Option<u32> {
+    log::info!("Vote passed in lobby {}: {:?}", lobby.code, outcome);
+    match outcome {
+        VoteKind::Kick { target_id } => {
+            lobbies::leave_lobby(lobby, target_id, server_state)?;
+            lobby.push_event(SyncEvent::PlayerKicked { player_id: target_id, reason: votes::VOTE_KICK_REASON.to_string() });
+            Some(target_id)
+        }
+        VoteKind::ChangeScene { scene } => {
+            lobbies::change_scene(lobby, scene);
+            None
+        }
+        VoteKind::RestartMatch => {
+            process_command(lobby, weapons, LobbyCommand::EndMatch, server_state);
+            None
+        }
+    }
+}
+
+/// Append this tick's lobby-wide events to the match timeline
+/// A timeline opens on the first live tick and is archived on the tick the match ends
+fn record_timeline(lobby: &mut Lobby, state: &ServerState, tick: u64, events: &[SyncEvent]) {
+    let live = lobby.is_match_live();
+    if live && lobby.timeline.is_none() {
+        let match_id = state.begin_match(&lobby.code);
+        lobby.timeline = Some(MatchTimeline::new(match_id, lobby.code.clone(), tick));
+    }
+    if lobby.timeline.is_none() {
+        return;
+    }
+
+    // Whispers are private, and muzzle flashes aren't worth a timeline entry each
+    let packets: Vec<serde_json::Value> = events.iter()
+        .filter(|event| event.recipient().is_none() && !matches!(event, SyncEvent::PlayerShot { .. }))
+        .filter_map(|event| event_packet(lobby, event))
+        .collect();
+    if let Some(timeline) = lobby.timeline.as_mut() {
+        timeline.advance(tick);
+        for packet in packets {
+            timeline.record(packet);
+        }
+    }
+    if !live {
+        if let Some(timeline) = lobby.timeline.take() {
+            state.finish_match(timeline);
+        }
+    }
+}
+
+/// Turn addresses reported unreachable by the sender into PlayerLeave commands
+fn unreachable_leaves(
+    lobby: &Lobby,
+    unreachable_rx: &mut mpsc::UnboundedReceiver<std::net::SocketAddr>,
+) -> Vec<LobbyCommand> {
+    let mut leaves = Vec::new();
+    while let Ok(addr) = unreachable_rx.try_recv() {
+        for (player_id, _) in lobby.client_addresses.iter().filter(|(_, a)| **a == addr) {
+            log::info!("Disconnecting unreachable player {} ({}) from lobby {}", player_id, addr, lobby.code);
+            leaves.push(LobbyCommand::PlayerLeave { player_id: *player_id });
+        }
+    }
+    leaves
+}
+
+/// Process commands outside a running tick loop (no server state), leaving their events pending
+pub fn apply_commands(lobby: &mut Lobby, weapons: &WeaponDb, commands: Vec<LobbyCommand>) {
+    for cmd in commands {
+        process_command(lobby, weapons, cmd, None);
     }
 }
 
@@ -207,10 +614,23 @@ fn process_command(
     cmd: LobbyCommand,
     server_state: Option<&ServerState>,
 ) {
+    // All weapon reads go through the lobby's overrides
+    let overlay = lobby.settings.weapons.clone();
+    let weapons = &WeaponView::new(weapons, &overlay);
+
+    // The tick loop logs correlation ids; here only the command matters
+    let (_, cmd) = cmd.untrace();
+    let command = cmd.name();
+    // Gameplay input, not just staying connected, is what keeps a player from going AFK
+    if let Some(player_id) = afk::input_player(lobby, &cmd) {
+        afk::mark_active(lobby, player_id, clock::now());
+    }
     match cmd {
+        LobbyCommand::Traced { .. } => {} // Unwrapped above
         LobbyCommand::PlayerJoin { player_id, name, addr } => {
             let default_weapon = WeaponDb::default_weapon_id();
-            if let Err(e) = lobbies::add_player(lobby, player_id, name, default_weapon, weapons) {
+            let vip = server_state.is_some_and(|state| state.is_vip_player(player_id));
+            if let Err(e) = lobbies::add_player_as(lobby, player_id, name, default_weapon, weapons, vip, None) {
                 log::warn!("Failed to add player {}: {}", player_id, e);
                 return;
             }
@@ -219,22 +639,25 @@ fn process_command(
             }
             if let Some(state) = server_state {
                 state.register_player_lobby(player_id, &lobby.code);
+                lobbies::locate_player(lobby, player_id, state.infer_region(addr.ip()));
             }
         }
         LobbyCommand::PlayerLeave { player_id } => {
-            lobbies::remove_player(lobby, player_id);
-            if let Some(state) = server_state {
-                state.unregister_player(player_id);
-            }
+            lobbies::leave_lobby(lobby, player_id, server_state);
         }
-        LobbyCommand::UdpConnect { player_id, name: _, addr } => {
+        LobbyCommand::UdpConnect { player_id, name: _, addr, client_id } => {
             if lobby.players.contains_key(&player_id) {
                 lobby.client_addresses.insert(player_id, addr);
+                lobby.rebinds.settle(player_id);
                 if let Some(player) = lobby.players.get_mut(&player_id) {
-                    player.last_update = std::time::SystemTime::now();
+                    player.last_update = clock::now();
+                    if client_id.is_some() {
+                        player.client_id = client_id;
+                    }
                 }
                 if let Some(state) = server_state {
                     state.register_player_lobby(player_id, &lobby.code);
+                    lobbies::locate_player(lobby, player_id, state.infer_region(addr.ip()));
                 }
                 log::debug!("Player {} UDP connected from {}, now has {} addresses", 
                     player_id, addr, lobby.client_addresses.len());
@@ -242,77 +665,396 @@ fn process_command(
                 log::warn!("UDP connect for unknown player {} from {}", player_id, addr);
             }
         }
-        LobbyCommand::PositionUpdate { player_id, position, rotation, addr } => {
-            // Update client address (ensures HTTP-joined players get their UDP address tracked)
-            if lobby.players.contains_key(&player_id) {
-                lobby.client_addresses.insert(player_id, addr);
+        LobbyCommand::PositionUpdate { player_id, mut position, rotation } => {
+            if let Some(clamped) = validation::clamp_to_scene(position, &lobby.scene_data) {
+                validation::record_violation(lobby, player_id, ViolationKind::OutOfBounds);
+                position = clamped;
             }
             if let Err(e) = lobbies::update_position(lobby, player_id, position, rotation) {
                 log::debug!("Position update failed for player {}: {}", player_id, e);
+                return;
             }
+            if let Some(cause) = logic::apply_fall_checks(lobby, player_id) {
+                log::debug!("Player {} died ({}) in lobby {}", player_id, cause.as_str(), lobby.code);
+                lobby.push_event(SyncEvent::PlayerDied { player_id, cause: cause.as_str() });
+            }
+            streaming::update(lobby, player_id);
         }
-        LobbyCommand::Shoot { player_id, target_id } => {
-            match logic::try_shoot(lobby, weapons, player_id) {
-                Ok(can_shoot) => {
-                    if can_shoot {
-                        // Get weapon damage
-                        if let Some(player) = lobby.players.get(&player_id) {
-                            if let Some(weapon) = weapons.get(player.current_weapon_id) {
-                                let _ = logic::apply_damage(lobby, target_id, weapon.damage);
-                            }
-                        }
-                    }
-                }
-                Err(e) => log::debug!("Shoot failed for player {}: {}", player_id, e),
+        LobbyCommand::Shoot { player_id, target_id, pellet_hits } => {
+            // The shot still fires, as a miss
+            if target_id.is_some_and(|id| validation::is_unknown_target(lobby, id, clock::now())) {
+                validation::record_violation(lobby, player_id, ViolationKind::UnknownTarget);
+            }
+            if let Err(e) = logic::pull_trigger(lobby, weapons, player_id, target_id, &pellet_hits) {
+                action_failed(lobby, player_id, command, e);
+            }
+        }
+        LobbyCommand::FireHeld { player_id, held, target_id } => {
+            if target_id.is_some_and(|target_id| validation::is_unknown_target(lobby, target_id, clock::now())) {
+                validation::record_violation(lobby, player_id, ViolationKind::UnknownTarget);
+            }
+            if let Err(e) = logic::set_trigger_held(lobby, player_id, held, target_id) {
+                action_failed(lobby, player_id, command, e);
+            }
+        }
+        LobbyCommand::Pickup { player_id, pickup_id } => {
+            match pickups::take(lobby, player_id, pickup_id, clock::now()) {
+                Ok(kind) => log::debug!("Player {} picked up {} pickup {}", player_id, kind.as_str(), pickup_id),
+                Err(e) => action_failed(lobby, player_id, command, e),
+            }
+        }
+        LobbyCommand::LootPickup { player_id, item_id } => {
+            match loot::pick_up(lobby, weapons, player_id, item_id) {
+                Ok(kind) => log::debug!("Player {} picked up {} loot {}", player_id, kind.as_str(), item_id),
+                Err(e) => action_failed(lobby, player_id, command, e),
             }
         }
         LobbyCommand::Reload { player_id } => {
             if let Err(e) = logic::start_reload(lobby, weapons, player_id) {
-                log::debug!("Reload failed for player {}: {}", player_id, e);
+                action_failed(lobby, player_id, command, e);
             }
         }
         LobbyCommand::WeaponSwitch { player_id, weapon_id } => {
+            if !weapons.contains(weapon_id) {
+                validation::record_violation(lobby, player_id, ViolationKind::UnknownWeapon);
+                action_failed(lobby, player_id, command, "Unknown weapon");
+                return;
+            }
             if let Err(e) = logic::switch_weapon(lobby, weapons, player_id, weapon_id) {
-                log::debug!("Weapon switch failed for player {}: {}", player_id, e);
+                action_failed(lobby, player_id, command, e);
             }
         }
-        LobbyCommand::Heartbeat { player_id, addr } => {
-            // Update client address (ensures HTTP-joined players get their UDP address tracked)
-            if lobby.players.contains_key(&player_id) {
+        LobbyCommand::SelectLoadout { player_id, loadout, immediate } => {
+            let Some(player) = lobby.players.get_mut(&player_id) else {
+                return;
+            };
+            player.pending_loadout = Some(loadout);
+            // Joining counts as spawning, so a loadout picked with the join is equipped right away
+            if immediate && !player.is_dead {
+                if let Err(e) = logic::apply_pending_loadout(lobby, weapons, player_id) {
+                    action_failed(lobby, player_id, command, e);
+                }
+            }
+        }
+        LobbyCommand::WeaponCycle { player_id, forward } => {
+            if let Err(e) = logic::cycle_weapon(lobby, weapons, player_id, forward) {
+                action_failed(lobby, player_id, command, e);
+            }
+        }
+        LobbyCommand::Chat { player_id, text } => {
+            // Commands answer privately; everything else goes to the whole lobby
+            if chat_commands::is_command(&text) {
+                let reply = chat_commands::execute(lobby, player_id, &text, clock::now())
+                    .unwrap_or_else(|reason| reason.to_string());
+                lobby.push_event(chat::server_whisper(player_id, reply));
+                return;
+            }
+            match chat::prepare_message(lobby, player_id, &text) {
+                Ok(text) => {
+                    let from_name = lobby.players.get(&player_id)
+                        .map(|p| p.name.clone())
+                        .unwrap_or_default();
+                    lobby.push_event(SyncEvent::ChatMessage { from_id: player_id, from_name, text });
+                }
+                Err(reason) => {
+                    log::debug!("Chat from player {} rejected: {}", player_id, reason);
+                    lobby.push_event(chat::server_whisper(player_id, reason));
+                }
+            }
+        }
+        LobbyCommand::Emote { player_id, emote } => {
+            if let Err(reason) = emotes::play(lobby, player_id, &emote, clock::now()) {
+                action_failed(lobby, player_id, command, reason);
+            }
+        }
+        LobbyCommand::RequestTeamSwitch { player_id, team } => {
+            if let Err(reason) = teams::request_switch(lobby, player_id, team, clock::now()) {
+                action_failed(lobby, player_id, command, reason);
+            }
+        }
+        LobbyCommand::RebindAck { player_id, addr, generation } => {
+            if lobby.rebinds.confirm(player_id, addr, generation) {
                 lobby.client_addresses.insert(player_id, addr);
+                log::debug!("Player {} confirmed address {} (generation {})", player_id, addr, generation);
+            } else {
+                log::debug!("Ignoring rebind ack from {} for player {} (generation {})", addr, player_id, generation);
+            }
+        }
+        LobbyCommand::ObserverJoin { addr } => {
+            lobby.observers.add(addr, clock::now());
+        }
+        LobbyCommand::ObserverLeave { addr } => {
+            if lobby.observers.remove(addr) {
+                log::debug!("Observer {} left lobby {}", addr, lobby.code);
+            }
+        }
+        LobbyCommand::Whisper { player_id, target_id, text } => {
+            match chat::prepare_whisper(lobby, player_id, target_id, &text) {
+                Ok(text) => {
+                    let from_name = lobby.players.get(&player_id)
+                        .map(|p| p.name.clone())
+                        .unwrap_or_default();
+                    lobby.push_event(SyncEvent::Whisper { from_id: player_id, from_name, to_id: target_id, text });
+                }
+                Err(reason) => {
+                    log::debug!("Whisper from player {} to {} rejected: {}", player_id, target_id, reason);
+                    lobby.push_event(SyncEvent::WhisperFailed { player_id, to_id: target_id, reason });
+                }
             }
-            // Update last_update timestamp
+        }
+        LobbyCommand::ChannelSubscribe { player_id, channel, subscribe } => {
+            let Some(state) = server_state else { return };
+            let channel = match chat_channels::resolve(&channel, &lobby.settings.region) {
+                Ok(channel) => channel,
+                Err(reason) => return action_failed(lobby, player_id, "channel_subscribe", reason),
+            };
+            if subscribe {
+                if let Err(reason) = state.chat_channels.subscribe(&channel, player_id) {
+                    return action_failed(lobby, player_id, "channel_subscribe", reason);
+                }
+            } else {
+                state.chat_channels.unsubscribe(&channel, player_id);
+            }
+            lobby.push_event(SyncEvent::ChannelSubscription { player_id, channel, subscribed: subscribe });
+        }
+        LobbyCommand::ChannelChat { player_id, channel, text } => {
+            let Some(state) = server_state else { return };
+            let channel = match chat_channels::resolve(&channel, &lobby.settings.region) {
+                Ok(channel) => channel,
+                Err(reason) => return action_failed(lobby, player_id, "channel_chat", reason),
+            };
+            // Same moderation and lobby rate limit as lobby chat, then the channel's own cooldown
+            let checked = state.chat_channels.consume_rate_limit(&channel, player_id, std::time::Instant::now())
+                .and_then(|_| chat::prepare_message(lobby, player_id, &text));
+            match checked {
+                Ok(text) => {
+                    let from_name = lobby.players.get(&player_id)
+                        .map(|p| p.name.clone())
+                        .unwrap_or_default();
+                    let delivered = state.publish_channel_message(&channel, player_id, &from_name, &text);
+                    log::debug!("Channel {} message from player {} queued for {} subscribers", channel, player_id, delivered);
+                }
+                Err(reason) => action_failed(lobby, player_id, "channel_chat", reason),
+            }
+        }
+        LobbyCommand::ChannelDeliver { channel, from_id, from_name, text, recipients } => {
+            for to_id in recipients {
+                // Subscribers may have moved on since the message was fanned out
+                if lobby.players.contains_key(&to_id) {
+                    lobby.push_event(SyncEvent::ChannelMessage {
+                        to_id,
+                        channel: channel.clone(),
+                        from_id,
+                        from_name: from_name.clone(),
+                        text: text.clone(),
+                    });
+                }
+            }
+        }
+        LobbyCommand::SetCapabilities { player_id, quaternion_rotation, message_keys, preferences } => {
             if let Some(player) = lobby.players.get_mut(&player_id) {
-                player.last_update = std::time::SystemTime::now();
+                player.quaternion_rotation = quaternion_rotation;
+                player.message_keys = message_keys;
+                player.preferences = preferences;
+            }
+        }
+        LobbyCommand::LatencySample { player_id, rtt_ms } => {
+            if let Err(e) = latency::record_rtt_sample(lobby, player_id, rtt_ms) {
+                log::debug!("Latency sample from player {} rejected: {}", player_id, e);
+            }
+        }
+        LobbyCommand::TimeSync { player_id, seq, client_time_ms, received_at_ms } => {
+            match clock_sync::record_sample(lobby, player_id, client_time_ms, received_at_ms) {
+                Ok(stats) => lobby.push_event(SyncEvent::TimeSync(TimeSyncReply {
+                    player_id,
+                    seq,
+                    client_time_ms,
+                    received_at_ms,
+                    server_time_ms: clock_sync::now_ms(),
+                    tick: lobby.current_tick,
+                    tick_interval_ms: lobby.tick_interval_ms,
+                    stats,
+                })),
+                Err(e) => log::debug!("Time sync from player {} rejected: {}", player_id, e),
+            }
+        }
+        LobbyCommand::Pause { player_id } => {
+            if let Some(pid) = player_id.filter(|pid| !lobby.is_owner(*pid)) {
+                action_failed(lobby, pid, command, NOT_OWNER);
+                return;
+            }
+            match lobbies::pause(lobby, clock::now()) {
+                Ok(()) => {
+                    log::info!("Lobby {} paused", lobby.code);
+                    lobby.push_event(SyncEvent::GamePaused { paused_by: player_id });
+                }
+                Err(e) => match player_id {
+                    Some(pid) => action_failed(lobby, pid, command, e),
+                    None => log::debug!("Pause failed in lobby {}: {}", lobby.code, e),
+                },
+            }
+        }
+        LobbyCommand::BanWeapon { player_id, weapon_id, banned } => {
+            if let Some(pid) = player_id.filter(|pid| !lobby.is_owner(*pid)) {
+                action_failed(lobby, pid, command, NOT_OWNER);
+                return;
+            }
+            let result = if banned {
+                weapon_bans::ban(lobby, weapons, weapon_id).map(|switched| {
+                    log::info!("Weapon {} banned in lobby {}; switched {} players off it", weapon_id, lobby.code, switched.len());
+                })
+            } else {
+                weapon_bans::unban(lobby, weapon_id)
+            };
+            if let Err(e) = result {
+                match player_id {
+                    Some(pid) => action_failed(lobby, pid, command, e),
+                    None => log::debug!("Weapon ban failed in lobby {}: {}", lobby.code, e),
+                }
+            }
+        }
+        LobbyCommand::Resume { player_id } => {
+            if let Some(pid) = player_id.filter(|pid| !lobby.is_owner(*pid)) {
+                action_failed(lobby, pid, command, NOT_OWNER);
+                return;
+            }
+            match lobbies::resume(lobby, clock::now()) {
+                Ok(paused_for) => {
+                    log::info!("Lobby {} resumed", lobby.code);
+                    lobby.push_event(SyncEvent::GameResumed { resumed_by: player_id, paused_secs: paused_for.as_secs_f32() });
+                }
+                Err(e) => match player_id {
+                    Some(pid) => action_failed(lobby, pid, command, e),
+                    None => log::debug!("Resume failed in lobby {}: {}", lobby.code, e),
+                },
+            }
+        }
+        LobbyCommand::VoteStart { player_id, kind } => {
+            if let Err(e) = votes::start_vote(lobby, player_id, kind, votes::VOTE_DURATION_SECS) {
+                action_failed(lobby, player_id, command, e);
+            }
+        }
+        LobbyCommand::VoteCast { player_id, yes } => {
+            if let Err(e) = votes::cast_vote(lobby, player_id, yes) {
+                action_failed(lobby, player_id, command, e);
+            }
+        }
+        LobbyCommand::Violation { player_id, kind } => {
+            validation::record_violation(lobby, player_id, kind);
+        }
+        LobbyCommand::Ready { player_id, ready } => {
+            if let Err(e) = lobbies::set_ready(lobby, player_id, ready) {
+                action_failed(lobby, player_id, command, e);
             }
         }
+        LobbyCommand::Kick { player_id, reason } => {
+            if lobbies::leave_lobby(lobby, player_id, server_state).is_some() {
+                log::info!("Player {} kicked from lobby {}: {}", player_id, lobby.code, reason);
+                lobby.push_event(SyncEvent::PlayerKicked { player_id, reason });
+            }
+        }
+        LobbyCommand::Announce { message } => {
+            log::info!("Announcement in lobby {}: {}", lobby.code, message);
+            lobby.push_event(SyncEvent::Announcement { message });
+        }
+        LobbyCommand::SetRulesScript { name, source } => {
+            // Checked when uploaded, so this only fails if the command was replayed from elsewhere
+            let script = match source.map(|source| scripting::RulesScript::compile(&name, &source)).transpose() {
+                Ok(script) => script.map(Arc::new),
+                Err(e) => {
+                    log::warn!("Rejected rules script {} for lobby {}: {}", name, lobby.code, e);
+                    return;
+                }
+            };
+            log::info!("Rules script for lobby {} set to {:?}", lobby.code, script.as_ref().map(|s| &s.name));
+            scripting::set_script(lobby, script);
+        }
+        LobbyCommand::EndMatch => {
+            let global_stats = server_state.map(|state| state.global_stats.as_ref());
+            let was_live = lobby.is_match_live();
+            let standings = lobbies::end_match(lobby, global_stats);
+            // Only a match that was actually played settles a tournament pairing
+            if let Some(winner) = server_state.filter(|_| was_live).and_then(|state| state.tournaments.record_result(&lobby.code, &standings)) {
+                log::info!("{} won tournament match {}", winner, lobby.code);
+            }
+            log::info!("Match ended in lobby {} ({} players)", lobby.code, standings.len());
+            let ranked = lobby.settings.ranked;
+            let awards = awards::compute(&standings);
+            if let Some(timeline) = lobby.timeline.as_mut() {
+                timeline.awards = awards.clone();
+            }
+            lobby.push_event(SyncEvent::MatchEnded { ranked, standings, awards });
+            scripting::apply_staged(lobby);
+        }
     }
 }
 
+/// Rejection for pause/resume from anyone but the lobby owner
+const NOT_OWNER: &str = "Not the lobby owner";
+
+/// Tell a player their action was rejected, so the client can roll back what it predicted
+fn action_failed(lobby: &mut Lobby, player_id: u32, command: &'static str, reason: &'static str) {
+    log::debug!("{} failed for player {} in lobby {}: {}", command, player_id, lobby.code, reason);
+    lobby.push_event(SyncEvent::ActionFailed { player_id, command, reason });
+}
+
+/// Machine-readable form of an error message, e.g. "Weapon on cooldown" -> "weapon_on_cooldown"
+fn reason_code(reason: &str) -> String {
+    reason.split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| word.to_ascii_lowercase())
+        .collect::<Vec<_>>()
+        .join("_")
+}
+
+/// Emotes still playing, so a late joiner sees them too
+fn playing_emotes(lobby: &Lobby) -> Vec<serde_json::Value> {
+    lobby.emotes.playing(clock::now()).into_iter()
+        .map(|(player_id, emote, remaining)| json!({ "player_id": player_id, "emote": emote, "remaining": remaining }))
+        .collect()
+}
+
 /// Send welcome message to joining player with current lobby state
-async fn send_welcome_message(
+fn send_welcome_message(
     lobby: &Lobby,
-    socket: &UdpSocket,
+    weapons: &WeaponDb,
+    outbox: &Outbox,
     player_id: u32,
     addr: std::net::SocketAddr,
 ) {
     // Send welcome message
-    let welcome_packet = json!({
+    let mut welcome_packet = json!({
         "type": "welcome",
-        "message": "Connected to lobby",
         "player_id": player_id,
-        "scene_load": true
+        "scene_load": true,
+        "weapon_overrides": lobby.settings.weapons.overrides,
+        "weapon_fx": WeaponView::new(weapons, &lobby.settings.weapons).fx_table(),
+        "weapons_version": weapons.version(),
+        "motd": lobby.settings.motd,
+        "emotes": playing_emotes(lobby),
+        "team_count": lobby.settings.team_count,
+        "team": lobby.teams.team_of(player_id),
+        "banned_weapons": lobby.settings.banned_weapons,
+        "heartbeat": {
+            "interval_ms": lobby.heartbeat_interval_ms,
+            "degraded_after_ms": lobby.heartbeat_interval_ms * DEGRADED_AFTER_MISSED as u64,
+            "lost_after_ms": lobby.heartbeat_interval_ms * LOST_AFTER_MISSED as u64
+        },
+        "last_event_id": lobby.last_event_id
     });
+    let fallback = !lobby.players.get(&player_id).is_some_and(|p| p.message_keys);
+    messages::welcome().write(&mut welcome_packet, "message", fallback);
 
-    if let Ok(data) = serde_json::to_vec(&welcome_packet) {
-        let _ = socket.send_to(&data, addr).await;
+    if let Ok(data) = serde_json::to_vec(&welcome_packet).map(Datagrams::from) {
+        let _ = outbox.send(&data, addr);
     }
 
     // Send current player list to joining player
+    let quaternion = lobby.players.get(&player_id).is_some_and(|p| p.quaternion_rotation);
     let mut player_list = Vec::new();
     for player in lobby.players.values() {
         if player.id != player_id {
-            player_list.push(json!({
+            let mut entry = json!({
                 "id": player.id,
                 "name": player.name,
                 "position": {
@@ -324,8 +1066,15 @@ async fn send_welcome_message(
                     "x": player.rotation.0,
                     "y": player.rotation.1,
                     "z": player.rotation.2
-                }
-            }));
+                },
+                "health": player.current_health,
+                "max_health": player.max_health,
+                "team": lobby.teams.team_of(player.id)
+            });
+            if quaternion {
+                entry["orientation"] = orientation_json(player.rotation);
+            }
+            player_list.push(entry);
         }
     }
 
@@ -335,16 +1084,98 @@ async fn send_welcome_message(
         "notification": true
     });
 
-    if let Ok(data) = serde_json::to_vec(&players_packet) {
-        let _ = socket.send_to(&data, addr).await;
+    if let Ok(data) = serde_json::to_vec(&players_packet).map(Datagrams::from) {
+        let _ = outbox.send(&data, addr);
+    }
+
+    // Scene pickups, with the time left on any that are respawning
+    let pickups_packet = json!({
+        "type": "pickup_list",
+        "pickups": pickups::list_json(lobby, clock::now())
+    });
+    if let Ok(data) = serde_json::to_vec(&pickups_packet).map(Datagrams::from) {
+        let _ = outbox.send(&data, addr);
+    }
+}
+
+/// Ask clients seen at a new address to confirm it; they get no other traffic until they do
+fn send_rebind_challenges(lobby: &mut Lobby, outbox: &Outbox, now: std::time::SystemTime) {
+    for (player_id, addr, generation) in lobby.rebinds.due(now) {
+        let packet = json!({
+            "type": "rebind",
+            "player_id": player_id,
+            "generation": generation
+        });
+        if let Ok(data) = serde_json::to_vec(&packet).map(Datagrams::from) {
+            let _ = outbox.send(&data, addr);
+        }
+    }
+}
+
+/// Answer new observers, then send everyone watching a snapshot when one is due
+fn send_observer_snapshots(lobby: &mut Lobby, outbox: &Outbox, now: std::time::SystemTime) {
+    for (addr, result) in lobby.observers.take_replies() {
+        let packet = match result {
+            Ok(()) => json!({
+                "type": "observing",
+                "lobby_code": lobby.code,
+                "snapshot_hz": observers::OBSERVER_SNAPSHOT_HZ,
+                "timeout_secs": observers::OBSERVER_TIMEOUT.as_secs()
+            }),
+            Err(reason) => {
+                let mut packet = json!({ "type": "error" });
+                messages::Message::new("error.too_many_observers", reason).write(&mut packet, "message", true);
+                packet
+            }
+        };
+        if let Ok(data) = serde_json::to_vec(&packet).map(Datagrams::from) {
+            let _ = outbox.send(&data, addr);
+        }
+    }
+
+    let recipients = lobby.observers.due(now);
+    if recipients.is_empty() {
+        return;
+    }
+    if let Ok(data) = serde_json::to_vec(&observers::snapshot(lobby)).map(Datagrams::from) {
+        for addr in recipients {
+            // The next snapshot replaces a dropped one
+            let _ = outbox.send_non_critical(&data, addr);
+        }
+    }
+}
+
+/// Send a player their own health, ammo and weapon (as answered to `request_state`)
+fn send_player_state(lobby: &Lobby, outbox: &Outbox, player_id: u32, addr: std::net::SocketAddr) {
+    let Some(player) = lobby.players.get(&player_id) else { return };
+    let state_packet = json!({
+        "type": "player_state_update",
+        "player_id": player_id,
+        "health": player.current_health,
+        "max_health": player.max_health,
+        "armor": player.armor,
+        "ammo": player.current_ammo,
+        "max_ammo": player.max_ammo,
+        "heat": player.heat,
+        "overheated": player.overheated_until.is_some(),
+        "is_reloading": player.is_reloading,
+        "weapon_id": player.current_weapon_id,
+        "lobby_code": lobby.code,
+        "lobby_players": lobby.players.len(),
+        "last_event_id": lobby.last_event_id,
+        "resumed": true
+    });
+
+    if let Ok(data) = serde_json::to_vec(&state_packet).map(Datagrams::from) {
+        let _ = outbox.send(&data, addr);
     }
 }
 
 /// Send UDP connection acknowledgment without scene info
 /// Used when player reconnects via UDP after HTTP join
-async fn send_udp_connected_message(
+fn send_udp_connected_message(
     lobby: &Lobby,
-    socket: &UdpSocket,
+    outbox: &Outbox,
     player_id: u32,
     addr: std::net::SocketAddr,
 ) {
@@ -352,17 +1183,19 @@ async fn send_udp_connected_message(
         "type": "udp_connected",
         "player_id": player_id,
         "lobby_code": lobby.code,
+        "last_event_id": lobby.last_event_id,
         "notification": true
     });
 
-    if let Ok(data) = serde_json::to_vec(&ack_packet) {
-        let _ = socket.send_to(&data, addr).await;
+    if let Ok(data) = serde_json::to_vec(&ack_packet).map(Datagrams::from) {
+        let _ = outbox.send(&data, addr);
     }
 
+    let quaternion = lobby.players.get(&player_id).is_some_and(|p| p.quaternion_rotation);
     let mut player_list = Vec::new();
     for player in lobby.players.values() {
         if player.id != player_id {
-            player_list.push(json!({
+            let mut entry = json!({
                 "id": player.id,
                 "name": player.name,
                 "position": {
@@ -374,8 +1207,14 @@ async fn send_udp_connected_message(
                     "x": player.rotation.0,
                     "y": player.rotation.1,
                     "z": player.rotation.2
-                }
-            }));
+                },
+                "health": player.current_health,
+                "max_health": player.max_health
+            });
+            if quaternion {
+                entry["orientation"] = orientation_json(player.rotation);
+            }
+            player_list.push(entry);
         }
     }
 
@@ -385,15 +1224,15 @@ async fn send_udp_connected_message(
         "notification": true
     });
 
-    if let Ok(data) = serde_json::to_vec(&players_packet) {
-        let _ = socket.send_to(&data, addr).await;
+    if let Ok(data) = serde_json::to_vec(&players_packet).map(Datagrams::from) {
+        let _ = outbox.send(&data, addr);
     }
 }
 
 /// Broadcast player join events to all clients
-async fn broadcast_player_join_events(
-    lobby: &Lobby,
-    socket: &UdpSocket,
+fn broadcast_player_join_events(
+    lobby: &mut Lobby,
+    outbox: &Outbox,
     players: &[(u32, String)],
 ) {
     for (player_id, name) in players {
@@ -401,6 +1240,7 @@ async fn broadcast_player_join_events(
         
         let packet = json!({
             "type": "player_joined",
+            "event_id": lobby.next_event_id(),
             "player": {
                 "id": player_id,
                 "name": name
@@ -408,7 +1248,7 @@ async fn broadcast_player_join_events(
             "notification": true
         });
 
-        if let Ok(data) = serde_json::to_vec(&packet) {
+        if let Ok(data) = serde_json::to_vec(&packet).map(Datagrams::from) {
             // Send to all clients except the joining player
             let recipients: Vec<(u32, std::net::SocketAddr)> = lobby.client_addresses.iter()
                 .filter(|(cid, _)| **cid != *player_id)
@@ -418,11 +1258,11 @@ async fn broadcast_player_join_events(
             log::debug!("Sending to {} recipients: {:?}", recipients.len(), recipients);
             
             for (client_id, addr) in recipients {
-                log::debug!("Sending player_joined to client {} at {}", client_id, addr);
-                if let Err(e) = socket.send_to(&data, addr).await {
+                log::debug!("Queueing player_joined for client {} at {}", client_id, addr);
+                if let Err(e) = outbox.send(&data, addr) {
                     log::debug!("Failed to send join event to {} ({}): {:?}", client_id, addr, e);
                 } else {
-                    log::debug!("Successfully sent player_joined to client {} at {}", client_id, addr);
+                    log::debug!("Queued player_joined for client {} at {}", client_id, addr);
                 }
             }
         }
@@ -430,21 +1270,22 @@ async fn broadcast_player_join_events(
 }
 
 /// Broadcast player leave events to all clients
-async fn broadcast_player_leave_events(
-    lobby: &Lobby,
-    socket: &UdpSocket,
+fn broadcast_player_leave_events(
+    lobby: &mut Lobby,
+    outbox: &Outbox,
     player_ids: &[u32],
 ) {
     for player_id in player_ids {
         let packet = json!({
             "type": "player_left",
+            "event_id": lobby.next_event_id(),
             "player_id": player_id
         });
 
-        if let Ok(data) = serde_json::to_vec(&packet) {
+        if let Ok(data) = serde_json::to_vec(&packet).map(Datagrams::from) {
             // Send to all remaining clients
-            for (_client_id, addr) in &lobby.client_addresses {
-                if let Err(e) = socket.send_to(&data, *addr).await {
+            for addr in lobby.client_addresses.values() {
+                if let Err(e) = outbox.send(&data, *addr) {
                     log::debug!("Failed to send leave event to {}: {:?}", addr, e);
                 }
             }
@@ -452,10 +1293,34 @@ async fn broadcast_player_leave_events(
     }
 }
 
+/// Quaternion form of a player's rotation for clients with quaternion support
+fn orientation_json(rotation: (f32, f32, f32)) -> serde_json::Value {
+    let (x, y, z, w) = rotation::euler_to_quat(rotation);
+    json!({ "x": x, "y": y, "z": z, "w": w })
+}
+
+/// Client packet for a player's position and Euler rotation
+pub fn position_packet(player: &Player) -> serde_json::Value {
+    json!({
+        "type": "position_update",
+        "player_id": player.id,
+        "position": {
+            "x": player.position.0,
+            "y": player.position.1,
+            "z": player.position.2
+        },
+        "rotation": {
+            "x": player.rotation.0,
+            "y": player.rotation.1,
+            "z": player.rotation.2
+        }
+    })
+}
+
 /// Broadcast position updates for players that moved
-async fn broadcast_position_updates(
+fn broadcast_position_updates(
     lobby: &Lobby,
-    socket: &UdpSocket,
+    outbox: &Outbox,
     player_ids: &[u32],
 ) {
     for player_id in player_ids {
@@ -463,33 +1328,32 @@ async fn broadcast_position_updates(
             // log::debug!("Broadcasting position for player {}: ({}, {}, {})", 
             //     player_id, player.position.0, player.position.1, player.position.2);
             
-            let packet = json!({
-                "type": "position_update",
-                "player_id": player_id,
-                "position": {
-                    "x": player.position.0,
-                    "y": player.position.1,
-                    "z": player.position.2
-                },
-                "rotation": {
-                    "x": player.rotation.0,
-                    "y": player.rotation.1,
-                    "z": player.rotation.2
-                }
-            });
+            let packet = position_packet(player);
 
-            if let Ok(data) = serde_json::to_vec(&packet) {
+            // Clients that negotiated quaternions also get the orientation, built only if one is listening
+            let mut quaternion_data = None;
+
+            if let Ok(data) = serde_json::to_vec(&packet).map(Datagrams::from) {
                 // Send to all clients except the moving player
                 let recipients: Vec<(u32, std::net::SocketAddr)> = lobby.client_addresses.iter()
-                    .filter(|(cid, _)| **cid != *player_id)
+                    .filter(|(cid, _)| **cid != *player_id && !lobby.connectivity.is_lost(**cid))
                     .map(|(cid, addr)| (*cid, *addr))
                     .collect();
                 
                 // log::debug!("Sending position update to {} recipients: {:?}", recipients.len(), recipients);
                 
             for (client_id, addr) in recipients {
+                let data = if lobby.players.get(&client_id).is_some_and(|p| p.quaternion_rotation) {
+                    quaternion_data.get_or_insert_with(|| {
+                        let mut with_orientation = packet.clone();
+                        with_orientation["orientation"] = orientation_json(player.rotation);
+                        serde_json::to_vec(&with_orientation).map(Datagrams::from).unwrap_or_default()
+                    })
+                } else {
+                    &data
+                };
                 // log::debug!("Sending position update to client {} at {}", client_id, addr);
-                if let Err(e) = socket.send_to(&data, addr).await {
+                if let Err(_e) = outbox.send_non_critical(data, addr) {
                     // log::debug!("Failed to send position update to {} ({}): {:?}", client_id, addr, e);
                 } else {
                     // log::debug!("Successfully sent position update to client {} at {}", client_id, addr);
@@ -497,161 +1361,637 @@ async fn broadcast_position_updates(
             }
             }
         }
-    }
-}
-
-/// Broadcast kill event to all clients
-async fn broadcast_kill_event(
-    lobby: &Lobby,
-    socket: &UdpSocket,
-    event: &logic::KillEvent,
-) {
-    let packet = json!({
-        "type": "player_killed",
-        "killer_id": event.killer_id,
-        "killer_name": event.killer_name,
-        "victim_id": event.victim_id,
-        "victim_name": event.victim_name,
-        "weapon_id": event.weapon_id,
-        "weapon_name": event.weapon_name,
-        "killer_killstreak": event.killer_new_killstreak
-    });
-
-    if let Ok(data) = serde_json::to_vec(&packet) {
-        for (_player_id, addr) in &lobby.client_addresses {
-            if let Err(e) = socket.send_to(&data, *addr).await {
-                log::debug!("Failed to send kill event to {}: {:?}", addr, e);
-            }
+    }
+}
+
+/// Broadcast kill event to all clients
+fn broadcast_kill_event(
+    lobby: &mut Lobby,
+    outbox: &Outbox,
+    event: &logic::KillEvent,
+) {
+    let packet = json!({
+        "type": "player_killed",
+        "event_id": lobby.next_event_id(),
+        "killer_id": event.killer_id,
+        "killer_name": event.killer_name,
+        "victim_id": event.victim_id,
+        "victim_name": event.victim_name,
+        "weapon_id": event.weapon_id,
+        "weapon_name": event.weapon_name,
+        "killer_killstreak": event.killer_new_killstreak
+    });
+
+    if let Ok(data) = serde_json::to_vec(&packet).map(Datagrams::from) {
+        let minimal = Preferences::MINIMAL_KILL_FEED;
+        for (player_id, addr) in lobby.client_addresses.iter() {
+            let involved = *player_id == event.killer_id || *player_id == event.victim_id;
+            if !involved && lobby.players.get(player_id).is_some_and(|p| p.preferences.contains(minimal)) {
+                continue;
+            }
+            if let Err(e) = outbox.send(&data, *addr) {
+                log::debug!("Failed to send kill event to {}: {:?}", addr, e);
+            }
+        }
+    }
+}
+
+/// Broadcast respawn events to all clients
+fn broadcast_respawn_events(
+    lobby: &mut Lobby,
+    outbox: &Outbox,
+    player_ids: &[u32],
+) {
+    for player_id in player_ids {
+        let packet = json!({
+            "type": "player_respawned",
+            "event_id": lobby.next_event_id(),
+            "player_id": player_id,
+            "spawn_protection_secs": logic::spawn_protection_remaining(lobby, *player_id, clock::now())
+        });
+
+        if let Ok(data) = serde_json::to_vec(&packet).map(Datagrams::from) {
+            for addr in lobby.client_addresses.values() {
+                if let Err(e) = outbox.send(&data, *addr) {
+                    log::debug!("Failed to send respawn event to {}: {:?}", addr, e);
+                }
+            }
+        }
+    }
+}
+
+/// Client packet for a state event (None for events sent another way)
+pub fn event_packet(lobby: &Lobby, event: &SyncEvent) -> Option<serde_json::Value> {
+    let packet = match event {
+        SyncEvent::HealthChanged { player_id, health, max_health } => {
+            json!({
+                "type": "player_state_update",
+                "player_id": player_id,
+                "health": health,
+                "max_health": max_health
+            })
+        }
+        SyncEvent::AmmoChanged { player_id, ammo, heat, overheated } => {
+            json!({
+                "type": "player_state_update",
+                "player_id": player_id,
+                "ammo": ammo,
+                "heat": heat,
+                "overheated": overheated
+            })
+        }
+        SyncEvent::MaxAmmoChanged { player_id, max_ammo } => {
+            json!({
+                "type": "player_state_update",
+                "player_id": player_id,
+                "max_ammo": max_ammo
+            })
+        }
+        SyncEvent::ArmorChanged { player_id, armor } => {
+            json!({
+                "type": "player_state_update",
+                "player_id": player_id,
+                "armor": armor,
+                "max_armor": armor::MAX_ARMOR
+            })
+        }
+        SyncEvent::WeaponChanged { player_id, weapon_id, equip_time } => {
+            json!({
+                "type": "weapon_switched",
+                "player_id": player_id,
+                "weapon_id": weapon_id,
+                "equip_time": equip_time
+            })
+        }
+        SyncEvent::ReloadStateChanged { player_id, is_reloading } => {
+            if *is_reloading {
+                json!({
+                    "type": "reload_started",
+                    "player_id": player_id
+                })
+            } else {
+                json!({
+                    "type": "reload_finished",
+                    "player_id": player_id
+                })
+            }
+        }
+        SyncEvent::PositionChanged { .. } => {
+            // Position updates are handled separately
+            return None;
+        }
+        SyncEvent::PlayerKilled { killer_id, killer_name, victim_id, victim_name, weapon_id, weapon_name, killer_killstreak } => {
+            json!({
+                "type": "player_killed",
+                "killer_id": killer_id,
+                "killer_name": killer_name,
+                "victim_id": victim_id,
+                "victim_name": victim_name,
+                "weapon_id": weapon_id,
+                "weapon_name": weapon_name,
+                "killer_killstreak": killer_killstreak
+            })
+        }
+        SyncEvent::HitConfirmed { attacker_id, target_id, damage, multiplier, pellets, armor_absorbed, target_armor } => {
+            json!({
+                "type": "hit_confirm",
+                "attacker_id": attacker_id,
+                "target_id": target_id,
+                "damage": damage,
+                "multiplier": multiplier,
+                "pellets": pellets,
+                "armor_absorbed": armor_absorbed,
+                "target_armor": target_armor
+            })
+        }
+        SyncEvent::HitMarkers { attacker_id, hits } => {
+            let hits: Vec<serde_json::Value> = hits.iter()
+                .map(|hit| json!({
+                    "target_id": hit.target_id,
+                    "damage": hit.damage,
+                    "hits": hit.hits,
+                    "killed": hit.killed
+                }))
+                .collect();
+            json!({
+                "type": "hit_markers",
+                "attacker_id": attacker_id,
+                "tick": lobby.current_tick,
+                "hits": hits
+            })
+        }
+        SyncEvent::TeamChanged { player_id, team } => {
+            json!({
+                "type": "team_changed",
+                "player_id": player_id,
+                "team": team
+            })
+        }
+        SyncEvent::WeaponBanned { weapon_id, banned } => {
+            json!({
+                "type": "weapon_banned",
+                "weapon_id": weapon_id,
+                "banned": banned,
+                "banned_weapons": lobby.settings.banned_weapons
+            })
+        }
+        SyncEvent::ConnectionDegraded { player_id, state, .. } => {
+            json!({
+                "type": "connection_degraded",
+                "player_id": player_id,
+                "state": state.as_str()
+            })
+        }
+        SyncEvent::PlayerDamaged { player_id, attacker_id, damage, health } => {
+            json!({
+                "type": "player_damaged",
+                "player_id": player_id,
+                "attacker_id": attacker_id,
+                "damage": damage,
+                "health": health
+            })
+        }
+        SyncEvent::KillcamData { victim_id, killer_id, weapon_id, samples } => {
+            let samples: Vec<serde_json::Value> = samples.iter()
+                .map(|s| json!({
+                    "tick": s.tick,
+                    "position": { "x": s.position.0, "y": s.position.1, "z": s.position.2 },
+                    "rotation": { "x": s.rotation.0, "y": s.rotation.1, "z": s.rotation.2 }
+                }))
+                .collect();
+            json!({
+                "type": "killcam_data",
+                "victim_id": victim_id,
+                "killer_id": killer_id,
+                "weapon_id": weapon_id,
+                "samples": samples
+            })
+        }
+        SyncEvent::LootSpawned { item_id, kind, position, expires_in } => {
+            let (weapon_id, ammo) = match kind {
+                loot::LootKind::Weapon { weapon_id, ammo } => (Some(*weapon_id), Some(*ammo)),
+                _ => (None, None),
+            };
+            json!({
+                "type": "loot_spawned",
+                "item_id": item_id,
+                "kind": kind.as_str(),
+                "weapon_id": weapon_id,
+                "ammo": ammo,
+                "position": { "x": position.0, "y": position.1, "z": position.2 },
+                "expires_in": expires_in
+            })
+        }
+        SyncEvent::LootRemoved { item_id, picked_up_by } => {
+            json!({
+                "type": "loot_removed",
+                "item_id": item_id,
+                "picked_up_by": picked_up_by
+            })
+        }
+        SyncEvent::PickupTaken { pickup_id, player_id, respawn_in } => {
+            json!({
+                "type": "pickup_taken",
+                "pickup_id": pickup_id,
+                "player_id": player_id,
+                "respawn_in": respawn_in
+            })
+        }
+        SyncEvent::PickupRespawned { pickup_id } => {
+            json!({
+                "type": "pickup_respawned",
+                "pickup_id": pickup_id
+            })
+        }
+        SyncEvent::ProjectileSpawned { projectile_id, owner_id, weapon_id, position, velocity } => {
+            json!({
+                "type": "projectile_spawned",
+                "projectile_id": projectile_id,
+                "owner_id": owner_id,
+                "weapon_id": weapon_id,
+                "position": { "x": position.0, "y": position.1, "z": position.2 },
+                "velocity": { "x": velocity.0, "y": velocity.1, "z": velocity.2 }
+            })
+        }
+        SyncEvent::ZoneState { holder, contested, occupants, points, score_limit } => {
+            let points: Vec<_> = points.iter()
+                .map(|(player_id, points)| json!({ "player_id": player_id, "points": points }))
+                .collect();
+            json!({
+                "type": "zone_state",
+                "holder": holder,
+                "contested": contested,
+                "occupants": occupants,
+                "points": points,
+                "score_limit": score_limit
+            })
+        }
+        SyncEvent::RoundStarted { round, rounds, time_limit_secs } => {
+            json!({
+                "type": "round_started",
+                "round": round,
+                "rounds": rounds,
+                "time_limit_secs": time_limit_secs
+            })
+        }
+        SyncEvent::RoundEnded { round, winner, reason, wins } => {
+            let wins: Vec<_> = wins.iter()
+                .map(|(player_id, wins)| json!({ "player_id": player_id, "wins": wins }))
+                .collect();
+            json!({
+                "type": "round_ended",
+                "round": round,
+                "winner": winner,
+                "reason": reason,
+                "wins": wins
+            })
+        }
+        SyncEvent::LoadoutApplied { player_id, name, weapon_id, attachments } => {
+            json!({
+                "type": "loadout_applied",
+                "player_id": player_id,
+                "name": name,
+                "weapon_id": weapon_id,
+                "attachments": attachments
+            })
+        }
+        SyncEvent::ProjectileEnded { projectile_id, target_id } => {
+            json!({
+                "type": "projectile_ended",
+                "projectile_id": projectile_id,
+                "target_id": target_id
+            })
+        }
+        SyncEvent::TimeSync(reply) => {
+            json!({
+                "type": "time_sync",
+                "seq": reply.seq,
+                "client_time": reply.client_time_ms,
+                "server_receive_time": reply.received_at_ms,
+                "server_time": reply.server_time_ms,
+                "tick": reply.tick,
+                "tick_interval_ms": reply.tick_interval_ms,
+                "offset_ms": reply.stats.offset_ms,
+                "jitter_ms": reply.stats.jitter_ms,
+                "drift_ms_per_sec": reply.stats.drift_ms_per_sec,
+                "recommended_interp_delay_ms": reply.stats.interp_delay_ms(reply.tick_interval_ms)
+            })
+        }
+        SyncEvent::PlayerRespawned { player_id } => {
+            json!({
+                "type": "player_respawned",
+                "player_id": player_id
+            })
+        }
+        SyncEvent::PlayerDied { player_id, cause } => {
+            json!({
+                "type": "player_died",
+                "player_id": player_id,
+                "cause": cause
+            })
+        }
+        SyncEvent::ScoreChanged { player_id, score, kills, deaths, killstreak } => {
+            json!({
+                "type": "score_update",
+                "player_id": player_id,
+                "score": score,
+                "kills": kills,
+                "deaths": deaths,
+                "killstreak": killstreak
+            })
+        }
+        SyncEvent::PlayerKicked { player_id, reason } => {
+            let mut packet = json!({
+                "type": "player_kicked",
+                "player_id": player_id
+            });
+            messages::kick(reason).write(&mut packet, "reason", true);
+            packet
+        }
+        SyncEvent::Announcement { message } => {
+            let mut packet = json!({ "type": "announcement" });
+            messages::announcement(message).write(&mut packet, "message", true);
+            packet
+        }
+        SyncEvent::InactivityWarning { player_id, seconds_remaining } => {
+            json!({
+                "type": "inactivity_warning",
+                "player_id": player_id,
+                "seconds_remaining": seconds_remaining
+            })
+        }
+        SyncEvent::AfkWarning { player_id, seconds_remaining, kick } => {
+            json!({
+                "type": "afk_warning",
+                "player_id": player_id,
+                "seconds_remaining": seconds_remaining,
+                "action": if *kick { "kick" } else { "spectate" }
+            })
+        }
+        SyncEvent::SpectatorChanged { player_id, spectating } => {
+            json!({
+                "type": "spectator_changed",
+                "player_id": player_id,
+                "spectating": spectating
+            })
+        }
+        SyncEvent::LobbyTransfer { player_id, from_code } => {
+            json!({
+                "type": "lobby_transfer",
+                "player_id": player_id,
+                "lobby_code": lobby.code,
+                "from_lobby_code": from_code,
+                "last_event_id": lobby.last_event_id
+            })
+        }
+        SyncEvent::TimeCapReached { scope, cap_secs, closing } => {
+            json!({
+                "type": "time_cap_reached",
+                "scope": scope,
+                "cap_secs": cap_secs,
+                "closing": closing
+            })
+        }
+        SyncEvent::MatchEnded { ranked, standings, awards } => {
+            json!({
+                "type": "match_ended",
+                "ranked": ranked,
+                "standings": standings,
+                "awards": awards
+            })
+        }
+        SyncEvent::GamePaused { paused_by } => {
+            json!({
+                "type": "game_paused",
+                "paused_by": paused_by
+            })
+        }
+        SyncEvent::GameResumed { resumed_by, paused_secs } => {
+            json!({
+                "type": "game_resumed",
+                "resumed_by": resumed_by,
+                "paused_secs": paused_secs
+            })
+        }
+        SyncEvent::ReadyChanged { player_id, ready } => {
+            let (ready_count, player_count) = lobbies::ready_counts(lobby);
+            json!({
+                "type": "ready_changed",
+                "player_id": player_id,
+                "ready": ready,
+                "ready_count": ready_count,
+                "player_count": player_count
+            })
+        }
+        SyncEvent::CountdownTick { seconds_left } => {
+            json!({
+                "type": "countdown",
+                "seconds_left": seconds_left
+            })
+        }
+        SyncEvent::CountdownCancelled => {
+            json!({
+                "type": "countdown_cancelled"
+            })
+        }
+        SyncEvent::MatchStarted => {
+            json!({
+                "type": "match_started"
+            })
+        }
+        SyncEvent::VoteStarted { initiator, kind, seconds } => {
+            json!({
+                "type": "vote_started",
+                "initiator": initiator,
+                "kind": kind,
+                "seconds": seconds
+            })
+        }
+        SyncEvent::VoteProgress { yes, no, needed } => {
+            json!({
+                "type": "vote_progress",
+                "yes": yes,
+                "no": no,
+                "needed": needed
+            })
+        }
+        SyncEvent::VoteEnded { kind, passed } => {
+            json!({
+                "type": "vote_ended",
+                "kind": kind,
+                "passed": passed
+            })
+        }
+        SyncEvent::SceneChanged { scene } => {
+            json!({
+                "type": "scene_changed",
+                "scene": scene,
+                "pickups": pickups::list_json(lobby, clock::now())
+            })
+        }
+        SyncEvent::SpawnProtectionStarted { player_id, seconds } => {
+            json!({
+                "type": "spawn_protection_started",
+                "player_id": player_id,
+                "seconds": seconds
+            })
+        }
+        SyncEvent::SpawnProtectionEnded { player_id } => {
+            json!({
+                "type": "spawn_protection_ended",
+                "player_id": player_id
+            })
+        }
+        SyncEvent::MotdChanged { motd, changed_by } => {
+            json!({
+                "type": "motd_changed",
+                "motd": motd,
+                "changed_by": changed_by
+            })
+        }
+        SyncEvent::ChatMessage { from_id, from_name, text } => {
+            json!({
+                "type": "chat",
+                "from": from_id,
+                "from_name": from_name,
+                "text": text
+            })
+        }
+        SyncEvent::Whisper { from_id, from_name, to_id, text } => {
+            json!({
+                "type": "whisper",
+                "from": from_id,
+                "from_name": from_name,
+                "to": to_id,
+                "text": text
+            })
+        }
+        SyncEvent::GunfireNearby { position, loudness, .. } => {
+            json!({
+                "type": "gunfire_nearby",
+                "position": {
+                    "x": position.0,
+                    "y": position.1,
+                    "z": position.2
+                },
+                "loudness": loudness
+            })
+        }
+        SyncEvent::PlayerShot { player_id, weapon_id } => {
+            json!({
+                "type": "player_shot",
+                "player_id": player_id,
+                "weapon_id": weapon_id
+            })
+        }
+        SyncEvent::EmotePlayed { player_id, emote, duration, .. } => {
+            json!({
+                "type": "emote",
+                "player_id": player_id,
+                "emote": emote,
+                "duration": duration
+            })
+        }
+        SyncEvent::ChunkEnter { chunk, size, .. } => {
+            json!({
+                "type": "chunk_enter",
+                "chunk": [chunk.0, chunk.1],
+                // World-space x/z bounds, so clients needn't know the chunk size
+                "min": [chunk.0 as f32 * size, chunk.1 as f32 * size],
+                "max": [(chunk.0 + 1) as f32 * size, (chunk.1 + 1) as f32 * size]
+            })
         }
-    }
-}
-
-/// Broadcast respawn events to all clients
-async fn broadcast_respawn_events(
-    lobby: &Lobby,
-    socket: &UdpSocket,
-    player_ids: &[u32],
-) {
-    for player_id in player_ids {
-        let packet = json!({
-            "type": "player_respawned",
-            "player_id": player_id
-        });
-
-        if let Ok(data) = serde_json::to_vec(&packet) {
-            for (_player_id, addr) in &lobby.client_addresses {
-                if let Err(e) = socket.send_to(&data, *addr).await {
-                    log::debug!("Failed to send respawn event to {}: {:?}", addr, e);
-                }
-            }
+        SyncEvent::ChunkExit { chunk, .. } => {
+            json!({
+                "type": "chunk_exit",
+                "chunk": [chunk.0, chunk.1]
+            })
         }
-    }
+        SyncEvent::ChannelMessage { channel, from_id, from_name, text, .. } => {
+            json!({
+                "type": "channel_message",
+                "channel": channel,
+                "from": from_id,
+                "from_name": from_name,
+                "text": text
+            })
+        }
+        SyncEvent::ChannelSubscription { channel, subscribed, .. } => {
+            json!({
+                "type": "channel_subscription",
+                "channel": channel,
+                "subscribed": subscribed
+            })
+        }
+        SyncEvent::WhisperFailed { to_id, reason, .. } => {
+            json!({
+                "type": "whisper_failed",
+                "to": to_id,
+                "reason": reason
+            })
+        }
+        SyncEvent::ActionFailed { command, reason, .. } => {
+            json!({
+                "type": "action_failed",
+                "command": command,
+                "code": reason_code(reason),
+                "reason": reason
+            })
+        }
+    };
+    Some(packet)
 }
 
 /// Broadcast state events to all clients in lobby
-async fn broadcast_state_events(
-    lobby: &Lobby,
-    socket: &UdpSocket,
+/// Lobby-wide events carry an `event_id`; targeted ones don't, so recipients see no gaps
+fn broadcast_state_events(
+    lobby: &mut Lobby,
+    outbox: &Outbox,
     events: &[SyncEvent],
     buffer: &mut PacketBuffer,
 ) {
     for event in events {
-        let packet = match event {
-            SyncEvent::HealthChanged { player_id, health } => {
-                json!({
-                    "type": "player_state_update",
-                    "player_id": player_id,
-                    "health": health
-                })
-            }
-            SyncEvent::AmmoChanged { player_id, ammo } => {
-                json!({
-                    "type": "player_state_update",
-                    "player_id": player_id,
-                    "ammo": ammo
-                })
-            }
-            SyncEvent::MaxAmmoChanged { player_id, max_ammo } => {
-                json!({
-                    "type": "player_state_update",
-                    "player_id": player_id,
-                    "max_ammo": max_ammo
-                })
-            }
-            SyncEvent::WeaponChanged { player_id, weapon_id } => {
-                json!({
-                    "type": "weapon_switched",
-                    "player_id": player_id,
-                    "weapon_id": weapon_id
-                })
-            }
-            SyncEvent::ReloadStateChanged { player_id, is_reloading } => {
-                if *is_reloading {
-                    json!({
-                        "type": "reload_started",
-                        "player_id": player_id
-                    })
-                } else {
-                    json!({
-                        "type": "reload_finished",
-                        "player_id": player_id
-                    })
-                }
-            }
-            SyncEvent::PositionChanged { .. } => {
-                // Position updates are handled separately
-                continue;
-            }
-            SyncEvent::PlayerKilled { killer_id, killer_name, victim_id, victim_name, weapon_id, weapon_name, killer_killstreak } => {
-                json!({
-                    "type": "player_killed",
-                    "killer_id": killer_id,
-                    "killer_name": killer_name,
-                    "victim_id": victim_id,
-                    "victim_name": victim_name,
-                    "weapon_id": weapon_id,
-                    "weapon_name": weapon_name,
-                    "killer_killstreak": killer_killstreak
-                })
-            }
-            SyncEvent::PlayerRespawned { player_id } => {
-                json!({
-                    "type": "player_respawned",
-                    "player_id": player_id
-                })
-            }
-            SyncEvent::ScoreChanged { player_id, score, kills, deaths, killstreak } => {
-                json!({
-                    "type": "score_update",
-                    "player_id": player_id,
-                    "score": score,
-                    "kills": kills,
-                    "deaths": deaths,
-                    "killstreak": killstreak
-                })
-            }
-            SyncEvent::PlayerKicked { player_id, reason } => {
-                json!({
-                    "type": "player_kicked",
-                    "player_id": player_id,
-                    "reason": reason
-                })
-            }
-            SyncEvent::InactivityWarning { player_id, seconds_remaining } => {
-                json!({
-                    "type": "inactivity_warning",
-                    "player_id": player_id,
-                    "seconds_remaining": seconds_remaining
-                })
-            }
+        let Some(mut packet) = event_packet(lobby, event) else {
+            continue;
         };
+        if event.recipient().is_none() {
+            packet["event_id"] = json!(lobby.next_event_id());
+        }
 
         // Serialize to buffer
         buffer.clear();
-        if let Ok(data) = serde_json::to_vec(&packet) {
+        if let Ok(data) = serde_json::to_vec(&packet).map(Datagrams::from) {
+            // Clients that localize get message keys without the English text
+            let localized = messages::without_fallback(&packet)
+                .and_then(|packet| serde_json::to_vec(&packet).ok())
+                .map(Datagrams::from);
+            let data_for = |player_id: &u32| match &localized {
+                Some(localized) if lobby.players.get(player_id).is_some_and(|p| p.message_keys) => localized,
+                _ => &data,
+            };
+            // Chat, emotes and others' kills are skipped for clients that opted out of them
+            let wanted_by = |player_id: &u32| lobby.players.get(player_id)
+                .is_none_or(|p| preferences::wants(p.preferences, *player_id, event));
+
+            // Targeted events go only to their recipient
+            if let Some(recipient) = event.recipient() {
+                // Lost clients only get what they can't do without
+                if (event.is_non_critical() && lobby.connectivity.is_lost(recipient)) || !wanted_by(&recipient) {
+                    continue;
+                }
+                if let Some(addr) = lobby.client_addresses.get(&recipient) {
+                    let data = data_for(&recipient);
+                    let sent = if event.is_non_critical() { outbox.send_non_critical(data, *addr) } else { outbox.send(data, *addr) };
+                    if let Err(e) = sent {
+                        log::debug!("Failed to send event to {}: {:?}", addr, e);
+                    }
+                }
+                continue;
+            }
+
             // Send to all clients in lobby
-            for (_player_id, addr) in &lobby.client_addresses {
-                if let Err(e) = socket.send_to(&data, *addr).await {
+            for (player_id, addr) in lobby.client_addresses.iter().filter(|(id, _)| wanted_by(id)) {
+                if let Err(e) = outbox.send(data_for(player_id), *addr) {
                     log::debug!("Failed to send event to {}: {:?}", addr, e);
                 }
             }
@@ -688,7 +2028,7 @@ mod tests {
         let weapons = WeaponDb::load();
         
         // Add shooter and target
-        let mut shooter = crate::state::lobby::Player {
+        let shooter = crate::state::lobby::Player {
             id: 1,
             name: "Shooter".to_string(),
             position: (0.0, 1.0, 0.0),
@@ -709,9 +2049,40 @@ mod tests {
             warned_at: None,
             is_dead: false,
             respawn_time: None,
+            vertical_velocity: 0.0,
+            fall_speed: 0.0,
+            last_position_time: None,
+            last_whisper_time: std::time::SystemTime::UNIX_EPOCH,
+            changes: crate::state::lobby::ChangeMask::empty(),
+            trigger_held: false,
+            fire_target: None,
+            burst_remaining: 0,
+            overheal_decay: 0.0,
+            match_stats: crate::state::lobby::MatchStats::default(),
+            rtt_ms: None,
+            ready: false,
+            spawn_protection_until: None,
+            anticheat_strikes: 0,
+            pending_loadout: None,
+            quaternion_rotation: false,
+            message_keys: false,
+            last_activity: std::time::SystemTime::now(),
+            afk_warned: false,
+            spectating: false,
+            command_last_used: Default::default(),
+            equip_end_time: None,
+            active_loadout: None,
+            preferences: Default::default(),
+            armor: 0,
+            region: None,
+            carried_weapon: None,
+            party_id: None,
+            heat: 0.0,
+            overheated_until: None,
+            client_id: None,
         };
         
-        let mut target = crate::state::lobby::Player {
+        let target = crate::state::lobby::Player {
             id: 2,
             name: "Target".to_string(),
             position: (0.0, 1.0, 0.0),
@@ -732,12 +2103,43 @@ mod tests {
             warned_at: None,
             is_dead: false,
             respawn_time: None,
+            vertical_velocity: 0.0,
+            fall_speed: 0.0,
+            last_position_time: None,
+            last_whisper_time: std::time::SystemTime::UNIX_EPOCH,
+            changes: crate::state::lobby::ChangeMask::empty(),
+            trigger_held: false,
+            fire_target: None,
+            burst_remaining: 0,
+            overheal_decay: 0.0,
+            match_stats: crate::state::lobby::MatchStats::default(),
+            rtt_ms: None,
+            ready: false,
+            spawn_protection_until: None,
+            anticheat_strikes: 0,
+            pending_loadout: None,
+            quaternion_rotation: false,
+            message_keys: false,
+            last_activity: std::time::SystemTime::now(),
+            afk_warned: false,
+            spectating: false,
+            command_last_used: Default::default(),
+            equip_end_time: None,
+            active_loadout: None,
+            preferences: Default::default(),
+            armor: 0,
+            region: None,
+            carried_weapon: None,
+            party_id: None,
+            heat: 0.0,
+            overheated_until: None,
+            client_id: None,
         };
         
         lobby.players.insert(1, shooter);
         lobby.players.insert(2, target);
         
-        let cmd = LobbyCommand::Shoot { player_id: 1, target_id: 2 };
+        let cmd = LobbyCommand::Shoot { player_id: 1, target_id: Some(2), pellet_hits: Vec::new() };
         process_command(&mut lobby, &weapons, cmd, None);
         
         let shooter = lobby.players.get(&1).unwrap();
@@ -746,5 +2148,483 @@ mod tests {
         let target = lobby.players.get(&2).unwrap();
         assert_eq!(target.current_health, 80); // 100 - 20 damage
     }
-}
 
+    #[test]
+    fn test_process_command_end_match() {
+        let mut lobby = Lobby::new("TEST".to_string(), 4, "world".to_string());
+        let weapons = WeaponDb::load();
+        let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080);
+
+        process_command(&mut lobby, &weapons, LobbyCommand::PlayerJoin { player_id: 1, name: "Test".to_string(), addr }, None);
+        lobby.players.get_mut(&1).unwrap().score = 200;
+
+        process_command(&mut lobby, &weapons, LobbyCommand::EndMatch, None);
+
+        assert_eq!(lobby.players.get(&1).unwrap().score, 0);
+        assert!(matches!(&lobby.pending_events[0], SyncEvent::MatchEnded { standings, .. } if standings[0].score == 200));
+
+        let packet = event_packet(&lobby, &lobby.pending_events[0]).unwrap();
+        assert_eq!(packet["awards"][0]["award"], "mvp");
+        assert_eq!(packet["awards"][0]["player_id"], 1);
+    }
+
+    #[test]
+    fn test_process_command_position_out_of_world() {
+        let mut lobby = Lobby::new("TEST".to_string(), 4, "world".to_string());
+        let weapons = WeaponDb::load();
+        let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080);
+
+        process_command(&mut lobby, &weapons, LobbyCommand::PlayerJoin { player_id: 1, name: "Test".to_string(), addr }, None);
+        process_command(&mut lobby, &weapons, LobbyCommand::PositionUpdate {
+            player_id: 1,
+            position: (0.0, -500.0, 0.0),
+            rotation: (0.0, 0.0, 0.0),
+        }, None);
+
+        assert!(lobby.players.get(&1).unwrap().is_dead);
+        assert!(matches!(lobby.pending_events[0], SyncEvent::PlayerDied { player_id: 1, cause: "out_of_world" }));
+    }
+
+    #[test]
+    fn test_process_command_position_sends_chunk_hints() {
+        let mut lobby = Lobby::new("TEST".to_string(), 4, "world".to_string());
+        let weapons = WeaponDb::load();
+        let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080);
+        process_command(&mut lobby, &weapons, LobbyCommand::PlayerJoin { player_id: 1, name: "Test".to_string(), addr }, None);
+        lobby.pending_events.clear();
+
+        for x in [-10.0, 200.0] {
+            process_command(&mut lobby, &weapons, LobbyCommand::PositionUpdate { player_id: 1, position: (x, 1.0, 10.0), rotation: (0.0, 0.0, 0.0) }, None);
+        }
+        let packets: Vec<serde_json::Value> = lobby.pending_events.iter().filter_map(|e| event_packet(&lobby, e)).collect();
+        assert_eq!(packets[0]["type"], "chunk_enter");
+        assert_eq!(packets[0]["chunk"], json!([-1, 0]));
+        assert_eq!(packets[0]["min"], json!([-125.0, 0.0]));
+        assert_eq!(packets[1]["type"], "chunk_exit");
+        assert_eq!(packets[2]["chunk"], json!([1, 0]));
+        assert!(lobby.pending_events.iter().all(|e| e.recipient() == Some(1)));
+    }
+
+    #[test]
+    fn test_rejected_actions_reported_to_sender() {
+        let mut lobby = Lobby::new("TEST".to_string(), 4, "world".to_string());
+        let weapons = WeaponDb::load();
+        let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080);
+
+        process_command(&mut lobby, &weapons, LobbyCommand::PlayerJoin { player_id: 1, name: "Alice".to_string(), addr }, None);
+        process_command(&mut lobby, &weapons, LobbyCommand::PlayerJoin { player_id: 2, name: "Bob".to_string(), addr }, None);
+        lobby.owner_id = Some(1);
+        lobby.pending_events.clear();
+
+        // Full magazine, and a pause from someone other than the owner
+        process_command(&mut lobby, &weapons, LobbyCommand::Reload { player_id: 1 }, None);
+        process_command(&mut lobby, &weapons, LobbyCommand::Pause { player_id: Some(2) }, None);
+
+        assert!(matches!(lobby.pending_events[0], SyncEvent::ActionFailed { player_id: 1, command: "reload", reason: "Cannot reload" }));
+        assert_eq!(lobby.pending_events[1].recipient(), Some(2));
+        let packet = event_packet(&lobby, &lobby.pending_events[1]).unwrap();
+        assert_eq!(packet["type"], "action_failed");
+        assert_eq!(packet["command"], "pause");
+        assert_eq!(packet["code"], "not_the_lobby_owner");
+        assert!(!lobby.is_paused());
+    }
+
+    #[test]
+    fn test_process_command_whisper() {
+        let mut lobby = Lobby::new("TEST".to_string(), 4, "world".to_string());
+        let weapons = WeaponDb::load();
+        let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080);
+
+        process_command(&mut lobby, &weapons, LobbyCommand::PlayerJoin { player_id: 1, name: "Alice".to_string(), addr }, None);
+        process_command(&mut lobby, &weapons, LobbyCommand::PlayerJoin { player_id: 2, name: "Bob".to_string(), addr }, None);
+
+        process_command(&mut lobby, &weapons, LobbyCommand::Whisper { player_id: 1, target_id: 2, text: "hi".to_string() }, None);
+        process_command(&mut lobby, &weapons, LobbyCommand::Whisper { player_id: 1, target_id: 2, text: "again".to_string() }, None);
+
+        assert!(matches!(&lobby.pending_events[0], SyncEvent::Whisper { from_name, to_id: 2, .. } if from_name == "Alice"));
+        assert!(matches!(lobby.pending_events[1], SyncEvent::WhisperFailed { player_id: 1, reason: "Rate limited", .. }));
+    }
+
+    #[test]
+    fn test_process_command_emote() {
+        let mut lobby = Lobby::new("TEST".to_string(), 4, "world".to_string());
+        let weapons = WeaponDb::load();
+        let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080);
+
+        process_command(&mut lobby, &weapons, LobbyCommand::PlayerJoin { player_id: 1, name: "Alice".to_string(), addr }, None);
+        process_command(&mut lobby, &weapons, LobbyCommand::PlayerJoin { player_id: 2, name: "Bob".to_string(), addr }, None);
+        for player in lobby.players.values_mut() {
+            player.position = (0.0, 1.0, 0.0);
+        }
+        lobby.pending_events.clear();
+
+        process_command(&mut lobby, &weapons, LobbyCommand::Emote { player_id: 1, emote: "salute".to_string() }, None);
+        process_command(&mut lobby, &weapons, LobbyCommand::Emote { player_id: 1, emote: "wave".to_string() }, None);
+
+        let packet = event_packet(&lobby, &lobby.pending_events[0]).unwrap();
+        assert_eq!(packet["type"], "emote");
+        assert_eq!(packet["emote"], "salute");
+        assert_eq!(lobby.pending_events[0].recipient(), Some(2));
+        assert!(matches!(lobby.pending_events[1], SyncEvent::ActionFailed { player_id: 1, command: "emote", reason: "Emote on cooldown" }));
+
+        // Late joiners are told what is still playing
+        let playing = playing_emotes(&lobby);
+        assert_eq!(playing.len(), 1);
+        assert_eq!(playing[0]["player_id"], 1);
+    }
+
+    #[test]
+    fn test_process_command_rebind_ack_restores_address() {
+        let mut lobby = Lobby::new("TEST".to_string(), 4, "world".to_string());
+        let weapons = WeaponDb::load();
+        let old = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080);
+        let new = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8081);
+        process_command(&mut lobby, &weapons, LobbyCommand::PlayerJoin { player_id: 1, name: "Alice".to_string(), addr: old }, None);
+
+        lobby.presence.clone().touch(1, new, std::time::SystemTime::now());
+        lobby.presence.clone().apply(&mut lobby);
+        assert!(!lobby.client_addresses.contains_key(&1));
+
+        process_command(&mut lobby, &weapons, LobbyCommand::RebindAck { player_id: 1, addr: new, generation: 0 }, None);
+        assert!(!lobby.client_addresses.contains_key(&1));
+        process_command(&mut lobby, &weapons, LobbyCommand::RebindAck { player_id: 1, addr: new, generation: 1 }, None);
+        assert_eq!(lobby.client_addresses[&1], new);
+    }
+
+    #[test]
+    fn test_process_command_chat_routes_commands() {
+        let mut lobby = Lobby::new("TEST".to_string(), 4, "world".to_string());
+        let weapons = WeaponDb::load();
+        let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080);
+
+        process_command(&mut lobby, &weapons, LobbyCommand::PlayerJoin { player_id: 1, name: "Alice".to_string(), addr }, None);
+        process_command(&mut lobby, &weapons, LobbyCommand::Chat { player_id: 1, text: " gg ".to_string() }, None);
+        process_command(&mut lobby, &weapons, LobbyCommand::Chat { player_id: 1, text: "/players".to_string() }, None);
+
+        assert!(matches!(&lobby.pending_events[0], SyncEvent::ChatMessage { from_id: 1, text, .. } if text == "gg"));
+        let reply = &lobby.pending_events[1];
+        assert_eq!(reply.recipient(), Some(1));
+        assert!(matches!(reply, SyncEvent::Whisper { from_id: chat::SERVER_SENDER_ID, text, .. } if text == "1 players: Alice (1)"));
+        let packet = event_packet(&lobby, reply).unwrap();
+        assert_eq!(packet["type"], "whisper");
+        assert_eq!(packet["from_name"], chat::SERVER_SENDER_NAME);
+    }
+
+    #[tokio::test]
+    async fn test_channel_chat_fans_out_across_lobbies() {
+        use crate::state::server_state::LobbyHandle;
+        let weapons = WeaponDb::load();
+        let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080);
+        let state = ServerState::new();
+        let mut lobbies = Vec::new();
+        let mut receivers = Vec::new();
+        for (code, player_id, name) in [("A", 1, "Alice"), ("B", 2, "Bob")] {
+            let mut lobby = Lobby::new(code.to_string(), 4, "world".to_string());
+            process_command(&mut lobby, &weapons, LobbyCommand::PlayerJoin { player_id, name: name.to_string(), addr }, None);
+            let (tx, rx) = mpsc::channel(8);
+            state.insert_lobby(code.to_string(), LobbyHandle {
+                lobby: Arc::new(RwLock::new(Lobby::new(code.to_string(), 4, "world".to_string()))),
+                command_tx: tx,
+                task_handle: tokio::spawn(async {}),
+                summary: Default::default(),
+                presence: Default::default(),
+            });
+            state.register_player_lobby(player_id, code);
+            lobbies.push(lobby);
+            receivers.push(rx);
+        }
+
+        let subscribe = |player_id| LobbyCommand::ChannelSubscribe { player_id, channel: "global".to_string(), subscribe: true };
+        process_command(&mut lobbies[0], &weapons, subscribe(1), Some(&state));
+        process_command(&mut lobbies[1], &weapons, subscribe(2), Some(&state));
+        assert!(matches!(lobbies[0].pending_events.last(), Some(SyncEvent::ChannelSubscription { player_id: 1, subscribed: true, .. })));
+
+        let chat = |channel: &str| LobbyCommand::ChannelChat { player_id: 1, channel: channel.to_string(), text: " hello ".to_string() };
+        process_command(&mut lobbies[0], &weapons, chat("global"), Some(&state));
+        let delivery = receivers[1].try_recv().unwrap();
+        // The sender is a subscriber too, so their own lobby gets a copy
+        assert!(matches!(receivers[0].try_recv(), Ok(LobbyCommand::ChannelDeliver { recipients, .. }) if recipients == vec![1]));
+        process_command(&mut lobbies[1], &weapons, delivery, Some(&state));
+        let event = lobbies[1].pending_events.last().unwrap();
+        assert_eq!(event.recipient(), Some(2));
+        let packet = event_packet(&lobbies[1], event).unwrap();
+        assert_eq!(packet["type"], "channel_message");
+        assert_eq!(packet["from_name"], "Alice");
+        assert_eq!(packet["text"], "hello");
+
+        // Channel cooldown, unsubscribed channels and unknown channels are refused
+        process_command(&mut lobbies[0], &weapons, chat("global"), Some(&state));
+        assert!(matches!(lobbies[0].pending_events.last(), Some(SyncEvent::ActionFailed { reason: "Rate limited", .. })));
+        process_command(&mut lobbies[0], &weapons, chat("region"), Some(&state));
+        assert!(matches!(lobbies[0].pending_events.last(), Some(SyncEvent::ActionFailed { reason: "Not subscribed", .. })));
+        process_command(&mut lobbies[0], &weapons, chat("trade"), Some(&state));
+        assert!(matches!(lobbies[0].pending_events.last(), Some(SyncEvent::ActionFailed { reason: "Unknown channel", .. })));
+    }
+
+    #[test]
+    fn test_process_command_pause_requires_owner() {
+        let mut lobby = Lobby::new("TEST".to_string(), 4, "world".to_string());
+        let weapons = WeaponDb::load();
+        let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080);
+
+        process_command(&mut lobby, &weapons, LobbyCommand::PlayerJoin { player_id: 1, name: "Owner".to_string(), addr }, None);
+        process_command(&mut lobby, &weapons, LobbyCommand::PlayerJoin { player_id: 2, name: "Guest".to_string(), addr }, None);
+
+        process_command(&mut lobby, &weapons, LobbyCommand::Pause { player_id: Some(2) }, None);
+        assert!(!lobby.is_paused());
+
+        process_command(&mut lobby, &weapons, LobbyCommand::Pause { player_id: Some(1) }, None);
+        assert!(lobby.is_paused());
+
+        // Administrators can always resume
+        process_command(&mut lobby, &weapons, LobbyCommand::Resume { player_id: None }, None);
+        assert!(!lobby.is_paused());
+        assert!(matches!(lobby.pending_events.last(), Some(SyncEvent::GameResumed { resumed_by: None, .. })));
+    }
+
+    #[test]
+    fn test_vote_kick_through_commands() {
+        let mut lobby = Lobby::new("TEST".to_string(), 4, "world".to_string());
+        let weapons = WeaponDb::load();
+        for player_id in 1..=3 {
+            let addr = format!("127.0.0.1:{}", 6100 + player_id).parse().unwrap();
+            process_command(&mut lobby, &weapons, LobbyCommand::PlayerJoin { player_id, name: format!("P{}", player_id), addr }, None);
+        }
+
+        process_command(&mut lobby, &weapons, LobbyCommand::VoteStart { player_id: 1, kind: VoteKind::Kick { target_id: 3 } }, None);
+        process_command(&mut lobby, &weapons, LobbyCommand::VoteCast { player_id: 2, yes: true }, None);
+
+        let outcome = votes::update_vote(&mut lobby, 0.02).unwrap();
+        assert_eq!(execute_vote(&mut lobby, &weapons, outcome, None), Some(3));
+        assert!(!lobby.players.contains_key(&3));
+        assert!(matches!(lobby.pending_events.last(), Some(SyncEvent::PlayerKicked { player_id: 3, .. })));
+    }
+
+    #[test]
+    fn test_admin_kick_and_announce() {
+        let mut lobby = Lobby::new("TEST".to_string(), 4, "world".to_string());
+        let weapons = WeaponDb::load();
+        for player_id in 1..=2 {
+            let addr = format!("127.0.0.1:{}", 6200 + player_id).parse().unwrap();
+            process_command(&mut lobby, &weapons, LobbyCommand::PlayerJoin { player_id, name: format!("P{}", player_id), addr }, None);
+        }
+
+        process_command(&mut lobby, &weapons, LobbyCommand::Kick { player_id: 2, reason: "AFK".to_string() }, None);
+        assert!(!lobby.players.contains_key(&2) && !lobby.client_addresses.contains_key(&2));
+        assert!(matches!(lobby.pending_events.last(), Some(SyncEvent::PlayerKicked { player_id: 2, reason }) if reason == "AFK"));
+
+        process_command(&mut lobby, &weapons, LobbyCommand::Announce { message: "Restarting soon".to_string() }, None);
+        let packet = event_packet(&lobby, lobby.pending_events.last().unwrap()).unwrap();
+        assert_eq!(packet["type"], "announcement");
+        assert_eq!(packet["message"], "Restarting soon");
+        assert_eq!(packet["message_key"], "announcement");
+        assert_eq!(packet["message_params"]["text"], "Restarting soon");
+    }
+
+    #[test]
+    fn test_localizing_clients_get_keys_without_fallback() {
+        let mut lobby = Lobby::new("TEST".to_string(), 4, "world".to_string());
+        let weapons = WeaponDb::load();
+        for player_id in 1..=2 {
+            let addr = format!("127.0.0.1:{}", 6400 + player_id).parse().unwrap();
+            process_command(&mut lobby, &weapons, LobbyCommand::PlayerJoin { player_id, name: format!("P{}", player_id), addr }, None);
+        }
+        process_command(&mut lobby, &weapons, LobbyCommand::SetCapabilities { player_id: 2, quaternion_rotation: false, message_keys: true, preferences: Default::default() }, None);
+
+        let (outbox, mut rx) = Outbox::new(10);
+        let events = [SyncEvent::PlayerKicked { player_id: 3, reason: votes::VOTE_KICK_REASON.to_string() }];
+        broadcast_state_events(&mut lobby, &outbox, &events, &mut PacketBuffer::new(1024));
+        let mut reasons = std::collections::HashMap::new();
+        while let Ok(packet) = rx.try_recv() {
+            let json: serde_json::Value = serde_json::from_slice(&packet.data).unwrap();
+            assert_eq!(json["reason_key"], "kick.vote");
+            reasons.insert(packet.addr.port() - 6400, json.get("reason").cloned());
+        }
+        assert_eq!(reasons[&1], Some(json!("Vote kick")));
+        assert_eq!(reasons[&2], None);
+    }
+
+    #[test]
+    fn test_run_tick_welcomes_joins() {
+        let mut lobby = Lobby::new("TEST".to_string(), 4, "world".to_string());
+        let weapons = WeaponDb::load();
+        let (outbox, mut rx) = Outbox::new(10);
+        let mut io = TickIo { outbox, bandwidth: Arc::new(BandwidthTracker::new()), send_buffer: PacketBuffer::default() };
+        let addr: SocketAddr = "127.0.0.1:6500".parse().unwrap();
+        let join = LobbyCommand::PlayerJoin { player_id: 1, name: "P1".to_string(), addr };
+
+        let outcome = run_tick(&mut lobby, &weapons, &Config::default(), None, &mut io, vec![join]);
+        assert_eq!(outcome, TickOutcome { membership_changed: true, closing: false });
+        let first: serde_json::Value = serde_json::from_slice(&rx.try_recv().unwrap().data).unwrap();
+        assert_eq!(first["type"], "welcome");
+
+        let outcome = run_tick(&mut lobby, &weapons, &Config::default(), None, &mut io, Vec::new());
+        assert!(!outcome.membership_changed);
+        assert!(lobby.dirty_players.is_empty());
+    }
+
+    #[test]
+    fn test_process_command_pickup_checks_range_once() {
+        let mut lobby = Lobby::new("TEST".to_string(), 4, "arena".to_string());
+        let weapons = WeaponDb::load();
+        let addr: SocketAddr = "127.0.0.1:6600".parse().unwrap();
+        process_command(&mut lobby, &weapons, LobbyCommand::PlayerJoin { player_id: 1, name: "P1".to_string(), addr }, None);
+        let pickup = lobby.pickups.items[0].clone();
+        assert_eq!(pickup.kind, pickups::PickupKind::Health);
+        let player = lobby.players.get_mut(&1).unwrap();
+        player.current_health = 40;
+        player.position = (pickup.position.0 + 5.0, pickup.position.1, pickup.position.2);
+
+        let take = LobbyCommand::Pickup { player_id: 1, pickup_id: pickup.id };
+        process_command(&mut lobby, &weapons, take.clone(), None);
+        assert_eq!(lobby.players[&1].current_health, 40);
+
+        lobby.players.get_mut(&1).unwrap().position = pickup.position;
+        process_command(&mut lobby, &weapons, take.clone(), None);
+        process_command(&mut lobby, &weapons, take, None);
+        assert_eq!(lobby.players[&1].current_health, 65);
+        let refused: Vec<_> = lobby.pending_events.iter()
+            .filter_map(|e| match e {
+                SyncEvent::ActionFailed { command: "pickup", reason, .. } => Some(*reason),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(refused, vec!["Too far from pickup", "Pickup not available"]);
+    }
+
+    #[test]
+    fn test_vote_change_scene() {
+        let mut lobby = Lobby::new("TEST".to_string(), 4, "world".to_string());
+        let weapons = WeaponDb::load();
+        assert_eq!(execute_vote(&mut lobby, &weapons, VoteKind::ChangeScene { scene: "arena".to_string() }, None), None);
+        assert_eq!(lobby.scene, "arena");
+        assert_eq!(lobby.scene_data.name, "arena");
+    }
+
+    #[test]
+    fn test_lobby_wide_events_numbered_in_order() {
+        let mut lobby = Lobby::new("TEST".to_string(), 4, "world".to_string());
+        let addr: SocketAddr = "127.0.0.1:5000".parse().unwrap();
+        lobby.client_addresses.insert(1, addr);
+        let (outbox, mut rx) = Outbox::new(10);
+        let mut buffer = PacketBuffer::new(1024);
+
+        broadcast_player_leave_events(&mut lobby, &outbox, &[2]);
+        let events = [
+            SyncEvent::ReadyChanged { player_id: 1, ready: true },
+            SyncEvent::WhisperFailed { player_id: 1, to_id: 2, reason: "Rate limited" },
+            SyncEvent::ReadyChanged { player_id: 1, ready: false },
+        ];
+        broadcast_state_events(&mut lobby, &outbox, &events, &mut buffer);
+        broadcast_respawn_events(&mut lobby, &outbox, &[1]);
+
+        let mut ids = Vec::new();
+        while let Ok(packet) = rx.try_recv() {
+            let packet: serde_json::Value = serde_json::from_slice(&packet.data).unwrap();
+            ids.push(packet["event_id"].as_u64());
+        }
+        // Targeted packets don't use up ids, so lobby-wide ones stay gapless
+        assert_eq!(ids, vec![Some(1), Some(2), None, Some(3), Some(4)]);
+        assert_eq!(lobby.last_event_id, 4);
+    }
+
+    #[test]
+    fn test_orientation_sent_only_to_quaternion_clients() {
+        let mut lobby = Lobby::new("TEST".to_string(), 4, "world".to_string());
+        let weapons = WeaponDb::load();
+        for player_id in 1..=3 {
+            let addr = format!("127.0.0.1:{}", 6300 + player_id).parse().unwrap();
+            process_command(&mut lobby, &weapons, LobbyCommand::PlayerJoin { player_id, name: format!("P{}", player_id), addr }, None);
+        }
+        process_command(&mut lobby, &weapons, LobbyCommand::SetCapabilities { player_id: 2, quaternion_rotation: true, message_keys: false, preferences: Default::default() }, None);
+        let rotation = (0.0, std::f32::consts::TAU + 1.0, 0.0);
+        process_command(&mut lobby, &weapons, LobbyCommand::PositionUpdate { player_id: 1, position: (0.0, 1.0, 0.0), rotation }, None);
+        assert!((lobby.players[&1].rotation.1 - 1.0).abs() < 1e-4); // Wrapped on the way in
+
+        let (outbox, mut rx) = Outbox::new(10);
+        broadcast_position_updates(&lobby, &outbox, &[1]);
+        let mut orientations = std::collections::HashMap::new();
+        while let Ok(packet) = rx.try_recv() {
+            let json: serde_json::Value = serde_json::from_slice(&packet.data).unwrap();
+            orientations.insert(packet.addr.port() - 6300, json.get("orientation").cloned());
+        }
+        assert_eq!(orientations.len(), 2);
+        assert!(orientations[&3].is_none());
+        let q = orientations[&2].clone().unwrap();
+        assert!((q["y"].as_f64().unwrap() - 0.5f64.sin()).abs() < 1e-4);
+        assert!((q["w"].as_f64().unwrap() - 0.5f64.cos()).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_unreachable_leaves() {
+        let mut lobby = Lobby::new("TEST".to_string(), 4, "world".to_string());
+        let addr: std::net::SocketAddr = "127.0.0.1:5000".parse().unwrap();
+        lobby.client_addresses.insert(7, addr);
+        lobby.client_addresses.insert(8, "127.0.0.1:5001".parse().unwrap());
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        tx.send(addr).unwrap();
+        tx.send("127.0.0.1:6000".parse().unwrap()).unwrap(); // Already gone
+
+        let leaves = unreachable_leaves(&lobby, &mut rx);
+        assert_eq!(leaves.len(), 1);
+        assert!(matches!(leaves[0], LobbyCommand::PlayerLeave { player_id: 7 }));
+    }
+
+    #[test]
+    fn test_check_auto_resume() {
+        let mut lobby = Lobby::new("TEST".to_string(), 4, "world".to_string());
+        lobby.paused_at = Some(std::time::SystemTime::now() - std::time::Duration::from_secs(10));
+
+        check_auto_resume(&mut lobby, 60);
+        assert!(lobby.is_paused());
+
+        check_auto_resume(&mut lobby, 5);
+        assert!(!lobby.is_paused());
+        assert!(matches!(lobby.pending_events[0], SyncEvent::GameResumed { resumed_by: None, .. }));
+    }
+
+    #[test]
+    fn test_process_command_respects_weapon_overrides() {
+        use crate::state::lobby::LobbySettings;
+        use crate::utils::weapondb::{WeaponOverlay, WeaponOverride};
+
+        let weapons = WeaponDb::load();
+        let overrides = [
+            (1, WeaponOverride { damage_multiplier: Some(2.0), ..Default::default() }),
+            (3, WeaponOverride { disabled: true, ..Default::default() }),
+        ].into_iter().collect();
+        let settings = LobbySettings {
+            weapons: std::sync::Arc::new(WeaponOverlay::resolve(&weapons, overrides).unwrap()),
+            ..Default::default()
+        };
+        let mut lobby = Lobby::with_settings("TEST".to_string(), 4, "world".to_string(), settings);
+        let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080);
+
+        process_command(&mut lobby, &weapons, LobbyCommand::PlayerJoin { player_id: 1, name: "A".to_string(), addr }, None);
+        process_command(&mut lobby, &weapons, LobbyCommand::PlayerJoin { player_id: 2, name: "B".to_string(), addr }, None);
+
+        process_command(&mut lobby, &weapons, LobbyCommand::Shoot { player_id: 1, target_id: Some(2), pellet_hits: Vec::new() }, None);
+        assert_eq!(lobby.players.get(&2).unwrap().current_health, 60); // 2 x 20 damage
+
+        process_command(&mut lobby, &weapons, LobbyCommand::WeaponSwitch { player_id: 1, weapon_id: 3 }, None);
+        assert_eq!(lobby.players.get(&1).unwrap().current_weapon_id, 1);
+    }
+
+    #[test]
+    fn test_out_of_bounds_and_unknown_ids_strike() {
+        let mut lobby = Lobby::new("TEST".to_string(), 4, "world".to_string());
+        let weapons = WeaponDb::load();
+        let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080);
+        process_command(&mut lobby, &weapons, LobbyCommand::PlayerJoin { player_id: 1, name: "A".to_string(), addr }, None);
+
+        let position = (1e7, 10.0, 0.0);
+        process_command(&mut lobby, &weapons, LobbyCommand::PositionUpdate { player_id: 1, position, rotation: (0.0, 0.0, 0.0) }, None);
+        assert_eq!(lobby.players[&1].position.0, lobby.scene_data.half_extent);
+
+        process_command(&mut lobby, &weapons, LobbyCommand::WeaponSwitch { player_id: 1, weapon_id: 42 }, None);
+        process_command(&mut lobby, &weapons, LobbyCommand::Violation { player_id: 1, kind: ViolationKind::NonFiniteValue }, None);
+        assert_eq!(lobby.players[&1].anticheat_strikes, 3);
+        assert_eq!(lobby.players[&1].current_weapon_id, 1);
+    }
+}
@@ -1,3 +1,11 @@
 pub mod delta_sync;
 pub mod lobby_tick;
 
+pub mod outbound;
+pub mod net_sim;
+pub mod hibernation;
+pub mod idle;
+pub mod replication;
+pub mod replay;
+pub mod checkpoint;
+pub mod tournaments;
@@ -0,0 +1,71 @@
+use crate::utils::rng::SeededRng;
+use std::time::Duration;
+
+/// Artificial network conditions applied to every client's UDP packets
+/// Development aid for testing prediction and the reliability layer; never enable in production
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NetSimConfig {
+    pub latency_ms: u64,
+    /// Extra random delay of up to this many ms on top of latency_ms
+    pub jitter_ms: u64,
+    /// Fraction of packets lost, 0.0 to 1.0
+    pub drop_rate: f32,
+    /// Fraction of delivered packets that arrive twice
+    pub duplicate_rate: f32,
+}
+
+/// Rolls the fate of each packet passing one direction of the socket
+#[derive(Debug, Clone)]
+pub struct NetSim {
+    config: NetSimConfig,
+    rng: SeededRng,
+}
+
+impl NetSim {
+    pub fn new(config: NetSimConfig, rng: SeededRng) -> Self {
+        Self { config, rng }
+    }
+
+    /// Delay of each copy of the next packet to deliver; empty when it is dropped
+    pub fn roll(&mut self) -> Vec<Duration> {
+        if self.rng.next_f32() < self.config.drop_rate {
+            return Vec::new();
+        }
+        let copies = if self.rng.next_f32() < self.config.duplicate_rate { 2 } else { 1 };
+        (0..copies).map(|_| self.delay()).collect()
+    }
+
+    fn delay(&mut self) -> Duration {
+        let jitter = self.rng.below(self.config.jitter_ms.min(u32::MAX as u64) as u32 + 1) as u64;
+        Duration::from_millis(self.config.latency_ms + jitter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sim(drop_rate: f32, duplicate_rate: f32) -> NetSim {
+        let config = NetSimConfig { latency_ms: 80, jitter_ms: 40, drop_rate, duplicate_rate };
+        NetSim::new(config, SeededRng::new(3))
+    }
+
+    #[test]
+    fn test_delays_within_latency_and_jitter() {
+        let mut clean = sim(0.0, 0.0);
+        for _ in 0..200 {
+            let delays = clean.roll();
+            assert_eq!(delays.len(), 1);
+            assert!(delays[0] >= Duration::from_millis(80) && delays[0] <= Duration::from_millis(120));
+        }
+        assert!(sim(1.0, 1.0).roll().is_empty());
+        assert_eq!(sim(0.0, 1.0).roll().len(), 2);
+    }
+
+    #[test]
+    fn test_drop_rate_is_a_fraction_of_packets() {
+        let mut lossy = sim(0.25, 0.0);
+        let dropped = (0..1000).filter(|_| lossy.roll().is_empty()).count();
+        assert!((150..350).contains(&dropped), "dropped {}", dropped);
+    }
+}
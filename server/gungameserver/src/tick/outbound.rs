@@ -0,0 +1,506 @@
+use bytes::Bytes;
+use dashmap::DashMap;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use tokio::net::UdpSocket;
+use tokio::sync::mpsc;
+use tokio::sync::mpsc::error::TrySendError;
+use crate::state::bandwidth::BandwidthTracker;
+use crate::tick::net_sim::{NetSim, NetSimConfig};
+use crate::utils::rng::SeededRng;
+use crate::utils::udp_socket;
+
+/// Packets a lobby may have queued before new ones are dropped
+/// UDP is lossy anyway; the tick must never wait on the network
+const OUTBOUND_QUEUE_CAPACITY: usize = 4096;
+
+/// Largest datagram the server sends; kept under common path MTUs so nothing is fragmented at IP level
+pub const MAX_DATAGRAM_SIZE: usize = 1200;
+
+/// Most continuation packets one payload may be split into before it is dropped
+pub const MAX_FRAGMENTS: usize = 32;
+
+/// Bytes held back in each fragment for the JSON header around the chunk
+const FRAGMENT_HEADER_RESERVE: usize = 128;
+
+/// Ids tying fragments of one payload together
+static NEXT_MESSAGE_ID: AtomicU32 = AtomicU32::new(1);
+
+/// Split a serialized packet into datagrams that fit `MAX_DATAGRAM_SIZE`
+/// Oversized payloads become `fragment` packets carrying `message_id`, `index`, `total`
+/// and a slice of the original JSON in `data`; clients concatenate the slices in index order
+/// Returns None (and warns) when the payload would need more than `MAX_FRAGMENTS`
+pub fn split_datagram(data: &Bytes) -> Option<Vec<Bytes>> {
+    if data.len() <= MAX_DATAGRAM_SIZE {
+        return Some(vec![data.clone()]);
+    }
+    let Ok(text) = std::str::from_utf8(data) else {
+        log::warn!("Dropping {} byte binary payload over the datagram limit", data.len());
+        return None;
+    };
+
+    // Cut on char boundaries, budgeting for the escaping the chunk picks up inside a JSON string
+    let budget = MAX_DATAGRAM_SIZE - FRAGMENT_HEADER_RESERVE;
+    let mut chunks = Vec::new();
+    let (mut start, mut escaped_len) = (0, 0);
+    for (offset, ch) in text.char_indices() {
+        let ch_len = escaped_char_len(ch);
+        if escaped_len + ch_len > budget {
+            chunks.push(&text[start..offset]);
+            start = offset;
+            escaped_len = 0;
+        }
+        escaped_len += ch_len;
+    }
+    chunks.push(&text[start..]);
+
+    if chunks.len() > MAX_FRAGMENTS {
+        log::warn!(
+            "Dropping {} byte payload: needs {} fragments, limit is {}",
+            data.len(), chunks.len(), MAX_FRAGMENTS
+        );
+        return None;
+    }
+
+    let message_id = NEXT_MESSAGE_ID.fetch_add(1, Ordering::Relaxed);
+    let total = chunks.len();
+    chunks.into_iter().enumerate()
+        .map(|(index, chunk)| {
+            let packet = serde_json::json!({
+                "type": "fragment",
+                "message_id": message_id,
+                "index": index,
+                "total": total,
+                "data": chunk
+            });
+            serde_json::to_vec(&packet).ok().map(Bytes::from)
+        })
+        .collect()
+}
+
+/// Length of a char once serialized inside a JSON string
+fn escaped_char_len(ch: char) -> usize {
+    match ch {
+        '"' | '\\' | '\n' | '\r' | '\t' | '\u{08}' | '\u{0c}' => 2,
+        c if (c as u32) < 0x20 => 6,
+        c => c.len_utf8(),
+    }
+}
+
+/// A serialized packet split for the wire: one datagram, or the fragments of an oversized one
+/// Built once per broadcast so every recipient gets the same buffers and `message_id`
+#[derive(Debug, Clone, Default)]
+pub struct Datagrams(Vec<Bytes>);
+
+impl From<Bytes> for Datagrams {
+    /// Payloads past the fragment limit become an empty set and are never queued
+    fn from(data: Bytes) -> Self {
+        Self(split_datagram(&data).unwrap_or_default())
+    }
+}
+
+impl From<Vec<u8>> for Datagrams {
+    fn from(data: Vec<u8>) -> Self {
+        Bytes::from(data).into()
+    }
+}
+
+impl Datagrams {
+    /// Bytes queued per recipient, headers of fragments included
+    pub fn len(&self) -> usize {
+        self.0.iter().map(Bytes::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// Serialized packet waiting to be sent to one client
+#[derive(Debug, Clone)]
+pub struct OutboundPacket {
+    pub data: Bytes,
+    pub addr: SocketAddr,
+}
+
+/// Consecutive send failures per client address
+/// Shared between the outbox and its sender task
+#[derive(Debug, Clone)]
+pub struct SendFailures {
+    counts: Arc<DashMap<SocketAddr, u32>>,
+    max_failures: u32,
+}
+
+impl SendFailures {
+    pub fn new(max_failures: u32) -> Self {
+        Self { counts: Arc::new(DashMap::new()), max_failures }
+    }
+
+    /// Whether the address has failed too many times in a row to keep sending to
+    pub fn is_disconnected(&self, addr: SocketAddr) -> bool {
+        self.counts.get(&addr).is_some_and(|count| *count >= self.max_failures)
+    }
+
+    /// Count a failed send; returns true exactly when the threshold is crossed
+    pub fn record_failure(&self, addr: SocketAddr) -> bool {
+        let mut count = self.counts.entry(addr).or_insert(0);
+        *count += 1;
+        *count == self.max_failures
+    }
+
+    /// A successful send clears the streak (unless already disconnected)
+    pub fn record_success(&self, addr: SocketAddr) {
+        if self.counts.contains_key(&addr) {
+            self.counts.remove_if(&addr, |_, count| *count < self.max_failures);
+        }
+    }
+
+    /// Forget an address entirely (client reconnected)
+    pub fn reset(&self, addr: SocketAddr) {
+        self.counts.remove(&addr);
+    }
+}
+
+/// Outbound event bus - the tick loop publishes serialized packets here
+/// and a sender task performs the socket I/O
+#[derive(Debug, Clone)]
+pub struct Outbox {
+    tx: mpsc::Sender<OutboundPacket>,
+    failures: SendFailures,
+    bandwidth: Arc<BandwidthTracker>,
+    cap_bytes_per_sec: Option<u64>, // Per-client budget for non-critical packets
+}
+
+impl Outbox {
+    /// Create an outbox and the receiving end for a sender task
+    pub fn new(max_failures: u32) -> (Self, mpsc::Receiver<OutboundPacket>) {
+        let (tx, rx) = mpsc::channel(OUTBOUND_QUEUE_CAPACITY);
+        let outbox = Self {
+            tx,
+            failures: SendFailures::new(max_failures),
+            bandwidth: Arc::new(BandwidthTracker::new()),
+            cap_bytes_per_sec: None,
+        };
+        (outbox, rx)
+    }
+
+    /// Account queued bytes in a shared tracker and cap non-critical packets per client
+    pub fn with_bandwidth(mut self, bandwidth: Arc<BandwidthTracker>, cap_bytes_per_sec: Option<u64>) -> Self {
+        self.bandwidth = bandwidth;
+        self.cap_bytes_per_sec = cap_bytes_per_sec;
+        self
+    }
+
+    /// Create an outbox drained by a sender task on `socket`
+    /// Addresses the sender gives up on arrive on the returned receiver
+    /// The task exits once every clone of the outbox is dropped
+    pub fn spawn(socket: Arc<UdpSocket>, max_failures: u32) -> (Self, mpsc::UnboundedReceiver<SocketAddr>) {
+        Self::spawn_with_net_sim(socket, max_failures, None)
+    }
+
+    /// Like `spawn`, with the sender delaying, dropping and duplicating packets per `net_sim`
+    pub fn spawn_with_net_sim(
+        socket: Arc<UdpSocket>,
+        max_failures: u32,
+        net_sim: Option<NetSimConfig>,
+    ) -> (Self, mpsc::UnboundedReceiver<SocketAddr>) {
+        let (outbox, rx) = Self::new(max_failures);
+        let (unreachable_tx, unreachable_rx) = mpsc::unbounded_channel();
+        let net_sim = net_sim.map(|config| NetSim::new(config, SeededRng::from_clock()));
+        tokio::spawn(run_sender(socket, rx, outbox.failures.clone(), unreachable_tx, net_sim));
+        (outbox, unreachable_rx)
+    }
+
+    /// Queue a packet for one client without waiting
+    /// Cloning `Bytes` is cheap, so the datagrams of one broadcast are shared across recipients
+    /// Packets to disconnected clients are silently dropped
+    pub fn send(&self, data: &Datagrams, addr: SocketAddr) -> Result<(), TrySendError<OutboundPacket>> {
+        if self.failures.is_disconnected(addr) {
+            return Ok(());
+        }
+        for datagram in &data.0 {
+            self.queue(datagram.clone(), addr)?;
+        }
+        Ok(())
+    }
+
+    fn queue(&self, data: Bytes, addr: SocketAddr) -> Result<(), TrySendError<OutboundPacket>> {
+        let len = data.len();
+        self.tx.try_send(OutboundPacket { data, addr })?;
+        self.bandwidth.record_sent(addr, len);
+        Ok(())
+    }
+
+    /// Queue a packet the client can do without (e.g. position updates)
+    /// Dropped once the client's outbound cap for this second is spent
+    pub fn send_non_critical(&self, data: &Datagrams, addr: SocketAddr) -> Result<(), TrySendError<OutboundPacket>> {
+        if let Some(cap) = self.cap_bytes_per_sec {
+            if !self.bandwidth.within_cap(addr, data.len(), cap) {
+                return Ok(());
+            }
+        }
+        self.send(data, addr)
+    }
+
+    /// Resume sending to an address after the client reconnects
+    pub fn reset(&self, addr: SocketAddr) {
+        self.failures.reset(addr);
+    }
+}
+
+/// Sender task: drain the outbound queue onto the socket
+/// Reports an address once its consecutive failures reach the threshold
+/// With a network simulation, delayed copies go out from their own tasks
+pub async fn run_sender(
+    socket: Arc<UdpSocket>,
+    mut rx: mpsc::Receiver<OutboundPacket>,
+    failures: SendFailures,
+    unreachable_tx: mpsc::UnboundedSender<SocketAddr>,
+    mut net_sim: Option<NetSim>,
+) {
+    while let Some(packet) = rx.recv().await {
+        if failures.is_disconnected(packet.addr) {
+            continue;
+        }
+        let Some(sim) = net_sim.as_mut() else {
+            send_packet(&socket, &packet, &failures, &unreachable_tx).await;
+            continue;
+        };
+        for delay in sim.roll() {
+            if delay.is_zero() {
+                send_packet(&socket, &packet, &failures, &unreachable_tx).await;
+                continue;
+            }
+            let (socket, packet, failures, unreachable_tx) = (socket.clone(), packet.clone(), failures.clone(), unreachable_tx.clone());
+            tokio::spawn(async move {
+                tokio::time::sleep(delay).await;
+                send_packet(&socket, &packet, &failures, &unreachable_tx).await;
+            });
+        }
+    }
+}
+
+async fn send_packet(
+    socket: &UdpSocket,
+    packet: &OutboundPacket,
+    failures: &SendFailures,
+    unreachable_tx: &mpsc::UnboundedSender<SocketAddr>,
+) {
+    // Try without waiting first so a full send buffer shows up in the socket counters
+    let sent = match socket.try_send_to(&packet.data, packet.addr) {
+        Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+            udp_socket::COUNTERS.record_would_block();
+            socket.send_to(&packet.data, packet.addr).await
+        }
+        sent => sent,
+    };
+    match sent {
+        Ok(_) => failures.record_success(packet.addr),
+        Err(e) => {
+            udp_socket::COUNTERS.record_send_error();
+            log::debug!("Failed to send packet to {}: {:?}", packet.addr, e);
+            if failures.record_failure(packet.addr) {
+                log::info!("Client {} unreachable, disconnecting", packet.addr);
+                let _ = unreachable_tx.send(packet.addr);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::time::Duration;
+
+    #[test]
+    fn test_send_never_blocks_when_full() {
+        let (outbox, _rx) = Outbox::new(10);
+        let data = Datagrams::from(Bytes::from_static(b"{}"));
+        let addr: SocketAddr = "127.0.0.1:9000".parse().unwrap();
+
+        for _ in 0..OUTBOUND_QUEUE_CAPACITY {
+            assert!(outbox.send(&data, addr).is_ok());
+        }
+        assert!(matches!(outbox.send(&data, addr), Err(TrySendError::Full(_))));
+    }
+
+    #[tokio::test]
+    async fn test_sender_task_delivers_packets() {
+        let socket = Arc::new(UdpSocket::bind("127.0.0.1:0").await.unwrap());
+        let client = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let (outbox, _unreachable) = Outbox::spawn(socket, 10);
+
+        outbox.send(&Bytes::from_static(b"hello").into(), client.local_addr().unwrap()).unwrap();
+
+        let mut buf = [0u8; 16];
+        let (len, _) = tokio::time::timeout(Duration::from_secs(1), client.recv_from(&mut buf))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(&buf[..len], b"hello");
+    }
+
+    #[tokio::test]
+    async fn test_simulated_sender_duplicates_after_latency() {
+        let socket = Arc::new(UdpSocket::bind("127.0.0.1:0").await.unwrap());
+        let client = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let net_sim = NetSimConfig { latency_ms: 60, jitter_ms: 0, drop_rate: 0.0, duplicate_rate: 1.0 };
+        let (outbox, _unreachable) = Outbox::spawn_with_net_sim(socket, 10, Some(net_sim));
+
+        let sent_at = std::time::Instant::now();
+        outbox.send(&Bytes::from_static(b"hello").into(), client.local_addr().unwrap()).unwrap();
+
+        let mut buf = [0u8; 16];
+        for _ in 0..2 {
+            let (len, _) = tokio::time::timeout(Duration::from_secs(1), client.recv_from(&mut buf))
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(&buf[..len], b"hello");
+        }
+        assert!(sent_at.elapsed() >= Duration::from_millis(60));
+    }
+
+    #[test]
+    fn test_capped_outbox_sheds_only_non_critical() {
+        let addr: SocketAddr = "127.0.0.1:9000".parse().unwrap();
+        let bandwidth = Arc::new(BandwidthTracker::new());
+        bandwidth.bind(addr, 1, "TEST");
+        let (outbox, mut rx) = Outbox::new(10);
+        let outbox = outbox.with_bandwidth(bandwidth.clone(), Some(10));
+        let data = Datagrams::from(Bytes::from_static(b"12345678"));
+
+        outbox.send_non_critical(&data, addr).unwrap();
+        outbox.send_non_critical(&data, addr).unwrap(); // Over budget: shed
+        outbox.send(&data, addr).unwrap(); // Critical packets always go out
+
+        let mut queued = 0;
+        while rx.try_recv().is_ok() {
+            queued += 1;
+        }
+        assert_eq!(queued, 2);
+        let report = bandwidth.snapshot();
+        assert_eq!((report[0].total_bytes_sent, report[0].packets_shed), (16, 1));
+    }
+
+    /// Join fragments the way the client does: by index, whatever order they arrived in
+    fn reassemble(fragments: &[Bytes]) -> String {
+        let mut packets: Vec<serde_json::Value> = fragments.iter().map(|f| serde_json::from_slice(f).unwrap()).collect();
+        packets.sort_by_key(|p| p["index"].as_u64().unwrap());
+        packets.iter().map(|p| p["data"].as_str().unwrap()).collect()
+    }
+
+    #[test]
+    fn test_split_datagram_fragments_and_reassembles() {
+        let small = Bytes::from_static(b"{\"type\":\"ping\"}");
+        assert_eq!(split_datagram(&small).unwrap(), vec![small]);
+
+        // Quotes and control chars grow when escaped; fragments must still fit
+        let names: Vec<String> = (0..300).map(|i| format!("\"p{}\"\n\u{1}é", i)).collect();
+        let original = serde_json::to_vec(&serde_json::json!({ "type": "player_list", "players": names })).unwrap();
+        let fragments = split_datagram(&Bytes::from(original.clone())).unwrap();
+        assert!(fragments.len() > 1);
+        assert!(fragments.iter().all(|f| f.len() <= MAX_DATAGRAM_SIZE));
+
+        let headers: Vec<serde_json::Value> = fragments.iter().map(|f| serde_json::from_slice(f).unwrap()).collect();
+        assert!(headers.iter().enumerate().all(|(i, h)| {
+            h["type"] == "fragment" && h["index"] == i && h["total"] == fragments.len() && h["message_id"] == headers[0]["message_id"]
+        }));
+        assert_eq!(reassemble(&fragments).as_bytes(), original.as_slice());
+
+        // Out of order still yields the original packet
+        let mut shuffled = fragments.clone();
+        shuffled.reverse();
+        shuffled.swap(0, 1);
+        let packet: serde_json::Value = serde_json::from_str(&reassemble(&shuffled)).unwrap();
+        assert_eq!(packet["type"], "player_list");
+        assert_eq!(packet["players"][299], "\"p299\"\n\u{1}é");
+
+        // Each oversized payload gets its own message id
+        let again = split_datagram(&Bytes::from(original)).unwrap();
+        let again_id = serde_json::from_slice::<serde_json::Value>(&again[0]).unwrap()["message_id"].clone();
+        assert_ne!(again_id, headers[0]["message_id"]);
+
+        let huge = Bytes::from(vec![b'a'; MAX_DATAGRAM_SIZE * (MAX_FRAGMENTS + 1)]);
+        assert!(split_datagram(&huge).is_none());
+    }
+
+    #[test]
+    fn test_oversized_send_queues_fragments() {
+        let (outbox, mut rx) = Outbox::new(10);
+        let addr: SocketAddr = "127.0.0.1:9000".parse().unwrap();
+        let payload = format!("{{\"data\":\"{}\"}}", "x".repeat(MAX_DATAGRAM_SIZE * 2));
+        outbox.send(&Bytes::from(payload.clone()).into(), addr).unwrap();
+
+        let mut fragments = Vec::new();
+        while let Ok(packet) = rx.try_recv() {
+            fragments.push(packet.data);
+        }
+        assert_eq!(fragments.len(), 3);
+        assert_eq!(reassemble(&fragments), payload);
+
+        // Payloads past the fragment limit are dropped, not queued
+        let huge = Datagrams::from(Bytes::from(vec![b'a'; MAX_DATAGRAM_SIZE * (MAX_FRAGMENTS + 1)]));
+        assert!(huge.is_empty());
+        outbox.send(&huge, addr).unwrap();
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_broadcast_shares_fragments_across_recipients() {
+        let (outbox, mut rx) = Outbox::new(10);
+        let (a, b): (SocketAddr, SocketAddr) = ("127.0.0.1:9000".parse().unwrap(), "127.0.0.1:9001".parse().unwrap());
+        let payload = format!("{{\"data\":\"{}\"}}", "x".repeat(MAX_DATAGRAM_SIZE * 2));
+        let data = Datagrams::from(Bytes::from(payload));
+        outbox.send(&data, a).unwrap();
+        outbox.send(&data, b).unwrap();
+
+        let mut by_addr: HashMap<SocketAddr, Vec<Bytes>> = HashMap::new();
+        while let Ok(packet) = rx.try_recv() {
+            by_addr.entry(packet.addr).or_default().push(packet.data);
+        }
+        // Same buffers, so the same message_id: split once, not per recipient
+        assert_eq!(by_addr[&a].len(), 3);
+        assert!(by_addr[&a].iter().zip(&by_addr[&b]).all(|(x, y)| x.as_ptr() == y.as_ptr()));
+    }
+
+    #[test]
+    fn test_send_failures_threshold() {
+        let failures = SendFailures::new(3);
+        let addr: SocketAddr = "127.0.0.1:9000".parse().unwrap();
+
+        assert!(!failures.record_failure(addr));
+        failures.record_success(addr); // Streak broken
+        assert!(!failures.record_failure(addr));
+        assert!(!failures.record_failure(addr));
+        assert!(failures.record_failure(addr));
+        assert!(failures.is_disconnected(addr));
+
+        failures.record_success(addr); // Stays disconnected until reset
+        assert!(failures.is_disconnected(addr));
+        failures.reset(addr);
+        assert!(!failures.is_disconnected(addr));
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_client_reported_and_dropped() {
+        // An IPv4 socket cannot send to an IPv6 address, so every send fails
+        let socket = Arc::new(UdpSocket::bind("127.0.0.1:0").await.unwrap());
+        let (outbox, mut unreachable) = Outbox::spawn(socket, 3);
+        let addr: SocketAddr = "[::1]:9000".parse().unwrap();
+
+        for _ in 0..3 {
+            outbox.send(&Bytes::from_static(b"x").into(), addr).unwrap();
+        }
+        let reported = tokio::time::timeout(Duration::from_secs(1), unreachable.recv())
+            .await
+            .unwrap();
+        assert_eq!(reported, Some(addr));
+        assert!(outbox.failures.is_disconnected(addr));
+
+        outbox.reset(addr);
+        assert!(!outbox.failures.is_disconnected(addr));
+    }
+}
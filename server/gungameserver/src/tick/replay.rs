@@ -0,0 +1,221 @@
+use std::io::BufRead;
+use std::sync::Arc;
+use serde::Serialize;
+use crate::state::lobby::{Lobby, LobbyCode};
+use crate::tick::replication::{ReplicationRecord, StandbyMirror};
+use crate::utils::config::Config;
+use crate::utils::weapondb::WeaponDb;
+
+/// Ticks between the state hashes a primary writes into its replication stream
+pub const STATE_HASH_EVERY: u64 = 50;
+
+/// A player's gameplay state, as the tick leaves it
+#[derive(Serialize)]
+struct HashedPlayer<'a> {
+    id: u32,
+    name: &'a str,
+    team: Option<u8>,
+    position: (f32, f32, f32),
+    health: u32,
+    armor: u32,
+    is_dead: bool,
+    weapon_id: u32,
+    ammo: u32,
+    is_reloading: bool,
+    kills: u32,
+    deaths: u32,
+    score: u32,
+    killstreak: u32,
+    ready: bool,
+}
+
+/// FNV-1a over the lobby's players in id order, stable across runs and builds
+pub fn state_hash(lobby: &Lobby) -> u64 {
+    let mut players: Vec<_> = lobby.players.values().collect();
+    players.sort_unstable_by_key(|p| p.id);
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for p in players {
+        let hashed = HashedPlayer {
+            id: p.id,
+            name: &p.name,
+            team: lobby.teams.team_of(p.id),
+            position: p.position,
+            health: p.current_health,
+            armor: p.armor,
+            is_dead: p.is_dead,
+            weapon_id: p.current_weapon_id,
+            ammo: p.current_ammo,
+            is_reloading: p.is_reloading,
+            kills: p.kills,
+            deaths: p.deaths,
+            score: p.score,
+            killstreak: p.killstreak,
+            ready: p.ready,
+        };
+        for byte in serde_json::to_vec(&hashed).unwrap_or_default() {
+            hash = (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+    hash
+}
+
+/// First recorded hash the replay did not reproduce
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    pub code: LobbyCode,
+    pub tick: u64,
+    pub recorded: u64,
+    pub replayed: Option<u64>, // None when the lobby doesn't exist in the replay
+}
+
+#[derive(Debug, Default)]
+pub struct ReplayReport {
+    pub records: usize,
+    pub hashes_checked: usize,
+    pub divergence: Option<Divergence>,
+}
+
+/// Replay a recorded replication stream through the tick, checking each state hash against the replayed lobby
+/// `config` must match the recording server's for the ticks to come out the same; stops at the first divergence
+pub fn verify(reader: impl BufRead, weapons: &WeaponDb, config: Arc<Config>) -> Result<ReplayReport, Box<dyn std::error::Error>> {
+    let mut mirror = StandbyMirror::new(config);
+    let mut report = ReplayReport::default();
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let record: ReplicationRecord = serde_json::from_str(&line)
+            .map_err(|e| format!("line {}: {}", index + 1, e))?;
+        report.records += 1;
+        if let ReplicationRecord::StateHash { code, tick, hash } = &record {
+            report.hashes_checked += 1;
+            let replayed = mirror.lobbies.get(code).map(state_hash);
+            if replayed != Some(*hash) {
+                report.divergence = Some(Divergence { code: code.clone(), tick: *tick, recorded: *hash, replayed });
+                break;
+            }
+        }
+        mirror.apply(record, weapons);
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, SystemTime};
+    use crate::state::commands::LobbyCommand;
+    use crate::state::server_state::ServerState;
+    use crate::tick::checkpoint::LobbyCheckpoint;
+    use crate::tick::lobby_tick::{self, TickIo};
+    use crate::tick::outbound::Outbox;
+    use crate::tick::replication::Replicator;
+    use crate::utils::buffers::PacketBuffer;
+    use crate::utils::clock;
+
+    /// The tick player 1 starts holding the trigger on player 2 (the match is live by then)
+    const FIRE_TICK: u64 = 300;
+
+    /// Play a short match through the real tick, recording it as a server would
+    fn recorded_match(weapons: &WeaponDb) -> Vec<ReplicationRecord> {
+        let config = Config::default();
+        let (replicator, mut recorded) = Replicator::capturing();
+        let state = ServerState::new();
+        state.set_replicator(replicator).unwrap();
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let mut lobby = clock::frozen(start, || Lobby::new("REPLAY".to_string(), 4, "world".to_string()));
+        state.replicator().unwrap().publish(ReplicationRecord::LobbyCreated { lobby: LobbyCheckpoint::capture(&lobby), at: Some(start) });
+
+        let (outbox, mut outbound) = Outbox::new(config.max_send_failures);
+        let mut io = TickIo { outbox, bandwidth: Arc::default(), send_buffer: PacketBuffer::default() };
+        let join = |player_id: u32| LobbyCommand::PlayerJoin {
+            player_id,
+            name: format!("P{}", player_id),
+            addr: format!("127.0.0.1:{}", 9000 + player_id).parse().unwrap(),
+        };
+        for tick in 1..=8 * STATE_HASH_EVERY {
+            let commands = match tick {
+                1 => vec![join(1), join(2)],
+                2 => vec![LobbyCommand::Ready { player_id: 1, ready: true }, LobbyCommand::Ready { player_id: 2, ready: true }],
+                FIRE_TICK => vec![LobbyCommand::FireHeld { player_id: 1, held: true, target_id: Some(2) }],
+                _ => Vec::new(),
+            };
+            lobby.current_tick = tick;
+            lobby.tick_interval_ms = config.tick_interval_ms();
+            lobby.heartbeat_interval_ms = config.heartbeat_interval_ms;
+            let at = start + Duration::from_millis(tick * config.tick_interval_ms());
+            clock::frozen(at, || lobby_tick::run_tick(&mut lobby, weapons, &config, Some(&state), &mut io, commands));
+            while outbound.try_recv().is_ok() {}
+        }
+        // Only the tick's own fire and timers hurt player 2; no command after FIRE_TICK did
+        let victim = &lobby.players[&2];
+        assert!(victim.deaths > 0 || victim.current_health < victim.max_health);
+
+        let mut records = Vec::new();
+        while let Ok(record) = recorded.try_recv() {
+            records.push(record);
+        }
+        records
+    }
+
+    fn stream(records: &[ReplicationRecord]) -> Vec<u8> {
+        records.iter().flat_map(|r| serde_json::to_string(r).unwrap().into_bytes().into_iter().chain([b'\n'])).collect()
+    }
+
+    fn verify_records(records: &[ReplicationRecord], weapons: &WeaponDb) -> ReplayReport {
+        verify(stream(records).as_slice(), weapons, Arc::new(Config::default())).unwrap()
+    }
+
+    #[test]
+    fn test_faithful_replay_verifies() {
+        let weapons = WeaponDb::load();
+        let records = recorded_match(&weapons);
+        let report = verify_records(&records, &weapons);
+        assert_eq!(report.records, records.len());
+        assert_eq!(report.hashes_checked, 8);
+        assert_eq!(report.divergence, None);
+    }
+
+    #[test]
+    fn test_tampered_replay_reports_first_divergent_tick() {
+        let weapons = WeaponDb::load();
+        let mut records = recorded_match(&weapons);
+        // Someone edited the recorded trigger pull to aim at nobody
+        let fire = records.iter_mut()
+            .find_map(|record| match record {
+                ReplicationRecord::Tick { tick: FIRE_TICK, commands, .. } => Some(commands),
+                _ => None,
+            })
+            .expect("fire tick recorded");
+        fire[0] = LobbyCommand::FireHeld { player_id: 1, held: true, target_id: None };
+        let report = verify_records(&records, &weapons);
+        let divergence = report.divergence.expect("divergence found");
+        assert_eq!(divergence.tick, FIRE_TICK.next_multiple_of(STATE_HASH_EVERY));
+        assert_ne!(divergence.replayed, Some(divergence.recorded));
+
+        assert!(verify("{\"record\":\"nope\"}\n".as_bytes(), &weapons, Arc::new(Config::default())).unwrap_err().to_string().starts_with("line 1:"));
+    }
+
+    #[test]
+    fn test_replay_follows_the_recorded_clock() {
+        let weapons = WeaponDb::load();
+        let mut records = recorded_match(&weapons);
+        // The same ticks squeezed into half the time: the weapon's fire rate allows fewer shots
+        let fired_at = records.iter()
+            .find_map(|record| match record {
+                ReplicationRecord::Tick { tick: FIRE_TICK, at, .. } => *at,
+                _ => None,
+            })
+            .expect("fire tick recorded");
+        for record in &mut records {
+            if let ReplicationRecord::Tick { at: Some(at), .. } = record {
+                if let Ok(since) = at.duration_since(fired_at) {
+                    *at = fired_at + since / 2;
+                }
+            }
+        }
+        let divergence = verify_records(&records, &weapons).divergence.expect("divergence found");
+        assert!(divergence.tick > FIRE_TICK);
+    }
+}
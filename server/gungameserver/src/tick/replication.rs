@@ -0,0 +1,836 @@
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Weak};
+use std::time::{Duration, SystemTime};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tokio::io::{AsyncBufReadExt, AsyncWrite, AsyncWriteExt, BufReader, Lines};
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::{TcpListener, TcpStream, UdpSocket};
+use tokio::sync::mpsc;
+use crate::domain::lobbies;
+use crate::state::commands::LobbyCommand;
+use crate::state::lobby::{Lobby, LobbyCode};
+use crate::state::presence::Presence;
+use crate::state::server_state::ServerState;
+use crate::tick::checkpoint::{LobbyCheckpoint, SettingsCheckpoint};
+use crate::tick::lobby_tick::{self, TickIo};
+use crate::tick::outbound::{OutboundPacket, Outbox};
+use crate::utils::buffers::PacketBuffer;
+use crate::utils::clock;
+use crate::utils::config::Config;
+use crate::utils::weapondb::{WeaponDb, WeaponView};
+
+/// Records buffered for the standby before new ones are dropped (and the standby resynced)
+const REPLICATION_QUEUE: usize = 4096;
+
+/// Delay between attempts to reach the standby
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+/// How often the primary tells the standby it's still alive
+pub const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How long a new connection to the standby has to send its `Hello`
+const HELLO_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// One entry of the replication stream (sent as a line of JSON)
+///
+/// Experimental: lobbies are created with their full settings, HTTP joins carry the
+/// player's team, VIP status and party, and every full tick is replayed with the
+/// commands it applied, the client reports it took in and the time it ran at.
+/// Snapshots only carry checkpoints, so health, ammo and positions reset on a resync.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "record", rename_all = "snake_case")]
+pub enum ReplicationRecord {
+    /// First record on every connection; the standby refuses epochs older than one it accepted
+    Hello {
+        epoch: u64,
+    },
+    /// Sent every `HEARTBEAT_INTERVAL` so the standby can tell a quiet primary from a dead one
+    Heartbeat,
+    /// Every lobby as the primary has it, sent after `Hello` and whenever records were dropped
+    Snapshot {
+        lobbies: Vec<MirroredLobby>,
+    },
+    /// The standby's answer to a primary it won't mirror; the primary stops serving
+    Fenced {
+        epoch: u64,
+    },
+    LobbyCreated {
+        lobby: LobbyCheckpoint,
+        /// When the lobby was created (its lifetime starts here)
+        #[serde(default)]
+        at: Option<SystemTime>,
+    },
+    PlayerAdded {
+        code: LobbyCode,
+        player_id: u32,
+        name: String,
+        #[serde(default)]
+        team: Option<u8>,
+        #[serde(default)]
+        vip: bool,
+        #[serde(default)]
+        party_id: Option<String>,
+        #[serde(default)]
+        at: Option<SystemTime>,
+    },
+    /// Settings changed outside the tick (the owner's MOTD)
+    SettingsChanged {
+        code: LobbyCode,
+        settings: SettingsCheckpoint,
+    },
+    /// One full tick; calm ticks the primary skipped aren't sent
+    Tick {
+        code: LobbyCode,
+        tick: u64,
+        /// The tick's clock; replays without one run at the replaying machine's time
+        #[serde(default)]
+        at: Option<SystemTime>,
+        commands: Vec<LobbyCommand>,
+        /// Addresses and keepalives the tick picked up from the UDP handler
+        #[serde(default)]
+        presence: Vec<(u32, Presence)>,
+    },
+    /// The primary closed the lobby (it reached its lifetime cap)
+    LobbyRemoved {
+        code: LobbyCode,
+    },
+    /// Periodic hash of the primary's lobby at the end of a tick, checked by `--verify-replay`
+    StateHash {
+        code: LobbyCode,
+        tick: u64,
+        hash: u64,
+    },
+}
+
+/// One lobby of a snapshot, with the last tick it includes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MirroredLobby {
+    pub tick: u64,
+    pub lobby: LobbyCheckpoint,
+}
+
+/// Primary side: streams records to a standby and/or a replay file from background tasks
+#[derive(Default)]
+pub struct Replicator {
+    standby: Option<Sink>,
+    recording: Option<Sink>,
+}
+
+/// Queue into one background writer
+struct Sink {
+    tx: mpsc::Sender<ReplicationRecord>,
+    /// Set when a record was dropped; the standby's sender follows up with a snapshot
+    resync: Arc<AtomicBool>,
+}
+
+impl Sink {
+    fn new() -> (Self, mpsc::Receiver<ReplicationRecord>) {
+        let (tx, rx) = mpsc::channel(REPLICATION_QUEUE);
+        (Self { tx, resync: Arc::new(AtomicBool::new(false)) }, rx)
+    }
+}
+
+impl Replicator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Spawn the sender task, which (re)connects to `standby_addr` as needed
+    /// Every connection opens with a snapshot of `state`'s lobbies
+    pub fn with_standby(mut self, standby_addr: String, state: &Arc<ServerState>) -> Self {
+        let (sink, rx) = Sink::new();
+        let primary = Primary {
+            addr: standby_addr,
+            epoch: new_epoch(),
+            state: Arc::downgrade(state),
+            resync: sink.resync.clone(),
+        };
+        tokio::spawn(run_replicator(primary, rx));
+        self.standby = Some(sink);
+        self
+    }
+
+    /// Append the stream to `path` as well, as a replay of every match for `--verify-replay`
+    pub fn with_recording(mut self, path: String) -> Self {
+        let (sink, rx) = Sink::new();
+        tokio::spawn(record_replay(path, rx));
+        self.recording = Some(sink);
+        self
+    }
+
+    /// Queue a record without blocking the caller
+    /// If the standby's queue is full the record is dropped and the standby gets a fresh snapshot instead
+    pub fn publish(&self, record: ReplicationRecord) {
+        if let Some(recording) = &self.recording {
+            if recording.tx.try_send(record.clone()).is_err() {
+                log::warn!("Replay recording queue full, dropping record (the recording won't verify past it)");
+            }
+        }
+        if let Some(standby) = &self.standby {
+            if standby.tx.try_send(record).is_err() {
+                log::warn!("Replication queue full, dropping record and resyncing the standby");
+                standby.resync.store(true, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+impl Replicator {
+    /// Records go to the returned receiver instead of a standby or file
+    pub(crate) fn capturing() -> (Self, mpsc::Receiver<ReplicationRecord>) {
+        let (sink, rx) = Sink::new();
+        (Self { standby: None, recording: Some(sink) }, rx)
+    }
+}
+
+/// Epochs are start times, so a restarted primary outranks the one it replaces
+fn new_epoch() -> u64 {
+    SystemTime::now().duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis() as u64)
+}
+
+/// What the sender task streams, and to where
+struct Primary {
+    addr: String,
+    epoch: u64,
+    state: Weak<ServerState>,
+    resync: Arc<AtomicBool>,
+}
+
+/// Why a connection to the standby ended
+enum StreamEnd {
+    /// The replicator was dropped
+    Closed,
+    /// The connection failed; reconnect and resync
+    Lost,
+    /// The standby has taken over (or mirrors a newer primary)
+    Fenced(u64),
+}
+
+async fn run_replicator(primary: Primary, mut rx: mpsc::Receiver<ReplicationRecord>) {
+    loop {
+        let ended = match TcpStream::connect(&primary.addr).await {
+            Ok(stream) => {
+                log::info!("Replicating to standby at {} (epoch {})", primary.addr, primary.epoch);
+                stream_to_standby(&primary, stream, &mut rx).await
+            }
+            Err(e) => {
+                log::warn!("Standby {} unreachable: {}", primary.addr, e);
+                if rx.is_closed() && rx.is_empty() { StreamEnd::Closed } else { StreamEnd::Lost }
+            }
+        };
+        match ended {
+            StreamEnd::Closed => return,
+            StreamEnd::Lost => tokio::time::sleep(RECONNECT_DELAY).await,
+            StreamEnd::Fenced(epoch) => {
+                // Serving on would leave both servers running the same lobbies
+                log::error!("Standby {} fenced this primary (epoch {}), draining", primary.addr, epoch);
+                if let Some(state) = primary.state.upgrade() {
+                    state.start_draining();
+                }
+                return;
+            }
+        }
+    }
+}
+
+/// Stream one connection: `Hello` and a snapshot, then queued records and heartbeats
+async fn stream_to_standby(primary: &Primary, stream: TcpStream, rx: &mut mpsc::Receiver<ReplicationRecord>) -> StreamEnd {
+    let (reader, mut writer) = stream.into_split();
+    let mut replies = BufReader::new(reader).lines();
+    // Whatever was lost while disconnected is in the snapshot
+    primary.resync.store(true, Ordering::Relaxed);
+    if send_record(&mut writer, &ReplicationRecord::Hello { epoch: primary.epoch }).await.is_err() {
+        return StreamEnd::Lost;
+    }
+
+    let mut heartbeat = tokio::time::interval(HEARTBEAT_INTERVAL);
+    heartbeat.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    loop {
+        if primary.resync.swap(false, Ordering::Relaxed) {
+            if let Some(state) = primary.state.upgrade() {
+                if let Err(e) = send_record(&mut writer, &snapshot(&state).await).await {
+                    log::warn!("Lost connection to standby {}: {}", primary.addr, e);
+                    return StreamEnd::Lost;
+                }
+            }
+        }
+        let record = tokio::select! {
+            received = rx.recv() => match received {
+                Some(record) => record,
+                None => return StreamEnd::Closed,
+            },
+            _ = heartbeat.tick() => ReplicationRecord::Heartbeat,
+            reply = replies.next_line() => match reply {
+                Ok(Some(line)) => match serde_json::from_str(&line) {
+                    Ok(ReplicationRecord::Fenced { epoch }) => return StreamEnd::Fenced(epoch),
+                    _ => continue,
+                },
+                Ok(None) | Err(_) => {
+                    log::warn!("Standby {} closed the replication stream", primary.addr);
+                    return StreamEnd::Lost;
+                }
+            },
+        };
+        if let Err(e) = send_record(&mut writer, &record).await {
+            log::warn!("Lost connection to standby {}: {}", primary.addr, e);
+            return StreamEnd::Lost;
+        }
+    }
+}
+
+/// Every lobby with the tick it's at, for a standby (re)joining the stream
+async fn snapshot(state: &ServerState) -> ReplicationRecord {
+    let handles: Vec<_> = state.iter_lobbies().map(|entry| entry.lobby.clone()).collect();
+    let mut lobbies = Vec::with_capacity(handles.len());
+    for handle in handles {
+        let lobby = handle.read().await;
+        lobbies.push(MirroredLobby { tick: lobby.current_tick, lobby: LobbyCheckpoint::capture(&lobby) });
+    }
+    ReplicationRecord::Snapshot { lobbies }
+}
+
+async fn send_record(writer: &mut (impl AsyncWrite + Unpin), record: &ReplicationRecord) -> std::io::Result<()> {
+    let mut line = serde_json::to_vec(record).map_err(std::io::Error::other)?;
+    line.push(b'\n');
+    writer.write_all(&line).await
+}
+
+async fn record_replay(path: String, mut rx: mpsc::Receiver<ReplicationRecord>) {
+    let mut file = match tokio::fs::OpenOptions::new().create(true).append(true).open(&path).await {
+        Ok(file) => file,
+        Err(e) => {
+            log::error!("Could not open replay file {}: {}", path, e);
+            return;
+        }
+    };
+    while let Some(record) = rx.recv().await {
+        let Ok(mut line) = serde_json::to_vec(&record) else {
+            continue;
+        };
+        line.push(b'\n');
+        if let Err(e) = file.write_all(&line).await {
+            log::warn!("Failed to write replay file {}: {}", path, e);
+        }
+    }
+}
+
+/// Standby side: shadow copies of the primary's lobbies, ticked as the primary ticked them
+pub struct StandbyMirror {
+    pub lobbies: HashMap<LobbyCode, Lobby>,
+    /// Last tick each lobby's snapshot included; tick records up to it are already applied
+    synced_ticks: HashMap<LobbyCode, u64>,
+    config: Arc<Config>,
+    /// Replayed ticks queue packets like live ones; nothing sends them
+    io: TickIo,
+    outbound: mpsc::Receiver<OutboundPacket>,
+}
+
+/// A lobby as the standby keeps it: restored from the primary's capture, running rather than paused
+fn mirrored(checkpoint: LobbyCheckpoint, weapons: &WeaponDb) -> Result<Lobby, &'static str> {
+    let mut lobby = checkpoint.restore(weapons, Duration::ZERO)?;
+    lobby.paused_at = None;
+    Ok(lobby)
+}
+
+impl StandbyMirror {
+    /// Ticks replay with `config`, which should match the primary's
+    pub fn new(config: Arc<Config>) -> Self {
+        let (outbox, outbound) = Outbox::new(config.max_send_failures);
+        let io = TickIo { outbox, bandwidth: Arc::default(), send_buffer: PacketBuffer::default() };
+        Self { lobbies: HashMap::new(), synced_ticks: HashMap::new(), config, io, outbound }
+    }
+
+    /// Apply one record from the primary
+    pub fn apply(&mut self, record: ReplicationRecord, weapons: &WeaponDb) {
+        match record {
+            ReplicationRecord::Snapshot { lobbies } => {
+                self.lobbies.clear();
+                self.synced_ticks.clear();
+                for MirroredLobby { tick, lobby } in lobbies {
+                    let code = lobby.code.clone();
+                    match mirrored(lobby, weapons) {
+                        Ok(lobby) => {
+                            self.lobbies.insert(code.clone(), lobby);
+                            self.synced_ticks.insert(code, tick);
+                        }
+                        Err(e) => log::warn!("Could not mirror lobby {}: {}", code, e),
+                    }
+                }
+            }
+            ReplicationRecord::LobbyCreated { lobby, at } => {
+                // Records queued before a snapshot arrive after it; the snapshot is newer
+                if self.lobbies.contains_key(&lobby.code) {
+                    return;
+                }
+                let code = lobby.code.clone();
+                match clock::frozen(at.unwrap_or_else(clock::now), || mirrored(lobby, weapons)) {
+                    Ok(lobby) => {
+                        self.lobbies.insert(code, lobby);
+                    }
+                    Err(e) => log::warn!("Could not mirror lobby {}: {}", code, e),
+                }
+            }
+            ReplicationRecord::PlayerAdded { code, player_id, name, team, vip, party_id, at } => {
+                if let Some(lobby) = self.lobbies.get_mut(&code) {
+                    let overlay = lobby.settings.weapons.clone();
+                    let weapons = WeaponView::new(weapons, &overlay);
+                    let added = clock::frozen(at.unwrap_or_else(clock::now), || {
+                        lobbies::add_player_as(lobby, player_id, name, WeaponDb::default_weapon_id(), &weapons, vip, team)
+                    });
+                    if added.is_ok() {
+                        if let Some(player) = lobby.players.get_mut(&player_id) {
+                            player.party_id = party_id;
+                        }
+                    }
+                }
+            }
+            ReplicationRecord::SettingsChanged { code, settings } => {
+                if let Some(lobby) = self.lobbies.get_mut(&code) {
+                    match settings.restore(weapons) {
+                        Ok(settings) => lobby.settings = settings,
+                        Err(e) => log::warn!("Could not mirror settings of {}: {}", code, e),
+                    }
+                }
+            }
+            ReplicationRecord::Tick { code, tick, at, commands, presence } => {
+                if self.synced_ticks.get(&code).is_some_and(|synced| tick <= *synced) {
+                    return;
+                }
+                let Some(lobby) = self.lobbies.get_mut(&code) else {
+                    return;
+                };
+                // The same tick the primary ran, at the time it ran, fed what it was fed
+                lobby.current_tick = tick;
+                lobby.tick_interval_ms = self.config.tick_interval_ms();
+                lobby.heartbeat_interval_ms = self.config.heartbeat_interval_ms;
+                for (player_id, report) in presence {
+                    lobby.presence.touch(player_id, report.addr, report.last_seen);
+                }
+                // A lobby the tick closes is removed by the primary's `LobbyRemoved` that follows
+                clock::frozen(at.unwrap_or_else(clock::now), || {
+                    lobby_tick::run_tick(lobby, weapons, &self.config, None, &mut self.io, commands)
+                });
+                while self.outbound.try_recv().is_ok() {}
+                lobby.pending_events.clear();
+            }
+            ReplicationRecord::LobbyRemoved { code } => {
+                self.lobbies.remove(&code);
+                self.synced_ticks.remove(&code);
+            }
+            ReplicationRecord::StateHash { .. }
+            | ReplicationRecord::Hello { .. }
+            | ReplicationRecord::Heartbeat
+            | ReplicationRecord::Fenced { .. } => {}
+        }
+    }
+}
+
+/// The primary connection the standby is mirroring
+struct PrimaryStream {
+    lines: Lines<BufReader<OwnedReadHalf>>,
+    /// Kept open: closing it would look like the standby going away
+    _writer: OwnedWriteHalf,
+    addr: SocketAddr,
+}
+
+/// Read a new connection's `Hello`, fencing it if its epoch is older than one already accepted
+async fn greet(stream: TcpStream, addr: SocketAddr, epoch: &mut Option<u64>) -> Option<PrimaryStream> {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+    let line = match tokio::time::timeout(HELLO_TIMEOUT, lines.next_line()).await {
+        Ok(Ok(Some(line))) => line,
+        _ => {
+            log::warn!("Replication connection from {} sent no hello", addr);
+            return None;
+        }
+    };
+    let Ok(ReplicationRecord::Hello { epoch: offered }) = serde_json::from_str(&line) else {
+        log::warn!("Replication connection from {} didn't start with a hello", addr);
+        return None;
+    };
+    if let Some(current) = *epoch {
+        if offered < current {
+            log::warn!("Fencing stale primary {} (epoch {} < {})", addr, offered, current);
+            let _ = send_record(&mut writer, &ReplicationRecord::Fenced { epoch: current }).await;
+            return None;
+        }
+    }
+    *epoch = Some(offered);
+    Some(PrimaryStream { lines, _writer: writer, addr })
+}
+
+/// Next record from the mirrored primary; None once its connection ends
+/// Waits forever while no primary is connected
+async fn next_record(primary: &mut Option<PrimaryStream>) -> Option<Result<ReplicationRecord, serde_json::Error>> {
+    let Some(connection) = primary.as_mut() else {
+        return std::future::pending().await;
+    };
+    match connection.lines.next_line().await {
+        Ok(Some(line)) => Some(serde_json::from_str(&line)),
+        Ok(None) => None,
+        Err(e) => {
+            log::warn!("Replication stream from {} failed: {}", connection.addr, e);
+            None
+        }
+    }
+}
+
+/// Mirror the primary, and adopt its lobbies once it has been silent for `replication_failover_secs`
+///
+/// The stream may drop and reconnect (each connection sends a snapshot), so only silence
+/// past the timeout counts as the primary being dead. After taking over, the standby
+/// fences every primary that connects, so the old one stops serving its lobbies.
+pub async fn run_standby(
+    listener: TcpListener,
+    state: Arc<ServerState>,
+    weapons: Arc<WeaponDb>,
+    config: Arc<Config>,
+    socket: Arc<UdpSocket>,
+) {
+    let failover = Duration::from_secs(config.replication_failover_secs);
+    let mut mirror = StandbyMirror::new(config.clone());
+    let mut epoch: Option<u64> = None;
+    let mut primary: Option<PrimaryStream> = None;
+    let mut last_heard = tokio::time::Instant::now();
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok((stream, addr)) => {
+                    if let Some(connection) = greet(stream, addr, &mut epoch).await {
+                        log::info!("Standby mirroring primary {} (epoch {:?})", addr, epoch);
+                        // A reconnecting primary replaces its old connection
+                        primary = Some(connection);
+                        last_heard = tokio::time::Instant::now();
+                    }
+                }
+                Err(e) => log::warn!("Standby failed to accept primary: {}", e),
+            },
+            record = next_record(&mut primary) => match record {
+                Some(Ok(record)) => {
+                    last_heard = tokio::time::Instant::now();
+                    mirror.apply(record, &weapons);
+                }
+                Some(Err(e)) => log::warn!("Ignoring bad replication record: {}", e),
+                None => {
+                    log::warn!("Replication stream ended, waiting up to {:?} for the primary to reconnect", failover);
+                    primary = None;
+                }
+            },
+            // Before any primary has connected there's nothing to take over
+            _ = tokio::time::sleep_until(last_heard + failover), if epoch.is_some() => break,
+        }
+    }
+
+    log::warn!("Primary silent for {:?}, adopting {} lobbies", failover, mirror.lobbies.len());
+    tokio::spawn(fence_primaries(listener, epoch.map_or(0, |epoch| epoch.saturating_add(1))));
+    adopt_lobbies(mirror, state, weapons, config, socket).await;
+}
+
+/// After taking over, tell every primary that still connects to stand down
+async fn fence_primaries(listener: TcpListener, epoch: u64) {
+    loop {
+        match listener.accept().await {
+            Ok((mut stream, addr)) => {
+                log::warn!("Fencing primary {} after failover", addr);
+                let _ = send_record(&mut stream, &ReplicationRecord::Fenced { epoch }).await;
+            }
+            Err(e) => log::warn!("Standby failed to accept primary: {}", e),
+        }
+    }
+}
+
+/// Start tick loops for mirrored lobbies and point their clients at this server
+async fn adopt_lobbies(
+    mirror: StandbyMirror,
+    state: Arc<ServerState>,
+    weapons: Arc<WeaponDb>,
+    config: Arc<Config>,
+    socket: Arc<UdpSocket>,
+) {
+    for (code, mut lobby) in mirror.lobbies {
+        // Inactivity timers restart from the moment of adoption
+        let now = SystemTime::now();
+        for player in lobby.players.values_mut() {
+            player.last_update = now;
+        }
+        let player_ids: Vec<u32> = lobby.players.keys().copied().collect();
+        let addresses: Vec<SocketAddr> = lobby.client_addresses.values().copied().collect();
+        if state.lobby_exists(&code) {
+            log::warn!("Could not adopt lobby {}: already exists", code);
+            continue;
+        }
+
+        // Clients hear about the failover before the adopted lobby's first tick
+        let advertised = state.identity().map(|identity| &identity.advertised);
+        let packet = json!({
+            "type": "server_failover",
+            "lobby_code": code,
+            "server_ip": advertised.and_then(|addr| addr.ip.as_deref()),
+            "udp_port": advertised.map_or(config.udp_port, |addr| addr.udp_port),
+        });
+        let bytes = packet.to_string().into_bytes();
+        for addr in addresses {
+            if let Err(e) = socket.send_to(&bytes, addr).await {
+                log::debug!("Failover notice to {} failed: {}", addr, e);
+            }
+        }
+
+        if let Err(e) = crate::server::spawn_lobby(state.clone(), lobby, weapons.clone(), config.clone(), socket.clone()).await {
+            log::warn!("Could not adopt lobby {}: {}", code, e);
+            continue;
+        }
+        for player_id in player_ids {
+            state.register_player_lobby(player_id, &code);
+        }
+        log::info!("Adopted lobby {}", code);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_round_trip() {
+        let at = SystemTime::UNIX_EPOCH + Duration::from_nanos(1_700_000_000_123_456_789);
+        let keepalive = Presence { addr: "127.0.0.1:9000".parse().unwrap(), last_seen: at - Duration::from_millis(3) };
+        let record = ReplicationRecord::Tick {
+            code: "TEST".to_string(),
+            tick: 7,
+            at: Some(at),
+            commands: vec![
+                LobbyCommand::PlayerJoin { player_id: 1, name: "P1".to_string(), addr: "127.0.0.1:9000".parse().unwrap() },
+                LobbyCommand::Ready { player_id: 1, ready: true },
+            ],
+            presence: vec![(1, keepalive)],
+        };
+        let line = serde_json::to_string(&record).unwrap();
+        let ReplicationRecord::Tick { tick, at: decoded_at, commands, presence, .. } = serde_json::from_str(&line).unwrap() else {
+            panic!("Expected tick record");
+        };
+        assert_eq!(tick, 7);
+        // Times survive to the nanosecond, so replayed timers fire on the same tick
+        assert_eq!(decoded_at, Some(at));
+        assert_eq!(presence, vec![(1, keepalive)]);
+        assert!(matches!(commands[1], LobbyCommand::Ready { player_id: 1, ready: true }));
+
+        // Streams recorded before ticks carried their clock still load
+        let old = r#"{"record":"tick","code":"TEST","tick":7,"commands":[]}"#;
+        assert!(matches!(serde_json::from_str(old).unwrap(), ReplicationRecord::Tick { at: None, .. }));
+    }
+
+    #[tokio::test]
+    async fn test_recording_appends_every_record() {
+        let path = std::env::temp_dir().join(format!("gungame-replay-{}.jsonl", uuid::Uuid::new_v4()));
+        let replicator = Replicator::new().with_recording(path.to_string_lossy().into_owned());
+        replicator.publish(created("FILE", 4));
+        replicator.publish(ReplicationRecord::LobbyRemoved { code: "FILE".to_string() });
+        drop(replicator);
+
+        let mut lines = Vec::new();
+        for _ in 0..50 {
+            let text = tokio::fs::read_to_string(&path).await.unwrap_or_default();
+            lines = text.lines().map(str::to_string).collect();
+            if lines.len() == 2 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(lines.len(), 2);
+        assert!(matches!(serde_json::from_str(&lines[0]).unwrap(), ReplicationRecord::LobbyCreated { .. }));
+    }
+
+    fn created(code: &str, max_players: u32) -> ReplicationRecord {
+        ReplicationRecord::LobbyCreated { lobby: LobbyCheckpoint::capture(&Lobby::new(code.to_string(), max_players, "world".to_string())), at: None }
+    }
+
+    fn failover_config(secs: u64) -> Arc<Config> {
+        Arc::new(Config { replication_failover_secs: secs, ..Default::default() })
+    }
+
+    async fn read_record(lines: &mut Lines<BufReader<OwnedReadHalf>>) -> ReplicationRecord {
+        let line = tokio::time::timeout(Duration::from_secs(5), lines.next_line()).await.unwrap().unwrap().unwrap();
+        serde_json::from_str(&line).unwrap()
+    }
+
+    #[test]
+    fn test_mirror_keeps_settings_and_player_metadata() {
+        let weapons = WeaponDb::load();
+        let mut mirror = StandbyMirror::new(Arc::new(Config::default()));
+        let settings = crate::state::lobby::LobbySettings { reserved_slots: 1, team_count: 2, ..Default::default() };
+        let mut lobby = Lobby::with_settings("META".to_string(), 2, "world".to_string(), settings);
+        lobbies::add_player(&mut lobby, 1, "P1".to_string(), 1, &weapons).unwrap();
+        lobby.current_tick = 10;
+        mirror.apply(ReplicationRecord::Snapshot { lobbies: vec![MirroredLobby { tick: 10, lobby: LobbyCheckpoint::capture(&lobby) }] }, &weapons);
+
+        // Only a VIP fits in the reserved slot
+        mirror.apply(ReplicationRecord::PlayerAdded {
+            code: "META".to_string(),
+            player_id: 2,
+            name: "P2".to_string(),
+            team: Some(1),
+            vip: true,
+            party_id: Some("squad".to_string()),
+            at: None,
+        }, &weapons);
+        let mirrored = &mirror.lobbies["META"];
+        assert_eq!(mirrored.settings.reserved_slots, 1);
+        assert_eq!(mirrored.players[&2].party_id.as_deref(), Some("squad"));
+        assert_eq!(mirrored.teams.team_of(2), Some(1));
+        assert!(!mirrored.is_paused());
+
+        let mut motd = SettingsCheckpoint::capture(&mirrored.settings);
+        motd.motd = "Be nice".to_string();
+        mirror.apply(ReplicationRecord::SettingsChanged { code: "META".to_string(), settings: motd }, &weapons);
+        assert_eq!(mirror.lobbies["META"].settings.motd, "Be nice");
+
+        // Ticks the snapshot already has aren't applied twice
+        let ready = |tick| ReplicationRecord::Tick {
+            code: "META".to_string(),
+            tick,
+            at: None,
+            commands: vec![LobbyCommand::Ready { player_id: 1, ready: true }],
+            presence: Vec::new(),
+        };
+        mirror.apply(ready(10), &weapons);
+        assert!(!mirror.lobbies["META"].players[&1].ready);
+        mirror.apply(ready(11), &weapons);
+        assert!(mirror.lobbies["META"].players[&1].ready);
+        // Nor does a late creation record replace the snapshot
+        mirror.apply(created("META", 2), &weapons);
+        assert_eq!(mirror.lobbies["META"].players.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_standby_waits_for_reconnect_and_fences_stale_primaries() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let standby_addr = listener.local_addr().unwrap();
+        let state = Arc::new(ServerState::new());
+        let standby = tokio::spawn(run_standby(
+            listener,
+            state.clone(),
+            Arc::new(WeaponDb::load()),
+            failover_config(1),
+            Arc::new(UdpSocket::bind("127.0.0.1:0").await.unwrap()),
+        ));
+        let connect = |records: Vec<ReplicationRecord>| async move {
+            let stream = TcpStream::connect(standby_addr).await.unwrap();
+            let (reader, mut writer) = stream.into_split();
+            for record in &records {
+                send_record(&mut writer, record).await.unwrap();
+            }
+            (BufReader::new(reader).lines(), writer)
+        };
+
+        // The stream drops, and the primary is back within the timeout
+        let first = connect(vec![ReplicationRecord::Hello { epoch: 5 }, created("BLIP", 4)]).await;
+        drop(first);
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        let (_replies, mut writer) = connect(vec![ReplicationRecord::Hello { epoch: 5 }]).await;
+        for _ in 0..6 {
+            tokio::time::sleep(Duration::from_millis(300)).await;
+            send_record(&mut writer, &ReplicationRecord::Heartbeat).await.unwrap();
+        }
+        assert!(!standby.is_finished());
+        assert!(state.get_lobby("BLIP").is_none());
+
+        // An older primary is turned away
+        let (mut stale, _stale_writer) = connect(vec![ReplicationRecord::Hello { epoch: 4 }]).await;
+        assert!(matches!(read_record(&mut stale).await, ReplicationRecord::Fenced { epoch: 5 }));
+
+        // Silence past the timeout is a dead primary
+        drop(writer);
+        tokio::time::timeout(Duration::from_secs(5), standby).await.unwrap().unwrap();
+        assert!(state.get_lobby("BLIP").is_some());
+        let (mut late, _late_writer) = connect(vec![ReplicationRecord::Hello { epoch: 9 }]).await;
+        assert!(matches!(read_record(&mut late).await, ReplicationRecord::Fenced { epoch: 6 }));
+    }
+
+    #[tokio::test]
+    async fn test_primary_resyncs_on_reconnect_and_drains_when_fenced() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let state = Arc::new(ServerState::new());
+        let socket = Arc::new(UdpSocket::bind("127.0.0.1:0").await.unwrap());
+        crate::server::create_lobby_with_tick(state.clone(), "SNAP".to_string(), 4, "world".to_string(), Arc::new(WeaponDb::load()), Arc::new(Config::default()), socket).await.unwrap();
+        let _replicator = Replicator::new().with_standby(listener.local_addr().unwrap().to_string(), &state);
+
+        let mut epochs = Vec::new();
+        for _ in 0..2 {
+            let (stream, _) = tokio::time::timeout(Duration::from_secs(5), listener.accept()).await.unwrap().unwrap();
+            let (reader, writer) = stream.into_split();
+            let mut lines = BufReader::new(reader).lines();
+            let ReplicationRecord::Hello { epoch } = read_record(&mut lines).await else {
+                panic!("Expected hello");
+            };
+            epochs.push(epoch);
+            let ReplicationRecord::Snapshot { lobbies } = read_record(&mut lines).await else {
+                panic!("Expected snapshot");
+            };
+            assert_eq!(lobbies.len(), 1);
+            assert_eq!(lobbies[0].lobby.code, "SNAP");
+
+            if epochs.len() == 2 {
+                let mut writer = writer;
+                send_record(&mut writer, &ReplicationRecord::Fenced { epoch: epoch + 1 }).await.unwrap();
+                let _keep = (lines, writer);
+                for _ in 0..50 {
+                    if state.is_draining() {
+                        break;
+                    }
+                    tokio::time::sleep(Duration::from_millis(20)).await;
+                }
+                break;
+            }
+            // Standby goes away; the primary reconnects with a fresh snapshot
+        }
+        assert_eq!(epochs[0], epochs[1]);
+        assert!(state.is_draining());
+    }
+
+    #[tokio::test]
+    async fn test_standby_adopts_lobby_when_primary_stops() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let standby_addr = listener.local_addr().unwrap().to_string();
+        let state = Arc::new(ServerState::new());
+        let socket = Arc::new(UdpSocket::bind("127.0.0.1:0").await.unwrap());
+        let client = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let standby = tokio::spawn(run_standby(
+            listener,
+            state.clone(),
+            Arc::new(WeaponDb::load()),
+            failover_config(1),
+            socket,
+        ));
+
+        let replicator = Replicator::new().with_standby(standby_addr, &Arc::new(ServerState::new()));
+        replicator.publish(created("TEST", 4));
+        replicator.publish(ReplicationRecord::PlayerAdded { code: "TEST".to_string(), player_id: 2, name: "P2".to_string(), team: None, vip: false, party_id: None, at: None });
+        replicator.publish(ReplicationRecord::Tick {
+            code: "TEST".to_string(),
+            tick: 1,
+            at: None,
+            commands: vec![LobbyCommand::PlayerJoin { player_id: 1, name: "P1".to_string(), addr: client.local_addr().unwrap() }],
+            presence: Vec::new(),
+        });
+        // Primary goes away
+        drop(replicator);
+
+        tokio::time::timeout(Duration::from_secs(5), standby).await.unwrap().unwrap();
+        let lobby = state.get_lobby("TEST").expect("lobby adopted");
+        assert!(lobby.read().await.players.contains_key(&1));
+        assert!(lobby.read().await.players.contains_key(&2));
+        assert_eq!(state.find_lobby_by_player(1).await.as_deref(), Some("TEST"));
+
+        let mut buf = [0u8; 1024];
+        let (len, _) = tokio::time::timeout(Duration::from_secs(2), client.recv_from(&mut buf)).await.unwrap().unwrap();
+        let packet: serde_json::Value = serde_json::from_slice(&buf[..len]).unwrap();
+        assert_eq!(packet["type"], "server_failover");
+        assert_eq!(packet["lobby_code"], "TEST");
+    }
+}
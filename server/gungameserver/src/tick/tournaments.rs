@@ -0,0 +1,49 @@
+use crate::state::lobby::{GameMode, Lobby, LobbySettings};
+use crate::state::server_state::ServerState;
+use crate::state::tournaments::Tournament;
+use crate::utils::config::Config;
+use crate::utils::weapondb::WeaponDb;
+use std::sync::Arc;
+use tokio::net::UdpSocket;
+
+/// Drive a tournament: open a duel lobby for every match whose entrants are known,
+/// wait for results to advance the winners, and stop once the final is decided
+pub fn spawn_runner(
+    state: Arc<ServerState>,
+    tournament: Arc<Tournament>,
+    weapons: Arc<WeaponDb>,
+    config: Arc<Config>,
+    socket: Arc<UdpSocket>,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        loop {
+            let (ready, champion) = match tournament.bracket.lock() {
+                Ok(bracket) => (bracket.ready_matches(), bracket.champion().map(str::to_string)),
+                Err(_) => return,
+            };
+            if let Some(champion) = champion {
+                log::info!("Tournament {} ({}) won by {}", tournament.id, tournament.name, champion);
+                return;
+            }
+            for (round, index) in ready {
+                let code = tournament.lobby_code(round, index);
+                let settings = LobbySettings {
+                    region: config.region.clone(),
+                    private: true, // Entrants are sent the code; nobody else should wander in
+                    game_mode: GameMode::Duel,
+                    duel_rounds: tournament.settings.duel_rounds,
+                    ..Default::default()
+                };
+                let lobby = Lobby::with_settings(code.clone(), 2, tournament.settings.scene.clone(), settings);
+                match crate::server::spawn_lobby(state.clone(), lobby, weapons.clone(), config.clone(), socket.clone()).await {
+                    Ok(()) => {
+                        state.tournaments.assign_lobby(&tournament, round, index, code.clone());
+                        log::info!("Tournament {} round {} match {} opened in lobby {}", tournament.id, round + 1, index + 1, code);
+                    }
+                    Err(e) => log::warn!("Could not open tournament lobby {}: {}", code, e),
+                }
+            }
+            tournament.changed.notified().await;
+        }
+    })
+}
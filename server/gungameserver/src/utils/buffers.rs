@@ -1,4 +1,10 @@
 use smallvec::SmallVec;
+use crate::state::lobby::MatchStanding;
+use crate::domain::awards::MatchAward;
+use crate::domain::votes::VoteKind;
+use crate::domain::history::PositionRecord;
+use crate::domain::clock_sync::TimeSyncReply;
+use crate::domain::connectivity::ConnectionState;
 
 /// Type alias for small collections that avoid allocations
 pub type SmallPlayerVec = SmallVec<[u32; 8]>;
@@ -10,18 +16,26 @@ pub enum SyncEvent {
     HealthChanged {
         player_id: u32,
         health: u32,
+        max_health: u32,
     },
     AmmoChanged {
         player_id: u32,
         ammo: u32,
+        heat: f32, // 0.0 to 1.0, for HUD heat bars
+        overheated: bool,
     },
     MaxAmmoChanged {
         player_id: u32,
         max_ammo: u32,
     },
+    ArmorChanged {
+        player_id: u32,
+        armor: u32,
+    },
     WeaponChanged {
         player_id: u32,
         weapon_id: u32,
+        equip_time: f32, // Seconds until the weapon can fire
     },
     ReloadStateChanged {
         player_id: u32,
@@ -41,9 +55,95 @@ pub enum SyncEvent {
         weapon_name: String,
         killer_killstreak: u32,
     },
+    HitConfirmed {
+        attacker_id: u32,
+        target_id: u32,
+        damage: u32,
+        multiplier: f32, // Ramp-up multiplier applied (1.0 without ramp-up)
+        pellets: Vec<u32>, // Damage of each pellet that hit, for multi-pellet weapons
+        armor_absorbed: u32, // Damage the target's armor soaked up, on top of `damage`
+        target_armor: u32,   // Armor the target has left
+    },
+    HitMarkers {
+        attacker_id: u32,
+        hits: Vec<HitMarker>, // One per target hit this tick, in the order first hit
+    },
+    TeamChanged {
+        player_id: u32,
+        team: u8,
+    },
+    WeaponBanned {
+        weapon_id: u32,
+        banned: bool, // false when the ban is lifted
+    },
+    ConnectionDegraded {
+        to_id: u32, // A teammate or spectator
+        player_id: u32,
+        state: ConnectionState,
+    },
+    PlayerDamaged {
+        player_id: u32,
+        attacker_id: u32,
+        damage: u32,
+        health: u32, // Server health after the hit; the client shows this rather than subtracting
+    },
+    KillcamData {
+        victim_id: u32,
+        killer_id: u32,
+        weapon_id: u32,
+        samples: Vec<PositionRecord>, // Killer's recent positions, oldest first
+    },
+    LootSpawned {
+        item_id: u32,
+        kind: crate::domain::loot::LootKind,
+        position: (f32, f32, f32),
+        expires_in: f32,
+    },
+    LootRemoved {
+        item_id: u32,
+        picked_up_by: Option<u32>, // None when it expired
+    },
+    /// A scene pickup was taken and is on cooldown for `respawn_in` seconds
+    PickupTaken {
+        pickup_id: u32,
+        player_id: u32,
+        respawn_in: f32,
+    },
+    PickupRespawned {
+        pickup_id: u32,
+    },
+    ProjectileSpawned {
+        projectile_id: u32,
+        owner_id: u32,
+        weapon_id: u32,
+        position: (f32, f32, f32),
+        velocity: (f32, f32, f32),
+    },
+    ProjectileEnded {
+        projectile_id: u32,
+        target_id: Option<u32>, // Player hit, None if it ran out of range
+    },
+    TimeSync(TimeSyncReply),
+    ZoneState {
+        holder: Option<u32>,
+        contested: bool,
+        occupants: Vec<u32>,
+        points: Vec<(u32, u32)>, // (player_id, points), highest first
+        score_limit: u32,
+    },
+    LoadoutApplied {
+        player_id: u32,
+        name: String,
+        weapon_id: u32,
+        attachments: Vec<String>,
+    },
     PlayerRespawned {
         player_id: u32,
     },
+    PlayerDied {
+        player_id: u32,
+        cause: &'static str,
+    },
     ScoreChanged {
         player_id: u32,
         score: u32,
@@ -55,10 +155,200 @@ pub enum SyncEvent {
         player_id: u32,
         reason: String,
     },
+    /// Server message to everyone in the lobby
+    Announcement {
+        message: String,
+    },
     InactivityWarning {
         player_id: u32,
         seconds_remaining: u64,
     },
+    /// Sent to a player about to be moved to spectator (or kicked, once spectating) for being AFK
+    AfkWarning {
+        player_id: u32,
+        seconds_remaining: u64,
+        kick: bool,
+    },
+    SpectatorChanged {
+        player_id: u32,
+        spectating: bool,
+    },
+    /// Sent to a player moved here by a merge or split; the client reconnects to this lobby
+    LobbyTransfer {
+        player_id: u32,
+        from_code: String,
+    },
+    /// A match or lobby ran past its time cap; sent ahead of the `MatchEnded` it forces
+    TimeCapReached {
+        scope: &'static str,
+        cap_secs: u64,
+        closing: bool, // The lobby is being closed, not just the match
+    },
+    MatchEnded {
+        ranked: bool,
+        standings: Vec<MatchStanding>,
+        awards: Vec<MatchAward>,
+    },
+    GamePaused {
+        paused_by: Option<u32>,
+    },
+    GameResumed {
+        resumed_by: Option<u32>,
+        paused_secs: f32,
+    },
+    ReadyChanged {
+        player_id: u32,
+        ready: bool,
+    },
+    CountdownTick {
+        seconds_left: u32,
+    },
+    CountdownCancelled,
+    MatchStarted,
+    VoteStarted {
+        initiator: u32,
+        kind: VoteKind,
+        seconds: u64,
+    },
+    VoteProgress {
+        yes: usize,
+        no: usize,
+        needed: usize,
+    },
+    VoteEnded {
+        kind: VoteKind,
+        passed: bool,
+    },
+    SceneChanged {
+        scene: String,
+    },
+    SpawnProtectionStarted {
+        player_id: u32,
+        seconds: f32,
+    },
+    SpawnProtectionEnded {
+        player_id: u32,
+    },
+    MotdChanged {
+        motd: String,
+        changed_by: Option<u32>,
+    },
+    ChatMessage {
+        from_id: u32,
+        from_name: String,
+        text: String,
+    },
+    Whisper {
+        from_id: u32,
+        from_name: String,
+        to_id: u32,
+        text: String,
+    },
+    GunfireNearby {
+        to_id: u32,
+        position: (f32, f32, f32), // Where the shooter stood
+        loudness: f32,
+    },
+    /// Every shot fired, hit or miss, for muzzle flashes
+    PlayerShot {
+        player_id: u32,
+        weapon_id: u32,
+    },
+    EmotePlayed {
+        to_id: u32,
+        player_id: u32,
+        emote: &'static str,
+        duration: f32,
+    },
+    /// Streaming hints: the recipient moved into or out of a scene chunk
+    ChunkEnter {
+        to_id: u32,
+        chunk: (i32, i32),
+        size: f32,
+    },
+    ChunkExit {
+        to_id: u32,
+        chunk: (i32, i32),
+    },
+    ChannelMessage {
+        to_id: u32,
+        channel: String,
+        from_id: u32,
+        from_name: String,
+        text: String,
+    },
+    ChannelSubscription {
+        player_id: u32,
+        channel: String,
+        subscribed: bool,
+    },
+    WhisperFailed {
+        player_id: u32,
+        to_id: u32,
+        reason: &'static str,
+    },
+    RoundStarted {
+        round: u32,
+        /// Best-of round count
+        rounds: u32,
+        time_limit_secs: u32,
+    },
+    RoundEnded {
+        round: u32,
+        /// None on a draw
+        winner: Option<u32>,
+        reason: &'static str,
+        /// Round wins by player, most first
+        wins: Vec<(u32, u32)>,
+    },
+    /// A player's command was rejected; `command` is the command's name
+    ActionFailed {
+        player_id: u32,
+        command: &'static str,
+        reason: &'static str,
+    },
+}
+
+/// Everything one attacker did to one target within a tick
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HitMarker {
+    pub target_id: u32,
+    pub damage: u32,
+    pub hits: u32,
+    pub killed: bool,
+}
+
+impl SyncEvent {
+    /// Player this event is addressed to (None = whole lobby)
+    pub fn recipient(&self) -> Option<u32> {
+        match self {
+            SyncEvent::Whisper { to_id, .. } => Some(*to_id),
+            SyncEvent::WhisperFailed { player_id, .. } => Some(*player_id),
+            SyncEvent::GunfireNearby { to_id, .. } => Some(*to_id),
+            SyncEvent::EmotePlayed { to_id, .. } => Some(*to_id),
+            SyncEvent::ChunkEnter { to_id, .. } | SyncEvent::ChunkExit { to_id, .. } => Some(*to_id),
+            SyncEvent::ChannelMessage { to_id, .. } => Some(*to_id),
+            SyncEvent::ChannelSubscription { player_id, .. } => Some(*player_id),
+            SyncEvent::ActionFailed { player_id, .. } => Some(*player_id),
+            SyncEvent::KillcamData { victim_id, .. } => Some(*victim_id),
+            SyncEvent::HitConfirmed { attacker_id, .. } => Some(*attacker_id),
+            SyncEvent::PlayerDamaged { player_id, .. } => Some(*player_id),
+            SyncEvent::HitMarkers { attacker_id, .. } => Some(*attacker_id),
+            SyncEvent::TimeSync(reply) => Some(reply.player_id),
+            SyncEvent::AfkWarning { player_id, .. } => Some(*player_id),
+            SyncEvent::LobbyTransfer { player_id, .. } => Some(*player_id),
+            SyncEvent::ConnectionDegraded { to_id, .. } => Some(*to_id),
+            _ => None,
+        }
+    }
+
+    /// Cosmetic hints that a bandwidth-capped client can do without
+    pub fn is_non_critical(&self) -> bool {
+        matches!(
+            self,
+            SyncEvent::GunfireNearby { .. } | SyncEvent::EmotePlayed { .. } | SyncEvent::HitMarkers { .. } | SyncEvent::PlayerShot { .. }
+        )
+    }
 }
 
 /// Pre-allocated buffer for packet serialization
@@ -104,6 +394,15 @@ mod tests {
         assert_eq!(vec.len(), 2);
     }
 
+    #[test]
+    fn test_event_recipient() {
+        let whisper = SyncEvent::Whisper { from_id: 1, from_name: "A".to_string(), to_id: 2, text: "hi".to_string() };
+        let failed = SyncEvent::WhisperFailed { player_id: 1, to_id: 2, reason: "Rate limited" };
+        assert_eq!(whisper.recipient(), Some(2));
+        assert_eq!(failed.recipient(), Some(1));
+        assert_eq!(SyncEvent::PlayerRespawned { player_id: 1 }.recipient(), None);
+    }
+
     #[test]
     fn test_packet_buffer() {
         let mut buf = PacketBuffer::new(512);
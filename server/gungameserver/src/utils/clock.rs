@@ -0,0 +1,43 @@
+use std::cell::Cell;
+use std::time::SystemTime;
+
+thread_local! {
+    static FROZEN: Cell<Option<SystemTime>> = const { Cell::new(None) };
+}
+
+/// Wall-clock time, or the time of the tick being run
+/// Gameplay code reads this rather than `SystemTime::now()` so a recorded tick replays at the time it ran
+pub fn now() -> SystemTime {
+    FROZEN.with(Cell::get).unwrap_or_else(SystemTime::now)
+}
+
+/// Run `f` with `now()` fixed at `at` (a tick, or a join being recorded)
+pub fn frozen<R>(at: SystemTime, f: impl FnOnce() -> R) -> R {
+    /// Puts the outer time back even if `f` panics
+    struct Restore(Option<SystemTime>);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            FROZEN.with(|frozen| frozen.set(self.0));
+        }
+    }
+    let _restore = Restore(FROZEN.with(|frozen| frozen.replace(Some(at))));
+    f()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_frozen_time_nests_and_restores() {
+        let tick = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000);
+        let join = tick + Duration::from_millis(5);
+        frozen(tick, || {
+            assert_eq!(now(), tick);
+            frozen(join, || assert_eq!(now(), join));
+            assert_eq!(now(), tick);
+        });
+        assert!(now() > join);
+    }
+}
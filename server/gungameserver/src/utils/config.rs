@@ -1,3 +1,11 @@
+use crate::domain::loot::DropTable;
+use crate::handlers::auth::{ApiKey, Role};
+use crate::tick::net_sim::NetSimConfig;
+use std::collections::HashMap;
+
+/// Region reported for lobbies when none is configured
+pub const DEFAULT_REGION: &str = "local";
+
 /// Server configuration - immutable after load
 #[derive(Debug, Clone)]
 pub struct Config {
@@ -5,7 +13,49 @@ pub struct Config {
     pub udp_port: u16,
     pub tick_rate_hz: u32,
     pub player_inactivity_timeout_secs: u64,
+    pub heartbeat_interval_ms: u64, // Keepalive interval asked of clients (keeps NAT mappings open); missed ones mark them degraded, then lost
     pub max_lobbies: usize,
+    pub max_pause_secs: u64, // Paused lobbies resume automatically after this
+    pub max_match_secs: Option<u64>, // Live matches are force-ended after this long; lobbies may set their own (no cap when unset)
+    pub max_lobby_lifetime_secs: Option<u64>, // Lobbies are closed this long after creation, e.g. ones kept alive by crashed clients' heartbeats
+    pub region: String,      // Default matchmaking region for new lobbies
+    pub geoip_db: Option<String>, // Local MaxMind DB (.mmdb) joining addresses are located with (no region inference when unset)
+    pub geoip_regions: HashMap<String, String>, // Country or continent code -> region; unmapped continents use their lowercased code
+    pub index_sweep_interval_secs: u64, // How often the player index is checked against lobbies
+    pub max_send_failures: u32, // Consecutive failed sends before a client is disconnected
+    pub ready_quorum: f32,   // Fraction of players that must be ready to start the countdown
+    pub countdown_secs: u32, // Countdown between the ready check passing and the match starting
+    pub replication_standby: Option<String>, // Standby address to stream lobby state to (experimental)
+    pub replay_record_path: Option<String>,  // File every lobby's ticks are appended to, checked with `--verify-replay` (experimental)
+    pub replication_listen: Option<String>,  // Address to accept a primary's stream on when running as standby
+    pub replication_failover_secs: u64, // How long a standby waits without hearing from the primary before taking over
+    pub max_player_bytes_per_sec: Option<u64>, // Outbound budget per player; non-critical updates are shed beyond it
+    pub stats_backend: Option<String>, // Global stats store: `file:<path>`, `redis://[:password@]host:port[/db]` or `rediss://` (TLS)
+    pub stats_flush_interval_secs: u64, // How often changed stats are written to the store
+    pub admin_token: Option<String>, // Bearer token for sensitive admin endpoints (disabled when unset)
+    pub api_keys: Vec<ApiKey>,       // Bearer keys and the role each grants; admin_token counts as an admin key
+    pub anonymous_role: Role,        // Role of HTTP requests without a key (player keeps the server open to clients)
+    pub checkpoint_path: Option<String>, // File lobby checkpoints are written to (off when unset)
+    pub loadouts_path: Option<String>, // File saved loadouts are kept in across restarts (memory only when unset)
+    pub checkpoint_interval_secs: u64, // How often lobbies are checkpointed
+    pub recovery_grace_secs: u64, // How long players of a recovered lobby have to reconnect
+    pub admin_console_socket: Option<String>, // Unix socket path for the admin console (local access only, no token)
+    pub admin_console_addr: Option<String>,   // TCP address for the admin console; sessions must `auth` with admin_token
+    pub afk_spectate_secs: Option<u64>, // Seconds without gameplay input before a player is moved to spectator (off when unset)
+    pub afk_kick_secs: u64,             // Seconds without gameplay input before AFK players are kicked from ranked lobbies
+    pub afk_warning_secs: u64,          // How long before each AFK step the player is warned
+    pub loot_drops: Option<DropTable>, // What players may drop when they die (no loot when unset)
+    pub loot_lifetime_secs: u64,       // How long dropped items stay before disappearing
+    pub net_sim: Option<NetSimConfig>, // Dev only: artificially delay, drop and duplicate UDP packets both ways (off when unset)
+    pub public_ip: Option<String>,        // IP advertised to clients (GUNGAME_PUBLIC_IP overrides; detected or per-request host when unset)
+    pub public_udp_port: Option<u16>,     // UDP port advertised to clients when it differs from udp_port (e.g. behind port forwarding)
+    pub public_addr_echo: Option<String>, // host:port of another server's UDP port that reports our public address
+    pub identity_path: Option<String>,    // Where the server id is kept across restarts (new id each start when unset)
+    pub udp_send_buffer_bytes: Option<usize>, // SO_SNDBUF for the game socket (OS default when unset)
+    pub udp_recv_buffer_bytes: Option<usize>, // SO_RCVBUF; raise it if /metrics shows kernel drops under load
+    pub hibernate_after_secs: Option<u64>, // Seconds empty before a lobby drops to 1Hz idle ticks (always full rate when unset)
+    pub otlp_endpoint: Option<String>, // OTLP/HTTP collector spans are exported to, e.g. http://localhost:4318 or https:// (no tracing when unset)
+    pub trace_sample_ratio: f64,       // Fraction of new traces recorded; traces continued from a caller follow its choice
 }
 
 impl Default for Config {
@@ -15,7 +65,49 @@ impl Default for Config {
             udp_port: 8081,
             tick_rate_hz: 50, // 20ms per tick
             player_inactivity_timeout_secs: 15,
+            heartbeat_interval_ms: 2000,
             max_lobbies: 1000,
+            max_pause_secs: 300,
+            max_match_secs: Some(7200),
+            max_lobby_lifetime_secs: Some(86_400),
+            region: DEFAULT_REGION.to_string(),
+            geoip_db: None,
+            geoip_regions: HashMap::new(),
+            index_sweep_interval_secs: 30,
+            max_send_failures: 50,
+            ready_quorum: 1.0,
+            countdown_secs: 5,
+            replication_standby: None,
+            replay_record_path: None,
+            replication_listen: None,
+            replication_failover_secs: 10,
+            max_player_bytes_per_sec: None,
+            stats_backend: None,
+            stats_flush_interval_secs: 5,
+            admin_token: None,
+            api_keys: Vec::new(),
+            anonymous_role: Role::Player,
+            checkpoint_path: Some("lobby_checkpoints.json".to_string()),
+            loadouts_path: Some("loadouts.json".to_string()),
+            checkpoint_interval_secs: 5,
+            recovery_grace_secs: 120,
+            admin_console_socket: None,
+            admin_console_addr: None,
+            afk_spectate_secs: Some(90),
+            afk_kick_secs: 180,
+            afk_warning_secs: 15,
+            loot_drops: None,
+            loot_lifetime_secs: 30,
+            net_sim: None,
+            public_ip: None,
+            public_udp_port: None,
+            public_addr_echo: None,
+            identity_path: Some("server_identity.json".to_string()),
+            udp_send_buffer_bytes: None,
+            udp_recv_buffer_bytes: None,
+            hibernate_after_secs: Some(30),
+            otlp_endpoint: None,
+            trace_sample_ratio: 0.01,
         }
     }
 }
@@ -0,0 +1,327 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+
+/// Marks the start of the metadata section, near the end of the file
+const METADATA_MARKER: &[u8] = b"\xAB\xCD\xEFMaxMind.com";
+
+/// Zero bytes between the search tree and the data section
+const DATA_SECTION_SEPARATOR: usize = 16;
+
+/// Nesting deeper than this is treated as a corrupt database
+const MAX_DEPTH: usize = 32;
+
+/// A decoded data section value; only the shapes lookups care about are kept
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Map(Vec<(String, Value)>),
+    Str(String),
+    Uint(u64),
+    Other,
+}
+
+impl Value {
+    fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Map(entries) => entries.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::Str(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    fn as_uint(&self) -> Option<u64> {
+        match self {
+            Value::Uint(n) => Some(*n),
+            _ => None,
+        }
+    }
+}
+
+/// Where an address was located
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location {
+    pub country: Option<String>,   // ISO 3166 code, e.g. "DE"
+    pub continent: Option<String>, // Two-letter continent code, e.g. "EU"
+}
+
+/// In-memory MaxMind DB (GeoLite2/GeoIP2 Country or City); lookups never leave the process
+/// Reads only what country and continent lookups need: metadata, search tree, maps, strings and integers
+#[derive(Debug)]
+pub struct MmdbReader {
+    bytes: Vec<u8>,
+    node_count: usize,
+    record_size: usize,
+    ip_version: u16,
+    data_start: usize,
+}
+
+impl MmdbReader {
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Self, &'static str> {
+        let marker = bytes.windows(METADATA_MARKER.len())
+            .rposition(|w| w == METADATA_MARKER)
+            .ok_or("Not a MaxMind DB: metadata not found")?;
+        let metadata_start = marker + METADATA_MARKER.len();
+        let (metadata, _) = decode(&bytes, metadata_start, metadata_start, 0)?;
+
+        let field = |name| metadata.get(name).and_then(Value::as_uint).ok_or("MaxMind DB metadata incomplete");
+        let node_count = field("node_count")? as usize;
+        let record_size = field("record_size")? as usize;
+        let ip_version = field("ip_version")? as u16;
+        if ![24, 28, 32].contains(&record_size) {
+            return Err("Unsupported MaxMind DB record size");
+        }
+        let data_start = node_count * record_size * 2 / 8 + DATA_SECTION_SEPARATOR;
+        if data_start > marker {
+            return Err("MaxMind DB search tree truncated");
+        }
+        Ok(Self { bytes, node_count, record_size, ip_version, data_start })
+    }
+
+    /// Country and continent of an address, if the database has them
+    pub fn locate(&self, ip: IpAddr) -> Option<Location> {
+        let record = self.lookup(ip)?;
+        let code = |section: &str, key: &str| {
+            record.get(section).and_then(|s| s.get(key)).and_then(Value::as_str).map(str::to_string)
+        };
+        Some(Location { country: code("country", "iso_code"), continent: code("continent", "code") })
+    }
+
+    fn lookup(&self, ip: IpAddr) -> Option<Value> {
+        let (bits, len): (u128, u32) = match (ip, self.ip_version) {
+            (IpAddr::V4(v4), 4) => (u32::from(v4) as u128, 32),
+            // IPv4 lives in the first /96 of IPv6 databases
+            (IpAddr::V4(v4), _) => (u32::from(v4) as u128, 128),
+            (IpAddr::V6(v6), 4) => (u32::from(v6.to_ipv4_mapped()?) as u128, 32),
+            (IpAddr::V6(v6), _) => (u128::from(v6), 128),
+        };
+
+        let mut node = 0;
+        for i in (0..len).rev() {
+            if node >= self.node_count {
+                break;
+            }
+            node = self.record(node, (bits >> i) & 1 == 1)?;
+        }
+        // node_count itself means "no data"; below it the address ran out of bits mid-tree
+        if node <= self.node_count {
+            return None;
+        }
+        // Records pointing into the separator come from a corrupt database
+        let offset = self.data_start + node.checked_sub(self.node_count + DATA_SECTION_SEPARATOR)?;
+        decode(&self.bytes, self.data_start, offset, 0).ok().map(|(value, _)| value)
+    }
+
+    /// Left (false) or right (true) record of a search tree node
+    fn record(&self, node: usize, right: bool) -> Option<usize> {
+        let node_bytes = self.record_size * 2 / 8;
+        let b = self.bytes.get(node * node_bytes..(node + 1) * node_bytes)?;
+        let be = |bytes: &[u8]| bytes.iter().fold(0usize, |acc, &byte| acc << 8 | byte as usize);
+        Some(match (self.record_size, right) {
+            (24, false) => be(&b[0..3]),
+            (24, true) => be(&b[3..6]),
+            (28, false) => ((b[3] as usize & 0xF0) << 20) | be(&b[0..3]),
+            (28, true) => ((b[3] as usize & 0x0F) << 24) | be(&b[4..7]),
+            (_, false) => be(&b[0..4]),
+            (_, true) => be(&b[4..8]),
+        })
+    }
+}
+
+/// Decode the value at `offset`; returns it and the offset just past it
+/// Pointers are relative to `base`, the start of the section being read
+fn decode(bytes: &[u8], base: usize, offset: usize, depth: usize) -> Result<(Value, usize), &'static str> {
+    const TRUNCATED: &str = "MaxMind DB data truncated";
+    if depth > MAX_DEPTH {
+        return Err("MaxMind DB data nested too deeply");
+    }
+    let byte = |at: usize| bytes.get(at).copied().ok_or(TRUNCATED);
+    let be = |from: usize, len: usize| -> Result<u64, &'static str> {
+        let slice = bytes.get(from..from + len).ok_or(TRUNCATED)?;
+        Ok(slice.iter().fold(0u64, |acc, &b| acc << 8 | b as u64))
+    };
+
+    let control = byte(offset)?;
+    let mut at = offset + 1;
+    let mut kind = control >> 5;
+
+    if kind == 1 {
+        let size = ((control >> 3) & 0x3) as usize;
+        let low = (control & 0x7) as u64;
+        let target = match size {
+            0 => (low << 8 | be(at, 1)?) as usize,
+            1 => (low << 16 | be(at, 2)?) as usize + 2048,
+            2 => (low << 24 | be(at, 3)?) as usize + 526_336,
+            _ => be(at, 4)? as usize,
+        };
+        let (value, _) = decode(bytes, base, base + target, depth + 1)?;
+        return Ok((value, at + size + 1));
+    }
+    if kind == 0 {
+        kind = 7 + byte(at)?;
+        at += 1;
+    }
+
+    let mut size = (control & 0x1F) as usize;
+    if size >= 29 {
+        let extra = size - 28;
+        let raw = be(at, extra)? as usize;
+        size = match extra {
+            1 => 29 + raw,
+            2 => 285 + raw,
+            _ => 65_821 + raw,
+        };
+        at += extra;
+    }
+
+    match kind {
+        2 => {
+            let raw = bytes.get(at..at + size).ok_or(TRUNCATED)?;
+            let s = std::str::from_utf8(raw).map_err(|_| "MaxMind DB string is not UTF-8")?;
+            Ok((Value::Str(s.to_string()), at + size))
+        }
+        5 | 6 | 9 => Ok((Value::Uint(be(at, size.min(8))?), at + size)),
+        7 => {
+            let mut entries = Vec::with_capacity(size.min(64));
+            for _ in 0..size {
+                let (key, next) = decode(bytes, base, at, depth + 1)?;
+                let (value, next) = decode(bytes, base, next, depth + 1)?;
+                entries.push((key.as_str().ok_or("MaxMind DB map key is not a string")?.to_string(), value));
+                at = next;
+            }
+            Ok((Value::Map(entries), at))
+        }
+        // Walked only to find where they end (subdivisions, say)
+        11 => {
+            for _ in 0..size {
+                at = decode(bytes, base, at, depth + 1)?.1;
+            }
+            Ok((Value::Other, at))
+        }
+        // Booleans carry their value in the size bits
+        14 => Ok((Value::Other, at)),
+        3 => Ok((Value::Other, at + 8)),
+        15 => Ok((Value::Other, at + 4)),
+        4 | 8 | 10 => Ok((Value::Other, at + size)),
+        _ => Err("Unsupported MaxMind DB data type"),
+    }
+}
+
+/// Maps joining addresses to matchmaking regions
+#[derive(Debug)]
+pub struct GeoIp {
+    reader: MmdbReader,
+    regions: HashMap<String, String>, // Country or continent code -> region
+}
+
+impl GeoIp {
+    pub fn new(reader: MmdbReader, regions: HashMap<String, String>) -> Self {
+        let regions = regions.into_iter().map(|(code, region)| (code.to_ascii_uppercase(), region)).collect();
+        Self { reader, regions }
+    }
+
+    /// Load the database at `path` into memory
+    pub fn open(path: &str, regions: HashMap<String, String>) -> std::io::Result<Self> {
+        let reader = MmdbReader::from_bytes(std::fs::read(path)?)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        Ok(Self::new(reader, regions))
+    }
+
+    /// Region a client at `ip` most likely plays from
+    /// A mapped country wins over its continent; unmapped continents use their code, lowercased
+    pub fn region(&self, ip: IpAddr) -> Option<String> {
+        let location = self.reader.locate(ip)?;
+        let mapped = |code: &Option<String>| code.as_ref().and_then(|c| self.regions.get(&c.to_ascii_uppercase())).cloned();
+        mapped(&location.country)
+            .or_else(|| mapped(&location.continent))
+            .or_else(|| location.continent.map(|c| c.to_ascii_lowercase()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn string(s: &str) -> Vec<u8> {
+        let mut out = vec![(2 << 5) | s.len() as u8];
+        out.extend_from_slice(s.as_bytes());
+        out
+    }
+
+    fn uint(n: u32) -> Vec<u8> {
+        let mut out = vec![(6 << 5) | 4];
+        out.extend_from_slice(&n.to_be_bytes());
+        out
+    }
+
+    fn map(entries: &[(&str, Vec<u8>)]) -> Vec<u8> {
+        let mut out = vec![(7 << 5) | entries.len() as u8];
+        for (key, value) in entries {
+            out.extend(string(key));
+            out.extend_from_slice(value);
+        }
+        out
+    }
+
+    fn location(country: &str, continent: &str) -> Vec<u8> {
+        map(&[
+            ("continent", map(&[("code", string(continent))])),
+            ("country", map(&[("iso_code", string(country)), ("names", map(&[("en", string("x"))]))])),
+        ])
+    }
+
+    /// IPv4 database with one node: 0.0.0.0/1 is in Germany, 128.0.0.0/1 in the US
+    fn test_db() -> Vec<u8> {
+        let germany = location("DE", "EU");
+        let us = location("US", "NA");
+        let node_count = 1;
+        let record = |offset: usize| ((node_count + DATA_SECTION_SEPARATOR + offset) as u32).to_be_bytes()[1..].to_vec();
+
+        let mut db = record(0);
+        db.extend(record(germany.len()));
+        db.extend([0u8; DATA_SECTION_SEPARATOR]);
+        db.extend(germany);
+        db.extend(us);
+        db.extend_from_slice(METADATA_MARKER);
+        db.extend(map(&[("node_count", uint(1)), ("record_size", uint(24)), ("ip_version", uint(4))]));
+        db
+    }
+
+    #[test]
+    fn test_locate_by_search_tree() {
+        let reader = MmdbReader::from_bytes(test_db()).unwrap();
+        let de = reader.locate("10.1.2.3".parse().unwrap()).unwrap();
+        assert_eq!(de, Location { country: Some("DE".to_string()), continent: Some("EU".to_string()) });
+        let us = reader.locate("200.1.2.3".parse().unwrap()).unwrap();
+        assert_eq!(us.country.as_deref(), Some("US"));
+        assert_eq!(reader.locate("::ffff:10.0.0.1".parse().unwrap()).unwrap().country.as_deref(), Some("DE"));
+        assert!(reader.locate("2001:db8::1".parse().unwrap()).is_none());
+
+        assert!(MmdbReader::from_bytes(b"not a database".to_vec()).is_err());
+    }
+
+    #[test]
+    fn test_record_into_separator_is_not_found() {
+        let mut db = test_db();
+        // Left record points one past node_count, inside the separator
+        db[0..3].copy_from_slice(&2u32.to_be_bytes()[1..]);
+        let reader = MmdbReader::from_bytes(db).unwrap();
+        assert!(reader.locate("10.0.0.1".parse().unwrap()).is_none());
+        assert!(reader.locate("200.0.0.1".parse().unwrap()).is_some());
+    }
+
+    #[test]
+    fn test_region_mapping() {
+        let regions = HashMap::from([("us".to_string(), "us-east".to_string()), ("EU".to_string(), "europe".to_string())]);
+        let geoip = GeoIp::new(MmdbReader::from_bytes(test_db()).unwrap(), regions);
+        assert_eq!(geoip.region("10.0.0.1".parse().unwrap()).as_deref(), Some("europe"));
+        assert_eq!(geoip.region("200.0.0.1".parse().unwrap()).as_deref(), Some("us-east"));
+
+        let unmapped = GeoIp::new(MmdbReader::from_bytes(test_db()).unwrap(), HashMap::new());
+        assert_eq!(unmapped.region("200.0.0.1".parse().unwrap()).as_deref(), Some("na"));
+    }
+}
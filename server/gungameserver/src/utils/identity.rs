@@ -0,0 +1,205 @@
+use crate::utils::config::Config;
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::net::UdpSocket;
+
+/// Overrides the advertised IP (takes precedence over config)
+pub const PUBLIC_IP_ENV: &str = "GUNGAME_PUBLIC_IP";
+
+/// Overrides the advertised UDP port (takes precedence over config)
+pub const PUBLIC_UDP_PORT_ENV: &str = "GUNGAME_PUBLIC_UDP_PORT";
+
+/// How long to wait for the echo endpoint to report our address
+const ECHO_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Advertised when nothing better is known (no config, no echo, no Host header)
+const FALLBACK_IP: &str = "127.0.0.1";
+
+/// Address clients are told to send UDP to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AdvertisedAddr {
+    /// None: use the host each HTTP client reached us by
+    pub ip: Option<String>,
+    pub udp_port: u16,
+}
+
+impl AdvertisedAddr {
+    /// Nothing configured or detected: per-request host, the bound UDP port
+    pub fn local(config: &Config) -> Self {
+        Self { ip: None, udp_port: config.udp_port }
+    }
+
+    /// IP to give a client whose request named `request_host` (a Host header, port optional)
+    pub fn ip_for(&self, request_host: Option<&str>) -> String {
+        self.ip.clone()
+            .or_else(|| request_host.map(host_without_port).filter(|host| !host.is_empty()))
+            .unwrap_or_else(|| FALLBACK_IP.to_string())
+    }
+}
+
+/// "host", "host:port", "[v6]" or "[v6]:port" -> host
+fn host_without_port(host: &str) -> String {
+    if let Some(rest) = host.strip_prefix('[') {
+        return rest.split(']').next().unwrap_or_default().to_string();
+    }
+    match host.rsplit_once(':') {
+        Some((name, port)) if !name.contains(':') && port.parse::<u16>().is_ok() => name.to_string(),
+        _ => host.to_string(),
+    }
+}
+
+/// Stable id of this server plus the address it advertises
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerIdentity {
+    pub server_id: String,
+    pub advertised: AdvertisedAddr,
+}
+
+#[derive(Serialize, Deserialize)]
+struct IdentityFile {
+    server_id: String,
+}
+
+impl ServerIdentity {
+    /// Load (or create and persist) the server id and work out the advertised address
+    pub async fn establish(config: &Config, socket: &UdpSocket) -> Self {
+        let server_id = match &config.identity_path {
+            Some(path) => load_or_create_id(path).await,
+            None => new_id(),
+        };
+        let env = |name| std::env::var(name).ok().filter(|value: &String| !value.is_empty());
+        let mut advertised = from_overrides(env(PUBLIC_IP_ENV), env(PUBLIC_UDP_PORT_ENV), config);
+        if advertised.ip.is_none() {
+            if let Some(echo) = &config.public_addr_echo {
+                match query_echo(socket, echo).await {
+                    Ok(observed) => {
+                        advertised.ip = Some(observed.ip().to_string());
+                        if config.public_udp_port.is_none() {
+                            advertised.udp_port = observed.port();
+                        }
+                    }
+                    Err(e) => log::warn!("Public address detection via {} failed: {}", echo, e),
+                }
+            }
+        }
+        log::info!(
+            "Server {} advertising {}:{}",
+            server_id, advertised.ip.as_deref().unwrap_or("<request host>"), advertised.udp_port
+        );
+        Self { server_id, advertised }
+    }
+}
+
+fn new_id() -> String {
+    uuid::Uuid::new_v4().to_string()
+}
+
+async fn load_or_create_id(path: &str) -> String {
+    if let Ok(bytes) = tokio::fs::read(path).await {
+        match serde_json::from_slice::<IdentityFile>(&bytes) {
+            Ok(file) => return file.server_id,
+            Err(e) => log::warn!("Ignoring unreadable identity file {}: {}", path, e),
+        }
+    }
+    let server_id = new_id();
+    let file = IdentityFile { server_id: server_id.clone() };
+    match serde_json::to_vec(&file) {
+        Ok(bytes) => {
+            if let Err(e) = tokio::fs::write(path, bytes).await {
+                log::warn!("Failed to persist server identity to {}: {}", path, e);
+            }
+        }
+        Err(e) => log::warn!("Failed to serialize server identity: {}", e),
+    }
+    server_id
+}
+
+/// Env vars first, then config; the echo endpoint only fills in what's still unknown
+fn from_overrides(env_ip: Option<String>, env_port: Option<String>, config: &Config) -> AdvertisedAddr {
+    let ip = env_ip.or_else(|| config.public_ip.clone());
+    let udp_port = env_port.and_then(|port| port.parse().ok())
+        .or(config.public_udp_port)
+        .unwrap_or(config.udp_port);
+    AdvertisedAddr { ip, udp_port }
+}
+
+/// STUN-style probe: ask another server's UDP port what address our packets arrive from
+/// Sent from the game socket itself so a NAT reports the mapping clients will hit
+pub async fn query_echo(socket: &UdpSocket, echo: &str) -> Result<SocketAddr, &'static str> {
+    let echo_addr = tokio::net::lookup_host(echo).await
+        .ok()
+        .and_then(|mut addrs| addrs.next())
+        .ok_or("Echo address does not resolve")?;
+    let probe = serde_json::to_vec(&serde_json::json!({ "type": "whoami" })).map_err(|_| "Probe encoding failed")?;
+    socket.send_to(&probe, echo_addr).await.map_err(|_| "Probe send failed")?;
+
+    let mut buf = [0u8; 512];
+    tokio::time::timeout(ECHO_TIMEOUT, async {
+        loop {
+            let (len, from) = socket.recv_from(&mut buf).await.map_err(|_| "Echo receive failed")?;
+            if from != echo_addr {
+                continue;
+            }
+            let reply: serde_json::Value = serde_json::from_slice(&buf[..len]).map_err(|_| "Malformed echo reply")?;
+            return reply["addr"].as_str()
+                .and_then(|addr| addr.parse().ok())
+                .ok_or("Malformed echo reply");
+        }
+    })
+    .await
+    .map_err(|_| "Echo timed out")?
+}
+
+/// Answer to a `whoami` probe: the address the packet came from
+pub fn whoami_reply(from: SocketAddr) -> serde_json::Value {
+    serde_json::json!({ "type": "whoami", "addr": from.to_string() })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_address_precedence_and_request_host() {
+        let config = Config { public_ip: Some("203.0.113.7".to_string()), ..Default::default() };
+        let advertised = from_overrides(Some("198.51.100.2".to_string()), Some("9000".to_string()), &config);
+        assert_eq!(advertised, AdvertisedAddr { ip: Some("198.51.100.2".to_string()), udp_port: 9000 });
+        assert_eq!(from_overrides(None, None, &config).ip.as_deref(), Some("203.0.113.7"));
+
+        let local = AdvertisedAddr::local(&Config::default());
+        assert_eq!(local.udp_port, 8081);
+        assert_eq!(local.ip_for(Some("game.example.com:8080")), "game.example.com");
+        assert_eq!(local.ip_for(Some("[2001:db8::1]:8080")), "2001:db8::1");
+        assert_eq!(local.ip_for(Some("10.0.0.5")), "10.0.0.5");
+        assert_eq!(local.ip_for(None), FALLBACK_IP);
+        assert_eq!(advertised.ip_for(Some("10.0.0.5")), "198.51.100.2");
+    }
+
+    #[tokio::test]
+    async fn test_echo_reports_observed_address() {
+        let echo = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let echo_addr = echo.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut buf = [0u8; 64];
+            let (_, from) = echo.recv_from(&mut buf).await.unwrap();
+            echo.send_to(whoami_reply(from).to_string().as_bytes(), from).await.unwrap();
+        });
+
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let observed = query_echo(&socket, &echo_addr.to_string()).await.unwrap();
+        assert_eq!(observed, socket.local_addr().unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_identity_persisted_across_starts() {
+        let path = std::env::temp_dir().join(format!("gungame_identity_{}.json", std::process::id()));
+        let config = Config { identity_path: Some(path.to_string_lossy().into_owned()), ..Default::default() };
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+
+        let first = ServerIdentity::establish(&config, &socket).await;
+        let second = ServerIdentity::establish(&config, &socket).await;
+        assert_eq!(first.server_id, second.server_id);
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -0,0 +1,228 @@
+use dashmap::DashMap;
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::LazyLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::state::commands::LobbyCommand;
+use crate::state::lobby::LobbyCode;
+
+/// Log lines kept per lobby; the oldest are dropped first
+pub const LOBBY_LOG_CAPACITY: usize = 500;
+
+/// Longest correlation id accepted from a client
+const MAX_CORRELATION_ID_LEN: usize = 64;
+
+tokio::task_local! {
+    static CONTEXT: RefCell<LogContext>;
+}
+
+static NEXT_CORRELATION_ID: AtomicU64 = AtomicU64::new(1);
+
+static LOBBY_LOGS: LazyLock<LobbyLogs> = LazyLock::new(LobbyLogs::default);
+
+/// What the current task is working on, attached to every log line it emits
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LogContext {
+    pub lobby_code: Option<LobbyCode>,
+    pub correlation_id: Option<String>,
+    pub command: Option<&'static str>,
+}
+
+impl LogContext {
+    /// `[lobby=X cid=Y cmd=Z] ` for the parts that are set
+    pub fn prefix(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(code) = &self.lobby_code {
+            parts.push(format!("lobby={}", code));
+        }
+        if let Some(id) = &self.correlation_id {
+            parts.push(format!("cid={}", id));
+        }
+        if let Some(command) = self.command {
+            parts.push(format!("cmd={}", command));
+        }
+        if parts.is_empty() {
+            String::new()
+        } else {
+            format!("[{}] ", parts.join(" "))
+        }
+    }
+}
+
+/// Run a future with a log context (e.g. a lobby's tick loop, one UDP packet)
+pub async fn scope<F: Future>(context: LogContext, future: F) -> F::Output {
+    CONTEXT.scope(RefCell::new(context), future).await
+}
+
+/// Run a lobby's tick loop with the lobby attached to its logs
+pub async fn with_lobby<F: Future>(lobby_code: LobbyCode, future: F) -> F::Output {
+    scope(LogContext { lobby_code: Some(lobby_code), ..Default::default() }, future).await
+}
+
+/// Context of the current task, if it has one
+pub fn current() -> Option<LogContext> {
+    CONTEXT.try_with(|context| context.borrow().clone()).ok()
+}
+
+/// Run one command with its correlation id and name attached
+pub fn in_command<R>(correlation_id: Option<String>, command: &'static str, f: impl FnOnce() -> R) -> R {
+    let set = |correlation_id: Option<String>, command: Option<&'static str>| {
+        let _ = CONTEXT.try_with(|context| {
+            let mut context = context.borrow_mut();
+            context.correlation_id = correlation_id;
+            context.command = command;
+        });
+    };
+    set(correlation_id, Some(command));
+    let result = f();
+    set(None, None);
+    result
+}
+
+/// A client-supplied correlation id, or a fresh one
+pub fn correlation_id(packet: &serde_json::Value) -> String {
+    let supplied = packet.get("correlation_id")
+        .and_then(|v| v.as_str())
+        .filter(|id| !id.is_empty() && id.len() <= MAX_CORRELATION_ID_LEN)
+        .filter(|id| id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+    match supplied {
+        Some(id) => id.to_string(),
+        None => format!("s{:x}", NEXT_CORRELATION_ID.fetch_add(1, Ordering::Relaxed)),
+    }
+}
+
+/// Tag a command with the current correlation id so the lobby logs it under the same id
+pub fn traced(command: LobbyCommand) -> LobbyCommand {
+    match current().and_then(|context| context.correlation_id) {
+        Some(correlation_id) => LobbyCommand::Traced { correlation_id, command: Box::new(command) },
+        None => command,
+    }
+}
+
+/// One captured log line
+#[derive(Debug, Clone, PartialEq, serde::Serialize, utoipa::ToSchema)]
+pub struct LobbyLogEntry {
+    pub timestamp_ms: u64,
+    pub level: String,
+    pub correlation_id: Option<String>,
+    pub command: Option<String>,
+    pub message: String,
+}
+
+/// Recent log lines per lobby, for debugging one match without the log files
+#[derive(Debug, Default)]
+pub struct LobbyLogs {
+    lobbies: DashMap<LobbyCode, VecDeque<LobbyLogEntry>>,
+}
+
+impl LobbyLogs {
+    pub fn record(&self, lobby_code: &str, entry: LobbyLogEntry) {
+        let mut lines = self.lobbies.entry(lobby_code.to_string()).or_default();
+        if lines.len() >= LOBBY_LOG_CAPACITY {
+            lines.pop_front();
+        }
+        lines.push_back(entry);
+    }
+
+    /// Up to `limit` most recent lines, oldest first
+    pub fn recent(&self, lobby_code: &str, limit: usize) -> Vec<LobbyLogEntry> {
+        self.lobbies.get(lobby_code)
+            .map(|lines| {
+                let skip = lines.len().saturating_sub(limit);
+                lines.iter().skip(skip).cloned().collect()
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn remove(&self, lobby_code: &str) {
+        self.lobbies.remove(lobby_code);
+    }
+}
+
+/// Process-wide per-lobby log buffers (filled by `LobbyLogCapture`)
+pub fn lobby_logs() -> &'static LobbyLogs {
+    &LOBBY_LOGS
+}
+
+/// Logger output that copies lines emitted inside a lobby context into its buffer
+pub struct LobbyLogCapture;
+
+impl log::Log for LobbyLogCapture {
+    fn enabled(&self, _metadata: &log::Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::Record) {
+        let Some(context) = current() else {
+            return;
+        };
+        let Some(lobby_code) = &context.lobby_code else {
+            return;
+        };
+        let timestamp_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0);
+        lobby_logs().record(lobby_code, LobbyLogEntry {
+            timestamp_ms,
+            level: record.level().to_string(),
+            correlation_id: context.correlation_id,
+            command: context.command.map(str::to_string),
+            message: record.args().to_string(),
+        });
+    }
+
+    fn flush(&self) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use log::Log;
+
+    fn emit(message: &str) {
+        LobbyLogCapture.log(&log::Record::builder()
+            .args(format_args!("{}", message))
+            .level(log::Level::Warn)
+            .build());
+    }
+
+    #[tokio::test]
+    async fn test_lines_captured_with_command_context() {
+        with_lobby("LOG_TEST".to_string(), async {
+            emit("tick");
+            in_command(Some("abc".to_string()), "shoot", || emit("shot failed"));
+            assert_eq!(current().unwrap().correlation_id, None);
+        }).await;
+        emit("outside any lobby");
+
+        let lines = lobby_logs().recent("LOG_TEST", 10);
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[1].message, "shot failed");
+        assert_eq!(lines[1].correlation_id.as_deref(), Some("abc"));
+        assert_eq!(lines[1].command.as_deref(), Some("shoot"));
+        assert_eq!(lobby_logs().recent("LOG_TEST", 1).len(), 1);
+        lobby_logs().remove("LOG_TEST");
+    }
+
+    #[tokio::test]
+    async fn test_commands_traced_inside_packet_scope() {
+        let packet = serde_json::json!({ "correlation_id": "client-7" });
+        let context = LogContext { correlation_id: Some(correlation_id(&packet)), ..Default::default() };
+        let command = scope(context, async { traced(LobbyCommand::EndMatch) }).await;
+        assert!(matches!(command, LobbyCommand::Traced { ref correlation_id, .. } if correlation_id == "client-7"));
+        assert!(matches!(traced(LobbyCommand::EndMatch), LobbyCommand::EndMatch));
+
+        // Unusable client ids are replaced
+        assert!(correlation_id(&serde_json::json!({ "correlation_id": "bad id!" })).starts_with('s'));
+    }
+
+    #[test]
+    fn test_prefix() {
+        let context = LogContext { lobby_code: Some("A".to_string()), correlation_id: Some("c1".to_string()), command: None };
+        assert_eq!(context.prefix(), "[lobby=A cid=c1] ");
+        assert_eq!(LogContext::default().prefix(), "");
+    }
+}
@@ -1,4 +1,12 @@
 pub mod weapondb;
 pub mod config;
 pub mod buffers;
+pub mod scenes;
+pub mod log_context;
+pub mod rng;
+pub mod clock;
+pub mod telemetry;
 
+pub mod identity;
+pub mod geoip;
+pub mod udp_socket;
@@ -0,0 +1,120 @@
+use crate::domain::pickups::PickupKind;
+
+/// Area players fight over in king-of-the-hill matches
+/// A vertical cylinder: within `radius` of the center on x/z and `height` above it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CaptureZone {
+    pub center: (f32, f32, f32),
+    pub radius: f32,
+    pub height: f32,
+}
+
+impl CaptureZone {
+    pub fn contains(&self, position: (f32, f32, f32)) -> bool {
+        let (dx, dz) = (position.0 - self.center.0, position.2 - self.center.2);
+        let dy = position.1 - self.center.1;
+        dx * dx + dz * dz <= self.radius * self.radius && (0.0..=self.height).contains(&dy)
+    }
+}
+
+/// A pickup the scene places; it respawns `respawn_secs` after being taken
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PickupSpawn {
+    pub kind: PickupKind,
+    pub position: (f32, f32, f32),
+    pub respawn_secs: u64,
+}
+
+/// Per-scene world rules used for server-side validation
+#[derive(Debug, Clone, PartialEq)]
+pub struct SceneData {
+    pub name: String,
+    /// Players below this height are out of the world and killed
+    pub kill_plane_y: f32,
+    /// Landing faster than this (units/sec) causes fall damage
+    pub safe_fall_speed: f32,
+    /// Damage per unit/sec of landing speed above the safe speed
+    pub fall_damage_per_speed: f32,
+    /// Playable area spans -half_extent..half_extent on x and z
+    pub half_extent: f32,
+    /// Highest reachable y
+    pub max_height: f32,
+    /// Capture zone for king-of-the-hill; scenes without one can't host the mode
+    pub capture_zone: Option<CaptureZone>,
+    /// Edge length of the square x/z chunks clients stream assets by; None loads the scene whole
+    pub chunk_size: Option<f32>,
+    /// Health, overheal and armor pickups placed in the world
+    pub pickups: Vec<PickupSpawn>,
+}
+
+impl SceneData {
+    fn new(name: &str, kill_plane_y: f32, half_extent: f32) -> Self {
+        Self {
+            name: name.to_string(),
+            kill_plane_y,
+            safe_fall_speed: 15.0,
+            fall_damage_per_speed: 4.0,
+            half_extent,
+            max_height: 500.0,
+            capture_zone: None,
+            chunk_size: None,
+            pickups: Vec::new(),
+        }
+    }
+}
+
+/// Look up world rules for a scene
+/// Unknown scenes fall back to default rules
+pub fn scene_data(name: &str) -> SceneData {
+    match name {
+        "world" | "test_world" => SceneData {
+            chunk_size: Some(125.0),
+            pickups: vec![
+                PickupSpawn { kind: PickupKind::Health, position: (40.0, 0.0, 40.0), respawn_secs: 20 },
+                PickupSpawn { kind: PickupKind::Health, position: (-40.0, 0.0, -40.0), respawn_secs: 20 },
+                PickupSpawn { kind: PickupKind::Armor, position: (0.0, 0.0, 60.0), respawn_secs: 30 },
+                PickupSpawn { kind: PickupKind::Overheal, position: (0.0, 0.0, -60.0), respawn_secs: 60 },
+            ],
+            ..SceneData::new(name, -50.0, 500.0)
+        },
+        "arena" => SceneData {
+            capture_zone: Some(CaptureZone { center: (0.0, 0.0, 0.0), radius: 8.0, height: 6.0 }),
+            pickups: vec![
+                PickupSpawn { kind: PickupKind::Health, position: (20.0, 0.0, 0.0), respawn_secs: 15 },
+                PickupSpawn { kind: PickupKind::Health, position: (-20.0, 0.0, 0.0), respawn_secs: 15 },
+                PickupSpawn { kind: PickupKind::Armor, position: (0.0, 0.0, 20.0), respawn_secs: 30 },
+            ],
+            ..SceneData::new(name, -20.0, 100.0)
+        },
+        _ => SceneData::new(name, -100.0, 1000.0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_known_scene() {
+        let scene = scene_data("world");
+        assert_eq!(scene.name, "world");
+        assert_eq!(scene.kill_plane_y, -50.0);
+    }
+
+    #[test]
+    fn test_unknown_scene_uses_defaults() {
+        let scene = scene_data("custom_map");
+        assert_eq!(scene.name, "custom_map");
+        assert_eq!(scene.kill_plane_y, -100.0);
+        assert!(scene.safe_fall_speed > 0.0);
+        assert!(scene.capture_zone.is_none());
+    }
+
+    #[test]
+    fn test_capture_zone_bounds() {
+        let zone = scene_data("arena").capture_zone.unwrap();
+        assert!(zone.contains((3.0, 1.0, -3.0)));
+        assert!(!zone.contains((8.0, 1.0, 8.0))); // Outside the radius
+        assert!(!zone.contains((0.0, 10.0, 0.0))); // Above the zone
+    }
+}
@@ -0,0 +1,193 @@
+use std::future::Future;
+use std::sync::OnceLock;
+use std::time::Duration;
+use opentelemetry::propagation::{Extractor, TextMapPropagator};
+use opentelemetry::trace::{FutureExt, Status, TraceContextExt, Tracer as _, TracerProvider as _};
+use opentelemetry::{Context, KeyValue, Value};
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::{Sampler, Tracer, TracerProvider};
+use opentelemetry_sdk::{runtime, Resource};
+
+pub use opentelemetry::trace::SpanKind;
+
+/// Name spans are reported under
+pub const SERVICE_NAME: &str = "gungameserver";
+
+/// W3C trace context header (and UDP packet field) a caller's trace arrives in
+pub const TRACEPARENT: &str = "traceparent";
+
+/// Path spans are posted to when the endpoint doesn't name one
+const DEFAULT_TRACES_PATH: &str = "/v1/traces";
+
+/// Collectors that take longer than this to accept a batch lose it
+const EXPORT_TIMEOUT: Duration = Duration::from_secs(5);
+
+static TRACING: OnceLock<Tracing> = OnceLock::new();
+
+/// The installed exporter; spans are batched and posted in the background
+struct Tracing {
+    provider: TracerProvider,
+    tracer: Tracer,
+}
+
+/// Start exporting spans to an OTLP/HTTP collector; until then spans are never recorded
+/// New traces are recorded at `sample_ratio`; ones continued from a caller follow its decision
+pub fn install(endpoint: &str, sample_ratio: f64) -> Result<(), &'static str> {
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_http()
+        .with_endpoint(traces_url(endpoint)?)
+        .with_timeout(EXPORT_TIMEOUT)
+        .build()
+        .map_err(|e| {
+            log::warn!("Failed to build the OTLP exporter: {}", e);
+            "Invalid OTLP endpoint"
+        })?;
+    let sampler = Sampler::ParentBased(Box::new(Sampler::TraceIdRatioBased(sample_ratio.clamp(0.0, 1.0))));
+    let provider = TracerProvider::builder()
+        .with_batch_exporter(exporter, runtime::Tokio)
+        .with_sampler(sampler)
+        .with_resource(Resource::new([KeyValue::new("service.name", SERVICE_NAME)]))
+        .build();
+    let tracer = provider.tracer(SERVICE_NAME);
+    TRACING.set(Tracing { provider, tracer }).map_err(|_| "Tracing is already installed")
+}
+
+/// Export whatever spans are still buffered
+pub async fn shutdown() {
+    let Some(tracing) = TRACING.get() else {
+        return;
+    };
+    // Shutdown blocks until the batch task has drained, so keep it off the async workers
+    let provider = tracing.provider.clone();
+    match tokio::task::spawn_blocking(move || provider.shutdown()).await {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) => log::warn!("Failed to flush traces: {}", e),
+        Err(e) => log::warn!("Failed to flush traces: {}", e),
+    }
+}
+
+/// Collector URL from `http(s)://host:port[/path]` or a bare `host:port`
+fn traces_url(endpoint: &str) -> Result<String, &'static str> {
+    let (scheme, rest) = match endpoint.split_once("://") {
+        Some((scheme @ ("http" | "https"), rest)) => (scheme, rest),
+        Some(_) => return Err("OTLP endpoint must be http:// or https://"),
+        None => ("http", endpoint),
+    };
+    let (host, path) = match rest.find('/') {
+        Some(index) if index + 1 < rest.len() => (&rest[..index], &rest[index..]),
+        Some(index) => (&rest[..index], DEFAULT_TRACES_PATH),
+        None => (rest, DEFAULT_TRACES_PATH),
+    };
+    if host.is_empty() || !host.contains(':') {
+        return Err("OTLP endpoint needs a host and port");
+    }
+    Ok(format!("{}://{}{}", scheme, host, path))
+}
+
+/// A lone `traceparent` value, as a propagator carrier
+struct Traceparent<'a>(&'a str);
+
+impl Extractor for Traceparent<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        (key == TRACEPARENT).then_some(self.0)
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        vec![TRACEPARENT]
+    }
+}
+
+/// The caller's trace from a `traceparent` value; malformed ones start a new trace
+fn remote_parent(traceparent: &str) -> Context {
+    TraceContextPropagator::new().extract(&Traceparent(traceparent))
+}
+
+/// A timed operation; spans that aren't sampled (or with no exporter installed) record nothing
+/// The span ends when dropped
+#[derive(Debug)]
+pub struct Span {
+    context: Context,
+}
+
+impl Span {
+    /// Start a trace, or continue a caller's when it passed its `traceparent` along
+    pub fn root(name: &'static str, kind: SpanKind, traceparent: Option<&str>) -> Self {
+        let Some(tracing) = TRACING.get() else {
+            return Self { context: Context::new() };
+        };
+        let parent = traceparent.map_or_else(Context::new, remote_parent);
+        let span = tracing.tracer.span_builder(name).with_kind(kind).start_with_context(&tracing.tracer, &parent);
+        Self { context: parent.with_span(span) }
+    }
+
+    /// Start a span inside this one
+    pub fn child(&self, name: &'static str) -> Self {
+        match TRACING.get() {
+            Some(tracing) if self.is_recording() => {
+                let span = tracing.tracer.start_with_context(name, &self.context);
+                Self { context: self.context.with_span(span) }
+            }
+            _ => Self { context: Context::new() },
+        }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.context.span().is_recording()
+    }
+
+    pub fn set(&mut self, key: &'static str, value: impl Into<Value>) {
+        self.context.span().set_attribute(KeyValue::new(key, value));
+    }
+
+    /// Mark the operation as failed
+    pub fn fail(&mut self, message: impl Into<String>) {
+        self.context.span().set_status(Status::error(message.into()));
+    }
+
+    pub fn end(self) {
+        self.context.span().end();
+    }
+}
+
+/// Run a future with `span` as the current span, for code that passes the trace on
+pub async fn in_span<F: Future>(span: &Span, future: F) -> F::Output {
+    future.with_context(span.context.clone()).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_traceparent_continues_caller_trace() {
+        let context = remote_parent("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01");
+        let span = context.span();
+        let parent = span.span_context();
+        assert_eq!(format!("{:032x}", parent.trace_id()), "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_eq!(format!("{:016x}", parent.span_id()), "00f067aa0ba902b7");
+        assert!(parent.is_sampled() && parent.is_remote());
+
+        let unsampled = remote_parent("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-00");
+        assert!(!unsampled.span().span_context().is_sampled());
+        assert!(!remote_parent("00-00000000000000000000000000000000-00f067aa0ba902b7-01").has_active_span());
+        assert!(!remote_parent("garbage").has_active_span());
+    }
+
+    #[test]
+    fn test_endpoints() {
+        assert_eq!(traces_url("http://collector:4318").unwrap(), "http://collector:4318/v1/traces");
+        assert_eq!(traces_url("https://collector:4318/").unwrap(), "https://collector:4318/v1/traces");
+        assert_eq!(traces_url("collector:4318/otlp/v1/traces").unwrap(), "http://collector:4318/otlp/v1/traces");
+        assert!(traces_url("grpc://collector:4317").is_err());
+        assert!(traces_url("http://collector").is_err());
+    }
+
+    #[test]
+    fn test_spans_record_nothing_until_installed() {
+        let mut span = Span::root("udp.packet", SpanKind::Server, Some("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01"));
+        span.set("lobby.code", "ABCD");
+        assert!(!span.is_recording());
+        assert!(!span.child("tick.commands").is_recording());
+    }
+}
@@ -0,0 +1,174 @@
+use crate::utils::config::Config;
+use socket2::{Domain, Protocol, SockRef, Socket, Type};
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::net::UdpSocket;
+
+/// Process-wide counters for the shared game socket
+#[derive(Debug, Default)]
+pub struct SocketCounters {
+    send_would_block: AtomicU64, // Send buffer was full; the send waited for room
+    send_errors: AtomicU64,
+    recv_errors: AtomicU64,
+}
+
+impl SocketCounters {
+    const fn new() -> Self {
+        Self { send_would_block: AtomicU64::new(0), send_errors: AtomicU64::new(0), recv_errors: AtomicU64::new(0) }
+    }
+
+    pub fn record_would_block(&self) {
+        self.send_would_block.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_send_error(&self) {
+        self.send_errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_recv_error(&self) {
+        self.recv_errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn send_would_block(&self) -> u64 {
+        self.send_would_block.load(Ordering::Relaxed)
+    }
+
+    pub fn send_errors(&self) -> u64 {
+        self.send_errors.load(Ordering::Relaxed)
+    }
+
+    pub fn recv_errors(&self) -> u64 {
+        self.recv_errors.load(Ordering::Relaxed)
+    }
+}
+
+/// Counters for every lobby's sender task and the receive loop
+pub static COUNTERS: SocketCounters = SocketCounters::new();
+
+/// Bind the game socket with the configured OS buffer sizes
+/// The OS may round or cap the sizes (Linux doubles them); the effective ones are logged
+pub fn bind(addr: SocketAddr, config: &Config) -> std::io::Result<UdpSocket> {
+    let socket = Socket::new(Domain::for_address(addr), Type::DGRAM, Some(Protocol::UDP))?;
+    if let Some(bytes) = config.udp_send_buffer_bytes {
+        socket.set_send_buffer_size(bytes)?;
+    }
+    if let Some(bytes) = config.udp_recv_buffer_bytes {
+        socket.set_recv_buffer_size(bytes)?;
+    }
+    socket.set_nonblocking(true)?;
+    socket.bind(&addr.into())?;
+    let socket = UdpSocket::from_std(socket.into())?;
+    if let Some((send, recv)) = buffer_sizes(&socket) {
+        log::info!("UDP socket buffers: send {} bytes, receive {} bytes", send, recv);
+    }
+    Ok(socket)
+}
+
+/// Effective (send, receive) buffer sizes of a socket
+pub fn buffer_sizes(socket: &UdpSocket) -> Option<(usize, usize)> {
+    let socket = SockRef::from(socket);
+    Some((socket.send_buffer_size().ok()?, socket.recv_buffer_size().ok()?))
+}
+
+/// Kernel view of one UDP socket (Linux /proc/net/udp)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OsSocketStats {
+    pub tx_queue_bytes: u64,
+    pub rx_queue_bytes: u64,
+    /// Datagrams the kernel dropped, mostly for a full receive buffer
+    pub drops: u64,
+}
+
+/// Host-wide UDP error counters (Linux /proc/net/snmp)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OsUdpCounters {
+    pub in_errors: u64,
+    pub rcvbuf_errors: u64,
+    pub sndbuf_errors: u64,
+}
+
+/// Kernel stats for the socket bound to `port`; None where the OS doesn't expose them
+pub fn os_socket_stats(port: u16) -> Option<OsSocketStats> {
+    let tables: Vec<String> = ["/proc/net/udp", "/proc/net/udp6"].iter()
+        .filter_map(|path| std::fs::read_to_string(path).ok())
+        .collect();
+    if tables.is_empty() {
+        return None;
+    }
+    tables.iter().map(|table| parse_socket_table(table, port)).reduce(|a, b| OsSocketStats {
+        tx_queue_bytes: a.tx_queue_bytes + b.tx_queue_bytes,
+        rx_queue_bytes: a.rx_queue_bytes + b.rx_queue_bytes,
+        drops: a.drops + b.drops,
+    })
+}
+
+/// Host-wide UDP counters; None where the OS doesn't expose them
+pub fn os_udp_counters() -> Option<OsUdpCounters> {
+    parse_snmp(&std::fs::read_to_string("/proc/net/snmp").ok()?)
+}
+
+/// Sum the rows of a /proc/net/udp table whose local port is `port`
+fn parse_socket_table(table: &str, port: u16) -> OsSocketStats {
+    let mut stats = OsSocketStats::default();
+    for row in table.lines().skip(1) {
+        let columns: Vec<&str> = row.split_whitespace().collect();
+        let local_port = columns.get(1)
+            .and_then(|local| local.rsplit_once(':'))
+            .and_then(|(_, hex)| u16::from_str_radix(hex, 16).ok());
+        if local_port != Some(port) {
+            continue;
+        }
+        if let Some((tx, rx)) = columns.get(4).and_then(|queues| queues.split_once(':')) {
+            stats.tx_queue_bytes += u64::from_str_radix(tx, 16).unwrap_or(0);
+            stats.rx_queue_bytes += u64::from_str_radix(rx, 16).unwrap_or(0);
+        }
+        stats.drops += columns.last().and_then(|drops| drops.parse::<u64>().ok()).unwrap_or(0);
+    }
+    stats
+}
+
+/// Read the `Udp:` header and value rows of /proc/net/snmp
+fn parse_snmp(snmp: &str) -> Option<OsUdpCounters> {
+    let mut rows = snmp.lines().filter(|line| line.starts_with("Udp:"));
+    let names: Vec<&str> = rows.next()?.split_whitespace().collect();
+    let values: Vec<&str> = rows.next()?.split_whitespace().collect();
+    let counter = |name: &str| names.iter().position(|n| *n == name)
+        .and_then(|i| values.get(i))
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(0);
+    Some(OsUdpCounters {
+        in_errors: counter("InErrors"),
+        rcvbuf_errors: counter("RcvbufErrors"),
+        sndbuf_errors: counter("SndbufErrors"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_proc_tables() {
+        let table = "\
+   sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode ref pointer drops
+  123: 00000000:1F91 00000000:0000 07 00000000:00000A00 00:00000000 00000000  1000        0 4242 2 0000000000000000 17
+  124: 0100007F:0035 00000000:0000 07 00000000:00000000 00:00000000 00000000   101        0 4343 2 0000000000000000 3";
+        let stats = parse_socket_table(table, 8081);
+        assert_eq!(stats, OsSocketStats { tx_queue_bytes: 0, rx_queue_bytes: 0xA00, drops: 17 });
+        assert_eq!(parse_socket_table(table, 9999), OsSocketStats::default());
+
+        let snmp = "\
+Udp: InDatagrams NoPorts InErrors OutDatagrams RcvbufErrors SndbufErrors InCsumErrors IgnoredMulti MemErrors
+Udp: 1000 2 40 900 35 5 0 0 0";
+        assert_eq!(parse_snmp(snmp), Some(OsUdpCounters { in_errors: 40, rcvbuf_errors: 35, sndbuf_errors: 5 }));
+        assert_eq!(parse_snmp("Ip: 1 2"), None);
+    }
+
+    #[tokio::test]
+    async fn test_bind_applies_buffer_sizes() {
+        let config = Config { udp_recv_buffer_bytes: Some(8 * 1024), ..Default::default() };
+        let socket = bind("127.0.0.1:0".parse().unwrap(), &config).unwrap();
+        let (_, recv) = buffer_sizes(&socket).unwrap();
+        assert!((8 * 1024..=16 * 1024).contains(&recv), "receive buffer {}", recv); // Linux reports double
+    }
+}
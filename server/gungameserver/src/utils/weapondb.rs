@@ -1,5 +1,83 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// How a weapon fires per trigger pull
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FireMode {
+    /// One shot per trigger pull
+    #[default]
+    Semi,
+    /// A fixed number of shots per trigger pull, spaced by fire_rate
+    Burst(u32),
+    /// Fires at fire_rate for as long as the trigger is held
+    Auto,
+}
+
+/// Broad weapon class, for client menus and HUD
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum WeaponCategory {
+    #[default]
+    Rifle,
+    Pistol,
+    Shotgun,
+    Melee,
+}
+
+/// What a weapon's hits are, for how much of them armor stops
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DamageType {
+    #[default]
+    Ballistic,
+    Explosive,
+    Melee,
+    Fall, // Hard landings; no weapon deals it
+}
+
+/// Damage ramp-up: consecutive hits on the same target within `window_secs`
+/// multiply damage by `factor` per hit, up to `max_multiplier`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct RampUp {
+    pub factor: f32,
+    pub max_multiplier: f32,
+    pub window_secs: f32,
+}
+
+impl RampUp {
+    /// Damage multiplier for the nth consecutive hit (n starts at 1)
+    pub fn multiplier(&self, consecutive_hits: u32) -> f32 {
+        let exponent = consecutive_hits.saturating_sub(1).min(64) as i32;
+        self.factor.powi(exponent).min(self.max_multiplier)
+    }
+
+    fn validate(&self) -> Result<(), &'static str> {
+        let valid = (1.0..=2.0).contains(&self.factor)
+            && (1.0..=5.0).contains(&self.max_multiplier)
+            && (0.1..=10.0).contains(&self.window_secs);
+        if valid { Ok(()) } else { Err("Ramp-up out of range") }
+    }
+}
+
+/// Overheat: each shot adds `heat_per_shot` (full heat is 1.0), heat bleeds off at
+/// `cool_per_sec`, and reaching full heat locks the weapon for `lockout_secs`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct Overheat {
+    pub heat_per_shot: f32,
+    pub cool_per_sec: f32,
+    pub lockout_secs: f32,
+}
+
+impl Overheat {
+    fn validate(&self) -> Result<(), &'static str> {
+        let valid = (0.01..=1.0).contains(&self.heat_per_shot)
+            && (0.01..=10.0).contains(&self.cool_per_sec)
+            && (0.1..=10.0).contains(&self.lockout_secs);
+        if valid { Ok(()) } else { Err("Overheat out of range") }
+    }
+}
 
 /// Weapon data structure matching client weapon.json
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -11,6 +89,62 @@ pub struct WeaponData {
     pub range: f32,
     pub reload_time: f32,
     pub ammo: u32,
+    #[serde(default)]
+    pub fire_mode: FireMode,
+    #[serde(default)]
+    pub ramp_up: Option<RampUp>,
+    /// Muzzle speed of server-simulated projectiles; None fires hit-scan
+    #[serde(default)]
+    pub projectile_speed: Option<f32>,
+    /// Career kills a player needs before loadouts may use this weapon
+    #[serde(default)]
+    pub unlock_kills: u32,
+    /// Seconds after switching to this weapon before it can fire
+    #[serde(default)]
+    pub equip_time: f32,
+    #[serde(default)]
+    pub category: WeaponCategory,
+    /// Sustained fire builds heat; None never overheats
+    #[serde(default)]
+    pub overheat: Option<Overheat>,
+    /// Scales how far away shots are heard (gunfire_nearby hints); 1.0 is a typical gun
+    #[serde(default = "default_loudness")]
+    pub loudness: f32,
+    /// Pellets per shot; `damage` is per pellet, and a shot's total on one target is capped like any hit
+    #[serde(default = "default_pellets")]
+    pub pellets: u32,
+    /// Half-angle in degrees of the cone pellets scatter over, for hits the server simulates
+    #[serde(default)]
+    pub spread: f32,
+    #[serde(default)]
+    pub damage_type: DamageType,
+}
+
+fn default_loudness() -> f32 {
+    1.0
+}
+
+fn default_pellets() -> u32 {
+    1
+}
+
+/// Client-side assets for a weapon, so clients don't hard-code the mapping
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct WeaponFx {
+    pub muzzle_flash_id: String,
+    pub sound_id: String,
+    /// Hex RGB, e.g. "#ffd700"
+    pub tracer_color: String,
+}
+
+impl WeaponFx {
+    fn new(muzzle_flash_id: &str, sound_id: &str, tracer_color: &str) -> Self {
+        Self {
+            muzzle_flash_id: muzzle_flash_id.to_string(),
+            sound_id: sound_id.to_string(),
+            tracer_color: tracer_color.to_string(),
+        }
+    }
 }
 
 /// Immutable weapon database - loaded once at startup
@@ -18,6 +152,10 @@ pub struct WeaponData {
 #[derive(Debug, Clone)]
 pub struct WeaponDb {
     weapons: HashMap<u32, WeaponData>,
+    /// FX metadata keyed by weapon id
+    fx: HashMap<u32, WeaponFx>,
+    /// Hash of every definition, so clients can tell when their cache is stale
+    version: String,
 }
 
 impl WeaponDb {
@@ -34,6 +172,17 @@ impl WeaponDb {
             range: 100.0,
             reload_time: 1.0,
             ammo: 20,
+            fire_mode: FireMode::Auto,
+            ramp_up: None,
+            projectile_speed: None,
+            unlock_kills: 0,
+            equip_time: 0.4,
+            category: WeaponCategory::Rifle,
+            overheat: None,
+            loudness: 1.0,
+            pellets: 1,
+            spread: 0.0,
+            damage_type: DamageType::Ballistic,
         });
 
         weapons.insert(2, WeaponData {
@@ -44,6 +193,17 @@ impl WeaponDb {
             range: 150.0,
             reload_time: 1.5,
             ammo: 8,
+            fire_mode: FireMode::Burst(3),
+            ramp_up: None,
+            projectile_speed: None,
+            unlock_kills: 25,
+            equip_time: 0.6,
+            category: WeaponCategory::Pistol,
+            overheat: None,
+            loudness: 1.4,
+            pellets: 1,
+            spread: 0.0,
+            damage_type: DamageType::Ballistic,
         });
 
         weapons.insert(3, WeaponData {
@@ -54,9 +214,48 @@ impl WeaponDb {
             range: 3.0,
             reload_time: 0.0,
             ammo: 0, // Melee weapon, no ammo limit
+            fire_mode: FireMode::Semi,
+            ramp_up: None,
+            projectile_speed: None,
+            unlock_kills: 0,
+            equip_time: 0.25,
+            category: WeaponCategory::Melee,
+            overheat: None,
+            loudness: 0.1,
+            pellets: 1,
+            spread: 0.0,
+            damage_type: DamageType::Melee,
         });
 
-        Self { weapons }
+        weapons.insert(4, WeaponData {
+            id: 4,
+            name: "Scattergun".to_string(),
+            damage: 12, // Per pellet
+            fire_rate: 1.2,
+            range: 30.0,
+            reload_time: 2.0,
+            ammo: 6,
+            fire_mode: FireMode::Semi,
+            ramp_up: None,
+            projectile_speed: None,
+            unlock_kills: 10,
+            equip_time: 0.5,
+            category: WeaponCategory::Shotgun,
+            overheat: None,
+            loudness: 1.6,
+            pellets: 8,
+            spread: 6.0,
+            damage_type: DamageType::Ballistic,
+        });
+
+        let mut fx = HashMap::new();
+        fx.insert(1, WeaponFx::new("muzzle_gold", "sfx_golden_friend", "#ffd700"));
+        fx.insert(2, WeaponFx::new("muzzle_plasma", "sfx_prototype_burst", "#4fc3f7"));
+        fx.insert(3, WeaponFx::new("none", "sfx_knife_swing", "#ffffff"));
+        fx.insert(4, WeaponFx::new("muzzle_shotgun", "sfx_scattergun", "#ff8a65"));
+
+        let version = definitions_hash(&weapons, &fx);
+        Self { weapons, fx, version }
     }
 
     /// Get weapon by ID
@@ -69,12 +268,165 @@ impl WeaponDb {
         self.weapons.contains_key(&id)
     }
 
+    /// All weapons, ordered by id
+    pub fn all(&self) -> Vec<&WeaponData> {
+        let mut weapons: Vec<_> = self.weapons.values().collect();
+        weapons.sort_by_key(|w| w.id);
+        weapons
+    }
+
+    /// FX metadata for a weapon
+    pub fn fx(&self, id: u32) -> Option<&WeaponFx> {
+        self.fx.get(&id)
+    }
+
+    /// Version hash of the definitions; changes whenever any weapon or FX entry does
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
     /// Get default weapon ID (Golden Friend)
     pub fn default_weapon_id() -> u32 {
         1
     }
 }
 
+/// FNV-1a over the definitions in id order, stable across runs and builds
+fn definitions_hash(weapons: &HashMap<u32, WeaponData>, fx: &HashMap<u32, WeaponFx>) -> String {
+    let mut ids: Vec<_> = weapons.keys().copied().collect();
+    ids.sort_unstable();
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for id in ids {
+        let entry = (&weapons[&id], fx.get(&id));
+        for byte in serde_json::to_vec(&entry).unwrap_or_default() {
+            hash = (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+    format!("{:016x}", hash)
+}
+
+/// Read access to weapon stats - implemented by the shared database
+/// and by per-lobby views with overrides applied
+pub trait WeaponLookup {
+    fn get(&self, id: u32) -> Option<&WeaponData>;
+
+    fn contains(&self, id: u32) -> bool {
+        self.get(id).is_some()
+    }
+}
+
+impl WeaponLookup for WeaponDb {
+    fn get(&self, id: u32) -> Option<&WeaponData> {
+        WeaponDb::get(self, id)
+    }
+}
+
+/// Highest per-shot damage an override may produce (matches damage validation)
+const MAX_OVERRIDE_DAMAGE: f32 = 100.0;
+
+/// Allowed range for override multipliers
+const MULTIPLIER_RANGE: std::ops::RangeInclusive<f32> = 0.1..=10.0;
+
+/// Lobby creator's adjustment to one weapon, e.g. +20% damage or disabled
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct WeaponOverride {
+    pub damage_multiplier: Option<f32>,
+    pub fire_rate_multiplier: Option<f32>,
+    pub reload_time_multiplier: Option<f32>,
+    #[serde(default)]
+    pub disabled: bool,
+    /// Enable damage ramp-up on consecutive hits
+    pub ramp_up: Option<RampUp>,
+    /// Enable overheating under sustained fire
+    pub overheat: Option<Overheat>,
+}
+
+/// Per-lobby weapon overrides, resolved once at lobby creation
+#[derive(Debug, Clone, Default)]
+pub struct WeaponOverlay {
+    /// Overrides as supplied, echoed to clients
+    pub overrides: HashMap<u32, WeaponOverride>,
+    weapons: HashMap<u32, WeaponData>,
+    disabled: HashSet<u32>,
+}
+
+impl WeaponOverlay {
+    /// Validate overrides against the base database and precompute adjusted stats
+    pub fn resolve(base: &WeaponDb, overrides: HashMap<u32, WeaponOverride>) -> Result<Self, &'static str> {
+        let mut weapons = HashMap::new();
+        let mut disabled = HashSet::new();
+
+        for (id, o) in &overrides {
+            let mut weapon = base.get(*id).ok_or("Unknown weapon in overrides")?.clone();
+
+            if o.disabled {
+                if *id == WeaponDb::default_weapon_id() {
+                    return Err("Default weapon cannot be disabled");
+                }
+                disabled.insert(*id);
+                continue;
+            }
+
+            let multipliers = [o.damage_multiplier, o.fire_rate_multiplier, o.reload_time_multiplier];
+            if multipliers.iter().flatten().any(|m| !MULTIPLIER_RANGE.contains(m)) {
+                return Err("Override multiplier out of range");
+            }
+
+            if let Some(m) = o.damage_multiplier {
+                weapon.damage = (weapon.damage as f32 * m).round().clamp(1.0, MAX_OVERRIDE_DAMAGE) as u32;
+            }
+            if let Some(m) = o.fire_rate_multiplier {
+                weapon.fire_rate *= m;
+            }
+            if let Some(m) = o.reload_time_multiplier {
+                weapon.reload_time *= m;
+            }
+            if let Some(ramp_up) = o.ramp_up {
+                ramp_up.validate()?;
+                weapon.ramp_up = Some(ramp_up);
+            }
+            if let Some(overheat) = o.overheat {
+                overheat.validate()?;
+                weapon.overheat = Some(overheat);
+            }
+            weapons.insert(*id, weapon);
+        }
+
+        Ok(Self { overrides, weapons, disabled })
+    }
+}
+
+/// Weapon stats as seen by one lobby: the shared database with the
+/// lobby's overlay applied
+#[derive(Debug, Clone, Copy)]
+pub struct WeaponView<'a> {
+    base: &'a WeaponDb,
+    overlay: &'a WeaponOverlay,
+}
+
+impl<'a> WeaponView<'a> {
+    pub fn new(base: &'a WeaponDb, overlay: &'a WeaponOverlay) -> Self {
+        Self { base, overlay }
+    }
+
+    /// FX metadata for the weapons enabled in this lobby
+    pub fn fx_table(&self) -> HashMap<u32, &'a WeaponFx> {
+        self.base.fx.iter()
+            .filter(|(id, _)| !self.overlay.disabled.contains(id))
+            .map(|(id, fx)| (*id, fx))
+            .collect()
+    }
+}
+
+impl WeaponLookup for WeaponView<'_> {
+    fn get(&self, id: u32) -> Option<&WeaponData> {
+        if self.overlay.disabled.contains(&id) {
+            return None;
+        }
+        self.overlay.weapons.get(&id).or_else(|| self.base.get(id))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -82,7 +434,29 @@ mod tests {
     #[test]
     fn test_weapon_db_load() {
         let db = WeaponDb::load();
-        assert_eq!(db.weapons.len(), 3);
+        assert_eq!(db.weapons.len(), 4);
+    }
+
+    #[test]
+    fn test_every_weapon_has_fx() {
+        let db = WeaponDb::load();
+        for weapon in db.all() {
+            assert!(db.fx(weapon.id).is_some(), "weapon {} has no fx", weapon.id);
+        }
+        assert_eq!(db.fx(1).unwrap().tracer_color, "#ffd700");
+        assert!(db.fx(999).is_none());
+    }
+
+    #[test]
+    fn test_version_tracks_definitions() {
+        let db = WeaponDb::load();
+        assert_eq!(db.version(), WeaponDb::load().version());
+        assert_eq!(db.version().len(), 16);
+
+        let mut changed = db.weapons.clone();
+        changed.get_mut(&1).unwrap().ammo += 1;
+        assert_ne!(definitions_hash(&changed, &db.fx), db.version());
+        assert_eq!(db.get(3).unwrap().category, WeaponCategory::Melee);
     }
 
     #[test]
@@ -115,5 +489,70 @@ mod tests {
         assert_eq!(knife.reload_time, 0.0);
         assert_eq!(knife.damage, 50);
     }
-}
 
+    #[test]
+    fn test_fire_modes() {
+        let db = WeaponDb::load();
+        assert_eq!(db.get(1).unwrap().fire_mode, FireMode::Auto);
+        assert_eq!(db.get(2).unwrap().fire_mode, FireMode::Burst(3));
+        assert_eq!(db.get(3).unwrap().fire_mode, FireMode::Semi);
+    }
+
+    fn overrides(entries: &[(u32, WeaponOverride)]) -> HashMap<u32, WeaponOverride> {
+        entries.iter().cloned().collect()
+    }
+
+    #[test]
+    fn test_weapon_view_applies_overrides() {
+        let db = WeaponDb::load();
+        let overlay = WeaponOverlay::resolve(&db, overrides(&[
+            (2, WeaponOverride { damage_multiplier: Some(1.2), ..Default::default() }),
+            (3, WeaponOverride { disabled: true, ..Default::default() }),
+        ])).unwrap();
+        let view = WeaponView::new(&db, &overlay);
+
+        assert_eq!(view.get(2).unwrap().damage, 36);
+        assert!(!view.contains(3));
+        assert!(!view.fx_table().contains_key(&3));
+        // Untouched weapons fall through to the base database
+        assert_eq!(view.get(1).unwrap().damage, 20);
+    }
+
+    #[test]
+    fn test_weapon_overlay_validation() {
+        let db = WeaponDb::load();
+        let disabled = WeaponOverride { disabled: true, ..Default::default() };
+        let huge = WeaponOverride { damage_multiplier: Some(50.0), ..Default::default() };
+
+        assert_eq!(WeaponOverlay::resolve(&db, overrides(&[(5, disabled.clone())])).err(), Some("Unknown weapon in overrides"));
+        assert_eq!(WeaponOverlay::resolve(&db, overrides(&[(1, disabled)])).err(), Some("Default weapon cannot be disabled"));
+        assert_eq!(WeaponOverlay::resolve(&db, overrides(&[(2, huge)])).err(), Some("Override multiplier out of range"));
+    }
+
+    #[test]
+    fn test_override_damage_capped() {
+        let db = WeaponDb::load();
+        let overlay = WeaponOverlay::resolve(&db, overrides(&[
+            (3, WeaponOverride { damage_multiplier: Some(5.0), ..Default::default() }),
+        ])).unwrap();
+        assert_eq!(WeaponView::new(&db, &overlay).get(3).unwrap().damage, 100);
+    }
+
+    #[test]
+    fn test_ramp_up_override() {
+        let db = WeaponDb::load();
+        let ramp_up = RampUp { factor: 1.25, max_multiplier: 1.5, window_secs: 1.0 };
+        assert_eq!(ramp_up.multiplier(1), 1.0);
+        assert_eq!(ramp_up.multiplier(2), 1.25);
+        assert_eq!(ramp_up.multiplier(10), 1.5);
+
+        let overlay = WeaponOverlay::resolve(&db, overrides(&[
+            (1, WeaponOverride { ramp_up: Some(ramp_up), ..Default::default() }),
+        ])).unwrap();
+        assert_eq!(WeaponView::new(&db, &overlay).get(1).unwrap().ramp_up, Some(ramp_up));
+
+        let runaway = RampUp { factor: 3.0, ..ramp_up };
+        let result = WeaponOverlay::resolve(&db, overrides(&[(1, WeaponOverride { ramp_up: Some(runaway), ..Default::default() })]));
+        assert_eq!(result.err(), Some("Ramp-up out of range"));
+    }
+}